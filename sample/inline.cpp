@@ -0,0 +1,19 @@
+#include <cstdio>
+
+__attribute__((always_inline)) static inline int inner(int v) {
+    return v * v + 1;
+}
+
+__attribute__((always_inline)) static inline int middle(int v) {
+    int r = inner(v + 1);
+    return r * 2;
+}
+
+int main(int argc, char **) {
+    int total = 0;
+    for (int i = 0; i < argc * 3; ++i) {
+        total += middle(i + argc);
+    }
+    printf("total: %d\n", total);
+    return 0;
+}
@@ -5,6 +5,8 @@ using namespace std;
 
 extern void printTest();
 
+static int counter = 0x100;
+
 class Test {
 public:
     Test(): num(0) {}
@@ -21,8 +23,14 @@ private:
     int num;
 };
 
+static void helper() {
+    cout << "helper in main" << endl;
+}
+
 void test_func(int a) {
     cout << "func is " << a << endl;
+    counter++;
+    helper();
 }
 
 int main() {
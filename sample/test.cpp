@@ -5,9 +5,67 @@ using namespace std;
 static int global_variable = 0x12239999;
 static short global_short = 0x1188;
 
+struct Flags {
+    unsigned int kind : 3;
+    int level : 5;
+    unsigned int id : 12;
+    int delta : 4;
+    short count;
+};
+static Flags global_flags = {5, -3, 0xABC, -8, 42};
+static int global_matrix[2][3] = {{1, 2, 3}, {4, 5, 6}};
+static char global_name[8] = "sample";
+static int counter = 0x200;
+
+static void helper() {
+    cout << "helper in test" << endl;
+}
+
+namespace ns {
+int ns_value = 0x300;
+namespace inner {
+int func(int v) {
+    return v + ns_value;
+}
+}
+}
+
+namespace {
+int anonFunc(int v) {
+    return v * 2;
+}
+}
+
+int scopeTest(int a) {
+    int x = a;
+    int sum = x;
+    {
+        int x = a * 2;
+        int y = x + 1;
+        sum += x + y;
+        {
+            int x = a * 3;
+            sum += x;
+        }
+    }
+    {
+        int y = a * 4;
+        sum += y;
+    }
+    return sum;
+}
+
 void printTest() {
     cout << "print test function" << endl;
     cout << "global_variable: 0x" << hex << global_variable << endl;
     cout << "global_short: 0x" << hex << global_short << endl;
+    cout << "global_flags.id: 0x" << hex << global_flags.id << endl;
+    cout << "global_matrix[1][2]: " << dec << global_matrix[1][2] << endl;
+    cout << "global_name: " << global_name << endl;
+    cout << "counter: " << ++counter << endl;
+    helper();
+    cout << "scope test: " << dec << scopeTest(5) << endl;
+    cout << "ns func: " << ns::inner::func(1) << endl;
+    cout << "anon func: " << anonFunc(2) << endl;
 }
 
@@ -0,0 +1,84 @@
+//! 補助ベクタ(/proc/<pid>/auxv)の読み込み
+//!
+//! カーネルがプログラムへ渡した、開始位置・インタプリタのロード先等を求める
+
+use nix::unistd::Pid;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs;
+use std::io;
+
+/// インタプリタ(動的リンカ)をロードしたアドレス(静的リンクであれば0)
+pub const AT_BASE: u64 = 7;
+/// プログラムの開始位置
+pub const AT_ENTRY: u64 = 9;
+
+// 補助ベクタの終端
+const AT_NULL: u64 = 0;
+
+/// 補助ベクタを読み込む
+pub fn load_auxv(pid: Pid) -> io::Result<HashMap<u64, u64>> {
+    let data = fs::read(format!("/proc/{}/auxv", pid))?;
+    Ok(parse_auxv(&data))
+}
+
+/// 補助ベクタ(種類・値の組の並び)を解析(終端以降は無視する)
+fn parse_auxv(data: &[u8]) -> HashMap<u64, u64> {
+    data.chunks_exact(16)
+        .map(|c| {
+            (
+                u64::from_le_bytes(c[..8].try_into().unwrap()),
+                u64::from_le_bytes(c[8..].try_into().unwrap()),
+            )
+        })
+        .take_while(|(key, _)| AT_NULL != *key)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn to_bytes(entries: &[(u64, u64)]) -> Vec<u8> {
+        entries
+            .iter()
+            .flat_map(|(k, v)| [k.to_le_bytes(), v.to_le_bytes()].concat())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_auxv() {
+        let cases = vec![
+            (vec![], vec![]),
+            (
+                vec![(AT_BASE, 0x7f00_0000_0000), (AT_ENTRY, 0x5555_0000_1040)],
+                vec![(AT_BASE, 0x7f00_0000_0000), (AT_ENTRY, 0x5555_0000_1040)],
+            ),
+            // 終端以降は無視する
+            (
+                vec![(AT_ENTRY, 0x401000), (AT_NULL, 0), (AT_BASE, 0x1234)],
+                vec![(AT_ENTRY, 0x401000)],
+            ),
+        ];
+        for (entries, expected) in cases {
+            let expected = expected.into_iter().collect::<HashMap<u64, u64>>();
+            assert_eq!(expected, parse_auxv(&to_bytes(&entries)), "{:x?}", entries);
+        }
+        // 途中で切れた組は無視する
+        let mut data = to_bytes(&[(AT_ENTRY, 0x401000)]);
+        data.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(Some(&0x401000), parse_auxv(&data).get(&AT_ENTRY));
+    }
+
+    #[test]
+    fn test_load_auxv() {
+        // 自身の開始位置は、実行ファイルにある
+        let auxv = load_auxv(Pid::this()).unwrap();
+        let entry = auxv[&AT_ENTRY];
+        let exe = crate::exec::read_exe_path(Pid::this()).unwrap();
+        let mappings = crate::memory_map::load_mappings(Pid::this()).unwrap();
+        assert!(mappings
+            .iter()
+            .any(|m| m.path == exe && m.start <= entry && entry < m.end));
+    }
+}
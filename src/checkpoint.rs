@@ -0,0 +1,297 @@
+//! プロセスの状態の保存・復元(checkpoint・restart)
+//!
+//! レジスタと、書き込み可能なプライベートな領域(スタック・ヒープ・.data等)の内容を保存する
+//! ファイルディスクリプタ・ソケット等の外部の状態は保存しないため、
+//! ファイルへの書き込み・通信等の取り消せない処理を行った後に戻すと、プログラムの状態と食い違う
+
+use nix::sys::ptrace::setregs;
+use nix::unistd::Pid;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::memory::{read_bulk, write_bulk};
+use crate::memory_map::MapInfo;
+
+/// これより大きな内容は、一時ファイルへ書き出す
+pub const SPILL_SIZE: usize = 64 * 1024 * 1024;
+
+// 保存した領域
+struct Region {
+    start: u64,  // 開始アドレス
+    end: u64,    // 終了アドレス
+    offset: u64, // 保存先でのオフセット
+}
+
+// 保存した内容の格納先
+enum Storage {
+    Memory(Vec<u8>), // メモリ上
+    File(File),      // 一時ファイル(作成後に削除し、閉じると消える)
+}
+
+/// 保存したプロセスの状態
+pub struct Checkpoint {
+    regs: libc::user_regs_struct, // レジスタ
+    regions: Vec<Region>,         // 保存した領域
+    storage: Storage,             // 保存した内容
+}
+
+impl Checkpoint {
+    /// 停止しているプロセスのレジスタ・指定した領域の内容を保存
+    ///
+    /// 内容の合計がspill_sizeより大きければ、一時ファイルへ書き出す
+    pub fn take(
+        pid: Pid,
+        regs: libc::user_regs_struct,
+        ranges: &[(u64, u64)],
+        spill_size: usize,
+    ) -> io::Result<Self> {
+        let total = ranges.iter().map(|(s, e)| e - s).sum::<u64>();
+        let mut storage = match spill_size < total as usize {
+            true => Storage::File(create_temp_file()?),
+            false => Storage::Memory(Vec::with_capacity(total as usize)),
+        };
+        let mut regions = vec![];
+        let mut offset = 0;
+        for (start, end) in ranges {
+            let data = read_bulk(pid, *start, (end - start) as usize).map_err(|e| {
+                io::Error::new(e.kind(), format!("0x{:x}-0x{:x}: {}", start, end, e))
+            })?;
+            match &mut storage {
+                Storage::Memory(buf) => buf.extend_from_slice(&data),
+                Storage::File(f) => f.write_all_at(&data, offset)?,
+            }
+            regions.push(Region {
+                start: *start,
+                end: *end,
+                offset,
+            });
+            offset += data.len() as u64;
+        }
+        Ok(Checkpoint {
+            regs,
+            regions,
+            storage,
+        })
+    }
+
+    /// 保存したレジスタを取得
+    pub fn get_regs(&self) -> &libc::user_regs_struct {
+        &self.regs
+    }
+
+    /// 保存した内容の合計サイズを取得
+    pub fn get_size(&self) -> u64 {
+        self.regions.iter().map(|r| r.end - r.start).sum()
+    }
+
+    /// 保存した領域数を取得
+    pub fn get_region_count(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// 一時ファイルへ書き出したか
+    pub fn is_spilled(&self) -> bool {
+        matches!(self.storage, Storage::File(_))
+    }
+
+    /// 保存した内容・レジスタをプロセスへ書き戻す
+    ///
+    /// currentは現在の書き込み可能な領域で、保存した領域がマップされていなければエラーとする
+    /// 保存した後にマップされた(書き戻していない)範囲を返す
+    pub fn restore(&self, pid: Pid, current: &[(u64, u64)]) -> io::Result<Vec<(u64, u64)>> {
+        let saved = self
+            .regions
+            .iter()
+            .map(|r| (r.start, r.end))
+            .collect::<Vec<(u64, u64)>>();
+        if let Some((s, e)) = uncovered(&saved, current).first() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("0x{:x}-0x{:x} is no longer mapped", s, e),
+            ));
+        }
+
+        for r in &self.regions {
+            let len = (r.end - r.start) as usize;
+            let data = match &self.storage {
+                Storage::Memory(buf) => buf[r.offset as usize..r.offset as usize + len].to_vec(),
+                Storage::File(f) => {
+                    let mut buf = vec![0; len];
+                    f.read_exact_at(&mut buf, r.offset)?;
+                    buf
+                }
+            };
+            write_bulk(pid, r.start, &data)?;
+        }
+        setregs(pid, self.regs).map_err(io::Error::from)?;
+        Ok(uncovered(current, &saved))
+    }
+}
+
+/// 書き込み可能なプライベートな領域を、アドレスの順に取得
+pub fn writable_regions(maps: &HashMap<String, Vec<MapInfo>>) -> Vec<(u64, u64)> {
+    let mut regions = maps
+        .values()
+        .flatten()
+        .filter(|m| m.permission.contains('w') && m.permission.ends_with('p'))
+        .filter_map(|m| {
+            let s = u64::from_str_radix(&m.start_address, 16).ok()?;
+            let e = u64::from_str_radix(&m.end_address, 16).ok()?;
+            Some((s, e))
+        })
+        .collect::<Vec<(u64, u64)>>();
+    regions.sort_unstable();
+    regions
+}
+
+/// rangesのうち、byの範囲に含まれない範囲を求める(byはアドレスの順)
+fn uncovered(ranges: &[(u64, u64)], by: &[(u64, u64)]) -> Vec<(u64, u64)> {
+    let mut result = vec![];
+    for (start, end) in ranges {
+        let mut addr = *start;
+        for (s, e) in by.iter().filter(|(s, e)| s < end && start < e) {
+            if addr < *s {
+                result.push((addr, *s));
+            }
+            addr = addr.max(*e);
+        }
+        if addr < *end {
+            result.push((addr, *end));
+        }
+    }
+    result
+}
+
+/// 一時ファイルを作成(作成後に削除し、閉じると消えるようにする)
+fn create_temp_file() -> io::Result<File> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    let path = std::env::temp_dir().join(format!(
+        "r-debugger-checkpoint-{}-{}",
+        std::process::id(),
+        nanos
+    ));
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    std::fs::remove_file(&path)?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_uncovered() {
+        let cases = vec![
+            (vec![(0x1000, 0x2000)], vec![(0x1000, 0x2000)], vec![]),
+            (vec![(0x1000, 0x2000)], vec![], vec![(0x1000, 0x2000)]),
+            // 連続した領域で覆う
+            (
+                vec![(0x1000, 0x3000)],
+                vec![(0x1000, 0x2000), (0x2000, 0x3000)],
+                vec![],
+            ),
+            // ヒープが伸びた
+            (
+                vec![(0x1000, 0x4000)],
+                vec![(0x1000, 0x2000)],
+                vec![(0x2000, 0x4000)],
+            ),
+            // 間に隙間がある
+            (
+                vec![(0x1000, 0x5000), (0x8000, 0x9000)],
+                vec![(0x2000, 0x3000), (0x4000, 0x6000)],
+                vec![(0x1000, 0x2000), (0x3000, 0x4000), (0x8000, 0x9000)],
+            ),
+        ];
+        for (ranges, by, expected) in cases {
+            assert_eq!(expected, uncovered(&ranges, &by), "{:x?}", ranges);
+        }
+    }
+
+    #[test]
+    fn test_writable_regions() {
+        let map = |s: &str, e: &str, p: &str| MapInfo {
+            start_address: s.to_string(),
+            end_address: e.to_string(),
+            permission: p.to_string(),
+        };
+        let mut maps = HashMap::new();
+        maps.insert(
+            "/tmp/a.out".to_string(),
+            vec![map("5000", "6000", "r-xp"), map("7000", "8000", "rw-p")],
+        );
+        maps.insert("[stack]".to_string(), vec![map("f000", "10000", "rw-p")]);
+        maps.insert("/dev/shm/x".to_string(), vec![map("3000", "4000", "rw-s")]);
+        maps.insert("none".to_string(), vec![map("1000", "2000", "rw-p")]);
+        assert_eq!(
+            vec![(0x1000, 0x2000), (0x7000, 0x8000), (0xf000, 0x10000)],
+            writable_regions(&maps)
+        );
+    }
+
+    #[test]
+    fn test_take_and_restore() {
+        use crate::memory::{read_bytes, write_bytes};
+        use crate::memory_map::MemoryMap;
+        use nix::sys::ptrace::{getregs, traceme};
+        use nix::sys::signal::{kill, raise, Signal};
+        use nix::sys::wait::waitpid;
+        use nix::unistd::{fork, ForkResult};
+
+        let buf = vec![0x5Au8; 0x100];
+        let addr = buf.as_ptr() as u64;
+        let _lock = crate::FORK_LOCK.lock().unwrap();
+        match unsafe { fork() }.expect("failed fork") {
+            ForkResult::Child => unsafe {
+                traceme().expect("failed traceme");
+                raise(Signal::SIGSTOP).expect("failed raise");
+                libc::_exit(0);
+            },
+            ForkResult::Parent { child } => {
+                waitpid(child, None).expect("failed waitpid");
+                let current = writable_regions(MemoryMap::new(child).load());
+                let ranges = current
+                    .iter()
+                    .copied()
+                    .filter(|(s, e)| *s <= addr && addr < *e)
+                    .collect::<Vec<(u64, u64)>>();
+                let regs = getregs(child).expect("failed getregs");
+
+                // メモリ上・一時ファイルのどちらに保存しても、元に戻せる
+                for spill_size in [SPILL_SIZE, 0] {
+                    let cp = Checkpoint::take(child, regs, &ranges, spill_size).unwrap();
+                    assert_eq!(0 == spill_size, cp.is_spilled());
+                    assert_eq!(1, cp.get_region_count());
+                    assert_eq!(ranges[0].1 - ranges[0].0, cp.get_size());
+
+                    write_bytes(child, addr + 8, &[0xA5; 16]).expect("failed write_bytes");
+                    let mut changed = regs;
+                    changed.rax ^= 0xFFFF;
+                    setregs(child, changed).expect("failed setregs");
+
+                    let added = cp.restore(child, &current).unwrap();
+                    assert_eq!(buf, read_bytes(child, addr, buf.len()).unwrap());
+                    assert_eq!(regs.rax, getregs(child).unwrap().rax);
+                    // 保存していない領域は書き戻さない
+                    assert_eq!(uncovered(&current, &ranges), added);
+                }
+
+                // 保存した領域がマップされていなければ、書き戻さない
+                let cp = Checkpoint::take(child, regs, &ranges, SPILL_SIZE).unwrap();
+                assert!(cp.restore(child, &[]).is_err());
+
+                kill(child, Signal::SIGKILL).expect("failed kill");
+                waitpid(child, None).expect("failed waitpid");
+            }
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! トレース結果の色付け
+//!
+//! ANSIエスケープシーケンスで、システムコール名(分類毎)・文字列・エラー・シグナルを色分けする
+
+use crate::syscall_info::{to_category, Category};
+
+/// エスケープシーケンス
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const SLOW: &str = "\x1b[1;33m";
+const FILE: &str = "\x1b[1;34m";
+const NETWORK: &str = "\x1b[1;35m";
+const MEMORY: &str = "\x1b[1;36m";
+const PROCESS: &str = "\x1b[1;32m";
+
+/// 色付け(無効な場合は、そのまま返す)
+pub struct Colors {
+    enabled: bool,
+}
+
+impl Colors {
+    /// コンストラクタ
+    pub fn new(enabled: bool) -> Self {
+        Colors { enabled }
+    }
+
+    /// 色付け
+    fn paint(&self, color: &str, s: &str) -> String {
+        if self.enabled {
+            format!("{}{}{}", color, s, RESET)
+        } else {
+            s.to_string()
+        }
+    }
+
+    /// システムコール名(分類毎に色を変える)
+    pub fn name(&self, no: i64, name: &str) -> String {
+        let color = match to_category(no) {
+            Some(Category::File) => FILE,
+            Some(Category::Network) => NETWORK,
+            Some(Category::Memory) => MEMORY,
+            Some(Category::Process) => PROCESS,
+            Some(Category::Desc) | Some(Category::Signal) | None => BOLD,
+        };
+        self.paint(color, name)
+    }
+
+    /// 引数(文字列のみ色付け)
+    ///
+    /// 文字列はquoteで整形済みのため、エスケープされていない"で囲まれた範囲とする
+    pub fn args(&self, args: &str) -> String {
+        if !self.enabled {
+            return args.to_string();
+        }
+        let mut s = String::new();
+        let mut quoted = false;
+        let mut escaped = false;
+        for c in args.chars() {
+            match c {
+                '"' if !quoted => {
+                    s.push_str(GREEN);
+                    quoted = true;
+                }
+                '"' if !escaped => {
+                    s.push(c);
+                    s.push_str(RESET);
+                    quoted = false;
+                    continue;
+                }
+                _ => {}
+            }
+            escaped = quoted && '\\' == c && !escaped;
+            s.push(c);
+        }
+        if quoted {
+            s.push_str(RESET);
+        }
+        s
+    }
+
+    /// 戻り値(エラーは赤)
+    pub fn ret(&self, ret: &str, error: bool) -> String {
+        if error {
+            self.paint(RED, ret)
+        } else {
+            ret.to_string()
+        }
+    }
+
+    /// シグナルの受信・終了の行
+    pub fn signal(&self, line: &str) -> String {
+        self.paint(YELLOW, line)
+    }
+
+    /// 閾値を超えた所要時間(--slower-than)
+    pub fn slow(&self, duration: &str) -> String {
+        self.paint(SLOW, duration)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_colors() {
+        {
+            // 無効な場合はそのまま
+            let colors = Colors::new(false);
+            let args = "AT_FDCWD, \"/etc/passwd\", O_RDONLY";
+            assert_eq!(args, colors.args(args));
+            assert_eq!("openat", colors.name(libc::SYS_openat, "openat"));
+            assert_eq!("-1 ENOENT", colors.ret("-1 ENOENT", true));
+            assert_eq!("--- SIGINT ---", colors.signal("--- SIGINT ---"));
+            assert_eq!("<0.010000>", colors.slow("<0.010000>"));
+        }
+        {
+            let colors = Colors::new(true);
+            let cases = vec![
+                (
+                    "AT_FDCWD, \"/etc/passwd\", O_RDONLY",
+                    "AT_FDCWD, \x1b[32m\"/etc/passwd\"\x1b[0m, O_RDONLY",
+                ),
+                // エスケープされた"・\
+                (
+                    "1, \"a\\\"b\\\\\", 5",
+                    "1, \x1b[32m\"a\\\"b\\\\\"\x1b[0m, 5",
+                ),
+                ("1, \"abc\"..., 32", "1, \x1b[32m\"abc\"\x1b[0m..., 32"),
+                ("3, 0x7ffd0000, 5", "3, 0x7ffd0000, 5"),
+            ];
+            for (args, expected) in cases {
+                assert_eq!(expected, colors.args(args));
+            }
+            assert_eq!(
+                "\x1b[1;34mopenat\x1b[0m",
+                colors.name(libc::SYS_openat, "openat")
+            );
+            assert_eq!(
+                "\x1b[1;35mconnect\x1b[0m",
+                colors.name(libc::SYS_connect, "connect")
+            );
+            assert_eq!("\x1b[1mread\x1b[0m", colors.name(libc::SYS_read, "read"));
+            assert_eq!("\x1b[31m-1 ENOENT\x1b[0m", colors.ret("-1 ENOENT", true));
+            assert_eq!("3", colors.ret("3", false));
+            assert_eq!(
+                "\x1b[33m--- SIGINT ---\x1b[0m",
+                colors.signal("--- SIGINT ---")
+            );
+            assert_eq!("\x1b[1;33m<0.010000>\x1b[0m", colors.slow("<0.010000>"));
+        }
+    }
+}
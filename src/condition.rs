@@ -0,0 +1,179 @@
+//! ブレイクポイントの停止条件(b <sym> if <reg>==<val>)
+//!
+//! ブレイクポイントで停止した際のレジスタの値と比較し、条件が成り立たなければ停止せずに再開する
+
+use std::fmt;
+
+/// 比較演算子(2文字の演算子を先に探す)
+const OPERATORS: [(&str, Operator); 6] = [
+    ("==", Operator::Eq),
+    ("!=", Operator::Ne),
+    ("<=", Operator::Le),
+    (">=", Operator::Ge),
+    ("<", Operator::Lt),
+    (">", Operator::Gt),
+];
+
+/// 比較演算子
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Eq, // ==
+    Ne, // !=
+    Lt, // <
+    Le, // <=
+    Gt, // >
+    Ge, // >=
+}
+
+/// 停止条件
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    reg: String,  // レジスタ名
+    op: Operator, // 比較演算子
+    value: u64,   // 比較する値
+}
+
+impl Condition {
+    /// 文字列(rdi==0x10・rax!=0等)から変換
+    ///
+    /// 値は0xで始まれば16進数、それ以外は10進数
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.split_whitespace().collect::<String>();
+        let (pos, sym, op) = OPERATORS
+            .iter()
+            .filter_map(|(sym, op)| s.find(sym).map(|pos| (pos, sym, *op)))
+            .min_by_key(|(pos, sym, _)| (*pos, std::cmp::Reverse(sym.len())))
+            .ok_or(format!("invalid condition: {} (<reg>==<val>)", s))?;
+        let (reg, value) = (&s[..pos], &s[pos + sym.len()..]);
+        if register_value(&zeroed_regs(), reg).is_none() {
+            return Err(format!("not register {}", reg));
+        }
+        let value = match value.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => value.parse::<u64>(),
+        }
+        .map_err(|_| format!("parse error: {}", value))?;
+        Ok(Condition {
+            reg: reg.to_string(),
+            op,
+            value,
+        })
+    }
+
+    /// レジスタの値で条件を評価
+    pub fn eval(&self, regs: &libc::user_regs_struct) -> bool {
+        let v = match register_value(regs, &self.reg) {
+            Some(v) => v,
+            None => return false,
+        };
+        match self.op {
+            Operator::Eq => v == self.value,
+            Operator::Ne => v != self.value,
+            Operator::Lt => v < self.value,
+            Operator::Le => v <= self.value,
+            Operator::Gt => v > self.value,
+            Operator::Ge => v >= self.value,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sym = OPERATORS
+            .iter()
+            .find(|(_, op)| *op == self.op)
+            .map_or("", |(sym, _)| sym);
+        write!(f, "{}{}0x{:x}", self.reg, sym, self.value)
+    }
+}
+
+/// レジスタ名から値を取得(存在しないレジスタであればNone)
+fn register_value(regs: &libc::user_regs_struct, reg: &str) -> Option<u64> {
+    let v = match reg {
+        "orig_rax" => regs.orig_rax,
+        "rip" => regs.rip,
+        "rsp" => regs.rsp,
+        "rbp" => regs.rbp,
+        "rbx" => regs.rbx,
+        "r15" => regs.r15,
+        "r14" => regs.r14,
+        "r13" => regs.r13,
+        "r12" => regs.r12,
+        "r11" => regs.r11,
+        "r10" => regs.r10,
+        "r9" => regs.r9,
+        "r8" => regs.r8,
+        "rax" => regs.rax,
+        "rcx" => regs.rcx,
+        "rdx" => regs.rdx,
+        "rsi" => regs.rsi,
+        "rdi" => regs.rdi,
+        "cs" => regs.cs,
+        "eflags" => regs.eflags,
+        "ss" => regs.ss,
+        "fs_base" => regs.fs_base,
+        "gs_base" => regs.gs_base,
+        "ds" => regs.ds,
+        "es" => regs.es,
+        "fs" => regs.fs,
+        "gs" => regs.gs,
+        _ => return None,
+    };
+    Some(v)
+}
+
+/// 全て0のレジスタ(レジスタ名の確認用)
+fn zeroed_regs() -> libc::user_regs_struct {
+    unsafe { std::mem::zeroed() }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let cases = vec![
+            ("rdi==0x10", Ok("rdi==0x10")),
+            ("rdi == 16", Ok("rdi==0x10")),
+            ("rax!=0", Ok("rax!=0x0")),
+            ("rsi<=0xff", Ok("rsi<=0xff")),
+            ("rsi<0xff", Ok("rsi<0xff")),
+            ("rcx>=3", Ok("rcx>=0x3")),
+            ("rcx>3", Ok("rcx>0x3")),
+            ("rdi", Err("invalid condition: rdi (<reg>==<val>)")),
+            ("xmm0==1", Err("not register xmm0")),
+            ("rdi==0xzz", Err("parse error: 0xzz")),
+            ("rdi==", Err("parse error: ")),
+        ];
+        for (s, expected) in cases {
+            let cond = Condition::parse(s).map(|c| c.to_string());
+            assert_eq!(
+                expected.map(|e| e.to_string()).map_err(|e| e.to_string()),
+                cond,
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn test_eval() {
+        let mut regs = zeroed_regs();
+        regs.rdi = 5;
+        let cases = vec![
+            ("rdi==5", true),
+            ("rdi==6", false),
+            ("rdi!=5", false),
+            ("rdi<6", true),
+            ("rdi<5", false),
+            ("rdi<=5", true),
+            ("rdi>4", true),
+            ("rdi>=6", false),
+            ("rax==0", true),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(expected, Condition::parse(s).unwrap().eval(&regs), "{}", s);
+        }
+    }
+}
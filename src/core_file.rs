@@ -0,0 +1,458 @@
+//! コアファイルの解析(rtracer core)
+//!
+//! PT_NOTEのNT_PRSTATUSからレジスタを、NT_PRFPREGから浮動小数点レジスタを、NT_FILEからマップされていたファイルを読み込む
+//! メモリはPT_LOADの内容から読み込み、コアに含まれない領域(コード等)はマップされていたファイルから読み込む
+
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result};
+use std::os::unix::fs::FileExt;
+
+use crate::elf::elf64::parse_notes;
+use crate::memory::ReadMemory;
+use crate::memory_map::Mapping;
+
+// ELFヘッダーの種類(コアファイル)
+const ET_CORE: u16 = 4;
+
+// プログラムヘッダーの種類
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+
+// プログラムヘッダーのフラグ(実行・書き込み・読み込み)
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+// ノートの種類
+const NT_PRSTATUS: u32 = 1;
+const NT_PRFPREG: u32 = 2;
+const NT_SIGINFO: u32 = 0x5349_4749;
+const NT_FILE: u32 = 0x4649_4c45;
+
+// ヘッダーのサイズ
+const ELF_HEADER_SIZE: usize = 64;
+const PROG_HEADER_SIZE: usize = 56;
+
+// elf_prstatus内のオフセット(x86-64)
+const PR_CURSIG_OFFSET: usize = 12;
+const PR_PID_OFFSET: usize = 32;
+const PR_REG_OFFSET: usize = 112;
+
+// コアに含まれるメモリ(PT_LOAD)
+#[derive(Debug, PartialEq)]
+struct Segment {
+    vaddr: u64,  // アドレス
+    offset: u64, // コアファイル上のオフセット
+    filesz: u64, // コアファイル上のサイズ(ダンプされなかった領域は0)
+    flags: u32,  // 権限(PF_R・PF_W・PF_X)
+}
+
+/// 停止時のスレッドの状態(NT_PRSTATUS)
+pub struct PrStatus {
+    pub pid: i32,                     // スレッドID
+    pub signal: i32,                  // 受信したシグナル
+    pub regs: libc::user_regs_struct, // レジスタ
+}
+
+/// コアファイル
+pub struct CoreFile {
+    file: File,                               // コアファイル
+    segments: Vec<Segment>,                   // コアに含まれるメモリ
+    mappings: Vec<Mapping>,                   // マップされていたファイル
+    status: PrStatus,                         // シグナルを受信したスレッドの状態
+    fpregs: Option<libc::user_fpregs_struct>, // シグナルを受信したスレッドの浮動小数点レジスタ
+    siginfo: Option<libc::siginfo_t>,         // 受信したシグナルの情報
+}
+
+impl CoreFile {
+    /// コアファイルを開き、ヘッダー・ノートを読み込む
+    pub fn open(path: &str) -> Result<Self> {
+        let file = File::open(path)?;
+        let mut header = [0; ELF_HEADER_SIZE];
+        file.read_exact_at(&mut header, 0)?;
+        if b"\x7fELF" != &header[..4] || ET_CORE != u16::from_le_bytes([header[16], header[17]]) {
+            return Err(invalid(format!("not a core file: {}", path)));
+        }
+        let phoff = u64::from_le_bytes(header[32..40].try_into().unwrap());
+        let phnum = u16::from_le_bytes([header[56], header[57]]) as usize;
+        let mut prog_headers = vec![0; PROG_HEADER_SIZE * phnum];
+        file.read_exact_at(&mut prog_headers, phoff)?;
+
+        // メモリの領域を登録し、ノートを読み込む
+        let mut segments = vec![];
+        let mut notes = vec![];
+        for ph in prog_headers.chunks(PROG_HEADER_SIZE) {
+            let word = |i: usize| u64::from_le_bytes(ph[i..i + 8].try_into().unwrap());
+            let (offset, vaddr, filesz) = (word(8), word(16), word(32));
+            let flags = u32::from_le_bytes(ph[4..8].try_into().unwrap());
+            match u32::from_le_bytes(ph[..4].try_into().unwrap()) {
+                PT_LOAD => segments.push(Segment {
+                    vaddr,
+                    offset,
+                    filesz,
+                    flags,
+                }),
+                PT_NOTE => {
+                    let mut data = vec![0; filesz as usize];
+                    file.read_exact_at(&mut data, offset)?;
+                    notes.push(data);
+                }
+                _ => {}
+            }
+        }
+
+        // シグナルを受信したスレッドのNT_PRSTATUSが先頭にある
+        let notes = notes
+            .iter()
+            .flat_map(|n| parse_notes(n))
+            .collect::<Vec<_>>();
+        let status = notes
+            .iter()
+            .find(|n| NT_PRSTATUS == n.ty)
+            .and_then(|n| parse_prstatus(n.desc))
+            .ok_or_else(|| invalid(format!("no NT_PRSTATUS in {}", path)))?;
+        let fpregs = notes
+            .iter()
+            .find(|n| NT_PRFPREG == n.ty)
+            .and_then(|n| read_struct::<libc::user_fpregs_struct>(n.desc, 0));
+        let mappings = notes
+            .iter()
+            .find(|n| NT_FILE == n.ty)
+            .and_then(|n| parse_file_note(n.desc))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|mut m| {
+                // 権限は、同じアドレスから始まるPT_LOADのフラグから求める
+                m.perms = segments
+                    .iter()
+                    .find(|s| s.vaddr == m.start)
+                    .map_or(String::new(), |s| to_perms(s.flags));
+                m
+            })
+            .collect();
+        let siginfo = notes
+            .iter()
+            .find(|n| NT_SIGINFO == n.ty)
+            .and_then(|n| read_struct::<libc::siginfo_t>(n.desc, 0));
+        Ok(CoreFile {
+            file,
+            segments,
+            mappings,
+            status,
+            fpregs,
+            siginfo,
+        })
+    }
+
+    /// シグナルを受信したスレッドの状態を取得
+    pub fn get_status(&self) -> &PrStatus {
+        &self.status
+    }
+
+    /// シグナルを受信したスレッドの浮動小数点レジスタを取得
+    pub fn get_fpregs(&self) -> Option<&libc::user_fpregs_struct> {
+        self.fpregs.as_ref()
+    }
+
+    /// 受信したシグナルの情報を取得
+    pub fn get_siginfo(&self) -> Option<&libc::siginfo_t> {
+        self.siginfo.as_ref()
+    }
+
+    /// マップされていたファイルを取得
+    pub fn get_mappings(&self) -> &Vec<Mapping> {
+        &self.mappings
+    }
+}
+
+impl ReadMemory for CoreFile {
+    fn read_memory(&self, addr: u64, len: usize) -> Option<Vec<u8>> {
+        let mut buf = vec![];
+        while buf.len() < len {
+            let a = addr + buf.len() as u64;
+            let rest = (len - buf.len()) as u64;
+
+            // コアに含まれていなければ、マップされていたファイルから読み込む
+            let chunk = match self
+                .segments
+                .iter()
+                .find(|s| s.vaddr <= a && a < s.vaddr + s.filesz)
+            {
+                Some(s) => {
+                    let n = rest.min(s.vaddr + s.filesz - a);
+                    read_at(&self.file, s.offset + (a - s.vaddr), n)?
+                }
+                None => {
+                    let m = self.mappings.iter().find(|m| m.start <= a && a < m.end)?;
+                    let n = rest.min(m.end - a);
+                    read_at(&File::open(&m.path).ok()?, m.offset + (a - m.start), n)?
+                }
+            };
+            buf.extend_from_slice(&chunk);
+        }
+        Some(buf)
+    }
+}
+
+/// ファイルのoffsetからlenバイト読み込む
+fn read_at(file: &File, offset: u64, len: u64) -> Option<Vec<u8>> {
+    let mut buf = vec![0; len as usize];
+    file.read_exact_at(&mut buf, offset).ok()?;
+    Some(buf)
+}
+
+/// 不正なファイルのエラー
+fn invalid(msg: String) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+/// バイト列のoffsetから、C言語の構造体を読み込む
+fn read_struct<T>(data: &[u8], offset: usize) -> Option<T> {
+    let bytes = data.get(offset..offset + std::mem::size_of::<T>())?;
+    Some(unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const T) })
+}
+
+/// NT_PRSTATUS(elf_prstatus)を解析
+fn parse_prstatus(desc: &[u8]) -> Option<PrStatus> {
+    let cursig = desc.get(PR_CURSIG_OFFSET..PR_CURSIG_OFFSET + 2)?;
+    let pid = desc.get(PR_PID_OFFSET..PR_PID_OFFSET + 4)?;
+    Some(PrStatus {
+        pid: i32::from_le_bytes(pid.try_into().ok()?),
+        signal: i16::from_le_bytes(cursig.try_into().ok()?) as i32,
+        regs: read_struct(desc, PR_REG_OFFSET)?,
+    })
+}
+
+/// PT_LOADのフラグを、mapsの権限の形式(r-xp等)へ変換
+///
+/// コアファイルからは共有・プライベートが分からないため、プライベートとする
+fn to_perms(flags: u32) -> String {
+    [(PF_R, 'r'), (PF_W, 'w'), (PF_X, 'x')]
+        .iter()
+        .map(|(f, c)| if 0 != flags & f { *c } else { '-' })
+        .chain(std::iter::once('p'))
+        .collect()
+}
+
+/// NT_FILEを解析
+///
+/// 領域数・ページサイズの後に、領域毎の開始・終了・ページ単位のオフセットが続き、
+/// 最後にNUL区切りのファイル名が並ぶ
+fn parse_file_note(desc: &[u8]) -> Option<Vec<Mapping>> {
+    let word = |i: usize| -> Option<u64> {
+        Some(u64::from_le_bytes(
+            desc.get(i * 8..i * 8 + 8)?.try_into().ok()?,
+        ))
+    };
+    let count = word(0)? as usize;
+    let page_size = word(1)?;
+    let names = desc.get(8 * (2 + 3 * count)..)?.split(|b| 0 == *b);
+    (0..count)
+        .zip(names)
+        .map(|(i, name)| {
+            Some(Mapping {
+                start: word(2 + 3 * i)?,
+                end: word(3 + 3 * i)?,
+                offset: word(4 + 3 * i)? * page_size,
+                perms: String::new(),
+                path: String::from_utf8_lossy(name).into_owned(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// ノートを作成
+    fn note(ty: u32, desc: &[u8]) -> Vec<u8> {
+        let mut n = vec![];
+        n.extend_from_slice(&5u32.to_le_bytes());
+        n.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+        n.extend_from_slice(&ty.to_le_bytes());
+        n.extend_from_slice(b"CORE\0\0\0\0");
+        n.extend_from_slice(desc);
+        n.resize((n.len() + 3) & !3, 0);
+        n
+    }
+
+    /// NT_FILEの内容を作成
+    fn file_note(page_size: u64, maps: &[(u64, u64, u64, &str)]) -> Vec<u8> {
+        let mut desc = vec![];
+        desc.extend_from_slice(&(maps.len() as u64).to_le_bytes());
+        desc.extend_from_slice(&page_size.to_le_bytes());
+        for (start, end, pgoff, _) in maps {
+            for v in [start, end, pgoff] {
+                desc.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        for (_, _, _, path) in maps {
+            desc.extend_from_slice(path.as_bytes());
+            desc.push(0);
+        }
+        desc
+    }
+
+    /// NT_PRSTATUSの内容を作成
+    fn prstatus(pid: i32, signal: i16, rip: u64, rsp: u64) -> Vec<u8> {
+        let mut desc = vec![0; 336];
+        desc[PR_CURSIG_OFFSET..PR_CURSIG_OFFSET + 2].copy_from_slice(&signal.to_le_bytes());
+        desc[PR_PID_OFFSET..PR_PID_OFFSET + 4].copy_from_slice(&pid.to_le_bytes());
+        // user_regs_structのrip・rspの位置
+        desc[PR_REG_OFFSET + 16 * 8..PR_REG_OFFSET + 17 * 8].copy_from_slice(&rip.to_le_bytes());
+        desc[PR_REG_OFFSET + 19 * 8..PR_REG_OFFSET + 20 * 8].copy_from_slice(&rsp.to_le_bytes());
+        desc
+    }
+
+    #[test]
+    fn test_parse_prstatus() {
+        let status =
+            parse_prstatus(&prstatus(4242, 11, 0x5555_5555_5139, 0x7ffd_0000_1000)).unwrap();
+        assert_eq!(4242, status.pid);
+        assert_eq!(libc::SIGSEGV, status.signal);
+        assert_eq!(0x5555_5555_5139, status.regs.rip);
+        assert_eq!(0x7ffd_0000_1000, status.regs.rsp);
+        // 途中で切れている
+        assert!(parse_prstatus(&prstatus(1, 11, 0, 0)[..300]).is_none());
+    }
+
+    #[test]
+    fn test_to_perms() {
+        let cases = vec![
+            (0, "---p"),
+            (PF_R, "r--p"),
+            (PF_R | PF_X, "r-xp"),
+            (PF_R | PF_W, "rw-p"),
+            (PF_R | PF_W | PF_X, "rwxp"),
+        ];
+        for (flags, expected) in cases {
+            assert_eq!(expected, to_perms(flags), "{}", flags);
+        }
+    }
+
+    #[test]
+    fn test_parse_file_note() {
+        let mapping = |start, end, offset, path: &str| Mapping {
+            start,
+            end,
+            offset,
+            perms: String::new(),
+            path: path.to_string(),
+        };
+        let cases = vec![
+            (
+                file_note(
+                    0x1000,
+                    &[
+                        (0x5555_5555_4000, 0x5555_5555_5000, 0, "/tmp/crash"),
+                        (0x5555_5555_5000, 0x5555_5555_6000, 1, "/tmp/crash"),
+                        (
+                            0x7fff_f7dc_3000,
+                            0x7fff_f7deb000,
+                            0x28,
+                            "/usr/lib/libc.so.6",
+                        ),
+                    ],
+                ),
+                Some(vec![
+                    mapping(0x5555_5555_4000, 0x5555_5555_5000, 0, "/tmp/crash"),
+                    mapping(0x5555_5555_5000, 0x5555_5555_6000, 0x1000, "/tmp/crash"),
+                    mapping(
+                        0x7fff_f7dc_3000,
+                        0x7fff_f7deb000,
+                        0x28000,
+                        "/usr/lib/libc.so.6",
+                    ),
+                ]),
+            ),
+            (file_note(0x1000, &[]), Some(vec![])),
+            // 領域が途中で切れている
+            (
+                file_note(0x1000, &[(0x1000, 0x2000, 0, "/a")])[..24].to_vec(),
+                None,
+            ),
+            (vec![], None),
+        ];
+        for (desc, expected) in cases {
+            assert_eq!(expected, parse_file_note(&desc));
+        }
+    }
+
+    #[test]
+    fn test_open() {
+        let dir = std::env::temp_dir().join(format!("core_file_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // コアに含まれない領域は、マップされていたファイルから読み込む
+        let mapped = dir.join("mapped.bin");
+        std::fs::write(&mapped, (0..0x20u8).collect::<Vec<u8>>()).unwrap();
+        let notes = [
+            note(NT_PRSTATUS, &prstatus(4242, 11, 0x1008, 0x3000)),
+            note(
+                NT_FILE,
+                &file_note(0x10, &[(0x1000, 0x1010, 1, mapped.to_str().unwrap())]),
+            ),
+        ]
+        .concat();
+
+        // ELFヘッダー・プログラムヘッダー(PT_NOTE・PT_LOAD×2)・ノート・メモリの内容
+        let data_offset = (ELF_HEADER_SIZE + 3 * PROG_HEADER_SIZE + notes.len()) as u64;
+        let mut core = vec![0; ELF_HEADER_SIZE];
+        core[..4].copy_from_slice(b"\x7fELF");
+        core[16..18].copy_from_slice(&ET_CORE.to_le_bytes());
+        core[32..40].copy_from_slice(&(ELF_HEADER_SIZE as u64).to_le_bytes());
+        core[56..58].copy_from_slice(&3u16.to_le_bytes());
+        let ph = |ty: u32, offset: u64, vaddr: u64, filesz: u64| {
+            let mut h = vec![0; PROG_HEADER_SIZE];
+            h[..4].copy_from_slice(&ty.to_le_bytes());
+            h[8..16].copy_from_slice(&offset.to_le_bytes());
+            h[16..24].copy_from_slice(&vaddr.to_le_bytes());
+            h[32..40].copy_from_slice(&filesz.to_le_bytes());
+            h
+        };
+        core.extend(ph(
+            PT_NOTE,
+            (ELF_HEADER_SIZE + 3 * PROG_HEADER_SIZE) as u64,
+            0,
+            notes.len() as u64,
+        ));
+        core.extend(ph(PT_LOAD, data_offset, 0x3000, 8));
+        core.extend(ph(PT_LOAD, data_offset + 8, 0x3008, 8));
+        core.extend(notes);
+        core.extend_from_slice(&0x1122_3344_5566_7788u64.to_le_bytes());
+        core.extend_from_slice(&0x99aa_bbcc_ddee_ff00u64.to_le_bytes());
+        let core_path = dir.join("core");
+        std::fs::write(&core_path, &core).unwrap();
+
+        let cf = CoreFile::open(core_path.to_str().unwrap()).unwrap();
+        assert_eq!(4242, cf.get_status().pid);
+        assert_eq!(0x1008, cf.get_status().regs.rip);
+        assert_eq!(1, cf.get_mappings().len());
+        assert!(cf.get_siginfo().is_none());
+        assert!(cf.get_fpregs().is_none());
+        let cases = vec![
+            (
+                0x3000,
+                8,
+                Some(0x1122_3344_5566_7788u64.to_le_bytes().to_vec()),
+            ),
+            // 連続したPT_LOADをまたぐ
+            (0x3006, 4, Some(vec![0x22, 0x11, 0x00, 0xff])),
+            (0x300c, 8, None),
+            // ファイルのオフセット(1ページ)から読み込む
+            (0x1000, 4, Some(vec![0x10, 0x11, 0x12, 0x13])),
+            (0x100e, 2, Some(vec![0x1e, 0x1f])),
+            (0x100e, 4, None),
+            (0x2000, 1, None),
+        ];
+        for (addr, len, expected) in cases {
+            assert_eq!(expected, cf.read_memory(addr, len), "0x{:x}", addr);
+        }
+
+        // コアファイル以外は開けない
+        assert!(CoreFile::open(mapped.to_str().unwrap()).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,22 +1,115 @@
-use nix::sys::ptrace::{cont, getregs, kill, read, setregs, step, write, AddressType};
+use nix::sys::ptrace::{
+    cont, detach, getregs, getsiginfo, kill, setoptions, setregs, step, write, AddressType, Options,
+};
+use nix::sys::signal::Signal;
 use nix::sys::wait::*;
 use nix::unistd::Pid;
-use std::io::{self, Result, Write};
+use regex::Regex;
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::convert::{TryFrom, TryInto};
+use std::io::{self, IsTerminal, Result, Write};
 
 use crate::address::{AddressTrait, AdrFromAbs, AdrFromRel};
+use crate::auxv::{load_auxv, AT_BASE, AT_ENTRY};
+use crate::checkpoint::{writable_regions, Checkpoint, SPILL_SIZE};
+use crate::condition::Condition;
+use crate::core_file::CoreFile;
+use crate::disasm::{format_function, format_instruction, format_instructions, DISAS_LEN};
+use crate::elf::dwarf::{CuInfo, LineInfo, LocalVarInfo, ScopeInfo};
 use crate::elf::elf64::Elf64;
+use crate::elf::location::{evaluate, EvalContext, Location};
+use crate::elf::type_info::{encode_int, FormatOption, TypeInfo};
+use crate::environ::{format_var, get_name, parse_environ, VALUE_LIMIT};
+use crate::exec::{format_args, read_exe_path, spawn_traced, FollowExecMode};
+use crate::fault::{diagnose, is_fault, FaultFunc};
+use crate::fpregs::{
+    format_fpregs, parse_u128, parse_xmm, set_xmm_value, write_fpregs, xmm_value, DWARF_XMM0,
+    XMM_REGS,
+};
+use crate::hexdump::hexdump;
+use crate::memory::{self, ProcessMemory, ReadMemory};
 use crate::memory_map::MemoryMap;
+use crate::pager::{Pager, StdoutPager, DEFAULT_HEIGHT};
+use crate::profile::{format_report, step_over_breakpoint, RunClock};
+use crate::prompt::{format_prompt, PromptFormat};
+use crate::record::{format_runs, RingBuffer, DEFAULT_RECORD_SIZE};
+use crate::solib::{
+    find_library, find_r_debug, group_libraries, parse_r_debug, to_libraries, CatchKind,
+    Catchpoint, RDebug, RT_CONSISTENT, R_DEBUG_SIZE,
+};
+use crate::source::{format_lines, read_source, LIST_CONTEXT};
+use crate::target::Target;
+use crate::task::{load_tasks, to_state_name};
+use crate::unwind::{is_ret, prologue_ret_offset, walk_frames, DEFAULT_BACKTRACE_LIMIT};
+use crate::user_command::{DefinitionBody, UserCommands};
+use crate::watchpoint::{
+    clear_status, hit_slots, read_status, set_watchpoints, watch_len, WatchKind, MAX_WATCHPOINTS,
+};
+
+// 変数表示時の最大読み込みサイズ
+const MAX_READ_SIZE: usize = 0x10000;
+
+// environから読み込む環境変数の最大数・1つあたりの最大サイズ
+const ENVIRON_MAX: usize = 0x10000;
+const ENVIRON_READ_SIZE: usize = 0x100000;
 
 // ブレイクポイントリスト
 struct Breakpoint<'a> {
     sym: String,                      // ブレイクポイントを貼るシンボル名
     inst: usize,                      // ブレイクポイント箇所の命令列
     addr: Box<dyn AddressTrait + 'a>, // シンボルテーブルに記載されているアドレス
+    pending: bool,                    // 実行ファイルが切り替わり、アドレスが未解決か
+    profiled: bool,                   // 停止せずにヒット回数を数えるか(profile on)
+    hits: u64,                        // ヒット回数(停止しなかったものも含む)
+    profile_hits: u64,                // 計測中のヒット回数(profile on)
+    condition: Option<Condition>,     // 停止条件(b <sym> if <reg>==<val>)
+    temporary: bool,                  // 最初に停止した際に削除するか(tb)
+    ignore: u64,                      // 停止せずに再開する残りの回数(ignore)
+    ignored: u64,                     // 前回の停止から、無視したヒット回数
+}
+
+// 共有ライブラリの検出に使う内部ブレイクポイント
+struct SolibBreakpoint {
+    addr: usize,    // アドレス
+    inst: usize,    // ブレイクポイント箇所の命令列
+    at_start: bool, // プログラムの開始位置(動的リンカがr_debugを設定するのを待つ)か
+}
+
+// 一時ブレイクポイント(until)
+struct TempBreakpoint {
+    addr: usize,      // アドレス
+    inst: usize,      // ブレイクポイント箇所の命令列
+    rsp: Option<u64>, // 戻りアドレスに貼った場合の、戻った後のrsp
+}
+
+// ハードウェアウォッチポイント(watch・rwatch)
+struct Watchpoint {
+    sym: String,     // 監視する変数名
+    addr: u64,       // アドレス
+    len: u64,        // 監視する長さ
+    kind: WatchKind, // 監視するアクセス
+    value: u64,      // 前回停止した時の値
+}
+
+// 引数なしのuntilで、抜けようとしているフレーム
+struct UntilFrame {
+    start: usize,   // 関数の先頭アドレス
+    end: usize,     // 関数の終端アドレス
+    rsp: u64,       // 開始時のrsp(これより深ければ呼び出し先、浅ければ関数から戻った)
+    line: LineInfo, // 開始時の行
+}
+
+// シンボルを読み込んだ共有ライブラリ
+struct SharedLibrary {
+    path: String, // パス
+    base: usize,  // ベースアドレス
+    elf: Elf64,   // シンボル
 }
 
 // ブレイクポイント管理
 struct BreakpointList<'a> {
     breakpoints: Vec<Breakpoint<'a>>,
+    targets: Option<Vec<usize>>, // 記録中であれば、対象となったブレイクポイント(登録済みのものを含む)
 }
 
 /// ブレイクポイント管理strcut実装
@@ -25,26 +118,41 @@ impl<'a> BreakpointList<'a> {
     pub fn new() -> Self {
         BreakpointList {
             breakpoints: vec![],
+            targets: None,
         }
     }
 
     /// ブレイクポイント設定取得
-    pub fn get(&self) -> &Vec<Breakpoint> {
+    pub fn get(&self) -> &Vec<Breakpoint<'_>> {
         &self.breakpoints
     }
 
     /// ブレイクポイント登録
+    ///
+    /// 記録中であれば、登録済みのブレイクポイントも含め、対象となったブレイクポイントを記録する
     pub fn register<T: 'a + AddressTrait>(&mut self, sym: &str, bp: T, bp_inst: usize) -> bool {
         // 既に登録されている場合、登録しない
-        match self.breakpoints.iter().find(|b| b.addr.get() == bp.get()) {
-            Some(_) => false,
+        match self.position(&bp) {
+            Some(i) => {
+                self.add_target(i);
+                false
+            }
             None => {
+                self.add_target(self.breakpoints.len());
                 // ブレイクポイント登録
                 self.breakpoints.push({
                     Breakpoint {
                         sym: sym.to_string(),
                         addr: Box::new(bp),
                         inst: bp_inst,
+                        pending: false,
+                        profiled: false,
+                        hits: 0,
+                        profile_hits: 0,
+                        condition: None,
+                        temporary: false,
+                        ignore: 0,
+                        ignored: 0,
                     }
                 });
                 true
@@ -57,15 +165,184 @@ impl<'a> BreakpointList<'a> {
         self.search(addr).is_some()
     }
 
-    /// ブレイクポイントサーチ
-    pub fn search<T: AddressTrait>(&self, addr: &T) -> Option<&Breakpoint> {
-        self.breakpoints.iter().find(|b| b.addr.get() == addr.get())
+    /// ブレイクポイントサーチ(未解決のものは除く)
+    pub fn search<T: AddressTrait>(&self, addr: &T) -> Option<&Breakpoint<'_>> {
+        self.breakpoints
+            .iter()
+            .find(|b| !b.pending && b.addr.get() == addr.get())
+    }
+
+    /// ブレイクポイント箇所の命令列を更新
+    pub fn set_inst<T: AddressTrait>(&mut self, addr: &T, inst: usize) {
+        if let Some(b) = self
+            .breakpoints
+            .iter_mut()
+            .find(|b| !b.pending && b.addr.get() == addr.get())
+        {
+            b.inst = inst;
+        }
+    }
+
+    /// ヒット回数を数えるか設定(indexを省略すれば全て)
+    ///
+    /// インデックス外であればfalse
+    pub fn set_profiled(&mut self, index: Option<usize>, profiled: bool) -> bool {
+        match index {
+            Some(i) => match self.breakpoints.get_mut(i) {
+                Some(b) => b.profiled = profiled,
+                None => return false,
+            },
+            None => self
+                .breakpoints
+                .iter_mut()
+                .for_each(|b| b.profiled = profiled),
+        }
+        true
+    }
+
+    /// 対象となったブレイクポイントの記録を開始(b <sym> if・tb)
+    pub fn start_targets(&mut self) {
+        self.targets = Some(vec![]);
+    }
+
+    /// 対象となったブレイクポイントの記録を取り出し、記録を終了
+    pub fn take_targets(&mut self) -> Vec<usize> {
+        self.targets.take().unwrap_or_default()
+    }
+
+    /// 登録済みのアドレスを、対象となったブレイクポイントとして記録
+    pub fn mark_target<T: AddressTrait>(&mut self, addr: &T) {
+        if let Some(i) = self.position(addr) {
+            self.add_target(i);
+        }
+    }
+
+    /// 記録中であれば、対象となったブレイクポイントを記録
+    fn add_target(&mut self, index: usize) {
+        if let Some(t) = self.targets.as_mut() {
+            t.push(index);
+        }
+    }
+
+    /// 指定したブレイクポイントへ停止条件を設定
+    pub fn set_condition(&mut self, indexes: &[usize], condition: &Condition) {
+        for i in indexes {
+            if let Some(b) = self.breakpoints.get_mut(*i) {
+                b.condition = Some(condition.clone());
+            }
+        }
+    }
+
+    /// 指定したブレイクポイントを、最初に停止した際に削除するよう設定
+    pub fn set_temporary(&mut self, indexes: &[usize]) {
+        for i in indexes {
+            if let Some(b) = self.breakpoints.get_mut(*i) {
+                b.temporary = true;
+            }
+        }
+    }
+
+    /// 停止せずに再開する回数を設定
+    ///
+    /// インデックス外であればfalse
+    pub fn set_ignore(&mut self, index: usize, count: u64) -> bool {
+        match self.breakpoints.get_mut(index) {
+            Some(b) => {
+                b.ignore = count;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 無視する回数が残っていれば、1減らしてtrueを返す
+    pub fn skip_hit<T: AddressTrait>(&mut self, addr: &T) -> bool {
+        match self
+            .breakpoints
+            .iter_mut()
+            .find(|b| !b.pending && b.addr.get() == addr.get())
+        {
+            Some(b) if 0 < b.ignore => {
+                b.ignore -= 1;
+                b.ignored += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 無視したヒット回数を取得し、0にする
+    pub fn take_ignored<T: AddressTrait>(&mut self, addr: &T) -> u64 {
+        self.breakpoints
+            .iter_mut()
+            .find(|b| !b.pending && b.addr.get() == addr.get())
+            .map_or(0, |b| std::mem::take(&mut b.ignored))
+    }
+
+    /// ブレイクポイントのインデックスを検索(未解決のものは除く)
+    pub fn position<T: AddressTrait>(&self, addr: &T) -> Option<usize> {
+        self.breakpoints
+            .iter()
+            .position(|b| !b.pending && b.addr.get() == addr.get())
+    }
+
+    /// ヒット回数を数えるブレイクポイントであれば、回数を加算して元の命令の先頭バイトを返す
+    pub fn count_hit<T: AddressTrait>(&mut self, addr: &T) -> Option<u8> {
+        let b = self
+            .breakpoints
+            .iter_mut()
+            .find(|b| b.profiled && !b.pending && b.addr.get() == addr.get())?;
+        b.hits += 1;
+        b.profile_hits += 1;
+        Some(b.inst as u8)
+    }
+
+    /// ヒット回数を加算(停止するブレイクポイント)
+    pub fn add_hit<T: AddressTrait>(&mut self, addr: &T) {
+        if let Some(b) = self
+            .breakpoints
+            .iter_mut()
+            .find(|b| !b.pending && b.addr.get() == addr.get())
+        {
+            b.hits += 1;
+        }
+    }
+
+    /// 加算したヒット回数を取り消す
+    pub fn uncount_hit<T: AddressTrait>(&mut self, addr: &T) {
+        if let Some(b) = self
+            .breakpoints
+            .iter_mut()
+            .find(|b| b.profiled && !b.pending && b.addr.get() == addr.get())
+        {
+            b.hits -= 1;
+            b.profile_hits -= 1;
+        }
+    }
+
+    /// 計測中のヒット回数を0にする(セッション中のヒット回数は残す)
+    pub fn reset_profile_hits(&mut self) {
+        self.breakpoints.iter_mut().for_each(|b| b.profile_hits = 0);
+    }
+
+    /// 全てのブレイクポイントを未解決にする(実行ファイルが切り替わった場合)
+    pub fn set_pending(&mut self) {
+        self.breakpoints.iter_mut().for_each(|b| b.pending = true);
+    }
+
+    /// 未解決のブレイクポイントに、解決したアドレスと命令列を設定
+    pub fn resolve<T: 'a + AddressTrait>(&mut self, index: usize, bp: T, bp_inst: usize) {
+        if let Some(b) = self.breakpoints.get_mut(index) {
+            b.addr = Box::new(bp);
+            b.inst = bp_inst;
+            b.pending = false;
+        }
     }
 
     /// ブレイクポイント削除
     ///
     /// 削除したブレイクポイントを返す
-    pub fn delete(&mut self, index: usize) -> Option<Breakpoint> {
+    pub fn delete(&mut self, index: usize) -> Option<Breakpoint<'_>> {
         // インデックス外はエラー
         let l = self.breakpoints.len();
         if l == 0 || index >= l {
@@ -80,11 +357,41 @@ impl<'a> BreakpointList<'a> {
 // デバッガ
 pub struct Debugger<'a> {
     pid: Pid,
+    target: Target, // レジスタ・メモリの読み込み元(プロセス・コアファイル)
     path: String,
-    entry: usize, // エントリーアドレス
+    program: String,   // 起動した実行ファイル(runで起動し直す)
+    args: Vec<String>, // プログラムへ渡す引数(-- 以降)
+    exited: bool,      // プログラムが終了したか
+    entry: usize,      // エントリーアドレス
     breakpoint: BreakpointList<'a>,
     memory_map: MemoryMap,
     elf: Elf64,
+    print_opt: FormatOption,                            // 変数表示オプション
+    frame: usize,                                       // 選択中のフレーム番号
+    height: usize,                                      // ページャーの1ページの行数
+    record: Option<RingBuffer<u64>>,                    // 実行した命令アドレスの記録(record on)
+    tracing: bool,            // 記録中の再開(ブレイクポイントまでステップ実行する)
+    running: bool,            // cで再開中か(sであればfalse)
+    catches: Vec<Catchpoint>, // キャッチポイント(catch load・catch unload)
+    solib_bp: Option<SolibBreakpoint>, // 共有ライブラリの検出に使う内部ブレイクポイント
+    libraries: BTreeSet<(String, u64)>, // マップされている共有ライブラリ(パス・ベースアドレス)
+    solibs: Vec<SharedLibrary>, // シンボルを読み込んだ共有ライブラリ
+    temp_bps: Vec<TempBreakpoint>, // 一時ブレイクポイント(until・finish)
+    until: Option<UntilFrame>, // 引数なしのuntilで、抜けようとしているフレーム
+    signal: Option<Signal>,   // 停止の原因となったシグナル(再開時にプログラムへ送る)
+    follow_exec: FollowExecMode, // execve後に停止するか(set follow-exec-mode)
+    user_commands: UserCommands, // ユーザー定義コマンド(define ... end)
+    input: VecDeque<String>,  // 実行待ちのコマンド(ユーザー定義コマンド・source)
+    checkpoints: Vec<Checkpoint>, // 保存したプロセスの状態(checkpoint)
+    run_clock: RunClock,      // ヒット回数を数えている間の実行時間(profile on)
+    stop_at_entry: bool,      // 最初の入力の前に、プログラムの開始位置まで実行する
+    at_start: bool,           // 最初の停止(再開していない)か
+    to_entry: bool,           // 開始位置の一時ブレイクポイントへ向かっているか(starti)
+    prompt_format: PromptFormat, // プロンプトの表示形式(set prompt-format)
+    prompt: Option<(u64, String)>, // 整形済みのプロンプトと、その時のrip
+    backtrace_limit: usize,   // btで辿るフレーム数の上限(set backtrace limit)
+    finish: Option<usize>,    // finishで向かっている戻りアドレス
+    watchpoints: [Option<Watchpoint>; MAX_WATCHPOINTS], // DR0〜DR3毎のウォッチポイント(watch)
 }
 
 /// デバッガ実装
@@ -93,17 +400,119 @@ impl<'a> Debugger<'a> {
     pub fn new(target_pid: Pid, path: String) -> Self {
         Debugger {
             path: path.clone(),
+            program: path.clone(),
+            args: vec![],
+            exited: false,
             pid: target_pid,
+            target: Target::Process(target_pid),
             entry: 0x0,
             breakpoint: BreakpointList::new(),
             memory_map: MemoryMap::new(target_pid),
             elf: Elf64::new(path),
+            print_opt: FormatOption::default(),
+            frame: 0,
+            height: DEFAULT_HEIGHT,
+            record: None,
+            tracing: false,
+            running: false,
+            catches: vec![],
+            solib_bp: None,
+            libraries: BTreeSet::new(),
+            solibs: vec![],
+            temp_bps: vec![],
+            until: None,
+            signal: None,
+            follow_exec: FollowExecMode::Continue,
+            user_commands: UserCommands::new(),
+            input: VecDeque::new(),
+            checkpoints: vec![],
+            run_clock: RunClock::new(),
+            stop_at_entry: false,
+            at_start: false,
+            to_entry: false,
+            prompt_format: PromptFormat::Compact,
+            prompt: None,
+            backtrace_limit: DEFAULT_BACKTRACE_LIMIT,
+            finish: None,
+            watchpoints: Default::default(),
+        }
+    }
+
+    /// 最初の入力の前に、プログラムの開始位置まで実行するか設定(--stop-at-entry)
+    pub fn set_stop_at_entry(&mut self, on: bool) {
+        self.stop_at_entry = on;
+    }
+
+    /// プログラムへ渡した引数を設定(runで起動し直す際にも渡す)
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+
+    /// コアファイルを開くデバッガのコンストラクタ
+    ///
+    /// pathはコアを出力した実行ファイル
+    pub fn from_core(path: String, core: CoreFile) -> Self {
+        let mut dbg = Debugger::new(Pid::from_raw(core.get_status().pid), path);
+        dbg.target = Target::Core(Box::new(core));
+        dbg
+    }
+
+    /// コアファイルのデバッグ開始
+    ///
+    /// 停止した原因を表示し、読み込みのみのコマンドを受け付ける
+    pub fn start_core(&mut self) {
+        let mappings = self.target.load_mappings().unwrap_or_default();
+        let name = std::path::Path::new(&self.path).file_name();
+        // 別の場所でコアを出力した場合は、ファイル名が一致するものとする
+        let base = [true, false].iter().find_map(|exact| {
+            mappings
+                .iter()
+                .filter(|m| match exact {
+                    true => m.path == self.path,
+                    false => std::path::Path::new(&m.path).file_name() == name,
+                })
+                .map(|m| m.start)
+                .min()
+        });
+        self.entry = match base {
+            Some(b) => b as usize,
+            None => {
+                println!("{} is not mapped in the core", self.path);
+                return;
+            }
+        };
+        if let Err(err) = self.load_symbols() {
+            println!("cannot parse ELF: {:?}", err);
+            return;
+        }
+
+        let regs = self.read_regs();
+        let (signal, info) = match &self.target {
+            Target::Core(core) => (core.get_status().signal, core.get_siginfo().copied()),
+            Target::Process(_) => return,
+        };
+        println!("Core was generated by pid {} ({})", self.pid, self.path);
+        match info {
+            Some(info) => {
+                let func = self.search_fault_func(regs.rip);
+                diagnose(&self.target, &info, &regs, &[], func.as_ref())
+                    .iter()
+                    .for_each(|l| println!("{}", l));
+            }
+            None => match Signal::try_from(signal) {
+                Ok(s) => println!("Program terminated with signal {}", s),
+                Err(_) => println!("Program terminated with signal {}", signal),
+            },
         }
+        self.shell();
     }
 
     /// デバッガ起動
     pub fn start(&mut self) {
         println!("start start_dbg({})", self.pid);
+        if !self.args.is_empty() {
+            println!("arguments: {}", format_args(&self.args));
+        }
 
         // 子プロセスWait
         let mut first_sig = true;
@@ -115,7 +524,8 @@ impl<'a> Debugger<'a> {
                         "[start_dbg] exit child process: pid={:?}, sig={:?}",
                         pid, sig
                     );
-                    break;
+                    self.show_profile_on_exit();
+                    self.exited_shell();
                 }
                 // シグナル受信による子プロセス停止
                 WaitStatus::Stopped(_pid, sig) => {
@@ -126,12 +536,26 @@ impl<'a> Debugger<'a> {
                             println!("cannot parse ELF: {:?}", err);
                             self.sh_quit();
                         }
+                        // プログラムが別の実行ファイルをexecveした場合も停止させる
+                        setoptions(self.pid, Options::PTRACE_O_TRACEEXEC)
+                            .expect("setoptions is failed");
+                        // 入力を受け付ける前に、開始位置まで実行する
+                        if self.stop_at_entry && self.run_to_entry() {
+                            first_sig = false;
+                            continue;
+                        }
                     }
+                    self.at_start = first_sig;
                     self.stopped_handler(sig);
                 }
+                // シグナル受信による子プロセス終了
                 WaitStatus::Signaled(pid, sig, _) => {
-                    println!("[start_dbg] recv signal : pid={:?}, sig={:?}", pid, sig)
+                    println!("[start_dbg] recv signal : pid={:?}, sig={:?}", pid, sig);
+                    self.show_profile_on_exit();
+                    self.exited_shell();
                 }
+                // execveによる停止
+                WaitStatus::PtraceEvent(_pid, _sig, libc::PTRACE_EVENT_EXEC) => self.exec_handler(),
                 WaitStatus::PtraceEvent(pid, sig, _) => {
                     println!("[start_dbg] ptrace event: pid={:?}, sig={:?}", pid, sig)
                 }
@@ -156,25 +580,351 @@ impl<'a> Debugger<'a> {
             16,
         )
         .expect("can not parse entry ddress");
+        self.load_symbols()
+    }
 
-        // ELFファイルロード
-        self.elf.load()
+    /// ELFファイル(ヘッダー・シンボル)ロード
+    ///
+    /// strip済みであればシンボルはないため、symbol-fileで読み込ませる
+    fn load_symbols(&mut self) -> Result<()> {
+        self.prompt = None;
+        self.elf.load_headers()?;
+        if let Err(e) = self.elf.load_symbols() {
+            println!(
+                "cannot load symbols from {}: {} (use symbol-file)",
+                self.path, e
+            );
+        }
+        Ok(())
     }
 
     /// WaitStatus::Stoppedハンドラ
+    ///
+    /// SIGTRAP以外のシグナルはsignal_handlerで処理する
     fn stopped_handler(&mut self, sig: nix::sys::signal::Signal) {
         // トレースシグナルであれば処理
         if sig == nix::sys::signal::Signal::SIGTRAP {
+            // 停止した位置の最も内側のフレームを選択
+            self.frame = 0;
+
             // ブレイクポイントで停止している場合、次の命令を指している
             let rip = (self.read_regs().rip - 1) as usize;
             let bp = AdrFromAbs::new(rip);
-            if self.breakpoint.has_addr(&bp) {
-                self.recover_bp(&bp);
-                println!("break at 0x{:x}", bp.get());
+            if let Some(hits) = self.take_watch_hits() {
+                // 書き込んだ命令の直後で停止している
+                self.tracing = false;
+                if let Some(r) = self.record.as_mut() {
+                    r.push(rip as u64 + 1);
+                }
+                self.show_watch_hits(&hits);
+            } else if self.solib_bp.as_ref().is_some_and(|b| b.addr == rip) {
+                if let Some(r) = self
+                    .record
+                    .as_mut()
+                    .filter(|r| r.last() != Some(rip as u64))
+                {
+                    r.push(rip as u64);
+                }
+                // キャッチポイントにマッチしなければ、そのまま再開する(sであれば停止済み)
+                if !self.solib_event() && self.running {
+                    self.resume(None);
+                    return;
+                }
+                self.tracing = false;
+            } else if let Some(hit) = self.temp_bps.iter().position(|b| b.addr == rip) {
+                // 一時ブレイクポイントを外し、元の命令から再開できるようにする
+                let ret_rsp = self.temp_bps[hit].rsp;
+                let tracing = self.tracing;
+                self.tracing = false;
+                self.clear_temp_bps();
+                let mut regs = self.read_regs();
+                regs.rip = rip as u64;
+                self.write_regs(regs);
+                if let Some(r) = self
+                    .record
+                    .as_mut()
+                    .filter(|r| r.last() != Some(rip as u64))
+                {
+                    r.push(rip as u64);
+                }
+                // 再帰呼び出しの深いフレームが戻りアドレスを通過した場合は、貼り直して再開する
+                if ret_rsp.is_some_and(|r| self.read_regs().rsp < r) {
+                    self.step();
+                    nix::sys::wait::waitpid(self.pid, None).expect("until: wait is failed");
+                    self.plant_temp_bp(rip, ret_rsp);
+                    self.tracing = tracing;
+                    self.resume(None);
+                    return;
+                }
+                if self.until.is_some() && !self.until_step() {
+                    return;
+                }
+                if std::mem::take(&mut self.to_entry) {
+                    self.show_entry();
+                } else if self.finish.take().is_some() {
+                    self.show_finish();
+                } else {
+                    self.show_until();
+                }
+            } else if let Some(orig) = self.count_profile_hit(&bp) {
+                // ヒット回数を数えるブレイクポイントは、停止せずに再開する
+                self.profile_hit(rip, orig);
+                return;
+            } else if self.breakpoint.has_addr(&bp) {
+                self.breakpoint.add_hit(&bp);
+                // 停止条件は、ブレイクポイントの命令を実行する前のレジスタで評価する
+                let mut regs = self.read_regs();
+                regs.rip = bp.get() as u64;
+                let hit = self
+                    .breakpoint
+                    .search(&bp)
+                    .and_then(|b| b.condition.as_ref())
+                    .is_none_or(|c| c.eval(&regs));
+                // 条件が成り立っても、無視する回数が残っていれば停止しない
+                let hit = hit && !self.breakpoint.skip_hit(&bp);
+                // 一時ブレイクポイントは復帰時に削除されるため、番号を先に求める
+                let no = self.breakpoint.position(&bp);
+                let tracing = self.tracing;
+                self.tracing = false;
+                self.recover_bp(&bp, hit);
+                // ブレイクポイントの命令は実行済みのため、停止した位置も記録する
+                // (記録中の再開では、int 3の実行前にブレイクポイントを記録済み)
+                let stopped = self.read_regs().rip;
+                if let Some(r) = self.record.as_mut() {
+                    if r.last() != Some(rip as u64) {
+                        r.push(rip as u64);
+                    }
+                    r.push(stopped);
+                }
+                // ブレイクポイントの命令が、監視している変数へ書き込んだか
+                let watched = self.take_watch_hits();
+                // 停止条件が成り立たなければ、停止を表示せずに再開する
+                if !hit && watched.is_none() {
+                    self.tracing = tracing;
+                    self.resume(None);
+                    return;
+                }
+                if hit {
+                    println!("{}", self.format_break_location(no, bp.get() as u64));
+                    let ignored = self.breakpoint.take_ignored(&bp);
+                    if 0 < ignored {
+                        println!("ignored {} hits before this stop", ignored);
+                    }
+                    // 戻りアドレスにブレイクポイントがあれば、一時ブレイクポイントを貼らずに止まる
+                    if self.finish.take() == Some(bp.get()) {
+                        self.show_finish_value();
+                    }
+                }
+                if let Some(hits) = watched {
+                    self.show_watch_hits(&hits);
+                }
+            } else {
+                // ブレイクポイント以外では、停止した位置の命令を記録
+                if let Some(r) = self.record.as_mut() {
+                    r.push(rip as u64 + 1);
+                }
+                // 記録中の再開は、ブレイクポイントまでステップ実行を続ける
+                if self.tracing {
+                    self.step();
+                    return;
+                }
+                if self.until.is_some() {
+                    if !self.until_step() {
+                        return;
+                    }
+                    self.show_until();
+                }
             }
 
-            // シェルから入力を受け付ける
+            // until・finishの途中で別の理由で停止した場合も、一時ブレイクポイントは外す
+            self.clear_temp_bps();
+            self.until = None;
+            self.finish = None;
+
+            // 停止した位置の命令を表示し、シェルから入力を受け付ける
+            let mem = ProcessMemory::new(self.pid);
+            let rip = self.read_regs().rip;
+            if let Some(inst) = format_instruction(&mem, rip, &self.get_original_bytes()) {
+                println!("{}", inst);
+            }
             self.shell();
+        } else {
+            self.signal_handler(sig);
+        }
+    }
+
+    /// execveによる停止
+    ///
+    /// 新しい実行ファイルのシンボル・メモリマップを読み込み直し、ブレイクポイントを解決し直す
+    /// follow-exec-modeがstop、またはステップ実行中であれば、新しい実行ファイルの開始位置で停止する
+    fn exec_handler(&mut self) {
+        let path = match read_exe_path(self.pid) {
+            Ok(p) => p,
+            Err(e) => {
+                println!("cannot read executable path: {}", e);
+                self.sh_quit();
+                return;
+            }
+        };
+        println!("process {} is executing new program: {}", self.pid, path);
+        self.path = path.clone();
+        self.elf = Elf64::new(path.clone());
+        self.memory_map = MemoryMap::new(self.pid);
+        if let Err(err) = self.load_elf() {
+            println!("cannot parse ELF: {:?}", err);
+            self.sh_quit();
+        }
+
+        // 以前のアドレス空間の状態を破棄
+        self.frame = 0;
+        self.signal = None;
+        self.solib_bp = None;
+        self.libraries.clear();
+        self.solibs.clear();
+        self.temp_bps.clear();
+        self.until = None;
+        self.checkpoints.clear();
+        // デバッグレジスタはexecveで消える
+        self.watchpoints = Default::default();
+        if let Some(r) = self.record.as_ref() {
+            self.record = Some(RingBuffer::new(r.get_capacity()));
+        }
+
+        // ブレイクポイントの元の命令は失われたため、新しい実行ファイルで解決し直す
+        self.breakpoint.set_pending();
+        self.resolve_pending_breaks();
+        if !self.catches.is_empty() {
+            self.setup_solib_bp();
+        }
+
+        if FollowExecMode::Continue == self.follow_exec && self.running {
+            self.resume(None);
+            return;
+        }
+        self.running = false;
+        self.tracing = false;
+        let mem = ProcessMemory::new(self.pid);
+        let rip = self.read_regs().rip;
+        if let Some(inst) = format_instruction(&mem, rip, &self.get_original_bytes()) {
+            println!("{}", inst);
+        }
+        self.shell();
+    }
+
+    /// 未解決のブレイクポイントを、シンボル名から解決して貼り直す
+    ///
+    /// 解決できなかったもの・読み込めないアドレス(未ロードの共有ライブラリ等)は未解決のまま残す
+    fn resolve_pending_breaks(&mut self) {
+        let pending = self
+            .breakpoint
+            .get()
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.pending)
+            .map(|(i, b)| (i, b.sym.clone()))
+            .collect::<Vec<(usize, String)>>();
+        for (i, sym) in pending {
+            // 同じ行に複数の文があれば、まだ貼っていない位置とする
+            let addr = self
+                .resolve_location(&sym)
+                .into_iter()
+                .find(|a| !self.breakpoint.has_addr(&AdrFromAbs::new(*a)));
+            match addr {
+                Some(addr) => {
+                    let address = AdrFromAbs::new(addr);
+                    let inst = match self.try_read_bytes(&address, 8) {
+                        Ok(data) => u64::from_le_bytes(data.try_into().unwrap()),
+                        Err(_) => {
+                            println!(
+                                "Breakpoint {} ({}) pending (cannot access 0x{:x})",
+                                i, sym, addr
+                            );
+                            continue;
+                        }
+                    };
+                    self.write_mem(&address, ((0xFFFF_FFFF_FFFF_FF00 & inst) | 0xCC) as usize);
+                    self.breakpoint.resolve(i, address, inst as usize);
+                    println!("Breakpoint {} ({}) re-set at 0x{:x}", i, sym, addr);
+                }
+                None => println!("Breakpoint {} ({}) pending", i, sym),
+            }
+        }
+    }
+
+    /// SIGTRAP以外のシグナルによる停止
+    ///
+    /// 不正命令・バスエラー等は原因を表示して停止し、再開時にシグナルを送る
+    /// それ以外のシグナルは、停止せずにプログラムへ送って再開する
+    fn signal_handler(&mut self, sig: Signal) {
+        if !is_fault(sig) {
+            self.resume(Some(sig));
+            return;
+        }
+        self.frame = 0;
+        self.running = false;
+        self.tracing = false;
+        self.clear_temp_bps();
+        self.until = None;
+
+        let regs = self.read_regs();
+        let func = self.search_fault_func(regs.rip);
+        match getsiginfo(self.pid) {
+            Ok(info) => {
+                let mem = ProcessMemory::new(self.pid);
+                let originals = self.get_original_bytes();
+                diagnose(&mem, &info, &regs, &originals, func.as_ref())
+                    .iter()
+                    .for_each(|l| println!("{}", l));
+            }
+            Err(e) => println!(
+                "Program received signal {} (cannot get siginfo: {})",
+                sig, e
+            ),
+        }
+        self.signal = Some(sig);
+        self.shell();
+    }
+
+    /// 停止した位置(rip)の関数を検索
+    fn search_fault_func(&self, rip: u64) -> Option<FaultFunc> {
+        (rip as usize)
+            .checked_sub(self.entry)
+            .and_then(|pc| self.elf.search_func_sym_by_addr(pc as u64))
+            .map(|sym| FaultFunc {
+                name: sym.get_name(),
+                start: (self.entry as u64) + sym.st_value,
+                size: sym.get_size(),
+            })
+    }
+
+    /// 停止せずにヒット回数を数えるブレイクポイントであれば、回数を加算する
+    ///
+    /// cで再開中のみ数える(s・記録中の再開では、通常のブレイクポイントとして停止する)
+    fn count_profile_hit<T: AddressTrait>(&mut self, bp: &T) -> Option<u8> {
+        if !self.running || self.tracing {
+            return None;
+        }
+        self.breakpoint.count_hit(bp)
+    }
+
+    /// ヒット回数を数えたブレイクポイントの元の命令を実行し、再開する
+    ///
+    /// ヒットする度に実行するため、割り当て・表示をしない
+    fn profile_hit(&mut self, rip: usize, orig: u8) {
+        match step_over_breakpoint(self.pid, rip as u64, orig).expect("profile: step is failed") {
+            WaitStatus::Stopped(_, Signal::SIGTRAP) => self.resume(None),
+            WaitStatus::Stopped(_, sig) => {
+                // 元の命令を実行する前にシグナルを受信した場合は、再開後にもう一度ヒットする
+                if self.read_regs().rip == rip as u64 {
+                    self.breakpoint.uncount_hit(&AdrFromAbs::new(rip));
+                }
+                self.signal_handler(sig);
+            }
+            status => {
+                println!("[start_dbg] exit child process: {:?}", status);
+                self.show_profile_on_exit();
+                std::process::exit(0);
+            }
         }
     }
 
@@ -184,7 +934,9 @@ impl<'a> Debugger<'a> {
     /// 2. ripをブレイクポイントのアドレスへ再設定
     /// 3. 1step実行し、元の命令を処理
     /// 4. SIGTRAPを待ち、1で書き換えたブレイクポイントを貼る
-    fn recover_bp<T: AddressTrait>(&mut self, rip_bp: &T) {
+    ///
+    /// 一時ブレイクポイント(tb)で停止した(hit)場合は、4で貼り直さずに削除する
+    fn recover_bp<T: AddressTrait>(&mut self, rip_bp: &T, hit: bool) {
         // 命令を書き換える
         let bp_info = self.breakpoint.search(rip_bp).unwrap();
 
@@ -202,6 +954,12 @@ impl<'a> Debugger<'a> {
         // SIGTRAP待ち
         match nix::sys::wait::waitpid(self.pid, None).expect("recover_bp: wait is failed") {
             // ブレイクポイントの設定をもとにもどす
+            WaitStatus::Stopped(_, _) if hit && bp_info.temporary => {
+                if let Some(i) = self.breakpoint.position(rip_bp) {
+                    self.breakpoint.delete(i);
+                    println!("delete temporary Breakpoint({})", i);
+                }
+            }
             WaitStatus::Stopped(_, _) => {
                 // 登録する際に、エントリーアドレス分を加算しているので、差し引く
                 let addr = bp_info.addr.get();
@@ -212,19 +970,33 @@ impl<'a> Debugger<'a> {
         };
     }
 
+    /// プログラム終了後の入力待ち
+    ///
+    /// runで起動し直せば戻る。入力の終端に達すれば、デバッガを終了する
+    fn exited_shell(&mut self) {
+        self.exited = true;
+        self.shell();
+    }
+
     /// 入力待ち
     fn shell(&mut self) {
+        self.run_clock.pause();
         loop {
-            // プロンプトを表示
-            let regs = self.read_regs();
-            print!("[rip: 0x{:x}] >> ", regs.rip);
-            io::stdout().flush().unwrap();
-
             // コマンド入力受付
-            let mut s = String::new();
-            std::io::stdin().read_line(&mut s).ok();
+            let prompt = match self.exited {
+                true => "[exited] >> ".to_string(),
+                false => self.format_prompt(),
+            };
+            let s = match self.read_command(&prompt) {
+                Some(s) => s,
+                None if self.exited => {
+                    println!();
+                    self.sh_quit();
+                    return;
+                }
+                None => String::new(),
+            };
             let coms: Vec<String> = s
-                .trim()
                 .split_whitespace()
                 .map(|e| e.parse().ok().unwrap())
                 .collect();
@@ -234,12 +1006,47 @@ impl<'a> Debugger<'a> {
                 continue;
             }
 
+            // コアファイルでは、プログラムを実行・変更するコマンドは使えない
+            if self.target.is_core() && needs_process(&coms) {
+                println!(
+                    "The program is not being run (core file): {}",
+                    coms.join(" ")
+                );
+                continue;
+            }
+
+            // 終了後は、プロセスを読み書きしないコマンドのみ使える
+            if self.exited && !available_after_exit(&coms) {
+                println!("The program is not being run: {}", coms.join(" "));
+                continue;
+            }
+
             // 各コマンドを実行
             match &*coms[0] {
+                // 起動し直す
+                "show" if coms.len() == 2 && "args" == coms[1] => self.show_args(),
+                "run" | "r" if coms.len() == 1 => {
+                    if self.sh_run() {
+                        break;
+                    }
+                }
                 // ブレイクポイント作成
                 "b" if coms.len() == 2 => self.sh_breakpoint(&coms[1]),
+                "tb" if coms.len() == 2 => self.sh_temp_breakpoint(&coms[1]),
+                "b" if 4 <= coms.len() && "if" == coms[2] => {
+                    self.sh_cond_breakpoint(&coms[1], &coms[3..].join(" "))
+                }
+                "b" if coms.len() == 3 && "--rel" == coms[1] => {
+                    self.sh_addr_breakpoint(&coms[2], true)
+                }
+                "b" if coms.len() == 3 && "--choose" == coms[1] => {
+                    self.sh_choose_line_breakpoint(&coms[2])
+                }
                 // ブレイクポイントリリース
+                "d" if coms.len() == 2 && "*" == coms[1] => self.sh_release_all_breaks(),
+                "delete" if coms.len() == 2 && "all" == coms[1] => self.sh_release_all_breaks(),
                 "d" if coms.len() == 2 => self.sh_release_break(&coms[1]),
+                "clear" if coms.len() == 2 => self.sh_clear_break(&coms[1]),
                 // シンボルリード
                 "p" if coms.len() == 2 => self.sh_read_sym(&coms[1]),
                 // シンボル書き込み
@@ -251,129 +1058,2979 @@ impl<'a> Debugger<'a> {
                     self.cont();
                     break;
                 }
+                // キャッチポイント
+                "catch" if 2 <= coms.len() && coms.len() <= 3 && "load" == coms[1] => {
+                    self.sh_catch(CatchKind::Load, coms.get(2))
+                }
+                "catch" if 2 <= coms.len() && coms.len() <= 3 && "unload" == coms[1] => {
+                    self.sh_catch(CatchKind::Unload, coms.get(2))
+                }
+                "d" if coms.len() == 3 && "catch" == coms[1] => self.sh_delete_catch(&coms[2]),
+                // プロセスの状態の保存・復元
+                "checkpoint" if coms.len() == 1 => self.sh_checkpoint(),
+                "restart" if coms.len() == 2 => self.sh_restart(&coms[1]),
+                "info" if coms.len() == 2 && "checkpoints" == coms[1] => self.show_checkpoints(),
+                "d" if coms.len() == 3 && "checkpoint" == coms[1] => {
+                    self.sh_delete_checkpoint(&coms[2])
+                }
+                // ブレイクポイントのヒット回数の計測
+                "profile" if 2 <= coms.len() && "on" == coms[1] => self.sh_profile_on(&coms[2..]),
+                "profile" if coms.len() == 2 && "off" == coms[1] => {
+                    self.breakpoint.set_profiled(None, false);
+                    println!("profiling off (hits are kept for profile report)");
+                }
+                "profile" if coms.len() == 2 && "report" == coms[1] => self.show_profile(),
+                "profile" if coms.len() == 2 && "reset" == coms[1] => {
+                    self.breakpoint.reset_profile_hits();
+                    self.run_clock = RunClock::new();
+                }
+                // プログラムの開始位置まで実行
+                "starti" if coms.len() == 1 => {
+                    if !self.at_start {
+                        println!("The program has already been resumed (starti is only available at the first stop)");
+                    } else if self.run_to_entry() {
+                        break;
+                    }
+                }
+                // 現在の関数から戻るまで実行
+                "finish" if coms.len() == 1 => {
+                    if self.sh_finish() {
+                        break;
+                    }
+                }
+                // 指定位置(省略時は現在の行より後ろの行)まで実行
+                "until" if coms.len() <= 2 => {
+                    if self.sh_until(coms.get(1).map(|s| s.as_str())) {
+                        break;
+                    }
+                }
+                "info" if coms.len() == 2 && "catch" == coms[1] => self.show_catches(),
+                // ユーザー定義コマンド
+                "define" if coms.len() == 2 => self.sh_define(&coms[1]),
+                "info" if coms.len() == 2 && "user-commands" == coms[1] => {
+                    self.show_user_commands()
+                }
+                "save" if coms.len() == 3 && "user-commands" == coms[1] => {
+                    self.save_user_commands(&coms[2])
+                }
+                // ファイルからコマンドを実行
+                "source" if coms.len() == 2 => self.sh_source(&coms[1]),
+                // 環境変数表示
+                "info" if 2 <= coms.len() && coms.len() <= 3 && "environ" == coms[1] => {
+                    self.show_environ(coms.get(2).map(|s| s.as_str()))
+                }
+                // 命令アドレスの記録
+                "record" if 2 <= coms.len() && coms.len() <= 3 && "on" == coms[1] => {
+                    self.record_on(coms.get(2))
+                }
+                "record" if coms.len() == 2 && "off" == coms[1] => self.record_off(),
+                "record" if 2 <= coms.len() && coms.len() <= 3 && "log" == coms[1] => {
+                    self.show_record(coms.get(2))
+                }
                 // STEP実行
                 "s" => {
-                    self.step();
+                    self.running = false;
+                    step(self.pid, self.signal.take()).expect("step is failed");
                     break;
                 }
                 // ヘルプ
                 "h" => self.help(),
                 // ブレイクポイント表示
                 "bl" => self.show_break(),
+                // ウォッチポイント
+                "watch" if coms.len() == 2 => self.sh_watch(&coms[1], WatchKind::Write),
+                "rwatch" if coms.len() == 2 => self.sh_watch(&coms[1], WatchKind::Access),
+                "dwatch" if coms.len() == 2 => self.sh_delete_watch(&coms[1]),
+                "info" if coms.len() == 2 && "watch" == coms[1] => self.show_watch(),
+                "ignore" if coms.len() == 3 => self.sh_ignore(&coms[1], &coms[2]),
+                "info" if coms.len() == 3 && "break" == coms[1] => self.show_break_info(&coms[2]),
                 // レジスタ表示
                 "info" if coms.len() == 2 && "regs" == coms[1] => self.show_regs(),
+                "info" if coms.len() == 2 && "fpregs" == coms[1] => self.show_fpregs(),
+                // スレッド一覧表示
+                "info" if coms.len() == 2 && "threads" == coms[1] => self.show_threads(),
+                // マップされている領域の表示
+                "info"
+                    if coms.len() == 3
+                        && "proc" == coms[1]
+                        && ("mappings" == coms[2] || "maps" == coms[2]) =>
+                {
+                    self.show_mappings()
+                }
                 // debugセクション情報表示
-                "info" if coms.len() == 2 && "debugsec" == coms[1] => self.elf.show_debug(),
+                "info" if 2 <= coms.len() && "debugsec" == coms[1] => {
+                    self.paged(|out| self.show_debugsec(out, &coms[2..]))
+                }
+                // 共有ライブラリ一覧表示
+                "info"
+                    if coms.len() == 2 && ("sharedlibrary" == coms[1] || "shared" == coms[1]) =>
+                {
+                    self.show_sharedlibrary()
+                }
+                // 実行ファイル・セクション一覧表示
+                "info" if coms.len() == 2 && "files" == coms[1] => {
+                    self.paged(|out| self.show_files(out))
+                }
+                // 変数一覧表示
+                "info" if 2 <= coms.len() && coms.len() <= 3 && "variables" == coms[1] => {
+                    match coms.get(2).map(|p| Regex::new(p)) {
+                        Some(Err(e)) => println!("invalid regex: {}", e),
+                        re => self.paged(|out| self.show_variables(out, re.and_then(|r| r.ok()))),
+                    }
+                }
+                // 関数一覧表示
+                "info" if 2 <= coms.len() && coms.len() <= 3 && "functions" == coms[1] => {
+                    match coms.get(2).map(|p| Regex::new(p)) {
+                        Some(Err(e)) => println!("invalid regex: {}", e),
+                        re => self.paged(|out| self.show_functions(out, re.and_then(|r| r.ok()))),
+                    }
+                }
+                // ローカル変数一覧表示
+                "info" if coms.len() == 2 && "locals" == coms[1] => self.show_locals(),
+                // 引数一覧表示
+                "info" if coms.len() == 2 && "args" == coms[1] => self.show_func_args(),
+                // CU一覧表示
+                "info" if 2 <= coms.len() && coms.len() <= 3 && "cu" == coms[1] => {
+                    self.show_cus(coms.get(2).map(|s| s.as_str()))
+                }
+                // ソースファイル一覧表示
+                "sources" if coms.len() <= 2 => match coms.get(1).map(|p| Regex::new(p)) {
+                    Some(Err(e)) => println!("invalid regex: {}", e),
+                    re => self.paged(|out| self.show_sources(out, re.and_then(|r| r.ok()))),
+                },
+                // 行情報表示
+                "info" if coms.len() == 2 && "line" == coms[1] => self.show_line(),
+                // ソースコード表示
+                "list" | "l" if coms.len() <= 2 => self.sh_list(coms.get(1).map(|s| s.as_str())),
+                // 行番号テーブル表示
+                "info" if 2 <= coms.len() && coms.len() <= 3 && "lines" == coms[1] => {
+                    self.paged(|out| self.show_lines(out, coms.get(2).map(|s| s.as_str())))
+                }
+                // セクションダンプ
+                "dump" if 3 <= coms.len() && coms.len() <= 4 && "section" == coms[1] => {
+                    self.dump_section(&coms[2], coms.get(3))
+                }
+                // メモリの内容をファイルへ書き込む
+                "dump" if coms.len() == 6 && "binary" == coms[1] && "memory" == coms[2] => {
+                    self.dump_binary_memory(&coms[3], &coms[4], &coms[5])
+                }
+                // ファイルの内容をメモリへ書き戻す
+                "restore" if coms.len() == 3 => self.restore(&coms[1], &coms[2]),
+                // 停止した位置からの命令を表示
+                "disas" if coms.len() == 1 => self.disassemble(),
+                // 関数全体の命令を表示
+                "disas" if coms.len() == 2 => self.disassemble_function(&coms[1]),
+                // メモリの内容を8バイト毎に表示
+                "x" if coms.len() == 3 => self.paged(|out| self.examine(out, &coms[1], &coms[2])),
+                // メモリダンプ
+                "dump" if 4 <= coms.len() && coms.len() <= 5 && "memory" == coms[1] => {
+                    self.dump_memory(&coms[2], &coms[3], coms.get(4))
+                }
+                // 別のファイルからシンボル読み込み(省略時は破棄)
+                "symbol-file" if coms.len() <= 2 => {
+                    self.sh_symbol_file(coms.get(1).map(|s| s.as_str()))
+                }
+                // デバッグ情報をすべて解析
+                "load" if coms.len() == 3 && "debug-info" == coms[1] && "now" == coms[2] => {
+                    self.load_debug_info()
+                }
+                // バックトレース表示
+                "bt" => self.show_backtrace(),
+                // フレーム選択
+                "frame" if coms.len() == 2 => self.sh_frame(&coms[1]),
                 // レジスタ書き込み
                 "set" if coms.len() == 4 && "regs" == coms[1] => self.set_regs(&coms[2], &coms[3]),
+                "set" if coms.len() == 4 && "fpregs" == coms[1] => {
+                    self.set_fpregs(&coms[2], &coms[3])
+                }
+                // 配列の表示要素数設定
+                "set" if coms.len() == 4 && "print" == coms[1] && "elements" == coms[2] => {
+                    self.set_print_elements(&coms[3])
+                }
+                // ページャーの行数設定
+                "set" if coms.len() == 3 && "height" == coms[1] => self.set_height(&coms[2]),
+                // btで辿るフレーム数の上限設定
+                "set" if coms.len() == 4 && "backtrace" == coms[1] && "limit" == coms[2] => {
+                    self.set_backtrace_limit(&coms[3])
+                }
+                // プロンプトの表示形式設定
+                "set" if coms.len() == 3 && "prompt-format" == coms[1] => {
+                    match PromptFormat::parse(&coms[2]) {
+                        Ok(f) => {
+                            self.prompt_format = f;
+                            self.prompt = None;
+                        }
+                        Err(e) => println!("{}", e),
+                    }
+                }
+                // execve後の動作設定
+                "set" if coms.len() == 3 && "follow-exec-mode" == coms[1] => {
+                    match FollowExecMode::parse(&coms[2]) {
+                        Ok(m) => self.follow_exec = m,
+                        Err(e) => println!("{}", e),
+                    }
+                }
                 // 終了
                 "quit" => self.sh_quit(),
+                "detach" if coms.len() == 1 => self.sh_detach(),
+                _ if self.user_commands.is_defined(&coms[0]) => self.call_user_command(&coms),
                 _ => println!("not support command: {}", coms[0]),
             };
         }
     }
 
-    /// シェルからのブレイクポイント設定
-    fn sh_breakpoint(&mut self, sym: &str) {
-        // シンボル探索
-        match self.elf.search_func_sym(sym) {
-            Some(s) => {
-                // シンボル→アドレス変換したものをブレイクポイント設定
-                let addr = s.st_value;
-                self.breakpoint(addr as usize, sym);
-                println!("BreakPoint at 0x{:x}", addr);
+    /// 停止した位置のプロンプトを整形
+    ///
+    /// 入力の度に表示するため、停止した位置(rip)が変わるまでは整形済みのものを使う
+    fn format_prompt(&mut self) -> String {
+        let rip = self.read_regs().rip;
+        if let Some((r, prompt)) = &self.prompt {
+            if rip == *r {
+                return prompt.clone();
+            }
+        }
+        let prompt = match self.prompt_format {
+            PromptFormat::Raw => format_prompt(PromptFormat::Raw, rip, None, None),
+            format => {
+                // 実行ファイルの関数になければ、共有ライブラリの直前の関数シンボルとする
+                let func = match self.search_fault_func(rip) {
+                    Some(f) => Some((f.name, rip - f.start)),
+                    None => {
+                        self.load_solib_at(rip);
+                        self.search_func_offset(rip)
+                    }
+                };
+                let line = self.search_line(rip as usize);
+                format_prompt(
+                    format,
+                    rip,
+                    func.as_ref().map(|(name, offset)| (name.as_str(), *offset)),
+                    line.as_ref().map(|l| (l.get_file(), l.get_line())),
+                )
             }
-            _ => println!("not found symbol: {}", sym),
         };
+        self.prompt = Some((rip, prompt.clone()));
+        prompt
     }
 
-    /// シェルからのブレイクポイントリリース
-    fn sh_release_break(&mut self, no: &str) {
-        let ret = self.release_break(no.parse::<usize>().unwrap());
-        if ret {
-            println!("release Breakpoint({})", no);
+    /// コマンドを1行読み込む
+    ///
+    /// 実行待ちのコマンドがあれば優先し、なければプロンプトを表示して標準入力から読み込む
+    /// 入力の終わりであればNone
+    fn read_command(&mut self, prompt: &str) -> Option<String> {
+        if let Some(line) = self.input.pop_front() {
+            return Some(line);
+        }
+        print!("{}", prompt);
+        io::stdout().flush().unwrap();
+        let mut s = String::new();
+        match std::io::stdin().read_line(&mut s) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(s),
         }
     }
 
-    /// シェルからのシンボルリード
-    fn sh_read_sym(&self, sym: &str) {
-        // シンボル探索
-        match self.elf.search_var_sym(sym) {
-            Some(s) => {
-                // シンボルの内容を表示
-                let addr = AdrFromRel::new(self.entry, s.st_value as usize);
-                println!("0x{:x}", self.read_mem(&addr));
-            }
-            _ => println!("not found symbol: {}", sym),
-        };
+    /// シェルからのユーザー定義コマンドの定義
+    ///
+    /// endまでの行を本体とする。定義済みであれば、置き換えるか確認する
+    fn sh_define(&mut self, name: &str) {
+        let redefine = !self.user_commands.is_defined(name)
+            || confirm(&format!("Redefine command \"{}\"?", name));
+        if self.input.is_empty() && io::stdin().is_terminal() {
+            println!("Type commands for definition of \"{}\".", name);
+            println!("End with a line saying just \"end\".");
+        }
+        let mut body = DefinitionBody::new();
+        loop {
+            match self.read_command(">") {
+                Some(line) if body.push(&line) => break,
+                Some(_) => {}
+                None => {
+                    println!("definition of {} aborted (end of input)", name);
+                    return;
+                }
+            }
+        }
+        if redefine {
+            self.user_commands.define(name, body.into_lines());
+        } else {
+            println!("command \"{}\" not redefined", name);
+        }
+    }
+
+    /// ユーザー定義コマンドの呼び出し
+    ///
+    /// 引数で置換した本体を、実行待ちのコマンドの先頭に積む(再開するコマンドがあれば、停止後に続きを実行する)
+    fn call_user_command(&mut self, coms: &[String]) {
+        let args = coms[1..].iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+        match self.user_commands.expand(&coms[0], &args) {
+            Ok(lines) => lines
+                .into_iter()
+                .rev()
+                .for_each(|l| self.input.push_front(l)),
+            Err(e) => println!("{}", e),
+        }
+    }
+
+    /// ユーザー定義コマンドの一覧表示
+    fn show_user_commands(&self) {
+        if self.user_commands.is_empty() {
+            println!("no user-defined commands");
+            return;
+        }
+        self.paged(|out| self.user_commands.write_definitions(out));
+    }
+
+    /// ユーザー定義コマンドをファイルへ保存(sourceで読み込める)
+    fn save_user_commands(&self, file: &str) {
+        let saved = std::fs::File::create(file).and_then(|mut f| {
+            self.user_commands.write_definitions(&mut f)?;
+            f.flush()
+        });
+        match saved {
+            Ok(_) => println!(
+                "saved {} user-defined commands to {}",
+                self.user_commands.len(),
+                file
+            ),
+            Err(e) => println!("cannot write {}: {}", file, e),
+        }
+    }
+
+    /// ファイルに書かれたコマンドを実行
+    ///
+    /// 空行・#で始まる行は読み飛ばす
+    fn sh_source(&mut self, file: &str) {
+        let text = match std::fs::read_to_string(file) {
+            Ok(t) => t,
+            Err(e) => {
+                println!("cannot read {}: {}", file, e);
+                return;
+            }
+        };
+        text.lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .rev()
+            .for_each(|l| self.input.push_front(l.to_string()));
+    }
+
+    /// シェルからのブレイクポイント設定
+    fn sh_breakpoint(&mut self, sym: &str) {
+        // *0x...・0x...形式であれば、アドレスへ設定
+        if let Some(addr) = sym.strip_prefix('*') {
+            self.sh_addr_breakpoint(addr, false);
+            return;
+        }
+        if sym.starts_with("0x") {
+            self.sh_addr_breakpoint(sym, false);
+            return;
+        }
+
+        // file:line形式であれば、行番号から設定
+        let file_line = sym
+            .rsplit_once(':')
+            .and_then(|(f, l)| Some((f, l.parse::<u64>().ok()?)));
+        if let Some((file, line)) = file_line {
+            self.sh_line_breakpoint(sym, file, line);
+            return;
+        }
+
+        // DWARFから関数を探索('file'::func形式であれば、ファイルを限定)
+        let (file, name) = split_scope(sym);
+        let funcs = self.elf.get_dwarf().search_funcs(name, file);
+        match funcs.len() {
+            0 => {}
+            1 => {
+                let addr = funcs[0].get_addr();
+                self.breakpoint(addr as usize, sym);
+                println!("BreakPoint at 0x{:x}", addr);
+                return;
+            }
+            _ => {
+                // 同名の関数が複数あれば選択させ、ファイル名付きで登録
+                let items = funcs
+                    .iter()
+                    .map(|f| {
+                        format!(
+                            "0x{:x} in {} at {}:{}",
+                            f.get_addr(),
+                            f.get_name(),
+                            f.get_file(),
+                            f.get_line()
+                        )
+                    })
+                    .collect::<Vec<String>>();
+                let title = format!("ambiguous function: {} (use 'file'::{})", sym, name);
+                for i in choose(&title, &items, false).unwrap_or_default() {
+                    let f = &funcs[i];
+                    let spec = format!("'{}'::{}", f.get_file(), f.get_name());
+                    self.breakpoint(f.get_addr() as usize, &spec);
+                    println!("BreakPoint at 0x{:x} ({})", f.get_addr(), spec);
+                }
+                return;
+            }
+        }
+
+        // シンボル探索(ファイル指定時は、DWARFの情報からのみ探す)
+        match self.elf.search_func_sym(sym) {
+            Some(s) if file.is_none() => {
+                // シンボル→アドレス変換したものをブレイクポイント設定
+                let addr = s.st_value;
+                self.breakpoint(addr as usize, sym);
+                println!("BreakPoint at 0x{:x}", addr);
+            }
+            _ if file.is_none() => self.solib_breakpoint(sym),
+            _ => println!("not found symbol: {}", sym),
+        };
+    }
+
+    /// 停止条件付きのブレイクポイント設定(b <sym> if <reg>==<val>)
+    ///
+    /// 条件を解析できなければ、ブレイクポイントを設定しない
+    /// 既にブレイクポイントがある位置であれば、そのブレイクポイントへ条件を設定する
+    fn sh_cond_breakpoint(&mut self, sym: &str, cond: &str) {
+        let condition = match Condition::parse(cond) {
+            Ok(c) => c,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        self.breakpoint.start_targets();
+        self.sh_breakpoint(sym);
+        let targets = self.breakpoint.take_targets();
+        if targets.is_empty() {
+            println!("no breakpoint at {}, condition is not set", sym);
+            return;
+        }
+        self.breakpoint.set_condition(&targets, &condition);
+    }
+
+    /// 一時ブレイクポイント設定(tb <sym>)
+    ///
+    /// bと同様に設定し、最初に停止した際に削除する
+    /// 既にブレイクポイントがある位置であれば、そのブレイクポイントを一時ブレイクポイントにする
+    fn sh_temp_breakpoint(&mut self, sym: &str) {
+        self.breakpoint.start_targets();
+        self.sh_breakpoint(sym);
+        let targets = self.breakpoint.take_targets();
+        if targets.is_empty() {
+            println!("no breakpoint at {}, temporary breakpoint is not set", sym);
+            return;
+        }
+        self.breakpoint.set_temporary(&targets);
+    }
+
+    /// シェルからの無視する回数の設定(ignore <no> <count>)
+    fn sh_ignore(&mut self, no: &str, count: &str) {
+        let count = match count.parse::<u64>() {
+            Ok(c) => c,
+            Err(_) => {
+                println!("parse error: {}", count);
+                return;
+            }
+        };
+        match no.parse::<usize>() {
+            Ok(i) if self.breakpoint.set_ignore(i, count) => match count {
+                0 => println!("will stop next time Breakpoint({}) is reached", i),
+                _ => println!("will ignore next {} hits of Breakpoint({})", count, i),
+            },
+            _ => println!("not entried breakpoint: {}", no),
+        }
+    }
+
+    /// シェルからのウォッチポイント設定(watch <symbol>・rwatch <symbol>)
+    ///
+    /// 変数の先頭から、アドレスが揃う範囲で最大8バイトへのアクセスを監視する
+    /// 型情報がなければ、シンボルテーブルのサイズ(st_size)を使う
+    fn sh_watch(&mut self, sym: &str, kind: WatchKind) {
+        let (addr, ty) = match self.search_var(sym) {
+            Some(v) => v,
+            None => {
+                println!("not found symbol: {}", sym);
+                return;
+            }
+        };
+        let size = ty.map(|t| t.get_size()).unwrap_or_else(|| {
+            let (_, name) = split_scope(sym);
+            self.elf.search_var_sym(name).map_or(8, |s| s.get_size())
+        });
+        let addr = AdrFromRel::new(self.entry, addr).get() as u64;
+        let len = watch_len(addr, size);
+        if self.watchpoints.iter().flatten().any(|w| w.addr == addr) {
+            println!("already watching: {}", sym);
+            return;
+        }
+        let slot = match self.watchpoints.iter().position(|w| w.is_none()) {
+            Some(i) => i,
+            None => {
+                println!(
+                    "no free debug register (max {} watchpoints)",
+                    MAX_WATCHPOINTS
+                );
+                return;
+            }
+        };
+        let value = match self.read_watch_value(addr, len) {
+            Some(v) => v,
+            None => {
+                println!("Cannot access memory at address 0x{:x}", addr);
+                return;
+            }
+        };
+        self.watchpoints[slot] = Some(Watchpoint {
+            sym: sym.to_string(),
+            addr,
+            len,
+            kind,
+            value,
+        });
+        match self.apply_watchpoints() {
+            Ok(_) => println!(
+                "Hardware {}: {} (0x{:x}, {} bytes)",
+                watch_title(kind, slot),
+                sym,
+                addr,
+                len
+            ),
+            Err(e) => {
+                println!("cannot set watchpoint: {}", e);
+                self.watchpoints[slot] = None;
+                let _ = self.apply_watchpoints();
+            }
+        }
+    }
+
+    /// シェルからのウォッチポイント削除(dwatch <no>)
+    fn sh_delete_watch(&mut self, no: &str) {
+        match no
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| self.watchpoints.get_mut(i)?.take())
+        {
+            Some(_) => match self.apply_watchpoints() {
+                Ok(_) => println!("release Watchpoint({})", no),
+                Err(e) => println!("cannot release watchpoint: {}", e),
+            },
+            None => println!("not entried watchpoint: {}", no),
+        }
+    }
+
+    /// ウォッチポイントをデバッグレジスタへ設定
+    fn apply_watchpoints(&self) -> nix::Result<()> {
+        let slots = self
+            .watchpoints
+            .iter()
+            .map(|w| w.as_ref().map(|w| (w.addr, w.len, w.kind)))
+            .collect::<Vec<Option<(u64, u64, WatchKind)>>>();
+        set_watchpoints(self.pid, &slots)
+    }
+
+    /// 監視している範囲の値を読み込む
+    fn read_watch_value(&self, addr: u64, len: u64) -> Option<u64> {
+        let data = self.target.read_memory(addr, len as usize)?;
+        let mut word = [0u8; 8];
+        word[..data.len()].copy_from_slice(&data);
+        Some(u64::from_le_bytes(word))
+    }
+
+    /// ヒットしたウォッチポイントを取得し、DR6を消す(ウォッチポイントがなければ読み込まない)
+    fn take_watch_hits(&mut self) -> Option<Vec<usize>> {
+        if self.watchpoints.iter().all(|w| w.is_none()) {
+            return None;
+        }
+        let dr6 = read_status(self.pid).ok()?;
+        let hits = hit_slots(dr6)
+            .into_iter()
+            .filter(|i| self.watchpoints[*i].is_some())
+            .collect::<Vec<usize>>();
+        if hits.is_empty() {
+            return None;
+        }
+        clear_status(self.pid).expect("watch: clear DR6 is failed");
+        Some(hits)
+    }
+
+    /// ヒットしたウォッチポイントの、前回と今回の値を表示
+    ///
+    /// 読み書きを監視している場合、値が変わっていれば書き込み、変わっていなければ読み込みとみなし、
+    /// アクセスした命令の直後の位置も表示する
+    fn show_watch_hits(&mut self, hits: &[usize]) {
+        let rip = self.read_regs().rip;
+        let location = self.format_func_offset(rip);
+        for i in hits {
+            let (addr, len) = match &self.watchpoints[*i] {
+                Some(w) => (w.addr, w.len),
+                None => continue,
+            };
+            let new = self.read_watch_value(addr, len).unwrap_or_default();
+            if let Some(w) = self.watchpoints[*i].as_mut() {
+                println!("Hardware {}: {}", watch_title(w.kind, *i), w.sym);
+                match (w.kind, w.value == new) {
+                    (WatchKind::Access, true) => println!("Value = 0x{:x} (read)", new),
+                    _ => {
+                        println!("Old value = 0x{:x}", w.value);
+                        println!("New value = 0x{:x}", new);
+                    }
+                }
+                if WatchKind::Access == w.kind {
+                    println!("accessed before 0x{:x} in {}", rip, location);
+                }
+                w.value = new;
+            }
+        }
+    }
+
+    /// ウォッチポイント表示(info watch)
+    fn show_watch(&self) {
+        if self.watchpoints.iter().all(|w| w.is_none()) {
+            println!("not entried watchpoint");
+            return;
+        }
+        for (i, w) in self.watchpoints.iter().enumerate() {
+            if let Some(w) = w {
+                println!(
+                    "{}: {} (0x{:016x}, {} bytes, {}) = 0x{:x}",
+                    i, w.sym, w.addr, w.len, w.kind, w.value
+                );
+            }
+        }
+    }
+
+    /// アドレスへのブレイクポイント設定(b *0x401136・b --rel 0x1136)
+    ///
+    /// relであればファイル上のアドレスとして、ロードしたアドレスを加算する
+    /// 実行ファイル内のアドレスは、ロードしたアドレスからの相対(--rel 0x...)として登録する
+    /// (再実行でロード先が変わっても、同じ命令へ解決する)
+    /// それ以外(共有ライブラリ等)は、実行時のアドレスを*0x...として登録する
+    fn sh_addr_breakpoint(&mut self, spec: &str, rel: bool) {
+        let addr = match parse_num(spec).map(|a| a as usize) {
+            Some(a) if rel => self.entry.checked_add(a),
+            a => a,
+        };
+        let addr = match addr {
+            Some(a) => a,
+            None => {
+                println!("invalid address: {}", spec);
+                return;
+            }
+        };
+        // ブレイクポイントはエントリーアドレスからの相対で管理する
+        let offset = match addr.checked_sub(self.entry) {
+            Some(o) => o,
+            None => {
+                println!(
+                    "cannot set breakpoint at 0x{:x} below load address 0x{:x}",
+                    addr, self.entry
+                );
+                return;
+            }
+        };
+        if self.breakpoint.has_addr(&AdrFromAbs::new(addr)) {
+            // 停止条件・一時ブレイクポイントの設定は、登録済みのブレイクポイントへ適用する
+            self.breakpoint.mark_target(&AdrFromAbs::new(addr));
+            println!("breakpoint already set at 0x{:x}", addr);
+            return;
+        }
+        if self.try_read_bytes(&AdrFromAbs::new(addr), 8).is_err() {
+            println!("Cannot access memory at address 0x{:x}", addr);
+            return;
+        }
+        let in_exe = self
+            .elf
+            .search_section((addr as u64).wrapping_sub(self.load_bias()))
+            .is_some();
+        let spec = match in_exe {
+            true => format!("--rel 0x{:x}", offset),
+            false => format!("*0x{:x}", addr),
+        };
+        self.breakpoint(offset, &spec);
+        println!("BreakPoint at 0x{:x}", addr);
+    }
+
+    /// シンボルを読み込んだ共有ライブラリの関数に、ブレイクポイントを設定
+    fn solib_breakpoint(&mut self, sym: &str) {
+        let found = self.solibs.iter().find_map(|l| {
+            let s = l.elf.search_func_sym(sym)?;
+            Some((l.base + s.st_value as usize, l.path.clone()))
+        });
+        // ブレイクポイントはエントリーアドレスからの相対で管理する
+        match found.and_then(|(a, p)| Some((a.checked_sub(self.entry)?, a, p))) {
+            Some((rel, addr, path)) => {
+                self.breakpoint(rel, sym);
+                println!("BreakPoint at 0x{:x} in {}", addr, path);
+            }
+            None => println!("not found symbol: {}", sym),
+        }
+    }
+
+    /// シェルからの行番号指定ブレイクポイント設定
+    ///
+    /// 1行に複数の文がある場合は、最も小さいアドレスの文へ設定する
+    fn sh_line_breakpoint(&mut self, spec: &str, file: &str, line: u64) {
+        match self.elf.get_dwarf().search_line_addr(file, line) {
+            Some(info) => {
+                let addr = info.get_address();
+                self.breakpoint(addr as usize, spec);
+                println!("BreakPoint at 0x{:x} ({})", addr, info);
+            }
+            None => self.show_no_code(file, line),
+        }
+    }
+
+    /// シェルからの行番号指定ブレイクポイント設定(b --choose)
+    ///
+    /// 1行に複数の文がある場合は、設定する文を選択させる
+    fn sh_choose_line_breakpoint(&mut self, spec: &str) {
+        let (file, line) = match spec
+            .rsplit_once(':')
+            .and_then(|(f, l)| Some((f, l.parse::<u64>().ok()?)))
+        {
+            Some(file_line) => file_line,
+            None => {
+                println!("invalid location: {} (expected [file]:[line])", spec);
+                return;
+            }
+        };
+        let infos = self.elf.get_dwarf().search_line_addrs(file, line);
+        let selected: Vec<&LineInfo> = match infos.len() {
+            0 => {
+                self.show_no_code(file, line);
+                return;
+            }
+            1 => infos.iter().collect(),
+            _ => {
+                let items = infos
+                    .iter()
+                    .map(|i| format!("0x{:x} {}", i.get_address(), i))
+                    .collect::<Vec<String>>();
+                let title = format!("multiple statements at {}", spec);
+                match choose(&title, &items, true) {
+                    Some(selected) => selected.iter().map(|i| &infos[*i]).collect(),
+                    None => return,
+                }
+            }
+        };
+
+        for info in selected {
+            let addr = info.get_address();
+            self.breakpoint(addr as usize, spec);
+            println!("BreakPoint at 0x{:x} ({})", addr, info);
+        }
+    }
+
+    /// 行にコードがない理由を表示
+    ///
+    /// ファイルが行番号テーブルになければその旨、あればコードを持つ次の行を表示する
+    fn show_no_code(&self, file: &str, line: u64) {
+        let table = self.elf.get_dwarf().get_line_table(file);
+        let next = table
+            .iter()
+            .filter(|l| line < l.get_line() && l.get_ranges().iter().any(|(_, stmt)| *stmt))
+            .map(|l| l.get_line())
+            .min();
+        match (table.is_empty(), next) {
+            (true, _) => println!("No source file named {}.", file),
+            (false, Some(n)) => println!(
+                "No code at line {} in {} (next line with code: {})",
+                line, file, n
+            ),
+            (false, None) => println!("No code at line {} in {}", line, file),
+        }
+    }
+
+    /// シェルからのブレイクポイントリリース
+    fn sh_release_break(&mut self, no: &str) {
+        let ret = self.release_break(no.parse::<usize>().unwrap());
+        if ret {
+            println!("release Breakpoint({})", no);
+        }
+    }
+
+    /// シェルからの全ブレイクポイント削除(d *・delete all)
+    fn sh_release_all_breaks(&mut self) {
+        let n = self.breakpoint.get().len();
+        if 0 == n {
+            println!("not entried breakpoint");
+            return;
+        }
+        self.release_breaks((0..n).collect());
+        println!("release all Breakpoints ({})", n);
+    }
+
+    /// シェルからのシンボル名を指定したブレイクポイント削除(clear <symbol>)
+    fn sh_clear_break(&mut self, sym: &str) {
+        let indexes = self
+            .breakpoint
+            .get()
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.sym == sym)
+            .map(|(i, _)| i)
+            .collect::<Vec<usize>>();
+        if indexes.is_empty() {
+            println!("not entried breakpoint: {}", sym);
+            return;
+        }
+        let nos = indexes
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<String>>()
+            .join(" ");
+        self.release_breaks(indexes);
+        println!("release Breakpoint({})", nos);
+    }
+
+    /// シェルからのシンボルリード
+    fn sh_read_sym(&self, sym: &str) {
+        // var.member形式であればメンバーを辿る('file'::var形式のファイル名は除く)
+        let start = sym.rfind('\'').map_or(0, |i| i + 1);
+        let (sym, path) = match sym[start..].find('.') {
+            Some(i) => (
+                &sym[..start + i],
+                sym[start + i + 1..].split('.').collect::<Vec<&str>>(),
+            ),
+            None => (sym, vec![]),
+        };
+
+        // ローカル変数を優先
+        if let Some((scope, ctx)) = self.search_scope() {
+            if let Some(var) = scope.search_var(sym) {
+                println!("{}", self.format_local(var, &ctx, &path));
+                return;
+            }
+        }
+
+        // シンボル探索
+        match self.search_var(sym) {
+            Some((addr, ty)) => {
+                // シンボルの内容を表示
+                let addr = AdrFromRel::new(self.entry, addr);
+
+                // DWARFの型情報があれば、型のサイズ分だけ読み込んで型に応じて表示
+                // (型情報がなければ、1ワードを16進数で表示)
+                match ty {
+                    Some(ty) if 0 != ty.get_size() => {
+                        // 巨大な配列を読み込まないよう、読み込みサイズを制限
+                        let size = std::cmp::min(ty.get_size() as usize, MAX_READ_SIZE);
+                        let buf = self.read_bytes(&addr, size);
+                        match ty.format_member(&path, &buf, &self.print_opt) {
+                            Ok(s) | Err(s) => println!("{}", s),
+                        }
+                    }
+                    _ if !path.is_empty() => println!("no type information: {}", sym),
+                    _ => println!("0x{:x}", self.read_mem(&addr)),
+                }
+            }
+            None => {
+                // 他のスコープのローカル変数であれば、生存していないことを表示
+                let funcs = self.elf.get_dwarf().search_local_var_funcs(sym);
+                match funcs.is_empty() {
+                    true => println!("not found symbol: {}", sym),
+                    false => println!(
+                        "variable {} is not live in the current scope (local to {})",
+                        sym,
+                        funcs.join(", ")
+                    ),
+                }
+            }
+        };
+    }
+
+    /// シェルからのシンボル書き込み
+    ///
+    /// 変数の型のサイズ分のみ書き換える(隣接するデータは変更しない)
+    /// ローカル変数を優先し、レジスタに配置されていればレジスタを書き換える
+    fn sh_write_sym(&self, sym: &str, val: &str) {
+        // ローカル変数を優先
+        if let Some((scope, ctx)) = self.search_scope() {
+            if let Some(var) = scope.search_var(sym) {
+                let data = match var.get_type().encode(val) {
+                    Ok(d) => d,
+                    Err(e) => {
+                        println!("{}", e);
+                        return;
+                    }
+                };
+                match evaluate(var.get_location(), &ctx) {
+                    Some(Location::Addr(a)) => self.write_var(a, &data),
+                    Some(Location::Reg(r)) => self.write_dwarf_reg(r as usize, &data),
+                    Some(Location::Value(_)) => println!("cannot assign to {}: not an lvalue", sym),
+                    None => println!("cannot assign to {}: optimized out", sym),
+                }
+                return;
+            }
+        }
+
+        // シンボル探索
+        match self.search_var(sym) {
+            Some((addr, ty)) => {
+                // 型情報がなければシンボルのサイズ、それもなければ1ワードとする
+                let data = match ty.filter(|t| 0 != t.get_size()) {
+                    Some(t) => t.encode(val),
+                    None => {
+                        let size = self
+                            .elf
+                            .search_var_sym(split_scope(sym).1)
+                            .map_or(0, |s| s.get_size());
+                        encode_int(val, if 0 == size { 8 } else { size })
+                    }
+                };
+                match data {
+                    Ok(d) => self.write_var(AdrFromRel::new(self.entry, addr).get() as u64, &d),
+                    Err(e) => println!("{}", e),
+                }
+            }
+            _ => println!("not found symbol: {}", sym),
+        };
+    }
+
+    /// 変数へ書き込み(データのサイズ分のみ書き換える)
+    fn write_var(&self, addr: u64, data: &[u8]) {
+        if memory::write_bytes(self.pid, addr, data).is_err() {
+            println!("Cannot access memory at address 0x{:x}", addr);
+        }
+    }
+
+    /// 変数のアドレスと型情報を検索
+    ///
+    /// DWARFで定義された変数(static変数含む)を優先し、なければシンボルテーブルから探す
+    /// 'file'::varの形式で、定義しているファイルを指定できる
+    fn search_var(&self, sym: &str) -> Option<(usize, Option<TypeInfo>)> {
+        let (file, name) = split_scope(sym);
+        if let Some(v) = self.elf.get_dwarf().search_global_var(name, file) {
+            return Some((v.get_addr() as usize, Some(v.get_type().clone())));
+        }
+
+        // ファイル指定時は、DWARFの情報からのみ探す
+        match file {
+            Some(_) => None,
+            None => self
+                .elf
+                .search_var_sym(name)
+                .map(|s| (s.st_value as usize, None)),
+        }
+    }
+
+    /// 現在の行情報表示
+    fn show_line(&self) {
+        let rip = self.read_regs().rip as usize;
+        match self.search_line(rip) {
+            Some(line) => println!(
+                "Line {} starts at address 0x{:x}",
+                line,
+                self.entry + line.get_address() as usize
+            ),
+            None => println!(
+                "No line number information available for address 0x{:x}",
+                rip
+            ),
+        }
+    }
+
+    /// ソースコード表示
+    ///
+    /// 関数が指定されなければ停止している行、指定されれば関数を宣言している行の前後を表示する
+    fn sh_list(&self, func: Option<&str>) {
+        let (file, line) = match func {
+            None => {
+                let rip = self.read_regs().rip as usize;
+                match self.search_line(rip) {
+                    Some(l) => (l.get_file().to_string(), l.get_line()),
+                    None => {
+                        println!(
+                            "No line number information available for address 0x{:x}",
+                            rip
+                        );
+                        return;
+                    }
+                }
+            }
+            Some(sym) => {
+                let (file, name) = split_scope(sym);
+                match self.elf.get_dwarf().search_funcs(name, file).first() {
+                    Some(f) => (f.get_file().to_string(), f.get_line()),
+                    None => {
+                        println!("Function \"{}\" not defined.", sym);
+                        return;
+                    }
+                }
+            }
+        };
+
+        // コンパイル時のディレクトリ・ディレクトリテーブルから解決したパスで開く
+        let path = self.elf.get_dwarf().find_source_file(&file).unwrap_or(file);
+        match read_source(&path) {
+            Ok(lines) => format_lines(&lines, line, LIST_CONTEXT)
+                .iter()
+                .for_each(|l| println!("{}", l)),
+            Err(e) => println!("{}:{} (cannot open source file: {})", path, line, e),
+        }
+    }
+
+    /// 行番号テーブル表示
+    ///
+    /// ファイルが指定されなければ、ファイル毎にコードを持つ行数を表示する
+    fn show_lines<W: Write>(&self, out: &mut W, file: Option<&str>) -> Result<()> {
+        let dwarf = self.elf.get_dwarf();
+        let file = match file {
+            Some(f) => f,
+            None => {
+                writeln!(out, "Lines with code:")?;
+                for (f, n) in dwarf.get_line_summary() {
+                    writeln!(out, "    {}: {}", f, n)?;
+                }
+                return Ok(());
+            }
+        };
+
+        let lines = dwarf.get_line_table(file);
+        if lines.is_empty() {
+            return writeln!(out, "No line table for file {}.", file);
+        }
+        writeln!(out, "Line table for {} (* marks is_stmt):", file)?;
+        let mut cur = "";
+        for l in &lines {
+            if cur != l.get_file() {
+                cur = l.get_file();
+                writeln!(out, "\nFile {}:", cur)?;
+            }
+            let ranges = l
+                .get_ranges()
+                .iter()
+                .map(|(r, is_stmt)| {
+                    let mark = if *is_stmt { "*" } else { "" };
+                    match r.start < r.end {
+                        true => format!(
+                            "{}0x{:x}-0x{:x}",
+                            mark,
+                            self.entry + r.start as usize,
+                            self.entry + r.end as usize
+                        ),
+                        false => format!("{}0x{:x}", mark, self.entry + r.start as usize),
+                    }
+                })
+                .collect::<Vec<String>>();
+            writeln!(out, "{:>6}: {}", l.get_line(), ranges.join(", "))?;
+        }
+        Ok(())
+    }
+
+    /// セクションダンプ
+    ///
+    /// ファイルが指定されれば、セクションの内容をファイルへ書き込む
+    fn dump_section(&self, name: &str, file: Option<&String>) {
+        match self.elf.read_section(name) {
+            Ok((addr, data)) => self.dump(addr, &data, file),
+            Err(e) => println!("{}", e),
+        }
+    }
+
+    /// メモリダンプ
+    ///
+    /// ファイルが指定されれば、メモリの内容をファイルへ書き込む
+    fn dump_memory(&self, start: &str, len: &str, file: Option<&String>) {
+        let (start, len) = match (parse_num(start), parse_num(len)) {
+            (Some(s), Some(l)) => (s as usize, l as usize),
+            _ => {
+                println!("parse error: {} {}", start, len);
+                return;
+            }
+        };
+        match self.try_read_bytes(&AdrFromAbs::new(start), len) {
+            Ok(data) => self.dump(start as u64, &data, file),
+            Err(_) => println!("Cannot access memory at address 0x{:x}", start),
+        }
+    }
+
+    /// 停止した位置から続く命令を、1命令1行で表示
+    ///
+    /// ブレイクポイントのint 3は、元の命令として表示する
+    fn disassemble(&self) {
+        let rip = self.read_regs().rip;
+        match format_instructions(&self.target, rip, DISAS_LEN, &self.get_original_bytes()) {
+            Some(lines) => lines.iter().for_each(|l| println!("{}", l)),
+            None => println!("Cannot access memory at address 0x{:x}", rip),
+        }
+    }
+
+    /// シンボルテーブルから関数を探し、関数全体の命令を表示
+    ///
+    /// 位置独立実行形式であれば、シンボルの値はエントリーアドレスからの相対
+    fn disassemble_function(&self, sym: &str) {
+        let (start, size) = match self.elf.search_func_sym(sym) {
+            Some(s) if 0 < s.get_size() => match self.elf.is_pie() {
+                true => (self.entry as u64 + s.st_value, s.get_size() as usize),
+                false => (s.st_value, s.get_size() as usize),
+            },
+            Some(_) => return println!("no size for function: {}", sym),
+            None => return println!("not found symbol: {}", sym),
+        };
+        let rip = self.read_regs().rip;
+        let originals = self.get_original_bytes();
+        match format_function(&self.target, sym, start, size, rip, &originals) {
+            Some(lines) => {
+                println!("Dump of assembler code for function {}:", sym);
+                self.paged(|out| lines.iter().try_for_each(|l| writeln!(out, "{}", l)));
+            }
+            None => println!("Cannot access memory at address 0x{:x}", start),
+        }
+    }
+
+    /// メモリの内容を8バイト毎に、アドレスを付けて表示
+    ///
+    /// アドレスは数値(0x始まりは16進数)かシンボル名で指定する
+    /// 読み込めないアドレスに達すれば、そこで表示を止める
+    fn examine<W: Write>(&self, out: &mut W, count: &str, addr: &str) -> Result<()> {
+        let count = match parse_num(count) {
+            Some(c) if 0 < c => c as usize,
+            _ => return writeln!(out, "invalid count: {}", count),
+        };
+        let start = match parse_num(addr) {
+            Some(a) => a as usize,
+            None => match self.search_var(addr) {
+                Some((a, _)) => AdrFromRel::new(self.entry, a).get(),
+                None => return writeln!(out, "not found symbol: {}", addr),
+            },
+        };
+        for i in 0..count {
+            let addr = start.wrapping_add(i.wrapping_mul(8));
+            match self.try_read_bytes(&AdrFromAbs::new(addr), 8) {
+                Ok(data) => writeln!(
+                    out,
+                    "0x{:016x}: 0x{:016x}",
+                    addr,
+                    u64::from_le_bytes(data.try_into().unwrap())
+                )?,
+                Err(_) => return writeln!(out, "Cannot access memory at address 0x{:x}", addr),
+            }
+        }
+        Ok(())
+    }
+
+    /// メモリの内容をファイルへ書き込む(start〜endの手前まで)
+    ///
+    /// ブレイクポイントのint 3は元の命令に戻して書き込み、ファイルを読み込み直して確認する
+    fn dump_binary_memory(&mut self, file: &str, start: &str, end: &str) {
+        let (start, end) = match (parse_num(start), parse_num(end)) {
+            (Some(s), Some(e)) if s < e => (s, e),
+            _ => {
+                println!("invalid range: {} {}", start, end);
+                return;
+            }
+        };
+        if !self.target.is_core() {
+            if let Err(addr) = self.memory_map.check_access(start, end, 'r') {
+                println!("Cannot access memory at address 0x{:x}", addr);
+                return;
+            }
+        }
+        let mut data =
+            match self.try_read_bytes(&AdrFromAbs::new(start as usize), (end - start) as usize) {
+                Ok(d) => d,
+                Err(_) => {
+                    println!("Cannot access memory at address 0x{:x}", start);
+                    return;
+                }
+            };
+        self.shadow_breakpoints(start, &mut data);
+        if let Err(e) = std::fs::write(file, &data) {
+            println!("cannot write {}: {}", file, e);
+            return;
+        }
+        match std::fs::read(file) {
+            Ok(d) if d == data => println!(
+                "wrote {} bytes from 0x{:x}-0x{:x} to {}",
+                data.len(),
+                start,
+                end,
+                file
+            ),
+            _ => println!("verify failed: {}", file),
+        }
+    }
+
+    /// ファイルの内容をメモリへ書き戻す
+    ///
+    /// 範囲内のブレイクポイントは、書き戻した命令を元の命令として、int 3を貼り直す
+    /// メモリを読み込み直して、ファイルの内容と一致することを確認する
+    fn restore(&mut self, file: &str, addr: &str) {
+        let addr = match parse_num(addr) {
+            Some(a) => a,
+            None => {
+                println!("parse error: {}", addr);
+                return;
+            }
+        };
+        let data = match std::fs::read(file) {
+            Ok(d) => d,
+            Err(e) => {
+                println!("cannot read {}: {}", file, e);
+                return;
+            }
+        };
+        let end = addr + data.len() as u64;
+        if let Err(a) = self.memory_map.check_access(addr, end, 'r') {
+            println!("Cannot access memory at address 0x{:x}", a);
+            return;
+        }
+
+        // 書き込むとint 3が消える、または元の命令列が古くなるブレイクポイント
+        let word = std::mem::size_of::<usize>() as u64;
+        let bps = self
+            .breakpoint
+            .get()
+            .iter()
+            .map(|b| (b.addr.get() as u64, b.inst))
+            .filter(|(a, _)| *a < end && addr < a + word)
+            .collect::<Vec<(u64, usize)>>();
+        if memory::write_bytes(self.pid, addr, &data).is_err() {
+            println!("Cannot access memory at address 0x{:x}", addr);
+            return;
+        }
+        for (a, inst) in &bps {
+            let bp = AdrFromAbs::new(*a as usize);
+            let current = self.read_mem(&bp);
+            // ブレイクポイントの位置を書き込んでいなければ、元の命令のまま
+            let orig = match (addr..end).contains(a) {
+                true => current & 0xFF,
+                false => *inst as u64 & 0xFF,
+            };
+            let upper = current & 0xFFFF_FFFF_FFFF_FF00;
+            self.breakpoint.set_inst(&bp, (upper | orig) as usize);
+            self.write_mem(&bp, (upper | 0xCC) as usize);
+        }
+
+        let verified = memory::read_bytes(self.pid, addr, data.len()).map(|mut d| {
+            self.shadow_breakpoints(addr, &mut d);
+            d == data
+        });
+        match verified {
+            Ok(true) => println!(
+                "restored {} bytes from {} to 0x{:x}-0x{:x} (re-applied {} breakpoints)",
+                data.len(),
+                file,
+                addr,
+                end,
+                bps.len()
+            ),
+            _ => println!("verify failed at 0x{:x}", addr),
+        }
+    }
+
+    /// 読み込んだメモリ内容のint 3を、ブレイクポイント箇所の元の命令に置き換える
+    fn shadow_breakpoints(&self, addr: u64, data: &mut [u8]) {
+        memory::shadow(addr, data, &self.get_original_bytes());
+    }
+
+    /// int 3を埋め込んだアドレスと、元の命令の1バイト目の一覧を取得(内部ブレイクポイントを含む)
+    fn get_original_bytes(&self) -> Vec<(u64, u8)> {
+        self.breakpoint
+            .get()
+            .iter()
+            .filter(|b| !b.pending)
+            .map(|b| (b.addr.get() as u64, b.inst as u8))
+            .chain(self.solib_bp.iter().map(|b| (b.addr as u64, b.inst as u8)))
+            .chain(self.temp_bps.iter().map(|b| (b.addr as u64, b.inst as u8)))
+            .collect()
+    }
+
+    /// ダンプ出力
+    ///
+    /// ファイルが指定されなければ、16進ダンプを表示する
+    fn dump(&self, addr: u64, data: &[u8], file: Option<&String>) {
+        match file {
+            Some(f) => match std::fs::write(f, data) {
+                Ok(_) => println!("wrote {} bytes to {}", data.len(), f),
+                Err(e) => println!("cannot write {}: {}", f, e),
+            },
+            None => self.paged(|out| hexdump(out, addr, data)),
+        }
+    }
+
+    /// デバッグ情報をすべて解析
+    ///
+    /// 通常は、参照されたCUのみを解析する
+    fn load_debug_info(&self) {
+        let start = std::time::Instant::now();
+        let count = self.elf.get_dwarf().load_all();
+        println!(
+            "loaded {} compile units in {:.3}s",
+            count,
+            start.elapsed().as_secs_f64()
+        );
+    }
+
+    /// アドレスに対応するソース位置を検索
+    fn search_line(&self, addr: usize) -> Option<LineInfo> {
+        let pc = addr.checked_sub(self.entry)?;
+        self.elf.get_dwarf().search_line(pc as u64)
+    }
+
+    /// アドレスを含むフレームを検索(インライン展開されたフレームを含む)
+    fn search_frames(&self, addr: usize) -> Vec<ScopeInfo> {
+        match addr.checked_sub(self.entry) {
+            Some(pc) => self.elf.get_dwarf().search_frames(pc as u64),
+            None => vec![],
+        }
+    }
+
+    /// バックトレース表示
+    ///
+    /// 停止した位置は、インライン展開された関数も個別のフレームとして内側から表示する
+    /// 呼び出し元は、フレームポインタを辿って関数+オフセットで表示する
+    fn show_backtrace(&self) {
+        let regs = self.read_regs();
+        let rip = regs.rip as usize;
+        let frames = self.search_frames(rip);
+        if frames.is_empty() {
+            println!(
+                "#0  0x{:016x} in {}",
+                rip,
+                self.format_func_offset(regs.rip)
+            );
+        }
+        (0..frames.len()).for_each(|i| println!("{}", self.format_frame(&frames, i, rip)));
+
+        let pcs = walk_frames(
+            &self.target,
+            &regs,
+            self.search_prologue_ret_offset(regs.rip),
+            self.backtrace_limit,
+        );
+        let first = frames.len().max(1);
+        for (i, pc) in pcs.iter().enumerate().skip(1) {
+            // リターンアドレスは呼び出し命令の次なので、1つ前のアドレスのソース位置を表示する
+            let location = self
+                .search_line(*pc as usize - 1)
+                .map_or(String::new(), |l| format!(" at {}", l));
+            println!(
+                "#{:<2} 0x{:016x} in {}{}",
+                first + i - 1,
+                pc,
+                self.format_func_offset(*pc),
+                location
+            );
+        }
+        if self.backtrace_limit == pcs.len() {
+            println!("(More stack frames follow...)");
+        }
+    }
+
+    /// アドレスを含む関数と、関数の先頭からのオフセットを検索
+    ///
+    /// 実行ファイル、なければベースアドレスが最も近い、シンボルを読み込んだ共有ライブラリから探す
+    fn search_func_offset(&self, addr: u64) -> Option<(String, u64)> {
+        let pc = match self.elf.is_pie() {
+            true => addr.checked_sub(self.entry as u64),
+            false => Some(addr),
+        };
+        if let Some((pc, sym)) = pc.and_then(|pc| Some((pc, self.elf.search_nearest_func_sym(pc)?)))
+        {
+            return Some((sym.get_name(), pc - sym.st_value));
+        }
+        let lib = self
+            .solibs
+            .iter()
+            .filter(|l| l.base as u64 <= addr)
+            .max_by_key(|l| l.base)?;
+        let pc = addr - lib.base as u64;
+        let sym = lib.elf.search_nearest_func_sym(pc)?;
+        Some((sym.get_name(), pc - sym.st_value))
+    }
+
+    /// ブレイクポイントで停止した位置を整形(Breakpoint 1, main () at main.c:12)
+    ///
+    /// 関数はDWARF(インライン展開を含む)、なければシンボルテーブルから求める
+    /// 行情報がなければアドレスと関数、関数も分からなければアドレスのみとする
+    fn format_break_location(&self, no: Option<usize>, addr: u64) -> String {
+        let func = self
+            .search_frames(addr as usize)
+            .first()
+            .map(|f| f.get_func().to_string())
+            .or_else(|| {
+                self.search_func_offset(addr)
+                    .map(|(name, _)| name.to_string())
+            });
+        let title = no.map_or("Breakpoint".to_string(), |n| format!("Breakpoint {}", n));
+        match (func, self.search_line(addr as usize)) {
+            (Some(func), Some(line)) => format!(
+                "{}, {} () at {}:{}",
+                title,
+                func,
+                line.get_file(),
+                line.get_line()
+            ),
+            (Some(func), None) => format!("{}, 0x{:x} in {} ()", title, addr, func),
+            (None, _) => format!("break at 0x{:x}", addr),
+        }
+    }
+
+    /// アドレスを関数+オフセットへ整形(見つからなければ??)
+    fn format_func_offset(&self, addr: u64) -> String {
+        match self.search_func_offset(addr) {
+            Some((name, offset)) => format!("{}+0x{:x}", name, offset),
+            None => "??".to_string(),
+        }
+    }
+
+    /// 関数の先頭でフレームポインタを設定する前、またはretでフレームポインタを戻した後に停止していれば、
+    /// リターンアドレスのrspからのオフセットを求める
+    fn search_prologue_ret_offset(&self, rip: u64) -> Option<u64> {
+        // endbr64; push rbpまでの範囲
+        const PROLOGUE_LEN: u64 = 5;
+        if let Ok(mut code) = self.try_read_bytes(&AdrFromAbs::new(rip as usize), 2) {
+            self.shadow_breakpoints(rip, &mut code);
+            if is_ret(&code) {
+                return Some(0);
+            }
+        }
+        let (_, offset) = self.search_func_offset(rip)?;
+        if PROLOGUE_LEN < offset {
+            return None;
+        }
+        let start = rip - offset;
+        let mut executed = match offset {
+            0 => vec![],
+            _ => self
+                .try_read_bytes(&AdrFromAbs::new(start as usize), offset as usize)
+                .ok()?,
+        };
+        self.shadow_breakpoints(start, &mut executed);
+        prologue_ret_offset(&executed)
+    }
+
+    /// フレーム選択
+    fn sh_frame(&mut self, no: &str) {
+        let rip = self.read_regs().rip as usize;
+        let frames = self.search_frames(rip);
+        match no.parse::<usize>() {
+            Ok(n) if n < frames.len() => {
+                self.frame = n;
+                println!("{}", self.format_frame(&frames, n, rip));
+            }
+            _ => println!("No frame at level {}.", no),
+        }
+    }
+
+    /// フレーム情報を文字列化
+    ///
+    /// 最も内側のフレームは現在の位置、それ以外は一つ内側のフレームの呼び出し元を表示する
+    fn format_frame(&self, frames: &[ScopeInfo], i: usize, rip: usize) -> String {
+        let frame = &frames[i];
+        let location = match i {
+            0 => self.search_line(rip).map(|l| l.to_string()),
+            _ => frames[i - 1].get_call_site().map(|c| c.to_string()),
+        };
+        let inlined = if frame.is_inlined() { " (inlined)" } else { "" };
+        match location {
+            Some(l) => format!(
+                "#{:<2} 0x{:016x} in {}{} at {}",
+                i,
+                rip,
+                frame.get_func(),
+                inlined,
+                l
+            ),
+            None => format!(
+                "#{:<2} 0x{:016x} in {}{}",
+                i,
+                rip,
+                frame.get_func(),
+                inlined
+            ),
+        }
+    }
+
+    /// 選択中のフレームのスコープを検索
+    ///
+    /// 位置式の評価に必要なレジスタ情報も合わせて返却する
+    /// CFIは未対応のため、CFAはフレームポインタ(rbp)から求める
+    fn search_scope(&self) -> Option<(ScopeInfo, EvalContext)> {
+        let regs = self.read_regs();
+        let scope = self
+            .search_frames(regs.rip as usize)
+            .into_iter()
+            .nth(self.frame)?;
+
+        let mut ctx = EvalContext {
+            regs: Self::to_dwarf_regs(&regs),
+            frame_base: None,
+            cfa: Some(regs.rbp + 16),
+        };
+        ctx.frame_base = match evaluate(scope.get_frame_base(), &ctx) {
+            Some(Location::Addr(a)) => Some(a),
+            Some(Location::Reg(r)) => ctx.regs.get(r as usize).copied(),
+            _ => None,
+        };
+        Some((scope, ctx))
+    }
+
+    /// ローカル変数の内容を文字列化(pathが指定されれば、そのメンバーのみ)
+    fn format_local(&self, var: &LocalVarInfo, ctx: &EvalContext, path: &[&str]) -> String {
+        let ty = var.get_type();
+        let size = std::cmp::min(ty.get_size() as usize, MAX_READ_SIZE);
+        let buf = match evaluate(var.get_location(), ctx) {
+            // フレームベースが正しくない(関数の先頭等)場合は、読み込めないことがある
+            Some(Location::Addr(a)) => {
+                match self.try_read_bytes(&AdrFromAbs::new(a as usize), size) {
+                    Ok(b) => b,
+                    Err(_) => return format!("<cannot access memory at 0x{:x}>", a),
+                }
+            }
+            Some(Location::Reg(r)) => match self.read_dwarf_reg(r as usize, ctx) {
+                Some(v) => v,
+                None => return "<unavailable>".to_string(),
+            },
+            Some(Location::Value(v)) => v.to_le_bytes().to_vec(),
+            None => return "<optimized out>".to_string(),
+        };
+        match ty.format_member(path, &buf, &self.print_opt) {
+            Ok(s) | Err(s) => s,
+        }
+    }
+
+    /// ローカル変数一覧表示
+    ///
+    /// 内側のスコープの変数から順に表示する
+    fn show_locals(&self) {
+        let (scope, ctx) = match self.search_scope() {
+            Some(s) => s,
+            None => {
+                println!("No symbol table info available.");
+                return;
+            }
+        };
+        let vars = scope
+            .get_vars()
+            .iter()
+            .filter(|v| !v.is_param())
+            .collect::<Vec<&LocalVarInfo>>();
+        if vars.is_empty() {
+            println!("No locals.");
+        }
+        vars.iter()
+            .for_each(|v| println!("{} = {}", v.get_name(), self.format_local(v, &ctx, &[])));
+    }
+
+    /// DWARFのレジスタ番号のレジスタの値を取得
+    ///
+    /// 汎用レジスタは停止時のレジスタ、xmm0〜xmm15は浮動小数点レジスタから読み込む
+    fn read_dwarf_reg(&self, reg: usize, ctx: &EvalContext) -> Option<Vec<u8>> {
+        match reg {
+            r if r < ctx.regs.len() => Some(ctx.regs[r].to_le_bytes().to_vec()),
+            r if (DWARF_XMM0..DWARF_XMM0 + XMM_REGS).contains(&r) => {
+                let fpregs = self.target.read_fpregs().ok()?;
+                Some(xmm_value(&fpregs, r - DWARF_XMM0).to_le_bytes().to_vec())
+            }
+            _ => None,
+        }
+    }
+
+    /// DWARFのレジスタ番号のレジスタへ書き込み
+    ///
+    /// データのサイズ分のみ(下位バイトから)書き換える
+    fn write_dwarf_reg(&self, reg: usize, data: &[u8]) {
+        if (DWARF_XMM0..DWARF_XMM0 + XMM_REGS).contains(&reg) {
+            let mut fpregs = match self.target.read_fpregs() {
+                Ok(f) => f,
+                Err(e) => {
+                    println!("cannot read fpregs: {}", e);
+                    return;
+                }
+            };
+            let mut bytes = xmm_value(&fpregs, reg - DWARF_XMM0).to_le_bytes();
+            let n = data.len().min(bytes.len());
+            bytes[..n].copy_from_slice(&data[..n]);
+            set_xmm_value(&mut fpregs, reg - DWARF_XMM0, u128::from_le_bytes(bytes));
+            if let Err(e) = write_fpregs(self.pid, &fpregs) {
+                println!("cannot write fpregs: {}", e);
+            }
+            return;
+        }
+
+        let mut regs = self.read_regs();
+        match Self::dwarf_reg_mut(&mut regs, reg) {
+            Some(r) => {
+                let mut bytes = r.to_le_bytes();
+                let n = data.len().min(bytes.len());
+                bytes[..n].copy_from_slice(&data[..n]);
+                *r = u64::from_le_bytes(bytes);
+                self.write_regs(regs);
+            }
+            None => println!("cannot write register: DWARF register {}", reg),
+        }
+    }
+
+    /// 引数一覧表示
+    ///
+    /// 現在の関数の仮引数を、型と共に宣言順に表示する
+    fn show_func_args(&self) {
+        let (scope, ctx) = match self.search_scope() {
+            Some(s) => s,
+            None => {
+                println!("No symbol table info available.");
+                return;
+            }
+        };
+        let args = scope
+            .get_vars()
+            .iter()
+            .filter(|v| v.is_param())
+            .collect::<Vec<&LocalVarInfo>>();
+        if args.is_empty() {
+            println!("No arguments.");
+        }
+        for v in args {
+            let ty = match v.get_type().get_name() {
+                "" => "?",
+                n => n,
+            };
+            println!(
+                "{} {} = {}",
+                ty,
+                v.get_name(),
+                self.format_local(v, &ctx, &[])
+            );
+        }
+    }
+
+    /// 関数一覧表示
+    ///
+    /// patternが指定されれば、名前が正規表現にマッチする関数のみ表示する
+    fn show_functions<W: Write>(&self, out: &mut W, pattern: Option<Regex>) -> Result<()> {
+        let matched = |name: &str| pattern.as_ref().is_none_or(|p| p.is_match(name));
+        let funcs = self.elf.get_dwarf().get_funcs();
+        match &pattern {
+            Some(p) => writeln!(
+                out,
+                "All functions matching regular expression \"{}\":",
+                p.as_str()
+            )?,
+            None => writeln!(out, "All defined functions:")?,
+        }
+
+        // ファイル毎に、実行時のアドレス順で表示
+        let base = self.load_bias();
+        let mut files = funcs
+            .iter()
+            .filter(|f| matched(f.get_name()))
+            .map(|f| f.get_file())
+            .collect::<Vec<&str>>();
+        files.sort_unstable();
+        files.dedup();
+        for file in files {
+            writeln!(out, "\nFile {}:", file)?;
+            let mut defs = funcs
+                .iter()
+                .filter(|f| f.get_file() == file && matched(f.get_name()))
+                .collect::<Vec<_>>();
+            defs.sort_by_key(|f| f.get_addr());
+            for f in defs {
+                writeln!(
+                    out,
+                    "0x{:016x}  {:>6}  {}:\t{};",
+                    base + f.get_addr(),
+                    f.get_size(),
+                    f.get_line(),
+                    f.get_name()
+                )?;
+            }
+        }
+
+        // DWARFに情報がない関数は、シンボルテーブルの情報(実行時のアドレス・サイズ)のみ表示
+        writeln!(out, "\nNon-debugging symbols:")?;
+        let addrs = funcs.iter().map(|f| f.get_addr()).collect::<HashSet<u64>>();
+        let mut syms = self
+            .elf
+            .get_func_syms()
+            .filter(|s| s.st_value != 0 && matched(&s.get_name()))
+            .filter(|s| !addrs.contains(&s.st_value))
+            .collect::<Vec<_>>();
+        syms.sort_by_key(|s| s.st_value);
+        for s in syms {
+            writeln!(
+                out,
+                "0x{:016x}  {:>6}  {}",
+                base + s.st_value,
+                s.get_size(),
+                s.get_name()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// ソースファイル一覧表示
+    ///
+    /// patternが指定されれば、パスにマッチするファイルのみ表示する
+    /// ファイルが存在しなければ、その旨を付けて表示する
+    fn show_sources<W: Write>(&self, out: &mut W, pattern: Option<Regex>) -> Result<()> {
+        let files = self
+            .elf
+            .get_dwarf()
+            .source_files()
+            .into_iter()
+            .filter(|f| pattern.as_ref().is_none_or(|p| p.is_match(f)))
+            .collect::<Vec<String>>();
+        writeln!(out, "Source files for which symbols have been read in:")?;
+        for f in &files {
+            match std::path::Path::new(f).exists() {
+                true => writeln!(out, "  {}", f)?,
+                false => writeln!(out, "  {} (not found)", f)?,
+            }
+        }
+        match files.len() {
+            1 => writeln!(out, "1 source file."),
+            n => writeln!(out, "{} source files.", n),
+        }
+    }
+
+    /// CU一覧表示
+    ///
+    /// CUの番号が指定された場合は、そのCUの統計情報も表示する
+    fn show_cus(&self, index: Option<&str>) {
+        let dwarf = self.elf.get_dwarf();
+        match index.map(|i| i.parse::<usize>()) {
+            None => dwarf.get_cu_infos().iter().for_each(|cu| self.show_cu(cu)),
+            Some(Ok(i)) => match dwarf.get_cu_info(i) {
+                Some(cu) => {
+                    self.show_cu(&cu);
+                    println!("    dies     : {}", cu.get_die_count());
+                    println!("    line rows: {}", cu.get_line_rows());
+                }
+                None => println!("No compile unit {}.", i),
+            },
+            Some(Err(_)) => println!("invalid compile unit number: {}", index.unwrap_or("")),
+        }
+    }
+
+    /// CU情報表示
+    fn show_cu(&self, cu: &CuInfo) {
+        println!("[{}] {}", cu.get_index(), cu.get_name());
+        println!("    comp dir : {}", cu.get_comp_dir());
+        println!("    producer : {}", cu.get_producer());
+        println!("    language : {}", cu.get_language());
+        println!("    version  : {}", cu.get_version());
+        let bias = self.load_bias();
+        let ranges = cu
+            .get_ranges()
+            .iter()
+            .map(|r| format!("0x{:x}-0x{:x}", bias + r.start, bias + r.end))
+            .collect::<Vec<String>>();
+        println!("    ranges   : {}", ranges.join(", "));
+    }
+
+    /// マップされている共有ライブラリの範囲(先頭はベースアドレス)と、シンボルの読み込み状況を表示
+    fn show_sharedlibrary(&self) {
+        let mappings = match self.target.load_mappings() {
+            Ok(m) => m,
+            Err(e) => {
+                println!("cannot read mappings: {}", e);
+                return;
+            }
+        };
+        let libraries = group_libraries(&mappings);
+        if libraries.is_empty() {
+            println!("No shared libraries loaded at this time.");
+            return;
+        }
+        self.paged(|out| {
+            writeln!(
+                out,
+                "{:<18}  {:<18}  {:<9}  Shared Object Library",
+                "From", "To", "Syms Read"
+            )?;
+            libraries.iter().try_for_each(|(path, start, end)| {
+                let read = self
+                    .solibs
+                    .iter()
+                    .any(|l| l.path == *path && l.base as u64 == *start);
+                writeln!(
+                    out,
+                    "0x{:016x}  0x{:016x}  {:<9}  {}",
+                    start,
+                    end,
+                    if read { "Yes" } else { "No" },
+                    path
+                )
+            })
+        });
+    }
+
+    /// 実行ファイルのエントリーポイント・ロードバイアス・セクション一覧表示
+    ///
+    /// ロードされるセクションのアドレスは、実行時のアドレスとする
+    fn show_files<W: Write>(&self, out: &mut W) -> Result<()> {
+        let bias = self.load_bias();
+        writeln!(out, "Symbols from \"{}\".", self.path)?;
+        writeln!(
+            out,
+            "Entry point: 0x{:x} (file 0x{:x})",
+            bias + self.elf.get_entry(),
+            self.elf.get_entry()
+        )?;
+        writeln!(out, "Load bias: 0x{:x}", bias)?;
+        writeln!(
+            out,
+            "{:<4} {:<24} {:<18} {:<10} Size",
+            "No", "Name", "Address", "Offset"
+        )?;
+        for (i, sec) in self.elf.get_sec_headers().iter().enumerate().skip(1) {
+            let addr = match sec.get_addr() {
+                0 => "-".to_string(),
+                a => format!("0x{:016x}", bias + a),
+            };
+            writeln!(
+                out,
+                "[{:>2}] {:<24} {:<18} 0x{:08x} 0x{:08x}",
+                i,
+                sec.get_name(),
+                addr,
+                sec.get_offset(),
+                sec.get_size()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// 変数一覧表示
+    fn show_variables<W: Write>(&self, out: &mut W, pattern: Option<Regex>) -> Result<()> {
+        let matched = |name: &str| pattern.as_ref().is_none_or(|p| p.is_match(name));
+        let vars = self.elf.get_dwarf().get_global_vars();
+        match &pattern {
+            Some(p) => writeln!(
+                out,
+                "All variables matching regular expression \"{}\":",
+                p.as_str()
+            )?,
+            None => writeln!(out, "All defined variables:")?,
+        }
+
+        // ファイル毎に、実行時のアドレス順で表示(サイズ・変数のあるセクションも表示)
+        let base = self.load_bias();
+        let mut files = vars
+            .iter()
+            .filter(|v| matched(v.get_name()))
+            .map(|v| v.get_file())
+            .collect::<Vec<&str>>();
+        files.sort_unstable();
+        files.dedup();
+        for file in files {
+            writeln!(out, "\nFile {}:", file)?;
+            let mut defs = vars
+                .iter()
+                .filter(|v| v.get_file() == file && matched(v.get_name()))
+                .collect::<Vec<_>>();
+            defs.sort_by_key(|v| v.get_addr());
+            for v in defs {
+                writeln!(
+                    out,
+                    "0x{:016x}  {:>6}  {:<14}  {} {};",
+                    base + v.get_addr(),
+                    v.get_type().get_size(),
+                    self.section_name(v.get_addr()),
+                    v.get_type().get_name(),
+                    v.get_name()
+                )?;
+            }
+        }
+
+        // DWARFに情報がない変数は、シンボルテーブルの情報(実行時のアドレス・サイズ・セクション)のみ表示
+        writeln!(out, "\nNon-debugging symbols:")?;
+        let addrs = vars.iter().map(|v| v.get_addr()).collect::<HashSet<u64>>();
+        let mut syms = self
+            .elf
+            .get_var_syms()
+            .filter(|s| matched(&s.get_name()))
+            .filter(|s| !addrs.contains(&s.st_value))
+            .collect::<Vec<_>>();
+        syms.sort_by_key(|s| s.st_value);
+        for s in syms {
+            writeln!(
+                out,
+                "0x{:016x}  {:>6}  {:<14}  {}",
+                base + s.st_value,
+                s.get_size(),
+                self.section_name(s.st_value),
+                s.get_name()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// ロードバイアス(位置独立実行形式であれば、ファイル上のアドレスに加算する値)
+    fn load_bias(&self) -> u64 {
+        if self.elf.is_pie() {
+            self.entry as u64
+        } else {
+            0
+        }
+    }
+
+    /// ファイル上のアドレスを含むセクション名(見つからなければ??)
+    fn section_name(&self, addr: u64) -> &str {
+        self.elf.search_section(addr).map_or("??", |s| s.get_name())
+    }
+
+    /// 配列の表示要素数設定
+    fn set_print_elements(&mut self, val: &str) {
+        match val.parse::<usize>() {
+            Ok(v) => self.print_opt.max_elements = v,
+            _ => println!("parse error: {}", val),
+        }
+    }
+
+    /// ページャーの行数設定(ゼロならば止めない)
+    fn set_height(&mut self, val: &str) {
+        match val.parse::<usize>() {
+            Ok(v) => self.height = v,
+            _ => println!("parse error: {}", val),
+        }
+    }
+
+    /// btで辿るフレーム数の上限設定
+    fn set_backtrace_limit(&mut self, val: &str) {
+        match val.parse::<usize>() {
+            Ok(v) if 0 < v => self.backtrace_limit = v,
+            _ => println!("parse error: {}", val),
+        }
+    }
+
+    /// ページャーを通して表示
+    fn paged<F: FnOnce(&mut StdoutPager) -> Result<()>>(&self, f: F) {
+        let mut pager = Pager::stdout(self.height);
+        if let Err(e) = f(&mut pager) {
+            drop(pager);
+            println!("{}", e);
+        }
+    }
+
+    /// debugセクション表示
+    ///
+    /// info debugsec info [cu-index] [--raw]
+    /// info debugsec abbrev [offset] [--raw]
+    /// info debugsec line [cu-index] [--raw]
+    fn show_debugsec<W: Write>(&self, out: &mut W, args: &[String]) -> Result<()> {
+        let raw = args.iter().any(|a| "--raw" == a);
+        let args = args
+            .iter()
+            .filter(|a| "--raw" != *a)
+            .map(|a| a.as_str())
+            .collect::<Vec<&str>>();
+        let dwarf = self.elf.get_dwarf();
+        let index = |arg: Option<&&str>| match arg {
+            Some(a) => a.parse::<usize>().map(Some).map_err(|_| a.to_string()),
+            None => Ok(None),
+        };
+        let offset = |arg: Option<&&str>| match arg {
+            Some(a) => parse_num(a).map(Some).ok_or_else(|| a.to_string()),
+            None => Ok(None),
+        };
+        match (args.first(), args.len()) {
+            (Some(&"info"), 1..=2) => match index(args.get(1)) {
+                Ok(i) => dwarf.show_info(out, i, raw),
+                Err(a) => writeln!(out, "invalid compile unit number: {}", a),
+            },
+            (Some(&"abbrev"), 1..=2) => match offset(args.get(1)) {
+                Ok(o) => dwarf.show_abbrev(out, o, raw),
+                Err(a) => writeln!(out, "invalid abbrev offset: {}", a),
+            },
+            (Some(&"line"), 1..=2) => match index(args.get(1)) {
+                Ok(i) => dwarf.show_line(out, i, raw),
+                Err(a) => writeln!(out, "invalid compile unit number: {}", a),
+            },
+            _ => writeln!(
+                out,
+                "usage: info debugsec info [cu-index] | abbrev [offset] | line [cu-index] [--raw]"
+            ),
+        }
+    }
+
+    /// シェルからのプログラム停止
+    fn sh_quit(&self) {
+        // コアファイルのpidは、別のプロセスが使っている場合がある
+        if !self.target.is_core() && !self.exited {
+            kill(self.pid).expect("cannot kill");
+        }
+        std::process::exit(0);
+    }
+
+    /// プログラムへ渡す引数の表示(show args)
+    fn show_args(&self) {
+        println!(
+            "Argument list to give program being debugged when it is started is \"{}\".",
+            format_args(&self.args)
+        );
+    }
+
+    /// シェルからのdetach
+    ///
+    /// ブレイクポイント(内部・一時的なものを含む)とウォッチポイントを外し、プログラムを実行させたまま終了する
+    /// 停止時はブレイクポイントの命令を実行済み(recover_bp)のため、ripを戻す必要はない
+    fn sh_detach(&mut self) {
+        let n = self.breakpoint.get().len();
+        self.release_breaks((0..n).collect());
+        self.clear_temp_bps();
+        if let Some(b) = self.solib_bp.take() {
+            self.write_mem(&AdrFromAbs::new(b.addr), b.inst);
+        }
+        let watches = self.watchpoints.iter().flatten().count();
+        if 0 < watches {
+            self.watchpoints = Default::default();
+            if let Err(e) = self.apply_watchpoints() {
+                println!("cannot release watchpoints: {}", e);
+            }
+        }
+        match detach(self.pid, None) {
+            Ok(_) => {
+                println!(
+                    "Detaching from program: {}, process {} (removed {} breakpoints, {} watchpoints)",
+                    self.path, self.pid, n, watches
+                );
+                std::process::exit(0);
+            }
+            Err(e) => println!("cannot detach: {}", e),
+        }
+    }
+
+    /// break point設定
+    ///
+    /// int 3命令を下位1バイトに埋め込み、ソフトウェア割り込みを発生させる
+    fn breakpoint(&mut self, addr: usize, sym: &str) {
+        // ブレイクポイント設定
+        let address = AdrFromRel::new(self.entry, addr);
+
+        // int 3命令を埋め込む
+        let inst = self.read_mem(&address);
+        let int_code = (0xFFFF_FFFF_FFFF_FF00 & inst) | 0xCC;
+        self.write_mem(&address, int_code as usize);
+
+        // ブレイクポイント登録
+        self.breakpoint.register(sym, address, inst as usize);
+    }
+
+    /// break point解除
+    ///
+    /// 指定されたインデックスに存在するブレイクポイントを削除
+    fn release_break(&mut self, index: usize) -> bool {
+        // ブレイクポイントを削除し、元の命令に書き換える
+        let bp = self.breakpoint.delete(index);
+        match bp {
+            // 未解決のブレイクポイントは、命令を書き換えていない
+            Some(bp) if bp.pending => true,
+            Some(bp) => {
+                // 命令を元にもどす
+                unsafe {
+                    write(
+                        self.pid,
+                        bp.addr.get() as AddressType,
+                        bp.inst as AddressType,
+                    )
+                    .expect("ptrace::write failed");
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 複数のブレイクポイントを解除
+    ///
+    /// 近いアドレスのブレイクポイントは、後から登録したものの命令列に先に登録したもののint 3を含むため、
+    /// 登録と逆の順に元の命令へ書き戻す
+    /// 停止時はブレイクポイントの命令を実行済み(recover_bp)のため、ripを戻す必要はない
+    fn release_breaks(&mut self, mut indexes: Vec<usize>) {
+        indexes.sort_unstable();
+        for i in indexes.into_iter().rev() {
+            self.release_break(i);
+        }
+    }
+
+    /// シェルからのuntil
+    ///
+    /// 指定位置に一時ブレイクポイントを貼って再開する(既にブレイクポイントがあれば貼らない)
+    /// 位置を省略した場合は、現在の関数内で現在の行より後ろの行に到達するまでステップ実行する
+    /// 再開すればtrueを返す
+    fn sh_until(&mut self, spec: Option<&str>) -> bool {
+        match spec {
+            Some(spec) => {
+                let addrs = self.resolve_location(spec);
+                if addrs.is_empty() {
+                    println!("not found location: {}", spec);
+                    return false;
+                }
+                let mut planted = true;
+                for addr in addrs {
+                    planted &= self.plant_temp_bp(addr, None);
+                }
+                if !planted {
+                    self.clear_temp_bps();
+                    return false;
+                }
+                self.cont();
+            }
+            None => {
+                let regs = self.read_regs();
+                let rip = regs.rip as usize;
+                let func = rip
+                    .checked_sub(self.entry)
+                    .and_then(|pc| self.elf.search_func_sym_by_addr(pc as u64));
+                let (func, line) = match (func, self.search_line(rip)) {
+                    (Some(f), Some(l)) => (f, l),
+                    _ => {
+                        println!("cannot find function and line at 0x{:x}", rip);
+                        return false;
+                    }
+                };
+                let start = self.entry + func.st_value as usize;
+                self.until = Some(UntilFrame {
+                    start,
+                    end: start + func.get_size() as usize,
+                    rsp: regs.rsp,
+                    line,
+                });
+                self.running = true;
+                self.step();
+                println!("until...");
+            }
+        }
+        true
+    }
+
+    /// 位置(file:line・*addr・--rel addr・関数名)をアドレスに変換
+    fn resolve_location(&self, spec: &str) -> Vec<usize> {
+        if let Some(addr) = spec.strip_prefix("--rel ") {
+            return parse_num(addr)
+                .and_then(|a| self.entry.checked_add(a as usize))
+                .into_iter()
+                .collect();
+        }
+        if let Some(addr) = spec.strip_prefix('*') {
+            return parse_num(addr)
+                .map(|a| vec![a as usize])
+                .unwrap_or_default();
+        }
+        let file_line = spec
+            .rsplit_once(':')
+            .and_then(|(f, l)| Some((f, l.parse::<u64>().ok()?)));
+        if let Some((file, line)) = file_line {
+            return self
+                .elf
+                .get_dwarf()
+                .search_line_addrs(file, line)
+                .iter()
+                .map(|i| self.entry + i.get_address() as usize)
+                .collect();
+        }
+
+        let (file, name) = split_scope(spec);
+        let funcs = self.elf.get_dwarf().search_funcs(name, file);
+        if !funcs.is_empty() || file.is_some() {
+            return funcs
+                .iter()
+                .map(|f| self.entry + f.get_addr() as usize)
+                .collect();
+        }
+        match self.elf.search_func_sym(spec) {
+            Some(s) => vec![self.entry + s.st_value as usize],
+            None => self
+                .solibs
+                .iter()
+                .find_map(|l| Some(l.base + l.elf.search_func_sym(spec)?.st_value as usize))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// 引数なしのuntilで、1step実行した後の判定
+    ///
+    /// 現在の関数内で現在の行より後ろの行に到達するか、関数から戻ればtrueを返す(停止する)
+    /// 呼び出し先に入った場合は、戻りアドレスに一時ブレイクポイントを貼って再開する
+    fn until_step(&mut self) -> bool {
+        let regs = self.read_regs();
+        let rip = regs.rip as usize;
+        let (start, end, rsp, line) = match &self.until {
+            Some(f) => (f.start, f.end, f.rsp, f.line.clone()),
+            None => return true,
+        };
+        let in_func = start <= rip && rip < end;
+        if regs.rsp < rsp && (!in_func || rip == start) {
+            // 呼び出し直後は、スタックの先頭が戻りアドレス(戻ればrspはその分浅くなる)
+            let ret = self.read_mem(&AdrFromAbs::new(regs.rsp as usize));
+            if self.plant_temp_bp(ret as usize, Some(regs.rsp + 8)) {
+                self.tracing = self.record.is_some();
+                self.resume(None);
+                return false;
+            }
+        }
+        if !in_func && rsp < regs.rsp {
+            return true;
+        }
+        let is_after =
+            |l: LineInfo| l.get_file() == line.get_file() && line.get_line() < l.get_line();
+        // 呼び出し先は戻りアドレスまで実行するため、関数内は常に開始時のフレーム
+        if in_func && self.search_line(rip).is_some_and(is_after) {
+            return true;
+        }
+        self.step();
+        false
+    }
+
+    /// 現在の関数から戻るまで実行(finish)
+    ///
+    /// フレームポインタを設定する前であればrspから、設定済みであればrbpから戻りアドレスを求め、
+    /// 一時ブレイクポイントを貼って再開する(再帰呼び出しの深いフレームが戻っても停止しない)
+    /// 再開した場合はtrue
+    fn sh_finish(&mut self) -> bool {
+        let regs = self.read_regs();
+        let slot = match self.search_prologue_ret_offset(regs.rip) {
+            Some(off) => regs.rsp + off,
+            None => regs.rbp + 8,
+        };
+        let ret = match self.try_read_bytes(&AdrFromAbs::new(slot as usize), 8) {
+            Ok(d) => u64::from_le_bytes(d.try_into().unwrap()) as usize,
+            Err(_) => {
+                println!("cannot find the return address at 0x{:x}", slot);
+                return false;
+            }
+        };
+        if !self.plant_temp_bp(ret, Some(slot + 8)) {
+            return false;
+        }
+        println!(
+            "Run till exit from 0x{:x} in {}",
+            regs.rip,
+            self.format_func_offset(regs.rip)
+        );
+        self.finish = Some(ret);
+        self.cont();
+        true
+    }
+
+    /// finishで戻った位置・戻り値を表示
+    fn show_finish(&self) {
+        let rip = self.read_regs().rip;
+        let func = self.format_func_offset(rip);
+        match self.search_line(rip as usize) {
+            Some(line) => println!("returned to 0x{:x} in {} ({})", rip, func, line),
+            None => println!("returned to 0x{:x} in {}", rip, func),
+        }
+        self.show_finish_value();
+    }
+
+    /// 戻り値(rax)を表示
+    fn show_finish_value(&self) {
+        let rax = self.read_regs().rax;
+        println!("Value returned: rax = 0x{:x} ({})", rax, rax as i64);
+    }
+
+    /// untilで停止した位置を表示
+    fn show_until(&self) {
+        let rip = self.read_regs().rip as usize;
+        match self.search_line(rip) {
+            Some(line) => println!("until at 0x{:x} ({})", rip, line),
+            None => println!("until at 0x{:x}", rip),
+        }
+    }
+
+    /// プログラムの開始位置(AT_ENTRY、読み込めなければe_entryにロードしたアドレスを加算)を取得
+    fn entry_point(&self) -> u64 {
+        match load_auxv(self.pid)
+            .ok()
+            .and_then(|a| a.get(&AT_ENTRY).copied())
+        {
+            Some(entry) => entry,
+            None if self.elf.is_pie() => self.entry as u64 + self.elf.get_entry(),
+            None => self.elf.get_entry(),
+        }
+    }
+
+    /// 開始位置に一時ブレイクポイントを貼り、再開する(starti・--stop-at-entry)
+    ///
+    /// 静的リンクであれば最初の命令が開始位置のため、再開せずに表示する
+    /// 再開した場合はtrue
+    fn run_to_entry(&mut self) -> bool {
+        let entry = self.entry_point();
+        if self.read_regs().rip == entry {
+            self.show_entry();
+            return false;
+        }
+        if !self.plant_temp_bp(entry as usize, None) {
+            return false;
+        }
+        self.to_entry = true;
+        self.cont();
+        true
+    }
+
+    /// 開始位置での停止を表示
+    ///
+    /// 動的リンクであれば、インタプリタ(動的リンカ)の実行後に停止している
+    fn show_entry(&self) {
+        let rip = self.read_regs().rip;
+        // 位置独立でなければ、シンボルは絶対アドレス
+        let pc = match self.elf.is_pie() {
+            true => rip.checked_sub(self.entry as u64),
+            false => Some(rip),
+        };
+        let func = pc
+            .and_then(|pc| self.elf.search_func_sym_by_addr(pc))
+            .map_or("??".to_string(), |s| s.get_name());
+        let base = load_auxv(self.pid)
+            .ok()
+            .and_then(|a| a.get(&AT_BASE).copied())
+            .unwrap_or(0);
+        let interp = self
+            .target
+            .load_mappings()
+            .unwrap_or_default()
+            .into_iter()
+            .find(|m| 0 != base && base == m.start);
+        let how = match interp {
+            Some(m) => format!("after interpreter {}", m.path),
+            None => "static binary, no interpreter".to_string(),
+        };
+        println!(
+            "Program stopped at entry point 0x{:x} ({} in {}, {})",
+            rip, func, self.path, how
+        );
+    }
+
+    /// 一時ブレイクポイントを貼る(既にブレイクポイントがあれば貼らない)
+    ///
+    /// rspは、戻りアドレスに貼る場合の戻った後のrsp(より深いフレームが通過しても停止しない)
+    /// 書き込めなければfalseを返す
+    fn plant_temp_bp(&mut self, addr: usize, rsp: Option<u64>) -> bool {
+        let exists = self.breakpoint.has_addr(&AdrFromAbs::new(addr))
+            || self.temp_bps.iter().any(|b| b.addr == addr)
+            || self.solib_bp.as_ref().is_some_and(|b| b.addr == addr);
+        if exists {
+            return true;
+        }
+        let address = AdrFromAbs::new(addr);
+        let inst = match self.try_read_bytes(&address, 8) {
+            Ok(d) => u64::from_le_bytes(d.try_into().unwrap()),
+            Err(_) => {
+                println!("Cannot access memory at address 0x{:x}", addr);
+                return false;
+            }
+        };
+        self.write_mem(&address, ((0xFFFF_FFFF_FFFF_FF00 & inst) | 0xCC) as usize);
+        self.temp_bps.push(TempBreakpoint {
+            addr,
+            inst: inst as usize,
+            rsp,
+        });
+        true
+    }
+
+    /// 一時ブレイクポイントをすべて外す
+    fn clear_temp_bps(&mut self) {
+        for b in std::mem::take(&mut self.temp_bps) {
+            self.write_mem(&AdrFromAbs::new(b.addr), b.inst);
+        }
+    }
+
+    /// 停止を表示せずに再開する
+    ///
+    /// sigがあればプログラムへ送る
+    /// ステップ実行中・記録中・引数なしのuntilでステップ実行している場合は、1step実行する
+    fn resume(&self, sig: Option<Signal>) {
+        let stepping = !self.running || (self.until.is_some() && self.temp_bps.is_empty());
+        if self.tracing || stepping {
+            step(self.pid, sig).expect("step is failed");
+        } else {
+            cont(self.pid, sig).expect("pcont is failed");
+        }
+    }
+
+    /// シェルからのキャッチポイント設定
+    ///
+    /// 共有ライブラリの検出を開始していなければ、内部ブレイクポイントを貼る
+    fn sh_catch(&mut self, kind: CatchKind, pattern: Option<&String>) {
+        match Catchpoint::new(kind, pattern.map(|p| p.as_str())) {
+            Ok(c) => {
+                println!("Catchpoint {} ({})", self.catches.len(), c);
+                self.catches.push(c);
+            }
+            Err(e) => {
+                println!("invalid regex: {}", e);
+                return;
+            }
+        }
+        if self.solib_bp.is_none() {
+            self.setup_solib_bp();
+        }
+    }
+
+    /// シェルからのキャッチポイント削除
+    ///
+    /// キャッチポイントがなくなれば、内部ブレイクポイントも外す
+    fn sh_delete_catch(&mut self, no: &str) {
+        match no.parse::<usize>() {
+            Ok(n) if n < self.catches.len() => {
+                self.catches.remove(n);
+                println!("release Catchpoint({})", n);
+            }
+            _ => {
+                println!("No catchpoint number {}.", no);
+                return;
+            }
+        }
+        if self.catches.is_empty() {
+            if let Some(b) = self.solib_bp.take() {
+                self.write_mem(&AdrFromAbs::new(b.addr), b.inst);
+            }
+        }
+    }
+
+    /// 環境変数表示
+    ///
+    /// 実行中の変更(setenv)を反映するため、environを参照できれば指している配列から読み込む
+    /// 参照できなければ、/proc/<pid>/environ(起動時の環境変数)から読み込む
+    fn show_environ(&self, pattern: Option<&str>) {
+        let (source, vars) = match self.read_environ() {
+            Some((addr, vars)) => (format!("environ at 0x{:x}", addr), vars),
+            None if self.target.is_core() => {
+                println!("cannot read environ from the core");
+                return;
+            }
+            None => {
+                let path = format!("/proc/{}/environ", self.pid);
+                match std::fs::read(&path) {
+                    Ok(data) => {
+                        let vars = parse_environ(&data).iter().map(|v| v.to_vec()).collect();
+                        (format!("{} (initial environment)", path), vars)
+                    }
+                    Err(e) => {
+                        println!("cannot read {}: {}", path, e);
+                        return;
+                    }
+                }
+            }
+        };
+        let matched =
+            |var: &[u8]| pattern.is_none_or(|p| String::from_utf8_lossy(get_name(var)).contains(p));
+        self.paged(|out| {
+            writeln!(out, "{}:", source)?;
+            vars.iter()
+                .filter(|v| matched(v))
+                .try_for_each(|v| writeln!(out, "{}", format_var(v, VALUE_LIMIT)))
+        });
+    }
+
+    /// environが指す配列から、環境変数を読み込む
+    ///
+    /// environのアドレスと環境変数を返す(シンボルがない・初期化前であればNone)
+    fn read_environ(&self) -> Option<(usize, Vec<Vec<u8>>)> {
+        let sym = self.elf.get_var_syms().find(|s| {
+            let name = s.get_name();
+            let name = name.split('@').next().unwrap_or_default();
+            ["environ", "__environ", "_environ"].contains(&name)
+        })?;
+        let addr = self.entry + sym.st_value as usize;
+        let envp = self.try_read_bytes(&AdrFromAbs::new(addr), 8).ok()?;
+        let envp = u64::from_le_bytes(envp.try_into().ok()?);
+        if 0 == envp {
+            return None;
+        }
+
+        let mem = &self.target;
+        let mut vars = vec![];
+        for i in 0..ENVIRON_MAX as u64 {
+            let p = mem.read_memory(envp + i * 8, 8)?;
+            let p = u64::from_le_bytes(p.try_into().ok()?);
+            if 0 == p {
+                break;
+            }
+            match mem.read_cstring(p, ENVIRON_READ_SIZE) {
+                Some((v, _)) => vars.push(v),
+                None => vars.push(format!("<fault at 0x{:x}>", p).into_bytes()),
+            }
+        }
+        Some((addr, vars))
     }
 
-    /// シェルからのシンボル書き込み
-    fn sh_write_sym(&self, sym: &str, val: &str) {
-        let val = match usize::from_str_radix(val.trim_start_matches("0x"), 16) {
-            Ok(v) => v,
+    /// プロセスの状態を保存(checkpoint)
+    fn sh_checkpoint(&mut self) {
+        let regs = self.read_regs();
+        let ranges = writable_regions(self.memory_map.load());
+        match Checkpoint::take(self.pid, regs, &ranges, SPILL_SIZE) {
+            Ok(cp) => {
+                println!(
+                    "checkpoint {}: {}",
+                    self.checkpoints.len(),
+                    self.format_checkpoint(&cp)
+                );
+                self.checkpoints.push(cp);
+            }
+            Err(e) => println!("cannot take checkpoint: {}", e),
+        }
+    }
+
+    /// 保存した状態へ戻す(restart)
+    ///
+    /// 書き込み可能な領域のみ戻すため、コードに貼ったブレイクポイントはそのまま残る
+    /// 書き戻した領域で消えたint 3は、貼り直す
+    fn sh_restart(&mut self, no: &str) {
+        let n = match no.parse::<usize>() {
+            Ok(n) if n < self.checkpoints.len() => n,
             _ => {
-                println!("parse error: {}", val);
+                println!("No checkpoint number {}.", no);
                 return;
             }
         };
+        println!("warning: file descriptors, sockets and other external state are not restored");
+        let current = writable_regions(self.memory_map.load());
+        match self.checkpoints[n].restore(self.pid, &current) {
+            Ok(added) => added.iter().for_each(|(s, e)| {
+                println!(
+                    "warning: 0x{:x}-0x{:x} was mapped after the checkpoint and is not restored",
+                    s, e
+                )
+            }),
+            Err(e) => {
+                println!("cannot restart from checkpoint {}: {}", n, e);
+                return;
+            }
+        }
 
-        // シンボル探索
-        match self.elf.search_var_sym(sym) {
-            Some(s) => {
-                // シンボルの内容を書き換え
-                let addr = AdrFromRel::new(self.entry, s.st_value as usize);
-                self.write_mem(&addr, val);
+        self.frame = 0;
+        self.signal = None;
+        self.clear_temp_bps();
+        self.until = None;
+        let replanted = self.reconcile_breakpoints();
+        println!(
+            "restarted from checkpoint {} (re-planted {} breakpoints)",
+            n, replanted
+        );
+        let rip = self.read_regs().rip;
+        if let Some(inst) = format_instruction(&self.target, rip, &self.get_original_bytes()) {
+            println!("{}", inst);
+        }
+    }
+
+    /// シェルからのrun
+    ///
+    /// 実行中であれば終了させてから、プログラムを起動し直す
+    /// ブレイクポイントは新しいロード先で解決し直し、開始位置から再開する(再開すればtrue)
+    fn sh_run(&mut self) -> bool {
+        if !self.exited {
+            if !confirm(
+                "The program being debugged has been started already. Start it from the beginning?",
+            ) {
+                return false;
             }
-            _ => println!("not found symbol: {}", sym),
+            let _ = kill(self.pid);
+            while let Ok(status) = waitpid(self.pid, None) {
+                if let WaitStatus::Exited(..) | WaitStatus::Signaled(..) = status {
+                    break;
+                }
+            }
+        }
+
+        // execv後の停止を待つ
+        let pid = match spawn_traced(&self.program, &self.args) {
+            Ok(pid) => pid,
+            Err(e) => {
+                println!("cannot start {}: {}", self.program, e);
+                return false;
+            }
+        };
+        match waitpid(pid, None) {
+            Ok(WaitStatus::Stopped(_, Signal::SIGTRAP)) => {}
+            status => {
+                println!("cannot start {}: {:?}", self.program, status);
+                return false;
+            }
+        }
+        match self.args.is_empty() {
+            true => println!("Starting program: {} (pid {})", self.program, pid),
+            false => println!(
+                "Starting program: {} {} (pid {})",
+                self.program,
+                format_args(&self.args),
+                pid
+            ),
+        }
+        self.pid = pid;
+        self.target = Target::Process(pid);
+        self.exited = false;
+        self.path = self.program.clone();
+        self.elf = Elf64::new(self.path.clone());
+        self.memory_map = MemoryMap::new(pid);
+        if let Err(err) = self.load_elf() {
+            println!("cannot parse ELF: {:?}", err);
+            self.sh_quit();
+        }
+        setoptions(pid, Options::PTRACE_O_TRACEEXEC).expect("setoptions is failed");
+
+        // 以前のプロセスの状態を破棄
+        self.frame = 0;
+        self.signal = None;
+        self.solib_bp = None;
+        self.libraries.clear();
+        self.solibs.clear();
+        self.temp_bps.clear();
+        self.until = None;
+        self.finish = None;
+        self.checkpoints.clear();
+        if let Some(r) = self.record.as_ref() {
+            self.record = Some(RingBuffer::new(r.get_capacity()));
+        }
+        // ロード先が変わるため、ウォッチポイントは設定し直す必要がある
+        if self.watchpoints.iter().any(|w| w.is_some()) {
+            println!("watchpoints are deleted (set them again after restart)");
+            self.watchpoints = Default::default();
+        }
+
+        // ブレイクポイントを新しいロード先で解決し直す
+        self.breakpoint.set_pending();
+        self.resolve_pending_breaks();
+        if !self.catches.is_empty() {
+            self.setup_solib_bp();
+        }
+        self.cont();
+        true
+    }
+
+    /// ブレイクポイントのint 3が消えていれば貼り直し、貼り直した数を返す
+    fn reconcile_breakpoints(&mut self) -> usize {
+        let addrs = self
+            .breakpoint
+            .get()
+            .iter()
+            .filter(|b| !b.pending)
+            .map(|b| b.addr.get())
+            .collect::<Vec<usize>>();
+        let mut count = 0;
+        for a in addrs {
+            let addr = AdrFromAbs::new(a);
+            let inst = self.read_mem(&addr);
+            if 0xCC != inst & 0xFF {
+                self.breakpoint.set_inst(&addr, inst as usize);
+                self.write_mem(&addr, ((inst & !0xFF) | 0xCC) as usize);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// 保存した状態を文字列化
+    fn format_checkpoint(&self, cp: &Checkpoint) -> String {
+        let rip = cp.get_regs().rip as usize;
+        let location = match self.search_line(rip) {
+            Some(l) => format!("0x{:x} at {}", rip, l),
+            None => format!("0x{:x}", rip),
         };
+        format!(
+            "{}, {} regions, {} bytes{}",
+            location,
+            cp.get_region_count(),
+            cp.get_size(),
+            if cp.is_spilled() {
+                " (spilled to a temp file)"
+            } else {
+                ""
+            }
+        )
     }
 
-    /// シェルからのプログラム停止
-    fn sh_quit(&self) {
-        kill(self.pid).expect("cannot kill");
-        std::process::exit(0);
+    /// 保存した状態の一覧表示
+    fn show_checkpoints(&self) {
+        if self.checkpoints.is_empty() {
+            println!("not entried checkpoint");
+        }
+        for (i, cp) in self.checkpoints.iter().enumerate() {
+            println!("{}: {}", i, self.format_checkpoint(cp));
+        }
     }
 
-    /// break point設定
+    /// 保存した状態の削除
+    fn sh_delete_checkpoint(&mut self, no: &str) {
+        match no.parse::<usize>() {
+            Ok(n) if n < self.checkpoints.len() => {
+                self.checkpoints.remove(n);
+                println!("release Checkpoint({})", n);
+            }
+            _ => println!("No checkpoint number {}.", no),
+        }
+    }
+
+    /// ヒット回数を数えるブレイクポイントを設定(番号を省略すれば全て)
+    fn sh_profile_on(&mut self, nos: &[String]) {
+        if nos.is_empty() {
+            self.breakpoint.set_profiled(None, true);
+        }
+        for no in nos {
+            match no.parse::<usize>() {
+                Ok(n) if self.breakpoint.set_profiled(Some(n), true) => {}
+                _ => println!("No breakpoint number {}.", no),
+            }
+        }
+        let count = self.breakpoint.get().iter().filter(|b| b.profiled).count();
+        println!(
+            "profiling {} breakpoints (hits are counted without stopping)",
+            count
+        );
+    }
+
+    /// ヒット回数の表示
+    fn show_profile(&self) {
+        let hits = self
+            .breakpoint
+            .get()
+            .iter()
+            .filter(|b| b.profiled || 0 < b.profile_hits)
+            .map(|b| (b.sym.as_str(), b.profile_hits))
+            .collect::<Vec<(&str, u64)>>();
+        if hits.is_empty() {
+            println!("not entried profiled breakpoint");
+            return;
+        }
+        format_report(&hits, self.run_clock.elapsed())
+            .iter()
+            .for_each(|l| println!("{}", l));
+    }
+
+    /// プログラムの終了時に、ヒット回数を数えていれば表示
+    fn show_profile_on_exit(&mut self) {
+        self.run_clock.pause();
+        if self
+            .breakpoint
+            .get()
+            .iter()
+            .any(|b| b.profiled || 0 < b.profile_hits)
+        {
+            self.show_profile();
+        }
+    }
+
+    /// キャッチポイント表示
+    fn show_catches(&self) {
+        if self.catches.is_empty() {
+            println!("not entried catchpoint");
+        }
+        for (i, c) in self.catches.iter().enumerate() {
+            println!("{}: {}", i, c);
+        }
+    }
+
+    /// 共有ライブラリの検出に使う内部ブレイクポイントを貼る
     ///
-    /// int 3命令を下位1バイトに埋め込み、ソフトウェア割り込みを発生させる
-    fn breakpoint(&mut self, addr: usize, sym: &str) {
-        // ブレイクポイント設定
-        let address = AdrFromRel::new(self.entry, addr);
+    /// 動的リンカがr_debugを設定していればr_brkへ、設定前(起動直後)であればプログラムの開始位置へ貼る
+    fn setup_solib_bp(&mut self) {
+        match self.read_r_debug().filter(|r| 0 != r.r_brk) {
+            Some(r) => {
+                self.libraries = to_libraries(self.memory_map.load());
+                self.plant_solib_bp(r.r_brk as usize, false);
+            }
+            None => {
+                let start = self.entry + self.elf.get_entry() as usize;
+                self.plant_solib_bp(start, true);
+            }
+        }
+    }
 
-        // int 3命令を埋め込む
+    /// 内部ブレイクポイント(int 3)を埋め込む
+    fn plant_solib_bp(&mut self, addr: usize, at_start: bool) {
+        let address = AdrFromAbs::new(addr);
         let inst = self.read_mem(&address);
-        let int_code = (0xFFFF_FFFF_FFFF_FF00 & inst as u64) | 0xCC;
-        self.write_mem(&address, int_code as usize);
+        self.write_mem(&address, ((0xFFFF_FFFF_FFFF_FF00 & inst) | 0xCC) as usize);
+        self.solib_bp = Some(SolibBreakpoint {
+            addr,
+            inst: inst as usize,
+            at_start,
+        });
+    }
 
-        // ブレイクポイント登録
-        self.breakpoint.register(sym, address, inst as usize);
+    /// ダイナミックセクションのDT_DEBUGから、r_debugを読み込む
+    fn read_r_debug(&self) -> Option<RDebug> {
+        let (addr, data) = self.elf.read_section(".dynamic").ok()?;
+        let dynamic = self
+            .try_read_bytes(&AdrFromRel::new(self.entry, addr as usize), data.len())
+            .ok()?;
+        let r_debug = find_r_debug(&dynamic)?;
+        let data = self
+            .try_read_bytes(&AdrFromAbs::new(r_debug as usize), R_DEBUG_SIZE)
+            .ok()?;
+        parse_r_debug(&data)
     }
 
-    /// break point解除
+    /// 内部ブレイクポイントで停止した際の処理
     ///
-    /// 指定されたインデックスに存在するブレイクポイントを削除
-    fn release_break(&mut self, index: usize) -> bool {
-        // ブレイクポイントを削除し、元の命令に書き換える
-        let bp = self.breakpoint.delete(index);
-        match bp {
-            Some(bp) => {
-                // 命令を元にもどす
-                unsafe {
-                    write(
-                        self.pid,
-                        bp.addr.get() as AddressType,
-                        bp.inst as AddressType,
-                    )
-                    .expect("ptrace::write failed");
-                }
-                true
+    /// 元の命令を1step実行してから、メモリマップの差分でロード・アンロードされたライブラリを求める
+    /// キャッチポイントにマッチすれば、ライブラリを表示してtrueを返す(停止する)
+    fn solib_event(&mut self) -> bool {
+        let bp = match self.solib_bp.take() {
+            Some(b) => b,
+            None => return false,
+        };
+
+        // 元の命令に戻して1step実行
+        let address = AdrFromAbs::new(bp.addr);
+        self.write_mem(&address, bp.inst);
+        let mut regs = self.read_regs();
+        regs.rip = bp.addr as u64;
+        self.write_regs(regs);
+        self.step();
+        match nix::sys::wait::waitpid(self.pid, None).expect("solib_event: wait is failed") {
+            WaitStatus::Stopped(_, _) => {}
+            _ => panic!("solib_event do not expect event"),
+        }
+
+        // プログラムの開始位置であれば、r_debugが設定済みのためr_brkへ貼り直す
+        if bp.at_start {
+            self.setup_solib_bp();
+            return false;
+        }
+        self.plant_solib_bp(bp.addr, false);
+
+        // ロード・アンロードの途中であれば、完了を待つ
+        if self
+            .read_r_debug()
+            .is_none_or(|r| RT_CONSISTENT != r.r_state)
+        {
+            return false;
+        }
+        let libraries = to_libraries(self.memory_map.load());
+        let loaded = libraries
+            .difference(&self.libraries)
+            .cloned()
+            .collect::<Vec<(String, u64)>>();
+        let unloaded = self
+            .libraries
+            .difference(&libraries)
+            .cloned()
+            .collect::<Vec<(String, u64)>>();
+        self.libraries = libraries;
+        self.solibs.retain(|l| {
+            !unloaded
+                .iter()
+                .any(|(p, b)| *p == l.path && *b as usize == l.base)
+        });
+
+        let mut stop = false;
+        for (path, base) in &loaded {
+            if let Some(no) = self.search_catch(CatchKind::Load, path) {
+                println!("Catchpoint {} (loaded {} at 0x{:x})", no, path, base);
+                self.load_solib(path, *base as usize);
+                stop = true;
             }
-            None => false,
+        }
+        for (path, base) in &unloaded {
+            if let Some(no) = self.search_catch(CatchKind::Unload, path) {
+                println!("Catchpoint {} (unloaded {} at 0x{:x})", no, path, base);
+                stop = true;
+            }
+        }
+        stop
+    }
+
+    /// ライブラリにマッチするキャッチポイントの番号を検索
+    fn search_catch(&self, kind: CatchKind, path: &str) -> Option<usize> {
+        self.catches
+            .iter()
+            .position(|c| kind == c.get_kind() && c.is_matched(path))
+    }
+
+    /// シェルからのシンボルファイル読み込み
+    ///
+    /// strip済みのプログラムに、strip前のファイルのシンボルを使う(アドレスは実行中のプログラムのもの)
+    /// ビルドIDが一致しなければ警告する。ファイルを省略した場合は、シンボルを破棄する
+    fn sh_symbol_file(&mut self, path: Option<&str>) {
+        self.prompt = None;
+        let path = match path {
+            Some(p) => p,
+            None => {
+                self.elf.clear_symbols();
+                println!("discarded symbols of {}", self.path);
+                return;
+            }
+        };
+        let mut elf = Elf64::new(path.to_string());
+        if let Err(e) = elf.load() {
+            println!("cannot load symbols from {}: {}", path, e);
+            return;
+        }
+
+        match (self.elf.get_build_id(), elf.get_build_id()) {
+            (Some(p), Some(s)) if p == s => println!("build-id {} matches", format_hex(&p)),
+            (Some(p), Some(s)) => {
+                println!("**********************************************************************");
+                println!("warning: build-id mismatch, symbols may not match the running program");
+                println!("  program     {}: {}", self.path, format_hex(&p));
+                println!("  symbol-file {}: {}", path, format_hex(&s));
+                println!("**********************************************************************");
+            }
+            _ => println!("warning: cannot verify {} (no build-id)", path),
+        }
+        println!(
+            "loaded {} symbols from {}",
+            elf.get_func_syms().count(),
+            path
+        );
+        self.elf.replace_symbols(elf);
+    }
+
+    /// 共有ライブラリのシンボルを読み込む
+    fn load_solib(&mut self, path: &str, base: usize) {
+        let mut elf = Elf64::new(path.to_string());
+        match elf.load_headers().and_then(|_| elf.load_library_symbols()) {
+            Ok(_) => {
+                println!(
+                    "loaded {} symbols from {}",
+                    elf.get_func_syms().count(),
+                    path
+                );
+                // プロンプトの表示で読み込み済みであれば置き換える
+                self.solibs.retain(|l| !(l.path == path && l.base == base));
+                self.solibs.push(SharedLibrary {
+                    path: path.to_string(),
+                    base,
+                    elf,
+                });
+            }
+            Err(e) => println!("cannot load symbols from {}: {}", path, e),
         }
     }
 
+    /// アドレスを含む共有ライブラリのシンボルを、読み込んでいなければ読み込む
+    ///
+    /// 停止する度に表示・読み込みをしないよう、読み込めなかったライブラリもシンボルなしで登録する
+    fn load_solib_at(&mut self, addr: u64) {
+        let (path, base) = match self
+            .target
+            .load_mappings()
+            .ok()
+            .and_then(|m| find_library(&m, addr))
+        {
+            Some(l) => l,
+            None => return,
+        };
+        if self
+            .solibs
+            .iter()
+            .any(|l| l.path == path && l.base as u64 == base)
+        {
+            return;
+        }
+        let mut elf = Elf64::new(path.clone());
+        if elf
+            .load_headers()
+            .and_then(|_| elf.load_library_symbols())
+            .is_err()
+        {
+            elf = Elf64::new(path.clone());
+        }
+        self.solibs.push(SharedLibrary {
+            path,
+            base: base as usize,
+            elf,
+        });
+    }
+
     /// break point表示
     fn show_break(&self) {
         let bps = self.breakpoint.get();
@@ -381,22 +4038,159 @@ impl<'a> Debugger<'a> {
             println!("not entried breakpoint");
         } else {
             for (i, b) in self.breakpoint.get().iter().enumerate() {
+                let cond = match &b.condition {
+                    Some(c) => format!(" if {}", c),
+                    None => String::new(),
+                };
+                let temporary = match b.temporary {
+                    true => " [temporary]",
+                    false => "",
+                };
+                let ignore = match b.ignore {
+                    0 => String::new(),
+                    n => format!(" [ignore next {}]", n),
+                };
+                if b.pending {
+                    println!("{}: {} <pending>{}{}{}", i, b.sym, cond, temporary, ignore);
+                    continue;
+                }
+                let profile = match b.profiled {
+                    true => format!(" [profile: {} hits]", b.profile_hits),
+                    false => String::new(),
+                };
                 println!(
-                    "{}: {} (0x{:016x})",
+                    "{}: {} (0x{:016x}){}{}{} [hits: {}]{}",
                     i,
                     b.sym,
-                    self.to_sym_addr(b.addr.get())
+                    self.to_sym_addr(b.addr.get()),
+                    cond,
+                    temporary,
+                    ignore,
+                    b.hits,
+                    profile
                 );
             }
         }
     }
 
+    /// ブレイクポイント1件の詳細表示(info break <n>)
+    fn show_break_info(&self, no: &str) {
+        let b = match no
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| self.breakpoint.get().get(i))
+        {
+            Some(b) => b,
+            None => {
+                println!("not entried breakpoint: {}", no);
+                return;
+            }
+        };
+        println!("Breakpoint {}: {}", no, b.sym);
+        if b.pending {
+            println!("  address      : <pending>");
+        } else {
+            let addr = b.addr.get();
+            match self.search_line(addr) {
+                Some(line) => println!("  address      : 0x{:016x} ({})", addr, line),
+                None => println!("  address      : 0x{:016x}", addr),
+            }
+            println!("  file address : 0x{:x}", self.to_sym_addr(addr));
+        }
+        println!("  hits         : {}", b.hits);
+        if let Some(c) = &b.condition {
+            println!("  condition    : {}", c);
+        }
+        if b.temporary {
+            println!("  temporary    : deleted after the first stop");
+        }
+        if 0 < b.ignore {
+            println!("  ignore       : next {} hits", b.ignore);
+        }
+        if b.profiled {
+            println!("  profile      : {} hits", b.profile_hits);
+        }
+    }
+
     /// ptrace cont実行
-    fn cont(&self) {
-        cont(self.pid, None).expect("pcont is failed");
+    ///
+    /// 記録中は、命令アドレスを記録するためステップ実行で再開する
+    /// シグナルで停止していた場合は、プログラムへ送る
+    fn cont(&mut self) {
+        self.running = true;
+        if self.breakpoint.get().iter().any(|b| b.profiled) {
+            self.run_clock.resume();
+        }
+        let sig = self.signal.take();
+        if self.record.is_some() {
+            self.tracing = true;
+            step(self.pid, sig).expect("step is failed");
+        } else {
+            cont(self.pid, sig).expect("pcont is failed");
+        }
         println!("continue...");
     }
 
+    /// 命令アドレスの記録開始
+    ///
+    /// 記録する命令数を省略した場合は、既定値とする
+    fn record_on(&mut self, size: Option<&String>) {
+        let size = match size.map(|s| s.parse::<usize>()) {
+            None => DEFAULT_RECORD_SIZE,
+            Some(Ok(s)) if 0 < s => s,
+            _ => {
+                println!("invalid record size: {}", size.unwrap());
+                return;
+            }
+        };
+        self.record = Some(RingBuffer::new(size));
+        println!("recording last {} instructions", size);
+    }
+
+    /// 命令アドレスの記録終了
+    fn record_off(&mut self) {
+        match self.record.take() {
+            Some(_) => println!("record stopped"),
+            None => println!("not recording"),
+        }
+    }
+
+    /// 記録した命令アドレスを、関数単位にまとめて表示
+    ///
+    /// 表示する命令数を省略した場合は、全て表示する
+    fn show_record(&self, count: Option<&String>) {
+        let record = match &self.record {
+            Some(r) => r,
+            None => {
+                println!("not recording (record on [size])");
+                return;
+            }
+        };
+        let count = match count.map(|c| c.parse::<usize>()) {
+            None => record.get_capacity(),
+            Some(Ok(c)) => c,
+            Some(Err(_)) => {
+                println!("parse error: {}", count.unwrap());
+                return;
+            }
+        };
+        let rips = record.recent(count);
+        let lines = format_runs(&rips, |rip| {
+            let pc = (rip as usize).checked_sub(self.entry)? as u64;
+            let sym = self.elf.search_func_sym_by_addr(pc)?;
+            Some((sym.get_name(), pc - sym.st_value))
+        });
+        self.paged(|out| {
+            writeln!(
+                out,
+                "last {} of {} recorded instructions",
+                rips.len(),
+                record.len()
+            )?;
+            lines.iter().try_for_each(|l| writeln!(out, "{}", l))
+        });
+    }
+
     /// ステップ実行
     fn step(&self) {
         step(self.pid, None).expect("step is failed");
@@ -452,6 +4246,35 @@ impl<'a> Debugger<'a> {
         self.write_regs(regs);
     }
 
+    /// SSEレジスタ(xmm0〜xmm15)の値を変更
+    fn set_fpregs(&self, reg: &str, val: &str) {
+        let no = match parse_xmm(reg) {
+            Some(no) => no,
+            None => {
+                println!("not register {} (xmm0-xmm15)", reg);
+                return;
+            }
+        };
+        let val = match parse_u128(val) {
+            Ok(v) => v,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        let mut fpregs = match self.target.read_fpregs() {
+            Ok(f) => f,
+            Err(e) => {
+                println!("cannot read fpregs: {}", e);
+                return;
+            }
+        };
+        set_xmm_value(&mut fpregs, no, val);
+        if let Err(e) = write_fpregs(self.pid, &fpregs) {
+            println!("cannot write fpregs: {}", e);
+        }
+    }
+
     /// レジスタ情報表示
     fn show_regs(&self) {
         let regs = self.read_regs();
@@ -482,9 +4305,135 @@ impl<'a> Debugger<'a> {
         println!("gs      : 0x{:016x}", regs.gs);
     }
 
+    /// 浮動小数点・SSEレジスタ表示
+    fn show_fpregs(&self) {
+        match self.target.read_fpregs() {
+            Ok(fpregs) => format_fpregs(&fpregs)
+                .iter()
+                .for_each(|l| println!("{}", l)),
+            Err(e) => println!("cannot read fpregs: {}", e),
+        }
+    }
+
     /// レジスタ読み込み
     fn read_regs(&self) -> libc::user_regs_struct {
-        getregs(self.pid).expect("read_regs is failed")
+        self.target.read_regs().expect("read_regs is failed")
+    }
+
+    /// スレッド一覧表示
+    ///
+    /// トレースしているスレッドに*を付け、レジスタを読み込めるスレッドはripの関数も表示する
+    fn show_threads(&self) {
+        let tasks = match load_tasks(self.pid) {
+            Ok(t) => t,
+            Err(e) => {
+                println!("cannot read threads: {}", e);
+                return;
+            }
+        };
+        self.paged(|out| {
+            writeln!(
+                out,
+                "  {:<4} {:<8} {:<18} {:<13} Frame",
+                "Id", "Tid", "Name", "State"
+            )?;
+            for (i, t) in tasks.iter().enumerate() {
+                let mark = if t.tid == self.pid { '*' } else { ' ' };
+                let frame = match getregs(t.tid) {
+                    Ok(regs) => {
+                        let rip = regs.rip as usize;
+                        let func = rip
+                            .checked_sub(self.entry)
+                            .and_then(|pc| self.elf.search_func_sym_by_addr(pc as u64));
+                        match func {
+                            Some(f) => format!(
+                                "0x{:x} in {}+0x{:x}",
+                                rip,
+                                f.get_name(),
+                                rip - self.entry - f.st_value as usize
+                            ),
+                            None => format!("0x{:x}", rip),
+                        }
+                    }
+                    Err(_) => "(not traced)".to_string(),
+                };
+                writeln!(
+                    out,
+                    "{} {:<4} {:<8} {:<18} {:<13} {}",
+                    mark,
+                    i + 1,
+                    t.tid,
+                    format!("\"{}\"", t.name),
+                    to_state_name(t.state),
+                    frame
+                )?;
+            }
+            Ok(())
+        });
+    }
+
+    /// マップされている領域の表示
+    ///
+    /// コアファイルでは、マップされていたファイルのみ表示する
+    fn show_mappings(&self) {
+        let mappings = match self.target.load_mappings() {
+            Ok(m) => m,
+            Err(e) => {
+                println!("cannot read mappings: {}", e);
+                return;
+            }
+        };
+        self.paged(|out| {
+            writeln!(
+                out,
+                "{:>18} {:>18} {:>10} {:>10} {:<5} objfile",
+                "Start Addr", "End Addr", "Size", "Offset", "Perms"
+            )?;
+            mappings.iter().try_for_each(|m| {
+                writeln!(
+                    out,
+                    "{:>#18x} {:>#18x} {:>#10x} {:>#10x} {:<5} {}",
+                    m.start,
+                    m.end,
+                    m.end - m.start,
+                    m.offset,
+                    m.perms,
+                    m.path
+                )
+            })
+        });
+    }
+
+    /// DWARFレジスタ番号順のレジスタ値へ変換
+    fn to_dwarf_regs(regs: &libc::user_regs_struct) -> [u64; 17] {
+        [
+            regs.rax, regs.rdx, regs.rcx, regs.rbx, regs.rsi, regs.rdi, regs.rbp, regs.rsp,
+            regs.r8, regs.r9, regs.r10, regs.r11, regs.r12, regs.r13, regs.r14, regs.r15, regs.rip,
+        ]
+    }
+
+    /// DWARFのレジスタ番号(0〜16)に対応するレジスタ
+    fn dwarf_reg_mut(regs: &mut libc::user_regs_struct, no: usize) -> Option<&mut u64> {
+        Some(match no {
+            0 => &mut regs.rax,
+            1 => &mut regs.rdx,
+            2 => &mut regs.rcx,
+            3 => &mut regs.rbx,
+            4 => &mut regs.rsi,
+            5 => &mut regs.rdi,
+            6 => &mut regs.rbp,
+            7 => &mut regs.rsp,
+            8 => &mut regs.r8,
+            9 => &mut regs.r9,
+            10 => &mut regs.r10,
+            11 => &mut regs.r11,
+            12 => &mut regs.r12,
+            13 => &mut regs.r13,
+            14 => &mut regs.r14,
+            15 => &mut regs.r15,
+            16 => &mut regs.rip,
+            _ => return None,
+        })
     }
 
     /// レジスタ書き込み
@@ -494,7 +4443,19 @@ impl<'a> Debugger<'a> {
 
     /// メモリ読み込み
     fn read_mem<T: AddressTrait>(&self, addr: &T) -> u64 {
-        read(self.pid, addr.get() as AddressType).expect("ptrace::read is failed") as u64
+        let data = self.read_bytes(addr, 8);
+        u64::from_le_bytes(data.try_into().unwrap())
+    }
+
+    /// 指定バイト数分のメモリ読み込み
+    fn read_bytes<T: AddressTrait>(&self, addr: &T, len: usize) -> Vec<u8> {
+        self.try_read_bytes(addr, len)
+            .expect("ptrace::read is failed")
+    }
+
+    /// 指定バイト数分のメモリ読み込み(読み込めないアドレスはエラー)
+    fn try_read_bytes<T: AddressTrait>(&self, addr: &T, len: usize) -> nix::Result<Vec<u8>> {
+        self.target.read_bytes(addr.get() as u64, len)
     }
 
     /// メモリ書き込み
@@ -508,16 +4469,106 @@ impl<'a> Debugger<'a> {
     /// ヘルプ表示
     fn help(&self) {
         println!("******************************************************************************");
+        println!("program: {} {}", self.program, format_args(&self.args));
         println!("b [symbol name]                 : breakpoint at symbol (ex b main)");
+        println!("b [file]:[line]                 : breakpoint at first statement of line (ex b test.cpp:20)");
+        println!("b --choose [file]:[line]        : choose statements of line to break at (ex b --choose test.cpp:20)");
+        println!("b '[file]'::[function]          : breakpoint at function in file (ex b 'test.cpp'::helper)");
+        println!(
+            "b *[addr]                       : breakpoint at runtime address (ex b *0x401136)"
+        );
+        println!("b --rel [addr]                  : breakpoint at file address, load address is added (ex b --rel 0x1136)");
+        println!("watch [variable]                : stop when the variable is written (up to 4, ex watch g_counter)");
+        println!("rwatch [variable]               : stop when the variable is read or written (ex rwatch g_config)");
+        println!("info watch                      : show watchpoints");
+        println!("dwatch [no]                     : delete watchpoint (ex dwatch 0)");
+        println!("ignore [no] [count]             : do not stop at breakpoint for the next count hits (ex ignore 0 9999)");
+        println!("tb [location]                   : breakpoint deleted after the first stop (ex tb main)");
+        println!("b [location] if [reg]==[value]  : stop only when register matches (==, !=, <, <=, >, >=) (ex b fact if rdi==0x1)");
         println!("d [no]                          : delete breakpoint (ex b 1)");
-        println!("bl                              : show breakpoints");
+        println!("d * | delete all                : delete all breakpoints");
+        println!(
+            "clear [symbol]                  : delete breakpoints set at symbol (ex clear main)"
+        );
+        println!("bl                              : show breakpoints and hits");
+        println!("info break [no]                 : show breakpoint address, file address and hits (ex info break 0)");
         println!("info regs                       : show registers");
-        println!("info debugsec                   : show debug section(.debug_info)");
-        println!("c                               : continue program");
-        println!("s                               : step-in");
-        println!("p [symbol name]                 : show symbol variable (ex p global_variable)");
+        println!("info fpregs                     : show st0-st7, mxcsr and xmm0-xmm15 (raw and as two f64)");
+        println!("set fpregs [xmm] [hex]          : set 128-bit value to xmm register (ex set fpregs xmm0 0x3ff0000000000000)");
+        println!("info threads                    : show threads with name, state and frame (* is the traced thread)");
+        println!("info proc mappings | maps       : show mapped memory regions with permissions, re-read on each call (mapped files for a core file)");
+        println!("info debugsec info [no] [--raw] : show .debug_info of compile units (ex info debugsec info 0)");
+        println!("info debugsec abbrev [offset]   : show .debug_abbrev tables, --raw for codes (ex info debugsec abbrev 0x0)");
+        println!("info debugsec line [no] [--raw] : show .debug_line of compile units (ex info debugsec line 0)");
+        println!("info sharedlibrary | shared     : show mapped shared libraries with base address and whether symbols are read");
+        println!("info files                      : show entry point, load bias and sections of the program");
+        println!("info variables [regex]          : show global/static variables matching regex, with section (ex info variables ^g_)");
+        println!("info functions [regex]          : show functions matching regex, with address and size of non-debugging symbols (ex info functions ^ns::)");
+        println!("info locals                     : show local variables in current scope");
+        println!(
+            "info args                       : show arguments of current function with their types"
+        );
+        println!("info line                       : show source line of current address");
+        println!("list [function] | l             : show source lines around current line or function (ex list fact)");
+        println!(
+            "info lines [file]               : show line table of file (ex info lines test.cpp)"
+        );
+        println!("info cu [no]                    : show compile units (ex info cu 0)");
+        println!("sources [regex]                 : show source files in the debug info, (not found) if missing on disk (ex sources \\.h$)");
+        println!("dump section [name] [file]      : hexdump section or write it to file (ex dump section .rodata)");
+        println!("disas                           : disassemble instructions from the current address (=> marks rip)");
+        println!("disas [function]                : disassemble whole function with func+offset (ex disas main)");
+        println!("x [count] [addr|symbol]         : show count 8-byte words of memory (ex x 4 0x7ffff7a00000, x 4 g_buffer)");
+        println!("dump memory [addr] [len] [file] : hexdump memory or write it to file (ex dump memory 0x1000 64 buf.bin)");
+        println!("dump binary memory [file] [start] [end] : write raw memory to file (ex dump binary memory buf.bin 0x1000 0x1040)");
+        println!("restore [file] [addr]           : write file contents back to memory (ex restore buf.bin 0x1000)");
+        println!("catch load [regex]              : stop when a matching shared library is loaded (ex catch load plugin)");
+        println!(
+            "catch unload [regex]            : stop when a matching shared library is unloaded"
+        );
+        println!("starti                          : run to the program entry point (_start) before static initializers and main");
+        println!("finish                          : run until the current function returns, show the caller and rax");
+        println!("until [location]                : run to location without a breakpoint, or past the current line (ex until test.cpp:30)");
+        println!("info catch                      : show catchpoints");
+        println!("info environ [name]             : show environment variables of the program (ex info environ PATH)");
+        println!("d catch [no]                    : delete catchpoint (ex d catch 0)");
+        println!(
+            "checkpoint                      : save registers and writable memory of the program"
+        );
+        println!("restart [no]                    : rewind the program to checkpoint, files and sockets are not rewound (ex restart 0)");
+        println!("info checkpoints                : show checkpoints");
+        println!("profile on [no...]              : count hits of breakpoints without stopping, no number for all (ex profile on 0 2)");
+        println!(
+            "profile off                     : stop counting hits, the breakpoints stop again"
+        );
+        println!("profile report                  : show hits per symbol and hits per second of run time (also shown on exit)");
+        println!("profile reset                   : clear hits and run time");
+        println!("d checkpoint [no]               : delete checkpoint (ex d checkpoint 0)");
+        println!("symbol-file [file]              : read symbols from file for a stripped program, no file discards symbols (ex symbol-file a.out.debug)");
+        println!("define [name]                   : define a command from following lines until end, $arg0..$arg9 and $argc are replaced (ex define dumpstate)");
+        println!("info user-commands              : show user-defined commands");
+        println!("save user-commands [file]       : write user-defined commands to file (ex save user-commands cmds.txt)");
+        println!("source [file]                   : run commands in file (ex source cmds.txt)");
+        println!("load debug-info now             : parse all debug info now (parsed on demand by default)");
+        println!("bt                              : show backtrace(includes inlined frames), callers by walking the frame pointer chain");
+        println!("set backtrace limit [count]     : max frames walked by bt (ex set backtrace limit 100)");
+        println!("frame [no]                      : select frame for info locals (ex frame 1)");
+        println!("c                               : continue program (delivers the signal that stopped it)");
+        println!("run | r                         : start the program again, also after it exited (breakpoints are kept)");
+        println!("show args                       : show arguments given to the program (r-debugger dbg prog -- arg...)");
+        println!("record on [size]                : record executed addresses while stepping, c steps to breakpoint (ex record on 1000)");
+        println!("record off                      : stop recording");
+        println!("record log [count]              : show recent recorded addresses grouped by function (ex record log 100)");
+        println!("s                               : step-in (delivers the signal that stopped it)");
+        println!("p [symbol name]                 : show symbol variable, struct member with . (ex p global_variable, p global_struct.member)");
+        println!("p '[file]'::[symbol name]       : show static variable in file (ex p 'test.cpp'::global_variable)");
         println!("set regs [register] [value]     : write registers (ex set regs rax 0x1000)");
-        println!("set var [variable name] [value] : write variable (ex set var g_var 0x1000)");
+        println!("set var [variable name] [value] : write variable in its type size, decimal or 0x hex (ex set var g_var 0x1000, set var n -1)");
+        println!("set print elements [count]      : max array elements to print (ex set print elements 20)");
+        println!("set height [lines]              : lines per page of long listings, 0 disables paging (ex set height 40)");
+        println!("set follow-exec-mode [mode]     : stop or continue when the program execs another binary (ex set follow-exec-mode stop)");
+        println!("set prompt-format [format]      : compact (func+off file:line), verbose (rip, full path) or raw (rip only) prompt");
+        println!("detach                          : remove breakpoints and quit, the program keeps running");
         println!("quit                            : quit program");
         println!("******************************************************************************");
     }
@@ -529,3 +4580,146 @@ impl<'a> Debugger<'a> {
         addr - self.entry
     }
 }
+
+/// プログラムの終了後も使えるコマンド(プロセスを読み書きしない)か
+fn available_after_exit(coms: &[String]) -> bool {
+    match &*coms[0] {
+        "run" | "r" | "quit" | "h" | "bl" => true,
+        "show" => matches!(coms.get(1).map(|s| s.as_str()), Some("args")),
+        "info" => matches!(coms.get(1).map(|s| s.as_str()), Some("break" | "watch")),
+        _ => false,
+    }
+}
+
+/// プロセスを実行・変更するコマンド(コアファイルでは使えない)か
+fn needs_process(coms: &[String]) -> bool {
+    match &*coms[0] {
+        "c" | "s" | "starti" | "until" | "finish" | "b" | "tb" | "watch" | "rwatch" | "dwatch"
+        | "restore" | "record" | "catch" | "checkpoint" | "restart" | "profile" | "run" | "r"
+        | "detach" => true,
+        "set" => matches!(
+            coms.get(1).map(|s| s.as_str()),
+            Some("var" | "regs" | "fpregs")
+        ),
+        "info" => matches!(coms.get(1).map(|s| s.as_str()), Some("threads")),
+        _ => false,
+    }
+}
+
+/// ウォッチポイントの表示名(watchpoint 0・access (read/write) watchpoint 0)
+fn watch_title(kind: WatchKind, no: usize) -> String {
+    match kind {
+        WatchKind::Write => format!("watchpoint {}", no),
+        WatchKind::Access => format!("access ({}) watchpoint {}", kind, no),
+    }
+}
+
+/// 確認(y or n)
+///
+/// 標準入力が端末でなければ、yesとする
+fn confirm(question: &str) -> bool {
+    if !io::stdin().is_terminal() {
+        println!(
+            "{} (y or n) [answered Y; input not from terminal]",
+            question
+        );
+        return true;
+    }
+    print!("{} (y or n) ", question);
+    io::stdout().flush().unwrap();
+    let mut s = String::new();
+    std::io::stdin().read_line(&mut s).ok();
+    matches!(s.trim(), "y" | "yes")
+}
+
+/// 数値を解析(0x始まりは16進数、それ以外は10進数)
+fn parse_num(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(h) => u64::from_str_radix(h, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// バイト列を16進数の文字列に変換
+fn format_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 'file'::name形式のシンボルを、ファイル名とシンボル名に分割
+fn split_scope(sym: &str) -> (Option<&str>, &str) {
+    match sym.strip_prefix('\'').and_then(|s| s.split_once("'::")) {
+        Some((file, name)) => (Some(file), name),
+        None => (None, sym),
+    }
+}
+
+/// 候補から選択させる
+///
+/// 選択された候補のインデックスを返却する(allow_allであれば、0ですべてを選択)
+/// 標準入力が端末でない(スクリプト実行の)場合は、選択できないのでエラーとする
+fn choose(title: &str, items: &[String], allow_all: bool) -> Option<Vec<usize>> {
+    println!("{}", title);
+    if !io::stdin().is_terminal() {
+        println!("error: ambiguous in batch mode");
+        return None;
+    }
+    if allow_all {
+        println!("[0] all");
+    }
+    for (i, item) in items.iter().enumerate() {
+        println!("[{}] {}", i + 1, item);
+    }
+    print!("> ");
+    io::stdout().flush().unwrap();
+
+    let mut s = String::new();
+    std::io::stdin().read_line(&mut s).ok();
+    match s.trim().parse::<usize>() {
+        Ok(0) if allow_all => Some((0..items.len()).collect()),
+        Ok(n) if 0 < n && n <= items.len() => Some(vec![n - 1]),
+        _ => {
+            println!("canceled");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_breakpoint_targets() {
+        let mut list = BreakpointList::new();
+        list.register("fact", AdrFromAbs::new(0x1000), 0x90);
+
+        // 記録中でなければ(停止後の貼り直し等)、対象を記録しない
+        list.register("fact", AdrFromAbs::new(0x1000), 0x90);
+        assert!(list.take_targets().is_empty());
+
+        // tb *addr: 登録済みのアドレスは、登録済みのブレイクポイントを対象とする
+        list.start_targets();
+        list.mark_target(&AdrFromAbs::new(0x1000));
+        let targets = list.take_targets();
+        assert_eq!(vec![0], targets);
+        list.set_temporary(&targets);
+        assert!(list.get()[0].temporary);
+        assert_eq!(1, list.get().len());
+
+        // 新規・登録済みの両方を対象とし、対象のみへ停止条件を設定する
+        list.start_targets();
+        list.register("*0x2000", AdrFromAbs::new(0x2000), 0x90);
+        list.register("fact", AdrFromAbs::new(0x1000), 0x90);
+        list.register("*0x3000", AdrFromAbs::new(0x3000), 0x90);
+        assert_eq!(vec![1, 0, 2], list.take_targets());
+        let condition = Condition::parse("rdi==0x1").unwrap();
+        list.set_condition(&[1], &condition);
+        assert!(list.get()[1].condition.is_some());
+        assert!(list.get()[0].condition.is_none());
+        assert!(!list.get()[1].temporary);
+
+        // 記録を取り出した後は、記録しない
+        list.mark_target(&AdrFromAbs::new(0x1000));
+        assert!(list.take_targets().is_empty());
+    }
+}
@@ -0,0 +1,398 @@
+//! 命令の表示・解析
+//!
+//! 停止した位置の命令をバイト列で表示する(disasは、停止した位置から続く命令・関数全体も表示する)
+//! disasm featureが有効であれば、命令の長さを解析してニーモニックも表示し、
+//! メモリオペランドの実効アドレスや命令の境界も求められる
+
+use crate::memory::{shadow, ReadMemory};
+
+/// x86-64の命令の最大長
+pub const MAX_INST_LEN: usize = 15;
+
+/// disasで解析するバイト数
+pub const DISAS_LEN: usize = 32;
+
+/// ページサイズ(読み込む命令がページをまたぐ場合に使用)
+const PAGE_SIZE: u64 = 0x1000;
+
+/// addrの命令を「=> 0x...: バイト列  ニーモニック」の形式に整形
+///
+/// originalsは、int 3を埋め込んだアドレスと元のバイトで、元の命令として表示する
+/// 読み込めなければNone
+pub fn format_instruction<M: ReadMemory>(
+    mem: &M,
+    addr: u64,
+    originals: &[(u64, u8)],
+) -> Option<String> {
+    let data = read_instruction(mem, addr, originals)?;
+    let (len, mnemonic) = decode(addr, &data);
+    Some(format_line(
+        &format!("=> 0x{:x}", addr),
+        &data[..len],
+        mnemonic,
+    ))
+}
+
+/// addrからlenバイトの範囲で始まる命令を、1命令1行で整形(disas)
+///
+/// 先頭(停止した位置)の命令に矢印を付け、範囲の末尾をまたぐ命令も最後まで表示する
+/// originalsは、int 3を埋め込んだアドレスと元のバイトで、元の命令として表示する
+/// 読み込めなければNone
+pub fn format_instructions<M: ReadMemory>(
+    mem: &M,
+    addr: u64,
+    len: usize,
+    originals: &[(u64, u8)],
+) -> Option<Vec<String>> {
+    let size = len + MAX_INST_LEN;
+    let mut data = mem.read_memory(addr, size).or_else(|| {
+        let len = (PAGE_SIZE - addr % PAGE_SIZE) as usize;
+        mem.read_memory(addr, len.min(size))
+    })?;
+    shadow(addr, &mut data, originals);
+
+    let mut lines = vec![];
+    let mut offset = 0;
+    while offset < len.min(data.len()) {
+        let a = addr + offset as u64;
+        let (n, mnemonic) = decode(a, &data[offset..]);
+        let marker = if 0 == offset { "=>" } else { "  " };
+        let head = format!("{} 0x{:x}", marker, a);
+        lines.push(format_line(&head, &data[offset..offset + n], mnemonic));
+        offset += n;
+    }
+    Some(lines)
+}
+
+/// 関数全体の命令を、1命令1行で関数+オフセットを付けて整形(disas 関数)
+///
+/// startからsizeバイトを関数の範囲とし、ripの命令に矢印を付ける
+/// originalsは、int 3を埋め込んだアドレスと元のバイトで、元の命令として表示する
+/// 読み込めなければNone
+pub fn format_function<M: ReadMemory>(
+    mem: &M,
+    name: &str,
+    start: u64,
+    size: usize,
+    rip: u64,
+    originals: &[(u64, u8)],
+) -> Option<Vec<String>> {
+    let mut data = mem.read_memory(start, size)?;
+    shadow(start, &mut data, originals);
+
+    let mut lines = vec![];
+    let mut offset = 0;
+    while offset < data.len() {
+        let a = start + offset as u64;
+        let (n, mnemonic) = decode(a, &data[offset..]);
+        let marker = if rip == a { "=>" } else { "  " };
+        let head = format!("{} 0x{:x} <{}+0x{:x}>", marker, a, name, offset);
+        lines.push(format_line(&head, &data[offset..offset + n], mnemonic));
+        offset += n;
+    }
+    Some(lines)
+}
+
+/// 命令を「=> 0x...: バイト列  ニーモニック」の形式に整形(headは「=> 0x...」の部分)
+fn format_line(head: &str, data: &[u8], mnemonic: Option<String>) -> String {
+    let bytes = data
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<String>>()
+        .join(" ");
+    match mnemonic {
+        Some(m) => format!("{}: {}  {}", head, bytes, m),
+        None => format!("{}: {}", head, bytes),
+    }
+}
+
+/// addrから命令の最大長のバイト列を読み込む
+///
+/// 次のページが読み込めない場合は、ページ境界までとする
+/// originalsは、int 3を埋め込んだアドレスと元のバイトで、元の命令に戻す
+pub fn read_instruction<M: ReadMemory>(
+    mem: &M,
+    addr: u64,
+    originals: &[(u64, u8)],
+) -> Option<Vec<u8>> {
+    let mut data = mem.read_memory(addr, MAX_INST_LEN).or_else(|| {
+        let len = (PAGE_SIZE - addr % PAGE_SIZE) as usize;
+        mem.read_memory(addr, len.min(MAX_INST_LEN))
+    })?;
+    shadow(addr, &mut data, originals);
+    Some(data)
+}
+
+/// 命令を解析し、命令の長さとニーモニックを返す
+#[cfg(feature = "disasm")]
+fn decode(addr: u64, data: &[u8]) -> (usize, Option<String>) {
+    use iced_x86::{Decoder, DecoderOptions, Formatter, IntelFormatter};
+
+    let inst = Decoder::with_ip(64, data, addr, DecoderOptions::NONE).decode();
+    if inst.is_invalid() {
+        return (data.len(), Some("(bad)".to_string()));
+    }
+    let mut formatter = IntelFormatter::new();
+    formatter
+        .options_mut()
+        .set_space_after_operand_separator(true);
+    let mut s = String::new();
+    formatter.format(&inst, &mut s);
+    (inst.len(), Some(s))
+}
+
+/// 命令を解析できないため、読み込んだバイト列をすべて表示する
+#[cfg(not(feature = "disasm"))]
+fn decode(_addr: u64, data: &[u8]) -> (usize, Option<String>) {
+    (data.len(), None)
+}
+
+/// 命令のメモリオペランドの実効アドレスとサイズを求める
+///
+/// メモリオペランドがない・解析できない場合はNone
+#[cfg(feature = "disasm")]
+pub fn memory_operand(
+    addr: u64,
+    data: &[u8],
+    regs: &libc::user_regs_struct,
+) -> Option<(u64, usize)> {
+    use iced_x86::{Decoder, DecoderOptions, OpKind};
+
+    let inst = Decoder::with_ip(64, data, addr, DecoderOptions::NONE).decode();
+    let op = (0..inst.op_count()).find(|i| OpKind::Memory == inst.op_kind(*i))?;
+    let ea = inst.virtual_address(op, 0, |reg, _, _| register_value(regs, reg))?;
+    Some((ea, inst.memory_size().size()))
+}
+
+#[cfg(not(feature = "disasm"))]
+pub fn memory_operand(
+    _addr: u64,
+    _data: &[u8],
+    _regs: &libc::user_regs_struct,
+) -> Option<(u64, usize)> {
+    None
+}
+
+/// 関数の先頭から命令を順に解析し、addrを含む命令の先頭アドレスを求める
+///
+/// addrが命令の境界であればaddr自身となる
+/// codeはstartからの関数の命令列で、範囲外・解析できない場合はNone
+#[cfg(feature = "disasm")]
+pub fn instruction_start(start: u64, code: &[u8], addr: u64) -> Option<u64> {
+    use iced_x86::{Decoder, DecoderOptions};
+
+    if addr < start || start + code.len() as u64 <= addr {
+        return None;
+    }
+    Decoder::with_ip(64, code, start, DecoderOptions::NONE)
+        .into_iter()
+        .map(|inst| inst.ip())
+        .take_while(|ip| *ip <= addr)
+        .last()
+}
+
+#[cfg(not(feature = "disasm"))]
+pub fn instruction_start(_start: u64, _code: &[u8], _addr: u64) -> Option<u64> {
+    None
+}
+
+/// レジスタの値を取得(実効アドレスの計算用)
+#[cfg(feature = "disasm")]
+fn register_value(regs: &libc::user_regs_struct, reg: iced_x86::Register) -> Option<u64> {
+    use iced_x86::Register;
+
+    let val = match reg.full_register() {
+        Register::RAX => regs.rax,
+        Register::RBX => regs.rbx,
+        Register::RCX => regs.rcx,
+        Register::RDX => regs.rdx,
+        Register::RSI => regs.rsi,
+        Register::RDI => regs.rdi,
+        Register::RBP => regs.rbp,
+        Register::RSP => regs.rsp,
+        Register::R8 => regs.r8,
+        Register::R9 => regs.r9,
+        Register::R10 => regs.r10,
+        Register::R11 => regs.r11,
+        Register::R12 => regs.r12,
+        Register::R13 => regs.r13,
+        Register::R14 => regs.r14,
+        Register::R15 => regs.r15,
+        Register::RIP => regs.rip,
+        // セグメントは、FS・GSのみベースアドレスを持つ
+        Register::FS => regs.fs_base,
+        Register::GS => regs.gs_base,
+        Register::ES | Register::CS | Register::SS | Register::DS => 0,
+        _ => return None,
+    };
+    Some(match reg.size() {
+        1 => val & 0xFF,
+        2 if !reg.is_segment_register() => val & 0xFFFF,
+        4 => val & 0xFFFF_FFFF,
+        _ => val,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::FakeMemory;
+
+    #[test]
+    fn test_format_instruction() {
+        // push rbp; mov rbp, rsp; ...(ページの末尾まで)
+        let mut data = vec![0x55, 0x48, 0x89, 0xE5, 0x48, 0x83, 0xEC, 0x10];
+        data.resize(0x20, 0x90);
+        let addr = 0x5555_5555_5000 - 0x20;
+        let mem = FakeMemory::new(addr, data);
+        let cases = vec![
+            // ブレイクポイントのint 3は、元の命令として表示する
+            (addr, vec![(addr, 0x55)], "55", "push rbp"),
+            (addr + 1, vec![], "48 89 e5", "mov rbp, rsp"),
+            (addr + 1, vec![(addr + 1, 0x48)], "48 89 e5", "mov rbp, rsp"),
+            (addr + 4, vec![], "48 83 ec 10", "sub rsp, 10h"),
+            // ページ境界まで読み込む
+            (addr + 0x1F, vec![], "90", "nop"),
+        ];
+        for (a, originals, bytes, mnemonic) in cases {
+            let mut expected = format!("=> 0x{:x}: {}  {}", a, bytes, mnemonic);
+            if !cfg!(feature = "disasm") {
+                // 命令の長さが分からないため、読み込んだバイト列をすべて表示する
+                let len = (addr + 0x20 - a).min(MAX_INST_LEN as u64) as usize;
+                let mut data = mem.read_memory(a, len).unwrap();
+                shadow(a, &mut data, &originals);
+                let bytes = data.iter().map(|b| format!("{:02x}", b));
+                expected = format!("=> 0x{:x}: {}", a, bytes.collect::<Vec<_>>().join(" "));
+            }
+            assert_eq!(Some(expected), format_instruction(&mem, a, &originals));
+        }
+        assert_eq!(None, format_instruction(&mem, 0x1000, &[]));
+    }
+
+    #[test]
+    fn test_format_instructions() {
+        // push rbp; mov rbp, rsp(int 3を埋め込み済み); sub rsp, 0x10; nop...(ページの末尾まで)
+        let mut data = vec![0x55, 0xCC, 0x89, 0xE5, 0x48, 0x83, 0xEC, 0x10];
+        data.resize(0x20, 0x90);
+        let addr = 0x5555_5555_5000 - 0x20;
+        let mem = FakeMemory::new(addr, data);
+        let originals = [(addr + 1, 0x48)];
+        if cfg!(feature = "disasm") {
+            let cases = vec![
+                // 範囲の末尾をまたぐ命令も表示する
+                (
+                    5,
+                    vec![
+                        format!("=> 0x{:x}: 55  push rbp", addr),
+                        format!("   0x{:x}: 48 89 e5  mov rbp, rsp", addr + 1),
+                        format!("   0x{:x}: 48 83 ec 10  sub rsp, 10h", addr + 4),
+                    ],
+                ),
+                (
+                    DISAS_LEN,
+                    (0..0x20 - 8)
+                        .map(|i| format!("   0x{:x}: 90  nop", addr + 8 + i))
+                        .collect(),
+                ),
+            ];
+            for (len, expected) in cases {
+                let lines = format_instructions(&mem, addr, len, &originals).unwrap();
+                assert_eq!(expected, lines[lines.len() - expected.len()..], "{}", len);
+            }
+        } else {
+            // 命令の長さが分からないため、読み込んだバイト列をすべて1行で表示する
+            let lines = format_instructions(&mem, addr, 5, &originals).unwrap();
+            assert_eq!(1, lines.len());
+            assert!(lines[0].starts_with(&format!("=> 0x{:x}: 55 48 89 e5", addr)));
+        }
+        assert_eq!(None, format_instructions(&mem, 0x1000, DISAS_LEN, &[]));
+    }
+
+    #[test]
+    fn test_format_function() {
+        // push rbp; mov rbp, rsp(int 3を埋め込み済み); sub rsp, 0x10; leave; ret
+        let data = vec![0x55, 0xCC, 0x89, 0xE5, 0x48, 0x83, 0xEC, 0x10, 0xC9, 0xC3];
+        let mem = FakeMemory::new(0x1000, data);
+        let originals = [(0x1001, 0x48)];
+        let lines = format_function(&mem, "main", 0x1000, 10, 0x1004, &originals).unwrap();
+        if cfg!(feature = "disasm") {
+            assert_eq!(
+                vec![
+                    "   0x1000 <main+0x0>: 55  push rbp",
+                    "   0x1001 <main+0x1>: 48 89 e5  mov rbp, rsp",
+                    "=> 0x1004 <main+0x4>: 48 83 ec 10  sub rsp, 10h",
+                    "   0x1008 <main+0x8>: c9  leave",
+                    "   0x1009 <main+0x9>: c3  ret",
+                ],
+                lines
+            );
+        } else {
+            assert_eq!(
+                vec!["   0x1000 <main+0x0>: 55 48 89 e5 48 83 ec 10 c9 c3"],
+                lines
+            );
+        }
+        // 関数の範囲を読み込めなければNone
+        assert_eq!(
+            None,
+            format_function(&mem, "main", 0x1000, 11, 0x1000, &originals)
+        );
+    }
+
+    #[cfg(feature = "disasm")]
+    #[test]
+    fn test_memory_operand() {
+        let mut regs: libc::user_regs_struct = unsafe { std::mem::zeroed() };
+        regs.rax = 0x7fff_0001;
+        regs.rbx = 0x10;
+        regs.fs_base = 0x7f00_0000_0000;
+        let cases: Vec<(Vec<u8>, _)> = vec![
+            // lock add qword ptr [rax], 1
+            (vec![0xF0, 0x48, 0x83, 0x00, 0x01], Some((0x7fff_0001, 8))),
+            // mov eax, dword ptr [rax+rbx*4+8]
+            (vec![0x8B, 0x44, 0x98, 0x08], Some((0x7fff_0001 + 0x48, 4))),
+            // mov rax, qword ptr fs:[0x28]
+            (
+                vec![0x64, 0x48, 0x8B, 0x04, 0x25, 0x28, 0x00, 0x00, 0x00],
+                Some((0x7f00_0000_0028, 8)),
+            ),
+            // mov rax, qword ptr [rip+0x10](命令の次のアドレスから)
+            (
+                vec![0x48, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00],
+                Some((0x1000 + 7 + 0x10, 8)),
+            ),
+            // push rbp(メモリオペランドなし)
+            (vec![0x55], None),
+            // ud2
+            (vec![0x0F, 0x0B], None),
+        ];
+        for (data, expected) in cases {
+            assert_eq!(
+                expected,
+                memory_operand(0x1000, &data, &regs),
+                "{:x?}",
+                data
+            );
+        }
+    }
+
+    #[cfg(feature = "disasm")]
+    #[test]
+    fn test_instruction_start() {
+        // push rbp; mov eax, 0x0b0f0b0f; ret
+        let code = [0x55, 0xB8, 0x0F, 0x0B, 0x0F, 0x0B, 0xC3];
+        let cases = vec![
+            (0x1000, Some(0x1000)),
+            (0x1001, Some(0x1001)),
+            // mov eax, imm32の即値の途中
+            (0x1002, Some(0x1001)),
+            (0x1005, Some(0x1001)),
+            (0x1006, Some(0x1006)),
+            (0x1007, None),
+            (0x0FFF, None),
+        ];
+        for (addr, expected) in cases {
+            assert_eq!(expected, instruction_start(0x1000, &code, addr));
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! 時間の解析
+//!
+//! 10ms・1.5s・250usのような、単位付きの時間を解析する
+
+use std::time::Duration;
+
+/// 単位と、1単位あたりのナノ秒
+const UNITS: &[(&str, f64)] = &[
+    ("ns", 1.0),
+    ("us", 1_000.0),
+    ("ms", 1_000_000.0),
+    ("s", 1_000_000_000.0),
+];
+
+/// 時間を解析
+///
+/// 単位はns・us・ms・s(省略した場合は秒)で、小数も指定できる
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration: {}", s);
+    let (val, scale) = UNITS
+        .iter()
+        .find_map(|(unit, scale)| s.strip_suffix(unit).map(|v| (v, *scale)))
+        .unwrap_or((s, 1_000_000_000.0));
+    let nanos = val
+        .parse::<f64>()
+        .map_err(|_| invalid())
+        .map(|v| (v * scale).round())?;
+    if !nanos.is_finite() || nanos < 0.0 || (u64::MAX as f64) <= nanos {
+        return Err(invalid());
+    }
+    Ok(Duration::from_nanos(nanos as u64))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        let cases = vec![
+            ("10ms", Ok(Duration::from_millis(10))),
+            ("1.5s", Ok(Duration::from_millis(1500))),
+            ("250us", Ok(Duration::from_micros(250))),
+            ("100ns", Ok(Duration::from_nanos(100))),
+            ("0.1us", Ok(Duration::from_nanos(100))),
+            ("2", Ok(Duration::from_secs(2))),
+            ("0ms", Ok(Duration::from_secs(0))),
+            ("", Err("invalid duration: ".to_string())),
+            ("ms", Err("invalid duration: ms".to_string())),
+            ("10m", Err("invalid duration: 10m".to_string())),
+            ("-1ms", Err("invalid duration: -1ms".to_string())),
+            ("infs", Err("invalid duration: infs".to_string())),
+            ("1e30s", Err("invalid duration: 1e30s".to_string())),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(expected, parse_duration(s), "{}", s);
+        }
+    }
+}
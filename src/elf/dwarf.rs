@@ -1,11 +1,70 @@
+use std::cell::{OnceCell, RefCell};
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryInto;
+use std::fmt;
 use std::fs::File;
-use std::io::{BufReader, Error, ErrorKind, Read, Result, Seek, SeekFrom};
+use std::io::{BufReader, Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::rc::Rc;
 
-use crate::elf::elf64::ElfSecHeader;
-use crate::elf::leb128::ULEB128;
+use crate::elf::elf64::{check_extent, ElfSecHeader};
+use crate::elf::leb128::{SLEB128, ULEB128};
+use crate::elf::type_info::{BitField, MemberInfo, TypeInfo, VariantInfo};
+
+// DW_UT(dwarf5のunit type)
+const DW_UT_TYPE: u8 = 0x02;
+const DW_UT_SKELETON: u8 = 0x04;
+const DW_UT_SPLIT_COMPILE: u8 = 0x05;
+const DW_UT_SPLIT_TYPE: u8 = 0x06;
+
+// DW_RLE(dwarf5のアドレス範囲リストのエントリー種別)
+const DW_RLE_END_OF_LIST: u8 = 0x00;
+const DW_RLE_BASE_ADDRESSX: u8 = 0x01;
+const DW_RLE_STARTX_ENDX: u8 = 0x02;
+const DW_RLE_STARTX_LENGTH: u8 = 0x03;
+const DW_RLE_OFFSET_PAIR: u8 = 0x04;
+const DW_RLE_BASE_ADDRESS: u8 = 0x05;
+const DW_RLE_START_END: u8 = 0x06;
+const DW_RLE_START_LENGTH: u8 = 0x07;
+
+/// DW_LLE(ロケーションリストのエントリー種別, dwarf5)
+const DW_LLE_END_OF_LIST: u8 = 0x00;
+const DW_LLE_BASE_ADDRESSX: u8 = 0x01;
+const DW_LLE_STARTX_ENDX: u8 = 0x02;
+const DW_LLE_STARTX_LENGTH: u8 = 0x03;
+const DW_LLE_OFFSET_PAIR: u8 = 0x04;
+const DW_LLE_DEFAULT_LOCATION: u8 = 0x05;
+const DW_LLE_BASE_ADDRESS: u8 = 0x06;
+const DW_LLE_START_END: u8 = 0x07;
+const DW_LLE_START_LENGTH: u8 = 0x08;
+
+/// 型情報を辿る際の最大深さ
+const MAX_TYPE_DEPTH: u32 = 16;
+
+// DW_OP_addr(アドレス即値をスタックへ積む)
+const DW_OP_ADDR: u8 = 0x03;
+
+// 行番号プログラムヘッダーのエントリー内容(dwarf5)
+const DW_LNCT_DIRECTORY_INDEX: u64 = 0x2;
+const DW_LNCT_TIMESTAMP: u64 = 0x3;
+const DW_LNCT_SIZE: u64 = 0x4;
+
+// 行番号プログラムの標準オペコード
+const DW_LNS_COPY: u8 = 0x01;
+const DW_LNS_ADVANCE_PC: u8 = 0x02;
+const DW_LNS_ADVANCE_LINE: u8 = 0x03;
+const DW_LNS_SET_FILE: u8 = 0x04;
+const DW_LNS_SET_COLUMN: u8 = 0x05;
+const DW_LNS_NEGATE_STMT: u8 = 0x06;
+const DW_LNS_CONST_ADD_PC: u8 = 0x08;
+const DW_LNS_FIXED_ADVANCE_PC: u8 = 0x09;
+
+// 行番号プログラムの拡張オペコード
+const DW_LNE_END_SEQUENCE: u8 = 0x01;
+const DW_LNE_SET_ADDRESS: u8 = 0x02;
 
 /// DW_TAG情報
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 enum DwTagInfo {
     Unknown, // 不明
     ArrayType,
@@ -175,11 +234,41 @@ enum DwAtInfo {
     ConstExpr,
     EnumClass,
     LinkageName,
+    // dwarf5
+    StringLengthBitSize,
+    StringLengthByteSize,
+    Rank,
+    StrOffsetsBase,
+    AddrBase,
+    RnglistsBase,
+    DwoName,
+    Reference,
+    RvalueReference,
+    Macros,
+    CallAllCalls,
+    CallAllSourceCalls,
+    CallAllTailCalls,
+    CallReturnPc,
+    CallValue,
+    CallOrigin,
+    CallParameter,
+    CallPc,
+    CallTailCall,
+    CallTarget,
+    CallTargetClobbered,
+    CallDataLocation,
+    CallDataValue,
+    Noreturn,
+    Alignment,
+    ExportSymbols,
+    Deleted,
+    Defaulted,
+    LoclistsBase,
     End, // 終了attr
 }
 
 /// DW_FORM情報
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 enum DwFormInfo {
     Unknown(u64), // 不明
     Addr,
@@ -208,9 +297,138 @@ enum DwFormInfo {
     Exprloc,
     FlagPresent,
     RefSig8,
+    // dwarf5
+    Strx,
+    Addrx,
+    RefSup4,
+    StrpSup,
+    Data16,
+    LineStrp,
+    ImplicitConst,
+    Loclistx,
+    Rnglistx,
+    RefSup8,
+    Strx1,
+    Strx2,
+    Strx3,
+    Strx4,
+    Addrx1,
+    Addrx2,
+    Addrx3,
+    Addrx4,
     End, // 終了form
 }
 
+/// DW_LANG情報
+#[derive(Debug, PartialEq)]
+enum DwLangInfo {
+    Unknown(u64), // 不明
+    C89,
+    C,
+    Ada83,
+    CPlusPlus,
+    Cobol74,
+    Cobol85,
+    Fortran77,
+    Fortran90,
+    Pascal83,
+    Modula2,
+    Java,
+    C99,
+    Ada95,
+    Fortran95,
+    Pli,
+    ObjC,
+    ObjCPlusPlus,
+    Upc,
+    D,
+    Python,
+    OpenCl,
+    Go,
+    Modula3,
+    Haskell,
+    CPlusPlus03,
+    CPlusPlus11,
+    OCaml,
+    Rust,
+    C11,
+    Swift,
+    Julia,
+    Dylan,
+    CPlusPlus14,
+    Fortran03,
+    Fortran08,
+    RenderScript,
+    Bliss,
+    Kotlin,
+    Zig,
+    Crystal,
+    CPlusPlus17,
+    CPlusPlus20,
+    C17,
+    Fortran18,
+    Ada2005,
+    Ada2012,
+    MipsAssembler,
+}
+
+impl fmt::Display for DwLangInfo {
+    /// 言語名を表示
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            DwLangInfo::Unknown(code) => return write!(f, "unknown(0x{:x})", code),
+            DwLangInfo::C89 => "C89",
+            DwLangInfo::C => "C",
+            DwLangInfo::Ada83 => "Ada83",
+            DwLangInfo::CPlusPlus => "C++",
+            DwLangInfo::Cobol74 => "Cobol74",
+            DwLangInfo::Cobol85 => "Cobol85",
+            DwLangInfo::Fortran77 => "Fortran77",
+            DwLangInfo::Fortran90 => "Fortran90",
+            DwLangInfo::Pascal83 => "Pascal83",
+            DwLangInfo::Modula2 => "Modula2",
+            DwLangInfo::Java => "Java",
+            DwLangInfo::C99 => "C99",
+            DwLangInfo::Ada95 => "Ada95",
+            DwLangInfo::Fortran95 => "Fortran95",
+            DwLangInfo::Pli => "PLI",
+            DwLangInfo::ObjC => "ObjC",
+            DwLangInfo::ObjCPlusPlus => "ObjC++",
+            DwLangInfo::Upc => "UPC",
+            DwLangInfo::D => "D",
+            DwLangInfo::Python => "Python",
+            DwLangInfo::OpenCl => "OpenCL",
+            DwLangInfo::Go => "Go",
+            DwLangInfo::Modula3 => "Modula3",
+            DwLangInfo::Haskell => "Haskell",
+            DwLangInfo::CPlusPlus03 => "C++03",
+            DwLangInfo::CPlusPlus11 => "C++11",
+            DwLangInfo::OCaml => "OCaml",
+            DwLangInfo::Rust => "Rust",
+            DwLangInfo::C11 => "C11",
+            DwLangInfo::Swift => "Swift",
+            DwLangInfo::Julia => "Julia",
+            DwLangInfo::Dylan => "Dylan",
+            DwLangInfo::CPlusPlus14 => "C++14",
+            DwLangInfo::Fortran03 => "Fortran03",
+            DwLangInfo::Fortran08 => "Fortran08",
+            DwLangInfo::RenderScript => "RenderScript",
+            DwLangInfo::Bliss => "BLISS",
+            DwLangInfo::Kotlin => "Kotlin",
+            DwLangInfo::Zig => "Zig",
+            DwLangInfo::Crystal => "Crystal",
+            DwLangInfo::CPlusPlus17 => "C++17",
+            DwLangInfo::CPlusPlus20 => "C++20",
+            DwLangInfo::C17 => "C17",
+            DwLangInfo::Fortran18 => "Fortran18",
+            DwLangInfo::Ada2005 => "Ada2005",
+            DwLangInfo::Ada2012 => "Ada2012",
+            DwLangInfo::MipsAssembler => "Mips_Assembler",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 /// DW情報変換trait
 trait DwInfo {
     /// DW_TAGコンバート
@@ -389,6 +607,35 @@ trait DwInfo {
             0x6C => DwAtInfo::ConstExpr,
             0x6D => DwAtInfo::EnumClass,
             0x6E => DwAtInfo::LinkageName,
+            0x6F => DwAtInfo::StringLengthBitSize,
+            0x70 => DwAtInfo::StringLengthByteSize,
+            0x71 => DwAtInfo::Rank,
+            0x72 => DwAtInfo::StrOffsetsBase,
+            0x73 => DwAtInfo::AddrBase,
+            0x74 => DwAtInfo::RnglistsBase,
+            0x76 => DwAtInfo::DwoName,
+            0x77 => DwAtInfo::Reference,
+            0x78 => DwAtInfo::RvalueReference,
+            0x79 => DwAtInfo::Macros,
+            0x7A => DwAtInfo::CallAllCalls,
+            0x7B => DwAtInfo::CallAllSourceCalls,
+            0x7C => DwAtInfo::CallAllTailCalls,
+            0x7D => DwAtInfo::CallReturnPc,
+            0x7E => DwAtInfo::CallValue,
+            0x7F => DwAtInfo::CallOrigin,
+            0x80 => DwAtInfo::CallParameter,
+            0x81 => DwAtInfo::CallPc,
+            0x82 => DwAtInfo::CallTailCall,
+            0x83 => DwAtInfo::CallTarget,
+            0x84 => DwAtInfo::CallTargetClobbered,
+            0x85 => DwAtInfo::CallDataLocation,
+            0x86 => DwAtInfo::CallDataValue,
+            0x87 => DwAtInfo::Noreturn,
+            0x88 => DwAtInfo::Alignment,
+            0x89 => DwAtInfo::ExportSymbols,
+            0x8A => DwAtInfo::Deleted,
+            0x8B => DwAtInfo::Defaulted,
+            0x8C => DwAtInfo::LoclistsBase,
             _ => DwAtInfo::Unknown,
         }
     }
@@ -421,20 +668,93 @@ trait DwInfo {
             0x17 => DwFormInfo::SecOffset,
             0x18 => DwFormInfo::Exprloc,
             0x19 => DwFormInfo::FlagPresent,
+            0x1A => DwFormInfo::Strx,
+            0x1B => DwFormInfo::Addrx,
+            0x1C => DwFormInfo::RefSup4,
+            0x1D => DwFormInfo::StrpSup,
+            0x1E => DwFormInfo::Data16,
+            0x1F => DwFormInfo::LineStrp,
             0x20 => DwFormInfo::RefSig8,
+            0x21 => DwFormInfo::ImplicitConst,
+            0x22 => DwFormInfo::Loclistx,
+            0x23 => DwFormInfo::Rnglistx,
+            0x24 => DwFormInfo::RefSup8,
+            0x25 => DwFormInfo::Strx1,
+            0x26 => DwFormInfo::Strx2,
+            0x27 => DwFormInfo::Strx3,
+            0x28 => DwFormInfo::Strx4,
+            0x29 => DwFormInfo::Addrx1,
+            0x2A => DwFormInfo::Addrx2,
+            0x2B => DwFormInfo::Addrx3,
+            0x2C => DwFormInfo::Addrx4,
             _ => DwFormInfo::Unknown(form),
         }
     }
+
+    /// DW_LANGコンバート
+    fn to_dw_lang(lang: u64) -> DwLangInfo {
+        match lang {
+            0x1 => DwLangInfo::C89,
+            0x2 => DwLangInfo::C,
+            0x3 => DwLangInfo::Ada83,
+            0x4 => DwLangInfo::CPlusPlus,
+            0x5 => DwLangInfo::Cobol74,
+            0x6 => DwLangInfo::Cobol85,
+            0x7 => DwLangInfo::Fortran77,
+            0x8 => DwLangInfo::Fortran90,
+            0x9 => DwLangInfo::Pascal83,
+            0xA => DwLangInfo::Modula2,
+            0xB => DwLangInfo::Java,
+            0xC => DwLangInfo::C99,
+            0xD => DwLangInfo::Ada95,
+            0xE => DwLangInfo::Fortran95,
+            0xF => DwLangInfo::Pli,
+            0x10 => DwLangInfo::ObjC,
+            0x11 => DwLangInfo::ObjCPlusPlus,
+            0x12 => DwLangInfo::Upc,
+            0x13 => DwLangInfo::D,
+            0x14 => DwLangInfo::Python,
+            0x15 => DwLangInfo::OpenCl,
+            0x16 => DwLangInfo::Go,
+            0x17 => DwLangInfo::Modula3,
+            0x18 => DwLangInfo::Haskell,
+            0x19 => DwLangInfo::CPlusPlus03,
+            0x1A => DwLangInfo::CPlusPlus11,
+            0x1B => DwLangInfo::OCaml,
+            0x1C => DwLangInfo::Rust,
+            0x1D => DwLangInfo::C11,
+            0x1E => DwLangInfo::Swift,
+            0x1F => DwLangInfo::Julia,
+            0x20 => DwLangInfo::Dylan,
+            0x21 => DwLangInfo::CPlusPlus14,
+            0x22 => DwLangInfo::Fortran03,
+            0x23 => DwLangInfo::Fortran08,
+            0x24 => DwLangInfo::RenderScript,
+            0x25 => DwLangInfo::Bliss,
+            0x26 => DwLangInfo::Kotlin,
+            0x27 => DwLangInfo::Zig,
+            0x28 => DwLangInfo::Crystal,
+            0x2A => DwLangInfo::CPlusPlus17,
+            0x2B => DwLangInfo::CPlusPlus20,
+            0x2C => DwLangInfo::C17,
+            0x2D => DwLangInfo::Fortran18,
+            0x2E => DwLangInfo::Ada2005,
+            0x2F => DwLangInfo::Ada2012,
+            0x8001 => DwLangInfo::MipsAssembler,
+            _ => DwLangInfo::Unknown(lang),
+        }
+    }
 }
 
 /// abbrev record
 #[derive(Debug)]
 struct DebugAbbRevRecord {
-    abbrev_no: u64,      // 実際は、ULEB128
-    tag: u64,            // 実際は、ULEB128
-    has_child: u8,       // このDIRをもつかどうか
-    attr_name: Vec<u64>, // 実際は、ULEB128の配列
-    attr_form: Vec<u64>, // 実際は、ULEB128の配列
+    abbrev_no: u64,       // 実際は、ULEB128
+    tag: u64,             // 実際は、ULEB128
+    has_child: u8,        // このDIRをもつかどうか
+    attr_name: Vec<u64>,  // 実際は、ULEB128の配列
+    attr_form: Vec<u64>,  // 実際は、ULEB128の配列
+    attr_const: Vec<i64>, // DW_FORM_implicit_constの値(それ以外は0)
 }
 impl DwInfo for DebugAbbRevRecord {}
 impl DebugAbbRevRecord {
@@ -446,62 +766,107 @@ impl DebugAbbRevRecord {
             has_child: 0,
             attr_name: vec![],
             attr_form: vec![],
+            attr_const: vec![],
         }
     }
 
     /// abbrev情報表示
-    fn show(&self) {
-        println!(
-            "{} {:?} [child: {}]",
-            self.abbrev_no,
-            Self::to_dw_tag(self.tag),
-            Self::to_has_child(self.has_child)
-        );
-        for (n, f) in self.attr_name.iter().zip(self.attr_form.iter()) {
-            println!("    {:?} {:?}", Self::to_dw_at(*n), Self::to_dw_form(*f));
+    ///
+    /// rawであれば、tag/attribute/formをコードで表示する
+    fn show<W: Write>(&self, out: &mut W, raw: bool) -> Result<()> {
+        match raw {
+            true => writeln!(
+                out,
+                "{} tag 0x{:x} [{}]",
+                self.abbrev_no,
+                self.tag,
+                Self::to_has_child(self.has_child)
+            )?,
+            false => writeln!(
+                out,
+                "{} {:?} [{}]",
+                self.abbrev_no,
+                Self::to_dw_tag(self.tag),
+                Self::to_has_child(self.has_child)
+            )?,
+        }
+        let attrs = self.attr_name.iter().zip(self.attr_form.iter());
+        for ((n, f), c) in attrs.zip(self.attr_const.iter()) {
+            // 終端(name=0x00, form=0x00)は表示しない
+            if 0 == *n && 0 == *f {
+                continue;
+            }
+            let value = match Self::to_dw_form(*f) {
+                DwFormInfo::ImplicitConst => format!(": {}", c),
+                _ => String::new(),
+            };
+            match raw {
+                true => writeln!(out, "    at 0x{:x} form 0x{:x}{}", n, f, value)?,
+                false => writeln!(
+                    out,
+                    "    {:?} {:?}{}",
+                    Self::to_dw_at(*n),
+                    Self::to_dw_form(*f),
+                    value
+                )?,
+            }
         }
+        Ok(())
     }
 }
 
 /// abbrev section
 #[derive(Debug)]
 struct DebugAbbRevSection {
-    abb_rev: Vec<DebugAbbRevRecord>,
+    abb_rev: HashMap<u64, DebugAbbRevRecord>, // abbrev noをキーとする
 }
 
 impl ULEB128 for DebugAbbRevSection {}
+impl SLEB128 for DebugAbbRevSection {}
+impl DwInfo for DebugAbbRevSection {}
 impl DebugAbbRevSection {
     /// コンストラクタ
     pub fn new() -> Self {
-        DebugAbbRevSection { abb_rev: vec![] }
+        DebugAbbRevSection {
+            abb_rev: HashMap::new(),
+        }
     }
 
     /// abbrev record取得
-    pub fn get_record(&self, i: usize) -> &DebugAbbRevRecord {
-        &self.abb_rev[i]
+    ///
+    /// abbrev noは連番とは限らないため、noで検索する
+    pub fn get(&self, code: u64) -> Option<&DebugAbbRevRecord> {
+        self.abb_rev.get(&code)
     }
 
     /// AbbRevセクションロード
-    pub fn load(
-        &mut self,
-        reader: &mut BufReader<File>,
-        sec_header: &ElfSecHeader,
-        abbrev_offset: u64,
-    ) -> Result<()> {
-        // debug_abbrevセクションへ移動
-        let offset = sec_header.get_offset() + abbrev_offset;
-        reader.seek(SeekFrom::Start(offset))?;
+    ///
+    /// bufにはdebug_abbrevセクション全体を渡し、abbrev_offsetの位置からロードする
+    /// ロードしたabbrevテーブルの終端オフセットを返す
+    pub fn load(&mut self, buf: &[u8], abbrev_offset: u64) -> Result<u64> {
+        // debug_abbrevセクション内のオフセットへ移動
+        let mut data = abbrev_offset
+            .try_into()
+            .ok()
+            .and_then(|o: usize| buf.get(o..))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid abbrev offset 0x{:x}", abbrev_offset),
+                )
+            })?;
+        let reader = &mut data;
 
         // 各データをロード
         loop {
             let mut abbrev = DebugAbbRevRecord::new();
-            abbrev.abbrev_no = Self::decode(reader).unwrap().1;
+            abbrev.abbrev_no = Self::decode(reader)?.1;
 
             // noがゼロならば、abbrevは終了
             if 0 == abbrev.abbrev_no {
                 break;
             }
-            abbrev.tag = Self::decode(reader).unwrap().1;
+            abbrev.tag = Self::decode(reader)?.1;
 
             // has_childは1byte
             let mut b = [0; 1];
@@ -510,11 +875,18 @@ impl DebugAbbRevSection {
 
             // attribute/formコードをロード（name=0x00, form=0x00までループ)
             loop {
-                let attr_name = Self::decode(reader).unwrap().1;
-                let attr_form = Self::decode(reader).unwrap().1;
+                let attr_name = Self::decode(reader)?.1;
+                let attr_form = Self::decode(reader)?.1;
                 abbrev.attr_name.push(attr_name);
                 abbrev.attr_form.push(attr_form);
 
+                // implicit_constは、値がabbrevに格納されている
+                let value = match Self::to_dw_form(attr_form) {
+                    DwFormInfo::ImplicitConst => Self::decode_signed(reader)?.1,
+                    _ => 0,
+                };
+                abbrev.attr_const.push(value);
+
                 // attr/formが共にゼロであれば、終了
                 if 0 == attr_name && 0 == attr_form {
                     break;
@@ -522,18 +894,20 @@ impl DebugAbbRevSection {
             }
 
             // abbrevデータ保存
-            self.abb_rev.push(abbrev);
+            self.abb_rev.insert(abbrev.abbrev_no, abbrev);
         }
 
-        Ok(())
+        Ok(buf.len() as u64 - reader.len() as u64)
     }
 
     /// abbrev表示
-    #[allow(dead_code)]
-    fn show(&self) {
-        for a in &self.abb_rev {
-            a.show();
+    fn show<W: Write>(&self, out: &mut W, raw: bool) -> Result<()> {
+        let mut codes = self.abb_rev.keys().collect::<Vec<&u64>>();
+        codes.sort();
+        for c in codes {
+            self.abb_rev[c].show(out, raw)?;
         }
+        Ok(())
     }
 }
 
@@ -562,6 +936,8 @@ impl Filenames {
 struct DebugLineHeader {
     len: u32,
     version: u16,
+    address_size: u8, // dwarf5
+    seg_sel_size: u8, // dwarf5
     header_len: u32,
     min_inst_len: u8,
     max_ope_len: u8,
@@ -579,6 +955,8 @@ impl DebugLineHeader {
         DebugLineHeader {
             len: 0,
             version: 0,
+            address_size: 0,
+            seg_sel_size: 0,
             header_len: 0,
             min_inst_len: 0,
             max_ope_len: 0,
@@ -593,74 +971,397 @@ impl DebugLineHeader {
     }
 }
 
+/// 行番号テーブルの行
+#[derive(Debug, Clone, PartialEq)]
+struct LineRow {
+    address: u64,
+    file: u64, // file_namesのインデックス(1オリジン)
+    line: u64,
+    column: u64, // ゼロは列情報なし
+    is_stmt: bool,
+    end_sequence: bool,
+}
+
+impl LineRow {
+    /// コンストラクタ(行番号プログラムのレジスタ初期値)
+    pub fn new(is_stmt: bool) -> Self {
+        LineRow {
+            address: 0,
+            file: 1,
+            line: 1,
+            column: 0,
+            is_stmt,
+            end_sequence: false,
+        }
+    }
+}
+
+/// アドレスに対応するソース位置
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineInfo {
+    file: String,
+    line: u64,
+    column: u64,
+    address: u64,
+}
+
+impl LineInfo {
+    /// アドレス取得
+    pub fn get_address(&self) -> u64 {
+        self.address
+    }
+
+    /// ファイル名取得
+    pub fn get_file(&self) -> &str {
+        &self.file
+    }
+
+    /// 行番号取得
+    pub fn get_line(&self) -> u64 {
+        self.line
+    }
+}
+
+impl std::fmt::Display for LineInfo {
+    /// file:line:column形式で表示(列情報がなければfile:line)
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.column {
+            0 => write!(f, "{}:{}", self.file, self.line),
+            c => write!(f, "{}:{}:{}", self.file, self.line, c),
+        }
+    }
+}
+
+/// 行番号に対応するアドレス範囲
+///
+/// 範囲毎に、文(statement)の開始位置かを持つ
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineAddrs {
+    file: String,
+    line: u64,
+    ranges: Vec<(Range<u64>, bool)>,
+}
+
+impl LineAddrs {
+    /// ファイル名取得
+    pub fn get_file(&self) -> &str {
+        &self.file
+    }
+
+    /// 行番号取得
+    pub fn get_line(&self) -> u64 {
+        self.line
+    }
+
+    /// アドレス範囲取得
+    pub fn get_ranges(&self) -> &[(Range<u64>, bool)] {
+        &self.ranges
+    }
+}
+
 /// debug_lineセクション
 #[derive(Debug)]
 struct DebugLineSection {
-    offset: u64,                     // セクションデータ先頭へのオフセット
+    offset: u64,                     // debug_lineセクション内のオフセット(DW_AT_stmt_list)
     cu_header: Vec<DebugLineHeader>, // CU毎に定義されているヘッダー情報
+    rows: Vec<LineRow>,              // 行番号テーブル
 }
 
 impl ULEB128 for DebugLineSection {}
+
+// 行番号プログラムの符号付きオペランド読み込み用
+struct DebugLineProgram {}
+impl SLEB128 for DebugLineProgram {}
 impl DebugLineSection {
     /// コンストラクタ
     pub fn new(o: u64) -> Self {
         DebugLineSection {
             offset: o,
             cu_header: vec![],
+            rows: vec![],
         }
     }
 
     /// debug_line ロード処理
-    pub fn load(&mut self, path: &str, offset: u64) -> Result<()> {
-        // debug_lineセクション先頭へ移動
-        let f = File::open(&path)?;
-        let mut reader = BufReader::new(f);
-        reader.seek(SeekFrom::Start(self.offset + offset))?;
+    ///
+    /// bufにはdebug_lineセクション全体を渡し、offsetの位置の行番号プログラムをロードする
+    /// line_strには、dwarf5のファイル名等が格納されるdebug_line_strセクションデータを渡す
+    pub fn load(&mut self, buf: &[u8], line_str: &[u8]) -> Result<()> {
+        // 行番号プログラムの先頭へ移動
+        let start = self.offset;
+        let mut reader = start
+            .try_into()
+            .ok()
+            .and_then(|o: usize| buf.get(o..))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("invalid debug_line offset 0x{:x}", start),
+                )
+            })?;
 
         // headerのロード
-        let h = self.load_header(&mut reader)?;
+        let h = self.load_header(&mut reader, line_str)?;
+
+        // 行番号プログラムは、header lenの直後からunit lenの終端まで
+        // (unit len(4) + version(2) + header len(4)の後にheaderが続く)
+        // (dwarf5は、versionとheader lenの間にaddress size(1) + seg sel size(1)が入る)
+        let header_start = match h.version {
+            v if 5 <= v => 12,
+            _ => 10,
+        };
+        let program_start = start + header_start + h.header_len as u64;
+        let program_end = start + 4 + h.len as u64;
+        let program = buf
+            .get(program_start as usize..program_end as usize)
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "line program is truncated"))?;
+        self.rows = Self::run_program(&h, program);
         self.cu_header.push(h);
 
         Ok(())
     }
 
+    /// 行番号プログラムを実行し、行番号テーブルを生成
+    fn run_program(h: &DebugLineHeader, program: &[u8]) -> Vec<LineRow> {
+        let mut rows = vec![];
+        let mut reader = program;
+        let mut row = LineRow::new(h.is_stmt != 0);
+        let line_range = std::cmp::max(h.line_range, 1) as u64;
+        let min_inst_len = h.min_inst_len as u64;
+        while let Some((&op, rest)) = reader.split_first() {
+            reader = rest;
+            match op {
+                // 特殊オペコード(アドレスと行を同時に進め、行を追加)
+                op if op >= h.opcode_base => {
+                    let adjusted = (op - h.opcode_base) as u64;
+                    row.address += (adjusted / line_range) * min_inst_len;
+                    row.line = row
+                        .line
+                        .wrapping_add((h.line_base as i64 + (adjusted % line_range) as i64) as u64);
+                    rows.push(row.clone());
+                }
+                // 拡張オペコード
+                0 => {
+                    let len = match Self::decode(&mut reader) {
+                        Ok((_, l)) if l as usize <= reader.len() && l > 0 => l as usize,
+                        _ => break,
+                    };
+                    let (ext, rest) = reader.split_at(len);
+                    reader = rest;
+                    match ext[0] {
+                        DW_LNE_END_SEQUENCE => {
+                            row.end_sequence = true;
+                            rows.push(row.clone());
+                            row = LineRow::new(h.is_stmt != 0);
+                        }
+                        DW_LNE_SET_ADDRESS if ext.len() == 9 => {
+                            let mut buf = [0; 8];
+                            buf.copy_from_slice(&ext[1..]);
+                            row.address = u64::from_le_bytes(buf);
+                        }
+                        _ => {} // define_file/set_discriminator等は読み飛ばし
+                    }
+                }
+                DW_LNS_COPY => rows.push(row.clone()),
+                DW_LNS_ADVANCE_PC => match Self::decode(&mut reader) {
+                    Ok((_, v)) => row.address += v * min_inst_len,
+                    _ => break,
+                },
+                DW_LNS_ADVANCE_LINE => match DebugLineProgram::decode_signed(&mut reader) {
+                    Ok((_, v)) => row.line = row.line.wrapping_add(v as u64),
+                    _ => break,
+                },
+                DW_LNS_SET_FILE => match Self::decode(&mut reader) {
+                    Ok((_, v)) => row.file = v,
+                    _ => break,
+                },
+                DW_LNS_SET_COLUMN => match Self::decode(&mut reader) {
+                    Ok((_, v)) => row.column = v,
+                    _ => break,
+                },
+                DW_LNS_NEGATE_STMT => row.is_stmt = !row.is_stmt,
+                DW_LNS_CONST_ADD_PC => {
+                    let adjusted = (255 - h.opcode_base) as u64;
+                    row.address += (adjusted / line_range) * min_inst_len;
+                }
+                DW_LNS_FIXED_ADVANCE_PC => {
+                    if reader.len() < 2 {
+                        break;
+                    }
+                    row.address += u16::from_le_bytes([reader[0], reader[1]]) as u64;
+                    reader = &reader[2..];
+                }
+                // その他の標準オペコードは、ヘッダーに記載された引数の数だけ読み飛ばす
+                op => {
+                    let args = h.standard_opcode_len.get(op as usize - 1).copied();
+                    for _ in 0..args.unwrap_or(0) {
+                        if Self::decode(&mut reader).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        rows
+    }
+
+    /// ファイル名取得
+    ///
+    /// ディレクトリエントリーがあれば、ディレクトリ名を付与する
+    /// (ディレクトリエントリー0はコンパイル時のディレクトリなので、付与しない)
+    fn get_file_name(&self, file: u64) -> String {
+        let h = match self.cu_header.first() {
+            Some(h) => h,
+            None => return "?".to_string(),
+        };
+
+        // dwarf5のファイル・ディレクトリ番号はゼロ始まり、それより前は1始まり
+        let to_index = |no: u64| match h.version {
+            v if 5 <= v => no as usize,
+            _ => (no as usize).wrapping_sub(1),
+        };
+        match h.file_names.get(to_index(file)) {
+            Some(f) if f.dir_entry == 0 || f.name.starts_with('/') => f.name.clone(),
+            Some(f) => match h.inc_dirs.get(to_index(f.dir_entry)) {
+                Some(d) => format!("{}/{}", d, f.name),
+                None => f.name.clone(),
+            },
+            None => "?".to_string(),
+        }
+    }
+
+    /// ファイル名一覧取得(ファイル番号順)
+    fn get_file_names(&self) -> Vec<String> {
+        let (count, first) = match self.cu_header.first() {
+            Some(h) if 5 <= h.version => (h.file_names.len() as u64, 0),
+            Some(h) => (h.file_names.len() as u64, 1),
+            None => (0, 1),
+        };
+        (first..first + count)
+            .map(|i| self.get_file_name(i))
+            .collect()
+    }
+
+    /// 行番号テーブルの行をソース位置へ変換
+    fn to_line_info(&self, row: &LineRow) -> LineInfo {
+        LineInfo {
+            file: self.get_file_name(row.file),
+            line: row.line,
+            column: row.column,
+            address: row.address,
+        }
+    }
+
+    /// アドレスに対応するソース位置を検索
+    pub fn search_line(&self, addr: u64) -> Option<LineInfo> {
+        self.rows
+            .windows(2)
+            .rev()
+            .find(|w| !w[0].end_sequence && w[0].address <= addr && addr < w[1].address)
+            .map(|w| self.to_line_info(&w[0]))
+    }
+
+    /// ファイル名と行番号から、文(statement)の開始位置を検索
+    ///
+    /// 1行に複数の文がある場合は、列毎に開始位置を返す
+    pub fn search_addrs(&self, file: &str, line: u64) -> Vec<LineInfo> {
+        let mut infos: Vec<LineInfo> = vec![];
+        for row in &self.rows {
+            if !row.is_stmt || row.end_sequence || row.line != line {
+                continue;
+            }
+            if !is_same_file(&self.get_file_name(row.file), file) {
+                continue;
+            }
+            if infos.iter().all(|i| i.address != row.address) {
+                infos.push(self.to_line_info(row));
+            }
+        }
+        infos
+    }
+
+    /// 行番号テーブルの各行を、ファイル名・行番号・アドレス範囲へ変換
+    ///
+    /// 各行のアドレス範囲は、次の行のアドレスまでとする
+    fn get_line_ranges(&self) -> Vec<(String, u64, Range<u64>, bool)> {
+        self.rows
+            .windows(2)
+            .filter(|w| !w[0].end_sequence)
+            .map(|w| {
+                (
+                    self.get_file_name(w[0].file),
+                    w[0].line,
+                    w[0].address..w[1].address,
+                    w[0].is_stmt,
+                )
+            })
+            .collect()
+    }
+
     /// debug line情報表示
-    pub fn show(&self) {
-        println!("The line numebr program header");
-        self.cu_header.iter().for_each(|h| {
-            println!("    len:          {}", h.len);
-            println!("    version:      {}", h.version);
-            println!("    header len:   {}", h.header_len);
-            println!("    min inst len: {}", h.min_inst_len);
-            println!("    max ope len:  {}", h.max_ope_len);
-            println!("    is stmt:      {}", h.is_stmt);
-            println!("    line base:    {}", h.line_base);
-            println!("    line range:   {}", h.line_range);
-            println!("    opecode base: {}", h.opcode_base);
-            println!();
-            println!("    ope code:");
+    ///
+    /// rawであれば、行番号テーブルの各行も表示する
+    pub fn show<W: Write>(&self, out: &mut W, raw: bool) -> Result<()> {
+        writeln!(out, "The line number program at offset 0x{:x}", self.offset)?;
+        for h in &self.cu_header {
+            writeln!(out, "    len:          {}", h.len)?;
+            writeln!(out, "    version:      {}", h.version)?;
+            writeln!(out, "    header len:   {}", h.header_len)?;
+            writeln!(out, "    min inst len: {}", h.min_inst_len)?;
+            writeln!(out, "    max ope len:  {}", h.max_ope_len)?;
+            writeln!(out, "    is stmt:      {}", h.is_stmt)?;
+            writeln!(out, "    line base:    {}", h.line_base)?;
+            writeln!(out, "    line range:   {}", h.line_range)?;
+            writeln!(out, "    opecode base: {}", h.opcode_base)?;
+            writeln!(out)?;
+            writeln!(out, "    ope code:")?;
             for (i, ope) in h.standard_opcode_len.iter().enumerate() {
-                println!("    opecode {}: has {} argment", i + 1, ope);
+                writeln!(out, "    opecode {}: has {} argment", i + 1, ope)?;
             }
-            println!();
-            println!("    directory entry:");
+            writeln!(out)?;
+            writeln!(out, "    directory entry:")?;
             for (i, entry) in h.inc_dirs.iter().enumerate() {
-                println!("    no {}: {}", i + 1, entry);
+                writeln!(out, "    no {}: {}", i + 1, entry)?;
             }
-            println!();
-            println!("    file entry:");
+            writeln!(out)?;
+            writeln!(out, "    file entry:")?;
             for entry in &h.file_names {
-                println!(
+                writeln!(
+                    out,
                     "    dir no: {} last modify: {} size: {} {}",
                     entry.dir_entry, entry.last_modify, entry.size, entry.name
-                );
+                )?;
             }
-            println!();
-        });
+            writeln!(out)?;
+        }
+        if !raw {
+            return Ok(());
+        }
+
+        writeln!(out, "    address            file  line  column  flags")?;
+        for row in &self.rows {
+            let mut flags = vec![];
+            if row.is_stmt {
+                flags.push("is_stmt");
+            }
+            if row.end_sequence {
+                flags.push("end_sequence");
+            }
+            writeln!(
+                out,
+                "    0x{:016x} {:>5} {:>5} {:>7}  {}",
+                row.address,
+                row.file,
+                row.line,
+                row.column,
+                flags.join(" ")
+            )?;
+        }
+        writeln!(out)
     }
 
     /// headerロード
-    fn load_header(&self, reader: &mut BufReader<File>) -> Result<DebugLineHeader> {
+    fn load_header<R: Read>(&self, reader: &mut R, line_str: &[u8]) -> Result<DebugLineHeader> {
         let mut header = DebugLineHeader::new();
 
         // len
@@ -673,22 +1374,32 @@ impl DebugLineSection {
         reader.read_exact(&mut half_word)?;
         header.version = u16::from_le_bytes(half_word);
 
+        // address size/segment selector size(version 5から追加)
+        let mut byte = [0; 1];
+        if header.version >= 5 {
+            reader.read_exact(&mut byte)?;
+            header.address_size = u8::from_le_bytes(byte);
+            reader.read_exact(&mut byte)?;
+            header.seg_sel_size = u8::from_le_bytes(byte);
+        }
+
         // header len
         reader.read_exact(&mut word)?;
         header.header_len = u32::from_le_bytes(word);
 
         // min inst len
-        let mut byte = [0; 1];
         reader.read_exact(&mut byte)?;
         header.min_inst_len = u8::from_le_bytes(byte);
 
-        // max ope len
-        reader.read_exact(&mut byte)?;
-        header.max_ope_len = u8::from_le_bytes(byte);
+        // max ope len(version 4から追加)
+        if header.version >= 4 {
+            reader.read_exact(&mut byte)?;
+            header.max_ope_len = u8::from_le_bytes(byte);
+        }
 
         // is stmt
         reader.read_exact(&mut byte)?;
-        header.max_ope_len = u8::from_le_bytes(byte);
+        header.is_stmt = u8::from_le_bytes(byte);
 
         // line base
         reader.read_exact(&mut byte)?;
@@ -712,6 +1423,16 @@ impl DebugLineSection {
             });
         }
 
+        // dwarf5は、ディレクトリ・ファイル名ともエントリーの形式が指定される
+        if header.version >= 5 {
+            header.inc_dirs = Self::load_entries(reader, line_str)?
+                .into_iter()
+                .map(|d| d.name)
+                .collect();
+            header.file_names = Self::load_entries(reader, line_str)?;
+            return Ok(header);
+        }
+
         // include directories
         loop {
             // null終端までがディレクトリエントリー
@@ -760,8 +1481,90 @@ impl DebugLineSection {
         Ok(header)
     }
 
+    /// ディレクトリ・ファイル名のエントリーをロード(dwarf5)
+    ///
+    /// (内容の種別, DW_FORM)の組で各エントリーの形式が指定され、その後にエントリーが続く
+    fn load_entries<R: Read>(reader: &mut R, line_str: &[u8]) -> Result<Vec<Filenames>> {
+        let invalid = |msg: String| Error::new(ErrorKind::InvalidData, msg);
+        let decode = |r: &mut R| -> Result<u64> { Ok(Self::decode(r)?.1) };
+        let read = |r: &mut R, size: usize| -> Result<u64> {
+            let mut buf = [0; 8];
+            r.read_exact(&mut buf[..size])?;
+            Ok(u64::from_le_bytes(buf))
+        };
+
+        // エントリーの形式
+        let mut byte = [0; 1];
+        reader.read_exact(&mut byte)?;
+        let mut formats = vec![];
+        for _ in 0..byte[0] {
+            formats.push((decode(reader)?, decode(reader)?));
+        }
+
+        let count = decode(reader)?;
+        let mut entries = vec![];
+        for _ in 0..count {
+            let mut entry = Filenames::new();
+            for (content, form) in &formats {
+                let value = match *form {
+                    0x08 => {
+                        // DW_FORM_string
+                        let mut s = vec![];
+                        loop {
+                            reader.read_exact(&mut byte)?;
+                            match byte[0] {
+                                0 => break,
+                                c => s.push(c),
+                            }
+                        }
+                        entry.name = String::from_utf8_lossy(&s).into_owned();
+                        continue;
+                    }
+                    0x1F => {
+                        // DW_FORM_line_strp
+                        let offset = read(reader, 4)? as usize;
+                        let s = line_str.get(offset..).unwrap_or(&[]);
+                        let s = s
+                            .iter()
+                            .take_while(|c| **c != 0)
+                            .cloned()
+                            .collect::<Vec<u8>>();
+                        entry.name = String::from_utf8_lossy(&s).into_owned();
+                        continue;
+                    }
+                    0x0B => read(reader, 1)?, // DW_FORM_data1
+                    0x05 => read(reader, 2)?, // DW_FORM_data2
+                    0x06 => read(reader, 4)?, // DW_FORM_data4
+                    0x07 => read(reader, 8)?, // DW_FORM_data8
+                    0x0F => decode(reader)?,  // DW_FORM_udata
+                    0x1E => {
+                        // DW_FORM_data16(MD5)
+                        read(reader, 8)?;
+                        read(reader, 8)?;
+                        0
+                    }
+                    0x09 => {
+                        // DW_FORM_block
+                        let len = decode(reader)?;
+                        std::io::copy(&mut reader.take(len), &mut std::io::sink())?;
+                        0
+                    }
+                    f => return Err(invalid(format!("unsupported line header form 0x{:x}", f))),
+                };
+                match *content {
+                    DW_LNCT_DIRECTORY_INDEX => entry.dir_entry = value,
+                    DW_LNCT_TIMESTAMP => entry.last_modify = value,
+                    DW_LNCT_SIZE => entry.size = value,
+                    _ => {} // MD5等は読み飛ばし
+                }
+            }
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
     /// null終端までの文字列を取得
-    fn get_null_term_str(&self, reader: &mut BufReader<File>) -> Result<String> {
+    fn get_null_term_str<R: Read>(&self, reader: &mut R) -> Result<String> {
         let mut buf = vec![];
         loop {
             // null終端までのデータを取得
@@ -776,10 +1579,137 @@ impl DebugLineSection {
             buf.push(c);
         }
 
-        // 文字列に変換し、返却
-        match String::from_utf8(buf) {
-            Ok(s) => Ok(s),
-            Err(n) => Err(Error::new(ErrorKind::Other, n)),
+        // 文字列に変換し、返却(UTF-8でないバイトは置き換える)
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+}
+
+/// ファイル名が一致するか
+///
+/// DWARFにはパス付きで格納されている場合があるので、後方のパス要素が一致すれば一致とする
+fn is_same_file(path: &str, file: &str) -> bool {
+    path == file || path.ends_with(&format!("/{}", file))
+}
+
+/// ソースファイルのパスを、コンパイル時のディレクトリからのパスへ変換
+///
+/// 相対パスはcomp_dirへ連結し、.・..の要素を取り除く(シンボリックリンクは解決しない)
+fn resolve_source_path(comp_dir: &str, file: &str) -> String {
+    let path = match file.starts_with('/') || comp_dir.is_empty() {
+        true => file.to_string(),
+        false => format!("{}/{}", comp_dir.trim_end_matches('/'), file),
+    };
+    let mut parts: Vec<&str> = vec![];
+    for p in path.split('/').filter(|p| !p.is_empty() && "." != *p) {
+        match p {
+            ".." if parts.last().is_some_and(|l| ".." != *l) => {
+                parts.pop();
+            }
+            ".." if path.starts_with('/') => {}
+            _ => parts.push(p),
+        }
+    }
+    match path.starts_with('/') {
+        true => format!("/{}", parts.join("/")),
+        false => parts.join("/"),
+    }
+}
+
+/// アドレス範囲をソートし、重複・隣接する範囲をまとめる
+fn merge_ranges(mut ranges: Vec<Range<u64>>) -> Vec<Range<u64>> {
+    ranges.sort_by_key(|r| r.start);
+    let mut merged: Vec<Range<u64>> = vec![];
+    for r in ranges.into_iter().filter(|r| r.start < r.end) {
+        match merged.last_mut() {
+            Some(last) if r.start <= last.end => last.end = std::cmp::max(last.end, r.end),
+            _ => merged.push(r),
+        }
+    }
+    merged
+}
+
+/// 行番号テーブルの各行を、ファイル・行番号毎にまとめる
+///
+/// 連続するアドレス範囲は、文の開始位置かが同じであれば1つにまとめる
+fn to_line_addrs(mut rows: Vec<(String, u64, Range<u64>, bool)>) -> Vec<LineAddrs> {
+    rows.sort_by(|a, b| (&a.0, a.1, a.2.start).cmp(&(&b.0, b.1, b.2.start)));
+    let mut lines: Vec<LineAddrs> = vec![];
+    for (file, line, range, is_stmt) in rows {
+        let l = match lines.last_mut() {
+            Some(l) if l.file == file && l.line == line => l,
+            _ => {
+                lines.push(LineAddrs {
+                    file,
+                    line,
+                    ranges: vec![],
+                });
+                lines.last_mut().unwrap()
+            }
+        };
+        match l.ranges.last_mut() {
+            Some((r, s)) if *s == is_stmt && range.start <= r.end => {
+                r.end = std::cmp::max(r.end, range.end)
+            }
+            _ => l.ranges.push((range, is_stmt)),
+        }
+    }
+    lines
+}
+
+/// 名前が一致するか
+///
+/// 修飾した名前(ns::inner::func)は、後方の名前要素が一致すれば一致とする
+fn is_same_name(qualified: &str, name: &str) -> bool {
+    qualified == name || qualified.ends_with(&format!("::{}", name))
+}
+
+/// セクションデータから、リトルエンディアンの数値を読み込む
+fn read_le(buf: &[u8], offset: u64, size: usize) -> Option<u64> {
+    let start: usize = offset.try_into().ok()?;
+    let data = buf.get(start..start.checked_add(size)?)?;
+    if 8 < size {
+        return None;
+    }
+    let mut bytes = [0; 8];
+    bytes[..size].copy_from_slice(data);
+    Some(u64::from_le_bytes(bytes))
+}
+
+/// 属性値
+///
+/// DW_FORMのクラス毎に、読み込んだ値を保持する
+#[derive(Debug, Clone, PartialEq)]
+enum AttrValue {
+    Addr(u64),      // アドレス
+    Udata(u64),     // 符号なし定数
+    Sdata(i64),     // 符号付き定数
+    SecOffset(u64), // 他セクションへのオフセット
+    Str(String),    // 文字列
+    Flag(bool),     // フラグ
+    Block(Vec<u8>), // exprloc/blockのデータ
+    Ref(u64),       // CU先頭からのオフセット
+    // dwarf5のインデックス(CUのDW_AT_*_baseを元に、CUのロード後に解決する)
+    StrIndex(u64),  // .debug_str_offsetsのインデックス
+    AddrIndex(u64), // .debug_addrのインデックス
+    RngIndex(u64),  // .debug_rnglistsのインデックス
+    LocIndex(u64),  // .debug_loclistsのインデックス
+}
+
+impl fmt::Display for AttrValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AttrValue::Addr(v) => write!(f, "0x{:x}", v),
+            AttrValue::Udata(v) => write!(f, "{}", v),
+            AttrValue::Sdata(v) => write!(f, "{}", v),
+            AttrValue::SecOffset(v) => write!(f, "<0x{:x}>", v),
+            AttrValue::Str(s) => write!(f, "{}", s),
+            AttrValue::Flag(v) => write!(f, "{}", v),
+            AttrValue::Block(b) => write!(f, "{} byte block: {:02x?}", b.len(), b),
+            AttrValue::Ref(v) => write!(f, "<0x{:x}>", v),
+            AttrValue::StrIndex(i) => write!(f, "(indexed string: 0x{:x})", i),
+            AttrValue::AddrIndex(i) => write!(f, "(indexed address: 0x{:x})", i),
+            AttrValue::RngIndex(i) => write!(f, "(indexed range: 0x{:x})", i),
+            AttrValue::LocIndex(i) => write!(f, "(indexed location list: 0x{:x})", i),
         }
     }
 }
@@ -787,41 +1717,152 @@ impl DebugLineSection {
 /// DIEレコード
 #[derive(Debug)]
 struct DebugInfoEntry {
-    no: u64,
     attr: DwAtInfo,
     form: DwFormInfo,
-    data: String,
+    value: AttrValue,
 }
 
 impl DwInfo for DebugInfoEntry {}
 impl DebugInfoEntry {
     /// コンストラクタ
-    pub fn new(n: u64, a: u64, f: u64, s: &str) -> Self {
+    pub fn new(a: u64, f: u64, v: AttrValue) -> Self {
         DebugInfoEntry {
-            no: n,
             attr: Self::to_dw_at(a),
             form: Self::to_dw_form(f),
-            data: s.to_string(),
+            value: v,
+        }
+    }
+
+    /// 属性値取得
+    pub fn get_value(&self) -> &AttrValue {
+        &self.value
+    }
+
+    /// exprloc/blockデータ取得
+    pub fn get_block(&self) -> &[u8] {
+        match &self.value {
+            AttrValue::Block(b) => b,
+            _ => &[],
+        }
+    }
+
+    /// セクションオフセットを取得
+    ///
+    /// DWARF4より前は、定数クラスのformで格納される
+    pub fn get_sec_offset(&self) -> Option<u64> {
+        match self.value {
+            AttrValue::SecOffset(v) | AttrValue::Udata(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// DIEノード
+///
+/// tagと属性をまとめ、CU内の親子関係をインデックスで保持する
+#[derive(Debug)]
+struct DieNode {
+    offset: u64,    // .debug_infoセクション先頭からのオフセット
+    abbrev_no: u64, // 対応するabbrev no
+    tag: DwTagInfo,
+    attrs: Vec<DebugInfoEntry>,
+    children: Vec<usize>, // 子DIEのインデックス
+}
+
+impl DieNode {
+    /// コンストラクタ
+    pub fn new(o: u64, t: DwTagInfo) -> Self {
+        DieNode {
+            offset: o,
+            abbrev_no: 0,
+            tag: t,
+            attrs: vec![],
+            children: vec![],
         }
     }
 
     /// DIE情報表示
-    #[allow(dead_code)]
-    pub fn show(&self) {
-        println!(
-            "[{}] {:?} {:?} {}",
-            self.no, self.attr, self.form, self.data
-        );
+    ///
+    /// 親子関係の深さで字下げする
+    /// rawであれば、abbrevのtag/attribute/formをコードで表示する
+    pub fn show<W: Write>(
+        &self,
+        out: &mut W,
+        depth: usize,
+        record: Option<&DebugAbbRevRecord>,
+    ) -> Result<()> {
+        let record = match record {
+            Some(r) => r,
+            None => {
+                let indent = "  ".repeat(depth);
+                writeln!(out, "{}<0x{:x}> {:?}", indent, self.offset, self.tag)?;
+                for attr in self.attrs.iter().filter(|a| DwAtInfo::End != a.attr) {
+                    writeln!(
+                        out,
+                        "{}    {:?} {:?}: {}",
+                        indent, attr.attr, attr.form, attr.value
+                    )?;
+                }
+                return Ok(());
+            }
+        };
+
+        writeln!(
+            out,
+            " <{}><0x{:x}>: abbrev {} tag 0x{:x}",
+            depth, self.offset, self.abbrev_no, record.tag
+        )?;
+        let codes = record.attr_name.iter().zip(record.attr_form.iter());
+        for (attr, (n, f)) in self.attrs.iter().zip(codes) {
+            if DwAtInfo::End != attr.attr {
+                writeln!(out, "    at 0x{:x} form 0x{:x}: {}", n, f, attr.value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 属性取得
+    pub fn get_attr(&self, at: DwAtInfo) -> Option<&DebugInfoEntry> {
+        self.attrs.iter().find(|a| a.attr == at)
+    }
+
+    /// 文字列クラスの属性データを取得
+    pub fn get_str(&self, at: DwAtInfo) -> Option<&str> {
+        match self.get_attr(at).map(|a| a.get_value()) {
+            Some(AttrValue::Str(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// 定数クラスの属性データを数値で取得
+    pub fn get_const(&self, at: DwAtInfo) -> Option<u64> {
+        match self.get_attr(at).map(|a| a.get_value()) {
+            Some(AttrValue::Udata(v)) => Some(*v),
+            Some(AttrValue::Sdata(v)) => Some(*v as u64),
+            _ => None,
+        }
     }
 
-    /// DwAtInfo取得
-    pub fn get_at_info(&self) -> DwAtInfo {
-        self.attr.clone()
+    /// アドレスクラスの属性データを取得
+    pub fn get_addr(&self, at: DwAtInfo) -> Option<u64> {
+        match self.get_attr(at).map(|a| a.get_value()) {
+            Some(AttrValue::Addr(v)) => Some(*v),
+            _ => None,
+        }
     }
 
-    /// data取得
-    pub fn get_data(&self) -> &str {
-        &self.data
+    /// DW_AT_high_pcから、終端アドレスを取得
+    ///
+    /// アドレスクラス(addr/addrx)は終端アドレスそのもの
+    /// 定数クラス(data1〜8/udata/sdata)は、dwarf4からlow_pcからのオフセット
+    pub fn get_high_pc(&self, low: u64, version: u16) -> Option<u64> {
+        match self.get_attr(DwAtInfo::HighPc).map(|a| a.get_value()) {
+            Some(AttrValue::Addr(addr)) => Some(*addr),
+            Some(AttrValue::Udata(v)) if 4 <= version => Some(low.wrapping_add(*v)),
+            Some(AttrValue::Sdata(v)) if 4 <= version => Some(low.wrapping_add(*v as u64)),
+            Some(AttrValue::Udata(addr)) => Some(*addr),
+            _ => None,
+        }
     }
 }
 
@@ -831,9 +1872,14 @@ struct CUHeader {
     len: u32, // debug_info length(for 32bit dwarf format. 0xFFFF_FFFF when 64bit dwarf mode)
     actual_len: u64, // debug_info length(for 64bit mode)
     version: u16, // dwarf version
-    abb_rev_offset: u32, // debug_abbrev section offset in .debug_abbrev
+    unit_type: u8, // unit type(dwarf5)
+    abb_rev_offset: u64, // debug_abbrev section offset in .debug_abbrev
     address_size: u8, // 1-byte unsigned integer representing the size in bytes of an address on the target architecture(pointer size)
-    dies: Vec<DebugInfoEntry>, // CUに紐付いたDIEを保存
+    offset: u64,      // .debug_infoセクション先頭からCU先頭までのオフセット
+    dies: Vec<DieNode>, // CUに紐付いたDIEを保存(オフセット順)
+    files: Vec<String>, // debug_lineのファイル名(DW_AT_decl_file等のインデックスに対応)
+    scopes: Vec<String>, // DIE毎の、囲んでいる名前空間・クラスの修飾名(ns::inner::)
+    lines: Vec<DebugLineSection>, // CUの行番号テーブル
 }
 
 impl CUHeader {
@@ -843,528 +1889,4071 @@ impl CUHeader {
             len: 0,
             actual_len: 0,
             version: 0,
+            unit_type: 0,
             abb_rev_offset: 0,
             address_size: 0,
+            offset: 0,
             dies: vec![],
+            files: vec![],
+            scopes: vec![],
+            lines: vec![],
         }
     }
 
     /// ヘッダー表示
-    #[allow(dead_code)]
-    pub fn show(&self) {
-        println!(".debug_info compile unit header:");
-        println!("    length      : 0x{:x}", self.len);
-        println!("    version     : 0x{:x}", self.version);
-        println!("    abb offset  : 0x{:x}", self.abb_rev_offset);
-        println!("    address size: 0x{:x}", self.address_size);
+    pub fn show<W: Write>(&self, out: &mut W) -> Result<()> {
+        writeln!(
+            out,
+            ".debug_info compile unit header at 0x{:x}:",
+            self.offset
+        )?;
+        writeln!(out, "    length      : 0x{:x}", self.len)?;
+        writeln!(out, "    version     : 0x{:x}", self.version)?;
+        if 5 <= self.version {
+            writeln!(out, "    unit type   : 0x{:x}", self.unit_type)?;
+        }
+        writeln!(out, "    abb offset  : 0x{:x}", self.abb_rev_offset)?;
+        writeln!(out, "    address size: 0x{:x}", self.address_size)
     }
 
-    /// DIE取得
-    pub fn get_dies(&self) -> &[DebugInfoEntry] {
-        &self.dies
+    /// セクションオフセットのサイズ(64bit形式は8byte、32bit形式は4byte)
+    pub fn offset_size(&self) -> usize {
+        match self.len {
+            0xFFFF_FFFF => 8,
+            _ => 4,
+        }
     }
-}
 
-/// debug_infoセクション
-///
-/// header/dies/abbrevは、インデックスで対応付け
-#[derive(Debug)]
-struct DebugInfoSection {
-    header: Vec<CUHeader>,
-}
+    /// CUの終端オフセット(.debug_infoセクション先頭から)を取得
+    ///
+    /// 64bit形式は、初期長(0xFFFF_FFFF + 8byte)の後にCUが続く
+    pub fn get_end(&self) -> u64 {
+        match self.len {
+            0xFFFF_FFFF => (self.offset + 12).saturating_add(self.actual_len),
+            len => self.offset + 4 + len as u64,
+        }
+    }
 
-impl DwInfo for DebugInfoSection {}
-impl ULEB128 for DebugInfoSection {}
-impl DebugInfoSection {
-    /// コンストラクタ
-    pub fn new() -> Self {
-        DebugInfoSection { header: vec![] }
+    /// CUのファイル名取得
+    pub fn get_name(&self) -> &str {
+        self.dies
+            .first()
+            .and_then(|d| d.get_str(DwAtInfo::Name))
+            .unwrap_or("?")
     }
 
-    /// CU Header取得
-    pub fn get_header(&self) -> &[CUHeader] {
-        &self.header
+    /// DW_AT_decl_file等のファイル番号から、ファイル名を取得
+    ///
+    /// dwarf5はゼロ始まり、それより前は1始まり
+    pub fn get_file(&self, no: u64) -> Option<&str> {
+        let index = match self.version {
+            v if 5 <= v => no,
+            _ => no.wrapping_sub(1),
+        };
+        self.files.get(index as usize).map(|f| f.as_str())
     }
 
-    /// DebugInfoSection情報表示
-    pub fn show(&self) {
-        for h in &self.header {
-            h.show();
-            for die in &h.dies {
-                die.show();
-            }
+    /// CUのファイル名が一致するか
+    pub fn is_same_file(&self, file: &str) -> bool {
+        is_same_file(self.get_name(), file)
+    }
+
+    /// DIE毎に、囲んでいる名前空間・クラスの修飾名を登録
+    ///
+    /// 無名の名前空間は(anonymous namespace)とする
+    pub fn index_scopes(&mut self) {
+        let mut scopes = vec![String::new(); self.dies.len()];
+        let mut stack = match self.dies.is_empty() {
+            true => vec![],
+            false => vec![0],
+        };
+        while let Some(i) = stack.pop() {
+            let die = &self.dies[i];
+            let name = die.get_str(DwAtInfo::Name);
+            let prefix = match die.tag {
+                DwTagInfo::Namespace => {
+                    format!("{}{}::", scopes[i], name.unwrap_or("(anonymous namespace)"))
+                }
+                DwTagInfo::StructureType | DwTagInfo::ClassType | DwTagInfo::UnionType
+                    if name.is_some() =>
+                {
+                    format!("{}{}::", scopes[i], name.unwrap())
+                }
+                _ => scopes[i].clone(),
+            };
+            for child in &die.children {
+                scopes[*child] = prefix.clone();
+                stack.push(*child);
+            }
+        }
+        self.scopes = scopes;
+    }
+
+    /// 名前空間・クラスで修飾した名前を取得
+    ///
+    /// 名前は、DW_AT_specification/DW_AT_abstract_originで指す宣言側から取得する
+    pub fn get_qualified_name(&self, die: &DieNode) -> Option<String> {
+        let (decl, name) = DebugInfoSection::find_origin(self, die, |d| {
+            d.get_str(DwAtInfo::Name).map(|n| (d, n))
+        })?;
+        let prefix = self
+            .dies
+            .binary_search_by_key(&decl.offset, |d| d.offset)
+            .ok()
+            .and_then(|i| self.scopes.get(i))
+            .map_or("", |s| s.as_str());
+        Some(format!("{}{}", prefix, name))
+    }
+
+    /// オフセットからDIEを取得
+    pub fn get_die(&self, offset: u64) -> Option<&DieNode> {
+        self.dies
+            .binary_search_by_key(&offset, |d| d.offset)
+            .ok()
+            .map(|i| &self.dies[i])
+    }
+
+    /// 参照属性が指すDIEのオフセットを取得
+    ///
+    /// CU内参照はCU先頭からのオフセットで格納されている
+    pub fn get_ref(&self, die: &DieNode, at: DwAtInfo) -> Option<u64> {
+        match die.get_attr(at).map(|a| a.get_value()) {
+            Some(AttrValue::Ref(o)) => Some(self.offset + o),
+            _ => None,
+        }
+    }
+}
+
+/// DWARFから取得した変数情報
+#[derive(Debug, Clone)]
+pub struct VarInfo {
+    name: String,
+    file: String, // 定義しているCUのファイル名
+    addr: u64,    // ELF上の仮想アドレス
+    ty: TypeInfo,
+}
+
+impl VarInfo {
+    /// 変数名取得
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// ファイル名取得
+    pub fn get_file(&self) -> &str {
+        &self.file
+    }
+
+    /// アドレス取得
+    pub fn get_addr(&self) -> u64 {
+        self.addr
+    }
+
+    /// 型情報取得
+    pub fn get_type(&self) -> &TypeInfo {
+        &self.ty
+    }
+}
+
+/// 関数情報
+#[derive(Debug, Clone)]
+pub struct FuncInfo {
+    name: String,
+    file: String, // 宣言されているファイル名
+    line: u64,    // 宣言されている行番号
+    addr: u64,    // 関数の先頭アドレス
+    size: u64,    // 関数のサイズ(アドレス範囲の合計)
+}
+
+impl FuncInfo {
+    /// 関数名取得
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// ファイル名取得
+    pub fn get_file(&self) -> &str {
+        &self.file
+    }
+
+    /// 行番号取得
+    pub fn get_line(&self) -> u64 {
+        self.line
+    }
+
+    /// アドレス取得
+    pub fn get_addr(&self) -> u64 {
+        self.addr
+    }
+
+    /// サイズ取得
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// CU情報
+#[derive(Debug, Clone)]
+pub struct CuInfo {
+    index: usize,
+    name: String,            // ソースファイル名(DW_AT_name)
+    comp_dir: String,        // コンパイル時のディレクトリ(DW_AT_comp_dir)
+    producer: String,        // コンパイラとオプション(DW_AT_producer)
+    language: String,        // 言語名(DW_AT_language)
+    version: u16,            // dwarf version
+    ranges: Vec<Range<u64>>, // CUのアドレス範囲
+    die_count: usize,        // DIE数
+    line_rows: usize,        // 行番号テーブルの行数
+}
+
+impl CuInfo {
+    /// インデックス取得
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+
+    /// ソースファイル名取得
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// コンパイル時のディレクトリ取得
+    pub fn get_comp_dir(&self) -> &str {
+        &self.comp_dir
+    }
+
+    /// コンパイラ情報取得
+    pub fn get_producer(&self) -> &str {
+        &self.producer
+    }
+
+    /// 言語名取得
+    pub fn get_language(&self) -> &str {
+        &self.language
+    }
+
+    /// dwarf version取得
+    pub fn get_version(&self) -> u16 {
+        self.version
+    }
+
+    /// アドレス範囲取得
+    pub fn get_ranges(&self) -> &[Range<u64>] {
+        &self.ranges
+    }
+
+    /// DIE数取得
+    pub fn get_die_count(&self) -> usize {
+        self.die_count
+    }
+
+    /// 行番号テーブルの行数取得
+    pub fn get_line_rows(&self) -> usize {
+        self.line_rows
+    }
+}
+
+/// ローカル変数情報
+#[derive(Debug, Clone)]
+pub struct LocalVarInfo {
+    name: String,
+    ty: TypeInfo,
+    location: Vec<u8>, // DW_AT_locationの位置式
+    is_param: bool,    // 仮引数か
+}
+
+impl LocalVarInfo {
+    /// 変数名取得
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// 型情報取得
+    pub fn get_type(&self) -> &TypeInfo {
+        &self.ty
+    }
+
+    /// 位置式取得
+    pub fn get_location(&self) -> &[u8] {
+        &self.location
+    }
+
+    /// 仮引数か
+    pub fn is_param(&self) -> bool {
+        self.is_param
+    }
+}
+
+/// pcを含むスコープ情報
+///
+/// インライン展開された関数は、それぞれ別のスコープとして扱う
+#[derive(Debug)]
+pub struct ScopeInfo {
+    func: String,              // 関数名
+    call_site: Option<String>, // インライン展開された場合、呼び出し元(file:line)
+    frame_base: Vec<u8>,       // DW_AT_frame_baseの位置式
+    vars: Vec<LocalVarInfo>,   // 内側のスコープから順に格納
+}
+
+impl ScopeInfo {
+    /// 関数名取得
+    pub fn get_func(&self) -> &str {
+        &self.func
+    }
+
+    /// インライン展開されたフレームか
+    pub fn is_inlined(&self) -> bool {
+        self.call_site.is_some()
+    }
+
+    /// 呼び出し元取得(インライン展開された場合のみ)
+    pub fn get_call_site(&self) -> Option<&str> {
+        self.call_site.as_deref()
+    }
+
+    /// フレームベースの位置式取得
+    pub fn get_frame_base(&self) -> &[u8] {
+        &self.frame_base
+    }
+
+    /// 変数一覧取得
+    pub fn get_vars(&self) -> &[LocalVarInfo] {
+        &self.vars
+    }
+
+    /// 変数検索(最も内側のスコープの変数を返す)
+    pub fn search_var(&self, name: &str) -> Option<&LocalVarInfo> {
+        self.vars.iter().find(|v| v.get_name() == name)
+    }
+}
+
+/// debug_infoセクション
+///
+/// ロード時はCUの位置のみを記録し、DIE・行番号テーブルは最初に参照された時に解析する
+/// units/unit_offsetsは、インデックスで対応付け
+#[derive(Debug)]
+struct DebugInfoSection {
+    units: Vec<OnceCell<CUHeader>>,    // 解析済みのCU
+    unit_offsets: Vec<u64>,            // 各CUの.debug_infoセクション内オフセット
+    unit_ranges: Vec<Vec<Range<u64>>>, // 各CUのアドレス範囲(ロード時にCUのDIEのみ解析して取得)
+    info: Vec<u8>,                     // debug_infoセクションデータ
+    str_buf: Vec<u8>,                  // debug_strセクションデータ
+    line: Vec<u8>,                     // debug_lineセクションデータ
+    ranges: Vec<u8>,                   // debug_rangesセクションデータ
+    rnglists: Vec<u8>,                 // debug_rnglistsセクションデータ(dwarf5)
+    loc: Vec<u8>,                      // debug_locセクションデータ
+    loclists: Vec<u8>,                 // debug_loclistsセクションデータ(dwarf5)
+    str_offsets: Vec<u8>,              // debug_str_offsetsセクションデータ(dwarf5)
+    addr: Vec<u8>,                     // debug_addrセクションデータ(dwarf5)
+    line_str: Vec<u8>,                 // debug_line_strセクションデータ(dwarf5)
+    abbrev: Vec<u8>,                   // debug_abbrevセクションデータ
+    abbrev_cache: RefCell<HashMap<u64, Rc<DebugAbbRevSection>>>, // ロード済みのabbrev(オフセットがキー)
+}
+
+impl DwInfo for DebugInfoSection {}
+impl ULEB128 for DebugInfoSection {}
+impl SLEB128 for DebugInfoSection {}
+impl DebugInfoSection {
+    /// コンストラクタ
+    pub fn new() -> Self {
+        DebugInfoSection {
+            units: vec![],
+            unit_offsets: vec![],
+            unit_ranges: vec![],
+            info: vec![],
+            str_buf: vec![],
+            line: vec![],
+            ranges: vec![],
+            rnglists: vec![],
+            loc: vec![],
+            loclists: vec![],
+            str_offsets: vec![],
+            addr: vec![],
+            line_str: vec![],
+            abbrev: vec![],
+            abbrev_cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// CU取得
+    ///
+    /// 未解析であれば、ここで解析する
+    fn get_unit(&self, i: usize) -> &CUHeader {
+        self.units[i].get_or_init(|| self.parse_unit(self.unit_offsets[i]))
+    }
+
+    /// すべてのCUを取得
+    fn units(&self) -> impl Iterator<Item = &CUHeader> {
+        (0..self.units.len()).map(move |i| self.get_unit(i))
+    }
+
+    /// pcを含むCUを取得
+    ///
+    /// ロード時に登録したアドレス範囲から探し、該当するCUのみ解析する
+    /// (アドレス範囲が登録されていないCUは、該当する可能性があるものとして含める)
+    fn units_at(&self, pc: u64) -> impl Iterator<Item = &CUHeader> {
+        (0..self.units.len())
+            .filter(move |i| {
+                self.unit_ranges
+                    .get(*i)
+                    .is_none_or(|r| r.iter().any(|r| r.contains(&pc)))
+            })
+            .map(move |i| self.get_unit(i))
+    }
+
+    /// abbrev取得
+    ///
+    /// 複数のCUで同じabbrevを共有することが多いため、オフセット毎に一度だけロードする
+    fn get_abbrev(&self, offset: u64) -> Result<Rc<DebugAbbRevSection>> {
+        if let Some(abbrev) = self.abbrev_cache.borrow().get(&offset) {
+            return Ok(Rc::clone(abbrev));
+        }
+        let mut abbrev = DebugAbbRevSection::new();
+        abbrev.load(&self.abbrev, offset)?;
+        let abbrev = Rc::new(abbrev);
+        self.abbrev_cache
+            .borrow_mut()
+            .insert(offset, Rc::clone(&abbrev));
+        Ok(abbrev)
+    }
+
+    /// CU一覧を取得
+    pub fn get_cu_infos(&self) -> Vec<CuInfo> {
+        self.units()
+            .enumerate()
+            .map(|(i, cu)| self.to_cu_info(i, cu))
+            .collect()
+    }
+
+    /// CU情報を取得
+    ///
+    /// 指定したCUのみ解析する
+    pub fn get_cu_info(&self, index: usize) -> Option<CuInfo> {
+        (index < self.units.len()).then(|| self.to_cu_info(index, self.get_unit(index)))
+    }
+
+    /// CU情報へ変換
+    fn to_cu_info(&self, index: usize, cu: &CUHeader) -> CuInfo {
+        let root = cu.dies.first();
+        let to_str = |at: DwAtInfo| root.and_then(|d| d.get_str(at)).unwrap_or("").to_string();
+        CuInfo {
+            index,
+            name: cu.get_name().to_string(),
+            comp_dir: to_str(DwAtInfo::CompDir),
+            producer: to_str(DwAtInfo::Producer),
+            language: root
+                .and_then(|d| d.get_const(DwAtInfo::Language))
+                .map_or("unknown".to_string(), |l| Self::to_dw_lang(l).to_string()),
+            version: cu.version,
+            ranges: root.map_or(vec![], |d| merge_ranges(self.get_ranges(cu, d))),
+            die_count: cu.dies.len(),
+            line_rows: cu.lines.iter().map(|l| l.rows.len()).sum(),
+        }
+    }
+
+    /// ソースファイル一覧を取得
+    ///
+    /// すべてのCUの行番号テーブルのファイルを、コンパイル時のディレクトリから解決する
+    /// 同じヘッダーを共有するCUがあるため、重複を除いてパスの順に返却する
+    pub fn get_source_files(&self) -> Vec<String> {
+        self.units()
+            .flat_map(|cu| {
+                let comp_dir = cu
+                    .dies
+                    .first()
+                    .and_then(|d| d.get_str(DwAtInfo::CompDir))
+                    .unwrap_or("");
+                cu.lines
+                    .iter()
+                    .flat_map(|l| l.get_file_names())
+                    .map(move |f| resolve_source_path(comp_dir, &f))
+            })
+            .collect::<BTreeSet<String>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// グローバル変数一覧を取得
+    pub fn get_global_vars(&self) -> Vec<VarInfo> {
+        self.units()
+            .flat_map(|cu| {
+                Self::global_var_dies(cu)
+                    .into_iter()
+                    .map(move |(name, die, addr)| self.to_var_info(cu, &name, die, addr))
+            })
+            .collect()
+    }
+
+    /// グローバル変数を検索
+    ///
+    /// 名前空間で修飾した名前(ns::var)や、その後方一致(inner::var)でも検索できる
+    /// fileが指定された場合は、そのファイルのCUで定義された変数のみを対象とする
+    pub fn search_global_var(&self, name: &str, file: Option<&str>) -> Option<VarInfo> {
+        self.units()
+            .filter(|cu| file.is_none_or(|f| cu.is_same_file(f)))
+            .find_map(|cu| {
+                Self::global_var_dies(cu)
+                    .into_iter()
+                    .find(|(n, _, _)| is_same_name(n, name))
+                    .map(|(n, die, addr)| self.to_var_info(cu, &n, die, addr))
+            })
+    }
+
+    /// 関数一覧を取得
+    pub fn get_funcs(&self) -> Vec<FuncInfo> {
+        self.collect_funcs(|_, _| true)
+    }
+
+    /// 関数を検索
+    ///
+    /// 名前空間・クラスで修飾した名前(ns::inner::func)や、その後方一致(inner::func)で検索する
+    /// 同名の関数が複数のCUに存在する場合(static関数等)は、すべて返却する
+    /// fileが指定された場合は、そのファイルで宣言された関数のみを対象とする
+    pub fn search_funcs(&self, name: &str, file: Option<&str>) -> Vec<FuncInfo> {
+        self.collect_funcs(|cu, f| {
+            is_same_name(&f.name, name)
+                && file.is_none_or(|file| is_same_file(&f.file, file) || cu.is_same_file(file))
+        })
+    }
+
+    /// 条件に一致する関数を収集
+    ///
+    /// 実体を持つ(アドレスが割り当てられた)関数のみ対象とし、ファイル名・アドレス順に返却する
+    fn collect_funcs(&self, pred: impl Fn(&CUHeader, &FuncInfo) -> bool) -> Vec<FuncInfo> {
+        let mut funcs: Vec<FuncInfo> = vec![];
+        for cu in self.units() {
+            let found = cu
+                .dies
+                .iter()
+                .filter(|d| d.tag == DwTagInfo::Subprogram)
+                .filter_map(|d| {
+                    let ranges = self.get_ranges(cu, d);
+                    let addr = ranges.first()?.start;
+                    let size = ranges.iter().map(|r| r.end - r.start).sum::<u64>();
+                    Some((d, cu.get_qualified_name(d)?, addr, size))
+                });
+            for (die, name, addr, size) in found {
+                let decl_file = Self::find_origin(cu, die, |d| d.get_const(DwAtInfo::DeclFile))
+                    .and_then(|f| cu.get_file(f))
+                    .unwrap_or(cu.get_name());
+                let func = FuncInfo {
+                    name,
+                    file: decl_file.to_string(),
+                    line: Self::find_origin(cu, die, |d| d.get_const(DwAtInfo::DeclLine))
+                        .unwrap_or(0),
+                    addr,
+                    size,
+                };
+                if pred(cu, &func) && !funcs.iter().any(|f| f.addr == addr) {
+                    funcs.push(func);
+                }
+            }
+        }
+        funcs.sort_by(|a, b| a.file.cmp(&b.file).then(a.addr.cmp(&b.addr)));
+        funcs
+    }
+
+    /// pcを含むフレームを検索
+    ///
+    /// pcを含む関数から、pcを含むレキシカルブロック・インライン展開された関数を辿る
+    /// インライン展開された関数毎にフレームを分け、内側のフレームから順に返却する
+    /// 各フレームの変数は、内側のスコープから順に収集する(同名の変数は内側が優先)
+    pub fn search_frames(&self, pc: u64) -> Vec<ScopeInfo> {
+        for cu in self.units_at(pc) {
+            let func = cu
+                .dies
+                .iter()
+                .find(|d| d.tag == DwTagInfo::Subprogram && self.has_pc(cu, d, pc));
+            let func = match func {
+                Some(f) => f,
+                None => continue,
+            };
+            let frame_base = func
+                .get_attr(DwAtInfo::FrameBase)
+                .map_or(vec![], |a| a.get_block().to_vec());
+
+            // フレーム毎に、入れ子になったスコープを外側から格納
+            let mut frames: Vec<Vec<&DieNode>> = vec![vec![func]];
+            let mut scope = func;
+            while let Some(next) = scope.children.iter().map(|i| &cu.dies[*i]).find(|d| {
+                (d.tag == DwTagInfo::LexicalBlock || d.tag == DwTagInfo::InlinedSubroutine)
+                    && self.has_pc(cu, d, pc)
+            }) {
+                match next.tag {
+                    DwTagInfo::InlinedSubroutine => frames.push(vec![next]),
+                    _ => frames.last_mut().unwrap().push(next),
+                }
+                scope = next;
+            }
+
+            return frames
+                .iter()
+                .rev()
+                .map(|scopes| {
+                    let top = scopes[0];
+                    let inlined = top.tag == DwTagInfo::InlinedSubroutine;
+                    ScopeInfo {
+                        func: cu
+                            .get_qualified_name(top)
+                            .unwrap_or_else(|| "??".to_string()),
+                        call_site: match inlined {
+                            true => Some(Self::to_call_site(cu, top)),
+                            false => None,
+                        },
+                        frame_base: frame_base.clone(),
+                        vars: scopes
+                            .iter()
+                            .rev()
+                            .flat_map(|s| s.children.iter().map(|i| &cu.dies[*i]))
+                            .filter_map(|d| self.to_local_var_info(cu, d, pc))
+                            .collect(),
+                    }
+                })
+                .collect();
+        }
+        vec![]
+    }
+
+    /// 変数・仮引数をローカル変数として持つ関数を検索
+    ///
+    /// pcに関係なく、関数内のすべてのスコープ(レキシカルブロック等)の変数を対象とする
+    pub fn search_local_var_funcs(&self, name: &str) -> Vec<String> {
+        let mut funcs = vec![];
+        for cu in self.units() {
+            for func in cu
+                .dies
+                .iter()
+                .filter(|d| d.tag == DwTagInfo::Subprogram && !self.get_ranges(cu, d).is_empty())
+            {
+                let mut stack = func.children.clone();
+                while let Some(i) = stack.pop() {
+                    let die = &cu.dies[i];
+                    if matches!(die.tag, DwTagInfo::Variable | DwTagInfo::FormalParamter)
+                        && Self::get_spec_str(cu, die, DwAtInfo::Name) == Some(name)
+                    {
+                        funcs.push(
+                            cu.get_qualified_name(func)
+                                .unwrap_or_else(|| "??".to_string()),
+                        );
+                        break;
+                    }
+                    stack.extend_from_slice(&die.children);
+                }
+            }
+        }
+        funcs.sort();
+        funcs.dedup();
+        funcs
+    }
+
+    /// インライン展開された関数の呼び出し元(file:line)を取得
+    fn to_call_site(cu: &CUHeader, die: &DieNode) -> String {
+        let file = die
+            .get_const(DwAtInfo::CallFile)
+            .and_then(|f| cu.get_file(f))
+            .unwrap_or("??");
+        match die.get_const(DwAtInfo::CallLine) {
+            Some(line) => format!("{}:{}", file, line),
+            None => file.to_string(),
+        }
+    }
+
+    /// DIEのアドレス範囲にpcが含まれるか
+    fn has_pc(&self, cu: &CUHeader, die: &DieNode, pc: u64) -> bool {
+        self.get_ranges(cu, die).iter().any(|r| r.contains(&pc))
+    }
+
+    /// DIEのアドレス範囲を取得
+    ///
+    /// DW_AT_low_pc/DW_AT_high_pc、またはDW_AT_rangesで指定される
+    fn get_ranges(&self, cu: &CUHeader, die: &DieNode) -> Vec<Range<u64>> {
+        // CUはDW_AT_rangesと共に、ベースアドレスとしてDW_AT_low_pcを持つ場合がある
+        let has_ranges = die.get_attr(DwAtInfo::Ranges).is_some();
+        if let Some(low) = die
+            .get_addr(DwAtInfo::LowPc)
+            .filter(|_| !has_ranges || die.get_attr(DwAtInfo::HighPc).is_some())
+        {
+            let high = die.get_high_pc(low, cu.version).unwrap_or(low);
+            return vec![Range {
+                start: low,
+                end: high,
+            }];
+        }
+        match die
+            .get_attr(DwAtInfo::Ranges)
+            .and_then(|a| a.get_sec_offset())
+        {
+            Some(offset) => {
+                // ベースアドレスの初期値は、CUのlow_pc
+                let base = cu
+                    .dies
+                    .first()
+                    .and_then(|d| d.get_addr(DwAtInfo::LowPc))
+                    .unwrap_or(0);
+                match cu.version {
+                    v if 5 <= v => self.to_rnglists(cu, offset as usize, base),
+                    _ => Self::to_ranges(&self.ranges, offset as usize, base),
+                }
+            }
+            None => vec![],
+        }
+    }
+
+    /// debug_rangesセクションのアドレス範囲リストを取得
+    ///
+    /// (開始, 終了)のペアが(0, 0)まで続く。開始が全ビット1であれば、終了がベースアドレス
+    fn to_ranges(buf: &[u8], offset: usize, base: u64) -> Vec<Range<u64>> {
+        let mut base = base;
+        let mut ranges = vec![];
+        let entries = buf.get(offset..).unwrap_or(&[]).chunks_exact(16);
+        for entry in entries {
+            let (begin, end) = entry.split_at(8);
+            let begin = u64::from_le_bytes(begin.try_into().unwrap());
+            let end = u64::from_le_bytes(end.try_into().unwrap());
+            match (begin, end) {
+                (0, 0) => break,
+                (u64::MAX, b) => base = b,
+                (b, e) => ranges.push(base + b..base + e),
+            }
+        }
+        ranges
+    }
+
+    /// debug_rnglistsセクションのアドレス範囲リストを取得(dwarf5)
+    ///
+    /// DW_RLE_*で始まるエントリーが、DW_RLE_end_of_listまで続く
+    fn to_rnglists(&self, cu: &CUHeader, offset: usize, base: u64) -> Vec<Range<u64>> {
+        let mut ranges = vec![];
+        self.read_rnglists(cu, offset, base, &mut ranges);
+        ranges
+    }
+
+    /// debug_rnglistsセクションのエントリーを読み込む
+    ///
+    /// 途中でデータが途切れた場合は、そこまでに読み込んだアドレス範囲のみとする
+    fn read_rnglists(
+        &self,
+        cu: &CUHeader,
+        offset: usize,
+        base: u64,
+        ranges: &mut Vec<Range<u64>>,
+    ) -> Option<()> {
+        let addr_base = cu
+            .dies
+            .first()
+            .and_then(|d| d.get_attr(DwAtInfo::AddrBase))
+            .and_then(|a| a.get_sec_offset())
+            .unwrap_or(8);
+        let addrx = |i: u64| read_le(&self.addr, addr_base + i * 8, 8);
+        let uleb = |r: &mut &[u8]| Self::decode(r).ok().map(|(_, v)| v);
+        let addr = |r: &mut &[u8]| {
+            let v = read_le(r, 0, 8)?;
+            *r = &r[8..];
+            Some(v)
+        };
+
+        let mut base = base;
+        let mut reader = self.rnglists.get(offset..)?;
+        while let Some((&kind, rest)) = reader.split_first() {
+            reader = rest;
+            match kind {
+                DW_RLE_END_OF_LIST => break,
+                DW_RLE_BASE_ADDRESSX => base = addrx(uleb(&mut reader)?)?,
+                DW_RLE_STARTX_ENDX => {
+                    let start = addrx(uleb(&mut reader)?)?;
+                    let end = addrx(uleb(&mut reader)?)?;
+                    ranges.push(start..end);
+                }
+                DW_RLE_STARTX_LENGTH => {
+                    let start = addrx(uleb(&mut reader)?)?;
+                    ranges.push(start..start + uleb(&mut reader)?);
+                }
+                DW_RLE_OFFSET_PAIR => {
+                    let start = uleb(&mut reader)?;
+                    ranges.push(base + start..base + uleb(&mut reader)?);
+                }
+                DW_RLE_BASE_ADDRESS => base = addr(&mut reader)?,
+                DW_RLE_START_END => {
+                    let start = addr(&mut reader)?;
+                    ranges.push(start..addr(&mut reader)?);
+                }
+                DW_RLE_START_LENGTH => {
+                    let start = addr(&mut reader)?;
+                    ranges.push(start..start + uleb(&mut reader)?);
+                }
+                _ => return None,
+            }
+        }
+        Some(())
+    }
+
+    /// ローカル変数情報を生成
+    ///
+    /// 変数・仮引数以外のDIEや、宣言のみの変数(関数内のextern等)はNone
+    /// 配置先のない(最適化で削除された)変数は、位置式を空とする
+    /// ロケーションリストの場合は、pcでの位置式とする
+    fn to_local_var_info(&self, cu: &CUHeader, die: &DieNode, pc: u64) -> Option<LocalVarInfo> {
+        let is_param = match die.tag {
+            DwTagInfo::Variable if die.get_attr(DwAtInfo::Declaration).is_some() => return None,
+            DwTagInfo::Variable => false,
+            DwTagInfo::FormalParamter => true,
+            _ => return None,
+        };
+        let location = match die.get_attr(DwAtInfo::Location) {
+            Some(a) => match a.get_sec_offset() {
+                Some(offset) => self.search_location(cu, offset, pc),
+                None => a.get_block().to_vec(),
+            },
+            None => vec![],
+        };
+        Some(LocalVarInfo {
+            name: Self::get_spec_str(cu, die, DwAtInfo::Name)?.to_string(),
+            ty: match Self::find_origin(cu, die, |d| cu.get_ref(d, DwAtInfo::Type)) {
+                Some(t) => self.to_type_info(cu, t, 0),
+                None => TypeInfo::Unknown {
+                    name: "?".to_string(),
+                    size: 0,
+                },
+            },
+            location,
+            is_param,
+        })
+    }
+
+    /// ロケーションリストから、pcでの位置式を検索
+    ///
+    /// pcを含むエントリーがなければデフォルトの位置式、それもなければ空とする
+    fn search_location(&self, cu: &CUHeader, offset: u64, pc: u64) -> Vec<u8> {
+        // ベースアドレスの初期値は、CUのlow_pc
+        let base = cu
+            .dies
+            .first()
+            .and_then(|d| d.get_addr(DwAtInfo::LowPc))
+            .unwrap_or(0);
+        let mut locs = vec![];
+        match cu.version {
+            v if 5 <= v => self.read_loclists(cu, offset as usize, base, &mut locs),
+            _ => Self::read_locs(&self.loc, offset as usize, base, &mut locs),
+        };
+        locs.iter()
+            .find(|(r, _)| r.as_ref().is_some_and(|r| r.contains(&pc)))
+            .or_else(|| locs.iter().find(|(r, _)| r.is_none()))
+            .map_or(vec![], |(_, e)| e.clone())
+    }
+
+    /// debug_locセクションのロケーションリストを読み込む
+    ///
+    /// (開始, 終了)のペアと2バイトの長さの位置式が、(0, 0)まで続く。開始が全ビット1であれば、終了がベースアドレス
+    fn read_locs(
+        buf: &[u8],
+        offset: usize,
+        base: u64,
+        locs: &mut Vec<(Option<Range<u64>>, Vec<u8>)>,
+    ) -> Option<()> {
+        let mut base = base;
+        let mut reader = buf.get(offset..)?;
+        loop {
+            let begin = read_le(reader, 0, 8)?;
+            let end = read_le(reader, 8, 8)?;
+            reader = reader.get(16..)?;
+            match (begin, end) {
+                (0, 0) => break,
+                (u64::MAX, b) => base = b,
+                (b, e) => {
+                    let len = read_le(reader, 0, 2)? as usize;
+                    locs.push((Some(base + b..base + e), reader.get(2..2 + len)?.to_vec()));
+                    reader = &reader[2 + len..];
+                }
+            }
+        }
+        Some(())
+    }
+
+    /// debug_loclistsセクションのロケーションリストを読み込む(dwarf5)
+    ///
+    /// DW_LLE_*で始まるエントリーが、DW_LLE_end_of_listまで続く
+    /// 各エントリーの位置式は、uLEB128の長さの後に続く。デフォルトの位置式は範囲をNoneとする
+    fn read_loclists(
+        &self,
+        cu: &CUHeader,
+        offset: usize,
+        base: u64,
+        locs: &mut Vec<(Option<Range<u64>>, Vec<u8>)>,
+    ) -> Option<()> {
+        let addr_base = cu
+            .dies
+            .first()
+            .and_then(|d| d.get_attr(DwAtInfo::AddrBase))
+            .and_then(|a| a.get_sec_offset())
+            .unwrap_or(8);
+        let addrx = |i: u64| read_le(&self.addr, addr_base + i * 8, 8);
+        let uleb = |r: &mut &[u8]| Self::decode(r).ok().map(|(_, v)| v);
+        let addr = |r: &mut &[u8]| {
+            let v = read_le(r, 0, 8)?;
+            *r = &r[8..];
+            Some(v)
+        };
+        let expr = |r: &mut &[u8]| {
+            let len = uleb(r)? as usize;
+            let e = r.get(..len)?.to_vec();
+            *r = &r[len..];
+            Some(e)
+        };
+
+        let mut base = base;
+        let mut reader = self.loclists.get(offset..)?;
+        while let Some((&kind, rest)) = reader.split_first() {
+            reader = rest;
+            let range = match kind {
+                DW_LLE_END_OF_LIST => break,
+                DW_LLE_BASE_ADDRESSX => {
+                    base = addrx(uleb(&mut reader)?)?;
+                    continue;
+                }
+                DW_LLE_BASE_ADDRESS => {
+                    base = addr(&mut reader)?;
+                    continue;
+                }
+                DW_LLE_STARTX_ENDX => {
+                    let start = addrx(uleb(&mut reader)?)?;
+                    Some(start..addrx(uleb(&mut reader)?)?)
+                }
+                DW_LLE_STARTX_LENGTH => {
+                    let start = addrx(uleb(&mut reader)?)?;
+                    Some(start..start + uleb(&mut reader)?)
+                }
+                DW_LLE_OFFSET_PAIR => {
+                    let start = uleb(&mut reader)?;
+                    Some(base + start..base + uleb(&mut reader)?)
+                }
+                DW_LLE_DEFAULT_LOCATION => None,
+                DW_LLE_START_END => {
+                    let start = addr(&mut reader)?;
+                    Some(start..addr(&mut reader)?)
+                }
+                DW_LLE_START_LENGTH => {
+                    let start = addr(&mut reader)?;
+                    Some(start..start + uleb(&mut reader)?)
+                }
+                _ => return None,
+            };
+            locs.push((range, expr(&mut reader)?));
+        }
+        Some(())
+    }
+
+    /// CU直下、または名前空間内で定義された変数DIEを列挙
+    ///
+    /// 修飾した名前とDW_OP_addrで示される配置アドレスを合わせて返却する
+    /// 宣言のみのDIE(extern等)はアドレスを持たないので対象外となる
+    fn global_var_dies(cu: &CUHeader) -> Vec<(String, &DieNode, u64)> {
+        let mut vars = vec![];
+        let mut stack = cu.dies.first().map_or(vec![], |d| d.children.clone());
+        stack.reverse();
+        while let Some(i) = stack.pop() {
+            let die = &cu.dies[i];
+            match die.tag {
+                DwTagInfo::Namespace => stack.extend(die.children.iter().rev()),
+                DwTagInfo::Variable => {
+                    let name = cu.get_qualified_name(die);
+                    if let (Some(name), Some(addr)) = (name, Self::to_static_addr(cu, die)) {
+                        vars.push((name, die, addr));
+                    }
+                }
+                _ => {}
+            }
+        }
+        vars
+    }
+
+    /// 属性の文字列を取得
+    fn get_spec_str<'a>(cu: &'a CUHeader, die: &'a DieNode, at: DwAtInfo) -> Option<&'a str> {
+        Self::find_origin(cu, die, |d| d.get_str(at.clone()))
+    }
+
+    /// DIEから情報を取得
+    ///
+    /// 定義側のDIEになければ、DW_AT_specificationで指す宣言側や、
+    /// DW_AT_abstract_originで指すインライン展開元を辿って取得する
+    fn find_origin<'a, T>(
+        cu: &'a CUHeader,
+        die: &'a DieNode,
+        f: impl Fn(&'a DieNode) -> Option<T>,
+    ) -> Option<T> {
+        let mut die = die;
+        for _ in 0..MAX_TYPE_DEPTH {
+            if let Some(v) = f(die) {
+                return Some(v);
+            }
+            let origin = cu
+                .get_ref(die, DwAtInfo::Specification)
+                .or_else(|| cu.get_ref(die, DwAtInfo::AbstractOrigin))?;
+            die = cu.get_die(origin)?;
+        }
+        None
+    }
+
+    /// DW_OP_addrのみからなる位置式から、アドレスを取得
+    fn to_static_addr(cu: &CUHeader, die: &DieNode) -> Option<u64> {
+        let expr = die.get_attr(DwAtInfo::Location)?.get_block();
+        let size = cu.address_size as usize;
+        match expr.split_first() {
+            Some((&DW_OP_ADDR, addr)) if addr.len() == size && size <= 8 => {
+                let mut buf = [0; 8];
+                buf[..size].copy_from_slice(addr);
+                Some(u64::from_le_bytes(buf))
+            }
+            _ => None,
+        }
+    }
+
+    /// 変数情報を生成
+    fn to_var_info(&self, cu: &CUHeader, name: &str, die: &DieNode, addr: u64) -> VarInfo {
+        let ty = Self::find_origin(cu, die, |d| cu.get_ref(d, DwAtInfo::Type));
+        VarInfo {
+            name: name.to_string(),
+            file: cu.get_name().to_string(),
+            addr,
+            ty: match ty {
+                Some(t) => self.to_type_info(cu, t, 0),
+                None => TypeInfo::Unknown {
+                    name: "?".to_string(),
+                    size: 0,
+                },
+            },
+        }
+    }
+
+    /// 型DIEから型情報を生成
+    ///
+    /// typedef/const/volatileは読み飛ばし、実体の型まで辿る
+    fn to_type_info(&self, cu: &CUHeader, offset: u64, depth: u32) -> TypeInfo {
+        let die = match cu.get_die(offset) {
+            Some(d) if depth < MAX_TYPE_DEPTH => d,
+            _ => {
+                return TypeInfo::Unknown {
+                    name: "?".to_string(),
+                    size: 0,
+                }
+            }
+        };
+        let name = die.get_str(DwAtInfo::Name).unwrap_or("").to_string();
+        let size = die.get_const(DwAtInfo::ByteSize).unwrap_or(0);
+
+        match die.tag {
+            DwTagInfo::BaseType => TypeInfo::Base {
+                name,
+                size,
+                encoding: die.get_const(DwAtInfo::Encoding).unwrap_or(0),
+            },
+            DwTagInfo::Typedef
+            | DwTagInfo::ConstType
+            | DwTagInfo::VolatileType
+            | DwTagInfo::RestritctType => match cu.get_ref(die, DwAtInfo::Type) {
+                Some(t) => self.to_type_info(cu, t, depth + 1),
+                None => TypeInfo::Unknown {
+                    name: "void".to_string(),
+                    size: 0,
+                },
+            },
+            DwTagInfo::PointerType | DwTagInfo::ReferenceType | DwTagInfo::RvalueReferenceType => {
+                TypeInfo::Pointer {
+                    name: self.to_type_name(cu, die.offset, 0),
+                    size: if size == 0 {
+                        cu.address_size as u64
+                    } else {
+                        size
+                    },
+                }
+            }
+            DwTagInfo::StructureType | DwTagInfo::ClassType => {
+                // variant_partを持つ構造体は、Rustのenum
+                match die
+                    .children
+                    .iter()
+                    .map(|i| &cu.dies[*i])
+                    .find(|c| c.tag == DwTagInfo::VariantPart)
+                {
+                    Some(vp) => TypeInfo::Variant {
+                        name,
+                        size,
+                        discr: cu
+                            .get_ref(vp, DwAtInfo::Discr)
+                            .and_then(|o| cu.get_die(o))
+                            .map(|d| Box::new(self.to_member(cu, d, depth))),
+                        variants: self.to_variants(cu, vp, depth),
+                    },
+                    None => TypeInfo::Struct {
+                        name,
+                        size,
+                        members: self.to_members(cu, die, depth),
+                    },
+                }
+            }
+            DwTagInfo::UnionType => TypeInfo::Union {
+                name,
+                size,
+                members: self.to_members(cu, die, depth),
+            },
+            DwTagInfo::ArrayType => {
+                let elem = match cu.get_ref(die, DwAtInfo::Type) {
+                    Some(t) => self.to_type_info(cu, t, depth + 1),
+                    None => TypeInfo::Unknown {
+                        name: "?".to_string(),
+                        size: 0,
+                    },
+                };
+                let dims = Self::to_array_dims(cu, die);
+
+                // DW_AT_byte_strideがなければ、要素のサイズが要素間の距離
+                let stride = die
+                    .get_const(DwAtInfo::ByteStride)
+                    .unwrap_or_else(|| elem.get_size());
+                TypeInfo::Array {
+                    name: self.to_type_name(cu, die.offset, 0),
+                    size: if size == 0 {
+                        dims.iter().product::<u64>() * stride
+                    } else {
+                        size
+                    },
+                    elem: Box::new(elem),
+                    dims,
+                    stride,
+                }
+            }
+            _ => TypeInfo::Unknown { name, size },
+        }
+    }
+
+    /// 配列の各次元の要素数を取得
+    ///
+    /// 多次元配列は、DW_TAG_subrange_typeが次元の数だけ並ぶ
+    fn to_array_dims(cu: &CUHeader, die: &DieNode) -> Vec<u64> {
+        die.children
+            .iter()
+            .map(|i| &cu.dies[*i])
+            .filter(|c| c.tag == DwTagInfo::SubrangeType)
+            .map(|c| match c.get_const(DwAtInfo::Count) {
+                Some(count) => count,
+                None => match c.get_const(DwAtInfo::UpperBound) {
+                    Some(upper) => {
+                        let lower = c.get_const(DwAtInfo::LowerBound).unwrap_or(0);
+                        upper.wrapping_sub(lower).wrapping_add(1)
+                    }
+                    None => 0, // 要素数不明(フレキシブル配列メンバー等)
+                },
+            })
+            .collect()
+    }
+
+    /// 構造体のメンバー情報を生成
+    fn to_members(&self, cu: &CUHeader, die: &DieNode, depth: u32) -> Vec<MemberInfo> {
+        die.children
+            .iter()
+            .map(|i| &cu.dies[*i])
+            .filter(|m| m.tag == DwTagInfo::Member && m.get_attr(DwAtInfo::Declaration).is_none())
+            .map(|m| self.to_member(cu, m, depth))
+            .collect()
+    }
+
+    /// variant情報を生成
+    ///
+    /// 各DW_TAG_variantは、判別値とvariant本体のメンバーを持つ
+    fn to_variants(&self, cu: &CUHeader, vp: &DieNode, depth: u32) -> Vec<VariantInfo> {
+        vp.children
+            .iter()
+            .map(|i| &cu.dies[*i])
+            .filter(|v| v.tag == DwTagInfo::Variant)
+            .filter_map(|v| {
+                let member = v
+                    .children
+                    .iter()
+                    .map(|i| &cu.dies[*i])
+                    .find(|m| m.tag == DwTagInfo::Member)?;
+                Some(VariantInfo {
+                    discr_value: v.get_const(DwAtInfo::DiscrValue),
+                    member: self.to_member(cu, member, depth),
+                })
+            })
+            .collect()
+    }
+
+    /// メンバー情報を生成
+    fn to_member(&self, cu: &CUHeader, m: &DieNode, depth: u32) -> MemberInfo {
+        let ty = match cu.get_ref(m, DwAtInfo::Type) {
+            Some(t) => self.to_type_info(cu, t, depth + 1),
+            None => TypeInfo::Unknown {
+                name: "?".to_string(),
+                size: 0,
+            },
+        };
+        let location = m.get_const(DwAtInfo::DataMemberLocation).unwrap_or(0);
+
+        // ビットフィールドはDWARFバージョンによって属性が異なる
+        let bit_field = m.get_const(DwAtInfo::BitSize).map(|bit_size| {
+            match m.get_const(DwAtInfo::DataBitOffset) {
+                Some(o) => BitField::from_data_bit_offset(bit_size, o),
+                None => {
+                    let byte_size = m
+                        .get_const(DwAtInfo::ByteSize)
+                        .unwrap_or_else(|| ty.get_size());
+                    let bit_offset = m.get_const(DwAtInfo::BitOffset).unwrap_or(0);
+                    BitField::from_bit_offset(bit_size, location, byte_size, bit_offset)
+                }
+            }
+        });
+
+        MemberInfo {
+            name: m.get_str(DwAtInfo::Name).unwrap_or("").to_string(),
+            offset: bit_field
+                .as_ref()
+                .map(|b| b.bit_offset / 8)
+                .unwrap_or(location),
+            bit_field,
+            ty,
+        }
+    }
+
+    /// 型名を生成
+    fn to_type_name(&self, cu: &CUHeader, offset: u64, depth: u32) -> String {
+        let die = match cu.get_die(offset) {
+            Some(d) if depth < MAX_TYPE_DEPTH => d,
+            _ => return "?".to_string(),
+        };
+        let target = || match cu.get_ref(die, DwAtInfo::Type) {
+            Some(t) => self.to_type_name(cu, t, depth + 1),
+            None => "void".to_string(),
+        };
+
+        match die.tag {
+            DwTagInfo::PointerType => format!("{} *", target()),
+            DwTagInfo::ReferenceType => format!("{} &", target()),
+            DwTagInfo::RvalueReferenceType => format!("{} &&", target()),
+            DwTagInfo::ConstType => format!("const {}", target()),
+            DwTagInfo::VolatileType => format!("volatile {}", target()),
+            DwTagInfo::ArrayType => {
+                let dims = Self::to_array_dims(cu, die)
+                    .iter()
+                    .map(|d| format!("[{}]", d))
+                    .collect::<String>();
+                format!("{} {}", target(), dims)
+            }
+            _ => die.get_str(DwAtInfo::Name).unwrap_or("?").to_string(),
+        }
+    }
+
+    /// 表示対象のCUのインデックスを取得
+    ///
+    /// 指定がなければ、すべてのCUとする
+    fn to_unit_indexes(&self, index: Option<usize>) -> Result<Range<usize>> {
+        match index {
+            None => Ok(0..self.units.len()),
+            Some(i) if i < self.units.len() => Ok(i..i + 1),
+            Some(i) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("No compile unit {}.", i),
+            )),
+        }
+    }
+
+    /// debug_info表示
+    ///
+    /// rawであれば、abbrev noと各コードを表示する
+    pub fn show_info<W: Write>(&self, out: &mut W, index: Option<usize>, raw: bool) -> Result<()> {
+        for i in self.to_unit_indexes(index)? {
+            let cu = self.get_unit(i);
+            write!(out, "[{}] ", i)?;
+            cu.show(out)?;
+            let abbrev = match raw {
+                true => Some(self.get_abbrev(cu.abb_rev_offset)?),
+                false => None,
+            };
+
+            // 子DIEは、親の深さ+1
+            let mut depths = vec![0; cu.dies.len()];
+            for (die_index, die) in cu.dies.iter().enumerate() {
+                for c in &die.children {
+                    depths[*c] = depths[die_index] + 1;
+                }
+                let record = abbrev.as_ref().and_then(|a| a.get(die.abbrev_no));
+                die.show(out, depths[die_index], record)?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// debug_abbrev表示
+    ///
+    /// オフセットの指定がなければ、セクション内のすべてのabbrevテーブルを表示する
+    pub fn show_abbrev<W: Write>(&self, out: &mut W, offset: Option<u64>, raw: bool) -> Result<()> {
+        let mut o = offset.unwrap_or(0);
+        loop {
+            let mut abbrev = DebugAbbRevSection::new();
+            let end = abbrev.load(&self.abbrev, o)?;
+            writeln!(out, "abbrev table at offset 0x{:x}:", o)?;
+            abbrev.show(out, raw)?;
+            writeln!(out)?;
+
+            o = end;
+            if offset.is_some() || self.abbrev.len() as u64 <= o {
+                return Ok(());
+            }
+        }
+    }
+
+    /// debug_line表示
+    pub fn show_line<W: Write>(&self, out: &mut W, index: Option<usize>, raw: bool) -> Result<()> {
+        for i in self.to_unit_indexes(index)? {
+            let cu = self.get_unit(i);
+            writeln!(out, "[{}] {}", i, cu.get_name())?;
+            for line in &cu.lines {
+                line.show(out, raw)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// debug_infoセクションのCUを登録
+    ///
+    /// CUヘッダーとCUのDIEのみを読み込んでCUの位置・アドレス範囲を記録し、
+    /// 子のDIEの解析は参照時まで遅延する
+    fn index_units(&mut self) -> Result<()> {
+        // (debug_infoセクションすべてを読み込めば終了)
+        let mut offset = 0;
+        while offset < self.info.len() as u64 {
+            let mut cu_h = CUHeader::new();
+            cu_h.offset = offset;
+            let mut reader = &self.info[offset as usize..];
+            match Self::read_cu_header(&mut reader, &mut cu_h) {
+                Ok(_) => {
+                    let ranges = self
+                        .parse_root(offset)
+                        .map_or(vec![], |cu| self.get_ranges(&cu, &cu.dies[0]));
+                    self.units.push(OnceCell::new());
+                    self.unit_offsets.push(offset);
+                    self.unit_ranges.push(ranges);
+                }
+                // 未対応のversionのCUは読み飛ばし、残りのCUを登録する
+                Err(e) if ErrorKind::InvalidData == e.kind() => {
+                    println!("warning: skip compile unit at 0x{:x}: {}", offset, e)
+                }
+                Err(e) => return Err(e),
+            }
+
+            // 次のCUは、ヘッダーの長さから求める
+            offset = cu_h.get_end();
+        }
+
+        Ok(())
+    }
+
+    /// CUのDIE(子のDIEを除く)のみを解析
+    ///
+    /// 解析できなければNone
+    fn parse_root(&self, offset: u64) -> Option<CUHeader> {
+        let mut cu_h = CUHeader::new();
+        cu_h.offset = offset;
+        let mut reader = &self.info[offset as usize..];
+        let size = Self::read_cu_header(&mut reader, &mut cu_h).ok()?;
+        let abbrev = self.get_abbrev(cu_h.abb_rev_offset).ok()?;
+        self.parse_dies(
+            &mut reader,
+            &mut cu_h,
+            &abbrev,
+            &self.str_buf,
+            offset + size,
+            Some(1),
+        )
+        .ok()?;
+        (!cu_h.dies.is_empty()).then_some(cu_h)
+    }
+
+    /// CUを解析
+    ///
+    /// 解析できないCUは、DIEを持たないCUとして扱う
+    fn parse_unit(&self, offset: u64) -> CUHeader {
+        let mut cu_h = CUHeader::new();
+        cu_h.offset = offset;
+        let mut reader = &self.info[offset as usize..];
+        let result = Self::read_cu_header(&mut reader, &mut cu_h).and_then(|size| {
+            // abbrevを読み取りながら、debug_infoセクションをロードしていく
+            let abbrev = self.get_abbrev(cu_h.abb_rev_offset)?;
+            self.parse(
+                &mut reader,
+                &mut cu_h,
+                &abbrev,
+                &self.str_buf,
+                offset + size,
+            )
+        });
+        match result {
+            Ok(_) => {
+                cu_h.index_scopes();
+                self.load_lines(&mut cu_h);
+            }
+            Err(e) => {
+                println!("warning: skip compile unit at 0x{:x}: {}", offset, e);
+                cu_h.dies.clear();
+            }
+        }
+        cu_h
+    }
+
+    /// CUの行番号テーブルをロード
+    ///
+    /// DW_AT_stmt_listが指すdebug_lineの行番号プログラムを実行し、ファイル名を登録する
+    fn load_lines(&self, cu_h: &mut CUHeader) {
+        let stmt_list = cu_h
+            .dies
+            .first()
+            .and_then(|d| d.get_attr(DwAtInfo::StmtList))
+            .and_then(|a| a.get_sec_offset());
+        if let Some(offset) = stmt_list {
+            let mut line = DebugLineSection::new(offset);
+            match line.load(&self.line, &self.line_str) {
+                Ok(_) => {
+                    // ファイル名はCUからインデックスで参照される
+                    cu_h.files = line.get_file_names();
+                    cu_h.lines.push(line);
+                }
+                Err(e) => println!("warning: skip line program at 0x{:x}: {}", offset, e),
+            }
+        }
+    }
+
+    /// CUヘッダー読み込み
+    ///
+    /// 読み込んだヘッダーのサイズを返却する
+    /// 未対応のversionの場合は、lenとversionのみ設定しエラーを返却する
+    fn read_cu_header<R: Read>(reader: &mut R, cu_h: &mut CUHeader) -> Result<u64> {
+        let mut size = 0;
+
+        // len
+        let mut word = [0; 4];
+        reader.read_exact(&mut word)?;
+        cu_h.len = u32::from_le_bytes(word);
+        size += 4;
+
+        // load actual len when 64bit mode
+        if cu_h.len == 0xFFFF_FFFF {
+            // 64bit mode
+            let mut word64 = [0; 8];
+            reader.read_exact(&mut word64)?;
+            cu_h.actual_len = u64::from_le_bytes(word64);
+            size += 8;
+        }
+
+        // version
+        let mut half_word = [0; 2];
+        reader.read_exact(&mut half_word)?;
+        cu_h.version = u16::from_le_bytes(half_word);
+        size += 2;
+
+        // dwarf2〜5以外は、ヘッダーのレイアウトが不明なため解析しない
+        if !(2..=5).contains(&cu_h.version) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported DWARF version {}", cu_h.version),
+            ));
+        }
+
+        let mut byte = [0; 1];
+        if 5 <= cu_h.version {
+            // dwarf5は、unit type/address size/abb_rev offsetの順
+            reader.read_exact(&mut byte)?;
+            cu_h.unit_type = u8::from_le_bytes(byte);
+            reader.read_exact(&mut byte)?;
+            cu_h.address_size = u8::from_le_bytes(byte);
+            cu_h.abb_rev_offset = Self::read_uint(reader, cu_h.offset_size())?;
+            size += 2 + cu_h.offset_size() as u64;
+
+            // unit typeに応じて、dwo id(8byte)や型シグネチャ(8byte)と型オフセット(オフセットサイズ)が続く
+            let mut extra = match cu_h.unit_type {
+                DW_UT_SKELETON | DW_UT_SPLIT_COMPILE => vec![0; 8],
+                DW_UT_TYPE | DW_UT_SPLIT_TYPE => vec![0; 8 + cu_h.offset_size()],
+                _ => vec![],
+            };
+            reader.read_exact(&mut extra)?;
+            size += extra.len() as u64;
+        } else {
+            // abb_rev offset
+            cu_h.abb_rev_offset = Self::read_uint(reader, cu_h.offset_size())?;
+            size += cu_h.offset_size() as u64;
+
+            // address size
+            reader.read_exact(&mut byte)?;
+            cu_h.address_size = u8::from_le_bytes(byte);
+            size += 1;
+        }
+
+        Ok(size)
+    }
+
+    /// debug_infoセクションパーズ
+    ///
+    /// parseした結果とリードしたサイズを返却する
+    /// die_offsetには、CUの先頭DIEのセクション内オフセットを渡す
+    /// CUの終端(ヘッダーの長さから求める)まで読み込み、終端を越える属性はエラーとする
+    /// 未対応のformを含む場合は、abbrev番号とDIEのオフセットを含むエラーを返却する
+    fn parse<R: Read>(
+        &self,
+        reader: &mut R,
+        cu_h: &mut CUHeader,
+        abbrev: &DebugAbbRevSection,
+        str_buf: &[u8],
+        die_offset: u64,
+    ) -> Result<u64> {
+        self.parse_dies(reader, cu_h, abbrev, str_buf, die_offset, None)
+    }
+
+    /// DIEを解析
+    ///
+    /// max_diesが指定されれば、その数のDIEを読み込んだ時点で終了する
+    fn parse_dies<R: Read>(
+        &self,
+        reader: &mut R,
+        cu_h: &mut CUHeader,
+        abbrev: &DebugAbbRevSection,
+        str_buf: &[u8],
+        die_offset: u64,
+        max_dies: Option<usize>,
+    ) -> Result<u64> {
+        // CUの終端を越えて読み込まないよう制限
+        let end = cu_h.get_end();
+        let reader = &mut reader.take(end.saturating_sub(die_offset));
+
+        // DIEをロード(終端のパディングはnullエントリーとして読み飛ばす)
+        let offset_size = cu_h.offset_size();
+        let mut read_size = 0;
+        let mut parents: Vec<usize> = vec![]; // 子を持つDIEのスタック
+        while die_offset + read_size < end {
+            // debug_infoセクションから対応するabbrev noを読み込む
+            let offset = die_offset + read_size;
+            let (size, abbrev_no) = Self::decode(reader)?;
+            read_size += size;
+
+            // abbrev_no=ゼロならば、nullエントリー(兄弟の終端)なので親へ戻る
+            if 0 == abbrev_no {
+                parents.pop();
+                continue;
+            }
+
+            // noに対応するabbrevがなければ、CUが壊れている
+            let record = abbrev.get(abbrev_no).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("unknown abbrev code {} (offset 0x{:x})", abbrev_no, offset),
+                )
+            })?;
+
+            // DIEノードを生成し、親子関係を登録
+            let die_index = cu_h.dies.len();
+            if let Some(p) = parents.last() {
+                cu_h.dies[*p].children.push(die_index);
+            }
+            let mut die = DieNode::new(offset, Self::to_dw_tag(record.tag));
+            die.abbrev_no = abbrev_no;
+            cu_h.dies.push(die);
+            if 1 == record.has_child {
+                parents.push(die_index);
+            }
+
+            // DW_FORMに応じたデータを読み取る
+            let attrs = record.attr_name.iter().zip(record.attr_const.iter());
+            for (form, (at, implicit)) in record.attr_form.iter().zip(attrs) {
+                let (size, value) = match Self::to_dw_form(*form) {
+                    DwFormInfo::ImplicitConst => (0, AttrValue::Sdata(*implicit)),
+                    // dwarf2のref_addrは、オフセットサイズではなくアドレスサイズ
+                    DwFormInfo::RefAddr if 2 == cu_h.version => {
+                        (8, AttrValue::Block(Self::read_block(reader, 8)?))
+                    }
+                    _ => self
+                        .read_value(reader, *form, str_buf, offset_size)
+                        .map_err(|e| {
+                            let msg = match e.kind() {
+                                ErrorKind::UnexpectedEof => {
+                                    format!("attribute crosses the end of compile unit 0x{:x}", end)
+                                }
+                                _ => e.to_string(),
+                            };
+                            Error::new(
+                                e.kind(),
+                                format!("{} (abbrev {}, offset 0x{:x})", msg, abbrev_no, offset),
+                            )
+                        })?,
+                };
+                read_size += size;
+
+                // 属性を生成し、DIEへ保存
+                let attr = DebugInfoEntry::new(*at, *form, value);
+                cu_h.dies[die_index].attrs.push(attr);
+            }
+            if max_dies == Some(cu_h.dies.len()) {
+                break;
+            }
+        }
+        self.resolve_index(cu_h, str_buf);
+        Ok(read_size)
+    }
+
+    /// dwarf5のインデックスを解決
+    ///
+    /// CUのDW_AT_str_offsets_base/DW_AT_addr_base/DW_AT_rnglists_base/DW_AT_loclists_baseを基準に、
+    /// 各セクションのテーブルから文字列・アドレス・アドレス範囲リスト・ロケーションリストのオフセットを取得する
+    /// (属性が省略された場合は、各テーブルのヘッダー直後を基準とする)
+    /// テーブルの各オフセットは、CUの形式に応じたオフセットサイズで格納されている
+    fn resolve_index(&self, cu_h: &mut CUHeader, str_buf: &[u8]) {
+        let base = |at: DwAtInfo, default: u64| {
+            cu_h.dies
+                .first()
+                .and_then(|d| d.get_attr(at))
+                .and_then(|a| a.get_sec_offset())
+                .unwrap_or(default)
+        };
+        // 各テーブルのヘッダーは、長さ(64bit形式は12byte)・version・padding等からなる
+        let off_size = cu_h.offset_size();
+        let len_size = if 8 == off_size { 12 } else { 4 };
+        let str_base = base(DwAtInfo::StrOffsetsBase, len_size + 4);
+        let addr_base = base(DwAtInfo::AddrBase, len_size + 4);
+        let rng_base = base(DwAtInfo::RnglistsBase, len_size + 8);
+        let loc_base = base(DwAtInfo::LoclistsBase, len_size + 8);
+        let addr_size = cu_h.address_size as usize;
+        let off_len = off_size as u64;
+
+        for attr in cu_h.dies.iter_mut().flat_map(|d| d.attrs.iter_mut()) {
+            let resolved = match attr.value {
+                AttrValue::StrIndex(i) => {
+                    read_le(&self.str_offsets, str_base + i * off_len, off_size)
+                        .map(|o| AttrValue::Str(self.to_string(str_buf, o as usize)))
+                }
+                AttrValue::AddrIndex(i) => {
+                    read_le(&self.addr, addr_base + i * addr_size as u64, addr_size)
+                        .map(AttrValue::Addr)
+                }
+                AttrValue::RngIndex(i) => read_le(&self.rnglists, rng_base + i * off_len, off_size)
+                    .map(|o| AttrValue::SecOffset(rng_base + o)),
+                AttrValue::LocIndex(i) => read_le(&self.loclists, loc_base + i * off_len, off_size)
+                    .map(|o| AttrValue::SecOffset(loc_base + o)),
+                _ => continue,
+            };
+            if let Some(v) = resolved {
+                attr.value = v;
+            }
+        }
+    }
+
+    /// DW_FORMに応じた属性値を読み込む
+    ///
+    /// 読み込んだサイズと属性値を返却する
+    /// サイズが分かる未対応のform(data16等)は読み飛ばし、データをそのまま保持する
+    /// セクションオフセット(strp・sec_offset等)は、CUの形式に応じたoffset_size(4 or 8)で読み込む
+    fn read_value<R: Read>(
+        &self,
+        reader: &mut R,
+        form: u64,
+        str_buf: &[u8],
+        offset_size: usize,
+    ) -> Result<(u64, AttrValue)> {
+        let off_len = offset_size as u64;
+        let value = match Self::to_dw_form(form) {
+            DwFormInfo::Strp => {
+                // DIEにはdebug_strのオフセットが入っている
+                let offset = Self::read_uint(reader, offset_size)?;
+                (
+                    off_len,
+                    AttrValue::Str(self.to_string(str_buf, offset as usize)),
+                )
+            }
+            // debug_infoセクションに即値が格納
+            DwFormInfo::Addr => (8, AttrValue::Addr(Self::read_uint(reader, 8)?)),
+            DwFormInfo::Data1 => (1, AttrValue::Udata(Self::read_uint(reader, 1)?)),
+            DwFormInfo::Data2 => (2, AttrValue::Udata(Self::read_uint(reader, 2)?)),
+            DwFormInfo::Data4 => (4, AttrValue::Udata(Self::read_uint(reader, 4)?)),
+            DwFormInfo::Data8 => (8, AttrValue::Udata(Self::read_uint(reader, 8)?)),
+            DwFormInfo::SecOffset => (
+                off_len,
+                AttrValue::SecOffset(Self::read_uint(reader, offset_size)?),
+            ),
+            // CUヘッダーからのオフセットが、.debug_infoセクションに格納
+            DwFormInfo::Ref1 => (1, AttrValue::Ref(Self::read_uint(reader, 1)?)),
+            DwFormInfo::Ref2 => (2, AttrValue::Ref(Self::read_uint(reader, 2)?)),
+            DwFormInfo::Ref4 => (4, AttrValue::Ref(Self::read_uint(reader, 4)?)),
+            DwFormInfo::Ref8 => (8, AttrValue::Ref(Self::read_uint(reader, 8)?)),
+            DwFormInfo::RefUdata => {
+                let (size, data) = Self::decode(reader)?;
+                (size, AttrValue::Ref(data))
+            }
+            DwFormInfo::Sdata => {
+                // sLEB128方式でdebug_infoセクションに格納
+                let (size, data) = Self::decode_signed(reader)?;
+                (size, AttrValue::Sdata(data))
+            }
+            DwFormInfo::Udata => {
+                // uUEB128方式でdebug_infoセクションに格納
+                let (size, data) = Self::decode(reader)?;
+                (size, AttrValue::Udata(data))
+            }
+            DwFormInfo::String => {
+                // null terminateの文字列がdebug_infoセクションに格納
+                let mut st: Vec<u8> = vec![];
+                loop {
+                    match Self::read_uint(reader, 1)? as u8 {
+                        0 => break,
+                        c => st.push(c),
+                    }
+                }
+                let size = st.len() as u64 + 1;
+                (
+                    size,
+                    AttrValue::Str(String::from_utf8_lossy(&st).into_owned()),
+                )
+            }
+            DwFormInfo::Exprloc | DwFormInfo::Block => {
+                // uUEB128方式で長さが格納され、その後に長さ分のデータが続く
+                let (size, len) = Self::decode(reader)?;
+                (size + len, AttrValue::Block(Self::read_block(reader, len)?))
+            }
+            DwFormInfo::Block1 | DwFormInfo::Block2 | DwFormInfo::Block4 => {
+                // 長さを読み取り、その後に続くデータをリード
+                let size = match Self::to_dw_form(form) {
+                    DwFormInfo::Block1 => 1,
+                    DwFormInfo::Block2 => 2,
+                    _ => 4,
+                };
+                let len = Self::read_uint(reader, size)?;
+                (
+                    size as u64 + len,
+                    AttrValue::Block(Self::read_block(reader, len)?),
+                )
+            }
+            DwFormInfo::Flag => (1, AttrValue::Flag(0 != Self::read_uint(reader, 1)?)),
+            // フラグが存在していることを暗黙的に示している
+            DwFormInfo::FlagPresent => (0, AttrValue::Flag(true)),
+            // 他CUへの参照・型シグネチャ・16byte定数は、データのみ保持
+            DwFormInfo::RefAddr | DwFormInfo::StrpSup => (
+                off_len,
+                AttrValue::Block(Self::read_block(reader, off_len)?),
+            ),
+            DwFormInfo::RefSig8 => (8, AttrValue::Block(Self::read_block(reader, 8)?)),
+            DwFormInfo::Data16 => (16, AttrValue::Block(Self::read_block(reader, 16)?)),
+            DwFormInfo::RefSup4 => (4, AttrValue::Block(Self::read_block(reader, 4)?)),
+            DwFormInfo::RefSup8 => (8, AttrValue::Block(Self::read_block(reader, 8)?)),
+            DwFormInfo::LineStrp => {
+                // debug_line_strのオフセットが入っている
+                let offset = Self::read_uint(reader, offset_size)?;
+                (
+                    off_len,
+                    AttrValue::Str(self.to_string(&self.line_str, offset as usize)),
+                )
+            }
+            // インデックスは、CUのロード後にDW_AT_*_baseを元に解決する
+            DwFormInfo::Strx => {
+                let (size, index) = Self::decode(reader)?;
+                (size, AttrValue::StrIndex(index))
+            }
+            DwFormInfo::Strx1 => (1, AttrValue::StrIndex(Self::read_uint(reader, 1)?)),
+            DwFormInfo::Strx2 => (2, AttrValue::StrIndex(Self::read_uint(reader, 2)?)),
+            DwFormInfo::Strx3 => (3, AttrValue::StrIndex(Self::read_uint(reader, 3)?)),
+            DwFormInfo::Strx4 => (4, AttrValue::StrIndex(Self::read_uint(reader, 4)?)),
+            DwFormInfo::Addrx => {
+                let (size, index) = Self::decode(reader)?;
+                (size, AttrValue::AddrIndex(index))
+            }
+            DwFormInfo::Addrx1 => (1, AttrValue::AddrIndex(Self::read_uint(reader, 1)?)),
+            DwFormInfo::Addrx2 => (2, AttrValue::AddrIndex(Self::read_uint(reader, 2)?)),
+            DwFormInfo::Addrx3 => (3, AttrValue::AddrIndex(Self::read_uint(reader, 3)?)),
+            DwFormInfo::Addrx4 => (4, AttrValue::AddrIndex(Self::read_uint(reader, 4)?)),
+            DwFormInfo::Rnglistx => {
+                let (size, index) = Self::decode(reader)?;
+                (size, AttrValue::RngIndex(index))
+            }
+            DwFormInfo::Loclistx => {
+                let (size, index) = Self::decode(reader)?;
+                (size, AttrValue::LocIndex(index))
+            }
+            DwFormInfo::Indirect => {
+                // 実際のformがuLEB128で格納され、その後にデータが続く
+                let (size, form) = Self::decode(reader)?;
+                let (data_size, value) = self.read_value(reader, form, str_buf, offset_size)?;
+                (size + data_size, value)
+            }
+            DwFormInfo::End => (0, AttrValue::Udata(0)),
+            DwFormInfo::ImplicitConst => {
+                // 値はabbrevに格納されているため、DIEからは読み込めない
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "DW_FORM_implicit_const without abbrev",
+                ));
+            }
+            DwFormInfo::Unknown(v) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("unknown DW_FORM 0x{:x}", v),
+                ))
+            }
+        };
+        Ok(value)
+    }
+
+    /// リトルエンディアンの数値を読み込む
+    fn read_uint<R: Read>(reader: &mut R, size: usize) -> Result<u64> {
+        let mut buf = [0; 8];
+        reader.read_exact(&mut buf[..size])?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    /// 指定サイズのデータを読み込む
+    ///
+    /// 不正な長さで巨大な領域を確保しないよう、読み込めたデータのみ保持する
+    fn read_block<R: Read>(reader: &mut R, len: u64) -> Result<Vec<u8>> {
+        let mut buf = vec![];
+        reader.take(len).read_to_end(&mut buf)?;
+        if buf.len() as u64 != len {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                format!("block of {} bytes is truncated", len),
+            ));
+        }
+        Ok(buf)
+    }
+
+    /// Null Terminator文字列
+    ///
+    /// シンボルが入っているセクションデータと文字列開始位置を受け取り、
+    /// NullTermnateである文字列を返却する
+    fn to_string(&self, buf: &[u8], offset: usize) -> String {
+        let t: Vec<u8> = buf
+            .iter()
+            .skip(offset)
+            .take_while(|&c| *c != 0) // nullまで読み込み
+            .cloned()
+            .collect();
+        String::from_utf8_lossy(&t).into_owned()
+    }
+}
+
+/// Dwarf情報
+pub struct Dwarf {
+    debug_info: DebugInfoSection,
+}
+
+impl ULEB128 for Dwarf {}
+impl Dwarf {
+    /// コンストラクタ
+    pub fn new() -> Self {
+        Dwarf {
+            debug_info: DebugInfoSection::new(),
+        }
+    }
+
+    /// debug_info表示
+    pub fn show_info<W: Write>(&self, out: &mut W, index: Option<usize>, raw: bool) -> Result<()> {
+        self.debug_info.show_info(out, index, raw)
+    }
+
+    /// debug_abbrev表示
+    pub fn show_abbrev<W: Write>(&self, out: &mut W, offset: Option<u64>, raw: bool) -> Result<()> {
+        self.debug_info.show_abbrev(out, offset, raw)
+    }
+
+    /// debug_line表示
+    pub fn show_line<W: Write>(&self, out: &mut W, index: Option<usize>, raw: bool) -> Result<()> {
+        self.debug_info.show_line(out, index, raw)
+    }
+
+    /// すべてのCUを解析
+    ///
+    /// 解析したCUの数を返却する
+    pub fn load_all(&self) -> usize {
+        self.debug_info.units().count()
+    }
+
+    /// すべてのCUの行番号テーブルを取得
+    fn debug_lines(&self) -> impl Iterator<Item = &DebugLineSection> {
+        self.debug_info.units().flat_map(|cu| cu.lines.iter())
+    }
+
+    /// CU一覧を取得
+    pub fn get_cu_infos(&self) -> Vec<CuInfo> {
+        self.debug_info.get_cu_infos()
+    }
+
+    /// CU情報を取得
+    pub fn get_cu_info(&self, index: usize) -> Option<CuInfo> {
+        self.debug_info.get_cu_info(index)
+    }
+
+    /// ソースファイル一覧を取得
+    pub fn source_files(&self) -> Vec<String> {
+        self.debug_info.get_source_files()
+    }
+
+    /// ファイル名(行番号テーブル・DW_AT_decl_fileの名前)から、ソースファイルのパスを検索
+    pub fn find_source_file(&self, file: &str) -> Option<String> {
+        self.source_files()
+            .into_iter()
+            .find(|f| is_same_file(f, file))
+    }
+
+    /// グローバル変数一覧を取得
+    pub fn get_global_vars(&self) -> Vec<VarInfo> {
+        self.debug_info.get_global_vars()
+    }
+
+    /// グローバル変数を検索
+    pub fn search_global_var(&self, name: &str, file: Option<&str>) -> Option<VarInfo> {
+        self.debug_info.search_global_var(name, file)
+    }
+
+    /// 関数一覧を取得
+    pub fn get_funcs(&self) -> Vec<FuncInfo> {
+        self.debug_info.get_funcs()
+    }
+
+    /// 関数を検索
+    pub fn search_funcs(&self, name: &str, file: Option<&str>) -> Vec<FuncInfo> {
+        self.debug_info.search_funcs(name, file)
+    }
+
+    /// pcを含むフレームを検索(インライン展開されたフレームを含む)
+    pub fn search_frames(&self, pc: u64) -> Vec<ScopeInfo> {
+        self.debug_info.search_frames(pc)
+    }
+
+    /// 変数をローカル変数として持つ関数を検索
+    pub fn search_local_var_funcs(&self, name: &str) -> Vec<String> {
+        self.debug_info.search_local_var_funcs(name)
+    }
+
+    /// アドレスに対応するソース位置を検索
+    pub fn search_line(&self, addr: u64) -> Option<LineInfo> {
+        self.debug_info
+            .units_at(addr)
+            .flat_map(|cu| cu.lines.iter())
+            .find_map(|l| l.search_line(addr))
+    }
+
+    /// ファイル名と行番号から、文の開始位置を検索
+    pub fn search_line_addrs(&self, file: &str, line: u64) -> Vec<LineInfo> {
+        let mut infos = self
+            .debug_lines()
+            .flat_map(|l| l.search_addrs(file, line))
+            .collect::<Vec<LineInfo>>();
+        infos.sort_by_key(|i| i.address);
+        infos
+    }
+
+    /// ファイル名と行番号から、ブレイクポイントを設定する文の開始位置を検索
+    ///
+    /// 1行に複数の文がある場合は、最も小さいアドレスを返す
+    pub fn search_line_addr(&self, file: &str, line: u64) -> Option<LineInfo> {
+        self.search_line_addrs(file, line).into_iter().next()
+    }
+
+    /// ファイルの行番号毎に、対応するアドレス範囲を取得
+    pub fn get_line_table(&self, file: &str) -> Vec<LineAddrs> {
+        let rows = self
+            .debug_lines()
+            .flat_map(|l| l.get_line_ranges())
+            .filter(|(f, ..)| is_same_file(f, file))
+            .collect();
+        to_line_addrs(rows)
+    }
+
+    /// ファイル毎に、コードを持つ行数を取得
+    pub fn get_line_summary(&self) -> Vec<(String, usize)> {
+        let rows = self
+            .debug_lines()
+            .flat_map(|l| l.get_line_ranges())
+            .collect();
+        let mut summary: Vec<(String, usize)> = vec![];
+        for l in to_line_addrs(rows) {
+            match summary.last_mut() {
+                Some((f, n)) if *f == l.file => *n += 1,
+                _ => summary.push((l.file, 1)),
+            }
+        }
+        summary
+    }
+
+    /// debug_infoロード
+    pub fn load(&mut self, path: &str, header: &[ElfSecHeader]) -> Result<()> {
+        // debug_info/debug_abbrevセクションを探す
+        let debug_info_sec = match self.search_debug_info_sec(header) {
+            Some(h) => h,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::NotFound,
+                    "Not found debug_info section header",
+                ))
+            }
+        };
+        let abbrev_header = match self.search_debug_abbrev_sec(header) {
+            Some(h) => h,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::NotFound,
+                    "Not found debug_abbrev section header",
+                ))
+            }
+        };
+        let debug_str = match self.search_debug_str(header) {
+            Some(h) => h,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::NotFound,
+                    "Not found debug_str section header",
+                ))
+            }
+        };
+        let line_h = match self.search_debug_line(header) {
+            Some(h) => h,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::NotFound,
+                    "Not found debug_line section header",
+                ))
+            }
+        };
+
+        // debug_infoから参照されるセクションをロード(存在しない場合もある)
+        let f = File::open(path)?;
+        let file_size = f.metadata()?.len();
+        let mut reader = BufReader::new(f);
+        let mut load = |name: &str| Self::load_section(&mut reader, header, name, file_size);
+        self.debug_info.ranges = load(".debug_ranges")?;
+        self.debug_info.rnglists = load(".debug_rnglists")?;
+        self.debug_info.loc = load(".debug_loc")?;
+        self.debug_info.loclists = load(".debug_loclists")?;
+        self.debug_info.str_offsets = load(".debug_str_offsets")?;
+        self.debug_info.addr = load(".debug_addr")?;
+        self.debug_info.line_str = load(".debug_line_str")?;
+        self.debug_info.abbrev = load(abbrev_header.get_name())?;
+        self.debug_info.str_buf = load(debug_str.get_name())?;
+        self.debug_info.line = load(line_h.get_name())?;
+        self.debug_info.info = load(debug_info_sec.get_name())?;
+
+        // CUの位置のみ登録し、DIE・行番号テーブルは参照時に解析する
+        self.debug_info.index_units()
+    }
+
+    /// セクションデータをロード
+    ///
+    /// セクションが存在しない場合は、空データを返却する
+    /// ファイルに収まらないセクションはエラーとする
+    fn load_section(
+        reader: &mut BufReader<File>,
+        header: &[ElfSecHeader],
+        name: &str,
+        file_size: u64,
+    ) -> Result<Vec<u8>> {
+        let mut buf = vec![];
+        if let Some(h) = header.iter().find(|s| s.get_name() == name) {
+            let what = format!("section {}", name);
+            check_extent(&what, h.get_offset(), h.get_size(), file_size)?;
+            reader.seek(SeekFrom::Start(h.get_offset()))?;
+            buf = vec![0; h.get_size() as usize];
+            reader.read_exact(&mut buf)?;
+        }
+        Ok(buf)
+    }
+
+    /// search debug_info section
+    fn search_debug_info_sec<'a>(&self, header: &'a [ElfSecHeader]) -> Option<&'a ElfSecHeader> {
+        header.iter().find(|s| s.get_name() == ".debug_info")
+    }
+
+    /// search debug_abbrev section
+    fn search_debug_abbrev_sec<'a>(&self, header: &'a [ElfSecHeader]) -> Option<&'a ElfSecHeader> {
+        header.iter().find(|s| s.get_name() == ".debug_abbrev")
+    }
+
+    /// search debug_str section
+    fn search_debug_str<'a>(&self, header: &'a [ElfSecHeader]) -> Option<&'a ElfSecHeader> {
+        header.iter().find(|s| s.get_name() == ".debug_str")
+    }
+
+    /// search debug_line section
+    fn search_debug_line<'a>(&self, header: &'a [ElfSecHeader]) -> Option<&'a ElfSecHeader> {
+        header.iter().find(|s| s.get_name() == ".debug_line")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// テスト用DIE生成(属性は(DW_AT, DW_FORM, data)で指定)
+    fn node(offset: u64, tag: DwTagInfo, attrs: &[(u64, u64, &str)]) -> DieNode {
+        let mut die = DieNode::new(offset, tag);
+        for (at, form, data) in attrs {
+            die.attrs.push(attr(*at, *form, data));
+        }
+        die
+    }
+
+    /// テスト用属性生成(dataはformに応じた値へ変換)
+    fn attr(at: u64, form: u64, data: &str) -> DebugInfoEntry {
+        let value = match DebugInfoSection::to_dw_form(form) {
+            DwFormInfo::String => AttrValue::Str(data.to_string()),
+            DwFormInfo::Addr => AttrValue::Addr(data.parse().unwrap()),
+            DwFormInfo::SecOffset => AttrValue::SecOffset(data.parse().unwrap()),
+            DwFormInfo::Ref4 => AttrValue::Ref(data.parse().unwrap()),
+            DwFormInfo::FlagPresent => AttrValue::Flag(true),
+            _ => AttrValue::Udata(data.parse().unwrap()),
+        };
+        DebugInfoEntry::new(at, form, value)
+    }
+
+    const AT_LOCATION: u64 = 0x2;
+    const AT_NAME: u64 = 0x3;
+    const AT_BYTE_SIZE: u64 = 0xB;
+    const AT_UPPER_BOUND: u64 = 0x2F;
+    const AT_COUNT: u64 = 0x37;
+    const AT_ENCODING: u64 = 0x3E;
+    const AT_TYPE: u64 = 0x49;
+    const AT_LOW_PC: u64 = 0x11;
+    const AT_HIGH_PC: u64 = 0x12;
+    const AT_DECLARATION: u64 = 0x3C;
+    const AT_RANGES: u64 = 0x55;
+    const FORM_ADDR: u64 = 0x1;
+    const FORM_DATA8: u64 = 0x7;
+    const FORM_SEC_OFFSET: u64 = 0x17;
+    const AT_SPECIFICATION: u64 = 0x47;
+    const FORM_DATA1: u64 = 0xB;
+    const FORM_UDATA: u64 = 0xF;
+    const FORM_FLAG_PRESENT: u64 = 0x19;
+    const FORM_EXPRLOC: u64 = 0x18;
+    const FORM_STRING: u64 = 0x8;
+    const FORM_REF4: u64 = 0x13;
+
+    /// int型と、int[2][3]/int[4]の配列を持つCU
+    fn array_cu() -> CUHeader {
+        let mut cu = CUHeader::new();
+        cu.version = 4;
+        cu.address_size = 8;
+        cu.dies = vec![
+            node(
+                0x10,
+                DwTagInfo::BaseType,
+                &[
+                    (AT_BYTE_SIZE, FORM_DATA1, "4"),
+                    (AT_ENCODING, FORM_DATA1, "5"),
+                    (AT_NAME, FORM_STRING, "int"),
+                ],
+            ),
+            node(0x20, DwTagInfo::ArrayType, &[(AT_TYPE, FORM_REF4, "16")]),
+            node(
+                0x28,
+                DwTagInfo::SubrangeType,
+                &[(AT_UPPER_BOUND, FORM_DATA1, "1")],
+            ),
+            node(
+                0x2C,
+                DwTagInfo::SubrangeType,
+                &[(AT_COUNT, FORM_DATA1, "3")],
+            ),
+            node(0x30, DwTagInfo::ArrayType, &[(AT_TYPE, FORM_REF4, "16")]),
+            node(
+                0x38,
+                DwTagInfo::SubrangeType,
+                &[(AT_UPPER_BOUND, FORM_DATA1, "3")],
+            ),
+        ];
+        cu.dies[1].children = vec![2, 3];
+        cu.dies[4].children = vec![5];
+        cu
+    }
+
+    #[test]
+    fn test_array_dims() {
+        let cu = array_cu();
+        {
+            // 2次元配列(upper_bound/countの混在)
+            let die = cu.get_die(0x20).unwrap();
+            assert_eq!(vec![2, 3], DebugInfoSection::to_array_dims(&cu, die));
+        }
+        {
+            let die = cu.get_die(0x30).unwrap();
+            assert_eq!(vec![4], DebugInfoSection::to_array_dims(&cu, die));
+        }
+    }
+
+    #[test]
+    fn test_array_type() {
+        let cu = array_cu();
+        let sec = DebugInfoSection::new();
+        match sec.to_type_info(&cu, 0x20, 0) {
+            TypeInfo::Array {
+                name,
+                size,
+                elem,
+                dims,
+                stride,
+            } => {
+                assert_eq!("int [2][3]", name);
+                assert_eq!(24, size);
+                assert_eq!("int", elem.get_name());
+                assert_eq!(vec![2, 3], dims);
+                assert_eq!(4, stride);
+            }
+            t => panic!("unexpected type {:?}", t),
+        }
+    }
+
+    /// DW_OP_addrの位置式を持つ変数DIE
+    fn static_var(offset: u64, attrs: &[(u64, u64, &str)], addr: u64) -> DieNode {
+        let mut die = node(offset, DwTagInfo::Variable, attrs);
+        let expr = [&[DW_OP_ADDR][..], &addr.to_le_bytes()[..]].concat();
+        let loc = DebugInfoEntry::new(AT_LOCATION, FORM_EXPRLOC, AttrValue::Block(expr));
+        die.attrs.push(loc);
+        die
+    }
+
+    /// 変数を持つCU(ファイル名、オフセットを指定)
+    fn var_cu(file: &str, offset: u64) -> CUHeader {
+        let mut cu = CUHeader::new();
+        cu.version = 4;
+        cu.address_size = 8;
+        cu.offset = offset;
+        cu.dies = vec![
+            node(
+                offset + 0xB,
+                DwTagInfo::CompileUnit,
+                &[(AT_NAME, FORM_STRING, file)],
+            ),
+            node(
+                offset + 0x10,
+                DwTagInfo::BaseType,
+                &[
+                    (AT_BYTE_SIZE, FORM_DATA1, "4"),
+                    (AT_ENCODING, FORM_DATA1, "5"),
+                    (AT_NAME, FORM_STRING, "int"),
+                ],
+            ),
+            static_var(
+                offset + 0x20,
+                &[
+                    (AT_NAME, FORM_STRING, "counter"),
+                    (AT_TYPE, FORM_REF4, "16"),
+                ],
+                0x4000 + offset,
+            ),
+            // extern宣言(アドレスを持たない)
+            node(
+                offset + 0x30,
+                DwTagInfo::Variable,
+                &[
+                    (AT_NAME, FORM_STRING, "shared"),
+                    (AT_TYPE, FORM_REF4, "16"),
+                    (AT_DECLARATION, FORM_FLAG_PRESENT, "flag is present"),
+                ],
+            ),
+            // 宣言を参照する定義
+            static_var(
+                offset + 0x40,
+                &[(AT_SPECIFICATION, FORM_REF4, "48")],
+                0x5000 + offset,
+            ),
+        ];
+        cu.dies[0].children = vec![1, 2, 3, 4];
+        cu
+    }
+
+    #[test]
+    fn test_global_vars() {
+        let mut sec = DebugInfoSection::new();
+        sec.units = vec![var_cu("src/foo.c", 0), var_cu("src/bar.c", 0x100)]
+            .into_iter()
+            .map(OnceCell::from)
+            .collect();
+        {
+            let vars = sec.get_global_vars();
+            let names = vars
+                .iter()
+                .map(|v| (v.get_file(), v.get_name(), v.get_addr()))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                vec![
+                    ("src/foo.c", "counter", 0x4000),
+                    ("src/foo.c", "shared", 0x5000),
+                    ("src/bar.c", "counter", 0x4100),
+                    ("src/bar.c", "shared", 0x5100),
+                ],
+                names
+            );
+            assert_eq!("int", vars[1].get_type().get_name());
+        }
+        {
+            // ファイル指定で同名の変数を区別
+            let v = sec.search_global_var("counter", None).unwrap();
+            assert_eq!(0x4000, v.get_addr());
+            let v = sec.search_global_var("counter", Some("bar.c")).unwrap();
+            assert_eq!(0x4100, v.get_addr());
+            let v = sec.search_global_var("counter", Some("src/bar.c")).unwrap();
+            assert_eq!(0x4100, v.get_addr());
+            assert!(sec.search_global_var("counter", Some("ar.c")).is_none());
+            assert!(sec.search_global_var("missing", None).is_none());
+        }
+    }
+
+    /// DW_OP_fbregの位置式を持つ変数DIE
+    fn local_var(offset: u64, tag: DwTagInfo, name: &str, fb_offset: u8) -> DieNode {
+        let mut die = node(offset, tag, &[(AT_NAME, FORM_STRING, name)]);
+        let expr = vec![0x91, fb_offset];
+        let loc = DebugInfoEntry::new(AT_LOCATION, FORM_EXPRLOC, AttrValue::Block(expr));
+        die.attrs.push(loc);
+        die
+    }
+
+    /// アドレス範囲を持つDIE
+    fn pc_range(offset: u64, tag: DwTagInfo, low: u64, len: u64) -> DieNode {
+        node(
+            offset,
+            tag,
+            &[
+                (AT_LOW_PC, FORM_ADDR, &low.to_string()),
+                (AT_HIGH_PC, FORM_DATA8, &len.to_string()),
+            ],
+        )
+    }
+
+    /// 入れ子のブロックで変数をシャドーイングする関数を持つCU
+    ///
+    /// func(0x1000-0x1100)       : a(引数), x
+    ///   block1(0x1010-0x1080)   : x, y
+    ///     block2(DW_AT_ranges)  : x (0x1020-0x1030, 0x1040-0x1050)
+    ///   block3(0x1090-0x10A0)   : y
+    fn scope_section() -> DebugInfoSection {
+        let mut cu = CUHeader::new();
+        cu.version = 4;
+        cu.address_size = 8;
+        cu.dies = vec![
+            pc_range(0xB, DwTagInfo::CompileUnit, 0x1000, 0x100),
+            node(0x10, DwTagInfo::BaseType, &[(AT_NAME, FORM_STRING, "int")]),
+            pc_range(0x20, DwTagInfo::Subprogram, 0x1000, 0x100),
+            local_var(0x28, DwTagInfo::FormalParamter, "a", 0x6C),
+            local_var(0x2C, DwTagInfo::Variable, "x", 0x68),
+            pc_range(0x30, DwTagInfo::LexicalBlock, 0x1010, 0x70),
+            local_var(0x34, DwTagInfo::Variable, "x", 0x64),
+            local_var(0x38, DwTagInfo::Variable, "y", 0x60),
+            node(
+                0x3C,
+                DwTagInfo::LexicalBlock,
+                &[(AT_RANGES, FORM_SEC_OFFSET, "0")],
+            ),
+            local_var(0x40, DwTagInfo::Variable, "x", 0x5C),
+            pc_range(0x48, DwTagInfo::LexicalBlock, 0x1090, 0x10),
+            local_var(0x4C, DwTagInfo::Variable, "y", 0x58),
+        ];
+        cu.dies[0].children = vec![1, 2];
+        cu.dies[2].children = vec![3, 4, 5, 10];
+        cu.dies[5].children = vec![6, 7, 8];
+        cu.dies[8].children = vec![9];
+        cu.dies[10].children = vec![11];
+
+        let mut sec = DebugInfoSection::new();
+        sec.units = vec![cu].into_iter().map(OnceCell::from).collect();
+        sec.ranges = [0x20u64, 0x30, 0x40, 0x50, 0, 0]
+            .iter()
+            .flat_map(|v| v.to_le_bytes().to_vec())
+            .collect();
+        sec
+    }
+
+    /// スコープ内の変数を(名前, フレームベースからのオフセット)で取得
+    fn scope_vars(sec: &DebugInfoSection, pc: u64) -> Vec<(String, u8)> {
+        sec.search_frames(pc)[0]
+            .get_vars()
+            .iter()
+            .map(|v| (v.get_name().to_string(), v.get_location()[1]))
+            .collect()
+    }
+
+    #[test]
+    fn test_search_scope() {
+        let sec = scope_section();
+        let to_vec = |v: &[(&str, u8)]| {
+            v.iter()
+                .map(|(n, o)| (n.to_string(), *o))
+                .collect::<Vec<(String, u8)>>()
+        };
+        {
+            // block2の中(内側のxが優先)
+            let expect = to_vec(&[
+                ("x", 0x5C),
+                ("x", 0x64),
+                ("y", 0x60),
+                ("a", 0x6C),
+                ("x", 0x68),
+            ]);
+            assert_eq!(expect, scope_vars(&sec, 0x1025));
+            assert_eq!(expect, scope_vars(&sec, 0x1045));
+            let scope = &sec.search_frames(0x1025)[0];
+            assert_eq!(0x5C, scope.search_var("x").unwrap().get_location()[1]);
+            assert!(scope.search_var("a").unwrap().is_param());
+        }
+        {
+            // block1の中で、block2の範囲外
+            let expect = to_vec(&[("x", 0x64), ("y", 0x60), ("a", 0x6C), ("x", 0x68)]);
+            assert_eq!(expect, scope_vars(&sec, 0x1035));
+        }
+        {
+            // block3の中
+            let expect = to_vec(&[("y", 0x58), ("a", 0x6C), ("x", 0x68)]);
+            assert_eq!(expect, scope_vars(&sec, 0x1095));
+        }
+        {
+            // 関数外
+            assert!(sec.search_frames(0x2000).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_to_local_var_info() {
+        let sec = scope_section();
+        let cu = CUHeader::new();
+        {
+            // 配置先のない変数は、位置式を空とする
+            let die = node(0x10, DwTagInfo::Variable, &[(AT_NAME, FORM_STRING, "v")]);
+            let var = sec.to_local_var_info(&cu, &die, 0).unwrap();
+            assert_eq!("v", var.get_name());
+            assert!(var.get_location().is_empty());
+        }
+        {
+            // 宣言のみの変数は対象外
+            let die = node(
+                0x10,
+                DwTagInfo::Variable,
+                &[
+                    (AT_NAME, FORM_STRING, "v"),
+                    (AT_DECLARATION, FORM_FLAG_PRESENT, ""),
+                ],
+            );
+            assert!(sec.to_local_var_info(&cu, &die, 0).is_none());
+        }
+        {
+            let die = local_var(0x10, DwTagInfo::FormalParamter, "p", 0x70);
+            let var = sec.to_local_var_info(&cu, &die, 0).unwrap();
+            assert!(var.is_param());
+            assert_eq!(vec![0x91, 0x70], var.get_location());
+        }
+    }
+
+    #[test]
+    fn test_search_local_var_funcs() {
+        let sec = scope_section();
+        let cases = vec![
+            ("a", vec!["??"]),
+            ("y", vec!["??"]), // レキシカルブロック内の変数
+            ("z", vec![]),
+            ("int", vec![]), // 変数以外のDIEは対象外
+        ];
+        for (name, expected) in cases {
+            assert_eq!(expected, sec.search_local_var_funcs(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_ranges() {
+        let buf = [0x10u64, 0x20, u64::MAX, 0x5000, 0x1, 0x2, 0, 0, 0x30, 0x40]
+            .iter()
+            .flat_map(|v| v.to_le_bytes().to_vec())
+            .collect::<Vec<u8>>();
+        {
+            // ベースアドレスの切り替え
+            let ranges = DebugInfoSection::to_ranges(&buf, 0, 0x1000);
+            assert_eq!(vec![0x1010..0x1020, 0x5001..0x5002], ranges);
+        }
+        {
+            // 範囲外のオフセット
+            assert!(DebugInfoSection::to_ranges(&buf, 0x1000, 0).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_dw_lang() {
+        struct Test {}
+        impl DwInfo for Test {}
+        let cases = [
+            (0x1, DwLangInfo::C89, "C89"),
+            (0x4, DwLangInfo::CPlusPlus, "C++"),
+            (0xC, DwLangInfo::C99, "C99"),
+            (0x1C, DwLangInfo::Rust, "Rust"),
+            (0x1D, DwLangInfo::C11, "C11"),
+            (0x21, DwLangInfo::CPlusPlus14, "C++14"),
+            (0x2B, DwLangInfo::CPlusPlus20, "C++20"),
+            (0x8001, DwLangInfo::MipsAssembler, "Mips_Assembler"),
+            (0x29, DwLangInfo::Unknown(0x29), "unknown(0x29)"),
+            (0x9999, DwLangInfo::Unknown(0x9999), "unknown(0x9999)"),
+        ];
+        for (code, lang, name) in cases {
+            assert_eq!(lang, Test::to_dw_lang(code));
+            assert_eq!(name, lang.to_string());
+        }
+    }
+
+    #[test]
+    fn test_cu_infos() {
+        const AT_LANGUAGE: u64 = 0x13;
+        const AT_COMP_DIR: u64 = 0x1B;
+        const AT_PRODUCER: u64 = 0x25;
+        let mut sec = DebugInfoSection::new();
+
+        // low_pc/high_pcを持つCU
+        let mut cu = helper_cu("src/main.c", "1", 0x1000);
+        cu.dies[0] = node(
+            0xB,
+            DwTagInfo::CompileUnit,
+            &[
+                (AT_PRODUCER, FORM_STRING, "GNU C17 12.2.0 -g"),
+                (AT_LANGUAGE, FORM_DATA1, "29"),
+                (AT_NAME, FORM_STRING, "src/main.c"),
+                (AT_COMP_DIR, FORM_STRING, "/work"),
+                (AT_LOW_PC, FORM_ADDR, "4096"),
+                (AT_HIGH_PC, FORM_DATA8, "256"),
+            ],
+        );
+        cu.dies[0].children = vec![1];
+        cu.lines.push(line_section());
+
+        // ベースアドレスのlow_pcとDW_AT_rangesを持つCU(範囲は整列・結合する)
+        sec.ranges = [0x2000u64, 0x2010, 0x1000, 0x1010, 0x1010, 0x1020, 0, 0]
+            .iter()
+            .flat_map(|v| v.to_le_bytes().to_vec())
+            .collect();
+        let mut ranges_cu = helper_cu("src/lib.c", "1", 0x1000);
+        ranges_cu.dies[0] = node(
+            0xB,
+            DwTagInfo::CompileUnit,
+            &[
+                (AT_NAME, FORM_STRING, "src/lib.c"),
+                (AT_LOW_PC, FORM_ADDR, "0"),
+                (AT_RANGES, FORM_SEC_OFFSET, "0"),
+            ],
+        );
+        sec.units = vec![cu, ranges_cu]
+            .into_iter()
+            .map(OnceCell::from)
+            .collect();
+
+        let infos = sec.get_cu_infos();
+        assert_eq!(2, infos.len());
+        {
+            let info = &infos[0];
+            assert_eq!(0, info.get_index());
+            assert_eq!("src/main.c", info.get_name());
+            assert_eq!("/work", info.get_comp_dir());
+            assert_eq!("GNU C17 12.2.0 -g", info.get_producer());
+            assert_eq!("C11", info.get_language());
+            assert_eq!(4, info.get_version());
+            assert_eq!(
+                &[Range {
+                    start: 0x1000,
+                    end: 0x1100
+                }],
+                info.get_ranges()
+            );
+            assert_eq!(2, info.get_die_count());
+            assert_eq!(line_section().rows.len(), info.get_line_rows());
+        }
+        {
+            let info = &infos[1];
+            assert_eq!("", info.get_producer());
+            assert_eq!("unknown", info.get_language());
+            assert_eq!(&[0x1000..0x1020, 0x2000..0x2010], info.get_ranges());
+            assert_eq!(0, info.get_line_rows());
+        }
+        assert_eq!("src/lib.c", sec.get_cu_info(1).unwrap().get_name());
+        assert!(sec.get_cu_info(2).is_none());
+    }
+
+    /// 1行にメソッドチェーンを持つ行番号プログラム
+    ///
+    /// 10行目: 0x1000(5列), 0x1004(10列), 0x100A(20列), 0x100C(20列, 文の先頭ではない)
+    /// 11行目: 0x1010(1列)
+    fn line_section() -> DebugLineSection {
+        let mut h = DebugLineHeader::new();
+        h.version = 4;
+        h.min_inst_len = 1;
+        h.is_stmt = 1;
+        h.line_base = -5;
+        h.line_range = 14;
+        h.opcode_base = 13;
+        h.standard_opcode_len = vec![0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1];
+        h.inc_dirs = vec!["src".to_string()];
+        let mut f = Filenames::new();
+        f.name = "main.rs".to_string();
+        f.dir_entry = 1;
+        h.file_names = vec![f];
+
+        let mut program = vec![0, 9, DW_LNE_SET_ADDRESS];
+        program.extend_from_slice(&0x1000u64.to_le_bytes());
+        program.extend_from_slice(&[
+            DW_LNS_ADVANCE_LINE,
+            9,
+            DW_LNS_SET_COLUMN,
+            5,
+            DW_LNS_COPY,
+            DW_LNS_SET_COLUMN,
+            10,
+            74, // アドレス+4
+            DW_LNS_SET_COLUMN,
+            20,
+            102, // アドレス+6
+            DW_LNS_NEGATE_STMT,
+            46, // アドレス+2
+            DW_LNS_NEGATE_STMT,
+            DW_LNS_SET_COLUMN,
+            1,
+            75, // アドレス+4, 行+1
+            DW_LNS_ADVANCE_PC,
+            4,
+            0,
+            1,
+            DW_LNE_END_SEQUENCE,
+        ]);
+
+        let mut sec = DebugLineSection::new(0);
+        sec.rows = DebugLineSection::run_program(&h, &program);
+        sec.cu_header.push(h);
+        sec
+    }
+
+    #[test]
+    fn test_line_program() {
+        let sec = line_section();
+        {
+            let rows = sec
+                .rows
+                .iter()
+                .map(|r| (r.address, r.line, r.column, r.is_stmt, r.end_sequence))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                vec![
+                    (0x1000, 10, 5, true, false),
+                    (0x1004, 10, 10, true, false),
+                    (0x100A, 10, 20, true, false),
+                    (0x100C, 10, 20, false, false),
+                    (0x1010, 11, 1, true, false),
+                    (0x1014, 11, 1, true, true),
+                ],
+                rows
+            );
+        }
+        {
+            // アドレスからソース位置
+            let line = sec.search_line(0x1005).unwrap();
+            assert_eq!("src/main.rs:10:10", line.to_string());
+            assert_eq!(0x1004, line.get_address());
+            assert_eq!(
+                "src/main.rs:10:20",
+                sec.search_line(0x100D).unwrap().to_string()
+            );
+            assert_eq!(
+                "src/main.rs:11:1",
+                sec.search_line(0x1013).unwrap().to_string()
+            );
+            assert!(sec.search_line(0x1014).is_none());
+            assert!(sec.search_line(0xFFF).is_none());
+        }
+        {
+            // 1行にある文の開始位置をすべて取得
+            let addrs = sec
+                .search_addrs("main.rs", 10)
+                .iter()
+                .map(|l| (l.get_address(), l.column))
+                .collect::<Vec<_>>();
+            assert_eq!(vec![(0x1000, 5), (0x1004, 10), (0x100A, 20)], addrs);
+            assert_eq!(3, sec.search_addrs("src/main.rs", 10).len());
+            assert!(sec.search_addrs("ain.rs", 10).is_empty());
+            assert!(sec.search_addrs("main.rs", 12).is_empty());
+        }
+        {
+            // 列情報がなければfile:line
+            let mut line = sec.search_line(0x1000).unwrap();
+            line.column = 0;
+            assert_eq!("src/main.rs:10", line.to_string());
         }
     }
 
-    /// debug_infoセクションロード
-    fn load(
-        &mut self,
-        reader: &mut BufReader<File>,
-        info_h: &ElfSecHeader,
-        abbrev_h: &ElfSecHeader,
-        str_h: &ElfSecHeader,
-    ) -> Result<()> {
-        // debug_str読み込み
-        reader.seek(SeekFrom::Start(str_h.get_offset()))?;
-        let mut str_buf: Vec<u8> = vec![0; str_h.get_size() as usize];
-        reader.read_exact(&mut str_buf)?;
+    #[test]
+    fn test_search_line_addr() {
+        let mut dwarf = Dwarf::new();
+        dwarf.debug_info.units = vec![line_section()]
+            .into_iter()
+            .map(|l| {
+                let mut cu = CUHeader::new();
+                cu.lines.push(l);
+                OnceCell::from(cu)
+            })
+            .collect();
 
-        // debug_infoセクションへ移動
-        reader.seek(SeekFrom::Start(info_h.get_offset()))?;
+        // 複数の文を持つ行は、最も小さい文の開始位置を1つだけ返す
+        assert_eq!(3, dwarf.search_line_addrs("main.rs", 10).len());
+        let info = dwarf.search_line_addr("main.rs", 10).unwrap();
+        assert_eq!((0x1000, 5), (info.get_address(), info.column));
+        assert_eq!(
+            Some(0x1010),
+            dwarf
+                .search_line_addr("main.rs", 11)
+                .map(|i| i.get_address())
+        );
+        assert!(dwarf.search_line_addr("main.rs", 12).is_none());
+        assert!(dwarf.search_line_addr("lib.rs", 10).is_none());
+    }
 
-        // CU Headerを読み込み、CUの情報をロードする
-        let mut read_size = 0;
-        loop {
-            let mut cu_h = CUHeader::new();
+    #[test]
+    fn test_line_table() {
+        // 同じ行番号テーブルを持つCUと、別ファイルのCU
+        let mut lib = line_section();
+        lib.cu_header[0].file_names[0].name = "lib.rs".to_string();
+        let mut dwarf = Dwarf::new();
+        dwarf.debug_info.units = vec![line_section(), line_section(), lib]
+            .into_iter()
+            .map(|l| {
+                let mut cu = CUHeader::new();
+                cu.lines.push(l);
+                OnceCell::from(cu)
+            })
+            .collect();
+        {
+            // 連続する範囲は、文の開始位置かが同じであればまとめる
+            let lines = dwarf
+                .get_line_table("main.rs")
+                .iter()
+                .map(|l| {
+                    (
+                        l.get_file().to_string(),
+                        l.get_line(),
+                        l.get_ranges().to_vec(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(
+                vec![
+                    (
+                        "src/main.rs".to_string(),
+                        10,
+                        vec![(0x1000..0x100C, true), (0x100C..0x1010, false)]
+                    ),
+                    ("src/main.rs".to_string(), 11, vec![(0x1010..0x1014, true)]),
+                ],
+                lines
+            );
+            assert_eq!(2, dwarf.get_line_table("src/lib.rs").len());
+            assert!(dwarf.get_line_table("ain.rs").is_empty());
+        }
+        {
+            // ファイル毎の、コードを持つ行数
+            assert_eq!(
+                vec![
+                    ("src/lib.rs".to_string(), 2),
+                    ("src/main.rs".to_string(), 2)
+                ],
+                dwarf.get_line_summary()
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_source_path() {
+        let cases = vec![
+            ("/work", "src/main.rs", "/work/src/main.rs"),
+            ("/work/", "./src/../main.rs", "/work/main.rs"),
+            ("/work", "/usr/include/stdio.h", "/usr/include/stdio.h"),
+            ("/work/build", "../../../src/a.c", "/src/a.c"),
+            // コンパイル時のディレクトリが不明であれば、相対パスのまま
+            ("", "src/./main.rs", "src/main.rs"),
+            ("", "../main.rs", "../main.rs"),
+        ];
+        for (comp_dir, file, expected) in cases {
+            assert_eq!(expected, resolve_source_path(comp_dir, file), "{}", file);
+        }
+    }
 
-            // len
-            let mut word = [0; 4];
-            reader.read_exact(&mut word)?;
-            cu_h.len = u32::from_le_bytes(word);
-            read_size += 4;
+    #[test]
+    fn test_source_files() {
+        const AT_COMP_DIR: u64 = 0x1B;
+        // 同じヘッダーを共有する2つのCUと、コンパイル時のディレクトリが異なるCU
+        let mut lib = line_section();
+        lib.cu_header[0].inc_dirs.push("/usr/include".to_string());
+        let mut header = Filenames::new();
+        header.name = "stdio.h".to_string();
+        header.dir_entry = 2;
+        lib.cu_header[0].file_names.push(header);
+        let mut dwarf = Dwarf::new();
+        dwarf.debug_info.units = vec![
+            ("/work", line_section()),
+            ("/work", line_section()),
+            ("/other", lib),
+        ]
+        .into_iter()
+        .map(|(dir, l)| {
+            let mut cu = CUHeader::new();
+            cu.dies = vec![node(
+                0xB,
+                DwTagInfo::CompileUnit,
+                &[(AT_COMP_DIR, FORM_STRING, dir)],
+            )];
+            cu.lines.push(l);
+            OnceCell::from(cu)
+        })
+        .collect();
+        assert_eq!(
+            vec![
+                "/other/src/main.rs".to_string(),
+                "/usr/include/stdio.h".to_string(),
+                "/work/src/main.rs".to_string(),
+            ],
+            dwarf.source_files()
+        );
+        assert!(Dwarf::new().source_files().is_empty());
+    }
 
-            // load actual len when 64bit mode
-            if cu_h.len == 0xFFFF_FFFF {
-                // 64bit mode
-                let mut word64 = [0; 8];
-                reader.read_exact(&mut word64)?;
-                cu_h.actual_len = u64::from_le_bytes(word64);
-                read_size += 8;
+    /// インライン展開の連鎖を持つCU
+    ///
+    /// main(0x1000-0x1100)
+    ///   middle(inlined, test.cpp:20から呼び出し, 0x1010-0x1050)
+    ///     block(0x1018-0x1040)
+    ///       inner(inlined, test.cpp:10から呼び出し, 0x1020-0x1030) : v(引数)
+    fn inline_section() -> DebugInfoSection {
+        const AT_ABSTRACT_ORIGIN: u64 = 0x31;
+        const AT_CALL_FILE: u64 = 0x58;
+        const AT_CALL_LINE: u64 = 0x59;
+        let inlined = |offset: u64, origin: &str, line: &str, low: u64, len: u64| {
+            let mut die = pc_range(offset, DwTagInfo::InlinedSubroutine, low, len);
+            let attrs = [
+                (AT_ABSTRACT_ORIGIN, FORM_REF4, origin),
+                (AT_CALL_FILE, FORM_DATA1, "2"),
+                (AT_CALL_LINE, FORM_DATA1, line),
+            ];
+            for (at, form, data) in attrs.iter() {
+                die.attrs.push(attr(*at, *form, data));
             }
+            die
+        };
 
-            // version
-            let mut half_word = [0; 2];
-            reader.read_exact(&mut half_word)?;
-            cu_h.version = u16::from_le_bytes(half_word);
-            read_size += 2;
+        // 引数の名前は抽象インスタンスから取得
+        let mut param = local_var(0x70, DwTagInfo::FormalParamter, "", 0x6C);
+        param.attrs[0] = attr(AT_ABSTRACT_ORIGIN, FORM_REF4, "24");
 
-            // abb_rev offset
-            reader.read_exact(&mut word)?;
-            cu_h.abb_rev_offset = u32::from_le_bytes(word);
-            read_size += 4;
+        let mut cu = CUHeader::new();
+        cu.version = 4;
+        cu.address_size = 8;
+        cu.files = vec!["main.cpp".to_string(), "test.cpp".to_string()];
+        cu.dies = vec![
+            pc_range(0xB, DwTagInfo::CompileUnit, 0x1000, 0x100),
+            // 抽象インスタンス(inner, middle)
+            node(
+                0x10,
+                DwTagInfo::Subprogram,
+                &[(AT_NAME, FORM_STRING, "inner")],
+            ),
+            node(
+                0x18,
+                DwTagInfo::FormalParamter,
+                &[(AT_NAME, FORM_STRING, "v")],
+            ),
+            node(
+                0x20,
+                DwTagInfo::Subprogram,
+                &[(AT_NAME, FORM_STRING, "middle")],
+            ),
+            // 具象インスタンス
+            pc_range(0x30, DwTagInfo::Subprogram, 0x1000, 0x100),
+            inlined(0x40, "32", "20", 0x1010, 0x40),
+            pc_range(0x50, DwTagInfo::LexicalBlock, 0x1018, 0x28),
+            inlined(0x60, "16", "10", 0x1020, 0x10),
+            param,
+        ];
+        cu.dies[4].attrs.push(attr(AT_NAME, FORM_STRING, "main"));
+        cu.dies[0].children = vec![1, 3, 4];
+        cu.dies[1].children = vec![2];
+        cu.dies[4].children = vec![5];
+        cu.dies[5].children = vec![6];
+        cu.dies[6].children = vec![7];
+        cu.dies[7].children = vec![8];
 
-            // address size
-            let mut byte = [0; 1];
-            reader.read_exact(&mut byte)?;
-            cu_h.address_size = u8::from_le_bytes(byte);
-            read_size += 1;
+        let mut sec = DebugInfoSection::new();
+        sec.units = vec![cu].into_iter().map(OnceCell::from).collect();
+        sec
+    }
 
-            // 対応するabbrevをロード
-            let mut abbrev = DebugAbbRevSection::new();
-            let offset = cu_h.abb_rev_offset;
-            abbrev.load(reader, abbrev_h, offset as u64)?;
+    #[test]
+    fn test_inlined_frames() {
+        let sec = inline_section();
+        let to_frames = |pc: u64| {
+            sec.search_frames(pc)
+                .iter()
+                .map(|f| {
+                    (
+                        f.get_func().to_string(),
+                        f.get_call_site().map(|c| c.to_string()),
+                        f.get_vars().len(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        {
+            // inner -> middle -> mainの順
+            assert_eq!(
+                vec![
+                    ("inner".to_string(), Some("test.cpp:10".to_string()), 1),
+                    ("middle".to_string(), Some("test.cpp:20".to_string()), 0),
+                    ("main".to_string(), None, 0),
+                ],
+                to_frames(0x1025)
+            );
+            let frames = sec.search_frames(0x1025);
+            assert!(frames[0].is_inlined());
+            assert!(!frames[2].is_inlined());
+            assert_eq!("v", frames[0].get_vars()[0].get_name());
+        }
+        {
+            // innerの範囲外
+            assert_eq!(
+                vec![
+                    ("middle".to_string(), Some("test.cpp:20".to_string()), 0),
+                    ("main".to_string(), None, 0),
+                ],
+                to_frames(0x1045)
+            );
+        }
+        {
+            // インライン展開なし
+            assert_eq!(vec![("main".to_string(), None, 0)], to_frames(0x1080));
+        }
+    }
 
-            // abbrevを読み取りながら、debug_infoセクションをロードしていく
-            reader.seek(SeekFrom::Start(info_h.get_offset() + read_size))?;
-            let die_size = self.parse(reader, &mut cu_h, &abbrev, &str_buf);
-            read_size += die_size;
+    /// 同名のstatic関数(helper)を持つCU
+    fn helper_cu(name: &str, file_no: &str, low: u64) -> CUHeader {
+        const AT_DECL_FILE: u64 = 0x3A;
+        const AT_DECL_LINE: u64 = 0x3B;
+        let mut func = pc_range(0x20, DwTagInfo::Subprogram, low, 0x10);
+        let attrs = [
+            (AT_NAME, FORM_STRING, "helper"),
+            (AT_DECL_FILE, FORM_DATA1, file_no),
+            (AT_DECL_LINE, FORM_DATA1, "5"),
+        ];
+        for (at, form, data) in attrs.iter() {
+            func.attrs.push(attr(*at, *form, data));
+        }
 
-            // headerと対応するabbrevを保存
-            self.header.push(cu_h);
+        let mut cu = CUHeader::new();
+        cu.version = 4;
+        cu.address_size = 8;
+        cu.files = vec![name.to_string(), "common.h".to_string()];
+        cu.dies = vec![
+            node(0xB, DwTagInfo::CompileUnit, &[(AT_NAME, FORM_STRING, name)]),
+            func,
+        ];
+        cu.dies[0].children = vec![1];
+        cu
+    }
 
-            // debug_infoセクションすべてを読み込めば終了
-            if read_size == info_h.get_size() {
-                break;
-            }
+    #[test]
+    fn test_search_funcs() {
+        let mut sec = DebugInfoSection::new();
+        sec.units = vec![
+            helper_cu("src/parser.c", "1", 0x2000),
+            helper_cu("src/lexer.c", "1", 0x1000),
+        ]
+        .into_iter()
+        .map(OnceCell::from)
+        .collect();
+        let to_funcs = |file: Option<&str>| {
+            sec.search_funcs("helper", file)
+                .iter()
+                .map(|f| {
+                    (
+                        f.get_file().to_string(),
+                        f.get_line(),
+                        f.get_addr(),
+                        f.get_size(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        {
+            // ファイル名順
+            assert_eq!(
+                vec![
+                    ("src/lexer.c".to_string(), 5, 0x1000, 0x10),
+                    ("src/parser.c".to_string(), 5, 0x2000, 0x10),
+                ],
+                to_funcs(None)
+            );
+        }
+        {
+            // ファイル指定
+            assert_eq!(
+                vec![("src/parser.c".to_string(), 5, 0x2000, 0x10)],
+                to_funcs(Some("parser.c"))
+            );
+        }
+        {
+            // 存在しないファイル・関数
+            assert!(to_funcs(Some("main.c")).is_empty());
+            assert!(sec.search_funcs("main", None).is_empty());
         }
-
-        Ok(())
     }
 
-    /// debug_infoセクションパーズ
+    /// 名前空間・クラスを持つCU
     ///
-    /// parseした結果とリードしたサイズを返却する
-    fn parse(
-        &mut self,
-        reader: &mut BufReader<File>,
-        cu_h: &mut CUHeader,
-        abbrev: &DebugAbbRevSection,
-        str_buf: &[u8],
-    ) -> u64 {
-        // DIEをロード
-        let mut read_size = 0; // lenを除いたヘッダサイズが初期値
-        loop {
-            // debug_infoセクションから対応するabbrev noを読み込む
-            let (size, abbrev_no) = Self::decode(reader).unwrap();
-            read_size += size;
+    /// ns
+    ///   value(0x4000)
+    ///   inner
+    ///     func(0x1000)
+    /// (anonymous namespace)
+    ///   func(0x1100)
+    /// Test
+    ///   test(宣言のみ)
+    /// test(Test::testの定義, 0x1200)
+    fn namespace_cu() -> CUHeader {
+        let name = |n| [(AT_NAME, FORM_STRING, n)];
+        let mut method = pc_range(0x60, DwTagInfo::Subprogram, 0x1200, 0x10);
+        method.attrs.push(attr(AT_SPECIFICATION, FORM_REF4, "85"));
 
-            // すべてのDIEを読み込めば終了
-            let end = (read_size + 7) as u32;
-            if cu_h.len == end {
-                break;
-            }
+        let mut cu = CUHeader::new();
+        cu.version = 4;
+        cu.address_size = 8;
+        cu.dies = vec![
+            node(0xB, DwTagInfo::CompileUnit, &name("ns.cpp")),
+            node(0x10, DwTagInfo::Namespace, &name("ns")),
+            static_var(0x18, &name("value"), 0x4000),
+            node(0x20, DwTagInfo::Namespace, &name("inner")),
+            pc_range(0x28, DwTagInfo::Subprogram, 0x1000, 0x10),
+            node(0x40, DwTagInfo::Namespace, &[]),
+            pc_range(0x48, DwTagInfo::Subprogram, 0x1100, 0x10),
+            node(0x50, DwTagInfo::ClassType, &name("Test")),
+            node(0x55, DwTagInfo::Subprogram, &name("test")),
+            method,
+        ];
+        for i in [4, 6] {
+            cu.dies[i].attrs.push(attr(AT_NAME, FORM_STRING, "func"));
+        }
+        cu.dies[0].children = vec![1, 5, 7, 9];
+        cu.dies[1].children = vec![2, 3];
+        cu.dies[3].children = vec![4];
+        cu.dies[5].children = vec![6];
+        cu.dies[7].children = vec![8];
+        cu.index_scopes();
+        cu
+    }
 
-            // abbrev_no=ゼロならば、nullエントリーなので次のエントリーへ
-            if 0 == abbrev_no {
-                continue;
-            }
+    #[test]
+    fn test_qualified_name() {
+        let mut sec = DebugInfoSection::new();
+        sec.units = vec![namespace_cu()]
+            .into_iter()
+            .map(OnceCell::from)
+            .collect();
+        let to_names = |name: &str| {
+            sec.search_funcs(name, None)
+                .iter()
+                .map(|f| (f.get_name().to_string(), f.get_addr()))
+                .collect::<Vec<_>>()
+        };
+        {
+            // 完全修飾名・後方一致
+            let func = vec![("ns::inner::func".to_string(), 0x1000)];
+            assert_eq!(func, to_names("ns::inner::func"));
+            assert_eq!(func, to_names("inner::func"));
+        }
+        {
+            // 名前要素の途中からは一致しない
+            assert!(to_names("ner::func").is_empty());
+            assert!(to_names("ns::func").is_empty());
+        }
+        {
+            // 無名の名前空間を含め、同名の関数はすべて返却
+            assert_eq!(
+                vec![
+                    ("ns::inner::func".to_string(), 0x1000),
+                    ("(anonymous namespace)::func".to_string(), 0x1100),
+                ],
+                to_names("func")
+            );
+        }
+        {
+            // 定義の名前は、クラス内の宣言から修飾
+            assert_eq!(vec![("Test::test".to_string(), 0x1200)], to_names("test"));
+        }
+        {
+            // 名前空間内の変数
+            let vars = sec.get_global_vars();
+            assert_eq!(1, vars.len());
+            assert_eq!("ns::value", vars[0].get_name());
+            assert!(sec.search_global_var("value", None).is_some());
+            assert!(sec.search_global_var("ns::value", None).is_some());
+            assert!(sec.search_global_var("inner::value", None).is_none());
+        }
+    }
 
-            // noから配列インデックスへ(ELFには1オリジンで格納)
-            let index = abbrev_no - 1;
-            let record = abbrev.get_record(index as usize);
+    #[test]
+    fn test_read_value() {
+        let sec = DebugInfoSection::new();
+        let str_buf = b"\0main\0";
+        // (DW_FORM, データ, 読み込みサイズ, 属性値)
+        let cases: Vec<(u64, &[u8], u64, AttrValue)> = vec![
+            (
+                0x1,
+                &[0x10, 0x20, 0, 0, 0, 0, 0, 0],
+                8,
+                AttrValue::Addr(0x2010),
+            ),
+            (0xB, &[0xFF], 1, AttrValue::Udata(0xFF)),
+            (0x5, &[0x34, 0x12], 2, AttrValue::Udata(0x1234)),
+            (
+                0x6,
+                &[0x78, 0x56, 0x34, 0x12],
+                4,
+                AttrValue::Udata(0x1234_5678),
+            ),
+            (
+                0x7,
+                &[1, 0, 0, 0, 0, 0, 0, 0x80],
+                8,
+                AttrValue::Udata(0x8000_0000_0000_0001),
+            ),
+            (0xF, &[0xE5, 0x8E, 0x26], 3, AttrValue::Udata(624485)),
+            (0xD, &[0x05], 1, AttrValue::Sdata(5)),
+            (0xD, &[0x7F], 1, AttrValue::Sdata(-1)),
+            (0xD, &[0x80, 0x7F], 2, AttrValue::Sdata(-128)),
+            (0x17, &[0x40, 0, 0, 0], 4, AttrValue::SecOffset(0x40)),
+            (0x11, &[0x2A], 1, AttrValue::Ref(0x2A)),
+            (0x12, &[0x00, 0x01], 2, AttrValue::Ref(0x100)),
+            (0x13, &[0x00, 0x00, 0x01, 0x00], 4, AttrValue::Ref(0x10000)),
+            (
+                0x14,
+                &[0, 0, 0, 0, 1, 0, 0, 0],
+                8,
+                AttrValue::Ref(0x1_0000_0000),
+            ),
+            (0x8, b"int\0", 4, AttrValue::Str("int".to_string())),
+            // UTF-8でない文字列は、置き換えて読み込む
+            (
+                0x8,
+                b"a\xFFb\0",
+                4,
+                AttrValue::Str("a\u{FFFD}b".to_string()),
+            ),
+            (0xE, &[1, 0, 0, 0], 4, AttrValue::Str("main".to_string())),
+            (
+                0x18,
+                &[2, 0x91, 0x6C],
+                3,
+                AttrValue::Block(vec![0x91, 0x6C]),
+            ),
+            (0xA, &[1, 0x50], 2, AttrValue::Block(vec![0x50])),
+            (0x19, &[], 0, AttrValue::Flag(true)),
+            (0xC, &[0], 1, AttrValue::Flag(false)),
+            (0x15, &[0x80, 0x01], 2, AttrValue::Ref(0x80)),
+            (0x9, &[1, 0x50], 2, AttrValue::Block(vec![0x50])),
+            (0x3, &[1, 0, 0x50], 3, AttrValue::Block(vec![0x50])),
+            (0x4, &[1, 0, 0, 0, 0x50], 5, AttrValue::Block(vec![0x50])),
+            (0x20, &[1; 8], 8, AttrValue::Block(vec![1; 8])),
+            (0x1E, &[2; 16], 16, AttrValue::Block(vec![2; 16])),
+            (0x16, &[0xB, 0x2A], 2, AttrValue::Udata(0x2A)),
+        ];
+        for (form, data, size, value) in cases {
+            let mut reader = data;
+            assert_eq!(
+                (size, value),
+                sec.read_value(&mut reader, form, str_buf, 4).unwrap(),
+                "DW_FORM 0x{:x}",
+                form
+            );
+            assert!(reader.is_empty(), "DW_FORM 0x{:x}", form);
+        }
 
-            // DW_FORMに応じたデータを読み取る
-            for (form, at) in record.attr_form.iter().zip(record.attr_name.iter()) {
-                let data = match Self::to_dw_form(*form) {
-                    DwFormInfo::Strp => {
-                        // DIEにはdebug_strのオフセットが入っている
-                        let mut buf = [0; 4];
-                        let offset = match reader.read_exact(&mut buf) {
-                            Ok(_) => u32::from_le_bytes(buf),
-                            Err(e) => panic!(
-                                "[DebugInfoSection::parse] cannot read from debug_str {:?}",
-                                e
-                            ),
-                        };
-                        read_size += 4;
-
-                        // debug_strbufセクションから対応する文字列を読み込む
-                        self.to_string(str_buf, offset as usize)
-                    }
-                    DwFormInfo::Addr => {
-                        // debug_infoセクションに即値が格納
-                        let mut buf = [0; 8];
-                        let addr = match reader.read_exact(&mut buf) {
-                            Ok(_) => u64::from_le_bytes(buf),
-                            Err(e) => panic!(
-                                "[DebugInfoSection::parse] cannot read from debug_info {:?}",
-                                e
-                            ),
-                        };
-                        read_size += 8;
-                        addr.to_string()
-                    }
-                    DwFormInfo::Data1 => {
-                        // 1byteデータがdebug_infoセクションに格納
-                        let mut buf = [0; 1];
-                        let data = match reader.read_exact(&mut buf) {
-                            Ok(_) => u8::from_le_bytes(buf),
-                            Err(e) => panic!(
-                                "[DebugInfoSection::parse] cannot read from debug_info {:?}",
-                                e
-                            ),
-                        };
-                        read_size += 1;
-                        data.to_string()
-                    }
-                    DwFormInfo::Data2 => {
-                        // 2byteデータがdebug_infoセクションに格納
-                        let mut buf = [0; 2];
-                        let data = match reader.read_exact(&mut buf) {
-                            Ok(_) => u16::from_le_bytes(buf),
-                            Err(e) => panic!(
-                                "[DebugInfoSection::parse] cannot read from debug_info {:?}",
-                                e
-                            ),
-                        };
-                        read_size += 2;
-                        data.to_string()
-                    }
-                    DwFormInfo::Data4 => {
-                        // 4byteデータがdebug_infoセクションに格納
-                        let mut buf = [0; 4];
-                        let data = match reader.read_exact(&mut buf) {
-                            Ok(_) => u32::from_le_bytes(buf),
-                            Err(e) => panic!(
-                                "[DebugInfoSection::parse] cannot read from debug_info {:?}",
-                                e
-                            ),
-                        };
-                        read_size += 4;
-                        data.to_string()
-                    }
-                    DwFormInfo::Data8 => {
-                        // 8byteデータがdebug_infoセクションに格納
-                        let mut buf = [0; 8];
-                        let data = match reader.read_exact(&mut buf) {
-                            Ok(_) => u64::from_le_bytes(buf),
-                            Err(e) => panic!(
-                                "[DebugInfoSection::parse] cannot read from debug_info {:?}",
-                                e
-                            ),
-                        };
-                        read_size += 8;
-                        data.to_string()
-                    }
-                    DwFormInfo::SecOffset => {
-                        // 4byteデータがdebug_infoセクションに格納
-                        let mut buf = [0; 4];
-                        let data = match reader.read_exact(&mut buf) {
-                            Ok(_) => u32::from_le_bytes(buf),
-                            Err(e) => panic!(
-                                "[DebugInfoSection::parse] cannot read from debug_info {:?}",
-                                e
-                            ),
-                        };
-                        read_size += 4;
-                        data.to_string()
-                    }
-                    DwFormInfo::Ref1 => {
-                        // CUヘッダーからのオフセットが、.debug_infoセクションに格納
-                        let mut buf = [0; 1];
-                        let data = match reader.read_exact(&mut buf) {
-                            Ok(_) => u8::from_le_bytes(buf),
-                            Err(e) => panic!(
-                                "[DebugInfoSection::parse] cannot read from debug_info {:?}",
-                                e
-                            ),
-                        };
-                        read_size += 1;
-                        data.to_string()
-                    }
-                    DwFormInfo::Ref2 => {
-                        // CUヘッダーからのオフセットが、.debug_infoセクションに格納
-                        let mut buf = [0; 2];
-                        let data = match reader.read_exact(&mut buf) {
-                            Ok(_) => u16::from_le_bytes(buf),
-                            Err(e) => panic!(
-                                "[DebugInfoSection::parse] cannot read from debug_info {:?}",
-                                e
-                            ),
-                        };
-                        read_size += 2;
-                        data.to_string()
-                    }
-                    DwFormInfo::Ref4 => {
-                        // CUヘッダーからのオフセットが、.debug_infoセクションに格納
-                        let mut buf = [0; 4];
-                        let data = match reader.read_exact(&mut buf) {
-                            Ok(_) => u32::from_le_bytes(buf),
-                            Err(e) => panic!(
-                                "[DebugInfoSection::parse] cannot read from debug_info {:?}",
-                                e
-                            ),
-                        };
-                        read_size += 4;
-                        data.to_string()
-                    }
-                    DwFormInfo::Ref8 => {
-                        // CUヘッダーからのオフセットが、.debug_infoセクションに格納
-                        let mut buf = [0; 8];
-                        let data = match reader.read_exact(&mut buf) {
-                            Ok(_) => u64::from_le_bytes(buf),
-                            Err(e) => panic!(
-                                "[DebugInfoSection::parse] cannot read from debug_info {:?}",
-                                e
-                            ),
-                        };
-                        read_size += 8;
-                        data.to_string()
-                    }
-                    DwFormInfo::Sdata => {
-                        // sUEB128方式でdebug_infoセクションに格納
-                        let (size, data) = Self::decode(reader).unwrap();
-                        read_size += size;
-                        data.to_string()
-                    }
-                    DwFormInfo::Udata => {
-                        // uUEB128方式でdebug_infoセクションに格納
-                        let (size, data) = Self::decode(reader).unwrap();
-                        read_size += size;
-                        data.to_string()
-                    }
-                    DwFormInfo::String => {
-                        // null terminateの文字列がdebug_infoセクションに格納
-                        let mut st: Vec<u8> = vec![];
-                        let mut st_size = 0;
-                        loop {
-                            let mut buf = [0; 1];
-                            let data = match reader.read_exact(&mut buf) {
-                                Ok(_) => u8::from_le_bytes(buf),
-                                Err(e) => panic!(
-                                    "[DebugInfoSection::parse] cannot read from debug_info {:?}",
-                                    e
-                                ),
-                            };
-                            st.push(data);
-                            st_size += 1;
-                            if data == 0 {
-                                break;
-                            }
-                        }
-                        read_size += st_size;
-                        String::from_utf8(st).unwrap()
-                    }
-                    DwFormInfo::Exprloc => {
-                        // uUEB128方式でdebug_infoセクションに格納
-                        let (size, data) = Self::decode(reader).unwrap();
-
-                        // この後に、exprlocで指定されたバイト数を読み込む
-                        let mut buf = vec![0; data as usize];
-                        match reader.read_exact(&mut buf) {
-                            Ok(_) => {
-                                // exprlocとそのデータサイズ分を加算
-                                read_size += size + data;
-                            }
-                            Err(e) => panic!("[DebugInfoSection::parse] cannot exprloc {:?}", e),
-                        }
-                        data.to_string()
-                    }
-                    DwFormInfo::FlagPresent => {
-                        // フラグが存在していることを暗黙的に示している
-                        "flag is present".to_string()
-                    }
-                    DwFormInfo::Block1 => {
-                        // 長さを読み取り、その後に続くデータをリード
-                        let mut buf = [0; 1];
-                        match reader.read_exact(&mut buf) {
-                            Ok(_) => {
-                                // サイズ分データ読み込み
-                                let size = buf[0] as usize;
-                                let mut buf = vec![0; size];
-                                reader.read_exact(&mut buf).unwrap();
-                                read_size += size as u64 + 1;
-                            }
-                            Err(e) => panic!("[DebugInfoSection::parse] cannot block1 {:?}", e),
-                        };
-                        "block1".to_string()
-                    }
-                    DwFormInfo::End => "value: 0".to_string(),
-                    DwFormInfo::Unknown(v) => {
-                        panic!("\tunknown DW Form[0x{:x}]", v)
-                    }
-                    _ => panic!("\tnot support DW Form[0x{:x}]", *form),
-                };
+        assert_eq!("c\u{FFFD}", sec.to_string(b"main\0c\xFE\0", 5));
 
-                // DIEを生成し、保存
-                let die = DebugInfoEntry::new(abbrev_no, *at, *form, &data);
-                cu_h.dies.push(die);
-            }
+        // 64bit形式では、セクションオフセットは8byte
+        let cases: Vec<(u64, &[u8], AttrValue)> = vec![
+            (
+                0x17,
+                &[0x40, 0, 0, 0, 1, 0, 0, 0],
+                AttrValue::SecOffset(0x1_0000_0040),
+            ),
+            (
+                0xE,
+                &[1, 0, 0, 0, 0, 0, 0, 0],
+                AttrValue::Str("main".to_string()),
+            ),
+            (0x10, &[3; 8], AttrValue::Block(vec![3; 8])),
+        ];
+        for (form, data, value) in cases {
+            let mut reader = data;
+            assert_eq!(
+                (8, value),
+                sec.read_value(&mut reader, form, str_buf, 8).unwrap(),
+                "DW_FORM 0x{:x}",
+                form
+            );
+            assert!(reader.is_empty(), "DW_FORM 0x{:x}", form);
         }
-        read_size
     }
 
-    /// Null Terminator文字列
-    ///
-    /// シンボルが入っているセクションデータと文字列開始位置を受け取り、
-    /// NullTermnateである文字列を返却する
-    fn to_string(&self, buf: &[u8], offset: usize) -> String {
-        let t: Vec<u8> = buf
-            .iter()
-            .skip(offset)
-            .take_while(|&c| *c != 0) // nullまで読み込み
-            .cloned()
-            .collect();
-        String::from_utf8(t).unwrap()
+    /// 1つのabbrev(CompileUnit)からなるabbrevセクション
+    fn abbrev_section(attrs: &[(u64, u64)]) -> DebugAbbRevSection {
+        let mut record = DebugAbbRevRecord::new();
+        record.abbrev_no = 1;
+        record.tag = 0x11;
+        for (at, form) in attrs.iter().chain([(0, 0)].iter()) {
+            record.attr_name.push(*at);
+            record.attr_form.push(*form);
+            record.attr_const.push(0);
+        }
+        let mut abbrev = DebugAbbRevSection::new();
+        abbrev.abb_rev.insert(1, record);
+        abbrev
     }
-}
 
-/// Dwarf情報
-pub struct Dwarf {
-    debug_info: DebugInfoSection,
-    debug_line: Vec<DebugLineSection>,
-}
+    #[test]
+    fn test_parse_skipped_forms() {
+        const AT_PRODUCER: u64 = 0x25;
+        const AT_LANGUAGE: u64 = 0x13;
+        {
+            // 未対応のformを、通常の属性の間に挟んだCU
+            let abbrev = abbrev_section(&[
+                (AT_NAME, FORM_STRING),
+                (AT_PRODUCER, 0x1E), // DW_FORM_data16
+                (AT_LANGUAGE, FORM_DATA1),
+                (AT_TYPE, 0x20), // DW_FORM_ref_sig8
+                (AT_BYTE_SIZE, FORM_DATA1),
+                (AT_LOCATION, 0x9), // DW_FORM_block
+                (AT_LOW_PC, FORM_ADDR),
+            ]);
+            let data = [
+                &[1][..],
+                b"a.c\0",
+                &[0xAA; 16],
+                &[0x0C],
+                &[0xBB; 8],
+                &[4],
+                &[2, 0x91, 0x6C],
+                &0x1000u64.to_le_bytes(),
+                &[0],
+            ]
+            .concat();
+            let mut cu = CUHeader::new();
+            cu.len = 7 + data.len() as u32;
 
-impl ULEB128 for Dwarf {}
-impl Dwarf {
-    /// コンストラクタ
-    pub fn new() -> Self {
-        Dwarf {
-            debug_info: DebugInfoSection::new(),
-            debug_line: vec![],
+            let sec = DebugInfoSection::new();
+            let size = sec
+                .parse(&mut &data[..], &mut cu, &abbrev, &[], 0xB)
+                .unwrap();
+            assert_eq!(data.len() as u64, size);
+            let die = &cu.dies[0];
+            assert_eq!(Some("a.c"), die.get_str(DwAtInfo::Name));
+            assert_eq!(Some(0x0C), die.get_const(DwAtInfo::Language));
+            assert_eq!(Some(4), die.get_const(DwAtInfo::ByteSize));
+            assert_eq!(
+                &[0x91, 0x6C],
+                die.get_attr(DwAtInfo::Location).unwrap().get_block()
+            );
+            assert_eq!(Some(0x1000), die.get_addr(DwAtInfo::LowPc));
+        }
+        {
+            // 不明なformを含むCUはエラー
+            let abbrev = abbrev_section(&[(AT_NAME, FORM_STRING), (AT_LANGUAGE, 0x7F)]);
+            let data = [&[1][..], b"a.c\0", &[0x0C], &[0]].concat();
+            let mut cu = CUHeader::new();
+            cu.len = 7 + data.len() as u32;
+
+            let sec = DebugInfoSection::new();
+            let err = sec
+                .parse(&mut &data[..], &mut cu, &abbrev, &[], 0xB)
+                .unwrap_err();
+            assert_eq!(
+                "unknown DW_FORM 0x7f (abbrev 1, offset 0xb)",
+                err.to_string()
+            );
+        }
+        {
+            // 64bitに収まらないLEB128、途中で終わるLEB128はエラー
+            let abbrev = abbrev_section(&[(AT_LANGUAGE, FORM_UDATA)]);
+            let cases: Vec<(&[u8], ErrorKind)> = vec![
+                (
+                    &[
+                        1, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0,
+                    ],
+                    ErrorKind::InvalidData,
+                ),
+                (&[1, 0x80, 0x80], ErrorKind::UnexpectedEof),
+                (&[0x80], ErrorKind::UnexpectedEof),
+            ];
+            for (data, kind) in cases {
+                let mut cu = CUHeader::new();
+                cu.len = 7 + 16;
+
+                let sec = DebugInfoSection::new();
+                let err = sec
+                    .parse(&mut &data[..], &mut cu, &abbrev, &[], 0xB)
+                    .unwrap_err();
+                assert_eq!(kind, err.kind());
+            }
         }
     }
 
-    /// debug情報表示
-    pub fn show(&self) {
-        self.debug_info.show();
-        self.debug_line.iter().for_each(|d| d.show());
+    #[test]
+    fn test_parse_cu_end() {
+        const AT_LANGUAGE: u64 = 0x13;
+        let abbrev = abbrev_section(&[(AT_NAME, FORM_STRING), (AT_LANGUAGE, FORM_DATA1)]);
+        {
+            // CUの終端にnullエントリーのパディングがある
+            let data = [&[1][..], b"a.c\0", &[0x0C], &[0, 0, 0, 0]].concat();
+            let mut cu = CUHeader::new();
+            cu.len = 7 + data.len() as u32;
+
+            let sec = DebugInfoSection::new();
+            let size = sec
+                .parse(&mut &data[..], &mut cu, &abbrev, &[], 0xB)
+                .unwrap();
+            assert_eq!(data.len() as u64, size);
+            assert_eq!(1, cu.dies.len());
+            assert_eq!(Some(0x0C), cu.dies[0].get_const(DwAtInfo::Language));
+        }
+        {
+            // 64bit形式の長さ(ヘッダーは12byte長い)
+            let data = [&[1][..], b"a.c\0", &[0x0C], &[0, 0]].concat();
+            let mut cu = CUHeader::new();
+            cu.len = 0xFFFF_FFFF;
+            cu.actual_len = 7 + data.len() as u64;
+
+            let sec = DebugInfoSection::new();
+            let size = sec
+                .parse(&mut &data[..], &mut cu, &abbrev, &[], 0x13)
+                .unwrap();
+            assert_eq!(data.len() as u64, size);
+            assert_eq!(0x13 + size, cu.get_end());
+        }
+        {
+            // 後続のデータがあっても、CUの終端で停止する
+            let data = [&[1][..], b"a.c\0", &[0x0C], &[0], &[1], b"b.c\0", &[0x0C]].concat();
+            let mut cu = CUHeader::new();
+            cu.len = 7 + 7;
+
+            let sec = DebugInfoSection::new();
+            let size = sec
+                .parse(&mut &data[..], &mut cu, &abbrev, &[], 0xB)
+                .unwrap();
+            assert_eq!(7, size);
+            assert_eq!(1, cu.dies.len());
+        }
+        {
+            // CUの終端を越える属性はエラー
+            let data = [&[1][..], b"a.c\0", &[0x0C], &[0]].concat();
+            let mut cu = CUHeader::new();
+            cu.len = 7 + 3;
+
+            let sec = DebugInfoSection::new();
+            let err = sec
+                .parse(&mut &data[..], &mut cu, &abbrev, &[], 0xB)
+                .unwrap_err();
+            assert_eq!(
+                "attribute crosses the end of compile unit 0xe (abbrev 1, offset 0xb)",
+                err.to_string()
+            );
+        }
     }
 
-    /// debug_infoロード
-    pub fn load(&mut self, path: &str, header: &[ElfSecHeader]) -> Result<()> {
-        // debug_info/debug_abbrevセクションを探す
-        let debug_info_sec = match self.search_debug_info_sec(header) {
-            Some(h) => h,
-            _ => {
-                return Err(Error::new(
-                    ErrorKind::NotFound,
-                    "Not found debug_info section header",
-                ))
+    #[test]
+    fn test_parse_sparse_abbrev() {
+        // abbrev noが連番でなく、昇順でもないabbrevセクション
+        let mut abbrev = DebugAbbRevSection::new();
+        for (no, tag, has_child) in [(5, 0x11, 1), (2, 0x34, 0)] {
+            let mut record = DebugAbbRevRecord::new();
+            record.abbrev_no = no;
+            record.tag = tag;
+            record.has_child = has_child;
+            record.attr_name = vec![AT_NAME, 0];
+            record.attr_form = vec![FORM_STRING, 0];
+            record.attr_const = vec![0, 0];
+            abbrev.abb_rev.insert(no, record);
+        }
+        {
+            let data = [&[5][..], b"a.c\0", &[2], b"x\0", &[0]].concat();
+            let mut cu = CUHeader::new();
+            cu.len = 7 + data.len() as u32;
+
+            let sec = DebugInfoSection::new();
+            let size = sec
+                .parse(&mut &data[..], &mut cu, &abbrev, &[], 0xB)
+                .unwrap();
+            assert_eq!(data.len() as u64, size);
+            assert_eq!(2, cu.dies.len());
+            assert_eq!(DwTagInfo::CompileUnit, cu.dies[0].tag);
+            assert_eq!(Some("a.c"), cu.dies[0].get_str(DwAtInfo::Name));
+            assert_eq!(vec![1], cu.dies[0].children);
+            assert_eq!(DwTagInfo::Variable, cu.dies[1].tag);
+            assert_eq!(Some("x"), cu.dies[1].get_str(DwAtInfo::Name));
+        }
+        {
+            // abbrevに存在しないnoはエラー
+            let data = [&[5][..], b"a.c\0", &[3], b"x\0", &[0]].concat();
+            let mut cu = CUHeader::new();
+            cu.len = 7 + data.len() as u32;
+
+            let sec = DebugInfoSection::new();
+            let err = sec
+                .parse(&mut &data[..], &mut cu, &abbrev, &[], 0xB)
+                .unwrap_err();
+            assert_eq!(ErrorKind::InvalidData, err.kind());
+            assert_eq!("unknown abbrev code 3 (offset 0x10)", err.to_string());
+        }
+    }
+
+    #[test]
+    fn test_cu_header() {
+        {
+            // dwarf2/4: len, version, abbrev offset, address size
+            for version in [2u16, 4] {
+                let data = [
+                    &0x100u32.to_le_bytes()[..],
+                    &version.to_le_bytes(),
+                    &0x20u32.to_le_bytes(),
+                    &[8],
+                ]
+                .concat();
+                let mut cu = CUHeader::new();
+                let size = DebugInfoSection::read_cu_header(&mut &data[..], &mut cu).unwrap();
+                assert_eq!(11, size);
+                assert_eq!(0x100, cu.len);
+                assert_eq!(version, cu.version);
+                assert_eq!(0x20, cu.abb_rev_offset);
+                assert_eq!(8, cu.address_size);
             }
-        };
-        let abbrev_header = match self.search_debug_abbrev_sec(header) {
-            Some(h) => h,
-            _ => {
-                return Err(Error::new(
-                    ErrorKind::NotFound,
-                    "Not found debug_abbrev section header",
-                ))
+        }
+        {
+            // dwarf5: len, version, unit type, address size, abbrev offset
+            let data = [
+                &0x100u32.to_le_bytes()[..],
+                &5u16.to_le_bytes(),
+                &[0x1, 8], // DW_UT_compile
+                &0x20u32.to_le_bytes(),
+            ]
+            .concat();
+            let mut cu = CUHeader::new();
+            let size = DebugInfoSection::read_cu_header(&mut &data[..], &mut cu).unwrap();
+            assert_eq!(12, size);
+            assert_eq!(0x1, cu.unit_type);
+            assert_eq!(0x20, cu.abb_rev_offset);
+            assert_eq!(8, cu.address_size);
+        }
+        {
+            // dwarf5のskeleton unitは、dwo idが続く
+            let data = [
+                &0x100u32.to_le_bytes()[..],
+                &5u16.to_le_bytes(),
+                &[DW_UT_SKELETON, 8],
+                &0x20u32.to_le_bytes(),
+                &[0xAA; 8],
+            ]
+            .concat();
+            let mut cu = CUHeader::new();
+            let size = DebugInfoSection::read_cu_header(&mut &data[..], &mut cu).unwrap();
+            assert_eq!(20, size);
+        }
+        {
+            // 64bit形式は、abbrev offsetが8byte
+            let data = [
+                &0xFFFF_FFFFu32.to_le_bytes()[..],
+                &0x100u64.to_le_bytes(),
+                &4u16.to_le_bytes(),
+                &0x1_0000_0020u64.to_le_bytes(),
+                &[8],
+            ]
+            .concat();
+            let mut cu = CUHeader::new();
+            let size = DebugInfoSection::read_cu_header(&mut &data[..], &mut cu).unwrap();
+            assert_eq!(23, size);
+            assert_eq!(8, cu.offset_size());
+            assert_eq!(0x1_0000_0020, cu.abb_rev_offset);
+            assert_eq!(8, cu.address_size);
+
+            let data = [
+                &0xFFFF_FFFFu32.to_le_bytes()[..],
+                &0x100u64.to_le_bytes(),
+                &5u16.to_le_bytes(),
+                &[0x1, 8],
+                &0x20u64.to_le_bytes(),
+            ]
+            .concat();
+            let mut cu = CUHeader::new();
+            let size = DebugInfoSection::read_cu_header(&mut &data[..], &mut cu).unwrap();
+            assert_eq!(24, size);
+            assert_eq!(0x20, cu.abb_rev_offset);
+        }
+        {
+            // 未対応のversionは、lenを保持したままエラー
+            for version in [1u16, 6, 0xFFFF] {
+                let data = [&0x100u32.to_le_bytes()[..], &version.to_le_bytes(), &[0; 6]].concat();
+                let mut cu = CUHeader::new();
+                let err = DebugInfoSection::read_cu_header(&mut &data[..], &mut cu).unwrap_err();
+                assert_eq!(ErrorKind::InvalidData, err.kind());
+                assert_eq!(
+                    format!("unsupported DWARF version {}", version),
+                    err.to_string()
+                );
+                assert_eq!(0x100, cu.len);
             }
-        };
-        let debug_str = match self.search_debug_str(header) {
-            Some(h) => h,
-            _ => {
-                return Err(Error::new(
-                    ErrorKind::NotFound,
-                    "Not found debug_str section header",
-                ))
+        }
+    }
+
+    #[test]
+    fn test_high_pc() {
+        const FORM_DATA2: u64 = 0x5;
+        const FORM_DATA4: u64 = 0x6;
+        // (version, form, high_pc, 終端アドレス)
+        // gccは定数クラス(data8)、clangは定数クラス(data4)やアドレスクラスを出力する
+        let cases = [
+            (4, FORM_DATA1, "256", 0x1100),
+            (4, FORM_DATA2, "256", 0x1100),
+            (4, FORM_DATA4, "256", 0x1100),
+            (4, FORM_DATA8, "256", 0x1100),
+            (4, FORM_UDATA, "256", 0x1100),
+            (5, FORM_DATA4, "256", 0x1100),
+            (4, FORM_ADDR, "4352", 0x1100),
+            (5, FORM_ADDR, "4352", 0x1100),
+            // dwarf3までは、定数クラスもアドレス
+            (3, FORM_DATA8, "4352", 0x1100),
+            (2, FORM_ADDR, "4352", 0x1100),
+        ];
+        let sec = DebugInfoSection::new();
+        for (version, form, high, end) in cases {
+            let mut cu = CUHeader::new();
+            cu.version = version;
+            let die = node(
+                0xB,
+                DwTagInfo::Subprogram,
+                &[(AT_LOW_PC, FORM_ADDR, "4096"), (AT_HIGH_PC, form, high)],
+            );
+            assert_eq!(
+                Some(end),
+                die.get_high_pc(0x1000, version),
+                "form 0x{:x}",
+                form
+            );
+            assert_eq!(vec![0x1000..end], sec.get_ranges(&cu, &die));
+        }
+        {
+            // dwarf5のaddrxは、解決後にアドレスクラスとなる
+            let mut die = DieNode::new(0xB, DwTagInfo::Subprogram);
+            die.attrs.push(DebugInfoEntry::new(
+                AT_HIGH_PC,
+                0x1B,
+                AttrValue::Addr(0x1100),
+            ));
+            assert_eq!(Some(0x1100), die.get_high_pc(0x1000, 5));
+        }
+        {
+            // high_pcがなければ、範囲は空
+            let mut cu = CUHeader::new();
+            cu.version = 4;
+            let die = node(
+                0xB,
+                DwTagInfo::Subprogram,
+                &[(AT_LOW_PC, FORM_ADDR, "4096")],
+            );
+            assert_eq!(None, die.get_high_pc(0x1000, 4));
+            assert_eq!(vec![0x1000..0x1000], sec.get_ranges(&cu, &die));
+        }
+    }
+
+    #[test]
+    fn test_lazy_units() {
+        const CU_COUNT: usize = 500;
+        const VAR_COUNT: usize = 100;
+
+        // no=1(CompileUnit, name/string, low_pc/addr, high_pc/data4)
+        // no=2(Variable, name/string, byte_size/data1)
+        let abbrev = [
+            &[1, 0x11, 1, 0x3, 0x8, 0x11, 0x1, 0x12, 0x6, 0, 0][..],
+            &[2, 0x34, 0, 0x3, 0x8, 0xB, 0xB, 0, 0],
+            &[0],
+        ]
+        .concat();
+
+        // 多数の変数を持つCU(i番目のCUは0x1000*(i+1)から0x100バイト)を、多数並べたdebug_info
+        let low_pc = |i: usize| 0x1000 * (i as u64 + 1);
+        let mut info = vec![];
+        for i in 0..CU_COUNT {
+            let mut dies = [
+                &[1][..],
+                format!("cu{}.c\0", i).as_bytes(),
+                &low_pc(i).to_le_bytes(),
+                &0x100u32.to_le_bytes(),
+            ]
+            .concat();
+            for _ in 0..VAR_COUNT {
+                dies.extend_from_slice(&[2, b'v', 0, 4]);
             }
-        };
+            dies.push(0);
+            info.extend_from_slice(&(7 + dies.len() as u32).to_le_bytes());
+            info.extend_from_slice(&4u16.to_le_bytes());
+            info.extend_from_slice(&0u32.to_le_bytes());
+            info.push(8);
+            info.extend_from_slice(&dies);
+        }
 
-        // debug_infoセクションロード
-        let f = File::open(&path)?;
-        let mut reader = BufReader::new(f);
-        self.debug_info
-            .load(&mut reader, debug_info_sec, abbrev_header, debug_str)?;
+        let mut sec = DebugInfoSection::new();
+        sec.abbrev = abbrev;
+        sec.info = info;
+        let parsed =
+            |sec: &DebugInfoSection| sec.units.iter().filter(|u| u.get().is_some()).count();
 
-        // debug_lineセクションロード
-        self.load_debug_line(path, header)?;
+        // ロード時は、CUの位置とアドレス範囲のみ登録する
+        sec.index_units().unwrap();
+        assert_eq!(CU_COUNT, sec.units.len());
+        assert_eq!(0, parsed(&sec));
+        assert_eq!(vec![0x8000..0x8100], sec.unit_ranges[7]);
 
-        Ok(())
+        // 参照されたCUのみ解析する
+        let cu = sec.get_unit(3);
+        assert_eq!("cu3.c", cu.get_name());
+        assert_eq!(VAR_COUNT + 1, cu.dies.len());
+        assert_eq!(1, parsed(&sec));
+
+        // pcを含むCUのみ解析する
+        let names = sec
+            .units_at(low_pc(7) + 0x10)
+            .map(|cu| cu.get_name().to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(vec!["cu7.c"], names);
+        assert_eq!(2, parsed(&sec));
+        assert!(sec.units_at(0x10).next().is_none());
+        assert!(sec.search_frames(low_pc(CU_COUNT)).is_empty());
+        assert_eq!(2, parsed(&sec));
+
+        // すべてのCUを解析
+        assert_eq!(CU_COUNT, sec.units().count());
+        assert_eq!(CU_COUNT, parsed(&sec));
     }
 
-    /// load debug_line section
-    fn load_debug_line(&mut self, path: &str, header: &[ElfSecHeader]) -> Result<()> {
-        let line_h = match self.search_debug_line(header) {
-            Some(h) => h,
-            _ => {
-                return Err(Error::new(
-                    ErrorKind::NotFound,
-                    "Not found debug_line section header",
-                ))
+    #[test]
+    fn test_abbrev_cache() {
+        // オフセット0: no=1(CompileUnit, name/string)
+        // オフセット8: no=1(Variable, name/string)
+        let data = [
+            &[1, 0x11, 1, 0x3, 0x8, 0, 0, 0][..],
+            &[1, 0x34, 0, 0x3, 0x8, 0, 0, 0],
+        ]
+        .concat();
+        let mut sec = DebugInfoSection::new();
+        sec.abbrev = data;
+
+        let first = sec.get_abbrev(0).unwrap();
+        assert_eq!(0x11, first.get(1).unwrap().tag);
+        assert_eq!(vec![0x3, 0], first.get(1).unwrap().attr_name);
+
+        // 同じオフセットは再ロードしない(セクションデータを壊しても同じabbrevを返す)
+        sec.abbrev = vec![0x80; 16];
+        let second = sec.get_abbrev(0).unwrap();
+        assert!(Rc::ptr_eq(&first, &second));
+        assert_eq!(1, sec.abbrev_cache.borrow().len());
+
+        // 異なるオフセットは、別にロードする
+        sec.abbrev = [
+            &[1, 0x11, 1, 0x3, 0x8, 0, 0, 0][..],
+            &[1, 0x34, 0, 0x3, 0x8, 0, 0, 0],
+        ]
+        .concat();
+        let other = sec.get_abbrev(8).unwrap();
+        assert!(!Rc::ptr_eq(&first, &other));
+        assert_eq!(0x34, other.get(1).unwrap().tag);
+        assert_eq!(2, sec.abbrev_cache.borrow().len());
+
+        // セクション外のオフセットはエラー
+        assert!(sec.get_abbrev(0x100).is_err());
+        assert_eq!(2, sec.abbrev_cache.borrow().len());
+    }
+
+    #[test]
+    fn test_show_debugsec() {
+        // オフセット0: no=1(CompileUnit, name/string), no=2(Variable, name/string)
+        // オフセット0xF: no=1(Subprogram, name/string)
+        let mut sec = DebugInfoSection::new();
+        sec.abbrev = [
+            &[1, 0x11, 1, 0x3, 0x8, 0, 0, 2, 0x34, 0, 0x3, 0x8, 0, 0, 0][..],
+            &[1, 0x2E, 0, 0x3, 0x8, 0, 0, 0],
+        ]
+        .concat();
+        let mut cu = CUHeader::new();
+        cu.version = 4;
+        cu.address_size = 8;
+        cu.dies = vec![
+            node(
+                0xB,
+                DwTagInfo::CompileUnit,
+                &[(AT_NAME, FORM_STRING, "main.c")],
+            ),
+            node(0x14, DwTagInfo::Variable, &[(AT_NAME, FORM_STRING, "v")]),
+        ];
+        cu.dies[0].abbrev_no = 1;
+        cu.dies[0].children = vec![1];
+        cu.dies[1].abbrev_no = 2;
+        sec.units = vec![OnceCell::from(cu)];
+
+        let show = |f: &dyn Fn(&mut Vec<u8>) -> Result<()>| {
+            let mut out = vec![];
+            f(&mut out).map(|_| String::from_utf8(out).unwrap())
+        };
+        {
+            // セクション内のabbrevテーブルをすべて表示
+            assert_eq!(
+                "abbrev table at offset 0x0:\n\
+                 1 CompileUnit [has children]\n    Name String\n\
+                 2 Variable [no children]\n    Name String\n\n\
+                 abbrev table at offset 0xf:\n\
+                 1 Subprogram [no children]\n    Name String\n\n",
+                show(&|out| sec.show_abbrev(out, None, false)).unwrap()
+            );
+            assert_eq!(
+                "abbrev table at offset 0xf:\n1 tag 0x2e [no children]\n    at 0x3 form 0x8\n\n",
+                show(&|out| sec.show_abbrev(out, Some(0xF), true)).unwrap()
+            );
+            assert!(show(&|out| sec.show_abbrev(out, Some(0x100), false)).is_err());
+        }
+        {
+            // DIEは親子関係の深さで字下げし、rawはコードで表示
+            let dies = |raw: bool| {
+                let s = show(&|out| sec.show_info(out, Some(0), raw)).unwrap();
+                s.split_once("address size: 0x8\n").unwrap().1.to_string()
+            };
+            assert_eq!(
+                "<0xb> CompileUnit\n    Name String: main.c\n\
+                 \x20 <0x14> Variable\n      Name String: v\n\n",
+                dies(false)
+            );
+            assert_eq!(
+                " <0><0xb>: abbrev 1 tag 0x11\n    at 0x3 form 0x8: main.c\n\
+                 \x20<1><0x14>: abbrev 2 tag 0x34\n    at 0x3 form 0x8: v\n\n",
+                dies(true)
+            );
+            let e = show(&|out| sec.show_info(out, Some(1), false)).unwrap_err();
+            assert_eq!("No compile unit 1.", e.to_string());
+        }
+    }
+
+    #[test]
+    fn test_parse_block_forms() {
+        const AT_CONST_VALUE: u64 = 0x1C;
+        // 長さの形式毎に、長さ・データを符号化
+        let uleb = |mut n: usize| {
+            let mut v = vec![];
+            loop {
+                let b = (n & 0x7F) as u8;
+                n >>= 7;
+                if 0 == n {
+                    v.push(b);
+                    return v;
+                }
+                v.push(b | 0x80);
             }
         };
+        let encode = |form: u64, len: usize| match form {
+            0xA => vec![len as u8],
+            0x3 => (len as u16).to_le_bytes().to_vec(),
+            0x4 => (len as u32).to_le_bytes().to_vec(),
+            _ => uleb(len),
+        };
+        let cases = vec![
+            (0xA, vec![0, 1, 255]),          // DW_FORM_block1
+            (0x3, vec![0, 300, 65535]),      // DW_FORM_block2
+            (0x4, vec![0, 70000]),           // DW_FORM_block4
+            (0x9, vec![0, 127, 128, 20000]), // DW_FORM_block(長さが複数byteのuLEB128)
+        ];
+        for (form, lens) in cases {
+            let abbrev = abbrev_section(&[(AT_CONST_VALUE, form)]);
+            for len in lens {
+                let payload = (0..len).map(|i| (i * 7) as u8).collect::<Vec<u8>>();
+                let data = [&[1][..], &encode(form, len), &payload, &[0]].concat();
+                let mut cu = CUHeader::new();
+                cu.len = 7 + data.len() as u32;
 
-        // stmt_listを抽出
-        for cu_h in self.debug_info.get_header() {
-            let stmt_list = cu_h
-                .get_dies()
-                .iter()
-                .filter(|die| die.get_at_info() == DwAtInfo::StmtList)
-                .collect::<Vec<&DebugInfoEntry>>();
-            // stmtに紐付いたdebug_lineセクションをロード
-            for stmt in stmt_list {
-                let mut line = DebugLineSection::new(line_h.get_offset());
-                match stmt.get_data().parse::<u64>() {
-                    Ok(offset) => line.load(path, offset)?,
-                    Err(e) => panic!("[load_debug_line] cannot parse offset ({:?})", e),
-                };
-                // ロードした情報を保存
-                self.debug_line.push(line)
+                let sec = DebugInfoSection::new();
+                let size = sec
+                    .parse(&mut &data[..], &mut cu, &abbrev, &[], 0xB)
+                    .unwrap();
+                assert_eq!(data.len() as u64, size, "form 0x{:x} len {}", form, len);
+                let die = &cu.dies[0];
+                let block = die.get_attr(DwAtInfo::ConstValue).unwrap().get_block();
+                assert_eq!(payload, block, "form 0x{:x} len {}", form, len);
+
+                // データが途中で切れていれば、エラーとする
+                if 0 < len {
+                    let truncated = &data[..data.len() - 2];
+                    let mut cu = CUHeader::new();
+                    cu.len = 7 + truncated.len() as u32;
+                    let result = sec.parse(&mut &truncated[..], &mut cu, &abbrev, &[], 0xB);
+                    assert!(result.is_err(), "form 0x{:x} len {}", form, len);
+                }
             }
         }
+    }
 
-        Ok(())
+    #[test]
+    fn test_parse_blocks() {
+        const AT_CONST_VALUE: u64 = 0x1C;
+        const AT_FRAME_BASE: u64 = 0x40;
+        const AT_DATA_MEMBER_LOCATION: u64 = 0x38;
+        const AT_DATA_LOCATION: u64 = 0x50;
+        // 長さが2byteのuLEB128となるexprloc、各blockの後に通常の属性を挟む
+        let expr = (0..200).map(|i| i as u8).collect::<Vec<u8>>();
+        let abbrev = abbrev_section(&[
+            (AT_LOCATION, FORM_EXPRLOC),
+            (AT_NAME, FORM_STRING),
+            (AT_CONST_VALUE, 0xA), // DW_FORM_block1
+            (AT_BYTE_SIZE, FORM_DATA1),
+            (AT_FRAME_BASE, 0x3),           // DW_FORM_block2
+            (AT_DATA_MEMBER_LOCATION, 0x4), // DW_FORM_block4
+            (AT_DATA_LOCATION, 0x9),        // DW_FORM_block
+            (AT_LOW_PC, FORM_ADDR),
+        ]);
+        let data = [
+            &[1][..],
+            &[0xC8, 0x01],
+            &expr,
+            b"v\0",
+            &[3, 0x01, 0x02, 0x03],
+            &[4],
+            &[2, 0, 0x91, 0x70],
+            &[1, 0, 0, 0, 0x9C],
+            &[0],
+            &0x1000u64.to_le_bytes(),
+            &[0],
+        ]
+        .concat();
+        let mut cu = CUHeader::new();
+        cu.len = 7 + data.len() as u32;
+
+        let sec = DebugInfoSection::new();
+        let size = sec
+            .parse(&mut &data[..], &mut cu, &abbrev, &[], 0xB)
+            .unwrap();
+        assert_eq!(data.len() as u64, size);
+
+        let die = &cu.dies[0];
+        let block = |at: DwAtInfo| die.get_attr(at).unwrap().get_block().to_vec();
+        assert_eq!(expr, block(DwAtInfo::Location));
+        assert_eq!(vec![0x01, 0x02, 0x03], block(DwAtInfo::ConstValue));
+        assert_eq!(vec![0x91, 0x70], block(DwAtInfo::FrameBase));
+        assert_eq!(vec![0x9C], block(DwAtInfo::DataMemberLocation));
+        assert!(block(DwAtInfo::DataLocation).is_empty());
+
+        // block後の属性も、ずれずに読み込める
+        assert_eq!(Some("v"), die.get_str(DwAtInfo::Name));
+        assert_eq!(Some(4), die.get_const(DwAtInfo::ByteSize));
+        assert_eq!(Some(0x1000), die.get_addr(DwAtInfo::LowPc));
     }
 
-    /// search debug_info section
-    fn search_debug_info_sec<'a>(&self, header: &'a [ElfSecHeader]) -> Option<&'a ElfSecHeader> {
-        header.iter().find(|s| s.get_name() == ".debug_info")
+    #[test]
+    fn test_dwarf5_forms() {
+        const AT_PRODUCER: u64 = 0x25;
+        const AT_STR_OFFSETS_BASE: u64 = 0x72;
+        const AT_ADDR_BASE: u64 = 0x73;
+        const AT_RNGLISTS_BASE: u64 = 0x74;
+        let mut abbrev = abbrev_section(&[
+            (AT_NAME, 0x25), // DW_FORM_strx1
+            (AT_STR_OFFSETS_BASE, FORM_SEC_OFFSET),
+            (AT_ADDR_BASE, FORM_SEC_OFFSET),
+            (AT_RNGLISTS_BASE, FORM_SEC_OFFSET),
+            (AT_LOW_PC, 0x1B),    // DW_FORM_addrx
+            (AT_RANGES, 0x23),    // DW_FORM_rnglistx
+            (AT_PRODUCER, 0x1F),  // DW_FORM_line_strp
+            (AT_BYTE_SIZE, 0x21), // DW_FORM_implicit_const
+        ]);
+        abbrev.abb_rev.get_mut(&1).unwrap().attr_const[7] = -4;
+        let data = [
+            &[1, 1][..],
+            &8u32.to_le_bytes(),
+            &8u32.to_le_bytes(),
+            &12u32.to_le_bytes(),
+            &[0, 0],
+            &0u32.to_le_bytes(),
+            &[0],
+        ]
+        .concat();
+
+        // 各テーブルはヘッダーの直後から
+        let mut sec = DebugInfoSection::new();
+        sec.str_offsets = [&[0; 8][..], &0u32.to_le_bytes(), &6u32.to_le_bytes()].concat();
+        sec.addr = [&[0; 8][..], &0x1000u64.to_le_bytes()].concat();
+        sec.rnglists = [&[0; 12][..], &4u32.to_le_bytes()].concat();
+        sec.line_str = b"GNU C17\0".to_vec();
+
+        let mut cu = CUHeader::new();
+        cu.version = 5;
+        cu.address_size = 8;
+        cu.len = 8 + data.len() as u32;
+        let str_buf = b"\0main\0a.c\0";
+        let size = sec
+            .parse(&mut &data[..], &mut cu, &abbrev, str_buf, 0xC)
+            .unwrap();
+        assert_eq!(data.len() as u64, size);
+
+        let die = &cu.dies[0];
+        assert_eq!(Some("a.c"), die.get_str(DwAtInfo::Name));
+        assert_eq!(Some(0x1000), die.get_addr(DwAtInfo::LowPc));
+        assert_eq!(
+            &AttrValue::SecOffset(16),
+            die.get_attr(DwAtInfo::Ranges).unwrap().get_value()
+        );
+        assert_eq!(Some("GNU C17"), die.get_str(DwAtInfo::Producer));
+        assert_eq!(
+            &AttrValue::Sdata(-4),
+            die.get_attr(DwAtInfo::ByteSize).unwrap().get_value()
+        );
     }
 
-    /// search debug_abbrev section
-    fn search_debug_abbrev_sec<'a>(&self, header: &'a [ElfSecHeader]) -> Option<&'a ElfSecHeader> {
-        header.iter().find(|s| s.get_name() == ".debug_abbrev")
+    #[test]
+    fn test_rnglists() {
+        let mut sec = DebugInfoSection::new();
+        sec.addr = [&[0; 8][..], &0x3000u64.to_le_bytes()].concat();
+        sec.rnglists = [
+            &[DW_RLE_BASE_ADDRESS][..],
+            &0x1000u64.to_le_bytes(),
+            &[DW_RLE_OFFSET_PAIR, 0x10, 0x20],
+            &[DW_RLE_START_LENGTH],
+            &0x2000u64.to_le_bytes(),
+            &[0x8],
+            &[DW_RLE_STARTX_LENGTH, 0, 0x4],
+            &[DW_RLE_END_OF_LIST],
+        ]
+        .concat();
+
+        let mut cu = CUHeader::new();
+        cu.version = 5;
+        cu.address_size = 8;
+        cu.dies = vec![node(
+            0xC,
+            DwTagInfo::LexicalBlock,
+            &[(AT_RANGES, FORM_SEC_OFFSET, "0")],
+        )];
+        assert_eq!(
+            vec![0x1010..0x1020, 0x2000..0x2008, 0x3000..0x3004],
+            sec.get_ranges(&cu, &cu.dies[0])
+        );
     }
 
-    /// search debug_str section
-    fn search_debug_str<'a>(&self, header: &'a [ElfSecHeader]) -> Option<&'a ElfSecHeader> {
-        header.iter().find(|s| s.get_name() == ".debug_str")
+    #[test]
+    fn test_loclists() {
+        let mut sec = DebugInfoSection::new();
+        sec.addr = [&[0; 8][..], &0x3000u64.to_le_bytes()].concat();
+        sec.loclists = [
+            &[DW_LLE_BASE_ADDRESS][..],
+            &0x1000u64.to_le_bytes(),
+            &[DW_LLE_OFFSET_PAIR, 0x10, 0x20, 1, 0x55], // DW_OP_reg5
+            &[DW_LLE_STARTX_LENGTH, 0, 0x4, 2, 0x91, 0x68], // DW_OP_fbreg -24
+            &[DW_LLE_DEFAULT_LOCATION, 1, 0x53],        // DW_OP_reg3
+            &[DW_LLE_END_OF_LIST],
+        ]
+        .concat();
+
+        let mut cu = CUHeader::new();
+        cu.version = 5;
+        cu.address_size = 8;
+        let cases = vec![
+            (0x1010, vec![0x55]),
+            (0x101F, vec![0x55]),
+            (0x3003, vec![0x91, 0x68]),
+            // 範囲外はデフォルトの位置式
+            (0x1020, vec![0x53]),
+        ];
+        for (pc, expected) in cases {
+            assert_eq!(expected, sec.search_location(&cu, 0, pc), "0x{:x}", pc);
+        }
+        // 途中でデータが途切れた場合は、そこまでのエントリーのみ
+        sec.loclists.truncate(16);
+        assert_eq!(vec![0x55], sec.search_location(&cu, 0, 0x1010));
+        assert!(sec.search_location(&cu, 0, 0x3000).is_empty());
     }
 
-    /// search debug_line section
-    fn search_debug_line<'a>(&self, header: &'a [ElfSecHeader]) -> Option<&'a ElfSecHeader> {
-        header.iter().find(|s| s.get_name() == ".debug_line")
+    #[test]
+    fn test_locs() {
+        let entry = |b: u64, e: u64, expr: &[u8]| {
+            [
+                &b.to_le_bytes()[..],
+                &e.to_le_bytes(),
+                &(expr.len() as u16).to_le_bytes(),
+                expr,
+            ]
+            .concat()
+        };
+        let mut sec = DebugInfoSection::new();
+        sec.loc = [
+            &[0xFF; 4][..], // 先頭以外のオフセット
+            &entry(0x10, 0x20, &[0x55]),
+            &u64::MAX.to_le_bytes(),
+            &0x5000u64.to_le_bytes(),
+            &entry(0x0, 0x8, &[0x91, 0x68]),
+            &[0; 16],
+        ]
+        .concat();
+
+        let mut cu = CUHeader::new();
+        cu.version = 4;
+        cu.address_size = 8;
+        cu.dies = vec![pc_range(0xB, DwTagInfo::CompileUnit, 0x1000, 0x100)];
+        let cases = vec![
+            (0x1010, vec![0x55]),
+            (0x5007, vec![0x91, 0x68]),
+            (0x1020, vec![]),
+        ];
+        for (pc, expected) in cases {
+            assert_eq!(expected, sec.search_location(&cu, 4, pc), "0x{:x}", pc);
+        }
+    }
+
+    #[test]
+    fn test_line_header_v5() {
+        let std_opcode_len: &[u8] = &[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1];
+        let data = [
+            &[0; 4][..],         // unit len
+            &5u16.to_le_bytes(), // version
+            &[8, 0],             // address size, seg sel size
+            &[0; 4],             // header len
+            &[1, 1, 1, 0xFB, 14, 13],
+            std_opcode_len,
+            // ディレクトリ(DW_LNCT_path: DW_FORM_line_strp)
+            &[1, 0x1, 0x1F, 2],
+            &0u32.to_le_bytes(),
+            &5u32.to_le_bytes(),
+            // ファイル名(DW_LNCT_path: DW_FORM_string, DW_LNCT_directory_index: DW_FORM_udata,
+            //           DW_LNCT_MD5: DW_FORM_data16)
+            &[3, 0x1, 0x08, 0x2, 0x0F, 0x5, 0x1E, 2],
+            b"main.c\0",
+            &[0],
+            &[0xAA; 16],
+            b"util.h\0",
+            &[1],
+            &[0xBB; 16],
+        ]
+        .concat();
+        let line_str = b"/src\0/inc\0";
+
+        let mut line = DebugLineSection::new(0);
+        let h = line.load_header(&mut &data[..], line_str).unwrap();
+        assert_eq!(5, h.version);
+        assert_eq!(13, h.opcode_base);
+        assert_eq!(vec!["/src", "/inc"], h.inc_dirs);
+        line.cu_header.push(h);
+
+        // ファイル・ディレクトリ番号はゼロ始まり(ディレクトリ0はコンパイル時のディレクトリ)
+        let files = line.get_file_names();
+        assert_eq!(vec!["main.c", "/inc/util.h"], files);
+
+        let mut cu = CUHeader::new();
+        cu.version = 5;
+        cu.files = files;
+        assert_eq!(Some("main.c"), cu.get_file(0));
+        assert_eq!(Some("/inc/util.h"), cu.get_file(1));
+        cu.version = 4;
+        assert_eq!(Some("main.c"), cu.get_file(1));
+        assert_eq!(None, cu.get_file(0));
     }
 }
@@ -17,6 +17,26 @@ const MASK_ST_TYPE: u8 = 0x0F;
 const MASK_ST_BIND: u8 = 0xF0;
 const SHIFT_ST_BIND: u8 = 0x04;
 
+// ノートの種類(ビルドID)
+const NT_GNU_BUILD_ID: u32 = 3;
+
+// ELFの種類(共有オブジェクト・PIE)
+const ET_DYN: u16 = 3;
+
+// セクションのフラグ(スレッドローカル)
+const SHF_TLS: u64 = 0x400;
+
+// 64bit・リトルエンディアンのみ対応
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+
+// ヘッダー・エントリのサイズ
+const EHDR_SIZE: u64 = 64;
+const PHDR_SIZE: u64 = 56;
+const SHDR_SIZE: u64 = 64;
+const SYM_SIZE: u64 = 24;
+
 // ELFヘッダー
 #[derive(Debug)]
 struct ElfHeader {
@@ -111,6 +131,10 @@ impl ElfSecHeader {
     pub fn get_name(&self) -> &str {
         &self.sh_rname
     }
+    /// セクションアドレス取得(ロードされないセクションは0)
+    pub fn get_addr(&self) -> Elf64Addr {
+        self.sh_addr
+    }
     /// セクションオフセット取得
     pub fn get_offset(&self) -> Elf64Offset {
         self.sh_offset
@@ -212,6 +236,16 @@ impl SymTbl {
             st_type: StType::Unknown,
         }
     }
+
+    /// サイズ取得
+    pub fn get_size(&self) -> u64 {
+        self.st_size
+    }
+
+    /// シンボル名取得(デマングル済み)
+    pub fn get_name(&self) -> String {
+        demangle(&self.st_rname).to_string()
+    }
 }
 
 // ELFデータ
@@ -221,7 +255,9 @@ pub struct Elf64 {
     prog_header: Vec<ElfProgHeader>,
     sec_header: Vec<ElfSecHeader>,
     sym_tbl: Vec<SymTbl>,
+    func_order: Vec<usize>, // 関数シンボルのインデックス(アドレスの順)
     dwarf: Dwarf,
+    file_size: u64, // ファイルサイズ(オフセット・サイズの検証に使う)
 }
 
 /// ELF解析
@@ -234,19 +270,45 @@ impl Elf64 {
             prog_header: vec![],
             sec_header: vec![],
             sym_tbl: vec![],
+            func_order: vec![],
             dwarf: Dwarf::new(),
+            file_size: 0,
         }
     }
 
-    /// debugセクション情表示
-    pub fn show_debug(&self) {
-        self.dwarf.show();
+    /// エントリーポイント取得
+    pub fn get_entry(&self) -> u64 {
+        self.header.e_entry
+    }
+
+    /// セクションヘッダー一覧取得(先頭はNULLセクション)
+    pub fn get_sec_headers(&self) -> &[ElfSecHeader] {
+        &self.sec_header
+    }
+
+    /// 位置独立(ロードしたアドレスからの相対アドレス)か
+    pub fn is_pie(&self) -> bool {
+        ET_DYN == self.header.e_type
+    }
+
+    /// dwarf情報取得
+    pub fn get_dwarf(&self) -> &Dwarf {
+        &self.dwarf
     }
 
     /// ELFデータロード
     pub fn load(&mut self) -> Result<()> {
+        self.load_headers()?;
+        self.load_symbols()
+    }
+
+    /// ヘッダー(ELF・プログラム・セクション)ロード
+    ///
+    /// ヘッダーのオフセット・サイズがファイルに収まらなければエラーとする
+    pub fn load_headers(&mut self) -> Result<()> {
         // ELFヘッダーロード
         let f = File::open(&self.path)?;
+        self.file_size = f.metadata()?.len();
         let mut reader = BufReader::new(f);
         self.load_elf_header(&mut reader)?;
 
@@ -256,8 +318,15 @@ impl Elf64 {
         // セクションヘッダーロード
         self.load_sec_header(&mut reader)?;
 
+        Ok(())
+    }
+
+    /// シンボル(シンボルテーブル・dwarf情報)ロード
+    ///
+    /// load_headersでセクションヘッダーをロードした後に呼ぶ
+    pub fn load_symbols(&mut self) -> Result<()> {
         // シンボルテーブルロード
-        self.load_symtab(&mut reader)?;
+        self.load_sym_table()?;
 
         // dwarf情報読み込み
         self.dwarf.load(&self.path, &self.sec_header)?;
@@ -265,6 +334,81 @@ impl Elf64 {
         Ok(())
     }
 
+    /// シンボルテーブルのみロード(dwarf情報は読み込まない)
+    ///
+    /// load_headersでセクションヘッダーをロードした後に呼ぶ
+    pub fn load_sym_table(&mut self) -> Result<()> {
+        let f = File::open(&self.path)?;
+        let mut reader = BufReader::new(f);
+        self.load_symtab(&mut reader)
+    }
+
+    /// 共有ライブラリのシンボル(シンボルテーブル・dwarf情報)ロード
+    ///
+    /// strip済みで.symtabがなければ、.dynsym(エクスポートされているシンボル)を読み込む
+    /// dwarf情報がなければ、シンボルのみとする
+    /// load_headersでセクションヘッダーをロードした後に呼ぶ
+    pub fn load_library_symbols(&mut self) -> Result<()> {
+        let f = File::open(&self.path)?;
+        let mut reader = BufReader::new(f);
+        let has_symtab = self
+            .sec_header
+            .iter()
+            .any(|s| self.to_shtype(s.sh_type) == ShType::ShmTab);
+        match has_symtab {
+            true => self.load_symtab(&mut reader)?,
+            false => self.load_dynsym(&mut reader)?,
+        }
+        // システムのライブラリはdwarf情報を持たないことが多いため、なくてもエラーとしない
+        match self.dwarf.load(&self.path, &self.sec_header) {
+            Err(e) if ErrorKind::NotFound == e.kind() => Ok(()),
+            r => r,
+        }
+    }
+
+    /// シンボルを別のファイル(symbol-file)から読み込んだものに差し替える
+    ///
+    /// セクション等のヘッダーは、このファイルのものを使い続ける
+    pub fn replace_symbols(&mut self, other: Elf64) {
+        self.sym_tbl = other.sym_tbl;
+        self.func_order = other.func_order;
+        self.dwarf = other.dwarf;
+    }
+
+    /// シンボルを破棄
+    pub fn clear_symbols(&mut self) {
+        self.sym_tbl.clear();
+        self.func_order.clear();
+        self.dwarf = Dwarf::new();
+    }
+
+    /// ビルドID(.note.gnu.build-id)取得
+    pub fn get_build_id(&self) -> Option<Vec<u8>> {
+        let (_, data) = self.read_section(".note.gnu.build-id").ok()?;
+        parse_build_id(&data)
+    }
+
+    /// セクションの内容を取得
+    ///
+    /// セクションのアドレスとデータを返す(ファイル上にデータを持たないNOBITSはエラー)
+    pub fn read_section(&self, name: &str) -> Result<(u64, Vec<u8>)> {
+        let sec = self
+            .sec_header
+            .iter()
+            .find(|s| s.get_name() == name)
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("no section named {}", name)))?;
+        if self.to_shtype(sec.sh_type) == ShType::Nobits {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("section {} has no contents in file", name),
+            ));
+        }
+
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let buf = self.read_sec_data(&mut reader, sec)?;
+        Ok((sec.sh_addr, buf))
+    }
+
     /// Functionシンボルサーチ
     pub fn search_func_sym(&self, sym_name: &str) -> Option<&SymTbl> {
         self.sym_tbl
@@ -272,6 +416,43 @@ impl Elf64 {
             .find(|sym| *sym_name == demangle(&sym.st_rname) && sym.st_type == StType::Func)
     }
 
+    /// アドレスを含むFunctionシンボルサーチ
+    pub fn search_func_sym_by_addr(&self, addr: u64) -> Option<&SymTbl> {
+        self.get_func_syms()
+            .find(|sym| sym.st_value <= addr && addr < sym.st_value + sym.st_size)
+    }
+
+    /// アドレスに最も近い(アドレス以前で最後の)Functionシンボルサーチ
+    ///
+    /// サイズを持つシンボルは範囲内のみとし、サイズのないシンボルは同じセクション内の次のシンボルまでとする
+    /// 未定義(アドレス0)のシンボルは対象外
+    pub fn search_nearest_func_sym(&self, addr: u64) -> Option<&SymTbl> {
+        let i = self
+            .func_order
+            .partition_point(|i| self.sym_tbl[*i].st_value <= addr);
+        let sym = &self.sym_tbl[*self.func_order[..i].last()?];
+        let found = match sym.st_size {
+            0 => self
+                .sec_header
+                .get(sym.st_shndx as usize)
+                .is_some_and(|s| s.sh_addr <= addr && addr < s.sh_addr + s.sh_size),
+            size => addr < sym.st_value + size,
+        };
+        found.then_some(sym)
+    }
+
+    /// アドレスを含むセクションを検索(.data・.bss等)
+    ///
+    /// スレッドローカルのセクションは、他のセクションとアドレスが重なるため対象外とする
+    pub fn search_section(&self, addr: u64) -> Option<&ElfSecHeader> {
+        self.sec_header.iter().find(|s| {
+            0 != s.sh_addr
+                && 0 == s.sh_flags & SHF_TLS
+                && s.sh_addr <= addr
+                && addr < s.sh_addr + s.sh_size
+        })
+    }
+
     /// Variableシンボルサーチ
     pub fn search_var_sym(&self, sym_name: &str) -> Option<&SymTbl> {
         self.sym_tbl
@@ -279,14 +460,44 @@ impl Elf64 {
             .find(|sym| *sym_name == demangle(&sym.st_rname) && sym.st_type == StType::Object)
     }
 
+    /// Functionシンボル一覧取得
+    pub fn get_func_syms(&self) -> impl Iterator<Item = &SymTbl> {
+        self.sym_tbl
+            .iter()
+            .filter(|sym| sym.st_type == StType::Func)
+    }
+
+    /// Variableシンボル一覧取得
+    pub fn get_var_syms(&self) -> impl Iterator<Item = &SymTbl> {
+        self.sym_tbl
+            .iter()
+            .filter(|sym| sym.st_type == StType::Object)
+    }
+
     /// ELFヘッダー読み込み
     fn load_elf_header(&mut self, reader: &mut BufReader<File>) -> Result<()> {
+        check_extent("ELF header", 0, EHDR_SIZE, self.file_size)?;
+
         // e_ident
         reader.read_exact(&mut self.header.e_ident)?;
+        let ident = &self.header.e_ident;
+        if ELF_MAGIC != &ident[..4] {
+            return Err(Error::new(ErrorKind::InvalidData, "not an ELF file"));
+        }
+        if ELFCLASS64 != ident[4] || ELFDATA2LSB != ident[5] {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "unsupported ELF class {} / data encoding {} (64-bit little endian only)",
+                    ident[4], ident[5]
+                ),
+            ));
+        }
 
         // e_type
         let mut half_word = [0; 2];
         reader.read_exact(&mut half_word)?;
+        self.header.e_type = u16::from_le_bytes(half_word);
 
         // e_machine
         reader.read_exact(&mut half_word)?;
@@ -338,7 +549,8 @@ impl Elf64 {
         reader.read_exact(&mut half_word)?;
         self.header.e_shstrndx = u16::from_le_bytes(half_word);
 
-        // プログラムヘッダー、セクションヘッダー数が判明したので、リサイズ
+        // ヘッダーテーブルがファイルに収まるか検証してから、リサイズ
+        self.check_header_tables()?;
         self.prog_header
             .resize(self.header.e_phnum as usize, ElfProgHeader::new());
         self.sec_header
@@ -347,12 +559,58 @@ impl Elf64 {
         Ok(())
     }
 
+    /// プログラムヘッダー・セクションヘッダーのテーブルを検証
+    ///
+    /// エントリのサイズ・テーブルの範囲・セクション名の文字列テーブルの番号を確認する
+    fn check_header_tables(&self) -> Result<()> {
+        let h = &self.header;
+        let tables = [
+            (
+                "program header",
+                h.e_phoff,
+                h.e_phnum,
+                h.e_phentsize,
+                PHDR_SIZE,
+            ),
+            (
+                "section header",
+                h.e_shoff,
+                h.e_shnum,
+                h.e_shentsize,
+                SHDR_SIZE,
+            ),
+        ];
+        for (what, offset, num, entsize, expected) in tables {
+            if 0 == num {
+                continue;
+            }
+            if expected != entsize as u64 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("unsupported {} entry size {}", what, entsize),
+                ));
+            }
+            let table = format!("{} table", what);
+            check_extent(&table, offset, num as u64 * expected, self.file_size)?;
+        }
+        if 0 < h.e_shnum && h.e_shnum <= h.e_shstrndx {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "section name string table index {} out of range ({} sections)",
+                    h.e_shstrndx, h.e_shnum
+                ),
+            ));
+        }
+        Ok(())
+    }
+
     /// プログラムヘッダーロード
     fn load_prog_header(&mut self, reader: &mut BufReader<File>) -> Result<()> {
-        // プログラムヘッダー位置へSeek
-        reader.seek(SeekFrom::Start(self.header.e_phoff))?;
-
         for i in 0..self.header.e_phnum {
+            // プログラムヘッダー位置へSeek
+            reader.seek(SeekFrom::Start(self.header.e_phoff + i as u64 * PHDR_SIZE))?;
+
             // p_type
             let mut word = [0; 4];
             reader.read_exact(&mut word)?;
@@ -362,7 +620,7 @@ impl Elf64 {
             reader.read_exact(&mut word)?;
             self.prog_header[i as usize].p_flags = u32::from_le_bytes(word);
 
-            // p_flags
+            // p_offset
             let mut word64 = [0; 8];
             reader.read_exact(&mut word64)?;
             self.prog_header[i as usize].p_offset = u64::from_le_bytes(word64);
@@ -375,6 +633,10 @@ impl Elf64 {
             reader.read_exact(&mut word64)?;
             self.prog_header[i as usize].p_paddr = u64::from_le_bytes(word64);
 
+            // p_filesz
+            reader.read_exact(&mut word64)?;
+            self.prog_header[i as usize].p_filesz = u64::from_le_bytes(word64);
+
             // p_memsz
             reader.read_exact(&mut word64)?;
             self.prog_header[i as usize].p_memsz = u64::from_le_bytes(word64);
@@ -444,7 +706,8 @@ impl Elf64 {
         for i in 0..self.header.e_shnum {
             // 実際のセクション名をstrtabセクションからリード
             let offset = self.sec_header[i as usize].sh_name as usize;
-            self.sec_header[i as usize].sh_rname = self.to_string(&strtab_buf, offset);
+            self.sec_header[i as usize].sh_rname =
+                to_string(&strtab_buf, offset, "section name string table")?;
         }
 
         Ok(())
@@ -463,13 +726,68 @@ impl Elf64 {
             .collect::<Vec<&ElfSecHeader>>()
             .pop()
         {
-            Some(header) => header,
+            Some(header) => header.clone(),
             _ => return Err(Error::new(ErrorKind::NotFound, "Not found symtab")),
         };
+        self.read_syms(reader, &symtab, &strtab_buf)
+    }
+
+    /// 動的シンボルテーブル(.dynsym)ロード
+    ///
+    /// シンボル名は、sh_linkが指す文字列テーブル(.dynstr)からリードする
+    fn load_dynsym(&mut self, reader: &mut BufReader<File>) -> Result<()> {
+        let dynsym = match self
+            .sec_header
+            .iter()
+            .rfind(|s| self.to_shtype(s.sh_type) == ShType::DynSym)
+        {
+            Some(header) => header.clone(),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::NotFound,
+                    "Not found symtab and dynsym",
+                ))
+            }
+        };
+        let dynstr = match self.sec_header.get(dynsym.sh_link as usize) {
+            Some(header) => header.clone(),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "section {}: link {} is out of sections",
+                        dynsym.get_name(),
+                        dynsym.sh_link
+                    ),
+                ))
+            }
+        };
+        let strtab_buf = self.read_sec_data(reader, &dynstr)?;
+        self.read_syms(reader, &dynsym, &strtab_buf)
+    }
+
+    /// シンボルテーブルのエントリをリード
+    fn read_syms(
+        &mut self,
+        reader: &mut BufReader<File>,
+        symtab: &ElfSecHeader,
+        strtab_buf: &[u8],
+    ) -> Result<()> {
+        let what = format!("section {}", symtab.get_name());
+        if SYM_SIZE != symtab.sh_entsize || !symtab.sh_size.is_multiple_of(SYM_SIZE) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "{}: size 0x{:x} is not a multiple of symbol size {} (entry size {})",
+                    what, symtab.sh_size, SYM_SIZE, symtab.sh_entsize
+                ),
+            ));
+        }
+        check_extent(&what, symtab.sh_offset, symtab.sh_size, self.file_size)?;
         reader.seek(SeekFrom::Start(symtab.sh_offset))?;
 
-        // sym_tblリサイズ
-        let count = (symtab.sh_size / symtab.sh_entsize) as usize;
+        // sym_tblリサイズ(ファイルに収まる数のみ)
+        let count = (symtab.sh_size / SYM_SIZE) as usize;
         self.sym_tbl.resize(count, SymTbl::new());
 
         // すべてのシンボルをロード
@@ -481,7 +799,8 @@ impl Elf64 {
             self.sym_tbl[i].st_name = offset;
 
             // 実際のシンボル名をstrtabセクションからリード
-            self.sym_tbl[i].st_rname = self.to_string(&strtab_buf, offset as usize);
+            self.sym_tbl[i].st_rname =
+                to_string(strtab_buf, offset as usize, "symbol string table")?;
 
             // st_info
             let mut c = [0; 1];
@@ -510,10 +829,21 @@ impl Elf64 {
             reader.read_exact(&mut word64)?;
             self.sym_tbl[i].st_size = u64::from_le_bytes(word64);
         }
+        self.index_func_syms();
 
         Ok(())
     }
 
+    /// 関数シンボルを、アドレスの順に並べる(同じアドレスはサイズを持つものを後ろにする)
+    fn index_func_syms(&mut self) {
+        let syms = &self.sym_tbl;
+        let mut order = (0..syms.len())
+            .filter(|i| syms[*i].st_type == StType::Func && 0 != syms[*i].st_value)
+            .collect::<Vec<usize>>();
+        order.sort_by_key(|i| (syms[*i].st_value, syms[*i].st_size));
+        self.func_order = order;
+    }
+
     /// strtabセクションデータリード
     fn read_strtab(&self, reader: &mut BufReader<File>) -> Result<Vec<u8>> {
         // .strtabセクションをサーチ(shstrtabは除外する)
@@ -531,11 +861,7 @@ impl Elf64 {
         };
 
         // strtab情報をリード
-        reader.seek(SeekFrom::Start(strtab.sh_offset))?;
-        let mut buf: Vec<u8> = vec![0; strtab.sh_size as usize];
-        reader.read_exact(&mut buf)?;
-
-        Ok(buf)
+        self.read_sec_data(reader, strtab)
     }
 
     /// strtabセクションデータリード（for section name）
@@ -555,10 +881,19 @@ impl Elf64 {
         };
 
         // strtab情報をリード
-        reader.seek(SeekFrom::Start(strtab.sh_offset))?;
-        let mut buf: Vec<u8> = vec![0; strtab.sh_size as usize];
-        reader.read_exact(&mut buf)?;
+        self.read_sec_data(reader, strtab)
+    }
 
+    /// セクションのデータをリード(ファイルに収まらなければエラー)
+    fn read_sec_data(&self, reader: &mut BufReader<File>, sec: &ElfSecHeader) -> Result<Vec<u8>> {
+        let what = match sec.get_name() {
+            "" => format!("section [{}]", sec.sh_no),
+            name => format!("section {}", name),
+        };
+        check_extent(&what, sec.sh_offset, sec.sh_size, self.file_size)?;
+        reader.seek(SeekFrom::Start(sec.sh_offset))?;
+        let mut buf = vec![0; sec.sh_size as usize];
+        reader.read_exact(&mut buf)?;
         Ok(buf)
     }
 
@@ -604,18 +939,505 @@ impl Elf64 {
             _ => StBind::Unknown,
         }
     }
+}
+
+/// Null Terminator文字列
+///
+/// シンボルが入っているセクションデータと文字列開始位置を受け取り、
+/// NullTermnateである文字列を返却する(whatは文字列テーブルの名前で、範囲外であればエラー)
+fn to_string(buf: &[u8], offset: usize, what: &str) -> Result<String> {
+    if 0 != offset && buf.len() <= offset {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "{}: string offset 0x{:x} out of range (size 0x{:x})",
+                what,
+                offset,
+                buf.len()
+            ),
+        ));
+    }
+    let t = buf
+        .iter()
+        .skip(offset)
+        .take_while(|&c| *c != 0) // nullまで読み込み
+        .cloned()
+        .collect::<Vec<u8>>();
+    Ok(String::from_utf8_lossy(&t).into_owned())
+}
 
-    /// Null Terminator文字列
-    ///
-    /// シンボルが入っているセクションデータと文字列開始位置を受け取り、
-    /// NullTermnateである文字列を返却する
-    fn to_string(&self, buf: &[u8], offset: usize) -> String {
-        let t: Vec<u8> = buf
-            .iter()
-            .skip(offset)
-            .take_while(|&c| *c != 0) // nullまで読み込み
-            .cloned()
-            .collect();
-        String::from_utf8(t).unwrap()
+/// ファイル上の範囲(オフセット・サイズ)がファイルに収まるか検証
+///
+/// whatは範囲の名前で、収まらなければ範囲を含めたエラーを返す
+pub fn check_extent(what: &str, offset: u64, size: u64, file_size: u64) -> Result<()> {
+    match offset.checked_add(size) {
+        Some(end) if end <= file_size => Ok(()),
+        _ => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "{} at offset 0x{:x} (size 0x{:x}) exceeds file size 0x{:x}",
+                what, offset, size, file_size
+            ),
+        )),
+    }
+}
+
+/// ノートセクションから、GNUのビルドIDを取得
+pub fn parse_build_id(data: &[u8]) -> Option<Vec<u8>> {
+    parse_notes(data)
+        .into_iter()
+        .find(|n| NT_GNU_BUILD_ID == n.ty && b"GNU\0" == n.name)
+        .map(|n| n.desc.to_vec())
+}
+
+/// ノート
+pub struct Note<'a> {
+    pub name: &'a [u8], // 名前(NUL終端を含む)
+    pub ty: u32,        // 種類
+    pub desc: &'a [u8], // 内容
+}
+
+/// ノートセクション・セグメントを、ノート毎に分割
+///
+/// ノートは名前サイズ・内容サイズ・種類の後に、4バイト境界に揃えた名前・内容が続く
+/// 途中で壊れていれば、そこまでのノートを返す
+pub fn parse_notes(data: &[u8]) -> Vec<Note<'_>> {
+    let align = |n: usize| (n + 3) & !3;
+    let mut notes = vec![];
+    let mut rest = data;
+    while 12 <= rest.len() {
+        let word = |i: usize| u32::from_le_bytes([rest[i], rest[i + 1], rest[i + 2], rest[i + 3]]);
+        let (namesz, descsz, ty) = (word(0) as usize, word(4) as usize, word(8));
+        let desc = 12 + align(namesz);
+        let (name, value) = match (rest.get(12..12 + namesz), rest.get(desc..desc + descsz)) {
+            (Some(n), Some(v)) => (n, v),
+            _ => break,
+        };
+        notes.push(Note {
+            name,
+            ty,
+            desc: value,
+        });
+        rest = rest.get(desc + align(descsz)..).unwrap_or_default();
+    }
+    notes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryInto;
+
+    // 検証用ELFのセクション番号
+    const SEC_SHSTRTAB: usize = 1;
+    const SEC_STRTAB: usize = 2;
+    const SEC_SYMTAB: usize = 3;
+    const SEC_DEBUG_INFO: usize = 4;
+
+    fn put(data: &mut [u8], offset: usize, bytes: &[u8]) {
+        data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    // セクションヘッダーのフィールドの位置
+    fn sh_field(data: &[u8], no: usize, field: usize) -> usize {
+        let shoff = u64::from_le_bytes(data[40..48].try_into().unwrap()) as usize;
+        shoff + no * SHDR_SIZE as usize + field
+    }
+
+    // シンボルテーブル・dwarfセクションを持つ、最小限のELFを作成
+    //
+    // ELFヘッダー・プログラムヘッダー(PT_LOAD)・セクションのデータ・セクションヘッダーの順に並べる
+    fn build_elf(debug_info: &[u8]) -> Vec<u8> {
+        let names = [
+            ".shstrtab",
+            ".strtab",
+            ".symtab",
+            ".debug_info",
+            ".debug_abbrev",
+            ".debug_str",
+            ".debug_line",
+        ];
+        let mut shstrtab = vec![0];
+        let mut name_offsets = vec![];
+        for n in names.iter() {
+            name_offsets.push(shstrtab.len() as u32);
+            shstrtab.extend_from_slice(n.as_bytes());
+            shstrtab.push(0);
+        }
+        let strtab = b"\0main\0".to_vec();
+        let mut symtab = vec![0; SYM_SIZE as usize];
+        symtab.extend_from_slice(&1u32.to_le_bytes()); // st_name(main)
+        symtab.extend_from_slice(&[0x12, 0]); // GLOBAL FUNC・st_other
+        symtab.extend_from_slice(&1u16.to_le_bytes()); // st_shndx
+        symtab.extend_from_slice(&0x1000u64.to_le_bytes()); // st_value
+        symtab.extend_from_slice(&0x10u64.to_le_bytes()); // st_size
+        let sections: [(u32, &[u8], u64); 7] = [
+            (3, &shstrtab, 0),
+            (3, &strtab, 0),
+            (2, &symtab, SYM_SIZE),
+            (1, debug_info, 0),
+            (1, &[], 0),
+            (1, &[], 0),
+            (1, &[], 0),
+        ];
+
+        let mut data = vec![0; (EHDR_SIZE + PHDR_SIZE) as usize];
+        let mut headers = vec![0; SHDR_SIZE as usize]; // [0]はNULLセクション
+        for (i, (ty, content, entsize)) in sections.iter().enumerate() {
+            headers.extend_from_slice(&name_offsets[i].to_le_bytes());
+            headers.extend_from_slice(&ty.to_le_bytes());
+            headers.extend_from_slice(&[0; 16]); // sh_flags・sh_addr
+            headers.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            headers.extend_from_slice(&(content.len() as u64).to_le_bytes());
+            headers.extend_from_slice(&[0; 16]); // sh_link・sh_info・sh_addralign
+            headers.extend_from_slice(&entsize.to_le_bytes());
+            data.extend_from_slice(content);
+        }
+        let shoff = data.len() as u64;
+        data.extend_from_slice(&headers);
+
+        put(&mut data, 0, ELF_MAGIC);
+        put(&mut data, 4, &[ELFCLASS64, ELFDATA2LSB, 1]);
+        put(&mut data, 16, &ET_DYN.to_le_bytes());
+        put(&mut data, 18, &62u16.to_le_bytes()); // x86-64
+        put(&mut data, 20, &1u32.to_le_bytes());
+        put(&mut data, 32, &EHDR_SIZE.to_le_bytes()); // e_phoff
+        put(&mut data, 40, &shoff.to_le_bytes());
+        put(&mut data, 52, &(EHDR_SIZE as u16).to_le_bytes());
+        put(&mut data, 54, &(PHDR_SIZE as u16).to_le_bytes());
+        put(&mut data, 56, &1u16.to_le_bytes()); // e_phnum
+        put(&mut data, 58, &(SHDR_SIZE as u16).to_le_bytes());
+        put(&mut data, 60, &(sections.len() as u16 + 1).to_le_bytes());
+        put(&mut data, 62, &(SEC_SHSTRTAB as u16).to_le_bytes());
+        // PT_LOAD(ファイル全体)
+        put(&mut data, 64, &1u32.to_le_bytes());
+        let len = data.len() as u64;
+        put(&mut data, 64 + 32, &len.to_le_bytes());
+        data
+    }
+
+    // ファイルへ書き出して、ヘッダー・シンボルをロード
+    fn load(name: &str, data: &[u8]) -> Result<Elf64> {
+        let path =
+            std::env::temp_dir().join(format!("r-debugger-elf64-{}-{}", std::process::id(), name));
+        std::fs::write(&path, data).unwrap();
+        let mut elf = Elf64::new(path.to_string_lossy().into_owned());
+        let result = elf.load_headers().and_then(|_| elf.load_symbols());
+        let _ = std::fs::remove_file(&path);
+        result.map(|_| elf)
+    }
+
+    #[test]
+    fn test_load() {
+        let elf = load("valid", &build_elf(&[])).unwrap();
+        assert!(elf.is_pie());
+        assert_eq!(1, elf.prog_header.len());
+        assert_eq!(
+            elf.file_size, elf.prog_header[0].p_filesz,
+            "p_filesz is parsed"
+        );
+        let main = elf.search_func_sym("main").unwrap();
+        assert_eq!((0x1000, 0x10), (main.st_value, main.st_size));
+    }
+
+    #[test]
+    fn test_load_corrupted() {
+        let huge = u64::MAX - 0x10;
+        let sh = |no, field| move |d: &mut Vec<u8>| sh_field(d, no, field);
+        let symtab_name = |d: &mut Vec<u8>| {
+            u64::from_le_bytes(d[sh_field(d, SEC_SYMTAB, 24)..][..8].try_into().unwrap()) as usize
+                + SYM_SIZE as usize
+        };
+        type Corrupt = Box<dyn Fn(&mut Vec<u8>)>;
+        let cases: Vec<(&str, Corrupt, &str)> = vec![
+            (
+                "truncated",
+                Box::new(|d| d.truncate(10)),
+                "ELF header at offset 0x0 (size 0x40) exceeds file size 0xa",
+            ),
+            (
+                "magic",
+                Box::new(|d| put(d, 0, b"\x7fBAD")),
+                "not an ELF file",
+            ),
+            (
+                "class",
+                Box::new(|d| put(d, 4, &[1])),
+                "unsupported ELF class 1",
+            ),
+            (
+                "shoff",
+                Box::new(move |d| put(d, 40, &huge.to_le_bytes())),
+                "section header table at offset 0xffffffffffffffef",
+            ),
+            (
+                "phnum",
+                Box::new(|d| put(d, 56, &0xFFFFu16.to_le_bytes())),
+                "program header table at offset 0x40 (size 0x37ffc8)",
+            ),
+            (
+                "shentsize",
+                Box::new(|d| put(d, 58, &40u16.to_le_bytes())),
+                "unsupported section header entry size 40",
+            ),
+            (
+                "shstrndx",
+                Box::new(|d| put(d, 62, &99u16.to_le_bytes())),
+                "section name string table index 99 out of range (8 sections)",
+            ),
+            (
+                "shstrtab_size",
+                Box::new(move |d| {
+                    let at = sh(SEC_SHSTRTAB, 32)(d);
+                    put(d, at, &huge.to_le_bytes())
+                }),
+                "section [1] at offset",
+            ),
+            (
+                "sh_name",
+                Box::new(move |d| {
+                    let at = sh(SEC_STRTAB, 0)(d);
+                    put(d, at, &0x1000u32.to_le_bytes())
+                }),
+                "section name string table: string offset 0x1000 out of range",
+            ),
+            (
+                "strtab_offset",
+                Box::new(move |d| {
+                    let at = sh(SEC_STRTAB, 24)(d);
+                    put(d, at, &huge.to_le_bytes())
+                }),
+                "section .strtab at offset 0xffffffffffffffef",
+            ),
+            (
+                "symtab_size",
+                Box::new(move |d| {
+                    let at = sh(SEC_SYMTAB, 32)(d);
+                    put(d, at, &(SYM_SIZE * 0x1000_0000).to_le_bytes())
+                }),
+                "section .symtab at offset",
+            ),
+            (
+                "symtab_entsize",
+                Box::new(move |d| {
+                    let at = sh(SEC_SYMTAB, 56)(d);
+                    put(d, at, &0u64.to_le_bytes())
+                }),
+                "section .symtab: size 0x30 is not a multiple of symbol size 24 (entry size 0)",
+            ),
+            (
+                "st_name",
+                Box::new(move |d| {
+                    let at = symtab_name(d);
+                    put(d, at, &0x100u32.to_le_bytes())
+                }),
+                "symbol string table: string offset 0x100 out of range (size 0x6)",
+            ),
+            (
+                "debug_info_offset",
+                Box::new(move |d| {
+                    let at = sh(SEC_DEBUG_INFO, 24)(d);
+                    put(d, at, &huge.to_le_bytes())
+                }),
+                "section .debug_info at offset 0xffffffffffffffef",
+            ),
+        ];
+        for (name, corrupt, expected) in cases {
+            let mut data = build_elf(&[]);
+            corrupt(&mut data);
+            match load(name, &data) {
+                Ok(_) => panic!("{}: loaded", name),
+                Err(e) => assert!(e.to_string().contains(expected), "{}: {}", name, e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_search_section() {
+        let mut elf = Elf64::new("".to_string());
+        let secs = [
+            ("", 0, 0, 0),
+            (".rodata", 0x2000, 0x100, 0),
+            (".tbss", 0x3de0, 0x10, SHF_TLS),
+            (".init_array", 0x3de0, 0x8, 0),
+            (".data", 0x4000, 0x10, 0),
+            (".bss", 0x4010, 0x20, 0),
+            (".comment", 0, 0x2b, 0),
+        ];
+        for (name, addr, size, flags) in secs {
+            let mut sec = ElfSecHeader::new();
+            sec.sh_rname = name.to_string();
+            sec.sh_addr = addr;
+            sec.sh_size = size;
+            sec.sh_flags = flags;
+            elf.sec_header.push(sec);
+        }
+        let cases = vec![
+            (0x0, None),
+            (0x2010, Some(".rodata")),
+            (0x3de0, Some(".init_array")),
+            (0x4008, Some(".data")),
+            (0x4010, Some(".bss")),
+            (0x402f, Some(".bss")),
+            (0x4030, None),
+        ];
+        for (addr, expected) in cases {
+            let name = elf.search_section(addr).map(|s| s.get_name());
+            assert_eq!(expected, name, "0x{:x}", addr);
+        }
+    }
+
+    #[test]
+    fn test_search_nearest_func_sym() {
+        let mut elf = Elf64::new("".to_string());
+        // .text(0x1000-0x1300)・.fini(0x1300-0x1310)
+        for (addr, size) in [(0, 0), (0x1000, 0x300), (0x1300, 0x10)] {
+            let mut sec = ElfSecHeader::new();
+            sec.sh_addr = addr;
+            sec.sh_size = size;
+            elf.sec_header.push(sec);
+        }
+        let syms = [
+            ("leaf", 0x1200, 0x10, 1, StType::Func),
+            ("main", 0x1100, 0x40, 1, StType::Func),
+            ("_start", 0x1000, 0, 1, StType::Func),
+            ("g_var", 0x1180, 8, 1, StType::Object),
+            ("puts", 0, 0, 0, StType::Func),
+            ("_fini", 0x1300, 0, 2, StType::Func),
+        ];
+        for (name, value, size, shndx, ty) in syms {
+            let mut s = SymTbl::new();
+            s.st_rname = name.to_string();
+            s.st_value = value;
+            s.st_size = size;
+            s.st_shndx = shndx;
+            s.st_type = ty;
+            elf.sym_tbl.push(s);
+        }
+        elf.index_func_syms();
+        let cases = vec![
+            (0x0fff, None),
+            // サイズのないシンボルは、次のシンボルまで
+            (0x1000, Some("_start")),
+            (0x10ff, Some("_start")),
+            (0x1100, Some("main")),
+            (0x113f, Some("main")),
+            // サイズを持つシンボルの範囲外・変数は対象外
+            (0x1140, None),
+            (0x1180, None),
+            (0x120f, Some("leaf")),
+            (0x1210, None),
+            // サイズのないシンボルも、セクションの範囲外は対象外
+            (0x130f, Some("_fini")),
+            (0x1310, None),
+            (0x7f00_0000_0000, None),
+        ];
+        for (addr, expected) in cases {
+            let name = elf.search_nearest_func_sym(addr).map(|s| s.get_name());
+            assert_eq!(expected.map(String::from), name, "0x{:x}", addr);
+        }
+    }
+
+    #[test]
+    fn test_load_dynsym() {
+        // .symtabがなければ、実行ファイルとしてはロードできない
+        let mut data = build_elf(&[]);
+        let at = sh_field(&data, SEC_SYMTAB, 4);
+        put(&mut data, at, &11u32.to_le_bytes());
+        assert!(load("dynsym", &data).is_err());
+
+        // 共有ライブラリは、sh_linkが指す文字列テーブルで.dynsymをロードする
+        let at = sh_field(&data, SEC_SYMTAB, 40);
+        put(&mut data, at, &(SEC_STRTAB as u32).to_le_bytes());
+        let path =
+            std::env::temp_dir().join(format!("r-debugger-elf64-{}-dynsym", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+        let mut elf = Elf64::new(path.to_string_lossy().into_owned());
+        let result = elf.load_headers().and_then(|_| elf.load_library_symbols());
+        let _ = std::fs::remove_file(&path);
+        result.unwrap();
+        let main = elf.search_func_sym("main").unwrap();
+        assert_eq!((0x1000, 0x10), (main.st_value, main.st_size));
+    }
+
+    #[test]
+    fn test_load_tolerated() {
+        // 不正なUTF-8のシンボル名は、置き換えて読み込む
+        let mut data = build_elf(&[]);
+        let at = u64::from_le_bytes(
+            data[sh_field(&data, SEC_STRTAB, 24)..][..8]
+                .try_into()
+                .unwrap(),
+        );
+        put(&mut data, at as usize + 1, &[0xFF]);
+        let elf = load("utf8", &data).unwrap();
+        assert_eq!(1, elf.get_func_syms().count());
+
+        // 長さが最大のCU(64bit形式)は、セクションの終わりまでとする
+        let mut cu = vec![0xFF; 12];
+        cu.extend_from_slice(&5u16.to_le_bytes()); // version
+        cu.extend_from_slice(&[1, 8]); // unit_type・address_size
+        cu.extend_from_slice(&0u64.to_le_bytes()); // abbrev offset(64bit形式は8byte)
+        assert!(load("cu_len", &build_elf(&cu)).is_ok());
+    }
+
+    #[test]
+    fn test_load_fuzzed() {
+        // どこで切れていても、どのバイトが壊れていてもパニックしない
+        let valid = build_elf(&[]);
+        for len in 0..valid.len() {
+            assert!(
+                load("truncate", &valid[..len]).is_err(),
+                "truncated at {}",
+                len
+            );
+        }
+        let mut seed = 0x2545_F491_4F6C_DD1Du64;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        for _ in 0..500 {
+            let mut data = valid.clone();
+            for _ in 0..1 + next() % 4 {
+                let at = (next() % data.len() as u64) as usize;
+                data[at] = next() as u8;
+            }
+            let _ = load("fuzz", &data);
+        }
+    }
+
+    #[test]
+    fn test_parse_build_id() {
+        let note = |name: &[u8], ty: u32, desc: &[u8]| {
+            let mut n = vec![];
+            n.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            n.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+            n.extend_from_slice(&ty.to_le_bytes());
+            n.extend_from_slice(name);
+            n.resize((n.len() + 3) & !3, 0);
+            n.extend_from_slice(desc);
+            n.resize((n.len() + 3) & !3, 0);
+            n
+        };
+        let id = vec![0xde, 0xad, 0xbe, 0xef, 0x01];
+        let cases = vec![
+            (note(b"GNU\0", NT_GNU_BUILD_ID, &id), Some(id.clone())),
+            // 他のノートの後ろにあっても見つける
+            (
+                [note(b"GNU\0", 1, &[0; 16]), note(b"GNU\0", 3, &id)].concat(),
+                Some(id.clone()),
+            ),
+            // 名前が異なるノート
+            (note(b"Go\0\0", NT_GNU_BUILD_ID, &id), None),
+            // 途中で切れている
+            (note(b"GNU\0", NT_GNU_BUILD_ID, &id)[..18].to_vec(), None),
+            (vec![], None),
+        ];
+        for (data, expected) in cases {
+            assert_eq!(expected, parse_build_id(&data));
+        }
     }
 }
@@ -1,41 +1,140 @@
-/// uLEB128
+//! uLEB128/sLEB128
+
+/// u64/i64を表現できる最大バイト数
+const MAX_LEB128_LEN: u64 = 10;
 
 /// エラー情報
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum LEB128Error {
-    DecodeError,
+    UnexpectedEof, // 終端(MSB=0)の前にデータが終了
+    Overflow,      // 64bitに収まらない
+}
+
+impl From<LEB128Error> for std::io::Error {
+    fn from(e: LEB128Error) -> Self {
+        match e {
+            LEB128Error::UnexpectedEof => {
+                std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "LEB128: unexpected eof")
+            }
+            LEB128Error::Overflow => {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "LEB128: overflow")
+            }
+        }
+    }
+}
+
+/// 1byte読み込み
+fn read_byte<R: std::io::Read>(reader: &mut R) -> Result<u8, LEB128Error> {
+    let mut b = [0; 1];
+    match reader.read_exact(&mut b) {
+        Ok(_) => Ok(b[0]),
+        Err(_) => Err(LEB128Error::UnexpectedEof),
+    }
 }
 
 pub trait ULEB128 {
     /// LEBデータRead
     ///
     /// 読み取ったサイズとvalueをタプルで返す
+    /// 64bitに収まらないデータは、10byteまでで打ち切りエラーとする
     fn decode<R: std::io::Read>(reader: &mut R) -> Result<(u64, u64), LEB128Error> {
         // 終了判定であるMSB=0まで、続ける
         let mut val: u64 = 0;
         let mut size = 0;
         let mut s = 0;
         loop {
-            let mut b = [0; 1];
-            if reader.read_exact(&mut b).is_err() {
-                return Err(LEB128Error::DecodeError);
-            }
-
             // LEBデータを取得・復元
-            let b_val = u8::from_le_bytes(b) as u64;
-            val |= (b_val & 0x7F) << s;
+            let b_val = read_byte(reader)? as u64;
             size += 1;
 
+            // 10byte目は、最下位bitのみ有効
+            if size == MAX_LEB128_LEN && 0 != b_val & 0x7E {
+                return Err(LEB128Error::Overflow);
+            }
+            val |= (b_val & 0x7F) << s;
+
             // MSG=0であれば、終了
             if 0 == b_val & 0x80 {
                 break;
             }
+            if size == MAX_LEB128_LEN {
+                return Err(LEB128Error::Overflow);
+            }
 
             // 次回のシフト量を更新
             s += 7;
         }
         Ok((size, val))
     }
+
+    /// LEBデータへエンコード
+    #[allow(dead_code)]
+    fn encode(val: u64) -> Vec<u8> {
+        let mut val = val;
+        let mut buf = vec![];
+        loop {
+            let b = (val & 0x7F) as u8;
+            val >>= 7;
+            if 0 == val {
+                buf.push(b);
+                break;
+            }
+            buf.push(b | 0x80);
+        }
+        buf
+    }
+}
+
+pub trait SLEB128 {
+    /// 符号付きLEBデータRead
+    ///
+    /// 読み取ったサイズとvalueをタプルで返す
+    /// 64bitに収まらないデータは、10byteまでで打ち切りエラーとする
+    fn decode_signed<R: std::io::Read>(reader: &mut R) -> Result<(u64, i64), LEB128Error> {
+        let mut val: i64 = 0;
+        let mut size = 0;
+        let mut s = 0;
+        loop {
+            // LEBデータを取得・復元
+            let b_val = read_byte(reader)? as i64;
+            size += 1;
+
+            // 10byte目は、符号拡張(0x00か0x7F)のみ有効
+            if size == MAX_LEB128_LEN && b_val != 0x00 && b_val != 0x7F {
+                return Err(LEB128Error::Overflow);
+            }
+            val |= (b_val & 0x7F) << s;
+            s += 7;
+
+            // MSG=0であれば、最終バイトの符号ビットで拡張して終了
+            if 0 == b_val & 0x80 {
+                if s < 64 && 0 != b_val & 0x40 {
+                    val |= -1 << s;
+                }
+                break;
+            }
+        }
+        Ok((size, val))
+    }
+
+    /// 符号付きLEBデータへエンコード
+    #[allow(dead_code)]
+    fn encode_signed(val: i64) -> Vec<u8> {
+        let mut val = val;
+        let mut buf = vec![];
+        loop {
+            let b = (val & 0x7F) as u8;
+            val >>= 7;
+
+            // 残りが符号拡張のみで、符号ビットが一致すれば終了
+            if (0 == val && 0 == b & 0x40) || (-1 == val && 0 != b & 0x40) {
+                buf.push(b);
+                break;
+            }
+            buf.push(b | 0x80);
+        }
+        buf
+    }
 }
 
 #[cfg(test)]
@@ -44,6 +143,15 @@ mod test {
 
     struct Test {}
     impl ULEB128 for Test {}
+    impl SLEB128 for Test {}
+
+    /// テスト用の疑似乱数(xorshift)
+    fn xorshift(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
 
     #[test]
     fn test() {
@@ -77,5 +185,114 @@ mod test {
             assert_eq!(3, ret.0);
             assert_eq!(543210, ret.1);
         }
+        {
+            // 64bitの最大値
+            let mut b: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01];
+            let ret = Test::decode(&mut b).unwrap();
+            assert_eq!(10, ret.0);
+            assert_eq!(u64::MAX, ret.1);
+        }
+        {
+            // 64bitに収まらない
+            let mut b: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x02];
+            assert_eq!(Err(LEB128Error::Overflow), Test::decode(&mut b));
+            let mut b: &[u8] = &[0x80; 16];
+            assert_eq!(Err(LEB128Error::Overflow), Test::decode(&mut b));
+        }
+        {
+            // 終端がない
+            let mut b: &[u8] = &[0x80, 0x80];
+            assert_eq!(Err(LEB128Error::UnexpectedEof), Test::decode(&mut b));
+            let mut b: &[u8] = &[];
+            assert_eq!(Err(LEB128Error::UnexpectedEof), Test::decode(&mut b));
+        }
+    }
+
+    #[test]
+    fn test_signed() {
+        // DWARF仕様書の例
+        let cases: Vec<(&[u8], i64)> = vec![
+            (&[0x02], 2),
+            (&[0x7E], -2),
+            (&[0xFF, 0x00], 127),
+            (&[0x81, 0x7F], -127),
+            (&[0x80, 0x01], 128),
+            (&[0x80, 0x7F], -128),
+            (&[0x81, 0x01], 129),
+            (&[0xFF, 0x7E], -129),
+        ];
+        for (data, val) in cases {
+            let mut b = data;
+            let ret = Test::decode_signed(&mut b).unwrap();
+            assert_eq!(data.len() as u64, ret.0);
+            assert_eq!(val, ret.1);
+            assert_eq!(data, &Test::encode_signed(val)[..]);
+        }
+        {
+            let mut b: &[u8] = &[0x7F];
+            assert_eq!(-1, Test::decode_signed(&mut b).unwrap().1);
+        }
+        {
+            // 64bitの最小値
+            let mut b: &[u8] = &[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x7F];
+            let ret = Test::decode_signed(&mut b).unwrap();
+            assert_eq!(10, ret.0);
+            assert_eq!(i64::MIN, ret.1);
+        }
+        {
+            // 64bitに収まらない
+            let mut b: &[u8] = &[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x3F];
+            assert_eq!(Err(LEB128Error::Overflow), Test::decode_signed(&mut b));
+            let mut b: &[u8] = &[0xFF; 16];
+            assert_eq!(Err(LEB128Error::Overflow), Test::decode_signed(&mut b));
+        }
+        {
+            // 終端がない
+            let mut b: &[u8] = &[0x80, 0x80];
+            assert_eq!(Err(LEB128Error::UnexpectedEof), Test::decode_signed(&mut b));
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut state = 0x2545_F491_4F6C_DD1D;
+        let mut values = vec![0, 1, 63, 64, 127, 128, u64::MAX, i64::MAX as u64];
+        values.extend((0..1000).map(|_| xorshift(&mut state) >> (xorshift(&mut state) % 64)));
+        for val in values {
+            let data = Test::encode(val);
+            let ret = Test::decode(&mut &data[..]).unwrap();
+            assert_eq!((data.len() as u64, val), ret);
+
+            let val = val as i64;
+            let data = Test::encode_signed(val);
+            let ret = Test::decode_signed(&mut &data[..]).unwrap();
+            assert_eq!((data.len() as u64, val), ret);
+        }
+    }
+
+    #[test]
+    fn test_random_input() {
+        // 不正なデータでもパニックせず、10byteを超えて読み込まない
+        let mut state = 0x9E37_79B9_7F4A_7C15;
+        for _ in 0..10000 {
+            let len = (xorshift(&mut state) % 16) as usize;
+            // 長い継続を生成するため、3/4の確率でMSBを立てる
+            let data = (0..len)
+                .map(|_| {
+                    let b = xorshift(&mut state) as u8;
+                    if xorshift(&mut state).is_multiple_of(4) {
+                        b
+                    } else {
+                        b | 0x80
+                    }
+                })
+                .collect::<Vec<u8>>();
+            if let Ok((size, _)) = Test::decode(&mut &data[..]) {
+                assert!(size <= MAX_LEB128_LEN && size as usize <= data.len());
+            }
+            if let Ok((size, _)) = Test::decode_signed(&mut &data[..]) {
+                assert!(size <= MAX_LEB128_LEN && size as usize <= data.len());
+            }
+        }
     }
 }
@@ -0,0 +1,186 @@
+//! DWARF位置式(location expression)
+use crate::elf::leb128::{SLEB128, ULEB128};
+
+// DW_OP
+const DW_OP_ADDR: u8 = 0x03;
+const DW_OP_CONST1U: u8 = 0x08;
+const DW_OP_CONST1S: u8 = 0x09;
+const DW_OP_CONST2U: u8 = 0x0A;
+const DW_OP_CONST2S: u8 = 0x0B;
+const DW_OP_CONST4U: u8 = 0x0C;
+const DW_OP_CONST4S: u8 = 0x0D;
+const DW_OP_CONST8U: u8 = 0x0E;
+const DW_OP_CONST8S: u8 = 0x0F;
+const DW_OP_CONSTU: u8 = 0x10;
+const DW_OP_CONSTS: u8 = 0x11;
+const DW_OP_MINUS: u8 = 0x1C;
+const DW_OP_PLUS: u8 = 0x22;
+const DW_OP_PLUS_UCONST: u8 = 0x23;
+const DW_OP_LIT0: u8 = 0x30;
+const DW_OP_LIT31: u8 = 0x4F;
+const DW_OP_REG0: u8 = 0x50;
+const DW_OP_REG31: u8 = 0x6F;
+const DW_OP_BREG0: u8 = 0x70;
+const DW_OP_BREG31: u8 = 0x8F;
+const DW_OP_REGX: u8 = 0x90;
+const DW_OP_FBREG: u8 = 0x91;
+const DW_OP_BREGX: u8 = 0x92;
+const DW_OP_CALL_FRAME_CFA: u8 = 0x9C;
+const DW_OP_STACK_VALUE: u8 = 0x9F;
+
+/// 位置式の評価結果
+#[derive(Debug, PartialEq)]
+pub enum Location {
+    Addr(u64),  // メモリ上に配置
+    Reg(u64),   // レジスタに配置(DWARFレジスタ番号)
+    Value(u64), // 値そのもの(DW_OP_stack_value)
+}
+
+/// 位置式の評価に必要な情報
+///
+/// regsはDWARFレジスタ番号順(x86-64: rax, rdx, rcx, rbx, rsi, rdi, rbp, rsp, r8-r15, rip)
+#[derive(Debug, Default)]
+pub struct EvalContext {
+    pub regs: [u64; 17],
+    pub frame_base: Option<u64>, // DW_AT_frame_baseの評価結果
+    pub cfa: Option<u64>,        // Canonical Frame Address
+}
+
+struct Expr {}
+impl ULEB128 for Expr {}
+impl SLEB128 for Expr {}
+
+/// 位置式を評価
+///
+/// メモリ参照(DW_OP_deref)等、未対応の命令を含む場合はNoneを返す
+pub fn evaluate(expr: &[u8], ctx: &EvalContext) -> Option<Location> {
+    let mut reader = expr;
+    let mut stack: Vec<u64> = vec![];
+    let mut reg = None;
+    let mut is_value = false;
+    while let Some((&op, rest)) = reader.split_first() {
+        reader = rest;
+        match op {
+            DW_OP_ADDR => stack.push(read_u64(&mut reader, 8)?),
+            DW_OP_CONST1U => stack.push(read_u64(&mut reader, 1)?),
+            DW_OP_CONST2U => stack.push(read_u64(&mut reader, 2)?),
+            DW_OP_CONST4U => stack.push(read_u64(&mut reader, 4)?),
+            DW_OP_CONST8U => stack.push(read_u64(&mut reader, 8)?),
+            DW_OP_CONST1S => stack.push(read_u64(&mut reader, 1)? as i8 as u64),
+            DW_OP_CONST2S => stack.push(read_u64(&mut reader, 2)? as i16 as u64),
+            DW_OP_CONST4S => stack.push(read_u64(&mut reader, 4)? as i32 as u64),
+            DW_OP_CONST8S => stack.push(read_u64(&mut reader, 8)?),
+            DW_OP_CONSTU => stack.push(Expr::decode(&mut reader).ok()?.1),
+            DW_OP_CONSTS => stack.push(Expr::decode_signed(&mut reader).ok()?.1 as u64),
+            DW_OP_LIT0..=DW_OP_LIT31 => stack.push((op - DW_OP_LIT0) as u64),
+            DW_OP_PLUS => {
+                let (a, b) = (stack.pop()?, stack.pop()?);
+                stack.push(b.wrapping_add(a));
+            }
+            DW_OP_MINUS => {
+                let (a, b) = (stack.pop()?, stack.pop()?);
+                stack.push(b.wrapping_sub(a));
+            }
+            DW_OP_PLUS_UCONST => {
+                let v = Expr::decode(&mut reader).ok()?.1;
+                let top = stack.pop()?;
+                stack.push(top.wrapping_add(v));
+            }
+            DW_OP_REG0..=DW_OP_REG31 => reg = Some((op - DW_OP_REG0) as u64),
+            DW_OP_REGX => reg = Some(Expr::decode(&mut reader).ok()?.1),
+            DW_OP_BREG0..=DW_OP_BREG31 => {
+                let offset = Expr::decode_signed(&mut reader).ok()?.1;
+                let r = *ctx.regs.get((op - DW_OP_BREG0) as usize)?;
+                stack.push(r.wrapping_add(offset as u64));
+            }
+            DW_OP_BREGX => {
+                let no = Expr::decode(&mut reader).ok()?.1;
+                let offset = Expr::decode_signed(&mut reader).ok()?.1;
+                let r = *ctx.regs.get(no as usize)?;
+                stack.push(r.wrapping_add(offset as u64));
+            }
+            DW_OP_FBREG => {
+                let offset = Expr::decode_signed(&mut reader).ok()?.1;
+                stack.push(ctx.frame_base?.wrapping_add(offset as u64));
+            }
+            DW_OP_CALL_FRAME_CFA => stack.push(ctx.cfa?),
+            DW_OP_STACK_VALUE => is_value = true,
+            _ => return None,
+        }
+    }
+
+    match reg {
+        Some(r) => Some(Location::Reg(r)),
+        None if is_value => stack.pop().map(Location::Value),
+        None => stack.pop().map(Location::Addr),
+    }
+}
+
+/// リトルエンディアンの数値を読み込み
+fn read_u64(reader: &mut &[u8], size: usize) -> Option<u64> {
+    if reader.len() < size {
+        return None;
+    }
+    let (data, rest) = reader.split_at(size);
+    *reader = rest;
+    let mut buf = [0; 8];
+    buf[..size].copy_from_slice(data);
+    Some(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        let mut ctx = EvalContext::default();
+        ctx.regs[6] = 0x7FFF_0000; // rbp
+        ctx.frame_base = Some(0x7FFF_1000);
+        ctx.cfa = Some(0x7FFF_2000);
+        {
+            // DW_OP_addr
+            let expr = [DW_OP_ADDR, 0x10, 0x40, 0, 0, 0, 0, 0, 0];
+            assert_eq!(Some(Location::Addr(0x4010)), evaluate(&expr, &ctx));
+        }
+        {
+            // DW_OP_fbreg -20
+            let expr = [DW_OP_FBREG, 0x6C];
+            assert_eq!(Some(Location::Addr(0x7FFF_0FEC)), evaluate(&expr, &ctx));
+        }
+        {
+            // DW_OP_breg6(rbp) +16
+            let expr = [DW_OP_BREG0 + 6, 0x10];
+            assert_eq!(Some(Location::Addr(0x7FFF_0010)), evaluate(&expr, &ctx));
+        }
+        {
+            // DW_OP_call_frame_cfa
+            let expr = [DW_OP_CALL_FRAME_CFA];
+            assert_eq!(Some(Location::Addr(0x7FFF_2000)), evaluate(&expr, &ctx));
+        }
+        {
+            // DW_OP_reg3(rbx)
+            let expr = [DW_OP_REG0 + 3];
+            assert_eq!(Some(Location::Reg(3)), evaluate(&expr, &ctx));
+        }
+        {
+            // DW_OP_lit5 DW_OP_lit3 DW_OP_minus DW_OP_stack_value
+            let expr = [
+                DW_OP_LIT0 + 5,
+                DW_OP_LIT0 + 3,
+                DW_OP_MINUS,
+                DW_OP_STACK_VALUE,
+            ];
+            assert_eq!(Some(Location::Value(2)), evaluate(&expr, &ctx));
+        }
+        {
+            // フレームベースが不明
+            let ctx = EvalContext::default();
+            assert_eq!(None, evaluate(&[DW_OP_FBREG, 0x6C], &ctx));
+        }
+        {
+            // 途中で途切れた位置式
+            assert_eq!(None, evaluate(&[DW_OP_ADDR, 0x10], &ctx));
+        }
+    }
+}
@@ -1,3 +1,5 @@
 pub mod dwarf;
 pub mod elf64;
 pub mod leb128;
+pub mod location;
+pub mod type_info;
@@ -0,0 +1,901 @@
+#![allow(dead_code)]
+
+/// DW_ATE(base typeのエンコーディング)
+pub const DW_ATE_BOOLEAN: u64 = 0x2;
+pub const DW_ATE_FLOAT: u64 = 0x4;
+pub const DW_ATE_SIGNED: u64 = 0x5;
+pub const DW_ATE_SIGNED_CHAR: u64 = 0x6;
+pub const DW_ATE_UNSIGNED: u64 = 0x7;
+pub const DW_ATE_UNSIGNED_CHAR: u64 = 0x8;
+
+/// 整数値の文字列を、サイズ分のリトルエンディアンのバイト列へ変換
+///
+/// 10進数(負数を含む)、または0xで始まる16進数を受け付ける
+/// 符号付き・符号なしのいずれの範囲にも収まらなければエラー
+pub fn encode_int(val: &str, size: u64) -> Result<Vec<u8>, String> {
+    if size == 0 || size > 8 {
+        return Err(format!("unsupported size: {}", size));
+    }
+    let v = match val.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).map(|v| v as i128),
+        None => val.parse::<i128>(),
+    }
+    .map_err(|_| format!("parse error: {}", val))?;
+    let bits = size * 8;
+    if v < -(1i128 << (bits - 1)) || (1i128 << bits) <= v {
+        return Err(format!("out of range for {} bytes: {}", size, val));
+    }
+    Ok(v.to_le_bytes()[..size as usize].to_vec())
+}
+
+/// 型情報
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeInfo {
+    Base {
+        name: String,
+        size: u64,
+        encoding: u64, // DW_ATE
+    },
+    Pointer {
+        name: String,
+        size: u64,
+    },
+    Struct {
+        name: String,
+        size: u64,
+        members: Vec<MemberInfo>,
+    },
+    Union {
+        name: String,
+        size: u64,
+        members: Vec<MemberInfo>,
+    },
+    Variant {
+        name: String,
+        size: u64,
+        discr: Option<Box<MemberInfo>>, // 判別子(読めない場合はNone)
+        variants: Vec<VariantInfo>,
+    },
+    Array {
+        name: String,
+        size: u64,
+        elem: Box<TypeInfo>, // 要素の型
+        dims: Vec<u64>,      // 各次元の要素数
+        stride: u64,         // 要素間のバイト数
+    },
+    Unknown {
+        name: String,
+        size: u64,
+    },
+}
+
+/// 表示オプション
+#[derive(Debug, Clone)]
+pub struct FormatOption {
+    pub max_elements: usize, // 配列の最大表示要素数
+}
+
+impl Default for FormatOption {
+    fn default() -> Self {
+        FormatOption { max_elements: 10 }
+    }
+}
+
+/// 構造体メンバー情報
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberInfo {
+    pub name: String,
+    pub offset: u64,                 // 構造体先頭からのオフセット(byte)
+    pub bit_field: Option<BitField>, // ビットフィールドの場合のみ
+    pub ty: TypeInfo,
+}
+
+/// variant情報(DW_TAG_variant)
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantInfo {
+    pub discr_value: Option<u64>, // Noneはデフォルトvariant
+    pub member: MemberInfo,       // variantの実体(メンバー名がvariant名)
+}
+
+/// ビットフィールド情報
+///
+/// DW_AT_data_bit_offset(DWARF4以降)とDW_AT_bit_offset(DWARF2/3)では
+/// ビットの数え方が異なるため、構造体先頭からのビット位置(LSB基準)に正規化して保持する
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitField {
+    pub bit_size: u64,
+    pub bit_offset: u64,
+}
+
+impl BitField {
+    /// DW_AT_data_bit_offsetから生成
+    pub fn from_data_bit_offset(bit_size: u64, data_bit_offset: u64) -> Self {
+        BitField {
+            bit_size,
+            bit_offset: data_bit_offset,
+        }
+    }
+
+    /// DW_AT_data_member_location/DW_AT_byte_size/DW_AT_bit_offsetから生成
+    ///
+    /// DW_AT_bit_offsetはストレージユニットのMSBからフィールドのMSBまでのビット数なので、
+    /// リトルエンディアンのLSB基準へ変換する
+    pub fn from_bit_offset(bit_size: u64, location: u64, byte_size: u64, bit_offset: u64) -> Self {
+        BitField {
+            bit_size,
+            bit_offset: ((location + byte_size) * 8)
+                .saturating_sub(bit_offset)
+                .saturating_sub(bit_size),
+        }
+    }
+
+    /// ビットフィールドの値を取り出す
+    ///
+    /// 構造体全体のデータを受け取り、該当ビットをシフト・マスクして返す
+    pub fn extract(&self, buf: &[u8]) -> Option<u64> {
+        if self.bit_size == 0 || self.bit_size > 64 {
+            return None;
+        }
+
+        // フィールドを含むバイト列をリトルエンディアンで読み出す
+        let start = (self.bit_offset / 8) as usize;
+        let shift = self.bit_offset % 8;
+        let len = (shift + self.bit_size).div_ceil(8) as usize;
+        let bytes = buf.get(start..start + len)?;
+        let raw = bytes
+            .iter()
+            .rev()
+            .fold(0u128, |acc, b| (acc << 8) | *b as u128);
+
+        let mask = (1u128 << self.bit_size) - 1;
+        Some(((raw >> shift) & mask) as u64)
+    }
+}
+
+/// 型情報実装
+impl TypeInfo {
+    /// 型名取得
+    pub fn get_name(&self) -> &str {
+        match self {
+            TypeInfo::Base { name, .. } => name,
+            TypeInfo::Pointer { name, .. } => name,
+            TypeInfo::Struct { name, .. } => name,
+            TypeInfo::Union { name, .. } => name,
+            TypeInfo::Variant { name, .. } => name,
+            TypeInfo::Array { name, .. } => name,
+            TypeInfo::Unknown { name, .. } => name,
+        }
+    }
+
+    /// サイズ取得
+    pub fn get_size(&self) -> u64 {
+        match self {
+            TypeInfo::Base { size, .. } => *size,
+            TypeInfo::Pointer { size, .. } => *size,
+            TypeInfo::Struct { size, .. } => *size,
+            TypeInfo::Union { size, .. } => *size,
+            TypeInfo::Variant { size, .. } => *size,
+            TypeInfo::Array { size, .. } => *size,
+            TypeInfo::Unknown { size, .. } => *size,
+        }
+    }
+
+    /// 符号付き整数か
+    pub fn is_signed(&self) -> bool {
+        matches!(
+            self,
+            TypeInfo::Base {
+                encoding: DW_ATE_SIGNED | DW_ATE_SIGNED_CHAR,
+                ..
+            }
+        )
+    }
+
+    /// データを型に応じて整形
+    ///
+    /// bufには変数先頭からのデータを渡す
+    pub fn format(&self, buf: &[u8]) -> String {
+        self.format_with(buf, &FormatOption::default())
+    }
+
+    /// データを型と表示オプションに応じて整形
+    pub fn format_with(&self, buf: &[u8], opt: &FormatOption) -> String {
+        match self {
+            // unionはどのメンバーが有効か分からないため、すべての解釈を表示
+            TypeInfo::Struct { members, .. } | TypeInfo::Union { members, .. } => {
+                Self::format_members(members, buf, opt)
+            }
+            TypeInfo::Variant {
+                discr, variants, ..
+            } => {
+                // 判別子に一致するvariantのみ表示し、判別できなければすべて表示
+                let selected = discr.as_ref().and_then(|d| d.to_u64(buf)).and_then(|v| {
+                    variants
+                        .iter()
+                        .find(|x| x.discr_value == Some(v))
+                        .or_else(|| variants.iter().find(|x| x.discr_value.is_none()))
+                });
+                match selected {
+                    Some(v) => v.format(buf, opt),
+                    None => Self::format_members(
+                        &variants
+                            .iter()
+                            .map(|v| v.member.clone())
+                            .collect::<Vec<MemberInfo>>(),
+                        buf,
+                        opt,
+                    ),
+                }
+            }
+            TypeInfo::Array {
+                elem, dims, stride, ..
+            } => Self::format_array(elem, dims, *stride, buf, opt),
+            TypeInfo::Base { size, encoding, .. } => match *encoding {
+                DW_ATE_FLOAT => Self::format_float(buf, *size),
+                _ => match Self::to_u64(buf, *size) {
+                    Some(v) if *encoding == DW_ATE_BOOLEAN && v <= 1 => (v == 1).to_string(),
+                    Some(v) if self.is_char() => self.format_char(v as u8),
+                    Some(v) if self.is_signed() => Self::sign_extend(v, *size * 8).to_string(),
+                    Some(v) => v.to_string(),
+                    None => "<unavailable>".to_string(),
+                },
+            },
+            TypeInfo::Pointer { size, .. } | TypeInfo::Unknown { size, .. } => {
+                match Self::to_u64(buf, *size) {
+                    Some(v) => format!("0x{:x}", v),
+                    None => "<unavailable>".to_string(),
+                }
+            }
+        }
+    }
+
+    /// 値の文字列を、型に応じたバイト列へ変換(変数への書き込み用)
+    ///
+    /// 浮動小数点型は小数、それ以外の基本型・ポインタは整数として受け付ける
+    pub fn encode(&self, val: &str) -> Result<Vec<u8>, String> {
+        match self {
+            TypeInfo::Base {
+                size,
+                encoding: DW_ATE_FLOAT,
+                ..
+            } => {
+                let v = val
+                    .parse::<f64>()
+                    .map_err(|_| format!("parse error: {}", val))?;
+                match size {
+                    4 => Ok((v as f32).to_le_bytes().to_vec()),
+                    8 => Ok(v.to_le_bytes().to_vec()),
+                    _ => Err(format!("unsupported size: {}", size)),
+                }
+            }
+            TypeInfo::Base { size, .. }
+            | TypeInfo::Pointer { size, .. }
+            | TypeInfo::Unknown { size, .. } => encode_int(val, *size),
+            _ => Err(format!("cannot assign a value to {}", self.get_name())),
+        }
+    }
+
+    /// メンバーを辿って整形(pathが["a", "b"]であれば、var.a.bを表示)
+    ///
+    /// 構造体・union以外の型を辿る場合や、メンバーが見つからない場合はエラー
+    pub fn format_member(
+        &self,
+        path: &[&str],
+        buf: &[u8],
+        opt: &FormatOption,
+    ) -> Result<String, String> {
+        let (name, rest) = match path.split_first() {
+            Some(p) => p,
+            None => return Ok(self.format_with(buf, opt)),
+        };
+        let members = match self {
+            TypeInfo::Struct { members, .. } | TypeInfo::Union { members, .. } => members,
+            _ => return Err(format!("not a struct or union: {}", self.get_name())),
+        };
+        let member = members
+            .iter()
+            .find(|m| m.name == *name)
+            .ok_or_else(|| format!("no member named {}", name))?;
+        if rest.is_empty() {
+            return Ok(member.format(buf, opt));
+        }
+        match buf.get(member.offset as usize..) {
+            Some(b) => member.ty.format_member(rest, b, opt),
+            None => Ok("<unavailable>".to_string()),
+        }
+    }
+
+    /// 文字型か
+    pub fn is_char(&self) -> bool {
+        matches!(
+            self,
+            TypeInfo::Base {
+                size: 1,
+                encoding: DW_ATE_SIGNED_CHAR | DW_ATE_UNSIGNED_CHAR,
+                ..
+            }
+        )
+    }
+
+    /// メンバー一覧を整形
+    fn format_members(members: &[MemberInfo], buf: &[u8], opt: &FormatOption) -> String {
+        let fields = members
+            .iter()
+            .map(|m| format!("{} = {}", m.name, m.format(buf, opt)))
+            .collect::<Vec<String>>();
+        format!("{{ {} }}", fields.join(", "))
+    }
+
+    /// 配列を整形
+    ///
+    /// 多次元配列は先頭の次元から再帰的に整形し、表示要素数を超えた分は省略する
+    fn format_array(
+        elem: &TypeInfo,
+        dims: &[u64],
+        stride: u64,
+        buf: &[u8],
+        opt: &FormatOption,
+    ) -> String {
+        let count = match dims.first() {
+            Some(c) => *c,
+            None => return elem.format_with(buf, opt),
+        };
+        let inner = &dims[1..];
+        let inner_size = inner.iter().product::<u64>() * stride;
+
+        // 1次元の文字配列は文字列としても表示
+        if inner.is_empty() && elem.is_char() {
+            let len = std::cmp::min(count as usize, buf.len());
+            let str_len = buf[..len].iter().take_while(|c| **c != 0).count();
+            if str_len > opt.max_elements {
+                let shown = &buf[..opt.max_elements];
+                return format!("\"{}\"...", Self::to_escaped_str(shown));
+            }
+            return format!("\"{}\"", Self::to_escaped_str(&buf[..len]));
+        }
+
+        let shown = std::cmp::min(count, opt.max_elements as u64);
+        let mut elems = (0..shown)
+            .map(|i| match buf.get((i * inner_size) as usize..) {
+                Some(b) => Self::format_array(elem, inner, stride, b, opt),
+                None => "<unavailable>".to_string(),
+            })
+            .collect::<Vec<String>>();
+        if count > shown {
+            elems.push(format!("... ({} total)", count));
+        }
+        format!("{{ {} }}", elems.join(", "))
+    }
+
+    /// 浮動小数点数を整形(long doubleは80ビットの拡張倍精度)
+    fn format_float(buf: &[u8], size: u64) -> String {
+        let value = match (size, buf.get(0..size as usize)) {
+            (4, Some(b)) => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            (8, Some(_)) => f64::from_bits(Self::to_u64(buf, 8).unwrap_or(0)),
+            (16, Some(b)) => crate::fpregs::ext_to_f64(
+                Self::to_u64(b, 8).unwrap_or(0),
+                u16::from_le_bytes([b[8], b[9]]),
+            ),
+            _ => return "<unavailable>".to_string(),
+        };
+        crate::fpregs::format_f64(value)
+    }
+
+    /// 文字を数値と文字で整形(例: 65 'A')
+    fn format_char(&self, c: u8) -> String {
+        let value = if self.is_signed() {
+            Self::sign_extend(c as u64, 8)
+        } else {
+            c as i64
+        };
+        let escaped = match c {
+            0 => "\\0".to_string(),
+            b'\'' => "\\'".to_string(),
+            b'"' => "\"".to_string(),
+            _ => Self::to_escaped_char(c),
+        };
+        format!("{} '{}'", value, escaped)
+    }
+
+    /// 表示できない文字をエスケープ
+    fn to_escaped_char(c: u8) -> String {
+        match c {
+            b'"' => "\\\"".to_string(),
+            b'\\' => "\\\\".to_string(),
+            b'\n' => "\\n".to_string(),
+            b'\t' => "\\t".to_string(),
+            0x20..=0x7E => (c as char).to_string(),
+            _ => format!("\\x{:02x}", c),
+        }
+    }
+
+    /// 文字列へ変換(nullで終端し、表示できない文字はエスケープ)
+    fn to_escaped_str(buf: &[u8]) -> String {
+        buf.iter()
+            .take_while(|c| **c != 0)
+            .map(|c| Self::to_escaped_char(*c))
+            .collect()
+    }
+
+    /// リトルエンディアンのデータを数値へ変換
+    fn to_u64(buf: &[u8], size: u64) -> Option<u64> {
+        if size == 0 || size > 8 {
+            return None;
+        }
+        let bytes = buf.get(0..size as usize)?;
+        Some(bytes.iter().rev().fold(0, |acc, b| (acc << 8) | *b as u64))
+    }
+
+    /// 符号拡張
+    fn sign_extend(v: u64, bits: u64) -> i64 {
+        if bits == 0 || bits >= 64 {
+            return v as i64;
+        }
+        let s = 64 - bits;
+        ((v << s) as i64) >> s
+    }
+}
+
+/// 構造体メンバー実装
+impl MemberInfo {
+    /// メンバーの値を整形
+    ///
+    /// bufには構造体全体のデータを渡す
+    pub fn format(&self, buf: &[u8], opt: &FormatOption) -> String {
+        match &self.bit_field {
+            Some(bf) => match bf.extract(buf) {
+                Some(v) if self.ty.is_signed() => TypeInfo::sign_extend(v, bf.bit_size).to_string(),
+                Some(v) => v.to_string(),
+                None => "<unavailable>".to_string(),
+            },
+            None => match buf.get(self.offset as usize..) {
+                Some(b) => self.ty.format_with(b, opt),
+                None => "<unavailable>".to_string(),
+            },
+        }
+    }
+
+    /// メンバーの値を数値で取得
+    pub fn to_u64(&self, buf: &[u8]) -> Option<u64> {
+        match &self.bit_field {
+            Some(bf) => bf.extract(buf),
+            None => TypeInfo::to_u64(buf.get(self.offset as usize..)?, self.ty.get_size()),
+        }
+    }
+}
+
+/// variant実装
+impl VariantInfo {
+    /// variantを整形
+    ///
+    /// タプル形式(メンバー名が__0, __1...)は`Some(5)`のように表示する
+    pub fn format(&self, buf: &[u8], opt: &FormatOption) -> String {
+        let data = match buf.get(self.member.offset as usize..) {
+            Some(b) => b,
+            None => return "<unavailable>".to_string(),
+        };
+        match &self.member.ty {
+            TypeInfo::Struct { members, .. } if members.is_empty() => self.member.name.clone(),
+            TypeInfo::Struct { members, .. }
+                if members.iter().all(|m| m.name.starts_with("__")) =>
+            {
+                let fields = members
+                    .iter()
+                    .map(|m| m.format(data, opt))
+                    .collect::<Vec<String>>();
+                format!("{}({})", self.member.name, fields.join(", "))
+            }
+            ty => format!("{} {}", self.member.name, ty.format_with(data, opt)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn int_type(signed: bool) -> TypeInfo {
+        TypeInfo::Base {
+            name: if signed { "int" } else { "unsigned int" }.to_string(),
+            size: 4,
+            encoding: if signed {
+                DW_ATE_SIGNED
+            } else {
+                DW_ATE_UNSIGNED
+            },
+        }
+    }
+
+    fn member(name: &str, bf: BitField, signed: bool) -> MemberInfo {
+        MemberInfo {
+            name: name.to_string(),
+            offset: bf.bit_offset / 8,
+            bit_field: Some(bf),
+            ty: int_type(signed),
+        }
+    }
+
+    // struct { unsigned a:3; int b:5; unsigned c:12; int d:4; }
+    // a=5, b=-3, c=0xABC, d=-8
+    const BITFIELD_DATA: [u8; 4] = [0xED, 0xBC, 0x8A, 0x00];
+
+    #[test]
+    fn test_data_bit_offset() {
+        // DWARF4以降(gcc/clangのDW_AT_data_bit_offset)
+        let s = TypeInfo::Struct {
+            name: "flags".to_string(),
+            size: 4,
+            members: vec![
+                member("a", BitField::from_data_bit_offset(3, 0), false),
+                member("b", BitField::from_data_bit_offset(5, 3), true),
+                member("c", BitField::from_data_bit_offset(12, 8), false),
+                member("d", BitField::from_data_bit_offset(4, 20), true),
+            ],
+        };
+        assert_eq!(
+            "{ a = 5, b = -3, c = 2748, d = -8 }",
+            s.format(&BITFIELD_DATA)
+        );
+    }
+
+    #[test]
+    fn test_bit_offset() {
+        // DWARF2/3(DW_AT_byte_size + DW_AT_bit_offset、MSB基準)
+        {
+            let bf = BitField::from_bit_offset(3, 0, 4, 29);
+            assert_eq!(0, bf.bit_offset);
+            assert_eq!(Some(5), bf.extract(&BITFIELD_DATA));
+        }
+        {
+            let bf = BitField::from_bit_offset(5, 0, 4, 24);
+            assert_eq!(3, bf.bit_offset);
+            assert_eq!(Some(0x1D), bf.extract(&BITFIELD_DATA));
+        }
+        {
+            // ストレージユニットが構造体先頭からずれている場合
+            let bf = BitField::from_bit_offset(12, 1, 2, 4);
+            assert_eq!(8, bf.bit_offset);
+            assert_eq!(Some(0xABC), bf.extract(&BITFIELD_DATA));
+        }
+        {
+            let m = member("d", BitField::from_bit_offset(4, 0, 4, 8), true);
+            assert_eq!("-8", m.format(&BITFIELD_DATA, &FormatOption::default()));
+        }
+    }
+
+    #[test]
+    fn test_bit_field_across_bytes() {
+        {
+            // 64bit幅のフィールドがバイト境界をまたぐ場合
+            let buf = [0xF0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F];
+            let bf = BitField::from_data_bit_offset(64, 4);
+            assert_eq!(Some(0xFFFF_FFFF_FFFF_FFFF), bf.extract(&buf));
+        }
+        {
+            // データ不足
+            let bf = BitField::from_data_bit_offset(16, 24);
+            assert_eq!(None, bf.extract(&BITFIELD_DATA));
+        }
+    }
+
+    fn option_u32(discr: bool) -> TypeInfo {
+        // Option<u32>相当
+        let u32_type = TypeInfo::Base {
+            name: "u32".to_string(),
+            size: 4,
+            encoding: DW_ATE_UNSIGNED,
+        };
+        let variant = |name: &str, value: u64, members: Vec<MemberInfo>| VariantInfo {
+            discr_value: Some(value),
+            member: MemberInfo {
+                name: name.to_string(),
+                offset: 0,
+                bit_field: None,
+                ty: TypeInfo::Struct {
+                    name: name.to_string(),
+                    size: 8,
+                    members,
+                },
+            },
+        };
+        TypeInfo::Variant {
+            name: "Option<u32>".to_string(),
+            size: 8,
+            discr: if discr {
+                Some(Box::new(MemberInfo {
+                    name: "".to_string(),
+                    offset: 0,
+                    bit_field: None,
+                    ty: u32_type.clone(),
+                }))
+            } else {
+                None
+            },
+            variants: vec![
+                variant("None", 0, vec![]),
+                variant(
+                    "Some",
+                    1,
+                    vec![MemberInfo {
+                        name: "__0".to_string(),
+                        offset: 4,
+                        bit_field: None,
+                        ty: u32_type,
+                    }],
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_encode_int() {
+        let cases = vec![
+            ("10", 4, vec![10, 0, 0, 0]),
+            ("-1", 2, vec![0xFF, 0xFF]),
+            ("0x1234", 2, vec![0x34, 0x12]),
+            ("255", 1, vec![0xFF]),
+            ("-128", 1, vec![0x80]),
+            ("0xffffffffffffffff", 8, vec![0xFF; 8]),
+            ("-9223372036854775808", 8, [&[0; 7][..], &[0x80]].concat()),
+            ("1000", 4, vec![0xE8, 0x3, 0, 0]), // 0xのない値は10進数
+        ];
+        for (val, size, expected) in cases {
+            assert_eq!(Ok(expected), encode_int(val, size), "{}", val);
+        }
+
+        let errors = vec![
+            // サイズの範囲外
+            ("256", 1, "out of range for 1 bytes: 256"),
+            ("-129", 1, "out of range for 1 bytes: -129"),
+            ("0x10000", 2, "out of range for 2 bytes: 0x10000"),
+            ("0x-1", 4, "parse error: 0x-1"),
+            ("abc", 4, "parse error: abc"),
+            ("1", 16, "unsupported size: 16"),
+        ];
+        for (val, size, expected) in errors {
+            assert_eq!(Err(expected.to_string()), encode_int(val, size), "{}", val);
+        }
+    }
+
+    #[test]
+    fn test_encode() {
+        let float = |size| TypeInfo::Base {
+            name: "float".to_string(),
+            size,
+            encoding: DW_ATE_FLOAT,
+        };
+        assert_eq!(Ok(1.5f32.to_le_bytes().to_vec()), float(4).encode("1.5"));
+        assert_eq!(
+            Ok((-2.25f64).to_le_bytes().to_vec()),
+            float(8).encode("-2.25")
+        );
+        assert!(float(8).encode("x").is_err());
+        assert_eq!(
+            Ok(vec![0xFB, 0xFF, 0xFF, 0xFF]),
+            int_type(true).encode("-5")
+        );
+        let ptr = TypeInfo::Pointer {
+            name: "int *".to_string(),
+            size: 8,
+        };
+        assert_eq!(Ok(0x1000u64.to_le_bytes().to_vec()), ptr.encode("0x1000"));
+        let s = TypeInfo::Struct {
+            name: "pt".to_string(),
+            size: 8,
+            members: vec![],
+        };
+        assert_eq!(
+            Err("cannot assign a value to pt".to_string()),
+            s.encode("1")
+        );
+    }
+
+    #[test]
+    fn test_format_member() {
+        let member = |name: &str, offset, ty| MemberInfo {
+            name: name.to_string(),
+            offset,
+            bit_field: None,
+            ty,
+        };
+        // struct outer { int a; struct inner { int x; unsigned int y; } in; }
+        let inner = TypeInfo::Struct {
+            name: "inner".to_string(),
+            size: 8,
+            members: vec![
+                member("x", 0, int_type(true)),
+                member("y", 4, int_type(false)),
+            ],
+        };
+        let outer = TypeInfo::Struct {
+            name: "outer".to_string(),
+            size: 12,
+            members: vec![member("a", 0, int_type(true)), member("in", 4, inner)],
+        };
+        let buf = [3, 0, 0, 0, 0xFE, 0xFF, 0xFF, 0xFF, 7, 0, 0, 0];
+        let opt = FormatOption::default();
+        let cases: Vec<(Vec<&str>, Result<&str, &str>)> = vec![
+            (vec![], Ok("{ a = 3, in = { x = -2, y = 7 } }")),
+            (vec!["a"], Ok("3")),
+            (vec!["in"], Ok("{ x = -2, y = 7 }")),
+            (vec!["in", "y"], Ok("7")),
+            (vec!["b"], Err("no member named b")),
+            (vec!["in", "z"], Err("no member named z")),
+            (vec!["a", "x"], Err("not a struct or union: int")),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(
+                expected.map(|s| s.to_string()).map_err(|s| s.to_string()),
+                outer.format_member(&path, &buf, &opt),
+                "{:?}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_union() {
+        let u = TypeInfo::Union {
+            name: "u".to_string(),
+            size: 4,
+            members: vec![
+                MemberInfo {
+                    name: "i".to_string(),
+                    offset: 0,
+                    bit_field: None,
+                    ty: int_type(true),
+                },
+                MemberInfo {
+                    name: "c".to_string(),
+                    offset: 0,
+                    bit_field: None,
+                    ty: TypeInfo::Base {
+                        name: "unsigned char".to_string(),
+                        size: 1,
+                        encoding: DW_ATE_UNSIGNED_CHAR,
+                    },
+                },
+            ],
+        };
+        assert_eq!(
+            "{ i = -1, c = 255 '\\xff' }",
+            u.format(&[0xFF, 0xFF, 0xFF, 0xFF])
+        );
+    }
+
+    #[test]
+    fn test_variant() {
+        {
+            let t = option_u32(true);
+            assert_eq!("Some(5)", t.format(&[1, 0, 0, 0, 5, 0, 0, 0]));
+            assert_eq!("None", t.format(&[0, 0, 0, 0, 5, 0, 0, 0]));
+        }
+        {
+            // 判別子が読めない場合は、すべてのvariantを表示
+            let t = option_u32(false);
+            assert_eq!(
+                "{ None = {  }, Some = { __0 = 5 } }",
+                t.format(&[1, 0, 0, 0, 5, 0, 0, 0])
+            );
+        }
+        {
+            // 一致する判別値がなければ、デフォルトvariant
+            let mut t = option_u32(true);
+            if let TypeInfo::Variant { variants, .. } = &mut t {
+                variants[1].discr_value = None;
+            }
+            assert_eq!("Some(7)", t.format(&[9, 0, 0, 0, 7, 0, 0, 0]));
+        }
+    }
+
+    fn array(elem: TypeInfo, dims: Vec<u64>) -> TypeInfo {
+        let stride = elem.get_size();
+        TypeInfo::Array {
+            name: "".to_string(),
+            size: dims.iter().product::<u64>() * stride,
+            elem: Box::new(elem),
+            dims,
+            stride,
+        }
+    }
+
+    #[test]
+    fn test_array() {
+        let buf = (0..24u8).flat_map(|i| [i, 0, 0, 0]).collect::<Vec<u8>>();
+        {
+            let t = array(int_type(true), vec![3]);
+            assert_eq!("{ 0, 1, 2 }", t.format(&buf));
+        }
+        {
+            // 2次元配列
+            let t = array(int_type(true), vec![2, 3]);
+            assert_eq!("{ { 0, 1, 2 }, { 3, 4, 5 } }", t.format(&buf));
+        }
+        {
+            // 表示要素数を超える場合は省略
+            let t = array(int_type(true), vec![24]);
+            let opt = FormatOption { max_elements: 4 };
+            assert_eq!("{ 0, 1, 2, 3, ... (24 total) }", t.format_with(&buf, &opt));
+        }
+        {
+            // 文字配列は文字列として表示
+            let c = TypeInfo::Base {
+                name: "char".to_string(),
+                size: 1,
+                encoding: DW_ATE_SIGNED_CHAR,
+            };
+            let t = array(c, vec![8]);
+            assert_eq!("\"ab\\\"c\\n\"", t.format(b"ab\"c\n\0xy"));
+
+            // 表示要素数を超える文字列は省略
+            let opt = FormatOption { max_elements: 2 };
+            assert_eq!("\"ab\"...", t.format_with(b"abcd\0\0\0\0", &opt));
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        {
+            let t = int_type(true);
+            assert_eq!("-1", t.format(&[0xFF, 0xFF, 0xFF, 0xFF]));
+        }
+        {
+            let t = int_type(false);
+            assert_eq!("4294967295", t.format(&[0xFF, 0xFF, 0xFF, 0xFF]));
+        }
+        {
+            let t = TypeInfo::Pointer {
+                name: "int *".to_string(),
+                size: 8,
+            };
+            assert_eq!("0x1234", t.format(&[0x34, 0x12, 0, 0, 0, 0, 0, 0]));
+        }
+        {
+            let t = int_type(true);
+            assert_eq!("<unavailable>", t.format(&[0xFF]));
+        }
+    }
+
+    #[test]
+    fn test_format_base() {
+        let base = |size, encoding| TypeInfo::Base {
+            name: "t".to_string(),
+            size,
+            encoding,
+        };
+        let cases: Vec<(TypeInfo, Vec<u8>, &str)> = vec![
+            (base(4, DW_ATE_FLOAT), 1.5f32.to_le_bytes().to_vec(), "1.5"),
+            (
+                base(8, DW_ATE_FLOAT),
+                (-2.25f64).to_le_bytes().to_vec(),
+                "-2.25",
+            ),
+            // long double(80ビットの拡張倍精度、16バイト)
+            (
+                base(16, DW_ATE_FLOAT),
+                vec![0, 0, 0, 0, 0, 0, 0, 0xa0, 0x00, 0xc0, 0, 0, 0, 0, 0, 0],
+                "-2.5",
+            ),
+            (base(8, DW_ATE_FLOAT), vec![0; 4], "<unavailable>"),
+            (base(1, DW_ATE_BOOLEAN), vec![1], "true"),
+            (base(1, DW_ATE_BOOLEAN), vec![0], "false"),
+            (base(1, DW_ATE_BOOLEAN), vec![2], "2"),
+            (base(1, DW_ATE_SIGNED_CHAR), vec![b'A'], "65 'A'"),
+            (base(1, DW_ATE_SIGNED_CHAR), vec![0xFF], "-1 '\\xff'"),
+            (base(1, DW_ATE_UNSIGNED_CHAR), vec![0xFF], "255 '\\xff'"),
+            (base(1, DW_ATE_UNSIGNED_CHAR), vec![0], "0 '\\0'"),
+            (base(1, DW_ATE_SIGNED_CHAR), vec![b'\''], "39 '\\''"),
+            (base(2, DW_ATE_SIGNED), vec![0xFD, 0xFF], "-3"),
+            // 型のサイズ以降のデータは無視
+            (
+                base(4, DW_ATE_SIGNED),
+                vec![0xFB, 0xFF, 0xFF, 0xFF, 0x07],
+                "-5",
+            ),
+            (base(8, DW_ATE_UNSIGNED), 7u64.to_le_bytes().to_vec(), "7"),
+        ];
+        for (t, buf, expected) in cases {
+            assert_eq!(expected, t.format(&buf), "{:?}", t);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! トレース対象プロセスの環境変数(info environ)
+//!
+//! /proc/<pid>/environ、またはenvironが指す配列から読み込んだ環境変数を整形する
+
+/// 表示する値の最大バイト数(超えた分は省略する)
+pub const VALUE_LIMIT: usize = 4096;
+
+/// NUL区切りの環境変数(/proc/<pid>/environ)を分割
+pub fn parse_environ(data: &[u8]) -> Vec<&[u8]> {
+    data.split(|b| 0 == *b).filter(|v| !v.is_empty()).collect()
+}
+
+/// 環境変数の名前を取得
+pub fn get_name(var: &[u8]) -> &[u8] {
+    var.split(|b| b'=' == *b).next().unwrap_or(var)
+}
+
+/// 環境変数をNAME=valueの形式に整形
+///
+/// 値がlimitバイトを超える場合は、文字の途中で切らないよう省略し、元のバイト数を付ける
+pub fn format_var(var: &[u8], limit: usize) -> String {
+    let name = get_name(var);
+    let value = var.get(name.len() + 1..);
+    let mut s = escape(name);
+    if let Some(value) = value {
+        s.push('=');
+        if value.len() <= limit {
+            s.push_str(&escape(value));
+        } else {
+            let mut cut = limit;
+            while 0 < cut && 0x80 == value[cut] & 0xC0 {
+                cut -= 1;
+            }
+            s.push_str(&escape(&value[..cut]));
+            s.push_str(&format!("... (truncated, {} bytes)", value.len()));
+        }
+    }
+    s
+}
+
+/// UTF-8として表示できない(不正なバイト・制御文字)部分をエスケープ
+fn escape(data: &[u8]) -> String {
+    let mut s = String::new();
+    for chunk in data.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\n' => s.push_str("\\n"),
+                '\t' => s.push_str("\\t"),
+                '\r' => s.push_str("\\r"),
+                '\\' => s.push_str("\\\\"),
+                c if c.is_control() => s.push_str(&format!("\\x{:02x}", c as u32)),
+                c => s.push(c),
+            }
+        }
+        chunk
+            .invalid()
+            .iter()
+            .for_each(|b| s.push_str(&format!("\\x{:02x}", b)));
+    }
+    s
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_environ() {
+        assert_eq!(
+            vec![&b"HOME=/root"[..], b"LANG=C", b"EMPTY="],
+            parse_environ(b"HOME=/root\0LANG=C\0EMPTY=\0")
+        );
+        assert_eq!(Vec::<&[u8]>::new(), parse_environ(b""));
+        assert_eq!(b"HOME", get_name(b"HOME=/root"));
+        assert_eq!(b"NOVALUE", get_name(b"NOVALUE"));
+    }
+
+    #[test]
+    fn test_format_var() {
+        let cases: Vec<(&[u8], usize, &str)> = vec![
+            (b"HOME=/root", 16, "HOME=/root"),
+            (b"EMPTY=", 16, "EMPTY="),
+            (b"NOVALUE", 16, "NOVALUE"),
+            // 値に=を含む
+            (b"OPT=a=b", 16, "OPT=a=b"),
+            ("LANG=日本語".as_bytes(), 16, "LANG=日本語"),
+            // 不正なバイト・制御文字はエスケープする
+            (b"BAD=a\xff\xfeb", 16, "BAD=a\\xff\\xfeb"),
+            (b"CTRL=a\nb\x1b\\", 16, "CTRL=a\\nb\\x1b\\\\"),
+            // 長い値は省略する(文字の途中で切らない)
+            (b"LONG=abcdefgh", 4, "LONG=abcd... (truncated, 8 bytes)"),
+            ("JA=あいう".as_bytes(), 4, "JA=あ... (truncated, 9 bytes)"),
+        ];
+        for (var, limit, expected) in cases {
+            assert_eq!(expected, format_var(var, limit));
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! トレース対象プロセスの起動・execveの追跡(set follow-exec-mode)
+//!
+//! PTRACE_O_TRACEEXECで停止した際に、新しい実行ファイルのパスを求める
+
+use nix::sys::ptrace::traceme;
+use nix::unistd::{execv, fork, ForkResult, Pid};
+use std::ffi::CString;
+use std::fmt;
+use std::io;
+
+/// execve後の動作
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FollowExecMode {
+    Stop,     // 新しい実行ファイルの開始位置で停止する
+    Continue, // 停止せずに再開する
+}
+
+impl FollowExecMode {
+    /// 文字列(stop・continue)から変換
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "stop" => Ok(FollowExecMode::Stop),
+            "continue" => Ok(FollowExecMode::Continue),
+            _ => Err(format!("invalid follow-exec-mode: {} (stop|continue)", s)),
+        }
+    }
+}
+
+impl fmt::Display for FollowExecMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FollowExecMode::Stop => write!(f, "stop"),
+            FollowExecMode::Continue => write!(f, "continue"),
+        }
+    }
+}
+
+/// トレース対象としてプログラムを起動
+///
+/// argsはプログラムへ渡す引数(argv[0]にはpathを渡す)
+/// 子プロセスは自身をトレース対象とした後にexecvするため、execv後にSIGTRAPで停止する
+pub fn spawn_traced(path: &str, args: &[String]) -> nix::Result<Pid> {
+    let argv = std::iter::once(path)
+        .chain(args.iter().map(|a| a.as_str()))
+        .map(|a| CString::new(a).map_err(|_| nix::errno::Errno::EINVAL))
+        .collect::<nix::Result<Vec<CString>>>()?;
+    match unsafe { fork() }? {
+        ForkResult::Parent { child } => Ok(child),
+        ForkResult::Child => {
+            traceme().expect("failed traceme");
+            let _ = execv(&argv[0], &argv);
+            panic!("execv is failed");
+        }
+    }
+}
+
+/// コマンドライン引数を、デバッガへのものと(-- 以降の)プログラムへ渡すものに分ける
+pub fn split_args(args: &[String]) -> (&[String], &[String]) {
+    match args.iter().position(|a| "--" == a) {
+        Some(i) => (&args[..i], &args[i + 1..]),
+        None => (args, &[]),
+    }
+}
+
+/// プログラムへ渡す引数を表示用に整形(空白等を含むものは引用符で囲む)
+pub fn format_args(args: &[String]) -> String {
+    args.iter()
+        .map(
+            |a| match a.is_empty() || a.contains(|c: char| c.is_whitespace() || '"' == c) {
+                true => format!("{:?}", a),
+                false => a.clone(),
+            },
+        )
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// 実行中のプログラムのパスを取得(/proc/<pid>/exe)
+pub fn read_exe_path(pid: Pid) -> io::Result<String> {
+    let path = std::fs::read_link(format!("/proc/{}/exe", pid))?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_follow_exec_mode() {
+        let cases = vec![
+            ("stop", Ok(FollowExecMode::Stop)),
+            ("continue", Ok(FollowExecMode::Continue)),
+            (
+                "new",
+                Err("invalid follow-exec-mode: new (stop|continue)".to_string()),
+            ),
+        ];
+        for (s, expected) in cases {
+            let mode = FollowExecMode::parse(s);
+            assert_eq!(expected, mode);
+            if let Ok(m) = mode {
+                assert_eq!(s, m.to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn test_spawn_traced() {
+        use nix::sys::ptrace::cont;
+        use nix::sys::signal::Signal;
+        use nix::sys::wait::{waitpid, WaitStatus};
+
+        // execv後に停止し、再開すれば最後まで実行する
+        let _lock = crate::FORK_LOCK.lock().unwrap();
+        let child = spawn_traced("/bin/true", &[]).unwrap();
+        assert_eq!(
+            WaitStatus::Stopped(child, Signal::SIGTRAP),
+            waitpid(child, None).expect("failed waitpid")
+        );
+        let exe = std::fs::canonicalize("/bin/true").unwrap();
+        assert_eq!(exe.to_string_lossy(), read_exe_path(child).unwrap());
+        cont(child, None).expect("failed cont");
+        assert_eq!(
+            WaitStatus::Exited(child, 0),
+            waitpid(child, None).expect("failed waitpid")
+        );
+    }
+
+    #[test]
+    fn test_spawn_traced_args() {
+        use nix::sys::ptrace::cont;
+        use nix::sys::wait::{waitpid, WaitStatus};
+
+        // 引数は空白を含んでも1つのまま渡す
+        let args = ["-c", "[ \"$0\" = \"a b\" ] && exit 3", "a b"]
+            .iter()
+            .map(|a| a.to_string())
+            .collect::<Vec<String>>();
+        let _lock = crate::FORK_LOCK.lock().unwrap();
+        let child = spawn_traced("/bin/sh", &args).unwrap();
+        waitpid(child, None).expect("failed waitpid");
+        cont(child, None).expect("failed cont");
+        assert_eq!(
+            WaitStatus::Exited(child, 3),
+            waitpid(child, None).expect("failed waitpid")
+        );
+        // NULを含む引数は渡せない
+        assert!(spawn_traced("/bin/true", &["a\0b".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_split_args() {
+        let to_vec = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        let cases = vec![
+            (vec!["./prog"], vec!["./prog"], vec![]),
+            (
+                vec!["./prog", "--", "a", "--", "b c"],
+                vec!["./prog"],
+                vec!["a", "--", "b c"],
+            ),
+            (vec!["./prog", "--"], vec!["./prog"], vec![]),
+        ];
+        for (args, before, after) in cases {
+            let args = to_vec(&args);
+            let (b, a) = split_args(&args);
+            assert_eq!((to_vec(&before), to_vec(&after)), (b.to_vec(), a.to_vec()));
+        }
+    }
+
+    #[test]
+    fn test_format_args() {
+        let to_vec = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        let cases = vec![
+            (vec![], ""),
+            (vec!["arg1", "arg2"], "arg1 arg2"),
+            (vec!["arg with spaces", ""], "\"arg with spaces\" \"\""),
+            (vec!["say \"hi\""], "\"say \\\"hi\\\"\""),
+        ];
+        for (args, expected) in cases {
+            assert_eq!(expected, format_args(&to_vec(&args)));
+        }
+    }
+
+    #[test]
+    fn test_exec_event() {
+        use crate::elf::elf64::Elf64;
+        use crate::memory_map::MemoryMap;
+        use nix::sys::ptrace::{cont, setoptions, traceme, Options};
+        use nix::sys::signal::{raise, Signal};
+        use nix::sys::wait::{waitpid, WaitStatus};
+        use nix::unistd::{execv, fork, ForkResult};
+        use std::ffi::CString;
+
+        let true_path = CString::new("/bin/true").unwrap();
+        let _lock = crate::FORK_LOCK.lock().unwrap();
+        match unsafe { fork() }.expect("failed fork") {
+            ForkResult::Child => unsafe {
+                traceme().expect("failed traceme");
+                raise(Signal::SIGSTOP).expect("failed raise");
+                execv(&true_path, &[&true_path]).ok();
+                libc::_exit(1);
+            },
+            ForkResult::Parent { child } => {
+                waitpid(child, None).expect("failed waitpid");
+                setoptions(child, Options::PTRACE_O_TRACEEXEC).expect("failed setoptions");
+                cont(child, None).expect("failed cont");
+                assert_eq!(
+                    WaitStatus::PtraceEvent(child, Signal::SIGTRAP, libc::PTRACE_EVENT_EXEC),
+                    waitpid(child, None).expect("failed waitpid")
+                );
+
+                // 新しい実行ファイルのパス・メモリマップ・シンボルを読み込める
+                let path = read_exe_path(child).expect("failed read_exe_path");
+                let expected = std::fs::canonicalize("/bin/true").unwrap();
+                assert_eq!(expected.to_string_lossy(), path);
+                assert!(MemoryMap::new(child).load().contains_key(&path));
+                // strip済みであればシンボルテーブルはないが、ヘッダーは読み込める
+                let mut elf = Elf64::new(path);
+                elf.load().ok();
+                assert_ne!(0, elf.get_entry());
+
+                cont(child, None).expect("failed cont");
+                assert_eq!(
+                    WaitStatus::Exited(child, 0),
+                    waitpid(child, None).expect("failed waitpid")
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! 不正命令・バスエラー等による停止の診断
+//!
+//! siginfo_tとレジスタから、停止した原因(si_code)・命令・メモリオペランドを表示用に整形する
+
+use nix::sys::signal::Signal;
+use std::convert::TryFrom;
+
+use crate::disasm::{format_instruction, instruction_start, memory_operand, read_instruction};
+use crate::memory::{shadow, ReadMemory};
+use crate::siginfo::format_code;
+
+/// SIGBUSのsi_code(アライメント違反)
+const BUS_ADRALN: i32 = 1;
+
+/// 停止した位置の関数
+pub struct FaultFunc {
+    pub name: String, // 関数名
+    pub start: u64,   // 先頭アドレス
+    pub size: u64,    // サイズ
+}
+
+/// 命令の実行に起因するシグナル(停止して原因を表示する)か
+pub fn is_fault(signal: Signal) -> bool {
+    matches!(
+        signal,
+        Signal::SIGILL | Signal::SIGBUS | Signal::SIGSEGV | Signal::SIGFPE
+    )
+}
+
+/// シグナルによる停止を診断し、表示する行を返す
+///
+/// originalsは、int 3を埋め込んだアドレスと元のバイトで、元の命令として解析する
+pub fn diagnose<M: ReadMemory>(
+    mem: &M,
+    info: &libc::siginfo_t,
+    regs: &libc::user_regs_struct,
+    originals: &[(u64, u8)],
+    func: Option<&FaultFunc>,
+) -> Vec<String> {
+    let rip = regs.rip;
+    let signal = Signal::try_from(info.si_signo).ok();
+    let name = signal.map_or_else(|| info.si_signo.to_string(), |s| s.as_str().to_string());
+    let location = |addr: u64| match func {
+        Some(f) if f.start <= addr => format!("0x{:x} ({}+0x{:x})", addr, f.name, addr - f.start),
+        _ => format!("0x{:x}", addr),
+    };
+    let mut lines = vec![format!(
+        "Program received signal {} ({}) at {}",
+        name,
+        format_code(signal, info.si_code),
+        location(rip)
+    )];
+    match format_instruction(mem, rip, originals) {
+        Some(inst) => lines.push(inst),
+        None => lines.push(format!("cannot read instruction at 0x{:x}", rip)),
+    }
+
+    // 関数の先頭から解析し、命令の途中に飛び込んでいないか確認する
+    let inst = func.and_then(|f| {
+        let mut code = mem.read_memory(f.start, f.size as usize)?;
+        shadow(f.start, &mut code, originals);
+        instruction_start(f.start, &code, rip)
+    });
+    if let Some(inst) = inst.filter(|i| *i != rip) {
+        lines.push(format!(
+            "you jumped into the middle of an instruction at {}",
+            location(inst)
+        ));
+    }
+
+    // アライメント違反は、メモリオペランドのアドレスから求める(si_addrは設定されない)
+    if Some(Signal::SIGBUS) == signal && BUS_ADRALN == info.si_code {
+        let operand =
+            read_instruction(mem, rip, originals).and_then(|data| memory_operand(rip, &data, regs));
+        match operand {
+            Some((addr, size)) if 0 < size && 0 != addr % size as u64 => {
+                let align = 1u64 << addr.trailing_zeros();
+                lines.push(format!(
+                    "misaligned memory operand: 0x{:x} ({} bytes, aligned to {} byte{})",
+                    addr,
+                    size,
+                    align,
+                    if 1 == align { "" } else { "s" }
+                ));
+            }
+            Some((addr, size)) => {
+                lines.push(format!("memory operand: 0x{:x} ({} bytes)", addr, size))
+            }
+            None => lines.push("cannot decode the memory operand".to_string()),
+        }
+    }
+
+    // 命令以外のアドレスが原因であれば表示する
+    let addr = unsafe { info.si_addr() } as u64;
+    if 0 != addr && rip != addr {
+        lines.push(format!("fault address: 0x{:x}", addr));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::ProcessMemory;
+    use nix::sys::ptrace::{cont, getregs, getsiginfo, traceme};
+    use nix::sys::signal::{kill, raise};
+    use nix::sys::wait::{waitpid, WaitStatus};
+    use nix::unistd::{fork, ForkResult};
+    use std::arch::asm;
+
+    // mov eax, 0x0b0f0b0f; ret(先頭+1から実行すると、即値のud2を実行する)
+    #[cfg(feature = "disasm")]
+    std::arch::global_asm!(
+        ".globl fault_test_fixture",
+        "fault_test_fixture:",
+        "mov eax, 0x0b0f0b0f",
+        "ret",
+    );
+
+    #[cfg(feature = "disasm")]
+    extern "C" {
+        fn fault_test_fixture();
+    }
+
+    /// 子プロセスでfixtureを実行し、シグナルで停止した時の診断結果を返す
+    fn run_fixture(fixture: fn(), func: Option<&FaultFunc>) -> (Signal, Vec<String>) {
+        let _lock = crate::FORK_LOCK.lock().unwrap();
+        match unsafe { fork() }.expect("failed fork") {
+            ForkResult::Child => unsafe {
+                traceme().expect("failed traceme");
+                raise(Signal::SIGSTOP).expect("failed raise");
+                fixture();
+                libc::_exit(0);
+            },
+            ForkResult::Parent { child } => {
+                waitpid(child, None).expect("failed waitpid");
+                cont(child, None).expect("failed cont");
+                let signal = match waitpid(child, None).expect("failed waitpid") {
+                    WaitStatus::Stopped(_, s) => s,
+                    s => panic!("unexpected status: {:?}", s),
+                };
+                let info = getsiginfo(child).expect("failed getsiginfo");
+                let regs = getregs(child).expect("failed getregs");
+                let lines = diagnose(&ProcessMemory::new(child), &info, &regs, &[], func);
+                kill(child, Signal::SIGKILL).expect("failed kill");
+                waitpid(child, None).expect("failed waitpid");
+                (signal, lines)
+            }
+        }
+    }
+
+    #[test]
+    fn test_diagnose_ud2() {
+        let (signal, lines) = run_fixture(|| unsafe { asm!("ud2") }, None);
+        assert_eq!(Signal::SIGILL, signal);
+        assert!(
+            lines[0].starts_with("Program received signal SIGILL (ILL_ILLOPN) at 0x"),
+            "{:?}",
+            lines
+        );
+        assert!(lines[1].contains(": 0f 0b"), "{:?}", lines);
+        if cfg!(feature = "disasm") {
+            assert!(lines[1].ends_with("  ud2"), "{:?}", lines);
+        }
+        assert_eq!(2, lines.len(), "{:?}", lines);
+    }
+
+    #[cfg(feature = "disasm")]
+    #[test]
+    fn test_diagnose_middle_of_instruction() {
+        let start = fault_test_fixture as *const () as u64;
+        let func = FaultFunc {
+            name: "fault_test_fixture".to_string(),
+            start,
+            size: 6,
+        };
+        let fixture = || unsafe {
+            asm!("jmp {}", in(reg) fault_test_fixture as *const () as u64 + 1, options(noreturn))
+        };
+        let (signal, lines) = run_fixture(fixture, Some(&func));
+        assert_eq!(Signal::SIGILL, signal);
+        assert_eq!(
+            vec![
+                format!(
+                    "Program received signal SIGILL (ILL_ILLOPN) at 0x{:x} (fault_test_fixture+0x1)",
+                    start + 1
+                ),
+                format!("=> 0x{:x}: 0f 0b  ud2", start + 1),
+                format!(
+                    "you jumped into the middle of an instruction at 0x{:x} (fault_test_fixture+0x0)",
+                    start
+                ),
+            ],
+            lines
+        );
+    }
+
+    #[cfg(feature = "disasm")]
+    #[test]
+    fn test_diagnose_misaligned_atomic() {
+        // アライメントチェック(EFLAGS.AC)を有効にし、境界をずらしてlock addを実行する
+        let fixture = || unsafe {
+            let buf = [0u64; 2];
+            let p = (buf.as_ptr() as usize + 1) as *mut u64;
+            asm!(
+                "pushfq",
+                "or dword ptr [rsp], 0x40000",
+                "popfq",
+                "lock add qword ptr [{}], 1",
+                in(reg) p,
+            );
+        };
+        let (signal, lines) = run_fixture(fixture, None);
+        assert_eq!(Signal::SIGBUS, signal);
+        assert!(
+            lines[0].starts_with("Program received signal SIGBUS (BUS_ADRALN) at 0x"),
+            "{:?}",
+            lines
+        );
+        assert!(lines[1].contains("lock add qword ptr ["), "{:?}", lines);
+        assert!(
+            lines[2].starts_with("misaligned memory operand: 0x")
+                && lines[2].ends_with(" (8 bytes, aligned to 1 byte)"),
+            "{:?}",
+            lines
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! ファイルディスクリプタのパス
+//!
+//! /proc/[pid]/fd/[n]のリンク先を読み込み、ファイルディスクリプタ毎にキャッシュする
+
+use nix::unistd::Pid;
+use std::collections::HashMap;
+use std::fs;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// ソケットのエンドポイントを探すファイル(/proc/[pid]/net/配下)とプロトコル名
+const SOCKET_TABLES: &[(&str, &str)] = &[
+    ("tcp", "TCP"),
+    ("tcp6", "TCPv6"),
+    ("udp", "UDP"),
+    ("udp6", "UDPv6"),
+];
+
+/// プロセスのファイルディスクリプタ→パス
+pub struct FdTable {
+    pid: Pid,
+    detail: bool,                        // ソケットのエンドポイントを表示するか(-yy)
+    paths: HashMap<i32, Option<String>>, // 読み込み済みのパス(読み込めなかった場合はNone)
+}
+
+impl FdTable {
+    /// コンストラクタ
+    pub fn new(pid: Pid, detail: bool) -> Self {
+        FdTable {
+            pid,
+            detail,
+            paths: HashMap::new(),
+        }
+    }
+
+    /// パスを取得(キャッシュになければ読み込む)
+    pub fn get_path(&mut self, fd: i32) -> Option<String> {
+        if !self.paths.contains_key(&fd) {
+            let path = self.read_path(fd);
+            self.paths.insert(fd, path);
+        }
+        self.paths[&fd].clone()
+    }
+
+    /// ファイルディスクリプタのキャッシュを破棄(close・dup2等の完了時)
+    pub fn invalidate(&mut self, fd: i32) {
+        self.paths.remove(&fd);
+    }
+
+    /// 全てのキャッシュを破棄(execve等の完了時)
+    pub fn clear(&mut self) {
+        self.paths.clear();
+    }
+
+    /// /proc/[pid]/fd/[n]のリンク先を読み込む
+    fn read_path(&self, fd: i32) -> Option<String> {
+        let link = fs::read_link(format!("/proc/{}/fd/{}", self.pid, fd)).ok()?;
+        let link = link.to_string_lossy().to_string();
+        if !self.detail {
+            return Some(link);
+        }
+
+        // ソケットは、/proc/[pid]/net/配下からエンドポイントを探す
+        let endpoint = to_socket_inode(&link).and_then(|inode| {
+            SOCKET_TABLES.iter().find_map(|(file, proto)| {
+                let content =
+                    fs::read_to_string(format!("/proc/{}/net/{}", self.pid, file)).ok()?;
+                find_endpoint(&content, inode).map(|e| format!("{}:[{}]", proto, e))
+            })
+        });
+        Some(endpoint.unwrap_or(link))
+    }
+}
+
+/// リンク先(socket:[inode])→ソケットのinode
+fn to_socket_inode(link: &str) -> Option<u64> {
+    link.strip_prefix("socket:[")?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+/// /proc/net/tcp等の内容から、inodeに対応するエンドポイント(local->remote)を探す
+fn find_endpoint(content: &str, inode: u64) -> Option<String> {
+    content.lines().skip(1).find_map(|line| {
+        let cols = line.split_whitespace().collect::<Vec<&str>>();
+        if Some(&inode.to_string().as_str()) != cols.get(9) {
+            return None;
+        }
+        let local = to_socket_addr(cols.get(1)?)?;
+        let remote = to_socket_addr(cols.get(2)?)?;
+        match remote.as_str() {
+            "0.0.0.0:0" | "[::]:0" => Some(local),
+            _ => Some(format!("{}->{}", local, remote)),
+        }
+    })
+}
+
+/// /proc/net/tcp等のアドレス(16進数のアドレス:ポート)を整形
+///
+/// アドレスは32bit単位でホストのバイトオーダーとなっている
+fn to_socket_addr(s: &str) -> Option<String> {
+    let (addr, port) = s.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let mut bytes = vec![];
+    for i in (0..addr.len()).step_by(8) {
+        let word = u32::from_str_radix(addr.get(i..i + 8)?, 16).ok()?;
+        bytes.extend_from_slice(&word.to_ne_bytes());
+    }
+    match bytes.len() {
+        4 => Some(format!(
+            "{}:{}",
+            Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]),
+            port
+        )),
+        16 => {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(&bytes);
+            Some(format!("[{}]:{}", Ipv6Addr::from(octets), port))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::os::unix::io::AsRawFd;
+
+    #[test]
+    fn test_get_path() {
+        let pid = Pid::this();
+        let mut table = FdTable::new(pid, false);
+        {
+            let file = fs::File::open("Cargo.toml").unwrap();
+            let fd = file.as_raw_fd();
+            let expected = fs::canonicalize("Cargo.toml").unwrap();
+            assert_eq!(
+                Some(expected.to_string_lossy().to_string()),
+                table.get_path(fd)
+            );
+
+            // 閉じた後もキャッシュを返し、破棄すると読み込み直す
+            drop(file);
+            assert!(table.get_path(fd).is_some());
+            table.invalidate(fd);
+            assert_ne!(
+                Some(expected.to_string_lossy().to_string()),
+                table.get_path(fd)
+            );
+        }
+        {
+            assert_eq!(None, table.get_path(-1));
+        }
+    }
+
+    #[test]
+    fn test_find_endpoint() {
+        let content = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n\
+            0: 0100007F:BC8F 00000000:0000 0A 00000000:00000000 00:00000000 00000000 65534        0 940 1 0000000000000000 100 0 0 10 0\n\
+            1: 0100007F:A2C4 0100007F:0050 01 00000000:00000000 00:00000000 00000000  1000        0 1234 1 0000000000000000 20 4 30 10 -1\n";
+        assert_eq!(
+            Some("127.0.0.1:48271".to_string()),
+            find_endpoint(content, 940)
+        );
+        assert_eq!(
+            Some("127.0.0.1:41668->127.0.0.1:80".to_string()),
+            find_endpoint(content, 1234)
+        );
+        assert_eq!(None, find_endpoint(content, 1));
+
+        // IPv6
+        assert_eq!(
+            Some("[::1]:8080".to_string()),
+            to_socket_addr("00000000000000000000000001000000:1F90")
+        );
+        assert_eq!(None, to_socket_addr("0100007F"));
+
+        assert_eq!(Some(42938), to_socket_inode("socket:[42938]"));
+        assert_eq!(None, to_socket_inode("pipe:[42938]"));
+    }
+}
@@ -0,0 +1,277 @@
+//! 浮動小数点・SSEレジスタ(info fpregs・set fpregs)
+//!
+//! PTRACE_GETFPREGSでFXSAVE形式のuser_fpregs_structを読み込み、st0〜st7・mxcsr・xmm0〜xmm15を表示する
+//! st0〜st7は80ビットの拡張倍精度(16バイト毎)、xmm0〜xmm15は128ビット(リトルエンディアン)で格納されている
+
+use nix::errno::Errno;
+use nix::unistd::Pid;
+
+/// x87レジスタの数(st0〜st7)
+pub const ST_REGS: usize = 8;
+
+/// SSEレジスタの数(xmm0〜xmm15)
+pub const XMM_REGS: usize = 16;
+
+/// DWARFのレジスタ番号で、xmm0に対応する番号(xmm1以降は連番)
+pub const DWARF_XMM0: usize = 17;
+
+/// 拡張倍精度の指数のバイアス
+const EXT_BIAS: i32 = 16383;
+
+/// PTRACE_GETFPREGSで読み込む
+pub fn read_fpregs(pid: Pid) -> nix::Result<libc::user_fpregs_struct> {
+    let mut fpregs: libc::user_fpregs_struct = unsafe { std::mem::zeroed() };
+    let ret = unsafe {
+        libc::ptrace(
+            libc::PTRACE_GETFPREGS,
+            pid.as_raw(),
+            std::ptr::null_mut::<libc::c_void>(),
+            &mut fpregs as *mut libc::user_fpregs_struct,
+        )
+    };
+    Errno::result(ret).map(|_| fpregs)
+}
+
+/// PTRACE_SETFPREGSで書き込む
+pub fn write_fpregs(pid: Pid, fpregs: &libc::user_fpregs_struct) -> nix::Result<()> {
+    let ret = unsafe {
+        libc::ptrace(
+            libc::PTRACE_SETFPREGS,
+            pid.as_raw(),
+            std::ptr::null_mut::<libc::c_void>(),
+            fpregs as *const libc::user_fpregs_struct,
+        )
+    };
+    Errno::result(ret).map(drop)
+}
+
+/// stNの80ビットの値(仮数部64ビット・符号と指数部16ビット)
+pub fn st_raw(fpregs: &libc::user_fpregs_struct, no: usize) -> (u64, u16) {
+    let words = &fpregs.st_space[no * 4..no * 4 + 4];
+    let mantissa = words[0] as u64 | (words[1] as u64) << 32;
+    (mantissa, words[2] as u16)
+}
+
+/// 拡張倍精度をf64へ変換(f64で表せない精度・範囲は丸める)
+pub fn ext_to_f64(mantissa: u64, sign_exp: u16) -> f64 {
+    let sign = if 0 != sign_exp & 0x8000 { -1.0 } else { 1.0 };
+    let exp = (sign_exp & 0x7fff) as i32;
+    let value = match exp {
+        // 整数ビットを除いた仮数部が0であれば無限大、それ以外はNaN
+        0x7fff if 0 == mantissa << 1 => f64::INFINITY,
+        0x7fff => f64::NAN,
+        // 非正規化数(f64では0となる)
+        0 => (mantissa as f64) * 2f64.powi(1 - EXT_BIAS - 63),
+        // 仮数部は整数ビットを含むため、[1, 2)にしてから指数を掛ける(途中でアンダーフローしないよう2回に分ける)
+        _ => {
+            let e = exp - EXT_BIAS;
+            (mantissa as f64) * 2f64.powi(-63) * 2f64.powi(e / 2) * 2f64.powi(e - e / 2)
+        }
+    };
+    sign * value
+}
+
+/// xmmNの128ビットの値
+pub fn xmm_value(fpregs: &libc::user_fpregs_struct, no: usize) -> u128 {
+    fpregs.xmm_space[no * 4..no * 4 + 4]
+        .iter()
+        .rev()
+        .fold(0, |v, w| v << 32 | *w as u128)
+}
+
+/// xmmNへ128ビットの値を設定
+pub fn set_xmm_value(fpregs: &mut libc::user_fpregs_struct, no: usize, value: u128) {
+    for (i, w) in fpregs.xmm_space[no * 4..no * 4 + 4].iter_mut().enumerate() {
+        *w = (value >> (i * 32)) as u32;
+    }
+}
+
+/// 128ビットの値を2つのf64(下位・上位)として解釈
+pub fn f64_lanes(value: u128) -> (f64, f64) {
+    (
+        f64::from_bits(value as u64),
+        f64::from_bits((value >> 64) as u64),
+    )
+}
+
+/// f64の表示(非常に大きい・小さい値は指数表記)
+pub fn format_f64(v: f64) -> String {
+    let abs = v.abs();
+    if v.is_finite() && 0.0 != abs && !(1e-4..1e16).contains(&abs) {
+        format!("{:e}", v)
+    } else {
+        format!("{}", v)
+    }
+}
+
+/// レジスタ名(xmm0〜xmm15)から番号を取得
+pub fn parse_xmm(reg: &str) -> Option<usize> {
+    reg.strip_prefix("xmm")
+        .and_then(|n| n.parse::<usize>().ok())
+        .filter(|n| *n < XMM_REGS && reg == format!("xmm{}", n))
+}
+
+/// 128ビットの16進数を変換(0xは省略できる)
+pub fn parse_u128(s: &str) -> Result<u128, String> {
+    let hex = s.strip_prefix("0x").unwrap_or(s);
+    u128::from_str_radix(hex, 16).map_err(|_| format!("parse error: {}", s))
+}
+
+/// 表示する行(st0〜st7・mxcsr・xmm0〜xmm15)
+pub fn format_fpregs(fpregs: &libc::user_fpregs_struct) -> Vec<String> {
+    let st = (0..ST_REGS).map(|i| {
+        let (mantissa, sign_exp) = st_raw(fpregs, i);
+        format!(
+            "{:<8}: {:<24} (raw 0x{:04x}{:016x})",
+            format!("st{}", i),
+            format_f64(ext_to_f64(mantissa, sign_exp)),
+            sign_exp,
+            mantissa
+        )
+    });
+    let mxcsr = format!("{:<8}: 0x{:08x}", "mxcsr", fpregs.mxcsr);
+    let xmm = (0..XMM_REGS).map(|i| {
+        let value = xmm_value(fpregs, i);
+        let (lo, hi) = f64_lanes(value);
+        format!(
+            "{:<8}: 0x{:032x} {{f64: [{}, {}]}}",
+            format!("xmm{}", i),
+            value,
+            format_f64(lo),
+            format_f64(hi)
+        )
+    });
+    st.chain(std::iter::once(mxcsr)).chain(xmm).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn zeroed_fpregs() -> libc::user_fpregs_struct {
+        unsafe { std::mem::zeroed() }
+    }
+
+    #[test]
+    fn test_ext_to_f64() {
+        let cases = vec![
+            (0, 0, 0.0),
+            (0x8000_0000_0000_0000, 0x3fff, 1.0),
+            (0xa000_0000_0000_0000, 0xc000, -2.5),
+            (0xc000_0000_0000_0000, 0x3ffe, 0.75),
+            (0x8000_0000_0000_0000, 0x4005, 64.0),
+            (0x8000_0000_0000_0000, 0x7fff, f64::INFINITY),
+            (0x8000_0000_0000_0000, 0xffff, f64::NEG_INFINITY),
+            // f64の範囲外
+            (0x8000_0000_0000_0000, 0x0001, 0.0),
+        ];
+        for (mantissa, sign_exp, expected) in cases {
+            assert_eq!(
+                expected,
+                ext_to_f64(mantissa, sign_exp),
+                "0x{:04x}{:016x}",
+                sign_exp,
+                mantissa
+            );
+        }
+        assert!(ext_to_f64(0xc000_0000_0000_0000, 0x7fff).is_nan());
+    }
+
+    #[test]
+    fn test_xmm_value() {
+        let mut fpregs = zeroed_fpregs();
+        let value = 0x4000_0000_0000_0000_3ff0_0000_0000_0000;
+        set_xmm_value(&mut fpregs, 15, value);
+        assert_eq!(value, xmm_value(&fpregs, 15));
+        assert_eq!([0, 0x3ff0_0000, 0, 0x4000_0000], fpregs.xmm_space[60..]);
+        assert_eq!(0, xmm_value(&fpregs, 0));
+        assert_eq!((1.0, 2.0), f64_lanes(value));
+    }
+
+    #[test]
+    fn test_format_f64() {
+        let cases = vec![
+            (0.0, "0"),
+            (1.5, "1.5"),
+            (-64.0, "-64"),
+            (0.0001, "0.0001"),
+            (0.00001, "1e-5"),
+            (1e16, "1e16"),
+            (-5.8e300, "-5.8e300"),
+            (f64::NAN, "NaN"),
+            (f64::NEG_INFINITY, "-inf"),
+        ];
+        for (v, expected) in cases {
+            assert_eq!(expected, format_f64(v), "{}", v);
+        }
+    }
+
+    #[test]
+    fn test_parse_xmm() {
+        let cases = vec![
+            ("xmm0", Some(0)),
+            ("xmm15", Some(15)),
+            ("xmm16", None),
+            ("xmm01", None),
+            ("xmm", None),
+            ("st0", None),
+        ];
+        for (reg, expected) in cases {
+            assert_eq!(expected, parse_xmm(reg), "{}", reg);
+        }
+    }
+
+    #[test]
+    fn test_format_fpregs() {
+        let mut fpregs = zeroed_fpregs();
+        fpregs.st_space[0] = 0;
+        fpregs.st_space[1] = 0x8000_0000;
+        fpregs.st_space[2] = 0x3fff;
+        fpregs.mxcsr = 0x1f80;
+        set_xmm_value(&mut fpregs, 1, 0x3ff0_0000_0000_0000);
+        let lines = format_fpregs(&fpregs);
+        assert_eq!(ST_REGS + 1 + XMM_REGS, lines.len());
+        assert_eq!(
+            "st0     : 1                        (raw 0x3fff8000000000000000)",
+            lines[0]
+        );
+        assert_eq!("mxcsr   : 0x00001f80", lines[ST_REGS]);
+        assert_eq!(
+            "xmm1    : 0x00000000000000003ff0000000000000 {f64: [1, 0]}",
+            lines[ST_REGS + 2]
+        );
+    }
+
+    #[test]
+    fn test_write_fpregs() {
+        use nix::sys::ptrace::{cont, traceme};
+        use nix::sys::signal::{raise, Signal};
+        use nix::sys::wait::{waitpid, WaitStatus};
+        use nix::unistd::{fork, ForkResult};
+
+        let _lock = crate::FORK_LOCK.lock().unwrap();
+        match unsafe { fork() }.expect("failed fork") {
+            ForkResult::Child => unsafe {
+                traceme().expect("failed traceme");
+                raise(Signal::SIGSTOP).expect("failed raise");
+                libc::_exit(0);
+            },
+            ForkResult::Parent { child } => {
+                waitpid(child, None).expect("failed waitpid");
+                let mut fpregs = read_fpregs(child).unwrap();
+                set_xmm_value(&mut fpregs, 7, 0x1122_3344_5566_7788_99aa_bbcc_ddee_ff00);
+                write_fpregs(child, &fpregs).unwrap();
+                let fpregs = read_fpregs(child).unwrap();
+                assert_eq!(
+                    0x1122_3344_5566_7788_99aa_bbcc_ddee_ff00,
+                    xmm_value(&fpregs, 7)
+                );
+                cont(child, None).expect("failed cont");
+                assert_eq!(
+                    WaitStatus::Exited(child, 0),
+                    waitpid(child, None).expect("failed waitpid")
+                );
+            }
+        }
+    }
+}
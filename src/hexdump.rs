@@ -0,0 +1,79 @@
+//! 16進ダンプ
+//!
+//! 1行16byteで、16進表示とASCII表示を並べる(hexdump -C形式)
+
+use std::io::{Result, Write};
+
+/// 1行のバイト数
+const BYTES_PER_ROW: usize = 16;
+
+/// 16進ダンプを出力
+///
+/// addrはデータ先頭のアドレスで、各行の先頭に表示する
+pub fn hexdump<W: Write>(out: &mut W, addr: u64, data: &[u8]) -> Result<()> {
+    for (i, row) in data.chunks(BYTES_PER_ROW).enumerate() {
+        // 8byte毎に区切り、足りない分は空白で埋める
+        let mut hex = String::new();
+        for col in 0..BYTES_PER_ROW {
+            if col == BYTES_PER_ROW / 2 {
+                hex.push(' ');
+            }
+            match row.get(col) {
+                Some(b) => hex.push_str(&format!("{:02x} ", b)),
+                None => hex.push_str("   "),
+            }
+        }
+
+        // 表示できない文字は'.'とする
+        let ascii = row
+            .iter()
+            .map(|b| match b {
+                0x20..=0x7E => *b as char,
+                _ => '.',
+            })
+            .collect::<String>();
+        writeln!(
+            out,
+            "{:016x}  {} |{}|",
+            addr + (i * BYTES_PER_ROW) as u64,
+            hex,
+            ascii
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dump(addr: u64, data: &[u8]) -> String {
+        let mut out = vec![];
+        hexdump(&mut out, addr, data).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_hexdump() {
+        {
+            // 16byte毎に改行し、最終行は空白で埋める
+            let data = b"Hello, world!\n\x00\x01\x7f\x80ab";
+            assert_eq!(
+                "0000000000002000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 01  |Hello, world!...|\n\
+                 0000000000002010  7f 80 61 62                                       |..ab|\n",
+                dump(0x2000, data)
+            );
+        }
+        {
+            // 8byte以下の行
+            assert_eq!(
+                "0000000000000010  20 7e                                             | ~|\n",
+                dump(0x10, &[0x20, 0x7E])
+            );
+        }
+        {
+            // データがなければ何も出力しない
+            assert_eq!("", dump(0, &[]));
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! システムコールの結果の注入(-e inject=)
+//!
+//! 開始時にシステムコールNoを無効な値へ書き換えてカーネルに実行させず、
+//! 終了時に戻り値を書き換えることで、指定したエラー・戻り値を返したように見せる
+
+use std::collections::{HashMap, HashSet};
+
+use crate::syscall_info::{parse_errno, parse_syscall_set};
+
+/// 注入する結果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InjectResult {
+    Error(i32),  // エラー番号(error=)
+    Retval(u64), // 成功時の戻り値(retval=)
+}
+
+/// 注入の指定
+#[derive(Debug, Clone, PartialEq)]
+pub struct Injection {
+    nos: HashSet<i64>,    // 対象のシステムコール
+    result: InjectResult, // 注入する結果
+    when: u64,            // 何回目の呼び出しから注入するか(1〜)
+}
+
+impl Injection {
+    /// 注入式を解析
+    ///
+    /// inject=name,...:error=ENOENT もしくは inject=name,...:retval=N
+    /// :when=Nを付けると、N回目以降の呼び出しのみ注入する
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid inject expression: {}", expr);
+        let mut items = expr.strip_prefix("inject=").ok_or_else(invalid)?.split(':');
+        let nos = parse_syscall_set(items.next().unwrap_or_default())?;
+
+        let mut result = None;
+        let mut when = 1;
+        for item in items {
+            let (key, val) = item.split_once('=').ok_or_else(invalid)?;
+            match key {
+                "error" => {
+                    let errno =
+                        parse_errno(val).ok_or_else(|| format!("unknown error: {}", val))?;
+                    result = Some(InjectResult::Error(errno));
+                }
+                "retval" => {
+                    let retval = val.parse().map_err(|_| invalid())?;
+                    result = Some(InjectResult::Retval(retval));
+                }
+                "when" => {
+                    when = val.parse().ok().filter(|n| 0 < *n).ok_or_else(invalid)?;
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(Injection {
+            nos,
+            result: result.ok_or_else(|| format!("inject requires error= or retval=: {}", expr))?,
+            when,
+        })
+    }
+}
+
+/// 注入の判定
+pub struct Injector {
+    injections: Vec<Injection>,
+    counts: HashMap<i64, u64>, // システムコール毎の呼び出し回数
+}
+
+impl Injector {
+    /// コンストラクタ
+    pub fn new(injections: &[Injection]) -> Self {
+        Injector {
+            injections: injections.to_vec(),
+            counts: HashMap::new(),
+        }
+    }
+
+    /// 開始時に、注入するか判定
+    ///
+    /// 注入する場合は、終了時に設定する戻り値(エラーは負の値)を返す
+    /// 複数の指定に一致する場合は、先に指定したものを優先する
+    pub fn at_entry(&mut self, no: i64) -> Option<u64> {
+        let matched = self
+            .injections
+            .iter()
+            .filter(|i| i.nos.contains(&no))
+            .collect::<Vec<&Injection>>();
+        if matched.is_empty() {
+            return None;
+        }
+        let count = self.counts.entry(no).or_insert(0);
+        *count += 1;
+        let injection = matched.into_iter().find(|i| i.when <= *count)?;
+        Some(match injection.result {
+            InjectResult::Error(errno) => -(errno as i64) as u64,
+            InjectResult::Retval(val) => val,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let set = |nos: &[i64]| nos.iter().copied().collect::<HashSet<i64>>();
+        let cases = vec![
+            (
+                "inject=read,write:error=ENOENT",
+                Ok(Injection {
+                    nos: set(&[libc::SYS_read, libc::SYS_write]),
+                    result: InjectResult::Error(libc::ENOENT),
+                    when: 1,
+                }),
+            ),
+            (
+                "inject=openat:retval=42:when=3",
+                Ok(Injection {
+                    nos: set(&[libc::SYS_openat]),
+                    result: InjectResult::Retval(42),
+                    when: 3,
+                }),
+            ),
+            (
+                "inject=read:error=ENOTHING",
+                Err("unknown error: ENOTHING".to_string()),
+            ),
+            (
+                "inject=read",
+                Err("inject requires error= or retval=: inject=read".to_string()),
+            ),
+            (
+                "inject=read:error=EIO:when=0",
+                Err("invalid inject expression: inject=read:error=EIO:when=0".to_string()),
+            ),
+            (
+                "inject=read:signal=SIGINT",
+                Err("invalid inject expression: inject=read:signal=SIGINT".to_string()),
+            ),
+            (
+                "inject=reed:error=EIO",
+                Err("unknown syscall: reed (did you mean read, readv, tee, rseq?)".to_string()),
+            ),
+        ];
+        for (expr, expected) in cases {
+            assert_eq!(expected, Injection::parse(expr));
+        }
+    }
+
+    #[test]
+    fn test_at_entry() {
+        let injections = vec![
+            Injection::parse("inject=read:error=EIO:when=3").unwrap(),
+            Injection::parse("inject=read,openat:error=ENOENT:when=2").unwrap(),
+            Injection::parse("inject=close:retval=0").unwrap(),
+        ];
+        let mut injector = Injector::new(&injections);
+        let eio = -(libc::EIO as i64) as u64;
+        let enoent = -(libc::ENOENT as i64) as u64;
+
+        // 回数はシステムコール毎に数え、先に指定したものを優先する
+        assert_eq!(None, injector.at_entry(libc::SYS_read));
+        assert_eq!(Some(enoent), injector.at_entry(libc::SYS_read));
+        assert_eq!(Some(eio), injector.at_entry(libc::SYS_read));
+        assert_eq!(Some(eio), injector.at_entry(libc::SYS_read));
+        assert_eq!(None, injector.at_entry(libc::SYS_openat));
+        assert_eq!(Some(enoent), injector.at_entry(libc::SYS_openat));
+        assert_eq!(Some(0), injector.at_entry(libc::SYS_close));
+        assert_eq!(None, injector.at_entry(libc::SYS_write));
+    }
+}
@@ -0,0 +1,267 @@
+//! ioctlのリクエスト
+//!
+//! よく使うリクエストを名前で表示し、不明なリクエストは_IOCの方向・種類・番号・サイズに分解する
+
+use std::mem::size_of;
+
+use crate::syscall_struct::{format_int, format_winsize};
+
+/// _IOCの各フィールドのbit位置・幅
+const IOC_NR_SHIFT: u64 = 0;
+const IOC_TYPE_SHIFT: u64 = 8;
+const IOC_SIZE_SHIFT: u64 = 16;
+const IOC_DIR_SHIFT: u64 = 30;
+const IOC_NR_MASK: u64 = 0xFF;
+const IOC_TYPE_MASK: u64 = 0xFF;
+const IOC_SIZE_MASK: u64 = 0x3FFF;
+const IOC_DIR_MASK: u64 = 0x3;
+
+/// _IOCの方向
+const IOC_NONE: u64 = 0;
+const IOC_WRITE: u64 = 1;
+const IOC_READ: u64 = 2;
+
+/// リクエストを生成(_IOC)
+const fn ioc(dir: u64, ty: u64, nr: u64, size: usize) -> u64 {
+    (dir << IOC_DIR_SHIFT)
+        | (ty << IOC_TYPE_SHIFT)
+        | (nr << IOC_NR_SHIFT)
+        | ((size as u64) << IOC_SIZE_SHIFT)
+}
+
+/// 引数なし(_IO)
+const fn io(ty: u64, nr: u64) -> u64 {
+    ioc(IOC_NONE, ty, nr, 0)
+}
+
+/// カーネルが書き込む(_IOR)
+const fn ior(ty: u64, nr: u64, size: usize) -> u64 {
+    ioc(IOC_READ, ty, nr, size)
+}
+
+/// カーネルが読み込む(_IOW)
+const fn iow(ty: u64, nr: u64, size: usize) -> u64 {
+    ioc(IOC_WRITE, ty, nr, size)
+}
+
+/// カーネルが読み書きする(_IOWR)
+const fn iowr(ty: u64, nr: u64, size: usize) -> u64 {
+    ioc(IOC_READ | IOC_WRITE, ty, nr, size)
+}
+
+/// 端末(termios)のリクエスト
+const TCGETS: u64 = 0x5401;
+const TIOCGPGRP: u64 = 0x540F;
+const TIOCSPGRP: u64 = 0x5410;
+const TIOCOUTQ: u64 = 0x5411;
+const TIOCGWINSZ: u64 = 0x5413;
+const TIOCSWINSZ: u64 = 0x5414;
+const FIONREAD: u64 = 0x541B;
+const FIONBIO: u64 = 0x5421;
+const TIOCGPTN: u64 = ior(b'T' as u64, 0x30, size_of::<u32>());
+
+/// リクエスト名
+const IOCTLS: &[(u64, &str)] = &[
+    // 端末
+    (TCGETS, "TCGETS"),
+    (0x5402, "TCSETS"),
+    (0x5403, "TCSETSW"),
+    (0x5404, "TCSETSF"),
+    (0x5405, "TCGETA"),
+    (0x5406, "TCSETA"),
+    (0x5407, "TCSETAW"),
+    (0x5408, "TCSETAF"),
+    (0x5409, "TCSBRK"),
+    (0x540A, "TCXONC"),
+    (0x540B, "TCFLSH"),
+    (0x540C, "TIOCEXCL"),
+    (0x540D, "TIOCNXCL"),
+    (0x540E, "TIOCSCTTY"),
+    (TIOCGPGRP, "TIOCGPGRP"),
+    (TIOCSPGRP, "TIOCSPGRP"),
+    (TIOCOUTQ, "TIOCOUTQ"),
+    (0x5412, "TIOCSTI"),
+    (TIOCGWINSZ, "TIOCGWINSZ"),
+    (TIOCSWINSZ, "TIOCSWINSZ"),
+    (0x5415, "TIOCMGET"),
+    (0x5416, "TIOCMBIS"),
+    (0x5417, "TIOCMBIC"),
+    (0x5418, "TIOCMSET"),
+    (FIONREAD, "FIONREAD"),
+    (0x541D, "TIOCCONS"),
+    (FIONBIO, "FIONBIO"),
+    (0x5422, "TIOCNOTTY"),
+    (0x5425, "TCSBRKP"),
+    (0x5427, "TIOCSBRK"),
+    (0x5428, "TIOCCBRK"),
+    (0x5429, "TIOCGSID"),
+    (ior(b'T' as u64, 0x2A, 44), "TCGETS2"),
+    (iow(b'T' as u64, 0x2B, 44), "TCSETS2"),
+    (iow(b'T' as u64, 0x2C, 44), "TCSETSW2"),
+    (iow(b'T' as u64, 0x2D, 44), "TCSETSF2"),
+    (TIOCGPTN, "TIOCGPTN"),
+    (iow(b'T' as u64, 0x31, size_of::<i32>()), "TIOCSPTLCK"),
+    (io(b'T' as u64, 0x41), "TIOCGPTPEER"),
+    (0x5450, "FIONCLEX"),
+    (0x5451, "FIOCLEX"),
+    (0x5452, "FIOASYNC"),
+    // ファイルシステム
+    (ior(b'f' as u64, 1, size_of::<u64>()), "FS_IOC_GETFLAGS"),
+    (iow(b'f' as u64, 2, size_of::<u64>()), "FS_IOC_SETFLAGS"),
+    (ior(b'v' as u64, 1, size_of::<u64>()), "FS_IOC_GETVERSION"),
+    (iow(b'v' as u64, 2, size_of::<u64>()), "FS_IOC_SETVERSION"),
+    (iowr(b'f' as u64, 11, 32), "FS_IOC_FIEMAP"),
+    (ior(b'X' as u64, 31, 28), "FS_IOC_FSGETXATTR"),
+    (iow(b'X' as u64, 32, 28), "FS_IOC_FSSETXATTR"),
+    (iowr(b'X' as u64, 119, size_of::<i32>()), "FIFREEZE"),
+    (iowr(b'X' as u64, 120, size_of::<i32>()), "FITHAW"),
+    (iowr(b'X' as u64, 121, 24), "FITRIM"),
+    (iow(0x94, 9, size_of::<i32>()), "FICLONE"),
+    (iow(0x94, 13, 32), "FICLONERANGE"),
+    (ior(0x94, 49, 256), "FS_IOC_GETFSLABEL"),
+    (iow(0x94, 50, 256), "FS_IOC_SETFSLABEL"),
+    (iowr(0x94, 54, 24), "FIDEDUPERANGE"),
+    // ブロックデバイス
+    (io(0x12, 93), "BLKROSET"),
+    (io(0x12, 94), "BLKROGET"),
+    (io(0x12, 95), "BLKRRPART"),
+    (io(0x12, 96), "BLKGETSIZE"),
+    (io(0x12, 97), "BLKFLSBUF"),
+    (io(0x12, 98), "BLKRASET"),
+    (io(0x12, 99), "BLKRAGET"),
+    (io(0x12, 104), "BLKSSZGET"),
+    (ior(0x12, 112, size_of::<usize>()), "BLKBSZGET"),
+    (iow(0x12, 113, size_of::<usize>()), "BLKBSZSET"),
+    (ior(0x12, 114, size_of::<u64>()), "BLKGETSIZE64"),
+    (io(0x12, 119), "BLKDISCARD"),
+    (io(0x12, 120), "BLKIOMIN"),
+    (io(0x12, 121), "BLKIOOPT"),
+    (io(0x12, 122), "BLKALIGNOFF"),
+    (io(0x12, 123), "BLKPBSZGET"),
+    (io(0x12, 124), "BLKDISCARDZEROES"),
+    (io(0x12, 125), "BLKSECDISCARD"),
+    (io(0x12, 126), "BLKROTATIONAL"),
+    (io(0x12, 127), "BLKZEROOUT"),
+];
+
+/// 引数の種類
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IoctlArg {
+    Int,     // intへのポインタ
+    Winsize, // struct winsizeへのポインタ
+}
+
+impl IoctlArg {
+    /// 引数のサイズ
+    pub fn get_size(&self) -> usize {
+        match self {
+            IoctlArg::Int => size_of::<i32>(),
+            IoctlArg::Winsize => size_of::<libc::winsize>(),
+        }
+    }
+
+    /// 引数を整形
+    pub fn format(&self, data: &[u8]) -> Option<String> {
+        match self {
+            IoctlArg::Int => format_int(data),
+            IoctlArg::Winsize => format_winsize(data),
+        }
+    }
+}
+
+/// 引数を解釈するリクエスト(リクエスト, 引数の種類, カーネルが書き込むか)
+const IOCTL_ARGS: &[(u64, IoctlArg, bool)] = &[
+    (TIOCGWINSZ, IoctlArg::Winsize, true),
+    (TIOCSWINSZ, IoctlArg::Winsize, false),
+    (TIOCGPGRP, IoctlArg::Int, true),
+    (TIOCSPGRP, IoctlArg::Int, false),
+    (TIOCOUTQ, IoctlArg::Int, true),
+    (FIONREAD, IoctlArg::Int, true),
+    (FIONBIO, IoctlArg::Int, false),
+    (TIOCGPTN, IoctlArg::Int, true),
+];
+
+/// リクエスト→引数の種類と、カーネルが書き込むか
+pub fn to_ioctl_arg(request: u64) -> Option<(IoctlArg, bool)> {
+    IOCTL_ARGS
+        .iter()
+        .find(|(r, _, _)| *r == request & 0xFFFF_FFFF)
+        .map(|(_, arg, output)| (*arg, *output))
+}
+
+/// リクエストを整形
+///
+/// 名前が分からなければ、_IOC(方向, 種類, 番号, サイズ)に分解する
+pub fn format_ioctl_request(request: u64) -> String {
+    // リクエストはunsigned int
+    let request = request & 0xFFFF_FFFF;
+    match IOCTLS.iter().find(|(r, _)| *r == request) {
+        Some((_, name)) => name.to_string(),
+        None => format_ioc(request),
+    }
+}
+
+/// _IOC(方向, 種類, 番号, サイズ)に分解
+fn format_ioc(request: u64) -> String {
+    let dir = match (request >> IOC_DIR_SHIFT) & IOC_DIR_MASK {
+        IOC_NONE => "NONE",
+        IOC_WRITE => "WRITE",
+        IOC_READ => "READ",
+        _ => "READ|WRITE",
+    };
+    format!(
+        "_IOC({}, 0x{:x}, 0x{:x}, {})",
+        dir,
+        (request >> IOC_TYPE_SHIFT) & IOC_TYPE_MASK,
+        (request >> IOC_NR_SHIFT) & IOC_NR_MASK,
+        (request >> IOC_SIZE_SHIFT) & IOC_SIZE_MASK
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_ioctl_request() {
+        let cases = vec![
+            (libc::TCGETS, "TCGETS"),
+            (libc::TIOCGWINSZ, "TIOCGWINSZ"),
+            (libc::FIONREAD, "FIONREAD"),
+            (libc::FIOCLEX, "FIOCLEX"),
+            (libc::TIOCGPTN, "TIOCGPTN"),
+            (libc::TIOCSPTLCK, "TIOCSPTLCK"),
+            (libc::TCGETS2, "TCGETS2"),
+            (0x8008_6601, "FS_IOC_GETFLAGS"),
+            (0x4004_9409, "FICLONE"),
+            (0x8008_1272, "BLKGETSIZE64"),
+            (0x1260, "BLKGETSIZE"),
+            // 上位32bitは無視する
+            (0xFFFF_FFFF_0000_5401, "TCGETS"),
+            // 不明なリクエストは分解する
+            (0x8008_1280, "_IOC(READ, 0x12, 0x80, 8)"),
+            (0x4010_AB01, "_IOC(WRITE, 0xab, 0x1, 16)"),
+            (0xC020_6601, "_IOC(READ|WRITE, 0x66, 0x1, 32)"),
+            (0x54FF, "_IOC(NONE, 0x54, 0xff, 0)"),
+        ];
+        for (request, expected) in cases {
+            assert_eq!(expected, format_ioctl_request(request));
+        }
+    }
+
+    #[test]
+    fn test_ioc() {
+        assert_eq!(0x8008_1272, ior(0x12, 114, 8));
+        assert_eq!(0x4004_5431, iow(b'T' as u64, 0x31, 4));
+        assert_eq!(0xC020_660B, iowr(b'f' as u64, 11, 32));
+        assert_eq!(0x1260, io(0x12, 96));
+        assert_eq!("_IOC(READ, 0x12, 0x72, 8)", format_ioc(0x8008_1272));
+
+        assert_eq!(
+            Some((IoctlArg::Winsize, true)),
+            to_ioctl_arg(libc::TIOCGWINSZ)
+        );
+        assert_eq!(Some((IoctlArg::Int, false)), to_ioctl_arg(libc::FIONBIO));
+        assert_eq!(None, to_ioctl_arg(libc::TCGETS));
+    }
+}
@@ -1,59 +1,126 @@
 mod address;
+mod auxv;
+mod checkpoint;
+mod color;
+mod condition;
+mod core_file;
 mod debugger;
+mod disasm;
+mod duration;
 mod elf;
+mod environ;
+mod exec;
+mod fault;
+mod fd_table;
+mod fpregs;
+mod hexdump;
+mod inject;
+mod ioctl;
+mod memory;
 mod memory_map;
+mod pager;
+mod path_filter;
+mod profile;
+mod prompt;
+mod record;
+mod siginfo;
+mod solib;
+mod source;
 mod stracer;
+mod symbolizer;
+mod syscall_info;
+mod syscall_stats;
+mod syscall_stop;
+mod syscall_struct;
+mod target;
+mod task;
+mod unwind;
+mod user_command;
+mod watchpoint;
 
+use crate::core_file::CoreFile;
 use crate::debugger::Debugger;
-use crate::stracer::Tracer;
-use nix::sys::ptrace::traceme;
-use nix::unistd::{execv, fork, ForkResult};
+use crate::exec::{spawn_traced, split_args};
+use crate::stracer::{TraceOption, Tracer};
 use std::env;
-use std::ffi::CString;
-use std::fs;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
 use std::path::Path;
 
+/// トレーサーはwaitpid(-1)で待つため、子プロセスを生成するテストは同時に実行しない
+#[cfg(test)]
+static FORK_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
 /// メイン処理
 ///
-/// rtracer [option] [filename]
+/// rtracer [trace|dbg] [option...] [filename] [-- arg...]
+/// (dbgのオプションは--stop-at-entryのみ、-- 以降はプログラムへ渡す引数)
+/// rtracer core [filename] [corefile]
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
-        panic!("arg len is not two");
+    let all_args: Vec<String> = env::args().collect();
+    let (args, prog_args) = split_args(&all_args);
+    if args.len() < 3 {
+        panic!("usage: r-debugger [trace|dbg] [option...] [filename] [-- arg...] | core [filename] [corefile] (dbg option: --stop-at-entry)");
     }
 
-    let path = &args[2];
+    // コアファイルは、子プロセスを生成せずに開く
+    if "core" == args[1] {
+        if args.len() != 4 {
+            panic!("usage: r-debugger core [filename] [corefile]");
+        }
+        let abs_path = fs::canonicalize(&args[2])
+            .unwrap_or_else(|e| panic!("cannot open {}: {}", args[2], e))
+            .to_string_lossy()
+            .into_owned();
+        let core = CoreFile::open(&args[3]).unwrap_or_else(|e| panic!("{}", e));
+        let mut dbg = Debugger::from_core(abs_path, core);
+        dbg.start_core();
+        return;
+    }
+
+    // デバッグモードのオプションは、プログラムの開始位置で停止する(--stop-at-entry)のみ
+    let path = &args[args.len() - 1];
+    let mut opts = &args[2..args.len() - 1];
+    let mut stop_at_entry = false;
+    if "trace" != args[1] {
+        if let Some(o) = opts.iter().find(|o| "--stop-at-entry" != *o) {
+            panic!("unknown option: {}", o);
+        }
+        stop_at_entry = !opts.is_empty();
+        opts = &[];
+    }
+    let trace_opt = TraceOption::parse(opts).unwrap_or_else(|e| panic!("{}", e));
     if !Path::new(path).exists() {
         panic!("file not exist: {}", path);
     }
 
+    // トレース結果の出力先(指定がなければ、対象の標準出力と混ざらないよう標準エラー出力)
+    let out: Box<dyn Write> = match trace_opt.get_output() {
+        Some(f) => Box::new(BufWriter::new(
+            File::create(f).unwrap_or_else(|e| panic!("cannot open {}: {}", f, e)),
+        )),
+        None => Box::new(io::stderr()),
+    };
+
     // 子プロセス生成
-    match unsafe { fork() } {
-        Ok(ForkResult::Parent { child }) => {
+    match spawn_traced(path, prog_args) {
+        Ok(child) => {
             if "trace" == args[1] {
-                let tracer = Tracer::new(child);
+                let mut tracer = Tracer::new(child, trace_opt, out);
                 tracer.start();
             } else {
-                let abs_path = fs::canonicalize(path.to_string())
+                let abs_path = fs::canonicalize(path)
                     .expect("failed fs::canonicalize")
                     .as_path()
                     .to_str()
                     .unwrap()
                     .to_string();
                 let mut dbg = Debugger::new(child, abs_path);
+                dbg.set_stop_at_entry(stop_at_entry);
+                dbg.set_args(prog_args.to_vec());
                 dbg.start();
             }
         }
-        Ok(ForkResult::Child) => child(path),
         Err(_) => println!("Fork failed"),
     }
 }
-
-/// 子プロセス実行
-fn child(path: &str) {
-    // 自身をトレース対象とする
-    traceme().expect("failed traceme");
-
-    let path = CString::new(path).unwrap();
-    execv(&path, &[path.clone()]).expect("execv is failed");
-}
@@ -0,0 +1,244 @@
+//! トレース対象プロセスのメモリ読み込み
+//!
+//! デバッガ・システムコールトレーサーで共通に使用する
+
+use nix::sys::ptrace::{read, write, AddressType};
+use nix::unistd::Pid;
+use std::fs::OpenOptions;
+use std::io;
+use std::os::unix::fs::FileExt;
+
+/// ワードサイズ(ptraceの読み込み単位)
+const WORD_SIZE: u64 = 8;
+
+/// メモリ読み込み
+pub trait ReadMemory {
+    /// addrからlenバイト読み込む(読み込めなければNone)
+    fn read_memory(&self, addr: u64, len: usize) -> Option<Vec<u8>>;
+
+    /// NUL終端の文字列を読み込む
+    ///
+    /// 最大limitバイトまで読み込み、NULが見つからなければtruncatedとする
+    /// 途中から読み込めなくなった場合は、読み込めた分までとする
+    fn read_cstring(&self, addr: u64, limit: usize) -> Option<(Vec<u8>, bool)> {
+        let mut buf: Vec<u8> = vec![];
+        while buf.len() < limit {
+            // ページ境界をまたがないよう、ワード境界までずつ読み込む
+            let a = addr + buf.len() as u64;
+            let len = (WORD_SIZE - a % WORD_SIZE) as usize;
+            let chunk = match self.read_memory(a, len) {
+                Some(c) => c,
+                None if buf.is_empty() => return None,
+                None => return Some((buf, false)),
+            };
+            if let Some(p) = chunk.iter().position(|b| 0 == *b) {
+                buf.extend_from_slice(&chunk[..p]);
+                return Some((buf, false));
+            }
+            buf.extend_from_slice(&chunk);
+        }
+        buf.truncate(limit);
+        Some((buf, true))
+    }
+}
+
+/// プロセスのメモリ
+pub struct ProcessMemory {
+    pid: Pid,
+}
+
+impl ProcessMemory {
+    /// コンストラクタ
+    pub fn new(pid: Pid) -> Self {
+        ProcessMemory { pid }
+    }
+}
+
+impl ReadMemory for ProcessMemory {
+    fn read_memory(&self, addr: u64, len: usize) -> Option<Vec<u8>> {
+        read_bytes(self.pid, addr, len).ok()
+    }
+}
+
+/// 指定バイト数分のメモリ読み込み
+///
+/// ワード境界に合わせて読み込み、範囲外のページへアクセスしないようにする
+pub fn read_bytes(pid: Pid, addr: u64, len: usize) -> nix::Result<Vec<u8>> {
+    let start = addr - addr % WORD_SIZE;
+    // 不正なアドレス(フレームベースが壊れている場合等)で溢れないようにする
+    let end = addr
+        .checked_add(len as u64)
+        .ok_or(nix::errno::Errno::EFAULT)?;
+    let mut buf = vec![];
+    let mut a = start;
+    while a < end {
+        let val = read(pid, a as AddressType)? as u64;
+        buf.extend_from_slice(&val.to_le_bytes());
+        a += WORD_SIZE;
+    }
+    let offset = (addr - start) as usize;
+    Ok(buf[offset..offset + len].to_vec())
+}
+
+/// /proc/<pid>/memから一括で読み込む
+///
+/// ワード単位のptraceより速いため、大きな領域(checkpoint等)の読み込みに使う
+/// 停止しているトレース対象のみ読み込める
+pub fn read_bulk(pid: Pid, addr: u64, len: usize) -> io::Result<Vec<u8>> {
+    let mem = OpenOptions::new()
+        .read(true)
+        .open(format!("/proc/{}/mem", pid))?;
+    let mut buf = vec![0; len];
+    mem.read_exact_at(&mut buf, addr)?;
+    Ok(buf)
+}
+
+/// /proc/<pid>/memへ一括で書き込む
+pub fn write_bulk(pid: Pid, addr: u64, data: &[u8]) -> io::Result<()> {
+    let mem = OpenOptions::new()
+        .write(true)
+        .open(format!("/proc/{}/mem", pid))?;
+    mem.write_all_at(data, addr)
+}
+
+/// 読み込んだメモリ内容のうち、書き換えたバイトを元のバイトに置き換える
+///
+/// originalsは、アドレスと元のバイト(ブレイクポイントのint 3で書き換えたもの)
+pub fn shadow(addr: u64, data: &mut [u8], originals: &[(u64, u8)]) {
+    for (a, b) in originals {
+        if let Some(d) = a
+            .checked_sub(addr)
+            .and_then(|pos| data.get_mut(pos as usize))
+        {
+            *d = *b;
+        }
+    }
+}
+
+/// 指定バイト列のメモリ書き込み
+///
+/// ワード単位で書き込むため、前後の端数は既存の内容と合成する
+pub fn write_bytes(pid: Pid, addr: u64, data: &[u8]) -> nix::Result<()> {
+    let start = addr - addr % WORD_SIZE;
+    let end = addr + data.len() as u64;
+    let mut a = start;
+    while a < end {
+        let mut word = (read(pid, a as AddressType)? as u64).to_le_bytes();
+        for (i, b) in word.iter_mut().enumerate() {
+            let pos = a + i as u64;
+            if addr <= pos && pos < end {
+                *b = data[(pos - addr) as usize];
+            }
+        }
+        unsafe {
+            write(
+                pid,
+                a as AddressType,
+                u64::from_le_bytes(word) as AddressType,
+            )?;
+        }
+        a += WORD_SIZE;
+    }
+    Ok(())
+}
+
+/// テスト用メモリ(登録した領域(先頭アドレス, データ)のみ読み込める)
+#[cfg(test)]
+pub(crate) struct FakeMemory(pub Vec<(u64, Vec<u8>)>);
+
+#[cfg(test)]
+impl FakeMemory {
+    /// base〜base+data.len()のみ読み込めるメモリ
+    pub fn new(base: u64, data: Vec<u8>) -> Self {
+        FakeMemory(vec![(base, data)])
+    }
+}
+
+#[cfg(test)]
+impl ReadMemory for FakeMemory {
+    fn read_memory(&self, addr: u64, len: usize) -> Option<Vec<u8>> {
+        self.0.iter().find_map(|(base, data)| {
+            let start = addr.checked_sub(*base)? as usize;
+            data.get(start..start.checked_add(len)?).map(|d| d.to_vec())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_cstring() {
+        let mem = FakeMemory::new(0x1003, b"/etc/ld.so.cache\0tail".to_vec());
+        let cases = vec![
+            (0x1003, 64, Some((b"/etc/ld.so.cache".to_vec(), false))),
+            (0x1008, 64, Some((b"ld.so.cache".to_vec(), false))),
+            // limitで打ち切る
+            (0x1003, 4, Some((b"/etc".to_vec(), true))),
+            // 終端がないまま読み込めなくなった
+            (0x1014, 64, Some((b"tail".to_vec(), false))),
+            // 読み込めない
+            (0x10, 64, None),
+        ];
+        for (addr, limit, expected) in cases {
+            assert_eq!(expected, mem.read_cstring(addr, limit));
+        }
+    }
+
+    #[test]
+    fn test_shadow() {
+        let mut data = vec![0x55, 0xCC, 0x89, 0xCC];
+        shadow(
+            0x1000,
+            &mut data,
+            &[
+                (0x1001, 0x48),
+                (0x1003, 0xE5),
+                (0x0FFF, 0x90),
+                (0x1004, 0x90),
+            ],
+        );
+        assert_eq!(vec![0x55, 0x48, 0x89, 0xE5], data);
+    }
+
+    #[test]
+    fn test_write_bytes() {
+        use nix::sys::ptrace::traceme;
+        use nix::sys::signal::{kill, raise, Signal};
+        use nix::sys::wait::waitpid;
+        use nix::unistd::{fork, ForkResult};
+
+        // 子プロセスは同じアドレスにバッファを持つため、ワード境界をまたいで書き込む
+        let buf = [0xAAu8; 32];
+        let addr = buf.as_ptr() as u64;
+        let _lock = crate::FORK_LOCK.lock().unwrap();
+        match unsafe { fork() }.expect("failed fork") {
+            ForkResult::Child => unsafe {
+                traceme().expect("failed traceme");
+                raise(Signal::SIGSTOP).expect("failed raise");
+                libc::_exit(0);
+            },
+            ForkResult::Parent { child } => {
+                waitpid(child, None).expect("failed waitpid");
+                let data = (1..=11).collect::<Vec<u8>>();
+                write_bytes(child, addr + 3, &data).expect("failed write_bytes");
+                let mut expected = buf.to_vec();
+                expected[3..14].copy_from_slice(&data);
+                assert_eq!(expected, read_bytes(child, addr, buf.len()).unwrap());
+                // 空の書き込みは何もしない
+                write_bytes(child, addr + 20, &[]).expect("failed write_bytes");
+                assert_eq!(expected, read_bytes(child, addr, buf.len()).unwrap());
+
+                // 一括での読み書きも、ptraceと同じ内容になる
+                assert_eq!(expected, read_bulk(child, addr, buf.len()).unwrap());
+                write_bulk(child, addr + 1, &[0x55; 4]).expect("failed write_bulk");
+                expected[1..5].copy_from_slice(&[0x55; 4]);
+                assert_eq!(expected, read_bytes(child, addr, buf.len()).unwrap());
+                assert!(read_bulk(child, 0, 8).is_err());
+                kill(child, Signal::SIGKILL).expect("failed kill");
+                waitpid(child, None).expect("failed waitpid");
+            }
+        }
+    }
+}
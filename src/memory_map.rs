@@ -4,6 +4,7 @@ use std::fs;
 use std::io::{BufRead, BufReader};
 
 // メモリマップデータ
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct MapInfo {
     pub start_address: String,
@@ -11,6 +12,16 @@ pub struct MapInfo {
     pub permission: String,
 }
 
+// マップされている領域(info proc mappings)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mapping {
+    pub start: u64,    // 開始アドレス
+    pub end: u64,      // 終了アドレス
+    pub offset: u64,   // ファイル上のオフセット
+    pub perms: String, // 権限(r-xp等、分からなければ空)
+    pub path: String,  // ファイル名(無名の領域は空)
+}
+
 // メモリーマップ
 pub struct MemoryMap {
     maps_path: String,
@@ -31,6 +42,7 @@ impl MemoryMap {
     ///
     /// 対象プログラムのスタートアドレス
     pub fn load(&mut self) -> &HashMap<String, Vec<MapInfo>> {
+        self.maps.clear();
         let content = BufReader::new(
             fs::File::open(&self.maps_path).unwrap_or_else(|p| panic!("cannot open file: {}", p)),
         );
@@ -71,4 +83,142 @@ impl MemoryMap {
 
         &self.maps
     }
+
+    /// start〜endの範囲が、permの権限(r・w・x)でマップされているか確認
+    ///
+    /// メモリマップを読み込み直し、アクセスできない最初のアドレスをエラーとする
+    pub fn check_access(&mut self, start: u64, end: u64, perm: char) -> Result<(), u64> {
+        let maps = self
+            .load()
+            .values()
+            .flatten()
+            .cloned()
+            .collect::<Vec<MapInfo>>();
+        match find_inaccessible(&maps, start, end, perm) {
+            Some(addr) => Err(addr),
+            None => Ok(()),
+        }
+    }
+}
+
+/// プロセスのマップされている領域を、アドレスの順に取得(/proc/<pid>/maps)
+pub fn load_mappings(pid: Pid) -> std::io::Result<Vec<Mapping>> {
+    let content = fs::read_to_string(format!("/proc/{}/maps", pid))?;
+    Ok(content.lines().filter_map(parse_mapping).collect())
+}
+
+/// mapsの1行(開始-終了 権限 オフセット デバイス inode ファイル名)を解析
+fn parse_mapping(line: &str) -> Option<Mapping> {
+    let fields = line.split_whitespace().collect::<Vec<&str>>();
+    let (start, end) = fields.first()?.split_once('-')?;
+    Some(Mapping {
+        start: u64::from_str_radix(start, 16).ok()?,
+        end: u64::from_str_radix(end, 16).ok()?,
+        offset: u64::from_str_radix(fields.get(2)?, 16).ok()?,
+        perms: fields.get(1)?.to_string(),
+        path: fields.get(5..).map(|p| p.join(" ")).unwrap_or_default(),
+    })
+}
+
+/// start〜endの範囲で、permの権限でマップされていない最初のアドレスを検索
+fn find_inaccessible(maps: &[MapInfo], start: u64, end: u64, perm: char) -> Option<u64> {
+    let mut addr = start;
+    while addr < end {
+        let end_of_map = maps.iter().find_map(|m| {
+            let s = u64::from_str_radix(&m.start_address, 16).ok()?;
+            let e = u64::from_str_radix(&m.end_address, 16).ok()?;
+            (s <= addr && addr < e && m.permission.contains(perm)).then_some(e)
+        });
+        match end_of_map {
+            Some(e) => addr = e,
+            None => return Some(addr),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_mapping() {
+        let mapping = |start, end, offset, perms: &str, path: &str| Mapping {
+            start,
+            end,
+            offset,
+            perms: perms.to_string(),
+            path: path.to_string(),
+        };
+        let cases = vec![
+            (
+                "55d4c8a00000-55d4c8a01000 r-xp 00001000 08:01 1048602    /tmp/crash",
+                Some(mapping(
+                    0x55d4_c8a0_0000,
+                    0x55d4_c8a0_1000,
+                    0x1000,
+                    "r-xp",
+                    "/tmp/crash",
+                )),
+            ),
+            (
+                "7ffd5e3f1000-7ffd5e412000 rw-p 00000000 00:00 0          [stack]",
+                Some(mapping(
+                    0x7ffd_5e3f_1000,
+                    0x7ffd_5e41_2000,
+                    0,
+                    "rw-p",
+                    "[stack]",
+                )),
+            ),
+            // 無名の領域
+            (
+                "7f1c2a000000-7f1c2a021000 rw-p 00000000 00:00 0",
+                Some(mapping(0x7f1c_2a00_0000, 0x7f1c_2a02_1000, 0, "rw-p", "")),
+            ),
+            // 空白を含むファイル名
+            (
+                "7f1c2b000000-7f1c2b001000 r--p 00000000 08:01 42 /tmp/a b.so",
+                Some(mapping(
+                    0x7f1c_2b00_0000,
+                    0x7f1c_2b00_1000,
+                    0,
+                    "r--p",
+                    "/tmp/a b.so",
+                )),
+            ),
+            ("broken", None),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(expected, parse_mapping(line), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_find_inaccessible() {
+        let map = |s: &str, e: &str, p: &str| MapInfo {
+            start_address: s.to_string(),
+            end_address: e.to_string(),
+            permission: p.to_string(),
+        };
+        let maps = vec![
+            map("1000", "2000", "r-xp"),
+            map("2000", "3000", "rw-p"),
+            map("4000", "5000", "---p"),
+        ];
+        let cases = vec![
+            (0x1000, 0x1010, 'r', None),
+            // 連続したマップをまたぐ
+            (0x1ff0, 0x2010, 'r', None),
+            (0x1ff0, 0x2010, 'w', Some(0x1ff0)),
+            (0x2ff0, 0x3010, 'r', Some(0x3000)),
+            (0x4000, 0x4010, 'r', Some(0x4000)),
+            (0x500, 0x1010, 'r', Some(0x500)),
+            // 空の範囲
+            (0x8000, 0x8000, 'r', None),
+        ];
+        for (start, end, perm, expected) in cases {
+            assert_eq!(expected, find_inaccessible(&maps, start, end, perm));
+        }
+    }
 }
@@ -0,0 +1,148 @@
+//! ページャー
+//!
+//! 長い一覧を指定行数毎に止め、続けるか入力を待つ(GDBのページャー相当)
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// 1ページの行数の既定値
+pub const DEFAULT_HEIGHT: usize = 24;
+
+/// 標準出力へのページャー
+pub type StdoutPager = Pager<io::Stdout, io::StdinLock<'static>>;
+
+/// ページャー
+///
+/// 改行毎に出力し、height行毎に入力を待つ(heightがゼロならば止めない)
+/// qが入力された場合は、以降の出力を破棄する
+pub struct Pager<W: Write, R: BufRead> {
+    out: W,
+    input: R,
+    height: usize,
+    lines: usize, // 現在のページに出力した行数
+    quit: bool,   // 表示を中断したか
+    buf: Vec<u8>, // 改行前のデータ
+}
+
+impl StdoutPager {
+    /// 標準出力へのページャーを生成
+    ///
+    /// 標準入力が端末でない(スクリプト実行の)場合は、止めずにすべて出力する
+    pub fn stdout(height: usize) -> Self {
+        let height = match io::stdin().is_terminal() {
+            true => height,
+            false => 0,
+        };
+        Pager::new(io::stdout(), io::stdin().lock(), height)
+    }
+}
+
+impl<W: Write, R: BufRead> Pager<W, R> {
+    /// コンストラクタ
+    pub fn new(out: W, input: R, height: usize) -> Self {
+        Pager {
+            out,
+            input,
+            height,
+            lines: 0,
+            quit: false,
+            buf: vec![],
+        }
+    }
+
+    /// 表示を中断したか
+    #[allow(dead_code)]
+    pub fn is_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// 続けるか入力を待つ
+    fn prompt(&mut self) -> io::Result<()> {
+        write!(self.out, "--Type <RET> for more, q to quit--")?;
+        self.out.flush()?;
+        let mut s = String::new();
+        self.input.read_line(&mut s)?;
+        self.quit = "q" == s.trim();
+        self.lines = 0;
+        Ok(())
+    }
+}
+
+impl<W: Write, R: BufRead> Write for Pager<W, R> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        for b in data {
+            if self.quit {
+                break;
+            }
+            self.buf.push(*b);
+            if b'\n' != *b {
+                continue;
+            }
+
+            // 1行出力し、ページの行数に達すれば入力を待つ
+            self.out.write_all(&self.buf)?;
+            self.buf.clear();
+            self.lines += 1;
+            if 0 < self.height && self.height <= self.lines {
+                self.prompt()?;
+            }
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.quit {
+            self.out.write_all(&self.buf)?;
+        }
+        self.buf.clear();
+        self.out.flush()
+    }
+}
+
+impl<W: Write, R: BufRead> Drop for Pager<W, R> {
+    fn drop(&mut self) {
+        self.flush().ok();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pager() {
+        let lines = (1..=5).map(|i| format!("line{}\n", i)).collect::<String>();
+        {
+            // ページ毎に入力を待つ
+            let mut out = vec![];
+            let mut pager = Pager::new(&mut out, &b"\n\n"[..], 2);
+            write!(pager, "{}", lines).unwrap();
+            drop(pager);
+            let prompt = "--Type <RET> for more, q to quit--";
+            assert_eq!(
+                format!("line1\nline2\n{0}line3\nline4\n{0}line5\n", prompt),
+                String::from_utf8(out).unwrap()
+            );
+        }
+        {
+            // qで以降の出力を破棄する
+            let mut out = vec![];
+            let mut pager = Pager::new(&mut out, &b"q\n"[..], 2);
+            write!(pager, "{}", lines).unwrap();
+            writeln!(pager, "after").unwrap();
+            assert!(pager.is_quit());
+            drop(pager);
+            assert_eq!(
+                "line1\nline2\n--Type <RET> for more, q to quit--",
+                String::from_utf8(out).unwrap()
+            );
+        }
+        {
+            // 高さゼロは止めない(改行がなくてもflushで出力)
+            let mut out = vec![];
+            let mut pager = Pager::new(&mut out, &b""[..], 0);
+            write!(pager, "{}tail", lines).unwrap();
+            drop(pager);
+            assert_eq!(format!("{}tail", lines), String::from_utf8(out).unwrap());
+        }
+    }
+}
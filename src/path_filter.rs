@@ -0,0 +1,230 @@
+//! パスによるフィルタ(-P)
+//!
+//! 指定したパスを引数とするシステムコールと、そのパスを開いたファイルディスクリプタを
+//! 引数とするシステムコールのみをトレース対象とする
+
+use nix::unistd::Pid;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use crate::memory::ReadMemory;
+use crate::syscall_info::{get_args, to_errno, to_signature, ArgKind, PATH_MAX};
+
+/// パスによるフィルタ
+pub struct PathFilter {
+    paths: Vec<PathBuf>,             // 対象のパス(正規化済み)
+    fds: HashMap<Pid, HashSet<i32>>, // プロセス毎の、対象のパスを開いたファイルディスクリプタ
+}
+
+impl PathFilter {
+    /// コンストラクタ
+    pub fn new(paths: &[String]) -> Self {
+        PathFilter {
+            paths: paths.iter().map(|p| normalize(Path::new(p))).collect(),
+            fds: HashMap::new(),
+        }
+    }
+
+    /// フィルタが有効か(-Pの指定があるか)
+    pub fn is_enabled(&self) -> bool {
+        !self.paths.is_empty()
+    }
+
+    /// 開始時のシステムコールが対象か
+    ///
+    /// ファイルディスクリプタの引数が対象のパスを開いたものか、パスの引数が対象のパスと一致すれば対象
+    /// (フィルタが無効な場合は全て対象)
+    pub fn is_matched<M: ReadMemory>(
+        &self,
+        pid: Pid,
+        regs: &libc::user_regs_struct,
+        mem: &M,
+    ) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+        let sig = match to_signature(regs.orig_rax as i64) {
+            Some(s) => s,
+            None => return false,
+        };
+        let args = get_args(regs);
+        let fds = self.fds.get(&pid);
+        let kinds = sig.get_arg_kinds();
+        kinds.iter().enumerate().any(|(i, k)| match k {
+            ArgKind::Fd | ArgKind::DirFd => fds.is_some_and(|s| s.contains(&(args[i] as i32))),
+            ArgKind::Path => {
+                // *at系は、直前の引数のディレクトリからの相対パス
+                let dirfd = match i.checked_sub(1).map(|p| kinds[p]) {
+                    Some(ArgKind::DirFd) => args[i - 1] as i32,
+                    _ => libc::AT_FDCWD,
+                };
+                mem.read_cstring(args[i], PATH_MAX)
+                    .map(|(path, _)| resolve(pid, dirfd, Path::new(OsStr::from_bytes(&path))))
+                    .is_some_and(|p| self.paths.contains(&normalize(&p)))
+            }
+            _ => false,
+        })
+    }
+
+    /// 終了時に、対象のパスを開いたファイルディスクリプタを更新
+    ///
+    /// 開始時に対象と判定したシステムコールのみ呼び出す
+    pub fn update(&mut self, pid: Pid, regs: &libc::user_regs_struct, ret: u64) {
+        if !self.is_enabled() || to_errno(ret).is_some() {
+            return;
+        }
+        let no = regs.orig_rax as i64;
+        let args = get_args(regs);
+        let fds = self.fds.entry(pid).or_default();
+        match no {
+            libc::SYS_close => {
+                fds.remove(&(args[0] as i32));
+            }
+            // 複製先は、複製元が対象の場合のみ対象(対象だった複製先は置き換わる)
+            libc::SYS_dup2 | libc::SYS_dup3 => {
+                if fds.contains(&(args[0] as i32)) {
+                    fds.insert(args[1] as i32);
+                } else {
+                    fds.remove(&(args[1] as i32));
+                }
+            }
+            _ if to_signature(no).is_some_and(|s| ArgKind::Fd == s.get_ret()) => {
+                fds.insert(ret as i32);
+            }
+            _ => {}
+        }
+    }
+
+    /// フォークしたプロセスへ、ファイルディスクリプタを引き継ぐ
+    pub fn fork(&mut self, parent: Pid, child: Pid) {
+        if let Some(fds) = self.fds.get(&parent).cloned() {
+            self.fds.insert(child, fds);
+        }
+    }
+
+    /// 終了したプロセスのファイルディスクリプタを破棄
+    pub fn remove(&mut self, pid: Pid) {
+        self.fds.remove(&pid);
+    }
+}
+
+/// 相対パスを、プロセスのカレントディレクトリ・ディレクトリのファイルディスクリプタから解決
+fn resolve(pid: Pid, dirfd: i32, path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    let dir = if libc::AT_FDCWD == dirfd {
+        format!("/proc/{}/cwd", pid)
+    } else {
+        format!("/proc/{}/fd/{}", pid, dirfd)
+    };
+    match fs::read_link(dir) {
+        Ok(dir) => dir.join(path),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// パスを正規化
+///
+/// 作成前のファイルも比較できるよう、存在しない場合はディレクトリのみ正規化する
+fn normalize(path: &Path) -> PathBuf {
+    if let Ok(p) = fs::canonicalize(path) {
+        return p;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) => fs::canonicalize(dir)
+            .map(|d| d.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::FakeMemory;
+
+    /// システムコールNoと引数からレジスタを生成
+    fn regs(no: i64, args: &[u64]) -> libc::user_regs_struct {
+        let mut regs: libc::user_regs_struct = unsafe { std::mem::zeroed() };
+        regs.orig_rax = no as u64;
+        let mut a = args.iter().copied().chain(std::iter::repeat(0));
+        regs.rdi = a.next().unwrap();
+        regs.rsi = a.next().unwrap();
+        regs.rdx = a.next().unwrap();
+        regs
+    }
+
+    #[test]
+    fn test_is_matched() {
+        let pid = Pid::this();
+        // 文字列はワード単位で読むため、NULで埋める
+        let mem = FakeMemory(vec![
+            (0x1000, b"/tmp/../tmp/target.txt\0\0\0\0\0\0\0\0".to_vec()),
+            (0x2000, b"/tmp/other.txt\0\0\0\0\0\0\0\0".to_vec()),
+            (0x3000, b"Cargo.toml\0\0\0\0\0\0\0\0".to_vec()),
+        ]);
+        let at_fdcwd = libc::AT_FDCWD as u64;
+        {
+            // 指定なしは全て対象
+            let filter = PathFilter::new(&[]);
+            assert!(filter.is_matched(pid, &regs(libc::SYS_getpid, &[]), &mem));
+        }
+        {
+            let mut filter =
+                PathFilter::new(&["/tmp/target.txt".to_string(), "Cargo.toml".to_string()]);
+            let open_target = regs(libc::SYS_openat, &[at_fdcwd, 0x1000]);
+            let open_other = regs(libc::SYS_openat, &[at_fdcwd, 0x2000]);
+            assert!(filter.is_matched(pid, &open_target, &mem));
+            assert!(!filter.is_matched(pid, &open_other, &mem));
+            // カレントディレクトリからの相対パス
+            assert!(filter.is_matched(pid, &regs(libc::SYS_stat, &[0x3000]), &mem));
+            // 引数にパス・ファイルディスクリプタがない
+            assert!(!filter.is_matched(pid, &regs(libc::SYS_getpid, &[]), &mem));
+
+            // 開いたファイルディスクリプタのみ対象
+            filter.update(pid, &open_target, 3);
+            let write = |fd: u64| regs(libc::SYS_write, &[fd, 0, 0]);
+            assert!(filter.is_matched(pid, &write(3), &mem));
+            assert!(!filter.is_matched(pid, &write(4), &mem));
+            assert!(!filter.is_matched(Pid::from_raw(1), &write(3), &mem));
+
+            // 複製
+            filter.update(pid, &regs(libc::SYS_dup2, &[3, 10]), 10);
+            assert!(filter.is_matched(pid, &write(10), &mem));
+            filter.update(pid, &regs(libc::SYS_dup2, &[4, 10]), 10);
+            assert!(!filter.is_matched(pid, &write(10), &mem));
+
+            // フォーク先に引き継ぐ
+            let child = Pid::from_raw(pid.as_raw() + 1);
+            filter.fork(pid, child);
+            assert!(filter.is_matched(child, &write(3), &mem));
+
+            // 失敗したcloseでは閉じない
+            let close = regs(libc::SYS_close, &[3]);
+            filter.update(pid, &close, -libc::EINTR as u64);
+            assert!(filter.is_matched(pid, &write(3), &mem));
+            filter.update(pid, &close, 0);
+            assert!(!filter.is_matched(pid, &write(3), &mem));
+            assert!(filter.is_matched(child, &write(3), &mem));
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        let cwd = fs::canonicalize(std::env::current_dir().unwrap()).unwrap();
+        assert_eq!(cwd.join("Cargo.toml"), normalize(Path::new("Cargo.toml")));
+        // 存在しないファイルは、ディレクトリのみ正規化
+        assert_eq!(
+            cwd.join("not_found.txt"),
+            normalize(Path::new("./src/../not_found.txt"))
+        );
+        assert_eq!(
+            PathBuf::from("/not_found/a.txt"),
+            normalize(Path::new("/not_found/a.txt"))
+        );
+    }
+}
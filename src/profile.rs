@@ -0,0 +1,208 @@
+//! ブレイクポイントのヒット回数の計測(profile on)
+//!
+//! 計測対象のブレイクポイントでは停止せず、回数を数えて再開する
+//! ヒットする度に実行するため、割り当て・表示をせずにint 3を貼り直す
+
+use nix::sys::ptrace::{getregs, read, setregs, step, write, AddressType};
+use nix::sys::wait::{waitpid, WaitStatus};
+use nix::unistd::Pid;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// int 3で停止したブレイクポイントの元の命令を1ステップ実行し、int 3を貼り直す
+///
+/// origはブレイクポイントのアドレスの元のバイトで、前後のバイトは現在の内容のまま書き戻す
+/// 1ステップ実行後に停止していなければ(終了した等)、貼り直さずに状態を返す
+pub fn step_over_breakpoint(pid: Pid, addr: u64, orig: u8) -> nix::Result<WaitStatus> {
+    let word = read(pid, addr as AddressType)? as u64 & !0xFF;
+    unsafe {
+        write(
+            pid,
+            addr as AddressType,
+            (word | orig as u64) as AddressType,
+        )?;
+    }
+    let mut regs = getregs(pid)?;
+    regs.rip = addr;
+    setregs(pid, regs)?;
+    step(pid, None)?;
+    let status = waitpid(pid, None)?;
+    if let WaitStatus::Stopped(..) = status {
+        unsafe {
+            write(pid, addr as AddressType, (word | 0xCC) as AddressType)?;
+        }
+    }
+    Ok(status)
+}
+
+/// プログラムを実行していた時間
+pub struct RunClock {
+    elapsed: Duration,        // 停止するまでに実行していた時間の合計
+    resumed: Option<Instant>, // 実行中であれば再開した時刻
+}
+
+impl RunClock {
+    /// コンストラクタ
+    pub fn new() -> Self {
+        RunClock {
+            elapsed: Duration::ZERO,
+            resumed: None,
+        }
+    }
+
+    /// 再開
+    pub fn resume(&mut self) {
+        self.resumed.get_or_insert_with(Instant::now);
+    }
+
+    /// 停止
+    pub fn pause(&mut self) {
+        if let Some(r) = self.resumed.take() {
+            self.elapsed += r.elapsed();
+        }
+    }
+
+    /// 実行していた時間を取得(実行中であれば現在まで)
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed + self.resumed.map_or(Duration::ZERO, |r| r.elapsed())
+    }
+}
+
+/// ヒット回数の一覧を、シンボル毎に回数の多い順に整形
+///
+/// hitsはブレイクポイント毎のシンボル名と回数で、実行していた時間から1秒あたりの回数も求める
+pub fn format_report(hits: &[(&str, u64)], elapsed: Duration) -> Vec<String> {
+    let mut by_sym = BTreeMap::new();
+    for (sym, n) in hits {
+        *by_sym.entry(*sym).or_insert(0) += n;
+    }
+    let mut rows = by_sym.into_iter().collect::<Vec<(&str, u64)>>();
+    rows.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    let secs = elapsed.as_secs_f64();
+    let total = rows.iter().map(|(_, n)| n).sum::<u64>();
+    let mut lines = vec![
+        format!("{} hits in {:.3}s of run time", total, secs),
+        format!("{:>12} {:>14}  symbol", "hits", "hits/s"),
+    ];
+    for (sym, n) in rows {
+        let rate = match 0.0 < secs {
+            true => format!("{:.1}", n as f64 / secs),
+            false => "-".to_string(),
+        };
+        lines.push(format!("{:>12} {:>14}  {}", n, rate, sym));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // 1,000,000回ループし、毎回profile_test_hitのnopを実行する
+    std::arch::global_asm!(
+        ".globl profile_test_loop",
+        "profile_test_loop:",
+        "mov ecx, 1000000",
+        ".globl profile_test_hit",
+        "profile_test_hit:",
+        "nop",
+        "dec ecx",
+        "jnz profile_test_hit",
+        "ret",
+    );
+
+    extern "C" {
+        fn profile_test_loop();
+        fn profile_test_hit();
+    }
+
+    #[test]
+    fn test_format_report() {
+        let hits = vec![("step", 300), ("main", 1), ("step", 100), ("done", 1)];
+        assert_eq!(
+            vec![
+                "402 hits in 2.000s of run time",
+                "        hits         hits/s  symbol",
+                "         400          200.0  step",
+                "           1            0.5  done",
+                "           1            0.5  main",
+            ],
+            format_report(&hits, Duration::from_secs(2))
+        );
+        // 実行していなければ、1秒あたりの回数は求めない
+        assert_eq!(
+            vec![
+                "0 hits in 0.000s of run time",
+                "        hits         hits/s  symbol",
+                "           0              -  main",
+            ],
+            format_report(&[("main", 0)], Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn test_run_clock() {
+        let mut clock = RunClock::new();
+        assert_eq!(Duration::ZERO, clock.elapsed());
+        clock.resume();
+        std::thread::sleep(Duration::from_millis(10));
+        clock.pause();
+        let elapsed = clock.elapsed();
+        assert!(Duration::from_millis(10) <= elapsed);
+        // 停止中は増えない
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(elapsed, clock.elapsed());
+    }
+
+    #[test]
+    fn test_step_over_breakpoint_hot_loop() {
+        use nix::sys::ptrace::{cont, traceme};
+        use nix::sys::signal::{raise, Signal};
+        use nix::unistd::{fork, ForkResult};
+
+        let addr = profile_test_hit as *const () as u64;
+        let _lock = crate::FORK_LOCK.lock().unwrap();
+        match unsafe { fork() }.expect("failed fork") {
+            ForkResult::Child => unsafe {
+                traceme().expect("failed traceme");
+                raise(Signal::SIGSTOP).expect("failed raise");
+                profile_test_loop();
+                libc::_exit(0);
+            },
+            ForkResult::Parent { child } => {
+                waitpid(child, None).expect("failed waitpid");
+                let word = read(child, addr as AddressType).expect("failed read") as u64;
+                let orig = word as u8;
+                unsafe {
+                    write(
+                        child,
+                        addr as AddressType,
+                        ((word & !0xFF) | 0xCC) as AddressType,
+                    )
+                    .expect("failed write");
+                }
+
+                // ヒットする度に貼り直し、最後まで実行できる
+                let mut hits = 0u64;
+                cont(child, None).expect("failed cont");
+                loop {
+                    match waitpid(child, None).expect("failed waitpid") {
+                        WaitStatus::Stopped(_, Signal::SIGTRAP) => {
+                            hits += 1;
+                            let status = step_over_breakpoint(child, addr, orig).unwrap();
+                            assert_eq!(WaitStatus::Stopped(child, Signal::SIGTRAP), status);
+                            cont(child, None).expect("failed cont");
+                        }
+                        WaitStatus::Exited(_, code) => {
+                            assert_eq!(0, code);
+                            break;
+                        }
+                        s => panic!("unexpected status: {:?}", s),
+                    }
+                }
+                assert_eq!(1_000_000, hits);
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! シェルのプロンプト(set prompt-format)
+//!
+//! 停止した位置を、関数名+オフセット・ソース位置で表示する
+
+use std::fmt;
+use std::path::Path;
+
+/// プロンプトの表示形式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PromptFormat {
+    Compact, // 関数名+オフセット・ファイル名:行番号
+    Verbose, // rip・関数名+オフセット・ファイルのパス:行番号
+    Raw,     // ripのみ(プロンプトを解析するスクリプト向け)
+}
+
+impl PromptFormat {
+    /// 文字列(compact・verbose・raw)から変換
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "compact" => Ok(PromptFormat::Compact),
+            "verbose" => Ok(PromptFormat::Verbose),
+            "raw" => Ok(PromptFormat::Raw),
+            _ => Err(format!(
+                "invalid prompt-format: {} (compact|verbose|raw)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PromptFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PromptFormat::Compact => write!(f, "compact"),
+            PromptFormat::Verbose => write!(f, "verbose"),
+            PromptFormat::Raw => write!(f, "raw"),
+        }
+    }
+}
+
+/// プロンプトを整形
+///
+/// funcは関数名とオフセット、lineはファイルと行番号(見つからなければNone)
+/// 関数が見つからなければ、形式によらずripを表示する
+pub fn format_prompt(
+    format: PromptFormat,
+    rip: u64,
+    func: Option<(&str, u64)>,
+    line: Option<(&str, u64)>,
+) -> String {
+    let (name, offset) = match (format, func) {
+        (PromptFormat::Raw, _) | (_, None) => return format!("[rip: 0x{:x}] >> ", rip),
+        (_, Some(f)) => f,
+    };
+    let mut fields = vec![];
+    if PromptFormat::Verbose == format {
+        fields.push(format!("0x{:x}", rip));
+    }
+    fields.push(format!("{}+0x{:x}", name, offset));
+    if let Some((file, no)) = line {
+        let file = match format {
+            PromptFormat::Compact => Path::new(file)
+                .file_name()
+                .map_or(file.into(), |n| n.to_string_lossy()),
+            _ => file.into(),
+        };
+        fields.push(format!("{}:{}", file, no));
+    }
+    format!("[{}] >> ", fields.join(" "))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        for f in [
+            PromptFormat::Compact,
+            PromptFormat::Verbose,
+            PromptFormat::Raw,
+        ] {
+            assert_eq!(Ok(f), PromptFormat::parse(&f.to_string()));
+        }
+        assert!(PromptFormat::parse("short").is_err());
+    }
+
+    #[test]
+    fn test_format_prompt() {
+        let func = Some(("main", 0x24));
+        let line = Some(("src/main.rs", 17));
+        let cases = vec![
+            (
+                PromptFormat::Compact,
+                func,
+                line,
+                "[main+0x24 main.rs:17] >> ",
+            ),
+            (
+                PromptFormat::Verbose,
+                func,
+                line,
+                "[0x55d10000 main+0x24 src/main.rs:17] >> ",
+            ),
+            (PromptFormat::Raw, func, line, "[rip: 0x55d10000] >> "),
+            // 行情報がなければ、関数名+オフセットのみ
+            (PromptFormat::Compact, func, None, "[main+0x24] >> "),
+            // 関数が見つからなければrip
+            (PromptFormat::Compact, None, line, "[rip: 0x55d10000] >> "),
+            (PromptFormat::Verbose, None, None, "[rip: 0x55d10000] >> "),
+        ];
+        for (format, func, line, expected) in cases {
+            assert_eq!(
+                expected,
+                format_prompt(format, 0x55d1_0000, func, line),
+                "{}",
+                format
+            );
+        }
+    }
+}
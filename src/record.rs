@@ -0,0 +1,156 @@
+//! 実行した命令アドレスの記録(record)
+//!
+//! ステップ実行中のripを一定数だけ保持し、関数単位にまとめて表示する
+
+/// 記録する命令数の既定値
+pub const DEFAULT_RECORD_SIZE: usize = 10000;
+
+/// 関数名と、関数先頭からのオフセット
+pub type Symbol = (String, u64);
+
+/// リングバッファ(容量を超えると古いものから上書きする)
+pub struct RingBuffer<T> {
+    buf: Vec<T>,     // 要素
+    capacity: usize, // 容量
+    head: usize,     // 次に上書きする位置(容量に達した後)
+}
+
+impl<T: Copy> RingBuffer<T> {
+    /// コンストラクタ
+    pub fn new(capacity: usize) -> Self {
+        RingBuffer {
+            buf: Vec::with_capacity(capacity),
+            capacity,
+            head: 0,
+        }
+    }
+
+    /// 容量を取得
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 要素数を取得
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// 追加
+    pub fn push(&mut self, val: T) {
+        if 0 == self.capacity {
+            return;
+        }
+        if self.buf.len() < self.capacity {
+            self.buf.push(val);
+        } else {
+            self.buf[self.head] = val;
+            self.head = (self.head + 1) % self.capacity;
+        }
+    }
+
+    /// 最後に追加した要素を取得
+    pub fn last(&self) -> Option<T> {
+        match self.buf.len() {
+            0 => None,
+            l if l < self.capacity => Some(self.buf[l - 1]),
+            _ => Some(self.buf[(self.head + self.capacity - 1) % self.capacity]),
+        }
+    }
+
+    /// 新しいものからn個を、古い順に取得
+    pub fn recent(&self, n: usize) -> Vec<T> {
+        let (new, old) = self.buf.split_at(self.head);
+        let all = old.iter().chain(new.iter()).copied().collect::<Vec<T>>();
+        all[all.len().saturating_sub(n)..].to_vec()
+    }
+}
+
+/// 命令アドレスを、連続して同じ関数を実行した範囲にまとめて整形
+///
+/// symbolizeはアドレス→(関数名, 関数先頭からのオフセット)で、各範囲の先頭の命令を
+/// 関数名+オフセット × 命令数と表示する(関数が不明な場合は、同じアドレスのみまとめる)
+pub fn format_runs<F: Fn(u64) -> Option<Symbol>>(rips: &[u64], symbolize: F) -> Vec<String> {
+    // 先頭の命令アドレス・関数・命令数
+    let mut runs: Vec<(u64, Option<Symbol>, usize)> = vec![];
+    for rip in rips {
+        let sym = symbolize(*rip);
+        match runs.last_mut() {
+            Some((_, Some((f, _)), count)) if sym.as_ref().is_some_and(|(s, _)| s == f) => {
+                *count += 1
+            }
+            Some((addr, None, count)) if sym.is_none() && addr == rip => *count += 1,
+            _ => runs.push((*rip, sym, 1)),
+        }
+    }
+    runs.iter()
+        .map(|(addr, sym, count)| {
+            let name = match sym {
+                Some((f, off)) => format!("0x{:016x} in {}+0x{:x}", addr, f, off),
+                None => format!("0x{:016x} in ??", addr),
+            };
+            match count {
+                1 => name,
+                c => format!("{} × {}", name, c),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ring_buffer() {
+        {
+            let mut buf = RingBuffer::new(3);
+            assert_eq!(None, buf.last());
+            assert_eq!(Vec::<u64>::new(), buf.recent(10));
+            buf.push(1);
+            buf.push(2);
+            assert_eq!(Some(2), buf.last());
+            assert_eq!(vec![1, 2], buf.recent(10));
+            assert_eq!(vec![2], buf.recent(1));
+        }
+        {
+            // 容量を超えると古いものから上書きする
+            let mut buf = RingBuffer::new(3);
+            (1..=7).for_each(|v| buf.push(v));
+            assert_eq!(3, buf.len());
+            assert_eq!(Some(7), buf.last());
+            assert_eq!(vec![5, 6, 7], buf.recent(10));
+            assert_eq!(vec![6, 7], buf.recent(2));
+            assert_eq!(Vec::<u64>::new(), buf.recent(0));
+        }
+        {
+            // 容量0は記録しない
+            let mut buf = RingBuffer::new(0);
+            buf.push(1);
+            assert_eq!(0, buf.len());
+            assert_eq!(None, buf.last());
+        }
+    }
+
+    #[test]
+    fn test_format_runs() {
+        let symbolize = |addr: u64| match addr {
+            0x1000..=0x10FF => Some(("main".to_string(), addr - 0x1000)),
+            0x2000..=0x20FF => Some(("helper".to_string(), addr - 0x2000)),
+            _ => None,
+        };
+        let rips = [
+            0x1010, 0x1014, 0x1018, 0x2000, 0x2004, 0x7F00, 0x7F00, 0x7F04, 0x101C,
+        ];
+        assert_eq!(
+            vec![
+                "0x0000000000001010 in main+0x10 × 3",
+                "0x0000000000002000 in helper+0x0 × 2",
+                "0x0000000000007f00 in ?? × 2",
+                "0x0000000000007f04 in ??",
+                "0x000000000000101c in main+0x1c",
+            ],
+            format_runs(&rips, symbolize)
+        );
+        assert_eq!(Vec::<String>::new(), format_runs(&[], symbolize));
+    }
+}
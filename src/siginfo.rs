@@ -0,0 +1,188 @@
+//! シグナル情報
+//!
+//! PTRACE_GETSIGINFOで取得したsiginfo_tを表示用に整形する
+
+use nix::sys::signal::Signal;
+use std::convert::TryFrom;
+
+/// si_code(libcに定義がないもの)
+const SI_USER: i32 = 0;
+const SI_KERNEL: i32 = 0x80;
+const SI_QUEUE: i32 = -1;
+const SI_TKILL: i32 = -6;
+
+/// 全シグナル共通のsi_code
+const COMMON_CODES: &[(i32, &str)] = &[
+    (SI_USER, "SI_USER"),
+    (SI_KERNEL, "SI_KERNEL"),
+    (SI_QUEUE, "SI_QUEUE"),
+    (-2, "SI_TIMER"),
+    (-3, "SI_MESGQ"),
+    (-4, "SI_ASYNCIO"),
+    (-5, "SI_SIGIO"),
+    (SI_TKILL, "SI_TKILL"),
+];
+
+/// シグナル固有のsi_code(1〜)
+fn to_signal_codes(signal: Signal) -> &'static [&'static str] {
+    match signal {
+        Signal::SIGCHLD => &[
+            "CLD_EXITED",
+            "CLD_KILLED",
+            "CLD_DUMPED",
+            "CLD_TRAPPED",
+            "CLD_STOPPED",
+            "CLD_CONTINUED",
+        ],
+        Signal::SIGSEGV => &["SEGV_MAPERR", "SEGV_ACCERR", "SEGV_BNDERR", "SEGV_PKUERR"],
+        Signal::SIGBUS => &[
+            "BUS_ADRALN",
+            "BUS_ADRERR",
+            "BUS_OBJERR",
+            "BUS_MCEERR_AR",
+            "BUS_MCEERR_AO",
+        ],
+        Signal::SIGFPE => &[
+            "FPE_INTDIV",
+            "FPE_INTOVF",
+            "FPE_FLTDIV",
+            "FPE_FLTOVF",
+            "FPE_FLTUND",
+            "FPE_FLTRES",
+            "FPE_FLTINV",
+            "FPE_FLTSUB",
+        ],
+        Signal::SIGILL => &[
+            "ILL_ILLOPC",
+            "ILL_ILLOPN",
+            "ILL_ILLADR",
+            "ILL_ILLTRP",
+            "ILL_PRVOPC",
+            "ILL_PRVREG",
+            "ILL_COPROC",
+            "ILL_BADSTK",
+        ],
+        Signal::SIGTRAP => &["TRAP_BRKPT", "TRAP_TRACE", "TRAP_BRANCH", "TRAP_HWBKPT"],
+        _ => &[],
+    }
+}
+
+/// シグナル名(不明な番号は数値)
+fn to_signal_name(signo: i32) -> String {
+    match Signal::try_from(signo) {
+        Ok(s) => s.as_str().to_string(),
+        Err(_) => signo.to_string(),
+    }
+}
+
+/// si_codeを整形
+pub fn format_code(signal: Option<Signal>, code: i32) -> String {
+    let specific = match signal {
+        Some(s) if 0 < code => to_signal_codes(s).get(code as usize - 1).copied(),
+        _ => None,
+    };
+    specific
+        .or_else(|| {
+            COMMON_CODES
+                .iter()
+                .find(|(c, _)| *c == code)
+                .map(|(_, n)| *n)
+        })
+        .map_or_else(|| code.to_string(), |n| n.to_string())
+}
+
+/// シグナル受信を整形(--- SIGCHLD {si_signo=SIGCHLD, ...} ---)
+///
+/// si_codeに応じて、送信元(si_pid・si_uid)やアドレス(si_addr)を表示する
+pub fn format_siginfo(info: &libc::siginfo_t) -> String {
+    let name = to_signal_name(info.si_signo);
+    let signal = Signal::try_from(info.si_signo).ok();
+    let mut fields = vec![
+        format!("si_signo={}", name),
+        format!("si_code={}", format_code(signal, info.si_code)),
+    ];
+
+    let code = info.si_code;
+    match signal {
+        // 子プロセスの状態変化
+        Some(Signal::SIGCHLD) if 0 < code => unsafe {
+            fields.push(format!("si_pid={}", info.si_pid()));
+            fields.push(format!("si_uid={}", info.si_uid()));
+            fields.push(format!("si_status={}", info.si_status()));
+        },
+        // 不正なアクセス等(原因のアドレス)
+        Some(Signal::SIGSEGV)
+        | Some(Signal::SIGBUS)
+        | Some(Signal::SIGFPE)
+        | Some(Signal::SIGILL)
+        | Some(Signal::SIGTRAP)
+            if 0 < code && SI_KERNEL != code =>
+        unsafe {
+            fields.push(format!("si_addr={:?}", info.si_addr()));
+        },
+        // kill・tgkill・sigqueueによる送信
+        _ if SI_USER == code || SI_TKILL == code || SI_QUEUE == code => unsafe {
+            fields.push(format!("si_pid={}", info.si_pid()));
+            fields.push(format!("si_uid={}", info.si_uid()));
+        },
+        _ => {}
+    }
+    format!("--- {} {{{}}} ---", name, fields.join(", "))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// テスト用のsiginfo_t(共用体部分は、先頭からのオフセットへ書き込む)
+    fn siginfo(signo: i32, code: i32, fields: &[(usize, &[u8])]) -> libc::siginfo_t {
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        info.si_signo = signo;
+        info.si_code = code;
+        let p = &mut info as *mut libc::siginfo_t as *mut u8;
+        for (offset, bytes) in fields {
+            for (i, b) in bytes.iter().enumerate() {
+                unsafe { *p.add(offset + i) = *b };
+            }
+        }
+        info
+    }
+
+    #[test]
+    fn test_format_siginfo() {
+        let pid = 1234i32.to_ne_bytes();
+        let uid = 1000u32.to_ne_bytes();
+        let status = 3i32.to_ne_bytes();
+        let addr = 0x10u64.to_ne_bytes();
+        let cases = vec![
+            (
+                siginfo(libc::SIGCHLD, 1, &[(16, &pid), (20, &uid), (24, &status)]),
+                "--- SIGCHLD {si_signo=SIGCHLD, si_code=CLD_EXITED, si_pid=1234, si_uid=1000, si_status=3} ---",
+            ),
+            (
+                siginfo(libc::SIGSEGV, 1, &[(16, &addr)]),
+                "--- SIGSEGV {si_signo=SIGSEGV, si_code=SEGV_MAPERR, si_addr=0x10} ---",
+            ),
+            (
+                siginfo(libc::SIGINT, SI_USER, &[(16, &pid), (20, &uid)]),
+                "--- SIGINT {si_signo=SIGINT, si_code=SI_USER, si_pid=1234, si_uid=1000} ---",
+            ),
+            (
+                siginfo(libc::SIGUSR1, SI_TKILL, &[(16, &pid), (20, &uid)]),
+                "--- SIGUSR1 {si_signo=SIGUSR1, si_code=SI_TKILL, si_pid=1234, si_uid=1000} ---",
+            ),
+            (
+                siginfo(libc::SIGSEGV, SI_KERNEL, &[]),
+                "--- SIGSEGV {si_signo=SIGSEGV, si_code=SI_KERNEL} ---",
+            ),
+            // 不明なsi_code
+            (
+                siginfo(libc::SIGALRM, 5, &[]),
+                "--- SIGALRM {si_signo=SIGALRM, si_code=5} ---",
+            ),
+        ];
+        for (info, expected) in cases {
+            assert_eq!(expected, format_siginfo(&info));
+        }
+    }
+}
@@ -0,0 +1,295 @@
+//! 共有ライブラリのロード・アンロードの検出(catch load・catch unload)
+//!
+//! 動的リンカのランデブー構造体(r_debug)をDT_DEBUGから求め、r_brkにブレイクポイントを貼る
+//! r_brkが呼ばれる毎にメモリマップの差分から、ロード・アンロードされたライブラリを求める
+
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryInto;
+use std::fmt;
+
+use crate::memory_map::{MapInfo, Mapping};
+
+/// ダイナミックセクションのタグ
+const DT_NULL: u64 = 0;
+const DT_DEBUG: u64 = 21;
+
+/// r_debugのサイズ(r_version・r_map・r_brk・r_state・r_ldbase)
+pub const R_DEBUG_SIZE: usize = 40;
+
+/// r_debug.r_state(RT_CONSISTENT以外は、ロード・アンロードの途中)
+pub const RT_CONSISTENT: u32 = 0;
+
+/// 動的リンカのランデブー構造体
+#[derive(Debug, PartialEq)]
+pub struct RDebug {
+    pub r_brk: u64,   // ロード・アンロードの度に呼ばれる関数のアドレス
+    pub r_state: u32, // 状態(RT_CONSISTENT・RT_ADD・RT_DELETE)
+}
+
+/// ダイナミックセクションから、DT_DEBUGの値(r_debugのアドレス)を取得
+///
+/// 動的リンカが設定するまでは0のため、Noneとする
+pub fn find_r_debug(dynamic: &[u8]) -> Option<u64> {
+    dynamic
+        .chunks_exact(16)
+        .map(|d| {
+            let tag = u64::from_le_bytes(d[..8].try_into().unwrap());
+            let val = u64::from_le_bytes(d[8..].try_into().unwrap());
+            (tag, val)
+        })
+        .take_while(|(tag, _)| DT_NULL != *tag)
+        .find(|(tag, _)| DT_DEBUG == *tag)
+        .map(|(_, val)| val)
+        .filter(|val| 0 != *val)
+}
+
+/// r_debugの解析
+pub fn parse_r_debug(data: &[u8]) -> Option<RDebug> {
+    let r_brk = u64::from_le_bytes(data.get(16..24)?.try_into().ok()?);
+    let r_state = u32::from_le_bytes(data.get(24..28)?.try_into().ok()?);
+    Some(RDebug { r_brk, r_state })
+}
+
+/// メモリマップから、マップされている共有ライブラリとベースアドレスの一覧を取得
+pub fn to_libraries(maps: &HashMap<String, Vec<MapInfo>>) -> BTreeSet<(String, u64)> {
+    maps.iter()
+        .filter(|(path, _)| is_library(path))
+        .filter_map(|(path, infos)| {
+            let base = infos
+                .iter()
+                .filter_map(|m| u64::from_str_radix(&m.start_address, 16).ok())
+                .min()?;
+            Some((path.clone(), base))
+        })
+        .collect()
+}
+
+/// アドレスを含む共有ライブラリのパスとベースアドレス(最も小さい開始アドレス)を検索
+pub fn find_library(mappings: &[Mapping], addr: u64) -> Option<(String, u64)> {
+    let path = &mappings
+        .iter()
+        .find(|m| is_library(&m.path) && m.start <= addr && addr < m.end)?
+        .path;
+    let base = mappings
+        .iter()
+        .filter(|m| m.path == *path)
+        .map(|m| m.start)
+        .min()?;
+    Some((path.clone(), base))
+}
+
+/// マップされている領域から、共有ライブラリ毎のパス・先頭・末尾のアドレスを、アドレスの順に取得
+pub fn group_libraries(mappings: &[Mapping]) -> Vec<(String, u64, u64)> {
+    let mut libraries: Vec<(String, u64, u64)> = vec![];
+    for m in mappings.iter().filter(|m| is_library(&m.path)) {
+        match libraries.iter_mut().find(|(p, ..)| *p == m.path) {
+            Some((_, start, end)) => {
+                *start = (*start).min(m.start);
+                *end = (*end).max(m.end);
+            }
+            None => libraries.push((m.path.clone(), m.start, m.end)),
+        }
+    }
+    libraries.sort_by_key(|(_, start, _)| *start);
+    libraries
+}
+
+/// 共有ライブラリのパスか
+fn is_library(path: &str) -> bool {
+    path.starts_with('/') && path.contains(".so")
+}
+
+/// キャッチポイントの種類
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CatchKind {
+    Load,   // ロード
+    Unload, // アンロード
+}
+
+/// キャッチポイント
+pub struct Catchpoint {
+    kind: CatchKind,        // 種類
+    pattern: Option<Regex>, // ライブラリのパスにマッチさせる正規表現(なければ全て)
+}
+
+impl Catchpoint {
+    /// コンストラクタ
+    pub fn new(kind: CatchKind, pattern: Option<&str>) -> Result<Self, regex::Error> {
+        Ok(Catchpoint {
+            kind,
+            pattern: pattern.map(Regex::new).transpose()?,
+        })
+    }
+
+    /// 種類を取得
+    pub fn get_kind(&self) -> CatchKind {
+        self.kind
+    }
+
+    /// ライブラリのパスがマッチするか
+    pub fn is_matched(&self, path: &str) -> bool {
+        self.pattern.as_ref().is_none_or(|p| p.is_match(path))
+    }
+}
+
+impl fmt::Display for Catchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            CatchKind::Load => "load",
+            CatchKind::Unload => "unload",
+        };
+        match &self.pattern {
+            Some(p) => write!(f, "catch {} {}", kind, p),
+            None => write!(f, "catch {}", kind),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_find_r_debug() {
+        let dynamic = |entries: &[(u64, u64)]| {
+            entries
+                .iter()
+                .flat_map(|(t, v)| [t.to_le_bytes(), v.to_le_bytes()].concat())
+                .collect::<Vec<u8>>()
+        };
+        let cases = vec![
+            (
+                vec![(1, 0x10), (DT_DEBUG, 0x7f00_1000), (DT_NULL, 0)],
+                Some(0x7f00_1000),
+            ),
+            // 動的リンカが設定する前
+            (vec![(1, 0x10), (DT_DEBUG, 0), (DT_NULL, 0)], None),
+            // DT_NULL以降は見ない
+            (vec![(1, 0x10), (DT_NULL, 0), (DT_DEBUG, 0x1000)], None),
+            (vec![], None),
+        ];
+        for (entries, expected) in cases {
+            assert_eq!(expected, find_r_debug(&dynamic(&entries)));
+        }
+    }
+
+    #[test]
+    fn test_parse_r_debug() {
+        let mut data = vec![0u8; R_DEBUG_SIZE];
+        data[..4].copy_from_slice(&1u32.to_le_bytes());
+        data[16..24].copy_from_slice(&0x7fff_f7fd_0000u64.to_le_bytes());
+        data[24..28].copy_from_slice(&1u32.to_le_bytes());
+        assert_eq!(
+            Some(RDebug {
+                r_brk: 0x7fff_f7fd_0000,
+                r_state: 1
+            }),
+            parse_r_debug(&data)
+        );
+        assert_eq!(None, parse_r_debug(&data[..20]));
+    }
+
+    #[test]
+    fn test_to_libraries() {
+        let info = |s: &str| MapInfo {
+            start_address: s.to_string(),
+            end_address: "ffffffff".to_string(),
+            permission: "r--p".to_string(),
+        };
+        let mut maps = HashMap::new();
+        maps.insert(
+            "/usr/lib/libc.so.6".to_string(),
+            vec![info("7f0000002000"), info("7f0000000000")],
+        );
+        maps.insert("/tmp/a.out".to_string(), vec![info("555555554000")]);
+        maps.insert("[heap]".to_string(), vec![info("555555560000")]);
+        maps.insert("none".to_string(), vec![info("7f0000100000")]);
+        let expected = vec![("/usr/lib/libc.so.6".to_string(), 0x7f00_0000_0000)]
+            .into_iter()
+            .collect::<BTreeSet<(String, u64)>>();
+        assert_eq!(expected, to_libraries(&maps));
+    }
+
+    #[test]
+    fn test_find_library() {
+        let mapping = |start: u64, end: u64, path: &str| Mapping {
+            start,
+            end,
+            offset: 0,
+            perms: String::new(),
+            path: path.to_string(),
+        };
+        let mappings = vec![
+            mapping(0x5555_5555_4000, 0x5555_5555_6000, "/tmp/a.out"),
+            mapping(0x7f00_0000_0000, 0x7f00_0000_2000, "/usr/lib/libc.so.6"),
+            mapping(0x7f00_0000_2000, 0x7f00_0000_8000, "/usr/lib/libc.so.6"),
+            mapping(0x7f00_0000_8000, 0x7f00_0000_9000, ""),
+        ];
+        let libc = Some(("/usr/lib/libc.so.6".to_string(), 0x7f00_0000_0000));
+        let cases = vec![
+            (0x7f00_0000_0000, libc.clone()),
+            (0x7f00_0000_4321, libc),
+            (0x7f00_0000_8000, None),
+            (0x5555_5555_4000, None),
+            (0x1000, None),
+        ];
+        for (addr, expected) in cases {
+            assert_eq!(expected, find_library(&mappings, addr), "0x{:x}", addr);
+        }
+    }
+
+    #[test]
+    fn test_group_libraries() {
+        let mapping = |start: u64, end: u64, path: &str| Mapping {
+            start,
+            end,
+            offset: 0,
+            perms: String::new(),
+            path: path.to_string(),
+        };
+        let mappings = vec![
+            mapping(0x5555_5555_4000, 0x5555_5555_6000, "/tmp/a.out"),
+            mapping(
+                0x7f00_0010_0000,
+                0x7f00_0010_1000,
+                "/usr/lib/ld-linux-x86-64.so.2",
+            ),
+            mapping(0x7f00_0000_2000, 0x7f00_0000_8000, "/usr/lib/libc.so.6"),
+            mapping(0x7f00_0000_8000, 0x7f00_0000_9000, ""),
+            mapping(0x7f00_0000_0000, 0x7f00_0000_2000, "/usr/lib/libc.so.6"),
+            mapping(0x7f00_0000_9000, 0x7f00_0000_a000, "/usr/lib/libc.so.6"),
+        ];
+        assert_eq!(
+            vec![
+                (
+                    "/usr/lib/libc.so.6".to_string(),
+                    0x7f00_0000_0000,
+                    0x7f00_0000_a000
+                ),
+                (
+                    "/usr/lib/ld-linux-x86-64.so.2".to_string(),
+                    0x7f00_0010_0000,
+                    0x7f00_0010_1000
+                ),
+            ],
+            group_libraries(&mappings)
+        );
+        assert!(group_libraries(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_catchpoint() {
+        let all = Catchpoint::new(CatchKind::Load, None).unwrap();
+        assert!(all.is_matched("/usr/lib/libc.so.6"));
+        assert_eq!("catch load", all.to_string());
+
+        let plugin = Catchpoint::new(CatchKind::Unload, Some("plugin.*\\.so$")).unwrap();
+        assert!(plugin.is_matched("/tmp/libplugin_a.so"));
+        assert!(!plugin.is_matched("/usr/lib/libc.so.6"));
+        assert_eq!(CatchKind::Unload, plugin.get_kind());
+        assert_eq!("catch unload plugin.*\\.so$", plugin.to_string());
+
+        assert!(Catchpoint::new(CatchKind::Load, Some("(")).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+//! ソースコードの表示(list)
+//!
+//! 行番号の前後の行を、指定した行に=>を付けて表示する
+
+use std::io;
+
+/// 前後に表示する行数
+pub const LIST_CONTEXT: u64 = 5;
+
+/// ソースファイルを読み込み、行毎に分割
+pub fn read_source(path: &str) -> io::Result<Vec<String>> {
+    let text = std::fs::read(path)?;
+    Ok(String::from_utf8_lossy(&text)
+        .lines()
+        .map(|l| l.to_string())
+        .collect())
+}
+
+/// lineの前後context行を、行番号付きで文字列化(lineに=>を付ける)
+///
+/// 行番号は1から始まる。ファイルの範囲外の行は含めない
+pub fn format_lines(lines: &[String], line: u64, context: u64) -> Vec<String> {
+    let first = line.saturating_sub(context).max(1);
+    let last = line.saturating_add(context).min(lines.len() as u64);
+    (first..=last)
+        .map(|no| {
+            let mark = if no == line { "=>" } else { "  " };
+            format!("{} {:<4} {}", mark, no, lines[no as usize - 1])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_lines() {
+        let lines = (1..=20)
+            .map(|i| format!("line{}", i))
+            .collect::<Vec<String>>();
+        let cases = vec![
+            (
+                10,
+                2,
+                vec![
+                    "   8    line8",
+                    "   9    line9",
+                    "=> 10   line10",
+                    "   11   line11",
+                    "   12   line12",
+                ],
+            ),
+            // 先頭・末尾で切り詰める
+            (
+                1,
+                2,
+                vec!["=> 1    line1", "   2    line2", "   3    line3"],
+            ),
+            (20, 1, vec!["   19   line19", "=> 20   line20"]),
+            // 範囲外の行は表示しない
+            (30, 5, vec![]),
+            (0, 1, vec!["   1    line1"]),
+        ];
+        for (line, context, expected) in cases {
+            assert_eq!(expected, format_lines(&lines, line, context), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_read_source() {
+        let path = std::env::temp_dir().join(format!("r-debugger-source-{}.c", std::process::id()));
+        std::fs::write(&path, "int main() {\r\n    return 0;\n}\n").unwrap();
+        assert_eq!(
+            vec!["int main() {", "    return 0;", "}"],
+            read_source(path.to_str().unwrap()).unwrap()
+        );
+        std::fs::remove_file(&path).unwrap();
+        assert!(read_source(path.to_str().unwrap()).is_err());
+    }
+}
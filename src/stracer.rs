@@ -1,103 +1,1269 @@
-use nix::sys::ptrace::{getregs, setoptions, syscall, Options};
+use nix::errno::Errno;
+use nix::sys::ptrace::{
+    getevent, getregs, getsiginfo, setoptions, setregs, syscall, Event, Options,
+};
+use nix::sys::signal::{kill, Signal};
 use nix::sys::wait::*;
 use nix::unistd::Pid;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::io::Write;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::color::Colors;
+use crate::duration::parse_duration;
+use crate::fd_table::FdTable;
+use crate::inject::{Injection, Injector};
+use crate::memory::ProcessMemory;
+use crate::path_filter::PathFilter;
+use crate::siginfo::format_siginfo;
+use crate::symbolizer::Symbolizer;
+use crate::syscall_info::{
+    format_args, format_input_args, format_ret, get_args, parse_syscall_set, to_display_name,
+    to_errno, to_signature, ArgKind, FormatOption,
+};
+use crate::syscall_stats::SyscallStats;
+use crate::syscall_stop::{Abi, SyscallReader, SyscallStop};
+
+/// 文字列・バッファの表示バイト数の既定値
+const DEFAULT_STRING_LIMIT: usize = 32;
+
+/// 設定されていれば、PTRACE_GET_SYSCALL_INFOを使用せずレジスタから解析する環境変数
+const NO_SYSCALL_INFO_ENV: &str = "R_DEBUGGER_NO_SYSCALL_INFO";
+
+/// 出力先をフラッシュする間隔
+const FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+
+// トレースオプション
+pub struct TraceOption {
+    string_limit: usize,           // 文字列・バッファの表示バイト数(-s)
+    verbose: bool,                 // 構造体・配列を省略せずに表示する(-v)
+    filter: SyscallFilter,         // トレースするシステムコール(-e trace=)
+    summary: Summary,              // 統計の表示(-c, -C)
+    timestamp: Timestamp,          // 時刻の表示(-t, -tt)
+    duration: bool,                // 所要時間の表示(-T)
+    slower_than: Option<Duration>, // 表示する所要時間の閾値(--slower-than)
+    follow: bool,                  // フォークしたプロセスもトレースする(-f)
+    output: Option<String>,        // 出力先のファイル(-o)
+    fd_path: FdPath,               // ファイルディスクリプタのパスの表示(-y, -yy)
+    paths: Vec<String>,            // トレースするパス(-P)
+    color: ColorMode,              // 色付け(--color)
+    injections: Vec<Injection>,    // 結果を注入するシステムコール(-e inject=)
+    deny: HashSet<i64>,            // 拒否するシステムコール(--deny, --deny-kill)
+    deny_kill: bool,               // 拒否したプロセスを強制終了する(--deny-kill)
+    syscall_info: bool,            // PTRACE_GET_SYSCALL_INFOを使用するか
+}
+
+// 色付け
+#[derive(Debug, PartialEq)]
+enum ColorMode {
+    Never,  // 色付けしない
+    Auto,   // 標準エラー出力が端末の場合のみ色付け(-oの場合はしない)
+    Always, // 常に色付け
+}
+
+// ファイルディスクリプタのパスの表示
+#[derive(Debug, PartialEq)]
+enum FdPath {
+    Off,    // 表示しない
+    Path,   // パスを表示(-y)
+    Socket, // ソケットはエンドポイントも表示(-yy)
+}
+
+// 時刻の表示
+#[derive(Debug, PartialEq)]
+enum Timestamp {
+    Off,     // 表示しない
+    Seconds, // 秒単位(-t)
+    Micros,  // マイクロ秒単位(-tt)
+}
+
+// 統計の表示
+#[derive(Debug, PartialEq)]
+enum Summary {
+    Off,      // 表示しない
+    Only,     // 統計のみ表示(-c)
+    Combined, // システムコール毎の表示に加えて表示(-C)
+}
+
+impl TraceOption {
+    /// コンストラクタ
+    pub fn new() -> Self {
+        TraceOption {
+            string_limit: DEFAULT_STRING_LIMIT,
+            verbose: false,
+            filter: SyscallFilter::All,
+            summary: Summary::Off,
+            timestamp: Timestamp::Off,
+            duration: false,
+            slower_than: None,
+            follow: false,
+            output: None,
+            fd_path: FdPath::Off,
+            paths: vec![],
+            color: ColorMode::Auto,
+            injections: vec![],
+            deny: HashSet::new(),
+            deny_kill: false,
+            syscall_info: env::var_os(NO_SYSCALL_INFO_ENV).is_none(),
+        }
+    }
+
+    /// コマンドライン引数を解析
+    ///
+    /// -s [len]  : 文字列・バッファの表示バイト数
+    /// -v        : 構造体(stat)・配列(execveのargv・envp)を省略せずに表示
+    /// -e [expr] : トレースするシステムコール(trace=name,...、trace=!name,...)
+    ///             結果を注入するシステムコール(inject=name,...:error=ENOENT[:when=N]等)
+    /// -c        : システムコール毎の表示をせず、終了時に統計を表示
+    /// -C        : システムコール毎の表示に加えて、終了時に統計を表示
+    /// -t        : 開始時刻を表示(-ttでマイクロ秒まで表示)
+    /// -T        : 所要時間を表示
+    /// -f        : フォークしたプロセスもトレース
+    /// -o [file] : 出力先のファイル(指定しない場合は標準エラー出力)
+    /// -y        : ファイルディスクリプタのパスを表示(-yyでソケットのエンドポイントも表示)
+    /// -P [path] : 指定したパスを操作するシステムコールのみトレース(複数指定可)
+    /// --color=[never|auto|always] : 色付け(autoは標準エラー出力が端末の場合のみ)
+    /// --slower-than [time]   : 所要時間が指定時間(10ms、1.5s等)以上のシステムコールのみ表示
+    /// --deny [name,...]      : 指定したシステムコールを実行させず、EPERMで失敗させる
+    /// --deny-kill [name,...] : 指定したシステムコールを呼び出したプロセスを強制終了する
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut opt = TraceOption::new();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "-s" => {
+                    let val = iter.next().ok_or("option -s requires a length")?;
+                    opt.string_limit = val
+                        .parse()
+                        .map_err(|_| format!("invalid string limit: {}", val))?;
+                }
+                "-e" => {
+                    let val = iter.next().ok_or("option -e requires an expression")?;
+                    if val.starts_with("inject=") {
+                        opt.injections.push(Injection::parse(val)?);
+                    } else {
+                        opt.filter = SyscallFilter::parse(val)?;
+                    }
+                }
+                "-v" => opt.verbose = true,
+                "-c" => opt.summary = Summary::Only,
+                "-C" => opt.summary = Summary::Combined,
+                "-t" if Timestamp::Off == opt.timestamp => opt.timestamp = Timestamp::Seconds,
+                "-t" | "-tt" => opt.timestamp = Timestamp::Micros,
+                "-T" => opt.duration = true,
+                "-f" => opt.follow = true,
+                "-y" if FdPath::Off == opt.fd_path => opt.fd_path = FdPath::Path,
+                "-y" | "-yy" => opt.fd_path = FdPath::Socket,
+                "-o" => {
+                    let val = iter.next().ok_or("option -o requires a file name")?;
+                    opt.output = Some(val.to_string());
+                }
+                "-P" => {
+                    let val = iter.next().ok_or("option -P requires a path")?;
+                    opt.paths.push(val.to_string());
+                }
+                "--slower-than" => {
+                    let val = iter
+                        .next()
+                        .ok_or("option --slower-than requires a duration")?;
+                    opt.slower_than = Some(parse_duration(val)?);
+                }
+                "--deny" | "--deny-kill" => {
+                    let val = iter
+                        .next()
+                        .ok_or_else(|| format!("option {} requires syscall names", arg))?;
+                    opt.deny.extend(parse_syscall_set(val)?);
+                    opt.deny_kill |= "--deny-kill" == arg;
+                }
+                "--color=never" => opt.color = ColorMode::Never,
+                "--color=auto" => opt.color = ColorMode::Auto,
+                "--color=always" => opt.color = ColorMode::Always,
+                _ if arg.starts_with("--color=") => {
+                    return Err(format!("invalid color mode: {}", &arg["--color=".len()..]))
+                }
+                _ => return Err(format!("unknown option: {}", arg)),
+            }
+        }
+        Ok(opt)
+    }
+
+    /// 出力先のファイルを取得
+    pub fn get_output(&self) -> Option<&String> {
+        self.output.as_ref()
+    }
+
+    /// 引数の整形オプションを取得
+    fn get_format_option(&self) -> FormatOption {
+        FormatOption::new(self.string_limit, self.verbose)
+    }
+
+    /// 色付けするか
+    fn is_colored(&self) -> bool {
+        match self.color {
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                self.output.is_none() && unsafe { 1 == libc::isatty(libc::STDERR_FILENO) }
+            }
+            ColorMode::Always => true,
+        }
+    }
+}
+
+// トレースするシステムコールのフィルタ
+#[derive(Debug, PartialEq)]
+pub enum SyscallFilter {
+    All,                 // 全て
+    Allow(HashSet<i64>), // 指定したシステムコールのみ
+    Deny(HashSet<i64>),  // 指定したシステムコール以外
+}
+
+impl SyscallFilter {
+    /// フィルタ式を解析
+    ///
+    /// trace=name,... もしくは trace=!name,...(否定)
+    /// 名前の代わりに、%file・%desc・%network・%memory・%process・%signalで分類を指定できる
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let set = expr
+            .strip_prefix("trace=")
+            .ok_or_else(|| format!("invalid filter expression: {}", expr))?;
+        let (deny, set) = match set.strip_prefix('!') {
+            Some(s) => (true, s),
+            None => (false, set),
+        };
+
+        let nos = parse_syscall_set(set)?;
+        Ok(if deny {
+            SyscallFilter::Deny(nos)
+        } else {
+            SyscallFilter::Allow(nos)
+        })
+    }
+
+    /// トレース対象のシステムコールか
+    pub fn is_traced(&self, no: i64) -> bool {
+        match self {
+            SyscallFilter::All => true,
+            SyscallFilter::Allow(nos) => nos.contains(&no),
+            SyscallFilter::Deny(nos) => !nos.contains(&no),
+        }
+    }
+}
+
+// 実行中のシステムコール
+struct SyscallEntry {
+    regs: libc::user_regs_struct, // 開始時のレジスタ
+    traced: bool,                 // トレース対象か(開始時に判定)
+    matched: bool,                // パスによるフィルタ(-P)の対象か
+    start: Instant,               // 開始時刻(所要時間の計測用)
+    time: SystemTime,             // 開始時刻(表示用)
+    inputs: Vec<Option<String>>,  // 開始時に整形した引数
+    injected: Option<u64>,        // 注入する戻り値(-e inject=, --deny)
+    denied: bool,                 // 拒否したか(--deny)
+    abi: Abi,                     // 呼び出し元のABI
+}
+
+impl SyscallEntry {
+    /// コンストラクタ
+    fn new(regs: libc::user_regs_struct, abi: Abi, traced: bool) -> Self {
+        SyscallEntry {
+            regs,
+            traced,
+            matched: false,
+            start: Instant::now(),
+            time: SystemTime::now(),
+            inputs: vec![],
+            injected: None,
+            denied: false,
+            abi,
+        }
+    }
+}
 
 // システムコールトレーサー
-pub struct Tracer {
-    pid: Pid,
+pub struct Tracer<W: Write> {
+    opt: TraceOption,
+    out: W,                                      // トレース結果の出力先
+    last_flush: Instant,                         // 最後に出力先をフラッシュした時刻
+    entries: HashMap<Pid, Option<SyscallEntry>>, // トレース中のプロセスと、実行中のシステムコール
+    fd_tables: HashMap<Pid, FdTable>,            // プロセス毎のファイルディスクリプタのパス(-y)
+    path_filter: PathFilter,                     // パスによるフィルタ(-P)
+    colors: Colors,                              // 色付け(--color)
+    injector: Injector,                          // 結果の注入(-e inject=)
+    denials: HashMap<i64, u64>,                  // システムコール毎の拒否した回数(--deny)
+    reader: SyscallReader,                       // システムコール停止の読み込み
+    symbolizer: Symbolizer,                      // 呼び出し元アドレスのシンボル化
+    started: HashSet<Pid>,                       // 最初の停止を処理したプロセス
+    exits: Vec<WaitStatus>,                      // 終了したプロセスの終了ステータス
+    stats: SyscallStats,
 }
 
 /// strace実装
-impl Tracer {
+impl<W: Write> Tracer<W> {
     /// コンストラクタ
-    pub fn new(target_pid: Pid) -> Self {
-        Tracer { pid: target_pid }
+    pub fn new(target_pid: Pid, opt: TraceOption, out: W) -> Self {
+        let mut entries = HashMap::new();
+        entries.insert(target_pid, None);
+        let path_filter = PathFilter::new(&opt.paths);
+        let colors = Colors::new(opt.is_colored());
+        let injector = Injector::new(&opt.injections);
+        let reader = SyscallReader::new(opt.syscall_info);
+        Tracer {
+            opt,
+            out,
+            last_flush: Instant::now(),
+            entries,
+            fd_tables: HashMap::new(),
+            path_filter,
+            colors,
+            injector,
+            denials: HashMap::new(),
+            reader,
+            symbolizer: Symbolizer::new(),
+            started: HashSet::new(),
+            exits: vec![],
+            stats: SyscallStats::new(),
+        }
     }
 
     /// システムコールトレース
-    pub fn start(&self) {
-        println!("start stracer({})", self.pid);
-
+    ///
+    /// トレース中の全プロセスが終了するまで続ける
+    pub fn start(&mut self) {
         // 子プロセスWait
-        loop {
-            match nix::sys::wait::waitpid(self.pid, None).expect("wait child process failed") {
+        while !self.entries.is_empty() {
+            let status = match waitpid(None, Some(WaitPidFlag::__WALL)) {
+                Ok(status) => status,
+                Err(Errno::ECHILD) => break,
+                Err(e) => panic!("wait child process failed: {}", e),
+            };
+            match status {
                 // 子プロセスからのシグナル待ち
                 WaitStatus::Exited(pid, status) => {
-                    println!(
-                        "[trace_syscall] exit child process: pid={:?}, status={:?}",
-                        pid, status
-                    );
-                    break;
+                    // pidは終了前のトレース数で判定するため、先に整形する
+                    let prefix = self.format_prefix(pid, SystemTime::now());
+                    self.exit(WaitStatus::Exited(pid, status));
+                    if self.is_show_calls() {
+                        let line = format!("+++ exited with {} +++", status);
+                        self.write_line(format!("{}{}", prefix, line));
+                    }
                 }
                 WaitStatus::PtraceSyscall(pid) => {
                     // syscall分析
-                    self.analysis_syscall();
+                    self.analysis_syscall(pid);
 
-                    // プロセス再開
-                    syscall(pid, None).expect("failed syscall");
+                    // プロセス再開(強制終了したプロセスは再開できない)
+                    match syscall(pid, None) {
+                        Ok(_) | Err(Errno::ESRCH) => {}
+                        Err(e) => panic!("failed syscall: {}", e),
+                    }
                 }
-                WaitStatus::Stopped(pid, status) => {
-                    // PTRACE_TRACESYSGOODを設定し、SIGTRAPと区別する
-                    println!(
-                        "[trace_syscall] stopped : pid={:?}, status={:?}",
-                        pid, status
-                    );
-                    setoptions(pid, Options::PTRACE_O_TRACESYSGOOD).expect("failed setoptions");
+                WaitStatus::Stopped(pid, _) if self.started.insert(pid) => {
+                    // 最初の停止で、PTRACE_TRACESYSGOODを設定し、SIGTRAPと区別する
+                    // (フォークしたプロセスは、イベントより先に停止を受け取る場合がある)
+                    self.entries.entry(pid).or_insert(None);
+                    setoptions(pid, self.get_options()).expect("failed setoptions");
                     syscall(pid, None).expect("failed syscall");
                 }
-                WaitStatus::Signaled(pid, sig, _) => {
-                    println!("[trace_syscall] recv signal : pid={:?}, sig={:?}", pid, sig)
+                WaitStatus::Stopped(pid, sig) => {
+                    // シグナル受信を表示し、シグナルを渡して再開する
+                    // (siginfoを取得できない場合は、グループストップのため渡さない)
+                    let (line, sig) = match getsiginfo(pid) {
+                        Ok(info) => (format_siginfo(&info), Some(sig)),
+                        Err(_) => (format!("--- stopped by {} ---", sig.as_str()), None),
+                    };
+                    if self.is_show_calls() {
+                        let prefix = self.format_prefix(pid, SystemTime::now());
+                        let line = self.colors.signal(&line);
+                        self.write_line(format!("{}{}", prefix, line));
+                    }
+                    syscall(pid, sig).expect("failed syscall");
+                }
+                WaitStatus::Signaled(pid, sig, core) => {
+                    let prefix = self.format_prefix(pid, SystemTime::now());
+                    self.exit(WaitStatus::Signaled(pid, sig, core));
+                    let core = if core { " (core dumped)" } else { "" };
+                    let line = format!("+++ killed by {}{} +++", sig.as_str(), core);
+                    let line = self.colors.signal(&line);
+                    self.write_line(format!("{}{}", prefix, line));
                 }
-                WaitStatus::PtraceEvent(pid, sig, _) => {
-                    println!("[trace_syscall] ptrace event: pid={:?}, sig={:?}", pid, sig)
+                WaitStatus::PtraceEvent(pid, _, event) => {
+                    if self.is_fork_event(event) {
+                        // フォークしたプロセスを、トレース対象に追加
+                        let child = Pid::from_raw(getevent(pid).expect("failed getevent") as i32);
+                        self.entries.entry(child).or_insert(None);
+                        self.path_filter.fork(pid, child);
+                    } else if event == Event::PTRACE_EVENT_EXEC as i32 {
+                        // 新しい実行ファイルでは、マップされている領域が変わる
+                        self.symbolizer.remove(pid);
+                    }
+                    syscall(pid, None).expect("failed syscall");
                 }
-                WaitStatus::Continued(pid) => println!("[trace_syscall] continued : pid={:?}", pid),
-                WaitStatus::StillAlive => println!("[trace_syscall] Still Alive"),
+                WaitStatus::Continued(_) | WaitStatus::StillAlive => {}
+            }
+        }
+        if !self.opt.deny.is_empty() {
+            self.write_line(format_denials(&self.denials));
+        }
+        if Summary::Off != self.opt.summary {
+            self.stats.show(&mut self.out).expect("failed show summary");
+        }
+        self.out.flush().expect("failed flush");
+    }
+
+    /// 1行出力
+    ///
+    /// 異常終了時に失われないよう、一定間隔でフラッシュする
+    fn write_line(&mut self, line: String) {
+        writeln!(self.out, "{}", line).expect("failed write");
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.out.flush().expect("failed flush");
+            self.last_flush = Instant::now();
+        }
+    }
+
+    /// 終了したプロセスの終了ステータスを取得
+    #[allow(dead_code)]
+    pub fn get_exits(&self) -> &Vec<WaitStatus> {
+        &self.exits
+    }
+
+    /// ptraceオプションを取得(-fの場合はフォークしたプロセスもトレースする)
+    ///
+    /// スレッドは常にトレースする(CLONE_THREADのcloneはPTRACE_EVENT_CLONEで通知される)
+    fn get_options(&self) -> Options {
+        // execveの完了は、SIGTRAPではなくイベントで通知させる
+        let options = Options::PTRACE_O_TRACESYSGOOD
+            | Options::PTRACE_O_TRACEEXEC
+            | Options::PTRACE_O_TRACECLONE;
+        if self.opt.follow {
+            options | Options::PTRACE_O_TRACEFORK | Options::PTRACE_O_TRACEVFORK
+        } else {
+            options
+        }
+    }
+
+    /// フォークのイベントか
+    fn is_fork_event(&self, event: i32) -> bool {
+        event == Event::PTRACE_EVENT_FORK as i32
+            || event == Event::PTRACE_EVENT_VFORK as i32
+            || event == Event::PTRACE_EVENT_CLONE as i32
+    }
+
+    /// プロセス終了
+    fn exit(&mut self, status: WaitStatus) {
+        let pid = status.pid().expect("no pid");
+        self.fd_tables.remove(&pid);
+        self.path_filter.remove(pid);
+        self.symbolizer.remove(pid);
+
+        // 戻らなかったシステムコール(exit_group等)を表示
+        // (pidの表示を判定するため、トレース対象から外す前に表示する)
+        let entry = self.entries.get_mut(&pid).and_then(Option::take);
+        if let Some(entry) = entry.filter(|e| e.traced) {
+            // 閾値(--slower-than)は、プロセスが終了するまでの時間で判定する
+            let slow = self
+                .opt
+                .slower_than
+                .is_none_or(|t| t <= entry.start.elapsed());
+            if self.is_show_calls() && slow {
+                let denied = if entry.denied { " (DENIED)" } else { "" };
+                let rip = self.symbolizer.symbolize(pid, entry.regs.rip);
+                self.write_line(format!(
+                    "{}{} = ?{}",
+                    self.format_prefix(pid, entry.time),
+                    self.format_call(pid, &entry, &rip, None, None),
+                    denied
+                ));
             }
         }
+        self.entries.remove(&pid);
+        self.exits.push(status);
     }
 
     /// syscall解析
-    fn analysis_syscall(&self) {
-        // レジスタ出力
-        let regs = getregs(self.pid).expect("failed getregs");
-        println!(
-            "[0x{:x}] {} (rsp=0x{:x} rax=0x{:x} rcx=0x{:x})",
-            regs.rip,
-            self.to_syscall(regs.orig_rax as i64),
-            regs.rsp,
-            regs.rax,
-            regs.rcx
+    ///
+    /// 開始時の情報をプロセス毎に保持し、終了時に引数と戻り値をまとめて表示する
+    fn analysis_syscall(&mut self, pid: Pid) {
+        let in_syscall = matches!(self.entries.get(&pid), Some(Some(_)));
+        match self
+            .reader
+            .read(pid, in_syscall)
+            .expect("failed read syscall")
+        {
+            SyscallStop::Entry(regs, abi) => self.enter_syscall(pid, regs, abi),
+            SyscallStop::Exit(ret) => {
+                // 開始を受け取っていない終了は無視する
+                if let Some(entry) = self.entries.get_mut(&pid).and_then(|e| e.take()) {
+                    self.exit_syscall(pid, entry, ret);
+                }
+            }
+        }
+    }
+
+    /// システムコール開始
+    ///
+    /// 終了時の表示も揃うよう、開始時にトレース対象か判定する
+    fn enter_syscall(&mut self, pid: Pid, regs: libc::user_regs_struct, abi: Abi) {
+        let no = regs.orig_rax as i64;
+        if Abi::X86_64 != abi {
+            // x86-64以外のシステムコールNoは解析できないため、フィルタ・注入の対象外とする
+            let traced = SyscallFilter::All == self.opt.filter && !self.path_filter.is_enabled();
+            self.entries
+                .insert(pid, Some(SyscallEntry::new(regs, abi, traced)));
+            return;
+        }
+
+        // (-Pの対象は、-eの対象外でもファイルディスクリプタの追跡に使う)
+        let memory = ProcessMemory::new(pid);
+        let matched = self.path_filter.is_matched(pid, &regs, &memory);
+        let traced = matched && self.opt.filter.is_traced(no);
+        let mut entry = SyscallEntry::new(regs, abi, traced);
+        entry.matched = matched;
+        if traced && self.is_show_calls() {
+            entry.inputs = format_input_args(no, &regs, &memory, self.opt.get_format_option());
+        }
+
+        // 閉じるファイルディスクリプタは、終了後に読み込めないため先に読み込む
+        if traced && libc::SYS_close == no {
+            if let Some(mut fds) = self.take_fd_table(pid) {
+                fds.get_path(regs.rdi as i32);
+                self.fd_tables.insert(pid, fds);
+            }
+        }
+        // 注入・拒否する場合は、無効なシステムコールNoへ書き換えてカーネルに実行させない
+        entry.denied = self.opt.deny.contains(&no);
+        entry.injected = if entry.denied {
+            *self.denials.entry(no).or_insert(0) += 1;
+            Some(-(libc::EPERM as i64) as u64)
+        } else {
+            self.injector.at_entry(no)
+        };
+        if entry.injected.is_some() {
+            let mut skipped = getregs(pid).expect("failed getregs");
+            skipped.orig_rax = u64::MAX;
+            setregs(pid, skipped).expect("failed setregs");
+        }
+        if entry.denied && self.opt.deny_kill {
+            kill(pid, Signal::SIGKILL).expect("failed kill");
+        }
+        self.entries.insert(pid, Some(entry));
+    }
+
+    /// システムコール終了
+    ///
+    /// retは戻り値(注入する場合は書き換える)
+    fn exit_syscall(&mut self, pid: Pid, entry: SyscallEntry, ret: u64) {
+        let no = entry.regs.orig_rax as i64;
+        let native = Abi::X86_64 == entry.abi;
+        let elapsed = entry.start.elapsed();
+        let ret = match entry.injected {
+            Some(injected) => {
+                let mut regs = getregs(pid).expect("failed getregs");
+                regs.rax = injected;
+                setregs(pid, regs).expect("failed setregs");
+                injected
+            }
+            None => ret,
+        };
+        if entry.matched {
+            self.path_filter.update(pid, &entry.regs, ret);
+        }
+        let mut fds = if native {
+            self.take_fd_table(pid)
+        } else {
+            None
+        };
+        if let Some(t) = fds.as_mut() {
+            invalidate_new_fd(t, no, ret);
+        }
+        if entry.traced && native {
+            self.stats.add(no, elapsed, to_errno(ret).is_some());
+        }
+        // 閾値より速いシステムコールは表示しない(統計・ファイルディスクリプタの追跡は行う)
+        let slow = self.opt.slower_than.is_none_or(|t| t <= elapsed);
+        if entry.traced && self.is_show_calls() && slow {
+            let duration = if self.opt.slower_than.is_some() {
+                format!(" {}", self.colors.slow(&format_duration(elapsed)))
+            } else if self.opt.duration {
+                format!(" {}", format_duration(elapsed))
+            } else {
+                String::new()
+            };
+            let injected = if entry.denied {
+                " (DENIED)"
+            } else if entry.injected.is_some() {
+                " (INJECTED)"
+            } else {
+                ""
+            };
+            let rip = self.symbolizer.symbolize(pid, entry.regs.rip);
+            let call = self.format_call(pid, &entry, &rip, Some(ret), fds.as_mut());
+            // x86-64以外は、シグネチャが不明なシステムコールとして整形する
+            let ret_no = if native { no } else { -1 };
+            let ret = self.colors.ret(
+                &format_ret(ret_no, ret, fds.as_mut()),
+                to_errno(ret).is_some(),
+            );
+            self.write_line(format!(
+                "{}{} = {}{}{}",
+                self.format_prefix(pid, entry.time),
+                call,
+                ret,
+                injected,
+                duration
+            ));
+        }
+        if let Some(mut t) = fds {
+            invalidate_closed_fd(&mut t, no, &entry.regs, ret);
+            self.fd_tables.insert(pid, t);
+        }
+    }
+
+    /// プロセスのファイルディスクリプタのパスを取り出す(-yでなければNone)
+    ///
+    /// 使用後はfd_tablesへ戻す
+    fn take_fd_table(&mut self, pid: Pid) -> Option<FdTable> {
+        let detail = match self.opt.fd_path {
+            FdPath::Off => return None,
+            FdPath::Path => false,
+            FdPath::Socket => true,
+        };
+        Some(
+            self.fd_tables
+                .remove(&pid)
+                .unwrap_or_else(|| FdTable::new(pid, detail)),
+        )
+    }
+
+    /// 行頭に表示するpid・時刻を整形
+    ///
+    /// pidは複数のプロセス・スレッドをトレース中の場合のみ表示する
+    fn format_prefix(&self, pid: Pid, time: SystemTime) -> String {
+        let pid = if 1 < self.entries.len() {
+            format!("[pid {:>6}] ", pid)
+        } else {
+            String::new()
+        };
+        let precise = match self.opt.timestamp {
+            Timestamp::Off => return pid,
+            Timestamp::Seconds => false,
+            Timestamp::Micros => true,
+        };
+        let (secs, micros) = to_local_time(time);
+        format!("{}{} ", pid, format_time_of_day(secs, micros, precise))
+    }
+
+    /// システムコール毎に表示するか
+    fn is_show_calls(&self) -> bool {
+        Summary::Only != self.opt.summary
+    }
+
+    /// システムコール呼び出しを整形
+    ///
+    /// ripは呼び出し元アドレスをシンボル化したもの、retは戻り値(終了していないシステムコールはNone)
+    fn format_call(
+        &self,
+        pid: Pid,
+        entry: &SyscallEntry,
+        rip: &str,
+        ret: Option<u64>,
+        fds: Option<&mut FdTable>,
+    ) -> String {
+        let regs = &entry.regs;
+        let no = regs.orig_rax as i64;
+        if Abi::X86_64 != entry.abi {
+            // x86-64以外は、ABIとシステムコールNo・6つの引数を表示する
+            let args = get_args(regs)
+                .iter()
+                .map(|a| format!("0x{:x}", a))
+                .collect::<Vec<String>>();
+            return format!(
+                "[{}] {:?}:syscall_{}({})",
+                rip,
+                entry.abi,
+                no,
+                args.join(", ")
+            );
+        }
+        let memory = ProcessMemory::new(pid);
+        let opt = self.opt.get_format_option();
+        let args = format_args(no, regs, ret, &entry.inputs, &memory, opt, fds);
+        format!(
+            "[{}] {}({})",
+            rip,
+            self.colors.name(no, &to_display_name(no)),
+            self.colors.args(&args)
+        )
+    }
+}
+
+/// 新しいファイルディスクリプタを返したシステムコールは、そのキャッシュを破棄する
+///
+/// 戻り値を表示する前に呼び出す
+fn invalidate_new_fd(fds: &mut FdTable, no: i64, ret: u64) {
+    let returns_fd = to_signature(no).is_some_and(|s| ArgKind::Fd == s.get_ret());
+    if returns_fd && to_errno(ret).is_none() {
+        fds.invalidate(ret as i32);
+    }
+}
+
+/// 閉じた・置き換わったファイルディスクリプタのキャッシュを破棄する
+///
+/// 表示した後に呼び出す
+fn invalidate_closed_fd(fds: &mut FdTable, no: i64, regs: &libc::user_regs_struct, ret: u64) {
+    if to_errno(ret).is_some() {
+        return;
+    }
+    match no {
+        libc::SYS_close => fds.invalidate(get_args(regs)[0] as i32),
+        // 複数のファイルディスクリプタが変わる
+        libc::SYS_close_range
+        | libc::SYS_execve
+        | libc::SYS_execveat
+        | libc::SYS_pipe
+        | libc::SYS_pipe2
+        | libc::SYS_socketpair => fds.clear(),
+        _ => {}
+    }
+}
+
+/// 拒否したシステムコールの一覧を整形(+++ denied syscalls: connect(2), execve(1) +++)
+fn format_denials(denials: &HashMap<i64, u64>) -> String {
+    let mut names = denials
+        .iter()
+        .map(|(no, count)| format!("{}({})", to_display_name(*no), count))
+        .collect::<Vec<String>>();
+    names.sort();
+    if names.is_empty() {
+        names.push("none".to_string());
+    }
+    format!("+++ denied syscalls: {} +++", names.join(", "))
+}
+
+/// 時刻→ローカルタイムの(当日0時からの秒数, マイクロ秒)
+fn to_local_time(time: SystemTime) -> (u64, u32) {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs() as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe { libc::localtime_r(&secs, &mut tm) };
+    let secs_of_day = tm.tm_hour as u64 * 3600 + tm.tm_min as u64 * 60 + tm.tm_sec as u64;
+    (secs_of_day, since_epoch.subsec_micros())
+}
+
+/// 時刻を整形(HH:MM:SS、preciseの場合はHH:MM:SS.uuuuuu)
+fn format_time_of_day(secs: u64, micros: u32, precise: bool) -> String {
+    let hms = format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    );
+    if precise {
+        format!("{}.{:06}", hms, micros)
+    } else {
+        hms
+    }
+}
+
+/// 所要時間を整形(<秒.マイクロ秒>)
+fn format_duration(duration: Duration) -> String {
+    format!("<{}.{:06}>", duration.as_secs(), duration.subsec_micros())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::FORK_LOCK;
+
+    #[test]
+    fn test_parse_filter() {
+        let set = |nos: &[i64]| nos.iter().copied().collect::<HashSet<i64>>();
+        let cases = vec![
+            (
+                "trace=openat,read,write",
+                Ok(SyscallFilter::Allow(set(&[
+                    libc::SYS_openat,
+                    libc::SYS_read,
+                    libc::SYS_write,
+                ]))),
+            ),
+            (
+                "trace=!futex",
+                Ok(SyscallFilter::Deny(set(&[libc::SYS_futex]))),
+            ),
+            (
+                "trace=opnat",
+                Err("unknown syscall: opnat (did you mean openat, openat2?)".to_string()),
+            ),
+            (
+                "trace=xxxxxxxx",
+                Err("unknown syscall: xxxxxxxx".to_string()),
+            ),
+            ("trace=", Err("unknown syscall: ".to_string())),
+            ("read", Err("invalid filter expression: read".to_string())),
+            ("trace=%ipc", Err("unknown syscall class: %ipc".to_string())),
+        ];
+        for (expr, expected) in cases {
+            assert_eq!(expected, SyscallFilter::parse(expr));
+        }
+    }
+
+    #[test]
+    fn test_is_traced() {
+        let allow = SyscallFilter::parse("trace=read").unwrap();
+        assert!(allow.is_traced(libc::SYS_read));
+        assert!(!allow.is_traced(libc::SYS_write));
+
+        let deny = SyscallFilter::parse("trace=!read").unwrap();
+        assert!(!deny.is_traced(libc::SYS_read));
+        assert!(deny.is_traced(libc::SYS_write));
+
+        assert!(SyscallFilter::All.is_traced(libc::SYS_read));
+
+        // 分類と名前を組み合わせる
+        let class = SyscallFilter::parse("trace=%network,read").unwrap();
+        assert!(class.is_traced(libc::SYS_connect));
+        assert!(class.is_traced(libc::SYS_read));
+        assert!(!class.is_traced(libc::SYS_openat));
+
+        // 分類の否定
+        let deny = SyscallFilter::parse("trace=!%memory,%signal").unwrap();
+        assert!(!deny.is_traced(libc::SYS_mmap));
+        assert!(!deny.is_traced(libc::SYS_rt_sigprocmask));
+        assert!(deny.is_traced(libc::SYS_openat));
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!("00:00:00", format_time_of_day(0, 0, false));
+        assert_eq!("13:05:09", format_time_of_day(47109, 12, false));
+        assert_eq!("13:05:09.000012", format_time_of_day(47109, 12, true));
+        assert_eq!("23:59:59.999999", format_time_of_day(86399, 999_999, true));
+
+        assert_eq!("<0.000123>", format_duration(Duration::from_nanos(123_456)));
+        assert_eq!("<1.500000>", format_duration(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn test_format_denials() {
+        let mut denials = HashMap::new();
+        assert_eq!("+++ denied syscalls: none +++", format_denials(&denials));
+        denials.insert(libc::SYS_execve, 1);
+        denials.insert(libc::SYS_connect, 2);
+        assert_eq!(
+            "+++ denied syscalls: connect(2), execve(1) +++",
+            format_denials(&denials)
         );
     }
 
-    /// システムコールNo→システムコール
-    fn to_syscall(&self, no: i64) -> &'static str {
-        match no {
-            libc::SYS_read => "read",
-            libc::SYS_write => "write",
-            libc::SYS_open => "open",
-            libc::SYS_close => "close",
-            libc::SYS_stat => "stat",
-            libc::SYS_fstat => "fstat",
-            libc::SYS_mmap => "mmap",
-            libc::SYS_munmap => "munmap",
-            libc::SYS_brk => "brk",
-            libc::SYS_pread64 => "pread",
-            libc::SYS_pwrite64 => "pwrite",
-            libc::SYS_readv => "readv",
-            libc::SYS_writev => "writev",
-            libc::SYS_access => "access",
-            libc::SYS_preadv => "preadv",
-            libc::SYS_pwritev => "pwritev",
-            libc::SYS_mprotect => "mprotect",
-            libc::SYS_arch_prctl => "arch_prctl",
-            libc::SYS_exit => "exit",
-            libc::SYS_exit_group => "exit_group",
-            libc::SYS_openat => "openat",
-            libc::SYS_clock_nanosleep => "clock_nanosleep",
-            libc::SYS_nanosleep => "nanosleep",
-            _ => "unknown system call",
+    #[test]
+    fn test_follow_fork() {
+        let _lock = FORK_LOCK.lock().unwrap();
+        use nix::sys::ptrace::traceme;
+        use nix::sys::signal::{raise, Signal};
+        use nix::unistd::{fork, ForkResult};
+
+        // 2回フォークし、各プロセスが異なるステータスで終了する
+        // (フォーク後の子プロセスでは、メモリ確保をしない)
+        match unsafe { fork() }.expect("failed fork") {
+            ForkResult::Child => unsafe {
+                traceme().expect("failed traceme");
+                raise(Signal::SIGSTOP).expect("failed raise");
+                for status in &[3, 4] {
+                    if let Ok(ForkResult::Child) = fork() {
+                        libc::_exit(*status);
+                    }
+                }
+                while wait().is_ok() {}
+                libc::_exit(0);
+            },
+            ForkResult::Parent { child } => {
+                let opt = TraceOption::parse(&["-f".to_string(), "-c".to_string()]).unwrap();
+                let mut tracer = Tracer::new(child, opt, vec![]);
+                tracer.start();
+
+                let exits = tracer.get_exits();
+                assert_eq!(3, exits.len());
+                assert_eq!(Some(&WaitStatus::Exited(child, 0)), exits.last());
+                let mut statuses = exits
+                    .iter()
+                    .filter_map(|s| match s {
+                        WaitStatus::Exited(pid, status) if *pid != child => Some(*status),
+                        _ => None,
+                    })
+                    .collect::<Vec<i32>>();
+                statuses.sort_unstable();
+                assert_eq!(vec![3, 4], statuses);
+
+                // -cの場合は、統計のみ出力する
+                let out = String::from_utf8(tracer.out).unwrap();
+                assert!(out.contains("% time     seconds"));
+                assert!(!out.contains("[pid "));
+            }
+        }
+    }
+
+    /// スレッドから/dev/nullへ交互に書き込む
+    extern "C" fn write_null(arg: *mut libc::c_void) -> *mut libc::c_void {
+        let fd = arg as usize as i32;
+        for _ in 0..3 {
+            unsafe {
+                libc::write(fd, b"x".as_ptr() as *const libc::c_void, 1);
+                libc::sched_yield();
+            }
+        }
+        std::ptr::null_mut()
+    }
+
+    #[test]
+    fn test_threads() {
+        let _lock = FORK_LOCK.lock().unwrap();
+        use nix::sys::ptrace::traceme;
+        use nix::sys::signal::{raise, Signal};
+        use nix::unistd::{fork, ForkResult};
+
+        // 2スレッドを生成し、各スレッドが書き込む
+        // (-fなしでもスレッドはトレースする)
+        match unsafe { fork() }.expect("failed fork") {
+            ForkResult::Child => unsafe {
+                traceme().expect("failed traceme");
+                raise(Signal::SIGSTOP).expect("failed raise");
+                let fd = libc::open(
+                    b"/dev/null\0".as_ptr() as *const libc::c_char,
+                    libc::O_WRONLY,
+                );
+                let mut threads = [0 as libc::pthread_t; 2];
+                for t in threads.iter_mut() {
+                    let arg = fd as usize as *mut libc::c_void;
+                    libc::pthread_create(t, std::ptr::null(), write_null, arg);
+                }
+                for t in &threads {
+                    libc::pthread_join(*t, std::ptr::null_mut());
+                }
+                libc::_exit(0);
+            },
+            ForkResult::Parent { child } => {
+                let opt = TraceOption::parse(&["--color=never".to_string()]).unwrap();
+                let mut tracer = Tracer::new(child, opt, vec![]);
+                tracer.start();
+
+                // 全スレッドの終了を待つ
+                let exits = tracer.get_exits();
+                assert_eq!(3, exits.len());
+                assert_eq!(Some(&WaitStatus::Exited(child, 0)), exits.last());
+
+                // 書き込みは、スレッド毎にpidを付けて表示する
+                let out = String::from_utf8(tracer.out).unwrap();
+                let writers = out
+                    .lines()
+                    .filter(|l| l.contains("write(") && l.ends_with(" = 1"))
+                    .map(|l| l.split(']').next().unwrap().to_string())
+                    .collect::<Vec<String>>();
+                assert_eq!(6, writers.len());
+                assert!(writers.iter().all(|w| w.starts_with("[pid ")));
+                let tids = writers.iter().collect::<HashSet<&String>>();
+                assert_eq!(2, tids.len());
+                assert!(!tids.contains(&format!("[pid {:>6}", child)));
+                assert_eq!(3, out.matches("+++ exited with 0 +++").count());
+            }
+        }
+    }
+
+    #[test]
+    fn test_slower_than() {
+        let _lock = FORK_LOCK.lock().unwrap();
+        use nix::sys::ptrace::traceme;
+        use nix::sys::signal::{raise, Signal};
+        use nix::unistd::{fork, ForkResult};
+
+        // 20msのsleepのみ表示し、統計は全てのシステムコールを数える
+        match unsafe { fork() }.expect("failed fork") {
+            ForkResult::Child => unsafe {
+                traceme().expect("failed traceme");
+                raise(Signal::SIGSTOP).expect("failed raise");
+                libc::syscall(libc::SYS_getppid);
+                let ts = libc::timespec {
+                    tv_sec: 0,
+                    tv_nsec: 20_000_000,
+                };
+                libc::syscall(
+                    libc::SYS_nanosleep,
+                    &ts,
+                    std::ptr::null_mut::<libc::timespec>(),
+                );
+                libc::_exit(0);
+            },
+            ForkResult::Parent { child } => {
+                let args = ["--color=never", "--slower-than", "10ms", "-C"];
+                let args = args.iter().map(|a| a.to_string()).collect::<Vec<String>>();
+                let opt = TraceOption::parse(&args).unwrap();
+                let mut tracer = Tracer::new(child, opt, vec![]);
+                tracer.start();
+
+                let out = String::from_utf8(tracer.out).unwrap();
+                let (calls, summary) = out.split_at(out.find("% time").unwrap());
+                let calls = calls
+                    .lines()
+                    .filter(|l| l.starts_with('['))
+                    .collect::<Vec<&str>>();
+                assert_eq!(1, calls.len(), "{:?}", calls);
+                assert!(
+                    calls[0].contains("] nanosleep({tv_sec=0, tv_nsec=20000000}, NULL) = 0 <0.")
+                );
+                assert!(summary.contains("getppid"));
+                assert!(summary.contains("nanosleep"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_signal_delivery() {
+        let _lock = FORK_LOCK.lock().unwrap();
+        use nix::sys::ptrace::traceme;
+        use nix::sys::signal::{raise, Signal};
+        use nix::unistd::{fork, ForkResult};
+
+        // 受信したシグナルを渡すため、既定の動作(終了)となる
+        match unsafe { fork() }.expect("failed fork") {
+            ForkResult::Child => unsafe {
+                traceme().expect("failed traceme");
+                raise(Signal::SIGSTOP).expect("failed raise");
+                let _ = raise(Signal::SIGUSR1);
+                libc::_exit(0);
+            },
+            ForkResult::Parent { child } => {
+                let opt = TraceOption::parse(&["--color=never".to_string()]).unwrap();
+                let mut tracer = Tracer::new(child, opt, vec![]);
+                tracer.start();
+
+                assert_eq!(
+                    &vec![WaitStatus::Signaled(child, Signal::SIGUSR1, false)],
+                    tracer.get_exits()
+                );
+                let out = String::from_utf8(tracer.out).unwrap();
+                assert!(out.contains(&format!(
+                    "--- SIGUSR1 {{si_signo=SIGUSR1, si_code=SI_TKILL, si_pid={}, si_uid=",
+                    child
+                )));
+                assert!(out.ends_with("+++ killed by SIGUSR1 +++\n"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_output_lines() {
+        let _lock = FORK_LOCK.lock().unwrap();
+        use nix::sys::ptrace::traceme;
+        use nix::sys::signal::{kill, Signal};
+        use nix::unistd::{fork, getpid, ForkResult};
+
+        // トレース結果のみを出力する(トレーサー自身の動作は出力しない)
+        // (システムコールの行は、呼び出し元アドレスを除いて比較する)
+        match unsafe { fork() }.expect("failed fork") {
+            ForkResult::Child => unsafe {
+                traceme().expect("failed traceme");
+                kill(getpid(), Signal::SIGSTOP).expect("failed kill");
+                libc::syscall(libc::SYS_getppid);
+                libc::syscall(libc::SYS_exit_group, 3);
+                libc::_exit(0);
+            },
+            ForkResult::Parent { child } => {
+                let opt = TraceOption::parse(&["--color=never".to_string()]).unwrap();
+                let mut tracer = Tracer::new(child, opt, vec![]);
+                tracer.start();
+
+                let out = String::from_utf8(tracer.out).unwrap();
+                assert_eq!(
+                    vec![
+                        format!("getppid() = {}", getpid()),
+                        "exit_group(3) = ?".to_string(),
+                        "+++ exited with 3 +++".to_string(),
+                    ],
+                    out.lines()
+                        .map(|l| l.split_once("] ").map_or(l, |(_, call)| call))
+                        .collect::<Vec<&str>>()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_path_filter() {
+        let _lock = FORK_LOCK.lock().unwrap();
+        use nix::sys::ptrace::traceme;
+        use nix::sys::signal::{raise, Signal};
+        use nix::unistd::{fork, ForkResult};
+        use std::ffi::CString;
+
+        // 2つのファイルへ書き込み、一方のみ指定する
+        // (フォーク後の子プロセスでは、メモリ確保をしない)
+        let dir = std::env::temp_dir();
+        let target = dir.join(format!("r-debugger-target-{}", std::process::id()));
+        let other = dir.join(format!("r-debugger-other-{}", std::process::id()));
+        let paths = [&target, &other]
+            .iter()
+            .map(|p| CString::new(p.to_string_lossy().as_bytes()).unwrap())
+            .collect::<Vec<CString>>();
+        match unsafe { fork() }.expect("failed fork") {
+            ForkResult::Child => unsafe {
+                traceme().expect("failed traceme");
+                raise(Signal::SIGSTOP).expect("failed raise");
+                let flags = libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC;
+                let fds = [
+                    libc::open(paths[0].as_ptr(), flags, 0o644),
+                    libc::open(paths[1].as_ptr(), flags, 0o644),
+                ];
+                for fd in &fds {
+                    libc::write(*fd, b"data".as_ptr() as *const libc::c_void, 4);
+                }
+                let dup = libc::dup(fds[0]);
+                libc::write(dup, b"dup".as_ptr() as *const libc::c_void, 3);
+                for fd in &[fds[0], fds[1], dup] {
+                    libc::close(*fd);
+                }
+                libc::_exit(0);
+            },
+            ForkResult::Parent { child } => {
+                let args = vec![
+                    "--color=never".to_string(),
+                    "-P".to_string(),
+                    target.to_string_lossy().to_string(),
+                ];
+                let opt = TraceOption::parse(&args).unwrap();
+                let mut tracer = Tracer::new(child, opt, vec![]);
+                tracer.start();
+                let _ = std::fs::remove_file(&target);
+                let _ = std::fs::remove_file(&other);
+
+                let out = String::from_utf8(tracer.out).unwrap();
+                let calls = out
+                    .lines()
+                    .filter(|l| l.starts_with('['))
+                    .map(|l| l.split_once("] ").unwrap().1.split('(').next().unwrap())
+                    .collect::<Vec<&str>>();
+                assert_eq!(
+                    vec!["openat", "write", "dup", "write", "close", "close"],
+                    calls
+                );
+                assert!(out.contains(&target.to_string_lossy().to_string()));
+                assert!(!out.contains(&other.to_string_lossy().to_string()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_inject() {
+        let _lock = FORK_LOCK.lock().unwrap();
+        use nix::sys::ptrace::traceme;
+        use nix::sys::signal::{raise, Signal};
+        use nix::unistd::{fork, ForkResult};
+
+        // 2回目以降のreadにENOENTを注入し、子プロセスは受け取ったerrnoで終了する
+        // (PTRACE_GET_SYSCALL_INFO・レジスタのどちらで解析しても同じ結果となる)
+        for syscall_info in &[true, false] {
+            match unsafe { fork() }.expect("failed fork") {
+                ForkResult::Child => unsafe {
+                    traceme().expect("failed traceme");
+                    raise(Signal::SIGSTOP).expect("failed raise");
+                    let fd = libc::open(b"/dev/zero\0".as_ptr() as *const libc::c_char, 0);
+                    let mut buf = [0u8; 4];
+                    let first = libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, 4);
+                    let second = libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, 4);
+                    if 4 == first && -1 == second {
+                        libc::_exit(*libc::__errno_location());
+                    }
+                    libc::_exit(100);
+                },
+                ForkResult::Parent { child } => {
+                    let args = [
+                        "--color=never",
+                        "-e",
+                        "trace=read",
+                        "-e",
+                        "inject=read:error=ENOENT:when=2",
+                    ];
+                    let args = args.iter().map(|a| a.to_string()).collect::<Vec<String>>();
+                    let mut opt = TraceOption::parse(&args).unwrap();
+                    opt.syscall_info = *syscall_info;
+                    let mut tracer = Tracer::new(child, opt, vec![]);
+                    tracer.start();
+
+                    assert_eq!(
+                        &vec![WaitStatus::Exited(child, libc::ENOENT)],
+                        tracer.get_exits()
+                    );
+                    let out = String::from_utf8(tracer.out).unwrap();
+                    let reads = out
+                        .lines()
+                        .filter(|l| l.contains("] read("))
+                        .collect::<Vec<&str>>();
+                    assert_eq!(2, reads.len());
+                    assert!(reads[0].ends_with(" = 4"));
+                    assert!(
+                        reads[1].ends_with(" = -1 ENOENT (No such file or directory) (INJECTED)")
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_deny() {
+        let _lock = FORK_LOCK.lock().unwrap();
+        use nix::sys::ptrace::traceme;
+        use nix::sys::signal::raise;
+        use nix::unistd::{fork, ForkResult};
+
+        // 拒否したgetppidはEPERMで失敗し、--deny-killの場合は強制終了する
+        for kill in &[false, true] {
+            match unsafe { fork() }.expect("failed fork") {
+                ForkResult::Child => unsafe {
+                    traceme().expect("failed traceme");
+                    raise(Signal::SIGSTOP).expect("failed raise");
+                    // (getppidのラッパーはerrnoを設定しないため、直接呼び出す)
+                    let first = libc::syscall(libc::SYS_getppid);
+                    let second = libc::syscall(libc::SYS_getppid);
+                    if -1 == first && -1 == second {
+                        libc::_exit(*libc::__errno_location());
+                    }
+                    libc::_exit(100);
+                },
+                ForkResult::Parent { child } => {
+                    let deny = if *kill { "--deny-kill" } else { "--deny" };
+                    let args = ["--color=never", "-e", "trace=getppid", deny, "getppid"];
+                    let args = args.iter().map(|a| a.to_string()).collect::<Vec<String>>();
+                    let opt = TraceOption::parse(&args).unwrap();
+                    let mut tracer = Tracer::new(child, opt, vec![]);
+                    tracer.start();
+
+                    let exits = tracer.get_exits().clone();
+                    let out = String::from_utf8(tracer.out).unwrap();
+                    let calls = out
+                        .lines()
+                        .filter(|l| l.contains("] getppid("))
+                        .collect::<Vec<&str>>();
+                    if *kill {
+                        assert_eq!(
+                            vec![WaitStatus::Signaled(child, Signal::SIGKILL, false)],
+                            exits
+                        );
+                        assert_eq!(1, calls.len());
+                        assert!(calls[0].ends_with("getppid() = ? (DENIED)"));
+                        assert!(out.contains("+++ denied syscalls: getppid(1) +++"));
+                    } else {
+                        assert_eq!(vec![WaitStatus::Exited(child, libc::EPERM)], exits);
+                        assert_eq!(2, calls.len());
+                        assert!(
+                            calls
+                                .iter()
+                                .all(|l| l
+                                    .ends_with(" = -1 EPERM (Operation not permitted) (DENIED)"))
+                        );
+                        assert!(out.contains("+++ denied syscalls: getppid(2) +++"));
+                    }
+                }
+            }
         }
     }
 }
@@ -0,0 +1,270 @@
+//! トレース結果に表示するアドレスのシンボル化
+//!
+//! マップされている領域から、アドレスをモジュール+オフセット(libc.so.6+0x11f8a3)へ変換する
+//! 実行ファイルのシンボルを読み込めれば、関数名+オフセットも表示する
+//! ライブラリは実行中にロードされるため、見つからなければ領域を読み込み直す
+
+use nix::unistd::Pid;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::elf::elf64::Elf64;
+use crate::exec::read_exe_path;
+use crate::memory_map::{load_mappings, Mapping};
+
+// ファイルをマップした領域
+#[derive(Debug, PartialEq)]
+struct Region {
+    start: u64,   // 開始アドレス
+    end: u64,     // 終了アドレス
+    base: u64,    // モジュールをロードしたアドレス
+    path: String, // ファイルのパス
+}
+
+// プロセス毎の領域
+struct ProcessMap {
+    exe: String,                     // 実行ファイルのパス
+    regions: Vec<Region>,            // ファイルをマップした領域(アドレスの順)
+    locations: HashMap<u64, String>, // 変換済みのアドレス
+}
+
+/// アドレスのシンボル化
+pub struct Symbolizer {
+    maps: HashMap<Pid, ProcessMap>, // プロセス毎の領域(初回に読み込む)
+    programs: HashMap<String, Option<Elf64>>, // 実行ファイル毎のシンボル
+}
+
+impl Symbolizer {
+    /// コンストラクタ
+    pub fn new() -> Self {
+        Symbolizer {
+            maps: HashMap::new(),
+            programs: HashMap::new(),
+        }
+    }
+
+    /// アドレスをモジュール+オフセットへ変換(ファイルをマップした領域でなければ16進数)
+    ///
+    /// 変換した結果はプロセス毎に保持し、見つからなければ領域を読み込み直す
+    pub fn symbolize(&mut self, pid: Pid, addr: u64) -> String {
+        let map = self
+            .maps
+            .entry(pid)
+            .or_insert_with(|| ProcessMap::load(pid));
+        if let Some(location) = map.locations.get(&addr) {
+            return location.clone();
+        }
+        if find_region(&map.regions, addr).is_none() {
+            *map = ProcessMap::load(pid);
+        }
+        let location = match find_region(&map.regions, addr) {
+            Some(r) if r.path == map.exe => {
+                let elf = self
+                    .programs
+                    .entry(r.path.clone())
+                    .or_insert_with(|| load_program(&r.path));
+                format_location(r, addr, elf.as_ref())
+            }
+            Some(r) => format_location(r, addr, None),
+            None => format!("0x{:x}", addr),
+        };
+        map.locations.insert(addr, location.clone());
+        location
+    }
+
+    /// プロセスの領域を破棄(終了・execve)
+    pub fn remove(&mut self, pid: Pid) {
+        self.maps.remove(&pid);
+    }
+}
+
+impl ProcessMap {
+    // 領域を読み込む(読み込めなければ空)
+    fn load(pid: Pid) -> Self {
+        ProcessMap {
+            exe: read_exe_path(pid).unwrap_or_default(),
+            regions: to_regions(&load_mappings(pid).unwrap_or_default()),
+            locations: HashMap::new(),
+        }
+    }
+}
+
+/// マップされている領域から、ファイルをマップした領域をアドレスの順に求める
+///
+/// モジュールをロードしたアドレスは、同じファイルの最も小さいアドレスの領域から求める
+fn to_regions(mappings: &[Mapping]) -> Vec<Region> {
+    let mut bases = HashMap::new();
+    for m in mappings.iter().filter(|m| !m.path.is_empty()) {
+        let base = m.start.wrapping_sub(m.offset);
+        bases
+            .entry(m.path.as_str())
+            .and_modify(|b: &mut (u64, u64)| {
+                if m.start < b.0 {
+                    *b = (m.start, base)
+                }
+            })
+            .or_insert((m.start, base));
+    }
+    let mut regions = mappings
+        .iter()
+        .filter(|m| !m.path.is_empty())
+        .map(|m| Region {
+            start: m.start,
+            end: m.end,
+            base: bases[m.path.as_str()].1,
+            path: m.path.clone(),
+        })
+        .collect::<Vec<Region>>();
+    regions.sort_by_key(|r| r.start);
+    regions
+}
+
+/// アドレスを含む領域を検索(regionsはアドレスの順)
+fn find_region(regions: &[Region], addr: u64) -> Option<&Region> {
+    let i = regions.partition_point(|r| r.start <= addr);
+    regions[..i].last().filter(|r| addr < r.end)
+}
+
+/// 実行ファイルのシンボルテーブルを読み込む(dwarf情報は読み込まない)
+fn load_program(path: &str) -> Option<Elf64> {
+    let mut elf = Elf64::new(path.to_string());
+    elf.load_headers().ok()?;
+    elf.load_sym_table().ok()?;
+    Some(elf)
+}
+
+/// モジュール+オフセット(シンボルがあれば、モジュール!関数+オフセット)へ整形
+fn format_location(region: &Region, addr: u64, elf: Option<&Elf64>) -> String {
+    let name = Path::new(&region.path)
+        .file_name()
+        .map_or(region.path.clone(), |n| n.to_string_lossy().into_owned());
+    let offset = addr - region.base;
+    // 位置独立でなければ、シンボルは絶対アドレス
+    let sym = elf.and_then(|e| {
+        let vaddr = if e.is_pie() { offset } else { addr };
+        e.search_func_sym_by_addr(vaddr)
+            .map(|s| (s.get_name(), vaddr - s.st_value))
+    });
+    match sym {
+        Some((func, off)) => format!("{}!{}+0x{:x}", name, func, off),
+        None => format!("{}+0x{:x}", name, offset),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // 関数シンボルとして検索できるよう、種類・サイズを付ける
+    std::arch::global_asm!(
+        ".globl symbolizer_test_func",
+        ".type symbolizer_test_func, @function",
+        "symbolizer_test_func:",
+        "nop",
+        "nop",
+        "ret",
+        ".size symbolizer_test_func, . - symbolizer_test_func",
+    );
+
+    extern "C" {
+        fn symbolizer_test_func();
+    }
+
+    fn mapping(start: u64, end: u64, offset: u64, path: &str) -> Mapping {
+        Mapping {
+            start,
+            end,
+            offset,
+            perms: String::new(),
+            path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_to_regions() {
+        let mappings = vec![
+            mapping(0x7f00_0000_1000, 0x7f00_0000_3000, 0x1000, "/lib/libc.so.6"),
+            mapping(0x5555_0000_0000, 0x5555_0000_1000, 0, "/tmp/a.out"),
+            mapping(0x5555_0000_1000, 0x5555_0000_2000, 0x1000, "/tmp/a.out"),
+            mapping(0x7f00_0000_0000, 0x7f00_0000_1000, 0, "/lib/libc.so.6"),
+            mapping(0x7ffd_0000_0000, 0x7ffd_0000_1000, 0, ""),
+        ];
+        let bases = to_regions(&mappings)
+            .iter()
+            .map(|r| (r.start, r.base))
+            .collect::<Vec<(u64, u64)>>();
+        // 無名の領域は含めず、同じファイルは同じアドレスにロードしている
+        assert_eq!(
+            vec![
+                (0x5555_0000_0000, 0x5555_0000_0000),
+                (0x5555_0000_1000, 0x5555_0000_0000),
+                (0x7f00_0000_0000, 0x7f00_0000_0000),
+                (0x7f00_0000_1000, 0x7f00_0000_0000),
+            ],
+            bases
+        );
+    }
+
+    #[test]
+    fn test_find_region() {
+        let regions = to_regions(&[
+            mapping(0x1000, 0x2000, 0, "/tmp/a.out"),
+            mapping(0x2000, 0x3000, 0x1000, "/tmp/a.out"),
+            mapping(0x8000, 0x9000, 0, "/lib/libc.so.6"),
+        ]);
+        let cases = vec![
+            (0x0fff, None),
+            (0x1000, Some(0x1000)),
+            (0x2000, Some(0x2000)),
+            (0x2fff, Some(0x2000)),
+            (0x3000, None),
+            (0x8abc, Some(0x8000)),
+            (0x9000, None),
+        ];
+        for (addr, expected) in cases {
+            let start = find_region(&regions, addr).map(|r| r.start);
+            assert_eq!(expected, start, "0x{:x}", addr);
+        }
+    }
+
+    #[test]
+    fn test_format_location() {
+        let region = |path: &str| Region {
+            start: 0x7f00_0000_1000,
+            end: 0x7f00_0000_2000,
+            base: 0x7f00_0000_0000,
+            path: path.to_string(),
+        };
+        let cases = vec![
+            ("/lib/x86_64-linux-gnu/libc.so.6", "libc.so.6+0x1234"),
+            ("[vdso]", "[vdso]+0x1234"),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(
+                expected,
+                format_location(&region(path), 0x7f00_0000_1234, None)
+            );
+        }
+    }
+
+    #[test]
+    fn test_symbolize() {
+        let pid = Pid::this();
+        let exe = read_exe_path(pid).unwrap();
+        let name = Path::new(&exe).file_name().unwrap().to_string_lossy();
+        let addr = symbolizer_test_func as *const () as u64;
+
+        // 実行ファイルは関数名まで変換し、2回目は変換済みの結果を返す
+        let mut symbolizer = Symbolizer::new();
+        let expected = format!("{}!symbolizer_test_func+0x1", name);
+        assert_eq!(expected, symbolizer.symbolize(pid, addr + 1));
+        assert_eq!(expected, symbolizer.symbolize(pid, addr + 1));
+
+        // ライブラリはモジュール+オフセット
+        let libc = symbolizer.symbolize(pid, libc::getpid as *const () as u64);
+        assert!(libc.starts_with("libc.so.6+0x"), "{}", libc);
+
+        // マップされていなければ16進数
+        assert_eq!("0x10", symbolizer.symbolize(pid, 0x10));
+    }
+}
@@ -0,0 +1,1969 @@
+//! システムコール情報
+//!
+//! システムコール毎の引数の種類(シグネチャ)を定義し、レジスタの値を表示用に整形する
+
+use libc::user_regs_struct;
+use nix::errno::Errno;
+
+use crate::fd_table::FdTable;
+use crate::ioctl::{format_ioctl_request, to_ioctl_arg};
+use crate::memory::ReadMemory;
+use crate::syscall_struct::{format_sockaddr, format_stat, format_timespec};
+use nix::sys::signal::Signal;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::ffi::CStr;
+use std::mem::size_of;
+
+/// 引数・戻り値の種類
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArgKind {
+    Int,         // 符号付き整数(int)
+    Long,        // 符号付き整数(long, off_t, ssize_t)
+    Uint,        // 符号なし整数(サイズ等)
+    Hex,         // 16進数
+    Ptr,         // ポインタ(NULLはNULLと表示)
+    Fd,          // ファイルディスクリプタ
+    DirFd,       // ディレクトリのファイルディスクリプタ(AT_FDCWD)
+    Path,        // パス文字列へのポインタ
+    Buf,         // バッファへのポインタ(次の引数がサイズ)
+    OutBuf,      // カーネルが書き込むバッファへのポインタ(戻り値がサイズ)
+    Mode,        // ファイルモード(8進数)
+    OpenFlags,   // O_*
+    AtFlags,     // AT_*
+    Prot,        // PROT_*
+    MapFlags,    // MAP_*
+    Whence,      // SEEK_*
+    Signal,      // シグナル番号
+    AccessMode,  // F_OK・R_OK等
+    CloneFlags,  // CLONE_*(下位8bitは終了時のシグナル)
+    FutexOp,     // FUTEX_*
+    IoctlReq,    // ioctlのリクエスト
+    IoctlArg,    // ioctlの引数(直前の引数のリクエストにより異なる)
+    Stat,        // カーネルが書き込むstruct statへのポインタ
+    Timespec,    // struct timespecへのポインタ
+    OutTimespec, // カーネルが書き込むstruct timespecへのポインタ
+    Sockaddr,    // struct sockaddrへのポインタ(次の引数がサイズ)
+    Argv,        // 文字列へのポインタの配列(NULL終端)
+    Envp,        // 環境変数の配列(NULL終端。省略時は個数のみ表示)
+}
+
+/// システムコールのシグネチャ
+#[derive(Debug, PartialEq)]
+pub struct Signature {
+    args: &'static [ArgKind],
+    ret: ArgKind,
+}
+
+impl Signature {
+    /// 戻り値の種類を取得
+    pub fn get_ret(&self) -> ArgKind {
+        self.ret
+    }
+
+    /// 引数の種類を取得
+    pub fn get_arg_kinds(&self) -> &'static [ArgKind] {
+        self.args
+    }
+}
+
+/// シグネチャ生成
+const fn sig(args: &'static [ArgKind], ret: ArgKind) -> Signature {
+    Signature { args, ret }
+}
+
+/// システムコールNo→シグネチャ
+pub fn to_signature(no: i64) -> Option<Signature> {
+    use ArgKind::*;
+    let s = match no {
+        libc::SYS_read => sig(&[Fd, OutBuf, Uint], Long),
+        libc::SYS_write => sig(&[Fd, Buf, Uint], Long),
+        libc::SYS_open => sig(&[Path, OpenFlags, Mode], Fd),
+        libc::SYS_close => sig(&[Fd], Int),
+        libc::SYS_stat => sig(&[Path, Stat], Int),
+        libc::SYS_fstat => sig(&[Fd, Stat], Int),
+        libc::SYS_lstat => sig(&[Path, Stat], Int),
+        libc::SYS_poll => sig(&[Ptr, Uint, Int], Int),
+        libc::SYS_lseek => sig(&[Fd, Long, Whence], Long),
+        libc::SYS_mmap => sig(&[Ptr, Uint, Prot, MapFlags, Fd, Hex], Hex),
+        libc::SYS_mprotect => sig(&[Ptr, Uint, Prot], Int),
+        libc::SYS_munmap => sig(&[Ptr, Uint], Int),
+        libc::SYS_brk => sig(&[Ptr], Hex),
+        libc::SYS_rt_sigaction => sig(&[Signal, Ptr, Ptr, Uint], Int),
+        libc::SYS_rt_sigprocmask => sig(&[Int, Ptr, Ptr, Uint], Int),
+        libc::SYS_rt_sigreturn => sig(&[], Int),
+        libc::SYS_ioctl => sig(&[Fd, IoctlReq, IoctlArg], Int),
+        libc::SYS_pread64 => sig(&[Fd, OutBuf, Uint, Long], Long),
+        libc::SYS_pwrite64 => sig(&[Fd, Buf, Uint, Long], Long),
+        libc::SYS_readv => sig(&[Fd, Ptr, Int], Long),
+        libc::SYS_writev => sig(&[Fd, Ptr, Int], Long),
+        libc::SYS_access => sig(&[Path, AccessMode], Int),
+        libc::SYS_pipe => sig(&[Ptr], Int),
+        libc::SYS_mremap => sig(&[Ptr, Uint, Uint, Hex, Ptr], Hex),
+        libc::SYS_msync => sig(&[Ptr, Uint, Hex], Int),
+        libc::SYS_mincore => sig(&[Ptr, Uint, Ptr], Int),
+        libc::SYS_madvise => sig(&[Ptr, Uint, Int], Int),
+        libc::SYS_shmat => sig(&[Int, Ptr, Hex], Hex),
+        libc::SYS_dup => sig(&[Fd], Fd),
+        libc::SYS_dup2 => sig(&[Fd, Fd], Fd),
+        libc::SYS_pause => sig(&[], Int),
+        libc::SYS_nanosleep => sig(&[Timespec, Ptr], Int),
+        libc::SYS_getpid => sig(&[], Int),
+        libc::SYS_socket => sig(&[Int, Int, Int], Fd),
+        libc::SYS_connect => sig(&[Fd, Sockaddr, Uint], Int),
+        libc::SYS_accept => sig(&[Fd, Ptr, Ptr], Fd),
+        libc::SYS_sendto => sig(&[Fd, Buf, Uint, Hex, Sockaddr, Uint], Long),
+        libc::SYS_recvfrom => sig(&[Fd, OutBuf, Uint, Hex, Ptr, Ptr], Long),
+        libc::SYS_sendmsg => sig(&[Fd, Ptr, Hex], Long),
+        libc::SYS_recvmsg => sig(&[Fd, Ptr, Hex], Long),
+        libc::SYS_shutdown => sig(&[Fd, Int], Int),
+        libc::SYS_bind => sig(&[Fd, Sockaddr, Uint], Int),
+        libc::SYS_listen => sig(&[Fd, Int], Int),
+        libc::SYS_getsockname => sig(&[Fd, Ptr, Ptr], Int),
+        libc::SYS_getpeername => sig(&[Fd, Ptr, Ptr], Int),
+        libc::SYS_socketpair => sig(&[Int, Int, Int, Ptr], Int),
+        libc::SYS_setsockopt => sig(&[Fd, Int, Int, Ptr, Uint], Int),
+        libc::SYS_getsockopt => sig(&[Fd, Int, Int, Ptr, Ptr], Int),
+        libc::SYS_clone => sig(&[CloneFlags, Ptr, Ptr, Ptr, Hex], Int),
+        libc::SYS_fork => sig(&[], Int),
+        libc::SYS_vfork => sig(&[], Int),
+        libc::SYS_execve => sig(&[Path, Argv, Envp], Int),
+        libc::SYS_exit => sig(&[Int], Int),
+        libc::SYS_wait4 => sig(&[Int, Ptr, Hex, Ptr], Int),
+        libc::SYS_kill => sig(&[Int, Signal], Int),
+        libc::SYS_uname => sig(&[Ptr], Int),
+        libc::SYS_fcntl => sig(&[Fd, Int, Hex], Int),
+        libc::SYS_truncate => sig(&[Path, Long], Int),
+        libc::SYS_getcwd => sig(&[OutBuf, Uint], Int),
+        libc::SYS_chdir => sig(&[Path], Int),
+        libc::SYS_rename => sig(&[Path, Path], Int),
+        libc::SYS_mkdir => sig(&[Path, Mode], Int),
+        libc::SYS_rmdir => sig(&[Path], Int),
+        libc::SYS_creat => sig(&[Path, Mode], Fd),
+        libc::SYS_link => sig(&[Path, Path], Int),
+        libc::SYS_unlink => sig(&[Path], Int),
+        libc::SYS_symlink => sig(&[Path, Path], Int),
+        libc::SYS_readlink => sig(&[Path, OutBuf, Uint], Long),
+        libc::SYS_chmod => sig(&[Path, Mode], Int),
+        libc::SYS_chown => sig(&[Path, Int, Int], Int),
+        libc::SYS_lchown => sig(&[Path, Int, Int], Int),
+        libc::SYS_getuid => sig(&[], Int),
+        libc::SYS_getgid => sig(&[], Int),
+        libc::SYS_geteuid => sig(&[], Int),
+        libc::SYS_getegid => sig(&[], Int),
+        libc::SYS_getppid => sig(&[], Int),
+        libc::SYS_rt_sigpending => sig(&[Ptr, Uint], Int),
+        libc::SYS_rt_sigtimedwait => sig(&[Ptr, Ptr, Timespec, Uint], Int),
+        libc::SYS_rt_sigqueueinfo => sig(&[Int, Signal, Ptr], Int),
+        libc::SYS_rt_sigsuspend => sig(&[Ptr, Uint], Int),
+        libc::SYS_sigaltstack => sig(&[Ptr, Ptr], Int),
+        libc::SYS_mknod => sig(&[Path, Mode, Hex], Int),
+        libc::SYS_statfs => sig(&[Path, Ptr], Int),
+        libc::SYS_mlock => sig(&[Ptr, Uint], Int),
+        libc::SYS_munlock => sig(&[Ptr, Uint], Int),
+        libc::SYS_mlockall => sig(&[Hex], Int),
+        libc::SYS_munlockall => sig(&[], Int),
+        libc::SYS_arch_prctl => sig(&[Hex, Hex], Int),
+        libc::SYS_chroot => sig(&[Path], Int),
+        libc::SYS_gettid => sig(&[], Int),
+        libc::SYS_tkill => sig(&[Int, Signal], Int),
+        libc::SYS_futex => sig(&[Ptr, FutexOp, Int, Ptr, Ptr, Int], Int),
+        libc::SYS_remap_file_pages => sig(&[Ptr, Uint, Int, Uint, Hex], Int),
+        libc::SYS_getdents64 => sig(&[Fd, Ptr, Uint], Long),
+        libc::SYS_set_tid_address => sig(&[Ptr], Int),
+        libc::SYS_clock_gettime => sig(&[Int, OutTimespec], Int),
+        libc::SYS_clock_getres => sig(&[Int, OutTimespec], Int),
+        libc::SYS_clock_nanosleep => sig(&[Int, Int, Timespec, Ptr], Int),
+        libc::SYS_exit_group => sig(&[Int], Int),
+        libc::SYS_tgkill => sig(&[Int, Int, Signal], Int),
+        libc::SYS_mbind => sig(&[Ptr, Uint, Int, Ptr, Uint, Hex], Long),
+        libc::SYS_set_mempolicy => sig(&[Int, Ptr, Uint], Long),
+        libc::SYS_get_mempolicy => sig(&[Ptr, Ptr, Uint, Ptr, Hex], Long),
+        libc::SYS_waitid => sig(&[Int, Int, Ptr, Hex, Ptr], Int),
+        libc::SYS_openat => sig(&[DirFd, Path, OpenFlags, Mode], Fd),
+        libc::SYS_mkdirat => sig(&[DirFd, Path, Mode], Int),
+        libc::SYS_mknodat => sig(&[DirFd, Path, Mode, Hex], Int),
+        libc::SYS_fchownat => sig(&[DirFd, Path, Int, Int, AtFlags], Int),
+        libc::SYS_newfstatat => sig(&[DirFd, Path, Stat, AtFlags], Int),
+        libc::SYS_unlinkat => sig(&[DirFd, Path, AtFlags], Int),
+        libc::SYS_renameat => sig(&[DirFd, Path, DirFd, Path], Int),
+        libc::SYS_linkat => sig(&[DirFd, Path, DirFd, Path, AtFlags], Int),
+        libc::SYS_symlinkat => sig(&[Path, DirFd, Path], Int),
+        libc::SYS_readlinkat => sig(&[DirFd, Path, OutBuf, Uint], Long),
+        libc::SYS_fchmodat => sig(&[DirFd, Path, Mode], Int),
+        libc::SYS_faccessat => sig(&[DirFd, Path, AccessMode], Int),
+        libc::SYS_set_robust_list => sig(&[Ptr, Uint], Int),
+        libc::SYS_utimensat => sig(&[DirFd, Path, Ptr, AtFlags], Int),
+        libc::SYS_signalfd => sig(&[Fd, Ptr, Uint], Fd),
+        libc::SYS_accept4 => sig(&[Fd, Ptr, Ptr, Hex], Fd),
+        libc::SYS_signalfd4 => sig(&[Fd, Ptr, Uint, OpenFlags], Fd),
+        libc::SYS_dup3 => sig(&[Fd, Fd, OpenFlags], Fd),
+        libc::SYS_pipe2 => sig(&[Ptr, OpenFlags], Int),
+        libc::SYS_rt_tgsigqueueinfo => sig(&[Int, Int, Signal, Ptr], Int),
+        libc::SYS_recvmmsg => sig(&[Fd, Ptr, Uint, Hex, Timespec], Int),
+        libc::SYS_prlimit64 => sig(&[Int, Int, Ptr, Ptr], Int),
+        libc::SYS_sendmmsg => sig(&[Fd, Ptr, Uint, Hex], Int),
+        libc::SYS_renameat2 => sig(&[DirFd, Path, DirFd, Path, Hex], Int),
+        libc::SYS_getrandom => sig(&[OutBuf, Uint, Hex], Long),
+        libc::SYS_execveat => sig(&[DirFd, Path, Argv, Envp, AtFlags], Int),
+        libc::SYS_mlock2 => sig(&[Ptr, Uint, Hex], Int),
+        libc::SYS_pkey_mprotect => sig(&[Ptr, Uint, Prot, Int], Int),
+        libc::SYS_statx => sig(&[DirFd, Path, AtFlags, Hex, Ptr], Int),
+        libc::SYS_pidfd_send_signal => sig(&[Fd, Signal, Ptr, Hex], Int),
+        libc::SYS_clone3 => sig(&[Ptr, Uint], Int),
+        libc::SYS_openat2 => sig(&[DirFd, Path, Ptr, Uint], Fd),
+        libc::SYS_faccessat2 => sig(&[DirFd, Path, AccessMode, AtFlags], Int),
+        _ => return None,
+    };
+    Some(s)
+}
+
+/// 省略時に表示する配列(argv)の要素数
+const ABBREV_ARRAY_LEN: usize = 4;
+
+/// 配列として読み込む要素数の上限
+const ARRAY_MAX: usize = 4096;
+
+/// 引数の整形オプション
+#[derive(Debug, Clone, Copy)]
+pub struct FormatOption {
+    limit: usize,  // 文字列・バッファの表示バイト数(-s)
+    verbose: bool, // 構造体・配列を省略せずに表示する(-v)
+}
+
+impl FormatOption {
+    /// コンストラクタ
+    pub fn new(limit: usize, verbose: bool) -> Self {
+        FormatOption { limit, verbose }
+    }
+}
+
+/// システムコールの引数レジスタ(rdi, rsi, rdx, r10, r8, r9)を取得
+pub fn get_args(regs: &user_regs_struct) -> [u64; 6] {
+    [regs.rdi, regs.rsi, regs.rdx, regs.r10, regs.r8, regs.r9]
+}
+
+/// 開始時に読み込む引数を整形
+///
+/// 文字列・バッファ・入力の構造体・配列は、カーネルが読み込む開始時の内容を表示する
+/// (バッファはlimitバイトまで。開始時に整形しない引数はNone)
+pub fn format_input_args<M: ReadMemory>(
+    no: i64,
+    regs: &user_regs_struct,
+    mem: &M,
+    opt: FormatOption,
+) -> Vec<Option<String>> {
+    let args = get_args(regs);
+    let sig = match to_signature(no) {
+        Some(s) => s,
+        None => return vec![],
+    };
+    let next = |i: usize| args.get(i + 1).copied().unwrap_or(0);
+    sig.args
+        .iter()
+        .enumerate()
+        .map(|(i, k)| match k {
+            ArgKind::Path => Some(format_data(args[i], mem.read_cstring(args[i], PATH_MAX))),
+            ArgKind::Buf => Some(read_buf(mem, args[i], next(i), opt.limit)),
+            ArgKind::Timespec => Some(read_struct(
+                mem,
+                args[i],
+                size_of::<libc::timespec>(),
+                format_timespec,
+            )),
+            ArgKind::Sockaddr => {
+                let len = std::cmp::min(next(i), size_of::<libc::sockaddr_storage>() as u64);
+                Some(read_struct(mem, args[i], len as usize, format_sockaddr))
+            }
+            ArgKind::Argv if opt.verbose => Some(read_array(mem, args[i], opt.limit, ARRAY_MAX)),
+            ArgKind::Argv => Some(read_array(mem, args[i], opt.limit, ABBREV_ARRAY_LEN)),
+            ArgKind::Envp if opt.verbose => Some(read_array(mem, args[i], opt.limit, ARRAY_MAX)),
+            ArgKind::Envp => Some(count_array(mem, args[i])),
+            // カーネルが読み込む引数のみ
+            ArgKind::IoctlArg => match to_ioctl_arg(args[i - 1]) {
+                Some((arg, false)) => {
+                    Some(read_struct(mem, args[i], arg.get_size(), |d| arg.format(d)))
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// 引数を整形
+///
+/// inputsは開始時に整形した引数(format_input_args)
+/// カーネルが書き込むバッファ・構造体は、終了時(retが成功)のみ読み込む
+/// fdsがあれば、ファイルディスクリプタにパスを付ける(-y)
+/// シグネチャが不明なシステムコールは、6つの引数をすべて16進数で表示する
+pub fn format_args<M: ReadMemory>(
+    no: i64,
+    regs: &user_regs_struct,
+    ret: Option<u64>,
+    inputs: &[Option<String>],
+    mem: &M,
+    opt: FormatOption,
+    mut fds: Option<&mut FdTable>,
+) -> String {
+    let args = get_args(regs);
+    let sig = match to_signature(no) {
+        Some(s) => s,
+        None => {
+            return args
+                .iter()
+                .map(|v| format!("0x{:x}", v))
+                .collect::<Vec<String>>()
+                .join(", ")
+        }
+    };
+
+    let ret = ret.filter(|r| 0 <= *r as i64);
+    sig.args
+        .iter()
+        .enumerate()
+        .map(|(i, k)| match (inputs.get(i).cloned().flatten(), k, ret) {
+            (Some(s), _, _) => s,
+            (None, ArgKind::OutBuf, Some(r)) => read_buf(mem, args[i], r, opt.limit),
+            (None, ArgKind::Stat, Some(_)) => {
+                read_struct(mem, args[i], size_of::<libc::stat>(), |d| {
+                    format_stat(d, opt.verbose)
+                })
+            }
+            (None, ArgKind::IoctlArg, Some(_)) => match to_ioctl_arg(args[i - 1]) {
+                Some((arg, true)) => read_struct(mem, args[i], arg.get_size(), |d| arg.format(d)),
+                _ => format_value(*k, args[i]),
+            },
+            (None, ArgKind::OutTimespec, Some(_)) => {
+                read_struct(mem, args[i], size_of::<libc::timespec>(), format_timespec)
+            }
+            (None, ArgKind::Fd, _) | (None, ArgKind::DirFd, _) => {
+                format_fd(*k, args[i], fds.as_deref_mut())
+            }
+            _ => format_value(*k, args[i]),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// ファイルディスクリプタを整形
+///
+/// パスが分かれば、3</etc/ld.so.cache>のように表示する
+fn format_fd(kind: ArgKind, val: u64, fds: Option<&mut FdTable>) -> String {
+    let fd = val as i32;
+    match fds.filter(|_| 0 <= fd).and_then(|t| t.get_path(fd)) {
+        Some(path) => format!("{}<{}>", fd, path),
+        None => format_value(kind, val),
+    }
+}
+
+/// 読み込めたバイト列をエスケープして表示し、読み込めなければアドレスに<fault>を付ける
+fn format_data(addr: u64, data: Option<(Vec<u8>, bool)>) -> String {
+    match data {
+        _ if 0 == addr => "NULL".to_string(),
+        Some((d, truncated)) => quote(&d, truncated),
+        None => format!("0x{:x} <fault>", addr),
+    }
+}
+
+/// バッファを読み込んで整形(limitバイトまで)
+fn read_buf<M: ReadMemory>(mem: &M, addr: u64, len: u64, limit: usize) -> String {
+    let size = std::cmp::min(len, limit as u64) as usize;
+    let data = mem
+        .read_memory(addr, size)
+        .map(|d| trim_partial_char((d, size as u64 != len)));
+    format_data(addr, data)
+}
+
+/// 打ち切ったバイト列の末尾から、途中で切れたUTF-8の文字を除く
+fn trim_partial_char((mut data, truncated): (Vec<u8>, bool)) -> (Vec<u8>, bool) {
+    if !truncated {
+        return (data, truncated);
+    }
+    // 末尾3バイト以内の先頭バイトから、文字の長さが足りているか判定する
+    for i in (data.len().saturating_sub(3)..data.len()).rev() {
+        let width = match data[i] {
+            0x80..=0xBF => continue,
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => break,
+        };
+        if data.len() - i < width {
+            data.truncate(i);
+        }
+        break;
+    }
+    (data, truncated)
+}
+
+/// ポインタを読み込む
+fn read_pointer<M: ReadMemory>(mem: &M, addr: u64) -> Option<u64> {
+    let data = mem.read_memory(addr, size_of::<u64>())?;
+    Some(u64::from_ne_bytes(<[u8; 8]>::try_from(&data[..]).ok()?))
+}
+
+/// 文字列へのポインタの配列(NULL終端)を読み込んで整形
+///
+/// max個を超える要素は...で省略し、文字列はlimitバイトまで表示する
+fn read_array<M: ReadMemory>(mem: &M, addr: u64, limit: usize, max: usize) -> String {
+    if 0 == addr {
+        return "NULL".to_string();
+    }
+    let mut items = vec![];
+    for i in 0..=max {
+        let a = addr + (i * size_of::<u64>()) as u64;
+        match read_pointer(mem, a) {
+            Some(0) => break,
+            Some(_) if max == i => items.push("...".to_string()),
+            Some(p) => {
+                let data = mem.read_cstring(p, limit).map(trim_partial_char);
+                items.push(format_data(p, data));
+            }
+            None if items.is_empty() => return format!("0x{:x} <fault>", addr),
+            None => {
+                items.push(format!("0x{:x} <fault>", a));
+                break;
+            }
+        }
+    }
+    format!("[{}]", items.join(", "))
+}
+
+/// 文字列へのポインタの配列(NULL終端)を、アドレスと要素数で整形(0x7ffd0000 /* 23 vars */)
+fn count_array<M: ReadMemory>(mem: &M, addr: u64) -> String {
+    match read_pointer(mem, addr) {
+        _ if 0 == addr => return "NULL".to_string(),
+        None => return format!("0x{:x} <fault>", addr),
+        Some(_) => {}
+    }
+    let count = (0..ARRAY_MAX)
+        .map(|i| read_pointer(mem, addr + (i * size_of::<u64>()) as u64))
+        .take_while(|p| p.is_some_and(|p| 0 != p))
+        .count();
+    format!("0x{:x} /* {} vars */", addr, count)
+}
+
+/// 構造体を読み込んで整形
+///
+/// 読み込めなければアドレスに<fault>を付け、解釈できなければアドレスのみ表示する
+fn read_struct<M: ReadMemory>(
+    mem: &M,
+    addr: u64,
+    size: usize,
+    format: impl Fn(&[u8]) -> Option<String>,
+) -> String {
+    if 0 == addr {
+        return "NULL".to_string();
+    }
+    match mem.read_memory(addr, size) {
+        Some(data) => format(&data).unwrap_or_else(|| format!("0x{:x}", addr)),
+        None => format!("0x{:x} <fault>", addr),
+    }
+}
+
+/// パス文字列の最大長
+pub const PATH_MAX: usize = libc::PATH_MAX as usize;
+
+/// バイト列をエスケープし、"で囲む
+///
+/// 打ち切った場合は、末尾に...を付ける
+pub fn quote(data: &[u8], truncated: bool) -> String {
+    let mut s = String::from("\"");
+    for b in data {
+        match b {
+            b'"' => s.push_str("\\\""),
+            b'\\' => s.push_str("\\\\"),
+            b'\n' => s.push_str("\\n"),
+            b'\t' => s.push_str("\\t"),
+            b'\r' => s.push_str("\\r"),
+            0x20..=0x7E => s.push(*b as char),
+            _ => s.push_str(&format!("\\x{:02x}", b)),
+        }
+    }
+    s.push('"');
+    if truncated {
+        s.push_str("...");
+    }
+    s
+}
+
+/// 戻り値→エラー番号
+///
+/// -4095〜-1はエラーとする
+pub fn to_errno(ret: u64) -> Option<i32> {
+    let val = ret as i64;
+    if (-4095..0).contains(&val) {
+        Some(-val as i32)
+    } else {
+        None
+    }
+}
+
+/// カーネル内部のエラー番号(番号, 名前, 説明)
+///
+/// ユーザー空間へは返らないが、システムコール終了時の停止では観測される
+const KERNEL_ERRNOS: &[(i32, &str, &str)] = &[
+    (512, "ERESTARTSYS", "To be restarted if SA_RESTART is set"),
+    (513, "ERESTARTNOINTR", "To be restarted"),
+    (514, "ERESTARTNOHAND", "To be restarted if no handler"),
+    (515, "ENOIOCTLCMD", "No ioctl command"),
+    (516, "ERESTART_RESTARTBLOCK", "Interrupted by signal"),
+    (517, "EPROBE_DEFER", "Driver requests probe retry"),
+    (518, "EOPENSTALE", "Open found a stale dentry"),
+    (519, "ENOPARAM", "Parameter not supported"),
+    (521, "EBADHANDLE", "Illegal NFS file handle"),
+    (522, "ENOTSYNC", "Update synchronization mismatch"),
+    (523, "EBADCOOKIE", "Cookie is stale"),
+    (524, "ENOTSUPP", "Operation is not supported"),
+    (525, "ETOOSMALL", "Buffer or request is too small"),
+    (526, "ESERVERFAULT", "An untranslatable error occurred"),
+    (527, "EBADTYPE", "Type not supported by server"),
+    (
+        528,
+        "EJUKEBOX",
+        "Request initiated, but will not complete before timeout",
+    ),
+    (529, "EIOCBQUEUED", "iocb queued, will get completion event"),
+    (530, "ERECALLCONFLICT", "Conflict with recalled state"),
+    (531, "ENOGRACE", "NFS file lock reclaim refused"),
+];
+
+/// エラーを整形
+///
+/// カーネル内部のエラー(再実行される等)は、戻り値を?とする
+fn format_errno(no: i32) -> String {
+    if let Some((_, name, desc)) = KERNEL_ERRNOS.iter().find(|(n, _, _)| *n == no) {
+        return format!("? {} ({})", name, desc);
+    }
+    match Errno::from_i32(no) {
+        Errno::UnknownErrno => format!("-1 E{} ({})", no, strerror(no)),
+        errno => format!("-1 {:?} ({})", errno, strerror(no)),
+    }
+}
+
+/// エラー番号の説明(libcのstrerrorと同じ文字列)
+fn strerror(no: i32) -> String {
+    let mut buf = [0 as libc::c_char; 128];
+    if unsafe { libc::strerror_r(no, buf.as_mut_ptr(), buf.len()) } != 0 {
+        return format!("Unknown error {}", no);
+    }
+    unsafe { CStr::from_ptr(buf.as_ptr()) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// エラー名(ENOENT等)もしくは番号→エラー番号
+pub fn parse_errno(name: &str) -> Option<i32> {
+    if let Ok(no) = name.parse::<i32>() {
+        return if (1..4096).contains(&no) {
+            Some(no)
+        } else {
+            None
+        };
+    }
+    (1..=libc::EHWPOISON).find(|no| {
+        let errno = Errno::from_i32(*no);
+        Errno::UnknownErrno != errno && format!("{:?}", errno) == name
+    })
+}
+
+/// 戻り値を整形
+///
+/// エラーの場合は、errno名と説明を表示する
+/// fdsがあれば、ファイルディスクリプタにパスを付ける(-y)
+pub fn format_ret(no: i64, ret: u64, fds: Option<&mut FdTable>) -> String {
+    if let Some(errno) = to_errno(ret) {
+        return format_errno(errno);
+    }
+    match to_signature(no).map_or(ArgKind::Long, |s| s.ret) {
+        ArgKind::Fd => format_fd(ArgKind::Fd, ret, fds),
+        kind => format_value(kind, ret),
+    }
+}
+
+/// 値を種類に応じて整形
+pub fn format_value(kind: ArgKind, val: u64) -> String {
+    match kind {
+        ArgKind::Int | ArgKind::Fd => format!("{}", val as i32),
+        ArgKind::Long => format!("{}", val as i64),
+        ArgKind::Uint => format!("{}", val),
+        ArgKind::Hex | ArgKind::IoctlArg => format!("0x{:x}", val),
+        ArgKind::Ptr
+        | ArgKind::Path
+        | ArgKind::Buf
+        | ArgKind::OutBuf
+        | ArgKind::Stat
+        | ArgKind::Timespec
+        | ArgKind::OutTimespec
+        | ArgKind::Sockaddr
+        | ArgKind::Argv
+        | ArgKind::Envp => match val {
+            0 => "NULL".to_string(),
+            v => format!("0x{:x}", v),
+        },
+        ArgKind::DirFd => match val as i32 {
+            libc::AT_FDCWD => "AT_FDCWD".to_string(),
+            fd => format!("{}", fd),
+        },
+        ArgKind::Mode => match val {
+            0 => "0".to_string(),
+            v => format!("0{:o}", v),
+        },
+        ArgKind::OpenFlags => format_open_flags(val),
+        ArgKind::AtFlags => format_flags(val, AT_FLAGS),
+        ArgKind::Prot => match val {
+            0 => "PROT_NONE".to_string(),
+            v => format_flags(v, PROT_FLAGS),
+        },
+        ArgKind::MapFlags => format_flags(val, MAP_FLAGS),
+        ArgKind::Whence => match val as i32 {
+            libc::SEEK_SET => "SEEK_SET".to_string(),
+            libc::SEEK_CUR => "SEEK_CUR".to_string(),
+            libc::SEEK_END => "SEEK_END".to_string(),
+            w => format!("{}", w),
+        },
+        ArgKind::Signal => match Signal::try_from(val as i32) {
+            Ok(s) => s.as_str().to_string(),
+            Err(_) => format!("{}", val as i64),
+        },
+        ArgKind::AccessMode => match val {
+            0 => "F_OK".to_string(),
+            v => format_flags(v, ACCESS_MODES),
+        },
+        ArgKind::CloneFlags => format_clone_flags(val),
+        ArgKind::FutexOp => format_futex_op(val),
+        ArgKind::IoctlReq => format_ioctl_request(val),
+    }
+}
+
+/// AT_*フラグ
+const AT_FLAGS: &[(u64, &str)] = &[
+    (libc::AT_SYMLINK_NOFOLLOW as u64, "AT_SYMLINK_NOFOLLOW"),
+    (libc::AT_REMOVEDIR as u64, "AT_REMOVEDIR"),
+    (libc::AT_SYMLINK_FOLLOW as u64, "AT_SYMLINK_FOLLOW"),
+    (libc::AT_NO_AUTOMOUNT as u64, "AT_NO_AUTOMOUNT"),
+    (libc::AT_EMPTY_PATH as u64, "AT_EMPTY_PATH"),
+];
+
+/// PROT_*フラグ
+const PROT_FLAGS: &[(u64, &str)] = &[
+    (libc::PROT_READ as u64, "PROT_READ"),
+    (libc::PROT_WRITE as u64, "PROT_WRITE"),
+    (libc::PROT_EXEC as u64, "PROT_EXEC"),
+];
+
+/// MAP_*フラグ
+const MAP_FLAGS: &[(u64, &str)] = &[
+    (libc::MAP_SHARED as u64, "MAP_SHARED"),
+    (libc::MAP_PRIVATE as u64, "MAP_PRIVATE"),
+    (libc::MAP_FIXED as u64, "MAP_FIXED"),
+    (libc::MAP_ANONYMOUS as u64, "MAP_ANONYMOUS"),
+    (libc::MAP_GROWSDOWN as u64, "MAP_GROWSDOWN"),
+    (libc::MAP_DENYWRITE as u64, "MAP_DENYWRITE"),
+    (libc::MAP_NORESERVE as u64, "MAP_NORESERVE"),
+    (libc::MAP_POPULATE as u64, "MAP_POPULATE"),
+    (libc::MAP_STACK as u64, "MAP_STACK"),
+    (libc::MAP_FIXED_NOREPLACE as u64, "MAP_FIXED_NOREPLACE"),
+];
+
+/// アクセス権の確認(R_OK等)
+const ACCESS_MODES: &[(u64, &str)] = &[
+    (libc::R_OK as u64, "R_OK"),
+    (libc::W_OK as u64, "W_OK"),
+    (libc::X_OK as u64, "X_OK"),
+];
+
+/// CLONE_*フラグ
+const CLONE_FLAGS: &[(u64, &str)] = &[
+    (libc::CLONE_VM as u64, "CLONE_VM"),
+    (libc::CLONE_FS as u64, "CLONE_FS"),
+    (libc::CLONE_FILES as u64, "CLONE_FILES"),
+    (libc::CLONE_SIGHAND as u64, "CLONE_SIGHAND"),
+    (0x1000, "CLONE_PIDFD"),
+    (libc::CLONE_PTRACE as u64, "CLONE_PTRACE"),
+    (libc::CLONE_VFORK as u64, "CLONE_VFORK"),
+    (libc::CLONE_PARENT as u64, "CLONE_PARENT"),
+    (libc::CLONE_THREAD as u64, "CLONE_THREAD"),
+    (libc::CLONE_NEWNS as u64, "CLONE_NEWNS"),
+    (libc::CLONE_SYSVSEM as u64, "CLONE_SYSVSEM"),
+    (libc::CLONE_SETTLS as u64, "CLONE_SETTLS"),
+    (libc::CLONE_PARENT_SETTID as u64, "CLONE_PARENT_SETTID"),
+    (libc::CLONE_CHILD_CLEARTID as u64, "CLONE_CHILD_CLEARTID"),
+    (libc::CLONE_DETACHED as u64, "CLONE_DETACHED"),
+    (libc::CLONE_UNTRACED as u64, "CLONE_UNTRACED"),
+    (libc::CLONE_CHILD_SETTID as u64, "CLONE_CHILD_SETTID"),
+    (libc::CLONE_NEWCGROUP as u64, "CLONE_NEWCGROUP"),
+    (libc::CLONE_NEWUTS as u64, "CLONE_NEWUTS"),
+    (libc::CLONE_NEWIPC as u64, "CLONE_NEWIPC"),
+    (libc::CLONE_NEWUSER as u64, "CLONE_NEWUSER"),
+    (libc::CLONE_NEWPID as u64, "CLONE_NEWPID"),
+    (libc::CLONE_NEWNET as u64, "CLONE_NEWNET"),
+    (libc::CLONE_IO as u64, "CLONE_IO"),
+];
+
+/// cloneの終了時のシグナル(CSIGNAL)
+const CLONE_SIGNAL_MASK: u64 = 0xFF;
+
+/// FUTEX_*の操作
+const FUTEX_OPS: &[(u64, &str)] = &[
+    (libc::FUTEX_WAIT as u64, "FUTEX_WAIT"),
+    (libc::FUTEX_WAKE as u64, "FUTEX_WAKE"),
+    (libc::FUTEX_FD as u64, "FUTEX_FD"),
+    (libc::FUTEX_REQUEUE as u64, "FUTEX_REQUEUE"),
+    (libc::FUTEX_CMP_REQUEUE as u64, "FUTEX_CMP_REQUEUE"),
+    (libc::FUTEX_WAKE_OP as u64, "FUTEX_WAKE_OP"),
+    (libc::FUTEX_LOCK_PI as u64, "FUTEX_LOCK_PI"),
+    (libc::FUTEX_UNLOCK_PI as u64, "FUTEX_UNLOCK_PI"),
+    (libc::FUTEX_TRYLOCK_PI as u64, "FUTEX_TRYLOCK_PI"),
+    (libc::FUTEX_WAIT_BITSET as u64, "FUTEX_WAIT_BITSET"),
+    (libc::FUTEX_WAKE_BITSET as u64, "FUTEX_WAKE_BITSET"),
+    (libc::FUTEX_WAIT_REQUEUE_PI as u64, "FUTEX_WAIT_REQUEUE_PI"),
+    (libc::FUTEX_CMP_REQUEUE_PI as u64, "FUTEX_CMP_REQUEUE_PI"),
+    (13, "FUTEX_LOCK_PI2"),
+];
+
+/// O_*フラグ(アクセスモード以外)
+const OPEN_FLAGS: &[(u64, &str)] = &[
+    (libc::O_CREAT as u64, "O_CREAT"),
+    (libc::O_EXCL as u64, "O_EXCL"),
+    (libc::O_NOCTTY as u64, "O_NOCTTY"),
+    (libc::O_TRUNC as u64, "O_TRUNC"),
+    (libc::O_APPEND as u64, "O_APPEND"),
+    (libc::O_NONBLOCK as u64, "O_NONBLOCK"),
+    (libc::O_SYNC as u64, "O_SYNC"),
+    (libc::O_DSYNC as u64, "O_DSYNC"),
+    (libc::O_DIRECT as u64, "O_DIRECT"),
+    (0o100000, "O_LARGEFILE"),
+    (libc::O_DIRECTORY as u64, "O_DIRECTORY"),
+    (libc::O_NOFOLLOW as u64, "O_NOFOLLOW"),
+    (libc::O_NOATIME as u64, "O_NOATIME"),
+    (libc::O_CLOEXEC as u64, "O_CLOEXEC"),
+    (libc::O_PATH as u64, "O_PATH"),
+];
+
+/// CLONE_*フラグを整形
+///
+/// 下位8bitは、子プロセスの終了時に親プロセスへ送るシグナル(CLONE_VM|SIGCHLD)
+fn format_clone_flags(val: u64) -> String {
+    let signal = match val & CLONE_SIGNAL_MASK {
+        0 => None,
+        s => Some(format_value(ArgKind::Signal, s)),
+    };
+    match (val & !CLONE_SIGNAL_MASK, signal) {
+        (0, Some(s)) => s,
+        (flags, Some(s)) => format!("{}|{}", format_flags(flags, CLONE_FLAGS), s),
+        (flags, None) => format_flags(flags, CLONE_FLAGS),
+    }
+}
+
+/// FUTEX_*の操作を整形
+///
+/// FUTEX_PRIVATE_FLAGは操作名に_PRIVATEを付け、FUTEX_CLOCK_REALTIMEはフラグとして表示する
+fn format_futex_op(val: u64) -> String {
+    let private = libc::FUTEX_PRIVATE_FLAG as u64;
+    let realtime = libc::FUTEX_CLOCK_REALTIME as u64;
+    let mut s = match FUTEX_OPS
+        .iter()
+        .find(|(op, _)| *op == val & !(private | realtime))
+    {
+        Some((_, name)) => name.to_string(),
+        None => return format!("0x{:x}", val),
+    };
+    if 0 != val & private {
+        s.push_str("_PRIVATE");
+    }
+    if 0 != val & realtime {
+        s.push_str("|FUTEX_CLOCK_REALTIME");
+    }
+    s
+}
+
+/// O_*フラグを整形
+///
+/// 下位2bitのアクセスモード(O_RDONLY/O_WRONLY/O_RDWR)は必ず表示する
+fn format_open_flags(val: u64) -> String {
+    let mode = match val & libc::O_ACCMODE as u64 {
+        0 => "O_RDONLY".to_string(),
+        1 => "O_WRONLY".to_string(),
+        2 => "O_RDWR".to_string(),
+        m => format!("0x{:x}", m),
+    };
+    match val & !(libc::O_ACCMODE as u64) {
+        0 => mode,
+        v => format!("{}|{}", mode, format_flags(v, OPEN_FLAGS)),
+    }
+}
+
+/// フラグを|区切りで整形
+///
+/// テーブルの先頭から照合するため、複数bitのフラグ(O_SYNC等)は含まれるフラグより前に定義する
+/// テーブルにないbitは16進数で表示する
+fn format_flags(val: u64, table: &[(u64, &str)]) -> String {
+    if 0 == val {
+        return "0".to_string();
+    }
+    let mut rest = val;
+    let mut names = vec![];
+    for (flag, name) in table {
+        if 0 != *flag && flag & rest == *flag {
+            names.push(*name);
+            rest &= !flag;
+        }
+    }
+    let mut s = names.join("|");
+    if 0 != rest {
+        if !s.is_empty() {
+            s.push('|');
+        }
+        s.push_str(&format!("0x{:x}", rest));
+    }
+    s
+}
+
+/// システムコールNo→システムコール名
+pub fn to_name(no: i64) -> Option<&'static str> {
+    if no >= UNIFIED_SYSCALL_BASE {
+        UNIFIED_SYSCALLS
+            .get((no - UNIFIED_SYSCALL_BASE) as usize)
+            .copied()
+    } else if no >= 0 {
+        ARCH_SYSCALLS.get(no as usize).copied()
+    } else {
+        None
+    }
+}
+
+/// システムコールNo→表示用のシステムコール名
+///
+/// 不明な番号はsyscall_NNNとする
+pub fn to_display_name(no: i64) -> String {
+    match to_name(no) {
+        Some(name) => name.to_string(),
+        None => format!("syscall_{}", no),
+    }
+}
+
+/// システムコール名→システムコールNo
+pub fn to_number(name: &str) -> Option<i64> {
+    syscalls().find(|(_, n)| *n == name).map(|(no, _)| no)
+}
+
+/// 似た名前のシステムコールを取得
+///
+/// 編集距離が2以下、もしくは名前の一部に含むものを候補とする
+pub fn similar_names(name: &str) -> Vec<&'static str> {
+    syscalls()
+        .map(|(_, n)| n)
+        .filter(|n| edit_distance(name, n) <= 2 || (name.len() >= 3 && n.contains(name)))
+        .collect()
+}
+
+/// システムコールの分類(-e trace=%file等)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Category {
+    File,    // ファイル名を引数とする
+    Desc,    // ファイルディスクリプタを引数・戻り値とする
+    Network, // ソケット操作
+    Memory,  // メモリのマッピング・保護
+    Process, // プロセスの生成・終了・待機・シグナル送信
+    Signal,  // シグナルの送信・ハンドラ・マスク
+}
+
+/// ネットワークのシステムコール(%network)
+const NETWORK_SYSCALLS: &[i64] = &[
+    libc::SYS_socket,
+    libc::SYS_socketpair,
+    libc::SYS_bind,
+    libc::SYS_listen,
+    libc::SYS_connect,
+    libc::SYS_accept,
+    libc::SYS_accept4,
+    libc::SYS_getsockname,
+    libc::SYS_getpeername,
+    libc::SYS_sendto,
+    libc::SYS_recvfrom,
+    libc::SYS_sendmsg,
+    libc::SYS_recvmsg,
+    libc::SYS_sendmmsg,
+    libc::SYS_recvmmsg,
+    libc::SYS_shutdown,
+    libc::SYS_setsockopt,
+    libc::SYS_getsockopt,
+];
+
+/// メモリのシステムコール(%memory)
+const MEMORY_SYSCALLS: &[i64] = &[
+    libc::SYS_brk,
+    libc::SYS_mmap,
+    libc::SYS_munmap,
+    libc::SYS_mremap,
+    libc::SYS_mprotect,
+    libc::SYS_pkey_mprotect,
+    libc::SYS_madvise,
+    libc::SYS_msync,
+    libc::SYS_mincore,
+    libc::SYS_mlock,
+    libc::SYS_mlock2,
+    libc::SYS_munlock,
+    libc::SYS_mlockall,
+    libc::SYS_munlockall,
+    libc::SYS_remap_file_pages,
+    libc::SYS_mbind,
+    libc::SYS_get_mempolicy,
+    libc::SYS_set_mempolicy,
+    libc::SYS_shmat,
+];
+
+/// プロセスのシステムコール(%process)
+const PROCESS_SYSCALLS: &[i64] = &[
+    libc::SYS_fork,
+    libc::SYS_vfork,
+    libc::SYS_clone,
+    libc::SYS_clone3,
+    libc::SYS_execve,
+    libc::SYS_execveat,
+    libc::SYS_exit,
+    libc::SYS_exit_group,
+    libc::SYS_wait4,
+    libc::SYS_waitid,
+    libc::SYS_kill,
+    libc::SYS_tkill,
+    libc::SYS_tgkill,
+    libc::SYS_rt_sigqueueinfo,
+    libc::SYS_rt_tgsigqueueinfo,
+    libc::SYS_pidfd_send_signal,
+];
+
+/// シグナル番号を引数としないシグナルのシステムコール(%signal)
+const SIGNAL_SYSCALLS: &[i64] = &[
+    libc::SYS_rt_sigprocmask,
+    libc::SYS_rt_sigreturn,
+    libc::SYS_rt_sigpending,
+    libc::SYS_rt_sigtimedwait,
+    libc::SYS_rt_sigsuspend,
+    libc::SYS_sigaltstack,
+    libc::SYS_signalfd,
+    libc::SYS_signalfd4,
+    libc::SYS_pause,
+];
+
+impl Category {
+    /// 分類名(%以降)→分類
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "file" => Some(Category::File),
+            "desc" => Some(Category::Desc),
+            "network" | "net" => Some(Category::Network),
+            "memory" => Some(Category::Memory),
+            "process" => Some(Category::Process),
+            "signal" => Some(Category::Signal),
+            _ => None,
+        }
+    }
+
+    /// 分類に含まれるシステムコールか
+    ///
+    /// シグネチャのあるシステムコールのみ含む
+    /// ファイル・ファイルディスクリプタは引数・戻り値の種類から判定し、シグナルはシグナル番号の引数でも判定する
+    pub fn contains(&self, no: i64) -> bool {
+        let sig = match to_signature(no) {
+            Some(s) => s,
+            None => return false,
+        };
+        let has_arg = |kind: ArgKind| sig.args.contains(&kind);
+        match self {
+            Category::File => has_arg(ArgKind::Path),
+            Category::Desc => {
+                has_arg(ArgKind::Fd) || has_arg(ArgKind::DirFd) || ArgKind::Fd == sig.ret
+            }
+            Category::Network => NETWORK_SYSCALLS.contains(&no),
+            Category::Memory => MEMORY_SYSCALLS.contains(&no),
+            Category::Process => PROCESS_SYSCALLS.contains(&no),
+            Category::Signal => has_arg(ArgKind::Signal) || SIGNAL_SYSCALLS.contains(&no),
+        }
+    }
+
+    /// 分類に含まれるシステムコールNoを取得
+    pub fn get_numbers(&self) -> Vec<i64> {
+        syscalls()
+            .map(|(no, _)| no)
+            .filter(|no| self.contains(*no))
+            .collect()
+    }
+}
+
+/// システムコールの分類を取得(表示の色分け用)
+///
+/// 複数の分類に含まれる場合(execve等)は、プロセス・ネットワーク・メモリ・ファイルの順に優先する
+/// (ファイルディスクリプタ・シグナルの分類は返さない)
+pub fn to_category(no: i64) -> Option<Category> {
+    [
+        Category::Process,
+        Category::Network,
+        Category::Memory,
+        Category::File,
+    ]
+    .iter()
+    .copied()
+    .find(|c| c.contains(no))
+}
+
+/// システムコール名の一覧(name,...)→システムコールNoの集合
+///
+/// 名前の代わりに、%file・%desc・%network・%memory・%process・%signalで分類を指定できる
+pub fn parse_syscall_set(set: &str) -> Result<HashSet<i64>, String> {
+    let mut nos = HashSet::new();
+    for name in set.split(',') {
+        if let Some(class) = name.strip_prefix('%') {
+            let category = Category::from_name(class)
+                .ok_or_else(|| format!("unknown syscall class: {}", name))?;
+            nos.extend(category.get_numbers());
+            continue;
+        }
+        let no = to_number(name).ok_or_else(|| {
+            let similar = similar_names(name);
+            if similar.is_empty() {
+                format!("unknown syscall: {}", name)
+            } else {
+                format!(
+                    "unknown syscall: {} (did you mean {}?)",
+                    name,
+                    similar.join(", ")
+                )
+            }
+        })?;
+        nos.insert(no);
+    }
+    Ok(nos)
+}
+
+/// 全システムコール(No, 名前)
+fn syscalls() -> impl Iterator<Item = (i64, &'static str)> {
+    let arch = ARCH_SYSCALLS
+        .iter()
+        .enumerate()
+        .map(|(i, n)| (i as i64, *n));
+    let unified = UNIFIED_SYSCALLS
+        .iter()
+        .enumerate()
+        .map(|(i, n)| (UNIFIED_SYSCALL_BASE + i as i64, *n));
+    arch.chain(unified)
+}
+
+/// 編集距離(レーベンシュタイン距離)
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut prev = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur.push((prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+/// x86-64固有の番号のシステムコール名(0〜)
+const ARCH_SYSCALLS: &[&str] = &[
+    "read",
+    "write",
+    "open",
+    "close",
+    "stat",
+    "fstat",
+    "lstat",
+    "poll",
+    "lseek",
+    "mmap",
+    "mprotect",
+    "munmap",
+    "brk",
+    "rt_sigaction",
+    "rt_sigprocmask",
+    "rt_sigreturn",
+    "ioctl",
+    "pread64",
+    "pwrite64",
+    "readv",
+    "writev",
+    "access",
+    "pipe",
+    "select",
+    "sched_yield",
+    "mremap",
+    "msync",
+    "mincore",
+    "madvise",
+    "shmget",
+    "shmat",
+    "shmctl",
+    "dup",
+    "dup2",
+    "pause",
+    "nanosleep",
+    "getitimer",
+    "alarm",
+    "setitimer",
+    "getpid",
+    "sendfile",
+    "socket",
+    "connect",
+    "accept",
+    "sendto",
+    "recvfrom",
+    "sendmsg",
+    "recvmsg",
+    "shutdown",
+    "bind",
+    "listen",
+    "getsockname",
+    "getpeername",
+    "socketpair",
+    "setsockopt",
+    "getsockopt",
+    "clone",
+    "fork",
+    "vfork",
+    "execve",
+    "exit",
+    "wait4",
+    "kill",
+    "uname",
+    "semget",
+    "semop",
+    "semctl",
+    "shmdt",
+    "msgget",
+    "msgsnd",
+    "msgrcv",
+    "msgctl",
+    "fcntl",
+    "flock",
+    "fsync",
+    "fdatasync",
+    "truncate",
+    "ftruncate",
+    "getdents",
+    "getcwd",
+    "chdir",
+    "fchdir",
+    "rename",
+    "mkdir",
+    "rmdir",
+    "creat",
+    "link",
+    "unlink",
+    "symlink",
+    "readlink",
+    "chmod",
+    "fchmod",
+    "chown",
+    "fchown",
+    "lchown",
+    "umask",
+    "gettimeofday",
+    "getrlimit",
+    "getrusage",
+    "sysinfo",
+    "times",
+    "ptrace",
+    "getuid",
+    "syslog",
+    "getgid",
+    "setuid",
+    "setgid",
+    "geteuid",
+    "getegid",
+    "setpgid",
+    "getppid",
+    "getpgrp",
+    "setsid",
+    "setreuid",
+    "setregid",
+    "getgroups",
+    "setgroups",
+    "setresuid",
+    "getresuid",
+    "setresgid",
+    "getresgid",
+    "getpgid",
+    "setfsuid",
+    "setfsgid",
+    "getsid",
+    "capget",
+    "capset",
+    "rt_sigpending",
+    "rt_sigtimedwait",
+    "rt_sigqueueinfo",
+    "rt_sigsuspend",
+    "sigaltstack",
+    "utime",
+    "mknod",
+    "uselib",
+    "personality",
+    "ustat",
+    "statfs",
+    "fstatfs",
+    "sysfs",
+    "getpriority",
+    "setpriority",
+    "sched_setparam",
+    "sched_getparam",
+    "sched_setscheduler",
+    "sched_getscheduler",
+    "sched_get_priority_max",
+    "sched_get_priority_min",
+    "sched_rr_get_interval",
+    "mlock",
+    "munlock",
+    "mlockall",
+    "munlockall",
+    "vhangup",
+    "modify_ldt",
+    "pivot_root",
+    "_sysctl",
+    "prctl",
+    "arch_prctl",
+    "adjtimex",
+    "setrlimit",
+    "chroot",
+    "sync",
+    "acct",
+    "settimeofday",
+    "mount",
+    "umount2",
+    "swapon",
+    "swapoff",
+    "reboot",
+    "sethostname",
+    "setdomainname",
+    "iopl",
+    "ioperm",
+    "create_module",
+    "init_module",
+    "delete_module",
+    "get_kernel_syms",
+    "query_module",
+    "quotactl",
+    "nfsservctl",
+    "getpmsg",
+    "putpmsg",
+    "afs_syscall",
+    "tuxcall",
+    "security",
+    "gettid",
+    "readahead",
+    "setxattr",
+    "lsetxattr",
+    "fsetxattr",
+    "getxattr",
+    "lgetxattr",
+    "fgetxattr",
+    "listxattr",
+    "llistxattr",
+    "flistxattr",
+    "removexattr",
+    "lremovexattr",
+    "fremovexattr",
+    "tkill",
+    "time",
+    "futex",
+    "sched_setaffinity",
+    "sched_getaffinity",
+    "set_thread_area",
+    "io_setup",
+    "io_destroy",
+    "io_getevents",
+    "io_submit",
+    "io_cancel",
+    "get_thread_area",
+    "lookup_dcookie",
+    "epoll_create",
+    "epoll_ctl_old",
+    "epoll_wait_old",
+    "remap_file_pages",
+    "getdents64",
+    "set_tid_address",
+    "restart_syscall",
+    "semtimedop",
+    "fadvise64",
+    "timer_create",
+    "timer_settime",
+    "timer_gettime",
+    "timer_getoverrun",
+    "timer_delete",
+    "clock_settime",
+    "clock_gettime",
+    "clock_getres",
+    "clock_nanosleep",
+    "exit_group",
+    "epoll_wait",
+    "epoll_ctl",
+    "tgkill",
+    "utimes",
+    "vserver",
+    "mbind",
+    "set_mempolicy",
+    "get_mempolicy",
+    "mq_open",
+    "mq_unlink",
+    "mq_timedsend",
+    "mq_timedreceive",
+    "mq_notify",
+    "mq_getsetattr",
+    "kexec_load",
+    "waitid",
+    "add_key",
+    "request_key",
+    "keyctl",
+    "ioprio_set",
+    "ioprio_get",
+    "inotify_init",
+    "inotify_add_watch",
+    "inotify_rm_watch",
+    "migrate_pages",
+    "openat",
+    "mkdirat",
+    "mknodat",
+    "fchownat",
+    "futimesat",
+    "newfstatat",
+    "unlinkat",
+    "renameat",
+    "linkat",
+    "symlinkat",
+    "readlinkat",
+    "fchmodat",
+    "faccessat",
+    "pselect6",
+    "ppoll",
+    "unshare",
+    "set_robust_list",
+    "get_robust_list",
+    "splice",
+    "tee",
+    "sync_file_range",
+    "vmsplice",
+    "move_pages",
+    "utimensat",
+    "epoll_pwait",
+    "signalfd",
+    "timerfd_create",
+    "eventfd",
+    "fallocate",
+    "timerfd_settime",
+    "timerfd_gettime",
+    "accept4",
+    "signalfd4",
+    "eventfd2",
+    "epoll_create1",
+    "dup3",
+    "pipe2",
+    "inotify_init1",
+    "preadv",
+    "pwritev",
+    "rt_tgsigqueueinfo",
+    "perf_event_open",
+    "recvmmsg",
+    "fanotify_init",
+    "fanotify_mark",
+    "prlimit64",
+    "name_to_handle_at",
+    "open_by_handle_at",
+    "clock_adjtime",
+    "syncfs",
+    "sendmmsg",
+    "setns",
+    "getcpu",
+    "process_vm_readv",
+    "process_vm_writev",
+    "kcmp",
+    "finit_module",
+    "sched_setattr",
+    "sched_getattr",
+    "renameat2",
+    "seccomp",
+    "getrandom",
+    "memfd_create",
+    "kexec_file_load",
+    "bpf",
+    "execveat",
+    "userfaultfd",
+    "membarrier",
+    "mlock2",
+    "copy_file_range",
+    "preadv2",
+    "pwritev2",
+    "pkey_mprotect",
+    "pkey_alloc",
+    "pkey_free",
+    "statx",
+    "io_pgetevents",
+    "rseq",
+    "uretprobe",
+];
+
+/// 全アーキテクチャ共通の番号の先頭
+///
+/// 424以降はアーキテクチャ間で番号が統一されている
+const UNIFIED_SYSCALL_BASE: i64 = 424;
+
+/// 全アーキテクチャ共通の番号のシステムコール名(UNIFIED_SYSCALL_BASE〜)
+const UNIFIED_SYSCALLS: &[&str] = &[
+    "pidfd_send_signal",
+    "io_uring_setup",
+    "io_uring_enter",
+    "io_uring_register",
+    "open_tree",
+    "move_mount",
+    "fsopen",
+    "fsconfig",
+    "fsmount",
+    "fspick",
+    "pidfd_open",
+    "clone3",
+    "close_range",
+    "openat2",
+    "pidfd_getfd",
+    "faccessat2",
+    "process_madvise",
+    "epoll_pwait2",
+    "mount_setattr",
+    "quotactl_fd",
+    "landlock_create_ruleset",
+    "landlock_add_rule",
+    "landlock_restrict_self",
+    "memfd_secret",
+    "process_mrelease",
+    "futex_waitv",
+    "set_mempolicy_home_node",
+    "cachestat",
+    "fchmodat2",
+    "map_shadow_stack",
+    "futex_wake",
+    "futex_wait",
+    "futex_requeue",
+    "statmount",
+    "listmount",
+    "lsm_get_self_attr",
+    "lsm_set_self_attr",
+    "lsm_list_modules",
+    "mseal",
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::FakeMemory;
+
+    /// テスト用レジスタ生成
+    fn regs(args: [u64; 6]) -> user_regs_struct {
+        let mut regs: user_regs_struct = unsafe { std::mem::zeroed() };
+        regs.rdi = args[0];
+        regs.rsi = args[1];
+        regs.rdx = args[2];
+        regs.r10 = args[3];
+        regs.r8 = args[4];
+        regs.r9 = args[5];
+        regs
+    }
+
+    /// 開始時・終了時の引数をまとめて整形
+    fn format_all<M: ReadMemory>(
+        no: i64,
+        args: [u64; 6],
+        ret: Option<u64>,
+        mem: &M,
+        limit: usize,
+    ) -> String {
+        let regs = regs(args);
+        let opt = FormatOption::new(limit, false);
+        let inputs = format_input_args(no, &regs, mem, opt);
+        format_args(no, &regs, ret, &inputs, mem, opt, None)
+    }
+
+    fn memory() -> FakeMemory {
+        FakeMemory(vec![
+            (0x7FFD_1000, b"/etc/ld.so.cache\0".to_vec()),
+            (0x2000, b"Hello, \"world\"!\n\x01\xff".to_vec()),
+        ])
+    }
+
+    #[test]
+    fn test_format_args() {
+        let at_fdcwd = libc::AT_FDCWD as i64 as u64;
+        let cases = vec![
+            (
+                libc::SYS_openat,
+                [at_fdcwd, 0x7FFD_1000, 0o2000000, 0, 9, 9],
+                "AT_FDCWD, \"/etc/ld.so.cache\", O_RDONLY|O_CLOEXEC, 0",
+            ),
+            (
+                libc::SYS_openat,
+                [3, 0x1000, 0o1101, 0o644, 0, 0],
+                "3, 0x1000 <fault>, O_WRONLY|O_CREAT|O_TRUNC, 0644",
+            ),
+            (
+                libc::SYS_write,
+                [1, 0x2000, 15, 0, 0, 0],
+                "1, \"Hello, \\\"world\\\"!\", 15",
+            ),
+            (libc::SYS_close, [u64::MAX, 0, 0, 0, 0, 0], "-1"),
+            // intの引数は下位32bitのみ有効
+            (libc::SYS_close, [0xFFFF_FFFF, 0, 0, 0, 0, 0], "-1"),
+            (
+                libc::SYS_mmap,
+                [0, 8192, 3, 0x22, 0xFFFF_FFFF, 0],
+                "NULL, 8192, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0x0",
+            ),
+            (
+                libc::SYS_mprotect,
+                [0x7000, 4096, 0, 0, 0, 0],
+                "0x7000, 4096, PROT_NONE",
+            ),
+            (libc::SYS_lseek, [3, 0, 2, 0, 0, 0], "3, 0, SEEK_END"),
+            (
+                libc::SYS_lseek,
+                [3, -0x1_0000_0000i64 as u64, 1, 0, 0, 0],
+                "3, -4294967296, SEEK_CUR",
+            ),
+            (libc::SYS_kill, [100, 9, 0, 0, 0, 0], "100, SIGKILL"),
+            (
+                libc::SYS_newfstatat,
+                [at_fdcwd, 0x1000, 0x3000, 0x1100, 0, 0],
+                "AT_FDCWD, 0x1000 <fault>, 0x3000, AT_SYMLINK_NOFOLLOW|AT_EMPTY_PATH",
+            ),
+            (libc::SYS_getpid, [1, 2, 3, 4, 5, 6], ""),
+            // 未対応のシステムコールは、6つの引数をすべて16進数で表示
+            (9999, [1, 2, 3, 4, 5, 0xFF], "0x1, 0x2, 0x3, 0x4, 0x5, 0xff"),
+        ];
+        for (no, args, expected) in cases {
+            assert_eq!(expected, format_all(no, args, None, &memory(), 32));
+        }
+    }
+
+    #[test]
+    fn test_format_buffers() {
+        let mem = memory();
+        let format = |no, args, ret, limit| format_all(no, args, ret, &mem, limit);
+        {
+            // limitで打ち切り、制御文字はエスケープする
+            let args = [1, 0x2000, 18, 0, 0, 0];
+            assert_eq!(
+                "1, \"Hello\"..., 18",
+                format(libc::SYS_write, args, Some(18), 5)
+            );
+            assert_eq!(
+                "1, \"Hello, \\\"world\\\"!\\n\\x01\\xff\", 18",
+                format(libc::SYS_write, args, Some(18), 32)
+            );
+        }
+        {
+            // カーネルが書き込むバッファは、終了時に戻り値のサイズ分を表示
+            let args = [3, 0x2000, 832, 0, 0, 0];
+            assert_eq!("3, 0x2000, 832", format(libc::SYS_read, args, None, 32));
+            assert_eq!(
+                "3, \"Hell\", 832",
+                format(libc::SYS_read, args, Some(4), 32)
+            );
+            assert_eq!(
+                "3, 0x2000, 832",
+                format(libc::SYS_read, args, Some(-11i64 as u64), 32)
+            );
+            assert_eq!(
+                "3, 0x3000 <fault>, 832",
+                format(libc::SYS_read, [3, 0x3000, 832, 0, 0, 0], Some(8), 32)
+            );
+        }
+        {
+            // NULLは読み込まない
+            assert_eq!(
+                "NULL, F_OK",
+                format(libc::SYS_access, [0, 0, 0, 0, 0, 0], None, 32)
+            );
+        }
+        {
+            // 打ち切りは文字単位(途中で切れたUTF-8の文字は表示しない)
+            let mem = FakeMemory(vec![(0x2000, "aあい".as_bytes().to_vec())]);
+            let format =
+                |limit| format_all(libc::SYS_write, [1, 0x2000, 7, 0, 0, 0], None, &mem, limit);
+            assert_eq!("1, \"a\"..., 7", format(3));
+            assert_eq!("1, \"a\\xe3\\x81\\x82\"..., 7", format(4));
+            assert_eq!("1, \"a\\xe3\\x81\\x82\"..., 7", format(6));
+            assert_eq!("1, \"a\\xe3\\x81\\x82\\xe3\\x81\\x84\", 7", format(7));
+        }
+    }
+
+    #[test]
+    fn test_format_arrays() {
+        // 文字列へのポインタの配列(文字列はワード単位で読むためNULで埋める)
+        let pointers = |ps: &[u64]| ps.iter().flat_map(|p| p.to_ne_bytes()).collect::<Vec<u8>>();
+        let mem = FakeMemory(vec![
+            (0x1000, b"/bin/ls\0\0\0\0\0\0\0\0".to_vec()),
+            (0x2000, b"ls\0\0\0\0\0\0\0\0".to_vec()),
+            (0x3000, b"-l\0\0\0\0\0\0\0\0".to_vec()),
+            (0x4000, b"HOME=/root/and/more\0\0\0\0\0\0\0\0".to_vec()),
+            (
+                0x5000,
+                pointers(&[0x2000, 0x3000, 0x3000, 0x3000, 0x3000, 0x3000, 0]),
+            ),
+            (0x6000, pointers(&[0x4000, 0x4000, 0])),
+            (0x7000, pointers(&[0x2000, 0x9000, 0])),
+        ]);
+        let format = |args: [u64; 6], verbose: bool| {
+            let opt = FormatOption::new(8, verbose);
+            let regs = regs(args);
+            let inputs = format_input_args(libc::SYS_execve, &regs, &mem, opt);
+            format_args(libc::SYS_execve, &regs, None, &inputs, &mem, opt, None)
+        };
+        {
+            // 省略時は、argvは先頭の要素のみ、envpは個数のみ表示する
+            assert_eq!(
+                "\"/bin/ls\", [\"ls\", \"-l\", \"-l\", \"-l\", ...], 0x6000 /* 2 vars */",
+                format([0x1000, 0x5000, 0x6000, 0, 0, 0], false)
+            );
+        }
+        {
+            // -vの場合は全ての要素を表示する(文字列は-sで打ち切る)
+            assert_eq!(
+                "\"/bin/ls\", [\"ls\", \"-l\", \"-l\", \"-l\", \"-l\", \"-l\"], \
+                 [\"HOME=/ro\"..., \"HOME=/ro\"...]",
+                format([0x1000, 0x5000, 0x6000, 0, 0, 0], true)
+            );
+        }
+        {
+            // 読み込めない要素・配列
+            assert_eq!(
+                "\"/bin/ls\", [\"ls\", 0x9000 <fault>], 0x8000 <fault>",
+                format([0x1000, 0x7000, 0x8000, 0, 0, 0], false)
+            );
+            assert_eq!(
+                "\"/bin/ls\", 0x8000 <fault>, NULL",
+                format([0x1000, 0x8000, 0, 0, 0, 0], true)
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_structs() {
+        let mut st: libc::stat = unsafe { std::mem::zeroed() };
+        st.st_mode = libc::S_IFDIR | 0o755;
+        st.st_size = 4096;
+        let st = unsafe {
+            std::slice::from_raw_parts(
+                &st as *const libc::stat as *const u8,
+                size_of::<libc::stat>(),
+            )
+        };
+        let mem = FakeMemory(vec![
+            (0x7FFD_1000, b"/etc/ld.so.cache\0".to_vec()),
+            (
+                0x4000,
+                vec![0, 0, 0, 0, 0, 0, 0, 0, 0x40, 0x42, 0x0F, 0, 0, 0, 0, 0],
+            ),
+            (0x5000, st.to_vec()),
+            (
+                0x6000,
+                vec![2, 0, 0x1F, 0x90, 10, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+            ),
+        ]);
+        {
+            // 入力の構造体は開始時に読み込む
+            let args = [1, 0, 0x4000, 0, 0, 0];
+            assert_eq!(
+                "1, 0, {tv_sec=0, tv_nsec=1000000}, NULL",
+                format_all(libc::SYS_clock_nanosleep, args, None, &mem, 32)
+            );
+        }
+        {
+            // 出力の構造体は、成功した終了時のみ読み込む
+            let args = [libc::AT_FDCWD as i64 as u64, 0x7FFD_1000, 0x5000, 0, 0, 0];
+            assert_eq!(
+                "AT_FDCWD, \"/etc/ld.so.cache\", {st_mode=S_IFDIR|0755, st_size=4096}, 0",
+                format_all(libc::SYS_newfstatat, args, Some(0), &mem, 32)
+            );
+            assert_eq!(
+                "AT_FDCWD, \"/etc/ld.so.cache\", 0x5000, 0",
+                format_all(libc::SYS_newfstatat, args, Some(-2i64 as u64), &mem, 32)
+            );
+            assert_eq!(
+                "3, 0x7000 <fault>",
+                format_all(libc::SYS_fstat, [3, 0x7000, 0, 0, 0, 0], Some(0), &mem, 32)
+            );
+        }
+        {
+            // sockaddrは次の引数のサイズ分を読み込む(足りなければアドレスのみ)
+            let format =
+                |len| format_all(libc::SYS_connect, [3, 0x6000, len, 0, 0, 0], None, &mem, 32);
+            assert_eq!(
+                "3, {sa_family=AF_INET, sin_port=htons(8080), sin_addr=inet_addr(\"10.0.0.1\")}, 16",
+                format(16)
+            );
+            assert_eq!("3, 0x6000, 4", format(4));
+            assert_eq!("3, 0x6000 <fault>, 32", format(32));
+        }
+        {
+            // ioctlの引数は、リクエストにより読み込む時点が異なる
+            let tiocgwinsz = libc::TIOCGWINSZ;
+            assert_eq!(
+                "1, TIOCGWINSZ, {ws_row=16960, ws_col=15, ws_xpixel=0, ws_ypixel=0}",
+                format_all(
+                    libc::SYS_ioctl,
+                    [1, tiocgwinsz, 0x4008, 0, 0, 0],
+                    Some(0),
+                    &mem,
+                    32
+                )
+            );
+            assert_eq!(
+                "1, TIOCGWINSZ, 0x4008",
+                format_all(
+                    libc::SYS_ioctl,
+                    [1, tiocgwinsz, 0x4008, 0, 0, 0],
+                    None,
+                    &mem,
+                    32
+                )
+            );
+            let fionbio = libc::FIONBIO;
+            assert_eq!(
+                "3, FIONBIO, [0]",
+                format_all(
+                    libc::SYS_ioctl,
+                    [3, fionbio, 0x4000, 0, 0, 0],
+                    None,
+                    &mem,
+                    32
+                )
+            );
+            assert_eq!(
+                "3, TCGETS, 0x4000",
+                format_all(
+                    libc::SYS_ioctl,
+                    [3, 0x5401, 0x4000, 0, 0, 0],
+                    Some(0),
+                    &mem,
+                    32
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_ret() {
+        assert_eq!("3", format_ret(libc::SYS_openat, 3, None));
+        assert_eq!(
+            "0x7f0000001000",
+            format_ret(libc::SYS_mmap, 0x7F00_0000_1000, None)
+        );
+        assert_eq!(
+            "-1 ENOENT (No such file or directory)",
+            format_ret(libc::SYS_openat, -2i64 as u64, None)
+        );
+        assert_eq!("0", format_ret(9999, 0, None));
+        assert_eq!(
+            "4294967296",
+            format_ret(libc::SYS_read, 0x1_0000_0000, None)
+        );
+        assert_eq!("-4096", format_ret(libc::SYS_lseek, -4096i64 as u64, None));
+        assert_eq!(
+            "0x7f0000002000",
+            format_ret(libc::SYS_mremap, 0x7F00_0000_2000, None)
+        );
+    }
+
+    #[test]
+    fn test_format_errno() {
+        let cases = vec![
+            (libc::EPERM, "-1 EPERM (Operation not permitted)"),
+            (libc::EAGAIN, "-1 EAGAIN (Resource temporarily unavailable)"),
+            (libc::EBADF, "-1 EBADF (Bad file descriptor)"),
+            (libc::ENOSYS, "-1 ENOSYS (Function not implemented)"),
+            (
+                libc::EHWPOISON,
+                "-1 EHWPOISON (Memory page has hardware error)",
+            ),
+            // カーネル内部のエラー
+            (512, "? ERESTARTSYS (To be restarted if SA_RESTART is set)"),
+            (516, "? ERESTART_RESTARTBLOCK (Interrupted by signal)"),
+            // 不明なエラー
+            (520, "-1 E520 (Unknown error 520)"),
+            (4095, "-1 E4095 (Unknown error 4095)"),
+        ];
+        for (no, expected) in cases {
+            assert_eq!(expected, format_errno(no));
+        }
+
+        assert_eq!(Some(libc::ENOENT), parse_errno("ENOENT"));
+        assert_eq!(Some(libc::EHWPOISON), parse_errno("EHWPOISON"));
+        assert_eq!(Some(520), parse_errno("520"));
+        assert_eq!(None, parse_errno("ENOTHING"));
+        assert_eq!(None, parse_errno("0"));
+        assert_eq!(None, parse_errno("4096"));
+
+        // Linuxのエラー番号(1〜EHWPOISON、41・58は欠番)は全て名前がある
+        for no in (1..=libc::EHWPOISON).filter(|n| 41 != *n && 58 != *n) {
+            assert_ne!(Errno::UnknownErrno, Errno::from_i32(no), "errno {}", no);
+        }
+    }
+
+    #[test]
+    fn test_format_flags() {
+        {
+            // 複数bitのフラグ(O_SYNCはO_DSYNCを含む)
+            assert_eq!(
+                "O_RDWR|O_SYNC",
+                format_open_flags(libc::O_RDWR as u64 | libc::O_SYNC as u64)
+            );
+            assert_eq!("O_WRONLY|O_DSYNC", format_open_flags(0o10001));
+        }
+        {
+            // テーブルにないbitは16進数
+            assert_eq!("PROT_READ|0x10", format_flags(0x11, PROT_FLAGS));
+            assert_eq!("0x10", format_flags(0x10, PROT_FLAGS));
+            assert_eq!("0", format_flags(0, PROT_FLAGS));
+            assert_eq!(
+                "MAP_PRIVATE|MAP_ANONYMOUS|0x40000000",
+                format_flags(0x4000_0022, MAP_FLAGS)
+            );
+        }
+        {
+            // 引数の種類毎のテーブル
+            let cases = vec![
+                (ArgKind::AccessMode, 0, "F_OK"),
+                (ArgKind::AccessMode, 6, "R_OK|W_OK"),
+                (ArgKind::AccessMode, 0x11, "X_OK|0x10"),
+                (ArgKind::Prot, 7, "PROT_READ|PROT_WRITE|PROT_EXEC"),
+                (
+                    ArgKind::CloneFlags,
+                    0x0120_0011,
+                    "CLONE_CHILD_CLEARTID|CLONE_CHILD_SETTID|SIGCHLD",
+                ),
+                (
+                    ArgKind::CloneFlags,
+                    0x003D_0F00,
+                    "CLONE_VM|CLONE_FS|CLONE_FILES|CLONE_SIGHAND|CLONE_THREAD|CLONE_SYSVSEM|\
+                     CLONE_SETTLS|CLONE_PARENT_SETTID|CLONE_CHILD_CLEARTID",
+                ),
+                (ArgKind::CloneFlags, 0x11, "SIGCHLD"),
+                (ArgKind::CloneFlags, 0x1_0000_0100, "CLONE_VM|0x100000000"),
+                (ArgKind::CloneFlags, 0, "0"),
+                (ArgKind::FutexOp, 0, "FUTEX_WAIT"),
+                (ArgKind::FutexOp, 0x81, "FUTEX_WAKE_PRIVATE"),
+                (
+                    ArgKind::FutexOp,
+                    0x189,
+                    "FUTEX_WAIT_BITSET_PRIVATE|FUTEX_CLOCK_REALTIME",
+                ),
+                (ArgKind::FutexOp, 0x8F, "0x8f"),
+            ];
+            for (kind, val, expected) in cases {
+                assert_eq!(expected, format_value(kind, val));
+            }
+        }
+    }
+
+    #[test]
+    fn test_to_name() {
+        let cases = vec![
+            (libc::SYS_read, "read"),
+            (libc::SYS_write, "write"),
+            (libc::SYS_pread64, "pread64"),
+            (libc::SYS_newfstatat, "newfstatat"),
+            (libc::SYS_statx, "statx"),
+            (334, "rseq"),
+            // 全アーキテクチャ共通の番号
+            (libc::SYS_pidfd_send_signal, "pidfd_send_signal"),
+            (libc::SYS_clone3, "clone3"),
+            (libc::SYS_mount_setattr, "mount_setattr"),
+            (462, "mseal"),
+            // 不明な番号
+            (336, "syscall_336"),
+            (423, "syscall_423"),
+            (463, "syscall_463"),
+            (-1, "syscall_-1"),
+        ];
+        for (no, expected) in cases {
+            assert_eq!(expected, to_display_name(no));
+        }
+    }
+
+    #[test]
+    fn test_to_number() {
+        assert_eq!(Some(libc::SYS_openat), to_number("openat"));
+        assert_eq!(Some(libc::SYS_clone3), to_number("clone3"));
+        assert_eq!(None, to_number("opnat"));
+        assert_eq!(vec!["openat", "openat2"], similar_names("opnat"));
+        assert_eq!(
+            vec!["io_uring_setup", "io_uring_enter", "io_uring_register"],
+            similar_names("io_uring")
+        );
+        assert!(similar_names("xxxxxxxx").is_empty());
+    }
+
+    #[test]
+    fn test_to_category() {
+        let cases = vec![
+            (libc::SYS_openat, Some(Category::File)),
+            (libc::SYS_stat, Some(Category::File)),
+            (libc::SYS_connect, Some(Category::Network)),
+            (libc::SYS_mmap, Some(Category::Memory)),
+            (libc::SYS_clone, Some(Category::Process)),
+            // ファイル・プロセスの両方に含まれる
+            (libc::SYS_execve, Some(Category::Process)),
+            (libc::SYS_read, None),
+            (999, None),
+        ];
+        for (no, expected) in cases {
+            assert_eq!(expected, to_category(no));
+        }
+        assert!(Category::File.contains(libc::SYS_execve));
+
+        assert_eq!(Some(Category::Network), Category::from_name("net"));
+        assert_eq!(None, Category::from_name("ipc"));
+        let nos = Category::Memory.get_numbers();
+        assert!(nos.contains(&libc::SYS_brk));
+        assert!(!nos.contains(&libc::SYS_openat));
+    }
+
+    #[test]
+    fn test_category_members() {
+        use Category::*;
+        let cases = vec![
+            (File, libc::SYS_rename, true),
+            (File, libc::SYS_unlinkat, true),
+            (File, libc::SYS_read, false),
+            (Desc, libc::SYS_read, true),
+            (Desc, libc::SYS_openat, true),
+            (Desc, libc::SYS_stat, false),
+            (Network, libc::SYS_sendto, true),
+            (Network, libc::SYS_recvmsg, true),
+            (Network, libc::SYS_write, false),
+            (Memory, libc::SYS_mprotect, true),
+            (Memory, libc::SYS_madvise, true),
+            (Process, libc::SYS_wait4, true),
+            (Process, libc::SYS_execveat, true),
+            (Process, libc::SYS_getpid, false),
+            (Signal, libc::SYS_rt_sigaction, true),
+            (Signal, libc::SYS_kill, true),
+            (Signal, libc::SYS_rt_sigreturn, true),
+            (Signal, libc::SYS_futex, false),
+        ];
+        for (category, no, expected) in cases {
+            assert_eq!(expected, category.contains(no), "{:?} {}", category, no);
+        }
+
+        // 分類の一覧は、全てシグネチャがある
+        for no in NETWORK_SYSCALLS
+            .iter()
+            .chain(MEMORY_SYSCALLS)
+            .chain(PROCESS_SYSCALLS)
+            .chain(SIGNAL_SYSCALLS)
+        {
+            assert!(to_signature(*no).is_some(), "{}", no);
+        }
+    }
+}
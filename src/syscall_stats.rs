@@ -0,0 +1,145 @@
+//! システムコールの統計
+//!
+//! システムコール毎の呼び出し回数・エラー回数・所要時間を集計し、表形式で表示する
+
+use std::collections::HashMap;
+use std::io::{Result, Write};
+use std::time::Duration;
+
+use crate::syscall_info::to_display_name;
+
+/// 表の区切り線
+const SEPARATOR: &str = "------ ----------- ----------- --------- --------- ----------------";
+
+/// システムコール毎の集計
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Stat {
+    calls: u64,     // 呼び出し回数
+    errors: u64,    // エラー回数
+    time: Duration, // 所要時間(開始〜終了の停止間)
+}
+
+/// システムコールの統計
+pub struct SyscallStats {
+    stats: HashMap<i64, Stat>, // システムコールNoをキーとする
+}
+
+impl SyscallStats {
+    /// コンストラクタ
+    pub fn new() -> Self {
+        SyscallStats {
+            stats: HashMap::new(),
+        }
+    }
+
+    /// システムコール1回分を追加
+    pub fn add(&mut self, no: i64, time: Duration, error: bool) {
+        let stat = self.stats.entry(no).or_default();
+        stat.calls += 1;
+        stat.time += time;
+        if error {
+            stat.errors += 1;
+        }
+    }
+
+    /// 統計を表示
+    ///
+    /// 所要時間の降順(同じ場合は名前順)に並べ、最後に合計を表示する
+    pub fn show<W: Write>(&self, out: &mut W) -> Result<()> {
+        let mut rows = self
+            .stats
+            .iter()
+            .map(|(no, stat)| (to_display_name(*no), *stat))
+            .collect::<Vec<(String, Stat)>>();
+        rows.sort_by(|(n1, s1), (n2, s2)| s2.time.cmp(&s1.time).then_with(|| n1.cmp(n2)));
+
+        let total = rows.iter().fold(Stat::default(), |acc, (_, s)| Stat {
+            calls: acc.calls + s.calls,
+            errors: acc.errors + s.errors,
+            time: acc.time + s.time,
+        });
+
+        writeln!(
+            out,
+            "% time     seconds  usecs/call     calls    errors syscall"
+        )?;
+        writeln!(out, "{}", SEPARATOR)?;
+        for (name, stat) in rows.iter() {
+            Self::show_row(out, name, stat, &total)?;
+        }
+        writeln!(out, "{}", SEPARATOR)?;
+        Self::show_row(out, "total", &total, &total)
+    }
+
+    /// 1行分を表示(エラーがなければエラー回数は空欄)
+    fn show_row<W: Write>(out: &mut W, name: &str, stat: &Stat, total: &Stat) -> Result<()> {
+        let percent = if total.time.is_zero() {
+            0.0
+        } else {
+            stat.time.as_secs_f64() * 100.0 / total.time.as_secs_f64()
+        };
+        let usecs = stat.time.as_micros() / stat.calls.max(1) as u128;
+        let errors = match stat.errors {
+            0 => String::new(),
+            n => n.to_string(),
+        };
+        writeln!(
+            out,
+            "{:>6.2} {:>11.6} {:>11} {:>9} {:>9} {}",
+            percent,
+            stat.time.as_secs_f64(),
+            usecs,
+            stat.calls,
+            errors,
+            name
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn show(stats: &SyscallStats) -> String {
+        let mut out = vec![];
+        stats.show(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_show() {
+        {
+            // 所要時間の降順、同じ場合は名前順
+            let mut stats = SyscallStats::new();
+            stats.add(libc::SYS_read, Duration::from_micros(100), false);
+            stats.add(libc::SYS_read, Duration::from_micros(200), false);
+            stats.add(libc::SYS_openat, Duration::from_micros(500), true);
+            stats.add(libc::SYS_openat, Duration::from_micros(100), false);
+            stats.add(libc::SYS_close, Duration::from_micros(50), false);
+            stats.add(libc::SYS_brk, Duration::from_micros(50), false);
+            stats.add(999, Duration::from_micros(0), true);
+            assert_eq!(
+                "% time     seconds  usecs/call     calls    errors syscall\n\
+                 ------ ----------- ----------- --------- --------- ----------------\n \
+                 60.00    0.000600         300         2         1 openat\n \
+                 30.00    0.000300         150         2           read\n  \
+                 5.00    0.000050          50         1           brk\n  \
+                 5.00    0.000050          50         1           close\n  \
+                 0.00    0.000000           0         1         1 syscall_999\n\
+                 ------ ----------- ----------- --------- --------- ----------------\n\
+                 100.00    0.001000         142         7         2 total\n",
+                show(&stats)
+            );
+        }
+        {
+            // システムコールなし
+            assert_eq!(
+                "% time     seconds  usecs/call     calls    errors syscall\n\
+                 ------ ----------- ----------- --------- --------- ----------------\n\
+                 ------ ----------- ----------- --------- --------- ----------------\n  \
+                 0.00    0.000000           0         0           total\n",
+                show(&SyscallStats::new())
+            );
+        }
+    }
+}
@@ -0,0 +1,244 @@
+//! システムコール停止の情報
+//!
+//! PTRACE_GET_SYSCALL_INFO(Linux 5.3以降)で、開始・終了の区別、システムコールNo、引数、戻り値を取得する
+//! 使用できないカーネルでは、レジスタと開始・終了の交互の停止から判定する
+
+use nix::errno::Errno;
+use nix::sys::ptrace::getregs;
+use nix::unistd::Pid;
+use std::mem::size_of;
+
+/// PTRACE_GET_SYSCALL_INFOの種類(libcに定義がないもの)
+const PTRACE_SYSCALL_INFO_NONE: u8 = 0;
+const PTRACE_SYSCALL_INFO_ENTRY: u8 = 1;
+const PTRACE_SYSCALL_INFO_EXIT: u8 = 2;
+const PTRACE_SYSCALL_INFO_SECCOMP: u8 = 3;
+
+/// アーキテクチャ(AUDIT_ARCH_*)
+const AUDIT_ARCH_X86_64: u32 = 0xC000_003E;
+const AUDIT_ARCH_I386: u32 = 0x4000_0003;
+
+/// x32 ABIのシステムコールNoに立つビット
+const X32_SYSCALL_BIT: u64 = 0x4000_0000;
+
+/// 64bitモードのコードセグメント
+const USER_CS: u64 = 0x33;
+
+/// トレース対象のABI
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Abi {
+    X86_64,     // x86-64(システムコール名・引数を解析できる)
+    I386,       // 32bit互換モード
+    X32,        // x32
+    Other(u32), // 不明(AUDIT_ARCH_*)
+}
+
+/// システムコール停止
+#[derive(Debug, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+pub enum SyscallStop {
+    Entry(libc::user_regs_struct, Abi), // 開始(システムコールNo・引数を設定したレジスタ)
+    Exit(u64),                          // 終了(戻り値)
+}
+
+/// システムコール停止の読み込み
+pub struct SyscallReader {
+    use_info: bool, // PTRACE_GET_SYSCALL_INFOを使用するか
+}
+
+impl SyscallReader {
+    /// コンストラクタ
+    ///
+    /// use_infoがfalseの場合は、常にレジスタから判定する
+    pub fn new(use_info: bool) -> Self {
+        SyscallReader { use_info }
+    }
+
+    /// PTRACE_GET_SYSCALL_INFOを使用しているか
+    #[allow(dead_code)]
+    pub fn is_use_info(&self) -> bool {
+        self.use_info
+    }
+
+    /// システムコール停止を読み込む
+    ///
+    /// in_syscallは、レジスタから判定する場合に使う開始済みか(開始・終了は交互に停止する)
+    /// PTRACE_GET_SYSCALL_INFOが使えなければ、以降はレジスタから判定する
+    pub fn read(&mut self, pid: Pid, in_syscall: bool) -> nix::Result<SyscallStop> {
+        if self.use_info {
+            match read_info(pid) {
+                Ok(Some(stop)) => return Ok(stop),
+                Ok(None) | Err(Errno::EIO) | Err(Errno::EINVAL) => self.use_info = false,
+                Err(e) => return Err(e),
+            }
+        }
+        let regs = getregs(pid)?;
+        Ok(if in_syscall {
+            SyscallStop::Exit(regs.rax)
+        } else {
+            SyscallStop::Entry(regs, to_abi_from_regs(&regs))
+        })
+    }
+}
+
+/// PTRACE_GET_SYSCALL_INFOで読み込む
+///
+/// システムコール停止でない(PTRACE_SYSCALL_INFO_NONE)場合はNone
+fn read_info(pid: Pid) -> nix::Result<Option<SyscallStop>> {
+    let mut info: libc::ptrace_syscall_info = unsafe { std::mem::zeroed() };
+    let ret = unsafe {
+        libc::ptrace(
+            libc::PTRACE_GET_SYSCALL_INFO,
+            pid.as_raw(),
+            size_of::<libc::ptrace_syscall_info>(),
+            &mut info as *mut libc::ptrace_syscall_info,
+        )
+    };
+    Errno::result(ret)?;
+    Ok(to_stop(&info))
+}
+
+/// ptrace_syscall_info→システムコール停止
+fn to_stop(info: &libc::ptrace_syscall_info) -> Option<SyscallStop> {
+    let (nr, args) = match info.op {
+        PTRACE_SYSCALL_INFO_ENTRY => unsafe { (info.u.entry.nr, info.u.entry.args) },
+        // seccompによる停止は、開始として扱う
+        PTRACE_SYSCALL_INFO_SECCOMP => unsafe { (info.u.seccomp.nr, info.u.seccomp.args) },
+        PTRACE_SYSCALL_INFO_EXIT => {
+            return Some(SyscallStop::Exit(unsafe { info.u.exit.sval } as u64));
+        }
+        PTRACE_SYSCALL_INFO_NONE => return None,
+        _ => return None,
+    };
+    let mut regs: libc::user_regs_struct = unsafe { std::mem::zeroed() };
+    regs.orig_rax = nr;
+    regs.rdi = args[0];
+    regs.rsi = args[1];
+    regs.rdx = args[2];
+    regs.r10 = args[3];
+    regs.r8 = args[4];
+    regs.r9 = args[5];
+    regs.rip = info.instruction_pointer;
+    regs.rsp = info.stack_pointer;
+    Some(SyscallStop::Entry(regs, to_abi(info.arch, nr)))
+}
+
+/// AUDIT_ARCH_*とシステムコールNo→ABI
+fn to_abi(arch: u32, nr: u64) -> Abi {
+    match arch {
+        AUDIT_ARCH_X86_64 if 0 != nr & X32_SYSCALL_BIT => Abi::X32,
+        AUDIT_ARCH_X86_64 => Abi::X86_64,
+        AUDIT_ARCH_I386 => Abi::I386,
+        _ => Abi::Other(arch),
+    }
+}
+
+/// レジスタ→ABI(コードセグメントで64bitモードか判定する)
+fn to_abi_from_regs(regs: &libc::user_regs_struct) -> Abi {
+    if USER_CS != regs.cs {
+        Abi::I386
+    } else if 0 != regs.orig_rax & X32_SYSCALL_BIT {
+        Abi::X32
+    } else {
+        Abi::X86_64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_to_stop() {
+        let mut info: libc::ptrace_syscall_info = unsafe { std::mem::zeroed() };
+        info.arch = AUDIT_ARCH_X86_64;
+        info.instruction_pointer = 0x1000;
+        {
+            info.op = PTRACE_SYSCALL_INFO_ENTRY;
+            info.u.entry = libc::__c_anonymous_ptrace_syscall_info_entry {
+                nr: libc::SYS_write as u64,
+                args: [1, 0x2000, 3, 4, 5, 6],
+            };
+            match to_stop(&info) {
+                Some(SyscallStop::Entry(regs, Abi::X86_64)) => {
+                    assert_eq!(libc::SYS_write as u64, regs.orig_rax);
+                    assert_eq!(
+                        [1, 0x2000, 3, 4, 5, 6],
+                        crate::syscall_info::get_args(&regs)
+                    );
+                    assert_eq!(0x1000, regs.rip);
+                }
+                stop => panic!("unexpected stop: {:?}", stop),
+            }
+        }
+        {
+            info.op = PTRACE_SYSCALL_INFO_EXIT;
+            info.u.exit = libc::__c_anonymous_ptrace_syscall_info_exit {
+                sval: -(libc::ENOENT as i64),
+                is_error: 1,
+            };
+            assert_eq!(
+                Some(SyscallStop::Exit(-(libc::ENOENT as i64) as u64)),
+                to_stop(&info)
+            );
+        }
+        {
+            info.op = PTRACE_SYSCALL_INFO_NONE;
+            assert_eq!(None, to_stop(&info));
+        }
+
+        assert_eq!(Abi::X86_64, to_abi(AUDIT_ARCH_X86_64, 1));
+        assert_eq!(Abi::X32, to_abi(AUDIT_ARCH_X86_64, X32_SYSCALL_BIT | 1));
+        assert_eq!(Abi::I386, to_abi(AUDIT_ARCH_I386, 4));
+        assert_eq!(Abi::Other(0xB7), to_abi(0xB7, 0));
+    }
+
+    #[test]
+    fn test_read() {
+        use nix::sys::ptrace::{setoptions, syscall, traceme, Options};
+        use nix::sys::signal::{kill, raise, Signal};
+        use nix::sys::wait::{waitpid, WaitStatus};
+        use nix::unistd::{fork, ForkResult};
+
+        // 同じ停止を両方の方法で読み込み、一致すること
+        let _lock = crate::FORK_LOCK.lock().unwrap();
+        match unsafe { fork() }.expect("failed fork") {
+            ForkResult::Child => unsafe {
+                traceme().expect("failed traceme");
+                raise(Signal::SIGSTOP).expect("failed raise");
+                libc::syscall(libc::SYS_getppid);
+                libc::_exit(0);
+            },
+            ForkResult::Parent { child } => {
+                waitpid(child, None).expect("failed waitpid");
+                setoptions(child, Options::PTRACE_O_TRACESYSGOOD).expect("failed setoptions");
+
+                let mut info = SyscallReader::new(true);
+                let mut regs = SyscallReader::new(false);
+                let mut in_syscall = false;
+                let mut found = false;
+                while !found {
+                    syscall(child, None).expect("failed syscall");
+                    match waitpid(child, None).expect("failed waitpid") {
+                        WaitStatus::PtraceSyscall(_) => {}
+                        status => panic!("unexpected status: {:?}", status),
+                    }
+                    let expected = regs.read(child, in_syscall).unwrap();
+                    let actual = info.read(child, in_syscall).unwrap();
+                    match (&expected, &actual) {
+                        (SyscallStop::Entry(r1, a1), SyscallStop::Entry(r2, a2)) => {
+                            assert_eq!((r1.orig_rax, r1.rip, a1), (r2.orig_rax, r2.rip, a2));
+                            found = libc::SYS_getppid as u64 == r1.orig_rax;
+                        }
+                        _ => assert_eq!(expected, actual),
+                    }
+                    in_syscall = !in_syscall;
+                }
+                assert!(info.is_use_info());
+                assert!(!regs.is_use_info());
+                kill(child, Signal::SIGKILL).expect("failed kill");
+                waitpid(child, None).expect("failed waitpid");
+            }
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! システムコールの構造体引数
+//!
+//! トレース対象のメモリから読み込んだバイト列を、libcの構造体として解釈し表示用に整形する
+
+use std::mem::size_of;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::syscall_info::quote;
+
+/// バイト列を構造体として読み込む(サイズが足りなければNone)
+fn read_as<T>(data: &[u8]) -> Option<T> {
+    if data.len() < size_of::<T>() {
+        return None;
+    }
+    Some(unsafe { std::ptr::read_unaligned(data.as_ptr() as *const T) })
+}
+
+/// ファイル種別(S_IF*)
+const FILE_TYPES: &[(u32, &str)] = &[
+    (libc::S_IFREG, "S_IFREG"),
+    (libc::S_IFDIR, "S_IFDIR"),
+    (libc::S_IFCHR, "S_IFCHR"),
+    (libc::S_IFBLK, "S_IFBLK"),
+    (libc::S_IFIFO, "S_IFIFO"),
+    (libc::S_IFLNK, "S_IFLNK"),
+    (libc::S_IFSOCK, "S_IFSOCK"),
+];
+
+/// ファイルモードを整形(S_IFREG|0644)
+fn format_mode(mode: u32) -> String {
+    let perm = format!("0{:03o}", mode & 0o7777);
+    match FILE_TYPES.iter().find(|(t, _)| *t == mode & libc::S_IFMT) {
+        Some((_, name)) => format!("{}|{}", name, perm),
+        None => perm,
+    }
+}
+
+/// デバイス番号を整形(makedev(0x1, 0x3))
+fn format_dev(dev: u64) -> String {
+    let major = ((dev >> 8) & 0xFFF) | ((dev >> 32) & !0xFFF);
+    let minor = (dev & 0xFF) | ((dev >> 12) & !0xFF);
+    format!("makedev(0x{:x}, 0x{:x})", major, minor)
+}
+
+/// struct statを整形
+///
+/// デバイスファイルはサイズの代わりにデバイス番号を表示する
+/// verboseの場合は、全てのフィールドを表示する(-v)
+pub fn format_stat(data: &[u8], verbose: bool) -> Option<String> {
+    let st: libc::stat = read_as(data)?;
+    let mode = format_mode(st.st_mode);
+    let size = match st.st_mode & libc::S_IFMT {
+        libc::S_IFCHR | libc::S_IFBLK => format!("st_rdev={}", format_dev(st.st_rdev)),
+        _ => format!("st_size={}", st.st_size),
+    };
+    if !verbose {
+        return Some(format!("{{st_mode={}, {}}}", mode, size));
+    }
+    Some(format!(
+        "{{st_dev={}, st_ino={}, st_mode={}, st_nlink={}, st_uid={}, st_gid={}, \
+         st_blksize={}, st_blocks={}, {}, st_atime={}.{:09}, st_mtime={}.{:09}, \
+         st_ctime={}.{:09}}}",
+        format_dev(st.st_dev),
+        st.st_ino,
+        mode,
+        st.st_nlink,
+        st.st_uid,
+        st.st_gid,
+        st.st_blksize,
+        st.st_blocks,
+        size,
+        st.st_atime,
+        st.st_atime_nsec,
+        st.st_mtime,
+        st.st_mtime_nsec,
+        st.st_ctime,
+        st.st_ctime_nsec
+    ))
+}
+
+/// struct timespecを整形
+pub fn format_timespec(data: &[u8]) -> Option<String> {
+    let ts: libc::timespec = read_as(data)?;
+    Some(format!("{{tv_sec={}, tv_nsec={}}}", ts.tv_sec, ts.tv_nsec))
+}
+
+/// intへのポインタを整形([5])
+pub fn format_int(data: &[u8]) -> Option<String> {
+    let val: i32 = read_as(data)?;
+    Some(format!("[{}]", val))
+}
+
+/// struct winsizeを整形
+pub fn format_winsize(data: &[u8]) -> Option<String> {
+    let ws: libc::winsize = read_as(data)?;
+    Some(format!(
+        "{{ws_row={}, ws_col={}, ws_xpixel={}, ws_ypixel={}}}",
+        ws.ws_row, ws.ws_col, ws.ws_xpixel, ws.ws_ypixel
+    ))
+}
+
+/// struct sockaddrを整形
+///
+/// AF_INET・AF_INET6・AF_UNIXはアドレスを表示し、それ以外はファミリのみ表示する
+pub fn format_sockaddr(data: &[u8]) -> Option<String> {
+    let family: libc::sa_family_t = read_as(data)?;
+    let s = match family as i32 {
+        libc::AF_INET => {
+            let sin: libc::sockaddr_in = read_as(data)?;
+            format!(
+                "{{sa_family=AF_INET, sin_port=htons({}), sin_addr=inet_addr(\"{}\")}}",
+                u16::from_be(sin.sin_port),
+                Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr))
+            )
+        }
+        libc::AF_INET6 => {
+            let sin6: libc::sockaddr_in6 = read_as(data)?;
+            format!(
+                "{{sa_family=AF_INET6, sin6_port=htons({}), sin6_addr=inet_pton(AF_INET6, \"{}\")}}",
+                u16::from_be(sin6.sin6_port),
+                Ipv6Addr::from(sin6.sin6_addr.s6_addr)
+            )
+        }
+        libc::AF_UNIX => {
+            // 先頭がNULの場合は抽象ソケット(@で表す)
+            let path = &data[size_of::<libc::sa_family_t>()..];
+            match path.split_first() {
+                None => "{sa_family=AF_UNIX}".to_string(),
+                Some((0, name)) => {
+                    format!("{{sa_family=AF_UNIX, sun_path=@{}}}", quote(name, false))
+                }
+                Some(_) => {
+                    let len = path.iter().position(|b| 0 == *b).unwrap_or(path.len());
+                    format!(
+                        "{{sa_family=AF_UNIX, sun_path={}}}",
+                        quote(&path[..len], false)
+                    )
+                }
+            }
+        }
+        f => format!("{{sa_family={}}}", f),
+    };
+    Some(s)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// 構造体→バイト列
+    fn to_bytes<T>(val: &T) -> Vec<u8> {
+        let p = val as *const T as *const u8;
+        unsafe { std::slice::from_raw_parts(p, size_of::<T>()) }.to_vec()
+    }
+
+    #[test]
+    fn test_format_stat() {
+        {
+            let mut st: libc::stat = unsafe { std::mem::zeroed() };
+            st.st_mode = libc::S_IFREG | 0o644;
+            st.st_size = 1234;
+            assert_eq!(
+                Some("{st_mode=S_IFREG|0644, st_size=1234}".to_string()),
+                format_stat(&to_bytes(&st), false)
+            );
+        }
+        {
+            // デバイスファイル(/dev/null)
+            let mut st: libc::stat = unsafe { std::mem::zeroed() };
+            st.st_mode = libc::S_IFCHR | 0o666;
+            st.st_rdev = 0x103;
+            assert_eq!(
+                Some("{st_mode=S_IFCHR|0666, st_rdev=makedev(0x1, 0x3)}".to_string()),
+                format_stat(&to_bytes(&st), false)
+            );
+        }
+        {
+            // 全てのフィールド(-v)
+            let mut st: libc::stat = unsafe { std::mem::zeroed() };
+            st.st_dev = 0x801;
+            st.st_ino = 42;
+            st.st_mode = libc::S_IFREG | 0o600;
+            st.st_nlink = 1;
+            st.st_uid = 1000;
+            st.st_gid = 100;
+            st.st_blksize = 4096;
+            st.st_blocks = 8;
+            st.st_size = 10;
+            st.st_mtime = 1_700_000_000;
+            st.st_mtime_nsec = 5;
+            assert_eq!(
+                Some(
+                    "{st_dev=makedev(0x8, 0x1), st_ino=42, st_mode=S_IFREG|0600, st_nlink=1, \
+                     st_uid=1000, st_gid=100, st_blksize=4096, st_blocks=8, st_size=10, \
+                     st_atime=0.000000000, st_mtime=1700000000.000000005, st_ctime=0.000000000}"
+                        .to_string()
+                ),
+                format_stat(&to_bytes(&st), true)
+            );
+        }
+        {
+            // サイズが足りない
+            assert_eq!(None, format_stat(&[0; 16], false));
+        }
+    }
+
+    #[test]
+    fn test_format_timespec() {
+        let data = [1, 0, 0, 0, 0, 0, 0, 0, 0x00, 0x65, 0xCD, 0x1D, 0, 0, 0, 0];
+        assert_eq!(
+            Some("{tv_sec=1, tv_nsec=500000000}".to_string()),
+            format_timespec(&data)
+        );
+        assert_eq!(None, format_timespec(&data[..8]));
+    }
+
+    #[test]
+    fn test_format_winsize() {
+        let data = [24, 0, 80, 0, 0, 0, 0, 0];
+        assert_eq!(
+            Some("{ws_row=24, ws_col=80, ws_xpixel=0, ws_ypixel=0}".to_string()),
+            format_winsize(&data)
+        );
+        assert_eq!(None, format_winsize(&data[..4]));
+        assert_eq!(Some("[-1]".to_string()), format_int(&[0xFF; 4]));
+    }
+
+    #[test]
+    fn test_format_sockaddr() {
+        let cases: Vec<(Vec<u8>, Option<&str>)> = vec![
+            (
+                vec![2, 0, 0, 80, 127, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+                Some("{sa_family=AF_INET, sin_port=htons(80), sin_addr=inet_addr(\"127.0.0.1\")}"),
+            ),
+            (
+                vec![
+                    10, 0, 0x01, 0xBB, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+                    0, 0, 0, 0,
+                ],
+                Some("{sa_family=AF_INET6, sin6_port=htons(443), sin6_addr=inet_pton(AF_INET6, \"::1\")}"),
+            ),
+            (
+                b"\x01\x00/run/test.sock\0".to_vec(),
+                Some("{sa_family=AF_UNIX, sun_path=\"/run/test.sock\"}"),
+            ),
+            // 抽象ソケット
+            (
+                b"\x01\x00\0abstract".to_vec(),
+                Some("{sa_family=AF_UNIX, sun_path=@\"abstract\"}"),
+            ),
+            (vec![16, 0, 0, 0], Some("{sa_family=16}")),
+            // サイズが足りない
+            (vec![2, 0, 0, 80], None),
+            (vec![2], None),
+        ];
+        for (data, expected) in cases {
+            assert_eq!(expected.map(|s| s.to_string()), format_sockaddr(&data));
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! デバッグ対象(実行中のプロセス・コアファイル)
+//!
+//! レジスタ・メモリの読み込み元を切り替え、コマンドからは区別せずに読み込めるようにする
+
+use nix::errno::Errno;
+use nix::sys::ptrace::getregs;
+use nix::unistd::Pid;
+use std::io;
+
+use crate::core_file::CoreFile;
+use crate::fpregs::read_fpregs;
+use crate::memory::{self, ReadMemory};
+use crate::memory_map::{load_mappings, Mapping};
+
+/// デバッグ対象
+pub enum Target {
+    Process(Pid),        // ptraceで停止させているプロセス
+    Core(Box<CoreFile>), // コアファイル(読み込みのみ)
+}
+
+impl Target {
+    /// コアファイルか
+    pub fn is_core(&self) -> bool {
+        matches!(self, Target::Core(_))
+    }
+
+    /// レジスタ読み込み
+    pub fn read_regs(&self) -> nix::Result<libc::user_regs_struct> {
+        match self {
+            Target::Process(pid) => getregs(*pid),
+            Target::Core(core) => Ok(core.get_status().regs),
+        }
+    }
+
+    /// 浮動小数点レジスタ読み込み(コアファイルに含まれなければエラー)
+    pub fn read_fpregs(&self) -> nix::Result<libc::user_fpregs_struct> {
+        match self {
+            Target::Process(pid) => read_fpregs(*pid),
+            Target::Core(core) => core.get_fpregs().copied().ok_or(Errno::ENODATA),
+        }
+    }
+
+    /// 指定バイト数分のメモリ読み込み(読み込めないアドレスはエラー)
+    pub fn read_bytes(&self, addr: u64, len: usize) -> nix::Result<Vec<u8>> {
+        match self {
+            Target::Process(pid) => memory::read_bytes(*pid, addr, len),
+            Target::Core(core) => core.read_memory(addr, len).ok_or(Errno::EFAULT),
+        }
+    }
+
+    /// マップされている領域を取得
+    pub fn load_mappings(&self) -> io::Result<Vec<Mapping>> {
+        match self {
+            Target::Process(pid) => load_mappings(*pid),
+            Target::Core(core) => Ok(core.get_mappings().clone()),
+        }
+    }
+}
+
+impl ReadMemory for Target {
+    fn read_memory(&self, addr: u64, len: usize) -> Option<Vec<u8>> {
+        self.read_bytes(addr, len).ok()
+    }
+}
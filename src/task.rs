@@ -0,0 +1,128 @@
+//! スレッド情報(/proc/<pid>/task)
+//!
+//! プロセスのスレッド毎の名前(comm)と、スケジューラの状態(stat)を読み込む
+
+use nix::unistd::Pid;
+use std::fs;
+use std::io;
+
+/// スレッド情報
+#[derive(Debug, PartialEq)]
+pub struct TaskInfo {
+    pub tid: Pid,     // スレッドID
+    pub name: String, // スレッド名
+    pub state: char,  // 状態(R・S・D・T・t・Z等)
+}
+
+/// プロセスのスレッド一覧を、スレッドIDの順に取得
+///
+/// 読み込む間に終了したスレッドは除く
+pub fn load_tasks(pid: Pid) -> io::Result<Vec<TaskInfo>> {
+    let mut tids = fs::read_dir(format!("/proc/{}/task", pid))?
+        .filter_map(|e| e.ok()?.file_name().to_str()?.parse::<i32>().ok())
+        .collect::<Vec<i32>>();
+    tids.sort_unstable();
+    Ok(tids
+        .into_iter()
+        .filter_map(|tid| {
+            let dir = format!("/proc/{}/task/{}", pid, tid);
+            let comm = fs::read_to_string(format!("{}/comm", dir)).ok()?;
+            let stat = fs::read_to_string(format!("{}/stat", dir)).ok()?;
+            Some(TaskInfo {
+                tid: Pid::from_raw(tid),
+                name: parse_comm(&comm),
+                state: parse_state(&stat)?,
+            })
+        })
+        .collect())
+}
+
+/// commの内容からスレッド名を取得(末尾の改行を除く)
+pub fn parse_comm(comm: &str) -> String {
+    comm.trim_end_matches('\n').to_string()
+}
+
+/// statの内容から状態を取得
+///
+/// スレッド名は空白や括弧を含むことがあるため、最後の)の後ろの項目とする
+pub fn parse_state(stat: &str) -> Option<char> {
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().next()?.chars().next()
+}
+
+/// 状態の表示名
+pub fn to_state_name(state: char) -> &'static str {
+    match state {
+        'R' => "Running",
+        'S' => "Sleeping",
+        'D' => "Disk sleep",
+        'T' => "Stopped",
+        't' => "Tracing stop",
+        'Z' => "Zombie",
+        'X' => "Dead",
+        'I' => "Idle",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_comm() {
+        assert_eq!("tokio-runtime-w", parse_comm("tokio-runtime-w\n"));
+        assert_eq!("rayon (1)", parse_comm("rayon (1)\n"));
+        assert_eq!("", parse_comm(""));
+    }
+
+    #[test]
+    fn test_parse_state() {
+        // /proc/<pid>/task/<tid>/statから取り出した内容
+        let cases = vec![
+            (
+                "4242 (tokio-runtime-w) S 4240 4240 4103 34816 4240 4194368 95 0 0 0 0 0 0 0 20 0 9 0 1288870 \
+                 1237770240 3357 18446744073709551615 1 1 0 0 0 0 0 4096 17663 0 0 0 -1 3 0 0 0 0 0\n",
+                Some('S'),
+            ),
+            (
+                "4240 (sample) t 4239 4240 4103 34816 4239 1077936128 97 0 0 0 0 0 0 0 20 0 1 0 1288860 \
+                 2482176 221 18446744073709551615 1 1 0 0 0 0 0 0 0 0 0 0 17 2 0 0 0 0 0\n",
+                Some('t'),
+            ),
+            // スレッド名に空白・括弧を含む
+            ("4243 (a) R (b) ) R 4240 4240\n", Some('R')),
+            ("4244 (worker 1) D 4240\n", Some('D')),
+            ("4245 (broken", None),
+            ("", None),
+        ];
+        for (stat, expected) in cases {
+            assert_eq!(expected, parse_state(stat), "{}", stat);
+        }
+    }
+
+    #[test]
+    fn test_to_state_name() {
+        let cases = vec![
+            ('R', "Running"),
+            ('S', "Sleeping"),
+            ('T', "Stopped"),
+            ('t', "Tracing stop"),
+            ('Z', "Zombie"),
+            ('?', "Unknown"),
+        ];
+        for (state, expected) in cases {
+            assert_eq!(expected, to_state_name(state));
+        }
+    }
+
+    #[test]
+    fn test_load_tasks() {
+        // テスト自身のプロセスには、少なくとも実行中のスレッドがある
+        let tasks = load_tasks(nix::unistd::getpid()).unwrap();
+        let me = nix::unistd::gettid();
+        let task = tasks.iter().find(|t| t.tid == me).unwrap();
+        assert_eq!('R', task.state);
+        assert!(tasks.windows(2).all(|w| w[0].tid < w[1].tid));
+    }
+}
@@ -0,0 +1,195 @@
+//! フレームポインタの連鎖によるスタックの巻き戻し(bt)
+//!
+//! 各フレームの[rbp]に呼び出し元のrbp、[rbp+8]にリターンアドレスが保存されている前提で辿る
+//! フレームポインタを省略した関数(-fomit-frame-pointer)を挟むと、正しく辿れない
+
+use std::convert::TryInto;
+
+use crate::memory::ReadMemory;
+
+/// 辿るフレーム数の既定値(壊れたスタックで止まらなくならないよう制限する)
+pub const DEFAULT_BACKTRACE_LIMIT: usize = 64;
+
+/// endbr64(CETが有効な関数の先頭)
+const ENDBR64: [u8; 4] = [0xF3, 0x0F, 0x1E, 0xFA];
+/// push rbp
+const PUSH_RBP: u8 = 0x55;
+/// ret
+const RET: u8 = 0xC3;
+/// rep(repz retの接頭辞)
+const REP: u8 = 0xF3;
+
+/// 関数の先頭から実行済みの命令列から、リターンアドレスのrspからのオフセットを求める
+///
+/// フレームポインタを設定する前(push rbp実行前・直後)であればオフセット、設定済みであればNone
+pub fn prologue_ret_offset(executed: &[u8]) -> Option<u64> {
+    let code = executed.strip_prefix(&ENDBR64).unwrap_or(executed);
+    match code {
+        [] => Some(0),
+        [PUSH_RBP] => Some(8),
+        _ => None,
+    }
+}
+
+/// ret命令か(pop rbp実行後のため、リターンアドレスはrspの位置にある)
+pub fn is_ret(code: &[u8]) -> bool {
+    matches!(code, [RET, ..] | [REP, RET, ..])
+}
+
+/// rip・呼び出し元のリターンアドレスを、内側のフレームから順に求める
+///
+/// ret_offsetは、フレームポインタを設定する前に停止している場合のリターンアドレスのrspからのオフセット
+/// rbpが0・読み込めない・スタックの上位へ進まない場合、またはlimit個に達すれば止める
+pub fn walk_frames<M: ReadMemory>(
+    mem: &M,
+    regs: &libc::user_regs_struct,
+    ret_offset: Option<u64>,
+    limit: usize,
+) -> Vec<u64> {
+    let mut frames = vec![regs.rip];
+    if let Some(off) = ret_offset {
+        match read_u64(mem, regs.rsp.wrapping_add(off)) {
+            Some(ret) if 0 != ret => frames.push(ret),
+            _ => return frames,
+        }
+    }
+
+    let mut rbp = regs.rbp;
+    while frames.len() < limit && 0 != rbp {
+        let (next, ret) = match (read_u64(mem, rbp), read_u64(mem, rbp.wrapping_add(8))) {
+            (Some(n), Some(r)) if 0 != r => (n, r),
+            _ => break,
+        };
+        frames.push(ret);
+        // 呼び出し元のフレームは、スタックの上位(大きいアドレス)にある
+        if next <= rbp {
+            break;
+        }
+        rbp = next;
+    }
+    frames.truncate(limit);
+    frames
+}
+
+/// 8バイト読み込む
+fn read_u64<M: ReadMemory>(mem: &M, addr: u64) -> Option<u64> {
+    let data = mem.read_memory(addr, 8)?;
+    Some(u64::from_le_bytes(data[..].try_into().ok()?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::FakeMemory;
+
+    fn regs(rip: u64, rsp: u64, rbp: u64) -> libc::user_regs_struct {
+        let mut regs: libc::user_regs_struct = unsafe { std::mem::zeroed() };
+        regs.rip = rip;
+        regs.rsp = rsp;
+        regs.rbp = rbp;
+        regs
+    }
+
+    /// 0x7000からのスタック(8バイト毎の値)
+    fn stack(words: &[u64]) -> FakeMemory {
+        FakeMemory::new(0x7000, words.iter().flat_map(|w| w.to_le_bytes()).collect())
+    }
+
+    #[test]
+    fn test_prologue_ret_offset() {
+        let cases: Vec<(Vec<u8>, Option<u64>)> = vec![
+            (vec![], Some(0)),
+            (vec![0x55], Some(8)),
+            (ENDBR64.to_vec(), Some(0)),
+            ([&ENDBR64[..], &[0x55]].concat(), Some(8)),
+            // mov rbp, rsp実行後
+            (vec![0x55, 0x48, 0x89, 0xE5], None),
+            // push rbpで始まらない
+            (vec![0x48], None),
+        ];
+        for (executed, expected) in cases {
+            assert_eq!(expected, prologue_ret_offset(&executed), "{:x?}", executed);
+        }
+    }
+
+    #[test]
+    fn test_is_ret() {
+        let cases: Vec<(Vec<u8>, bool)> = vec![
+            (vec![0xC3], true),
+            (vec![0xC3, 0x90], true),
+            (vec![0xF3, 0xC3], true),
+            // leave・pop rbp・retf
+            (vec![0xC9, 0xC3], false),
+            (vec![0x5D, 0xC3], false),
+            (vec![0xCB], false),
+            (vec![], false),
+        ];
+        for (code, expected) in cases {
+            assert_eq!(expected, is_ret(&code), "{:x?}", code);
+        }
+    }
+
+    #[test]
+    fn test_walk_frames() {
+        // leaf(rbp=0x7010) -> mid(rbp=0x7020) -> main(rbp=0x7030) -> 呼び出し元(rbp=0)
+        let mem = stack(&[0, 0, 0x7020, 0x1111, 0x7030, 0x2222, 0, 0x3333]);
+        let cases = vec![
+            // 保存されたrbpが0になれば止める
+            (
+                regs(0x1000, 0x7000, 0x7010),
+                None,
+                64,
+                vec![0x1000, 0x1111, 0x2222, 0x3333],
+            ),
+            // フレーム数を制限する
+            (regs(0x1000, 0x7000, 0x7010), None, 2, vec![0x1000, 0x1111]),
+            (regs(0x1000, 0x7000, 0x7010), None, 1, vec![0x1000]),
+            // rbpが読み込めなければ止める
+            (regs(0x1000, 0x7000, 0x9000), None, 64, vec![0x1000]),
+            // フレームポインタを設定する前は、rspからリターンアドレスを読み込む
+            (
+                regs(0x1000, 0x7018, 0x7020),
+                Some(0),
+                64,
+                vec![0x1000, 0x1111, 0x2222, 0x3333],
+            ),
+            (
+                regs(0x1000, 0x7010, 0x7020),
+                Some(8),
+                64,
+                vec![0x1000, 0x1111, 0x2222, 0x3333],
+            ),
+        ];
+        for (r, ret_offset, limit, expected) in cases {
+            assert_eq!(
+                expected,
+                walk_frames(&mem, &r, ret_offset, limit),
+                "rbp=0x{:x} limit={}",
+                r.rbp,
+                limit
+            );
+        }
+    }
+
+    #[test]
+    fn test_walk_frames_corrupted() {
+        // 自身を指すrbpで、止まらなくならない
+        let mem = stack(&[0x7000, 0x1111]);
+        assert_eq!(
+            vec![0x1000, 0x1111],
+            walk_frames(&mem, &regs(0x1000, 0x7000, 0x7000), None, 64)
+        );
+        // スタックの下位へ戻るrbp
+        let mem = stack(&[0, 0, 0x7000, 0x2222]);
+        assert_eq!(
+            vec![0x1000, 0x2222],
+            walk_frames(&mem, &regs(0x1000, 0x7000, 0x7010), None, 64)
+        );
+        // リターンアドレスが0であれば止める
+        let mem = stack(&[0x7010, 0, 0, 0x3333]);
+        assert_eq!(
+            vec![0x1000],
+            walk_frames(&mem, &regs(0x1000, 0x7000, 0x7000), None, 64)
+        );
+    }
+}
@@ -0,0 +1,326 @@
+//! ユーザー定義コマンド(define ... end)
+//!
+//! 定義したコマンドの本体を、呼び出し時の引数($arg0〜$arg9・$argc)で置換して展開する
+//! 本体から呼び出したユーザー定義コマンドも展開し、入れ子のdefineはそのまま残す
+
+use regex::{Captures, Regex};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// 引数の最大数($arg0〜$arg9)
+pub const MAX_ARGS: usize = 10;
+
+/// ユーザー定義コマンドの呼び出しの最大の深さ(再帰呼び出しの検出)
+pub const MAX_CALL_DEPTH: usize = 64;
+
+/// 定義中のコマンドの本体(endまで)
+pub struct DefinitionBody {
+    lines: Vec<String>, // 本体の行
+    depth: usize,       // 入れ子のdefineの深さ
+}
+
+impl DefinitionBody {
+    /// コンストラクタ
+    pub fn new() -> Self {
+        DefinitionBody {
+            lines: vec![],
+            depth: 0,
+        }
+    }
+
+    /// 本体に1行追加し、定義の終わり(対応するend)であればtrueを返す
+    ///
+    /// 入れ子のdefineのendは本体に含める
+    pub fn push(&mut self, line: &str) -> bool {
+        let line = line.trim();
+        if "end" == line {
+            if 0 == self.depth {
+                return true;
+            }
+            self.depth -= 1;
+        } else if Some("define") == line.split_whitespace().next() {
+            self.depth += 1;
+        }
+        if !line.is_empty() {
+            self.lines.push(line.to_string());
+        }
+        false
+    }
+
+    /// 本体の行を取得
+    pub fn into_lines(self) -> Vec<String> {
+        self.lines
+    }
+}
+
+/// ユーザー定義コマンドの一覧
+pub struct UserCommands {
+    commands: BTreeMap<String, Vec<String>>, // コマンド名と本体
+}
+
+impl UserCommands {
+    /// コンストラクタ
+    pub fn new() -> Self {
+        UserCommands {
+            commands: BTreeMap::new(),
+        }
+    }
+
+    /// コマンドを定義(定義済みであれば置き換える)
+    pub fn define(&mut self, name: &str, body: Vec<String>) {
+        self.commands.insert(name.to_string(), body);
+    }
+
+    /// 定義済みか
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.commands.contains_key(name)
+    }
+
+    /// 定義されているコマンド数を取得
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// 定義されていないか
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// コマンドを呼び出し、実行する行に展開
+    ///
+    /// 本体で呼び出しているユーザー定義コマンドも、その引数で展開する
+    pub fn expand(&self, name: &str, args: &[&str]) -> Result<Vec<String>, String> {
+        let mut lines = vec![];
+        self.expand_into(name, args, 0, &mut lines)?;
+        Ok(lines)
+    }
+
+    /// 展開処理(depthは呼び出しの深さ)
+    fn expand_into(
+        &self,
+        name: &str,
+        args: &[&str],
+        depth: usize,
+        lines: &mut Vec<String>,
+    ) -> Result<(), String> {
+        if MAX_CALL_DEPTH <= depth {
+            return Err(format!(
+                "max user call depth ({}) exceeded in {}",
+                MAX_CALL_DEPTH, name
+            ));
+        }
+        if MAX_ARGS < args.len() {
+            return Err(format!("too many arguments (max {})", MAX_ARGS));
+        }
+        let body = self
+            .commands
+            .get(name)
+            .ok_or_else(|| format!("undefined command: {}", name))?;
+
+        // 入れ子のdefineの本体は、その呼び出し時に置換するため、そのまま残す
+        let mut nested = 0;
+        for line in body {
+            let first = line.split_whitespace().next().unwrap_or("");
+            if "define" == first {
+                nested += 1;
+            }
+            if 0 < nested {
+                if "end" == line {
+                    nested -= 1;
+                }
+                lines.push(line.clone());
+                continue;
+            }
+
+            let line = substitute(line, args)?;
+            let words = line.split_whitespace().collect::<Vec<&str>>();
+            match words.first() {
+                Some(w) if self.is_defined(w) => {
+                    self.expand_into(w, &words[1..], depth + 1, lines)?
+                }
+                _ => lines.push(line),
+            }
+        }
+        Ok(())
+    }
+
+    /// 定義をdefine ... endの形式で出力(saveしたファイルはsourceで読み込める)
+    pub fn write_definitions<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for (name, body) in &self.commands {
+            writeln!(out, "define {}", name)?;
+            let mut depth = 1;
+            for line in body {
+                if "end" == line {
+                    depth -= 1;
+                }
+                writeln!(out, "{}{}", "  ".repeat(depth), line)?;
+                if line.starts_with("define ") {
+                    depth += 1;
+                }
+            }
+            writeln!(out, "end")?;
+        }
+        Ok(())
+    }
+}
+
+/// 引数を置換($arg0〜$arg9は各引数、$argcは引数の数)
+fn substitute(line: &str, args: &[&str]) -> Result<String, String> {
+    let re = Regex::new(r"\$arg(c|[0-9])").unwrap();
+    let mut missing = None;
+    let replaced = re.replace_all(line, |c: &Captures| match &c[1] {
+        "c" => args.len().to_string(),
+        n => {
+            let i = n.parse::<usize>().unwrap();
+            match args.get(i) {
+                Some(a) => a.to_string(),
+                None => {
+                    missing.get_or_insert(i);
+                    String::new()
+                }
+            }
+        }
+    });
+    match missing {
+        Some(i) => Err(format!("missing argument {} in user command", i)),
+        None => Ok(replaced.into_owned()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// 本体の行から定義を作成
+    fn body(lines: &[&str]) -> Vec<String> {
+        let mut def = DefinitionBody::new();
+        for l in lines {
+            assert!(!def.push(l), "finished at {}", l);
+        }
+        assert!(def.push("end"));
+        def.into_lines()
+    }
+
+    #[test]
+    fn test_definition_body() {
+        // 入れ子のdefineのendでは終わらない
+        let lines = body(&["  info regs", "", "define inner", "p $arg0", "end", "bt"]);
+        assert_eq!(
+            vec!["info regs", "define inner", "p $arg0", "end", "bt"],
+            lines
+        );
+        let mut def = DefinitionBody::new();
+        assert!(def.push("  end  "));
+        assert!(def.into_lines().is_empty());
+    }
+
+    #[test]
+    fn test_substitute() {
+        let cases: Vec<(&str, Vec<&str>, Result<&str, String>)> = vec![
+            ("p $arg0", vec!["g_counter"], Ok("p g_counter")),
+            (
+                "set var $arg0 $arg1",
+                vec!["x", "0x10"],
+                Ok("set var x 0x10"),
+            ),
+            ("p $argc", vec!["a", "b"], Ok("p 2")),
+            ("bt", vec![], Ok("bt")),
+            // 同じ引数を複数回使える
+            ("b $arg0 $arg0", vec!["main"], Ok("b main main")),
+            // $arg10は$arg1の後ろに0
+            ("p $arg10", vec!["a", "b"], Ok("p b0")),
+            (
+                "p $arg1",
+                vec!["a"],
+                Err("missing argument 1 in user command".to_string()),
+            ),
+        ];
+        for (line, args, expected) in cases {
+            assert_eq!(
+                expected.map(|s| s.to_string()),
+                substitute(line, &args),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn test_expand() {
+        let mut cmds = UserCommands::new();
+        cmds.define("dumpstate", body(&["info regs", "bt", "p $arg0"]));
+        cmds.define("twice", body(&["dumpstate $arg0", "dumpstate $arg1"]));
+        cmds.define(
+            "definer",
+            body(&["define inner", "p $arg0", "end", "p $arg0"]),
+        );
+        cmds.define("recurse", body(&["p $argc", "recurse"]));
+        assert!(cmds.is_defined("dumpstate"));
+        assert!(!cmds.is_defined("info"));
+        assert_eq!(4, cmds.len());
+
+        type Expanded = Result<Vec<&'static str>, String>;
+        let cases: Vec<(&str, Vec<&str>, Expanded)> = vec![
+            (
+                "dumpstate",
+                vec!["g_counter"],
+                Ok(vec!["info regs", "bt", "p g_counter"]),
+            ),
+            // 呼び出し先は、呼び出し元で置換した引数で展開する
+            (
+                "twice",
+                vec!["a", "b"],
+                Ok(vec!["info regs", "bt", "p a", "info regs", "bt", "p b"]),
+            ),
+            // 入れ子のdefineの本体は置換しない
+            (
+                "definer",
+                vec!["x"],
+                Ok(vec!["define inner", "p $arg0", "end", "p x"]),
+            ),
+            (
+                "dumpstate",
+                vec![],
+                Err("missing argument 0 in user command".to_string()),
+            ),
+            (
+                "recurse",
+                vec![],
+                Err("max user call depth (64) exceeded in recurse".to_string()),
+            ),
+            (
+                "dumpstate",
+                vec!["0"; 11],
+                Err("too many arguments (max 10)".to_string()),
+            ),
+            ("none", vec![], Err("undefined command: none".to_string())),
+        ];
+        for (name, args, expected) in cases {
+            let expected = expected.map(|v| v.iter().map(|s| s.to_string()).collect());
+            assert_eq!(expected, cmds.expand(name, &args), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_write_definitions() {
+        let mut cmds = UserCommands::new();
+        cmds.define("show", body(&["info regs", "p $arg0"]));
+        cmds.define("definer", body(&["define inner", "bt", "end"]));
+        let mut out = vec![];
+        cmds.write_definitions(&mut out).unwrap();
+        let expected = "define definer\n  define inner\n    bt\n  end\nend\n\
+                        define show\n  info regs\n  p $arg0\nend\n";
+        assert_eq!(expected, String::from_utf8(out).unwrap());
+
+        // 出力した定義を読み込むと、同じ定義になる
+        let mut lines = expected.lines();
+        let mut loaded = UserCommands::new();
+        while let Some(l) = lines.next() {
+            let name = l.strip_prefix("define ").unwrap();
+            let mut def = DefinitionBody::new();
+            while !def.push(lines.next().unwrap()) {}
+            loaded.define(name, def.into_lines());
+        }
+        assert_eq!(cmds.commands, loaded.commands);
+    }
+}
@@ -0,0 +1,297 @@
+//! x86のデバッグレジスタによるハードウェアウォッチポイント(watch)
+//!
+//! DR0〜DR3に監視するアドレス、DR7に有効化・監視するアクセス(書き込み・読み書き)・長さを設定する
+//! 停止した際は、DR6のB0〜B3でどのウォッチポイントにヒットしたかを判定する
+
+use nix::errno::Errno;
+use nix::unistd::Pid;
+use std::fmt;
+use std::mem::offset_of;
+
+/// 設定できるウォッチポイントの数(DR0〜DR3)
+pub const MAX_WATCHPOINTS: usize = 4;
+
+/// ステータスレジスタ・制御レジスタ
+const DR6: usize = 6;
+const DR7: usize = 7;
+
+/// 監視するアクセス
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchKind {
+    Write,  // 書き込み(watch)
+    Access, // 読み込み・書き込み(rwatch)
+}
+
+impl WatchKind {
+    /// DR7のR/Wビット
+    fn rw_bits(self) -> u64 {
+        match self {
+            WatchKind::Write => 0b01,
+            WatchKind::Access => 0b11,
+        }
+    }
+}
+
+impl fmt::Display for WatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WatchKind::Write => write!(f, "write"),
+            WatchKind::Access => write!(f, "read/write"),
+        }
+    }
+}
+
+/// 監視する長さ
+///
+/// アドレスが長さの倍数となる範囲で、sizeを超えない最大の長さ(1・2・4・8バイト)
+pub fn watch_len(addr: u64, size: u64) -> u64 {
+    [8, 4, 2, 1]
+        .iter()
+        .copied()
+        .find(|len| *len <= size && addr.is_multiple_of(*len))
+        .unwrap_or(1)
+}
+
+/// DR7の値を求める
+///
+/// slotsはDR0〜DR3毎の監視する長さとアクセス(未使用であればNone)
+pub fn dr7_value(slots: &[Option<(u64, WatchKind)>]) -> u64 {
+    slots
+        .iter()
+        .enumerate()
+        .take(MAX_WATCHPOINTS)
+        .filter_map(|(i, slot)| slot.map(|(len, kind)| (i, len, kind)))
+        .fold(0, |dr7, (i, len, kind)| {
+            // LENビット(8バイトは0b10)
+            let len = match len {
+                1 => 0b00,
+                2 => 0b01,
+                8 => 0b10,
+                _ => 0b11,
+            };
+            dr7 | 1 << (i * 2) | kind.rw_bits() << (16 + i * 4) | len << (18 + i * 4)
+        })
+}
+
+/// DR6からヒットしたウォッチポイント(DR0〜DR3)を求める
+pub fn hit_slots(dr6: u64) -> Vec<usize> {
+    (0..MAX_WATCHPOINTS)
+        .filter(|i| 0 != dr6 & (1 << i))
+        .collect()
+}
+
+/// ウォッチポイントを設定(DR0〜DR3・DR7)
+///
+/// slotsはDR0〜DR3毎のアドレス・長さ・アクセス(未使用であればNone)
+/// 設定中に無効なアドレスの組み合わせとならないよう、DR7を無効にしてから書き込む
+pub fn set_watchpoints(pid: Pid, slots: &[Option<(u64, u64, WatchKind)>]) -> nix::Result<()> {
+    write_debugreg(pid, DR7, 0)?;
+    for (i, slot) in slots.iter().enumerate().take(MAX_WATCHPOINTS) {
+        write_debugreg(pid, i, slot.map_or(0, |(addr, _, _)| addr))?;
+    }
+    let lens = slots
+        .iter()
+        .map(|s| s.map(|(_, len, kind)| (len, kind)))
+        .collect::<Vec<Option<(u64, WatchKind)>>>();
+    write_debugreg(pid, DR7, dr7_value(&lens))
+}
+
+/// DR6を読み込む
+pub fn read_status(pid: Pid) -> nix::Result<u64> {
+    read_debugreg(pid, DR6)
+}
+
+/// DR6を0にする(ヒットしたビットはCPUが消さないため、再開する前に消す)
+pub fn clear_status(pid: Pid) -> nix::Result<()> {
+    write_debugreg(pid, DR6, 0)
+}
+
+/// struct user内のデバッグレジスタのオフセット
+fn debugreg_offset(no: usize) -> usize {
+    offset_of!(libc::user, u_debugreg) + no * 8
+}
+
+/// PTRACE_PEEKUSERでデバッグレジスタを読み込む
+fn read_debugreg(pid: Pid, no: usize) -> nix::Result<u64> {
+    // 読み込んだ値が-1の場合と区別するため、errnoを消しておく
+    Errno::clear();
+    let ret = unsafe {
+        libc::ptrace(
+            libc::PTRACE_PEEKUSER,
+            pid.as_raw(),
+            debugreg_offset(no),
+            std::ptr::null_mut::<libc::c_void>(),
+        )
+    };
+    match (ret, Errno::last()) {
+        (-1, e) if Errno::UnknownErrno != e => Err(e),
+        _ => Ok(ret as u64),
+    }
+}
+
+/// PTRACE_POKEUSERでデバッグレジスタへ書き込む
+fn write_debugreg(pid: Pid, no: usize, value: u64) -> nix::Result<()> {
+    let ret = unsafe {
+        libc::ptrace(
+            libc::PTRACE_POKEUSER,
+            pid.as_raw(),
+            debugreg_offset(no),
+            value as usize,
+        )
+    };
+    Errno::result(ret).map(drop)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static mut WATCH_TEST_VAR: u64 = 0;
+    static mut WATCH_TEST_BYTE: u8 = 7;
+
+    #[test]
+    fn test_watch_len() {
+        let cases = vec![
+            (0x1000, 8, 8),
+            (0x1000, 16, 8),
+            (0x1000, 4, 4),
+            (0x1004, 8, 4),
+            (0x1002, 4, 2),
+            (0x1001, 8, 1),
+            (0x1000, 3, 2),
+            (0x1000, 0, 1),
+        ];
+        for (addr, size, expected) in cases {
+            assert_eq!(expected, watch_len(addr, size), "0x{:x} {}", addr, size);
+        }
+    }
+
+    #[test]
+    fn test_dr7_value() {
+        let cases = vec![
+            (vec![], 0),
+            (vec![None, None, None, None], 0),
+            // L0・R/W0=01・LEN0=11
+            (vec![Some((4, WatchKind::Write))], 0x000D_0001),
+            // L1・R/W1=01・LEN1=10
+            (vec![None, Some((8, WatchKind::Write))], 0x0090_0004),
+            (
+                vec![
+                    Some((1, WatchKind::Write)),
+                    None,
+                    Some((2, WatchKind::Write)),
+                    None,
+                ],
+                0x0501_0011,
+            ),
+            // 読み込みも監視する(R/W=11)
+            (vec![Some((1, WatchKind::Access))], 0x0003_0001),
+            (
+                vec![None, None, None, Some((4, WatchKind::Access))],
+                0xF000_0040,
+            ),
+            // DR4以降は無視する
+            (vec![None, None, None, None, Some((8, WatchKind::Write))], 0),
+        ];
+        for (slots, expected) in cases {
+            assert_eq!(expected, dr7_value(&slots), "{:?}", slots);
+        }
+    }
+
+    #[test]
+    fn test_hit_slots() {
+        let cases = vec![
+            (0, vec![]),
+            (0x1, vec![0]),
+            (0xA, vec![1, 3]),
+            // BS(シングルステップ)等は含めない
+            (0x4000 | 0x4, vec![2]),
+        ];
+        for (dr6, expected) in cases {
+            assert_eq!(expected, hit_slots(dr6), "0x{:x}", dr6);
+        }
+    }
+
+    #[test]
+    fn test_set_watchpoints() {
+        use nix::sys::ptrace::{cont, read, traceme};
+        use nix::sys::signal::{raise, Signal};
+        use nix::sys::wait::{waitpid, WaitStatus};
+        use nix::unistd::{fork, ForkResult};
+
+        let addr = std::ptr::addr_of!(WATCH_TEST_VAR) as u64;
+        let _lock = crate::FORK_LOCK.lock().unwrap();
+        match unsafe { fork() }.expect("failed fork") {
+            ForkResult::Child => unsafe {
+                traceme().expect("failed traceme");
+                raise(Signal::SIGSTOP).expect("failed raise");
+                std::ptr::write_volatile(std::ptr::addr_of_mut!(WATCH_TEST_VAR), 0x1234);
+                libc::_exit(0);
+            },
+            ForkResult::Parent { child } => {
+                waitpid(child, None).expect("failed waitpid");
+                set_watchpoints(child, &[None, Some((addr, 8, WatchKind::Write))]).unwrap();
+                assert_eq!(
+                    dr7_value(&[None, Some((8, WatchKind::Write))]),
+                    read_debugreg(child, DR7).unwrap()
+                );
+
+                // 書き込んだ命令の直後で停止し、DR6でヒットしたウォッチポイントが分かる
+                cont(child, None).expect("failed cont");
+                assert_eq!(
+                    WaitStatus::Stopped(child, Signal::SIGTRAP),
+                    waitpid(child, None).expect("failed waitpid")
+                );
+                assert_eq!(vec![1], hit_slots(read_status(child).unwrap()));
+                assert_eq!(0x1234, read(child, addr as _).unwrap());
+
+                // DR6を消し、ウォッチポイントを外せば最後まで実行できる
+                clear_status(child).unwrap();
+                assert!(hit_slots(read_status(child).unwrap()).is_empty());
+                set_watchpoints(child, &[None, None]).unwrap();
+                cont(child, None).expect("failed cont");
+                assert_eq!(
+                    WaitStatus::Exited(child, 0),
+                    waitpid(child, None).expect("failed waitpid")
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_set_watchpoints_access() {
+        use nix::sys::ptrace::{cont, traceme};
+        use nix::sys::signal::{raise, Signal};
+        use nix::sys::wait::{waitpid, WaitStatus};
+        use nix::unistd::{fork, ForkResult};
+
+        let addr = std::ptr::addr_of!(WATCH_TEST_BYTE) as u64;
+        let _lock = crate::FORK_LOCK.lock().unwrap();
+        match unsafe { fork() }.expect("failed fork") {
+            ForkResult::Child => unsafe {
+                traceme().expect("failed traceme");
+                raise(Signal::SIGSTOP).expect("failed raise");
+                let v = std::ptr::read_volatile(std::ptr::addr_of!(WATCH_TEST_BYTE));
+                libc::_exit(v as i32);
+            },
+            ForkResult::Parent { child } => {
+                waitpid(child, None).expect("failed waitpid");
+                set_watchpoints(child, &[Some((addr, 1, WatchKind::Access))]).unwrap();
+
+                // 読み込みでも停止する
+                cont(child, None).expect("failed cont");
+                assert_eq!(
+                    WaitStatus::Stopped(child, Signal::SIGTRAP),
+                    waitpid(child, None).expect("failed waitpid")
+                );
+                assert_eq!(vec![0], hit_slots(read_status(child).unwrap()));
+                clear_status(child).unwrap();
+                cont(child, None).expect("failed cont");
+                assert_eq!(
+                    WaitStatus::Exited(child, 7),
+                    waitpid(child, None).expect("failed waitpid")
+                );
+            }
+        }
+    }
+}